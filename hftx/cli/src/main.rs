@@ -1,5 +1,9 @@
 use clap::{Parser, Subcommand};
+use orderbook::codec::{self, BookEvent, PayloadKind};
+use orderbook::hotlog::{Entry, HotLogReader};
 use orderbook::Side;
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, Write};
 use serde::{Deserialize, Serialize};
 
 #[derive(Parser)]
@@ -42,6 +46,24 @@ enum Commands {
         #[arg(short, long)]
         order_id: String,
     },
+    /// Submit the orders in a CSV file, then optionally replay a CSV of events
+    Load {
+        /// Resting orders (`symbol,side,px_ticks,qty`)
+        #[arg(long)]
+        orders: Option<std::path::PathBuf>,
+        /// Timestamped submits and cancels (`action,symbol,id,side,px_ticks,qty`)
+        #[arg(long)]
+        events: Option<std::path::PathBuf>,
+    },
+    /// Rewrite a binary snapshot or journal at the current format version
+    Migrate {
+        input: std::path::PathBuf,
+        output: std::path::PathBuf,
+    },
+    /// Print a binary hot-path log (EXCHANGE_HOTLOG), one record per line
+    Hotlog {
+        input: std::path::PathBuf,
+    },
 }
 
 #[derive(Serialize)]
@@ -55,6 +77,10 @@ struct SubmitOrderRequest {
 struct SubmitOrderResponse {
     order_id: u128,
     status: String,
+    #[serde(default)]
+    filled_qty: i64,
+    #[serde(default)]
+    remaining_qty: i64,
     trades: Vec<Trade>,
 }
 
@@ -110,7 +136,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let request = SubmitOrderRequest { side, price, quantity };
             
             let response = client
-                .post(&format!("{}/symbols/{}/orders", cli.server, symbol))
+                .post(format!("{}/symbols/{}/orders", cli.server, symbol))
                 .json(&request)
                 .send()
                 .await?;
@@ -120,6 +146,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 
                 println!("Order ID: {}", result.order_id);
                 println!("Status: {}", result.status);
+                println!("Filled: {}, remaining: {}", result.filled_qty, result.remaining_qty);
                 
                 if !result.trades.is_empty() {
                     println!("Trades executed: {}", result.trades.len());
@@ -137,7 +164,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             match symbol {
                 Some(sym) => {
                     let response = client
-                        .get(&format!("{}/symbols/{}/orderbook", cli.server, sym))
+                        .get(format!("{}/symbols/{}/orderbook", cli.server, sym))
                         .send()
                         .await?;
 
@@ -154,7 +181,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 None => {
                     let response = client
-                        .get(&format!("{}/symbols", cli.server))
+                        .get(format!("{}/symbols", cli.server))
                         .send()
                         .await?;
 
@@ -163,7 +190,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         println!("Active symbols:");
                         for symbol in symbols.symbols {
                             let state_response = client
-                                .get(&format!("{}/symbols/{}/orderbook", cli.server, symbol))
+                                .get(format!("{}/symbols/{}/orderbook", cli.server, symbol))
                                 .send()
                                 .await?;
                             
@@ -182,7 +209,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         
         Commands::Health => {
             let response = client
-                .get(&format!("{}/health", cli.server))
+                .get(format!("{}/health", cli.server))
                 .send()
                 .await?;
 
@@ -196,7 +223,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         
         Commands::Symbols => {
             let response = client
-                .get(&format!("{}/symbols", cli.server))
+                .get(format!("{}/symbols", cli.server))
                 .send()
                 .await?;
 
@@ -212,7 +239,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         
         Commands::Depth { symbol, levels } => {
             let response = client
-                .get(&format!("{}/symbols/{}/depth?levels={}", cli.server, symbol, levels))
+                .get(format!("{}/symbols/{}/depth?levels={}", cli.server, symbol, levels))
                 .send()
                 .await?;
 
@@ -234,9 +261,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
         
+        Commands::Load { orders, events } => {
+            load(&client, &cli.server, orders, events).await?;
+        }
+
+        Commands::Migrate { input, output } => {
+            migrate(&input, &output)?;
+        }
+
+        Commands::Hotlog { input } => {
+            print_hot_log(&input)?;
+        }
+
         Commands::Cancel { symbol, order_id } => {
             let response = client
-                .delete(&format!("{}/symbols/{}/orders/{}", cli.server, symbol, order_id))
+                .delete(format!("{}/symbols/{}/orders/{}", cli.server, symbol, order_id))
                 .send()
                 .await?;
 
@@ -252,3 +291,143 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Submits one order; the server assigns its id.
+async fn submit(
+    client: &reqwest::Client,
+    server: &str,
+    order: &orderbook::Order,
+) -> Result<SubmitOrderResponse, Box<dyn std::error::Error>> {
+    let request = SubmitOrderRequest { side: order.side, price: order.px_ticks, quantity: order.qty };
+    let response = client
+        .post(format!("{}/symbols/{}/orders", server, order.symbol))
+        .json(&request)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(format!("{} rejected order {}: {}", order.symbol, order.id.0, response.status()).into());
+    }
+    Ok(response.json().await?)
+}
+
+/// Loads CSV orders and events into the exchange. Server ids replace the
+/// file's, so cancels are mapped through the ids the server assigned.
+async fn load(
+    client: &reqwest::Client,
+    server: &str,
+    orders: Option<std::path::PathBuf>,
+    events: Option<std::path::PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut server_ids = HashMap::new();
+    let mut trades = 0;
+
+    if let Some(path) = orders {
+        let orders = orderbook::csv::read_orders(BufReader::new(std::fs::File::open(&path)?))?;
+        for order in &orders {
+            let result = submit(client, server, order).await?;
+            server_ids.insert(order.id, result.order_id);
+            trades += result.trades.len();
+        }
+        println!("Submitted {} orders from {}", orders.len(), path.display());
+    }
+
+    if let Some(path) = events {
+        let events = orderbook::csv::read_events(BufReader::new(std::fs::File::open(&path)?))?;
+        let (mut submits, mut cancels) = (0, 0);
+        for event in &events {
+            match &event.event {
+                BookEvent::Submit(order) => {
+                    let result = submit(client, server, order).await?;
+                    server_ids.insert(order.id, result.order_id);
+                    trades += result.trades.len();
+                    submits += 1;
+                }
+                BookEvent::Cancel { id, .. } => {
+                    let Some(server_id) = server_ids.get(id) else {
+                        println!("  skipping cancel of {}: not submitted by this load", id.0);
+                        continue;
+                    };
+                    let response = client
+                        .delete(format!("{}/symbols/{}/orders/{}", server, event.symbol, server_id))
+                        .send()
+                        .await?;
+                    if response.status().is_success() {
+                        cancels += 1;
+                    }
+                }
+                // The CSV events format has only submits and cancels
+                _ => {}
+            }
+        }
+        println!("Replayed {} submits and {} cancels from {}", submits, cancels, path.display());
+    }
+
+    println!("Trades executed: {}", trades);
+    Ok(())
+}
+
+/// Reads a snapshot or journal of any supported format version and writes
+/// it to `output` at the current one.
+fn migrate(input: &std::path::Path, output: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(input)?;
+    let (version, kind) = codec::read_header(&bytes)?;
+    match kind {
+        PayloadKind::Snapshot => {
+            std::fs::write(output, codec::migrate_snapshot(&bytes)?)?;
+            println!("Migrated snapshot {} from version {} to {}", input.display(), version, codec::FORMAT_VERSION);
+        }
+        PayloadKind::Journal => {
+            let mut writer = BufWriter::new(std::fs::File::create(output)?);
+            let events = codec::migrate_journal(&bytes[..], &mut writer)?;
+            writer.flush()?;
+            println!(
+                "Migrated {} journal events from {} (version {} to {})",
+                events,
+                input.display(),
+                version,
+                codec::FORMAT_VERSION
+            );
+        }
+        PayloadKind::Trade => return Err("trade frames are not stored; nothing to migrate".into()),
+    }
+    Ok(())
+}
+
+/// Prints each record as `ts_ns +ns symbol kind fields`, where `+ns` is the
+/// time since the symbol's latest order entered matching. Books match one
+/// order at a time, so that is the order the record belongs to.
+fn print_hot_log(input: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = HotLogReader::new(BufReader::new(std::fs::File::open(input)?))?;
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    // Latest OrderIn time per symbol id
+    let mut entered: HashMap<u16, u64> = HashMap::new();
+    while let Some(record) = reader.next_record()? {
+        let symbol = reader.symbol(record.symbol).unwrap_or("?").to_string();
+        let since = match record.entry {
+            Entry::OrderIn { .. } => {
+                entered.insert(record.symbol, record.ts_ns);
+                Some(0)
+            }
+            Entry::Reject { .. } | Entry::Fill { .. } | Entry::Rest { .. } | Entry::Out { .. } => {
+                entered.get(&record.symbol).map(|&t| record.ts_ns.saturating_sub(t))
+            }
+            _ => None,
+        };
+        let since = since.map_or_else(|| "-".to_string(), |ns| format!("+{}", ns));
+        let detail = match record.entry {
+            Entry::Symbol { .. } => "symbol".to_string(),
+            Entry::OrderIn { id, side, px_ticks, qty } => format!("in {} {:?} {}@{}", id.0, side, qty, px_ticks),
+            Entry::Reject { id } => format!("reject {}", id.0),
+            Entry::Fill { maker, taker, px_ticks, qty } => format!("fill {} x {} {}@{}", taker.0, maker.0, qty, px_ticks),
+            Entry::Rest { id, side, px_ticks, qty } => format!("rest {} {:?} {}@{}", id.0, side, qty, px_ticks),
+            Entry::Out { taker, fills, filled } => format!("out {} fills={} filled={}", taker.0, fills, filled),
+            Entry::Cancel { id, side, found: true } => format!("cancel {} {:?}", id.0, side),
+            Entry::Cancel { id, found: false, .. } => format!("cancel {} not found", id.0),
+            Entry::Dropped { records } => format!("dropped {} records", records),
+        };
+        let symbol = if matches!(record.entry, Entry::Dropped { .. }) { "-" } else { &symbol };
+        writeln!(out, "{} {} {} {}", record.ts_ns, since, symbol, detail)?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,773 @@
+//! Trading accounts: who owns which orders, and the positions their fills
+//! build up.
+//!
+//! Any submit (REST, batch, or the order stream) may name an `account`.
+//! The exchange remembers the owner of each such order until it is filled
+//! or cancelled, and every trade touching an owned order, as maker or
+//! taker, is applied to that account's position in the symbol. Each
+//! account is numbered the first time it is used, and its orders carry
+//! that [`ParticipantId`] onto the book, which is how an account is kept
+//! to seeing and cancelling only its own resting orders. Orders
+//! without an account (the bot driver, seeded books) trade as before and
+//! are never tracked.
+//!
+//! A position is a signed quantity (positive long) with its average entry
+//! price and the PnL realized by reducing it, all in ticks. Funding paid or
+//! received on perpetuals accrues per position (see `perpetuals`).
+//!
+//! Fills, funding payments, and mark updates for held symbols are published
+//! as [`AccountEvent`]s; `/accounts/:account/stream` forwards the ones for
+//! its account.
+//!
+//! A killed account (`POST /accounts/:account/kill`) is blocked: its new
+//! orders are rejected, its resting orders are cancelled across every
+//! symbol, and any of its orders already on the way to a book are dropped
+//! when they get there. It stays blocked until explicitly unblocked.
+//! Liquidation orders still go through.
+//!
+//! With a [`MarginConfig`] (`EXCHANGE_MARGIN`), accounts that have
+//! collateral are margin-checked as orders arrive: an order that raises the
+//! account's initial margin (see `orderbook::margin`) past its equity is
+//! rejected before it reaches the book. Equity is collateral plus realized
+//! PnL, funding, and open PnL. Positions are valued at the perpetual mark
+//! where there is one, else the symbol's last trade, else the entry price
+//! (or, for orders on a symbol that has never traded, the order's price).
+
+use dashmap::{DashMap, DashSet};
+use orderbook::margin::{self, Exposure, MarginError, MarginRates};
+use orderbook::{Order, OrderId, ParticipantId, Side, Trade};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+use crate::liquidation::LiquidationConfig;
+
+const EVENT_BUFFER: usize = 4096;
+
+/// One account's holding in one symbol.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Position {
+    /// Positive long, negative short
+    pub qty: i64,
+    /// Average entry price of the open quantity, in ticks
+    pub avg_px: f64,
+    /// Ticks times quantity realized by closing trades
+    pub realized_pnl: f64,
+    /// Net funding received (negative when paid)
+    pub funding: f64,
+    /// Quantity of owned buy orders still working
+    pub open_bids: i64,
+    /// Quantity of owned sell orders still working
+    pub open_asks: i64,
+    /// Price of the latest owned order, for valuing a symbol with no trades
+    #[serde(skip)]
+    last_order_px: Option<i64>,
+}
+
+impl Position {
+    /// Applies a fill of `qty` at `px_ticks`; buys add, sells subtract.
+    fn apply(&mut self, side: Side, px_ticks: i64, qty: i64) {
+        let px = px_ticks as f64;
+        let signed = match side {
+            Side::Bid => qty,
+            Side::Ask => -qty,
+        };
+        if self.qty == 0 || self.qty.signum() == signed.signum() {
+            let open = self.qty.abs() as f64;
+            self.avg_px = (self.avg_px * open + px * qty as f64) / (open + qty as f64);
+        } else {
+            let closed = qty.min(self.qty.abs());
+            self.realized_pnl += (px - self.avg_px) * (closed * self.qty.signum()) as f64;
+            // Flipping through flat opens the rest at this price
+            if qty > closed {
+                self.avg_px = px;
+            }
+        }
+        self.qty += signed;
+        if self.qty == 0 {
+            self.avg_px = 0.0;
+        }
+    }
+
+    /// Open PnL against `mark`, in ticks times quantity.
+    pub fn unrealized_pnl(&self, mark: f64) -> f64 {
+        (mark - self.avg_px) * self.qty as f64
+    }
+
+    /// Whether `qty` more working on `side` keeps the working quantity,
+    /// and the position were it all to fill, within an `i64`.
+    fn fits(&self, side: Side, qty: i64) -> bool {
+        match side {
+            Side::Bid => self.open_bids.checked_add(qty).and_then(|working| self.qty.checked_add(working)).is_some(),
+            Side::Ask => self.open_asks.checked_add(qty).and_then(|working| self.qty.checked_sub(working)).is_some(),
+        }
+    }
+
+    fn working(&mut self, side: Side) -> &mut i64 {
+        match side {
+            Side::Bid => &mut self.open_bids,
+            Side::Ask => &mut self.open_asks,
+        }
+    }
+
+    fn exposure(&self) -> Exposure {
+        Exposure { position: self.qty, open_bids: self.open_bids, open_asks: self.open_asks }
+    }
+
+    /// Nothing held, working, or accrued
+    fn is_untouched(&self) -> bool {
+        self.qty == 0 && self.open_bids == 0 && self.open_asks == 0 && self.realized_pnl == 0.0 && self.funding == 0.0
+    }
+}
+
+/// Margin rates by symbol and collateral by account, from `EXCHANGE_MARGIN`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MarginConfig {
+    /// Symbols without rates here (or in `default_rates`) need no margin
+    #[serde(default)]
+    pub symbols: HashMap<String, MarginRates>,
+    #[serde(default)]
+    pub default_rates: Option<MarginRates>,
+    /// Collateral by account, in ticks times quantity
+    #[serde(default)]
+    pub accounts: HashMap<String, f64>,
+    /// Collateral for accounts not listed; unset leaves them unchecked
+    #[serde(default)]
+    pub default_collateral: Option<f64>,
+    /// Liquidate accounts below maintenance; unset only rejects orders
+    #[serde(default)]
+    pub liquidation: Option<LiquidationConfig>,
+}
+
+impl MarginConfig {
+    /// Reads and validates a margin config from a JSON file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let config: Self = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        for (symbol, rates) in &config.symbols {
+            rates.validate().map_err(|e| format!("{}: {}: {}", path, symbol, e))?;
+        }
+        if let Some(rates) = &config.default_rates {
+            rates.validate().map_err(|e| format!("{}: default_rates: {}", path, e))?;
+        }
+        if let Some(liquidation) = &config.liquidation {
+            if !(0.0..1.0).contains(&liquidation.slippage) {
+                return Err(format!("{}: liquidation slippage must be in [0, 1)", path));
+            }
+            if liquidation.max_order_qty.is_some_and(|qty| qty <= 0) {
+                return Err(format!("{}: liquidation max_order_qty must be positive", path));
+            }
+        }
+        Ok(config)
+    }
+
+    fn rates(&self, symbol: &str) -> Option<&MarginRates> {
+        self.symbols.get(symbol).or(self.default_rates.as_ref())
+    }
+
+    fn collateral(&self, account: &str) -> Option<f64> {
+        self.accounts.get(account).copied().or(self.default_collateral)
+    }
+}
+
+/// Reads bearer tokens by account (`{"alice": "token"}`) from a JSON file.
+pub fn load_tokens(path: &str) -> Result<HashMap<String, String>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Something that happened to an account, or to a symbol it holds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AccountEvent {
+    Fill {
+        account: String,
+        symbol: String,
+        order_id: u128,
+        side: Side,
+        px_ticks: i64,
+        qty: i64,
+        /// Position after the fill
+        position: i64,
+        ts_ns: u64,
+    },
+    /// A perpetual's mark moved; sent to accounts holding it
+    Mark {
+        symbol: String,
+        index: f64,
+        mark: f64,
+        predicted_rate: f64,
+        ts_ns: u64,
+    },
+    Funding {
+        account: String,
+        symbol: String,
+        rate: f64,
+        mark: f64,
+        position: i64,
+        /// Received; negative when paid
+        payment: f64,
+        ts_ns: u64,
+    },
+    /// A reduce-only order forced by a maintenance breach
+    Liquidation {
+        account: String,
+        symbol: String,
+        order_id: u128,
+        side: Side,
+        px_ticks: i64,
+        qty: i64,
+        /// Filled at once; the rest was cancelled
+        filled: i64,
+        /// Equity and requirement that triggered it
+        equity: f64,
+        maintenance_margin: f64,
+        ts_ns: u64,
+    },
+    /// The account was killed and its resting orders cancelled
+    Killed {
+        account: String,
+        cancelled_orders: usize,
+        ts_ns: u64,
+    },
+    Unblocked {
+        account: String,
+        ts_ns: u64,
+    },
+}
+
+impl AccountEvent {
+    /// The account this event belongs to; `None` for symbol-wide events.
+    pub fn account(&self) -> Option<&str> {
+        match self {
+            AccountEvent::Fill { account, .. }
+            | AccountEvent::Funding { account, .. }
+            | AccountEvent::Liquidation { account, .. }
+            | AccountEvent::Killed { account, .. }
+            | AccountEvent::Unblocked { account, .. } => Some(account),
+            AccountEvent::Mark { .. } => None,
+        }
+    }
+
+    /// The symbol this event is about; `None` for account-wide events.
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            AccountEvent::Fill { symbol, .. }
+            | AccountEvent::Mark { symbol, .. }
+            | AccountEvent::Funding { symbol, .. }
+            | AccountEvent::Liquidation { symbol, .. } => Some(symbol),
+            AccountEvent::Killed { .. } | AccountEvent::Unblocked { .. } => None,
+        }
+    }
+}
+
+/// A position as served over REST and the account stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionView {
+    pub symbol: String,
+    #[serde(flatten)]
+    pub position: Position,
+    /// Price the position is valued at
+    pub mark: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+}
+
+/// An account's margin position, for accounts with collateral.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginView {
+    pub collateral: f64,
+    /// Collateral plus realized, funding, and open PnL
+    pub equity: f64,
+    pub initial_margin: f64,
+    pub maintenance_margin: f64,
+    /// `initial_margin / equity`; `None` once equity is gone
+    pub utilization: Option<f64>,
+    /// `maintenance_margin / equity`; `None` once equity is gone
+    pub maintenance_utilization: Option<f64>,
+    /// Gross position notional over equity
+    pub leverage: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountView {
+    pub account: String,
+    pub positions: Vec<PositionView>,
+    /// Owned orders still resting
+    pub open_orders: usize,
+    /// Killed and not yet unblocked
+    pub blocked: bool,
+    pub margin: Option<MarginView>,
+}
+
+/// Why an account's order was refused.
+#[derive(Debug, PartialEq)]
+pub enum Rejection {
+    Blocked(String),
+    Margin(MarginError),
+    /// More than the account's position and working orders can count
+    TooLarge { qty: i64 },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Blocked(account) => write!(f, "account {} is blocked", account),
+            Rejection::Margin(e) => e.fmt(f),
+            Rejection::TooLarge { qty } => write!(f, "quantity {} would overflow the account's position", qty),
+        }
+    }
+}
+
+/// An owned order still live on a book.
+struct OpenOrder {
+    account: String,
+    symbol: String,
+    side: Side,
+    remaining: i64,
+    /// Placed by the exchange itself, so a block doesn't stop it
+    forced: bool,
+}
+
+pub struct Accounts {
+    orders: DashMap<OrderId, OpenOrder>,
+    positions: DashMap<String, BTreeMap<String, Position>>,
+    /// The number each account's orders carry onto the books
+    participants: DashMap<String, ParticipantId>,
+    next_participant: AtomicU32,
+    /// Killed accounts
+    blocked: DashSet<String>,
+    /// Latest perpetual marks
+    marks: DashMap<String, f64>,
+    margin: MarginConfig,
+    /// Set by the first owned order, so anonymous flow skips the lookups
+    in_use: AtomicBool,
+    events: broadcast::Sender<AccountEvent>,
+}
+
+impl Default for Accounts {
+    fn default() -> Self {
+        Self::new(MarginConfig::default())
+    }
+}
+
+impl Accounts {
+    pub fn new(margin: MarginConfig) -> Self {
+        Self {
+            orders: DashMap::new(),
+            positions: DashMap::new(),
+            participants: DashMap::new(),
+            next_participant: AtomicU32::new(1),
+            blocked: DashSet::new(),
+            marks: DashMap::new(),
+            margin,
+            in_use: AtomicBool::new(false),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    /// Records `account` as the owner of `order` and stamps the order with
+    /// its [`participant`](Self::participant); call before submitting it.
+    /// Fails, recording nothing, if the account is blocked, the order
+    /// would take a margined account past its equity, or it would take
+    /// the account's position past what an `i64` holds were it and the
+    /// account's other orders on that side to fill. `last_px` gives a
+    /// symbol's last trade.
+    pub fn assign(&self, account: &str, order: &mut Order, last_px: impl Fn(&str) -> Option<i64>) -> Result<(), Rejection> {
+        if self.blocked.contains(account) {
+            return Err(Rejection::Blocked(account.to_string()));
+        }
+        self.track(account, order, last_px, false)
+    }
+
+    /// Like [`assign`](Self::assign), for orders the exchange places itself
+    /// (liquidations): a block doesn't stop them.
+    pub fn assign_forced(&self, account: &str, order: &mut Order, last_px: impl Fn(&str) -> Option<i64>) -> Result<(), Rejection> {
+        self.track(account, order, last_px, true)
+    }
+
+    /// The number `account`'s orders carry on the books, handed out the
+    /// first time it is asked for.
+    pub fn participant(&self, account: &str) -> ParticipantId {
+        if let Some(id) = self.participants.get(account) {
+            return *id;
+        }
+        *self
+            .participants
+            .entry(account.to_string())
+            .or_insert_with(|| ParticipantId(self.next_participant.fetch_add(1, Ordering::Relaxed)))
+    }
+
+    /// `account`'s number, if it has ever been given one.
+    pub fn participant_of(&self, account: &str) -> Option<ParticipantId> {
+        self.participants.get(account).map(|id| *id)
+    }
+
+    fn track(&self, account: &str, order: &mut Order, last_px: impl Fn(&str) -> Option<i64>, forced: bool) -> Result<(), Rejection> {
+        self.in_use.store(true, Ordering::Relaxed);
+        // The entry stays locked through the check, so one account's
+        // concurrent orders are checked one at a time
+        let mut positions = self.positions.entry(account.to_string()).or_default();
+        if positions.get(order.symbol.as_str()).is_some_and(|position| !position.fits(order.side, order.qty)) {
+            return Err(Rejection::TooLarge { qty: order.qty });
+        }
+        let collateral = self.margin.collateral(account);
+        let before = collateral.map(|c| self.margin_view(c, &positions, &last_px));
+
+        let position = positions.entry(order.symbol.to_string()).or_default();
+        let prev_order_px = position.last_order_px.replace(order.px_ticks);
+        *position.working(order.side) += order.qty;
+
+        if let (Some(collateral), Some(before)) = (collateral, before) {
+            let after = self.margin_view(collateral, &positions, &last_px);
+            if after.initial_margin > before.initial_margin && after.initial_margin > after.equity {
+                let position = positions.get_mut(order.symbol.as_str()).expect("just inserted");
+                *position.working(order.side) -= order.qty;
+                position.last_order_px = prev_order_px;
+                if position.is_untouched() {
+                    positions.remove(order.symbol.as_str());
+                }
+                return Err(Rejection::Margin(MarginError::Insufficient { required: after.initial_margin, equity: after.equity }));
+            }
+        }
+        drop(positions);
+
+        order.owner = self.participant(account);
+        self.orders.insert(
+            order.id,
+            OpenOrder {
+                account: account.to_string(),
+                symbol: order.symbol.to_string(),
+                side: order.side,
+                remaining: order.qty,
+                forced,
+            },
+        );
+        Ok(())
+    }
+
+    /// Moves an owned order's working quantity to `qty` at `px_ticks` and
+    /// makes the amend on the book with `apply`; call under the book lock.
+    /// A size increase is checked like a new order, for margin on a
+    /// margined account and against overflow on any, and fails, changing
+    /// nothing and never calling `apply`. An amend `apply` refuses leaves
+    /// the account as it was too. Orders without an owner always pass.
+    pub(crate) fn amend<T, E>(
+        &self,
+        order_id: OrderId,
+        px_ticks: i64,
+        qty: i64,
+        last_px: impl Fn(&str) -> Option<i64>,
+        apply: impl FnOnce() -> Result<T, E>,
+    ) -> Result<Result<T, E>, Rejection> {
+        if !self.in_use() {
+            return Ok(apply());
+        }
+        let Some(mut order) = self.orders.get_mut(&order_id) else { return Ok(apply()) };
+        let delta = qty - order.remaining;
+        order.remaining = qty;
+        let (account, symbol, side) = (order.account.clone(), order.symbol.clone(), order.side);
+        drop(order);
+        // Puts the order and its working quantity back as they were
+        let undo = |prev_order_px: Option<Option<i64>>| {
+            if let Some(prev_order_px) = prev_order_px {
+                let mut positions = self.positions.entry(account.clone()).or_default();
+                let position = positions.get_mut(&symbol).expect("amended");
+                *position.working(side) -= delta;
+                position.last_order_px = prev_order_px;
+            }
+            if let Some(mut order) = self.orders.get_mut(&order_id) {
+                order.remaining -= delta;
+            }
+        };
+
+        let mut positions = self.positions.entry(account.clone()).or_default();
+        if positions.get(&symbol).is_some_and(|position| !position.fits(side, delta.max(0))) {
+            drop(positions);
+            undo(None);
+            return Err(Rejection::TooLarge { qty });
+        }
+        let collateral = self.margin.collateral(&account).filter(|_| delta > 0);
+        let before = collateral.map(|c| self.margin_view(c, &positions, &last_px));
+        let position = positions.entry(symbol.clone()).or_default();
+        let prev_order_px = position.last_order_px.replace(px_ticks);
+        *position.working(side) += delta;
+
+        if let (Some(collateral), Some(before)) = (collateral, before) {
+            let after = self.margin_view(collateral, &positions, &last_px);
+            if after.initial_margin > before.initial_margin && after.initial_margin > after.equity {
+                drop(positions);
+                undo(Some(prev_order_px));
+                return Err(Rejection::Margin(MarginError::Insufficient { required: after.initial_margin, equity: after.equity }));
+            }
+        }
+        drop(positions);
+        let applied = apply();
+        if applied.is_err() {
+            undo(Some(prev_order_px));
+        }
+        Ok(applied)
+    }
+
+    /// Whether `order` belongs to a blocked account and must not reach the
+    /// book; a refused order is forgotten. Called under the book lock, so
+    /// nothing a kill missed can rest afterwards.
+    pub(crate) fn refuses(&self, order: &Order) -> bool {
+        if !self.in_use.load(Ordering::Relaxed) {
+            return false;
+        }
+        let refused = self
+            .orders
+            .get(&order.id)
+            .is_some_and(|owned| !owned.forced && self.blocked.contains(&owned.account));
+        if refused {
+            self.forget(order.id);
+        }
+        refused
+    }
+
+    /// Blocks `account`; its resting orders are the caller's to cancel.
+    pub(crate) fn block(&self, account: &str) {
+        self.blocked.insert(account.to_string());
+        // Shows up on the account endpoint even before it ever trades
+        self.positions.entry(account.to_string()).or_default();
+    }
+
+    /// Lifts a kill. False if `account` wasn't blocked.
+    pub fn unblock(&self, account: &str) -> bool {
+        let unblocked = self.blocked.remove(account).is_some();
+        if unblocked {
+            self.publish(AccountEvent::Unblocked { account: account.to_string(), ts_ns: now_ns() });
+        }
+        unblocked
+    }
+
+    /// Applies one match's trades to the owners of both sides.
+    pub(crate) fn on_trades(&self, trades: &[Trade]) {
+        if !self.in_use.load(Ordering::Relaxed) {
+            return;
+        }
+        for trade in trades {
+            self.fill(trade.maker, trade);
+            self.fill(trade.taker, trade);
+        }
+    }
+
+    fn fill(&self, order_id: OrderId, trade: &Trade) {
+        let Some(mut order) = self.orders.get_mut(&order_id) else { return };
+        order.remaining -= trade.qty;
+        let (account, side) = (order.account.clone(), order.side);
+        let done = order.remaining <= 0;
+        drop(order);
+        if done {
+            self.orders.remove(&order_id);
+        }
+
+        let position = {
+            let mut positions = self.positions.entry(account.clone()).or_default();
+            let position = positions.entry(trade.symbol.to_string()).or_default();
+            *position.working(side) -= trade.qty;
+            position.apply(side, trade.px_ticks, trade.qty);
+            position.qty
+        };
+        let _ = self.events.send(AccountEvent::Fill {
+            account,
+            symbol: trade.symbol.to_string(),
+            order_id: order_id.0,
+            side,
+            px_ticks: trade.px_ticks,
+            qty: trade.qty,
+            position,
+            ts_ns: trade.ts_ns as u64,
+        });
+    }
+
+    /// Whether any order has been placed with an account.
+    pub(crate) fn in_use(&self) -> bool {
+        self.in_use.load(Ordering::Relaxed)
+    }
+
+    /// The account a working order belongs to.
+    pub(crate) fn owner(&self, order_id: OrderId) -> Option<String> {
+        self.orders.get(&order_id).map(|order| order.account.clone())
+    }
+
+    /// Stops tracking an order that was cancelled or never submitted.
+    pub(crate) fn forget(&self, order_id: OrderId) {
+        let Some((_, order)) = self.orders.remove(&order_id) else { return };
+        if let Some(mut positions) = self.positions.get_mut(&order.account) {
+            if let Some(position) = positions.get_mut(&order.symbol) {
+                *position.working(order.side) -= order.remaining;
+            }
+        }
+    }
+
+    /// Takes what a cancelled order had left off its account's working
+    /// quantity when a new order already tracked under its id replaced it,
+    /// as a quote reusing the ids of the one it replaces does.
+    pub(crate) fn forget_replaced(&self, old: &Order) {
+        let Some(account) = self.orders.get(&old.id).map(|order| order.account.clone()) else { return };
+        if let Some(mut positions) = self.positions.get_mut(&account) {
+            if let Some(position) = positions.get_mut(old.symbol.as_str()) {
+                *position.working(old.side) -= old.qty;
+            }
+        }
+    }
+
+    /// Stops tracking every order on a delisted symbol.
+    pub(crate) fn forget_symbol(&self, symbol: &str) {
+        self.orders.retain(|_, order| order.symbol != symbol);
+        for mut positions in self.positions.iter_mut() {
+            if let Some(position) = positions.get_mut(symbol) {
+                position.open_bids = 0;
+                position.open_asks = 0;
+            }
+        }
+        self.marks.remove(symbol);
+    }
+
+    /// Pays or charges funding on every open position in `symbol`.
+    pub fn apply_funding(&self, symbol: &str, mark: f64, rate: f64) {
+        let ts_ns = now_ns();
+        for mut entry in self.positions.iter_mut() {
+            let account = entry.key().clone();
+            let Some(position) = entry.value_mut().get_mut(symbol) else { continue };
+            if position.qty == 0 {
+                continue;
+            }
+            let payment = orderbook::funding::funding_payment(position.qty, mark, rate);
+            position.funding += payment;
+            let _ = self.events.send(AccountEvent::Funding {
+                account,
+                symbol: symbol.to_string(),
+                rate,
+                mark,
+                position: position.qty,
+                payment,
+                ts_ns,
+            });
+        }
+    }
+
+    /// Values `symbol` at `mark` from now on and publishes it to holders.
+    pub fn publish_mark(&self, symbol: &str, index: f64, mark: f64, predicted_rate: f64) {
+        self.marks.insert(symbol.to_string(), mark);
+        let _ = self.events.send(AccountEvent::Mark {
+            symbol: symbol.to_string(),
+            index,
+            mark,
+            predicted_rate,
+            ts_ns: now_ns(),
+        });
+    }
+
+    pub(crate) fn publish(&self, event: AccountEvent) {
+        let _ = self.events.send(event);
+    }
+
+    /// Whether `symbol` carries margin requirements.
+    pub fn margined(&self, symbol: &str) -> bool {
+        self.margin.rates(symbol).is_some()
+    }
+
+    /// `account`'s working orders as (symbol, id).
+    pub fn open_orders(&self, account: &str) -> Vec<(String, OrderId)> {
+        self.orders
+            .iter()
+            .filter(|order| order.account == account)
+            .map(|order| (order.symbol.clone(), *order.key()))
+            .collect()
+    }
+
+    /// Margined accounts whose maintenance margin exceeds their equity.
+    pub fn margin_calls(&self, last_px: impl Fn(&str) -> Option<i64>) -> Vec<(String, MarginView)> {
+        self.positions
+            .iter()
+            .filter_map(|entry| {
+                let collateral = self.margin.collateral(entry.key())?;
+                let margin = self.margin_view(collateral, entry.value(), &last_px);
+                let breached = margin.maintenance_margin > 0.0 && margin.maintenance_margin > margin.equity;
+                breached.then(|| (entry.key().clone(), margin))
+            })
+            .collect()
+    }
+
+    /// Whether `account` has an open position in `symbol`.
+    pub fn holds(&self, account: &str, symbol: &str) -> bool {
+        self.positions
+            .get(account)
+            .is_some_and(|positions| positions.get(symbol).is_some_and(|p| p.qty != 0))
+    }
+
+    /// Every event, for all accounts.
+    pub fn subscribe(&self) -> broadcast::Receiver<AccountEvent> {
+        self.events.subscribe()
+    }
+
+    /// Where a position in `symbol` is valued.
+    fn price(&self, symbol: &str, position: &Position, last_px: &impl Fn(&str) -> Option<i64>) -> Option<f64> {
+        self.marks
+            .get(symbol)
+            .map(|mark| *mark)
+            .or_else(|| last_px(symbol).map(|px| px as f64))
+            .or_else(|| (position.qty != 0).then_some(position.avg_px))
+            .or_else(|| position.last_order_px.map(|px| px as f64))
+    }
+
+    fn margin_view(
+        &self,
+        collateral: f64,
+        positions: &BTreeMap<String, Position>,
+        last_px: &impl Fn(&str) -> Option<i64>,
+    ) -> MarginView {
+        let (mut equity, mut initial_margin, mut maintenance_margin, mut gross) = (collateral, 0.0, 0.0, 0.0);
+        for (symbol, position) in positions {
+            equity += position.realized_pnl + position.funding;
+            let Some(px) = self.price(symbol, position, last_px) else { continue };
+            equity += position.unrealized_pnl(px);
+            gross += position.qty.unsigned_abs() as f64 * px.abs();
+            if let Some(rates) = self.margin.rates(symbol) {
+                initial_margin += rates.initial_margin(&position.exposure(), px);
+                maintenance_margin += rates.maintenance_margin(&position.exposure(), px);
+            }
+        }
+        MarginView {
+            collateral,
+            equity,
+            initial_margin,
+            maintenance_margin,
+            utilization: margin::utilization(initial_margin, equity),
+            maintenance_utilization: margin::utilization(maintenance_margin, equity),
+            leverage: margin::utilization(gross, equity),
+        }
+    }
+
+    /// `account`'s positions and margin, or `None` if it has never placed
+    /// an order. `last_px` gives a symbol's last trade.
+    pub fn view(&self, account: &str, last_px: impl Fn(&str) -> Option<i64>) -> Option<AccountView> {
+        let positions = self.positions.get(account)?;
+        let views = positions
+            .iter()
+            .map(|(symbol, position)| {
+                let mark = self.price(symbol, position, &last_px);
+                PositionView {
+                    symbol: symbol.clone(),
+                    position: position.clone(),
+                    mark,
+                    unrealized_pnl: mark.map(|m| position.unrealized_pnl(m)),
+                }
+            })
+            .collect();
+        let margin = self.margin.collateral(account).map(|c| self.margin_view(c, &positions, &last_px));
+        drop(positions);
+        let open_orders = self.orders.iter().filter(|order| order.account == account).count();
+        let blocked = self.blocked.contains(account);
+        Some(AccountView { account: account.to_string(), positions: views, open_orders, blocked, margin })
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+}
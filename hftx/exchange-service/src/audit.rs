@@ -0,0 +1,79 @@
+//! Append-only audit store for surveillance findings.
+//!
+//! Records are written as JSON lines, one object per record with a `kind`
+//! field, to the file named by `EXCHANGE_AUDIT_LOG`. The file is opened for
+//! append, so restarts add to the same history. Writes happen on a
+//! background task: recording never blocks, and if the writer falls more
+//! than `BUFFER` records behind, new records are dropped and counted rather
+//! than slowing matching.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::chaos;
+use crate::surveillance::{SpoofingAlert, WashTrade};
+
+/// Records queued for the writer before new ones are dropped
+const BUFFER: usize = 4096;
+
+/// One line of the audit log.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditRecord {
+    WashTrade(WashTrade),
+    Spoofing(SpoofingAlert),
+}
+
+#[derive(Clone)]
+pub struct AuditStore {
+    records: mpsc::Sender<AuditRecord>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditStore {
+    /// Opens `path` for append and starts its writer.
+    pub async fn open(path: &str) -> Result<Self, String> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .map_err(|e| format!("{}: {}", path, e))?;
+        let (records, rx) = mpsc::channel(BUFFER);
+        tokio::spawn(write(file, rx, path.to_string()));
+        Ok(Self { records, dropped: Arc::new(AtomicU64::new(0)) })
+    }
+
+    /// Queues `record` for the log without waiting.
+    pub fn record(&self, record: AuditRecord) {
+        if self.records.try_send(record).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                warn!("Audit log behind or closed; {} records dropped so far", dropped);
+            }
+        }
+    }
+}
+
+/// Writes each record as a line, flushing whenever the queue drains.
+async fn write(mut file: tokio::fs::File, mut rx: mpsc::Receiver<AuditRecord>, path: String) {
+    while let Some(record) = rx.recv().await {
+        let mut lines = Vec::new();
+        let mut next = Some(record);
+        while let Some(record) = next {
+            // Serializing plain fields can't fail
+            serde_json::to_writer(&mut lines, &record).unwrap();
+            lines.push(b'\n');
+            next = rx.try_recv().ok();
+        }
+        chaos::slow_write().await;
+        if let Err(e) = file.write_all(&lines).await.and(file.flush().await) {
+            warn!("Audit log {}: {}", path, e);
+        }
+    }
+}
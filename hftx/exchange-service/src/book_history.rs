@@ -0,0 +1,179 @@
+//! Sampled depth history for book heatmaps.
+//!
+//! Every `EXCHANGE_BOOK_HISTORY_MS` (default 1000, 0 turns it off) the top `DEPTH` levels
+//! of each side of every symbol are recorded, and samples older than
+//! `EXCHANGE_BOOK_HISTORY_SECS` (default 3600) are dropped. A sample equal
+//! to the one before shares its storage, so a quiet book costs little.
+//!
+//! `GET /symbols/:symbol/book-history` cuts a time range into equal buckets
+//! and returns a time x price matrix of resting quantity per side: each
+//! cell is the mean over the bucket's samples. A bucket with no samples
+//! repeats the last book before it, since the book didn't change as far as
+//! the history knows, and is all zeros before the first sample.
+
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::interval;
+
+use crate::exchange::Exchange;
+
+/// Levels per side recorded in each sample
+const DEPTH: usize = 20;
+/// Most buckets one request may ask for
+pub const MAX_BUCKETS: usize = 1000;
+
+/// One side's `(price, quantity)` levels, best first.
+type Levels = Vec<(i64, i64)>;
+
+struct Sample {
+    ts_ns: u64,
+    book: Arc<(Levels, Levels)>,
+}
+
+/// Served by `GET /symbols/:symbol/book-history`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BookHeatmap {
+    pub symbol: String,
+    pub from_ns: u64,
+    pub to_ns: u64,
+    pub bucket_ns: u64,
+    /// Start of each bucket
+    pub times: Vec<u64>,
+    /// Every price seen in the range, ascending
+    pub prices: Vec<i64>,
+    /// `bids[t][p]`: mean bid quantity at `prices[p]` during bucket `t`
+    pub bids: Vec<Vec<f64>>,
+    pub asks: Vec<Vec<f64>>,
+    /// Samples behind each bucket; 0 where the previous book was carried
+    pub samples: Vec<usize>,
+}
+
+pub struct BookHistory {
+    every: Duration,
+    retention_ns: u64,
+    books: Mutex<HashMap<String, VecDeque<Sample>>>,
+}
+
+impl BookHistory {
+    pub fn new(every: Duration, retention: Duration) -> Self {
+        Self { every, retention_ns: retention.as_nanos() as u64, books: Mutex::new(HashMap::new()) }
+    }
+
+    /// Appends a sample of every listed symbol and drops expired ones.
+    async fn record(&self, exchange: &Exchange) {
+        let now_ns = now_ns();
+        let mut samples = Vec::new();
+        for symbol in exchange.list_symbols().await {
+            let Some(depth) = exchange.get_market_depth(&symbol, DEPTH).await else { continue };
+            let side = |levels: &[crate::types::PriceLevel]| levels.iter().map(|l| (l.price, l.quantity)).collect();
+            samples.push((symbol, (side(&depth.bids), side(&depth.asks))));
+        }
+
+        let mut books = self.books.lock().unwrap();
+        for (symbol, book) in samples {
+            let history = books.entry(symbol).or_default();
+            let book = match history.back() {
+                Some(last) if *last.book == book => last.book.clone(),
+                _ => Arc::new(book),
+            };
+            history.push_back(Sample { ts_ns: now_ns, book });
+        }
+        // Delisted symbols age out like everything else
+        books.retain(|_, history| {
+            while history.front().is_some_and(|s| now_ns - s.ts_ns > self.retention_ns) {
+                history.pop_front();
+            }
+            !history.is_empty()
+        });
+    }
+
+    /// How far back samples are kept.
+    pub fn retention_ns(&self) -> u64 {
+        self.retention_ns
+    }
+
+    /// Whether `symbol` has samples, such as a recently delisted one.
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.books.lock().unwrap().contains_key(symbol)
+    }
+
+    /// Buckets `symbol`'s samples in `[from_ns, to_ns)`; all zeros if it
+    /// has none. Needs `from_ns < to_ns` and at least one bucket.
+    pub fn heatmap(&self, symbol: &str, from_ns: u64, to_ns: u64, buckets: usize) -> BookHeatmap {
+        let books = self.books.lock().unwrap();
+        let empty = VecDeque::new();
+        let history = books.get(symbol).unwrap_or(&empty);
+        let bucket_ns = (to_ns - from_ns).div_ceil(buckets as u64).max(1);
+        let first = history.partition_point(|s| s.ts_ns < from_ns);
+        let end = history.partition_point(|s| s.ts_ns < to_ns);
+        // The book going into the range, carried until the first sample
+        let carried = first.checked_sub(1).map(|i| &history[i]);
+        let in_range = history.range(first..end);
+
+        let prices: Vec<i64> = carried
+            .into_iter()
+            .chain(in_range.clone())
+            .flat_map(|s| s.book.0.iter().chain(s.book.1.iter()).map(|(px, _)| *px))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let column: HashMap<i64, usize> = prices.iter().enumerate().map(|(i, px)| (*px, i)).collect();
+
+        let mut heatmap = BookHeatmap {
+            symbol: symbol.to_string(),
+            from_ns,
+            to_ns,
+            bucket_ns,
+            times: (0..buckets as u64).map(|t| from_ns + t * bucket_ns).collect(),
+            prices,
+            bids: vec![vec![0.0; column.len()]; buckets],
+            asks: vec![vec![0.0; column.len()]; buckets],
+            samples: vec![0; buckets],
+        };
+        let add = |row: &mut Vec<f64>, levels: &Levels| {
+            for (px, qty) in levels {
+                row[column[px]] += *qty as f64;
+            }
+        };
+        for sample in in_range.clone() {
+            let t = ((sample.ts_ns - from_ns) / bucket_ns) as usize;
+            add(&mut heatmap.bids[t], &sample.book.0);
+            add(&mut heatmap.asks[t], &sample.book.1);
+            heatmap.samples[t] += 1;
+        }
+
+        let mut last = carried.map(|s| &s.book);
+        let mut samples = in_range.peekable();
+        for t in 0..buckets {
+            let n = heatmap.samples[t];
+            if n > 0 {
+                for cell in heatmap.bids[t].iter_mut().chain(heatmap.asks[t].iter_mut()) {
+                    *cell /= n as f64;
+                }
+                let bucket_end = from_ns + (t as u64 + 1) * bucket_ns;
+                while let Some(sample) = samples.next_if(|s| s.ts_ns < bucket_end) {
+                    last = Some(&sample.book);
+                }
+            } else if let Some(book) = last {
+                add(&mut heatmap.bids[t], &book.0);
+                add(&mut heatmap.asks[t], &book.1);
+            }
+        }
+        heatmap
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+}
+
+/// Samples every book at the configured interval, forever.
+pub async fn run(history: Arc<BookHistory>, exchange: Arc<Exchange>) {
+    let mut ticker = interval(history.every);
+    loop {
+        ticker.tick().await;
+        history.record(&exchange).await;
+    }
+}
@@ -8,11 +8,13 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use orderbook::{Order, OrderId, Side};
+use orderbook::rng::Rng;
+use orderbook::{Order, OrderId, ParticipantId, Side, TimeInForce};
 use tokio::sync::{broadcast, watch, Mutex};
 use tokio::time::interval;
 use tracing::{info, warn};
 
+use crate::chaos;
 use crate::exchange::Exchange;
 use crate::types::{BotConfig, LatencySample, SimStatusEntry, TradeEvent};
 
@@ -59,7 +61,7 @@ impl BotDriver {
             let _ = existing.cancel_tx.send(true);
         }
 
-        let (cancel_tx, cancel_rx) = watch::channel(false);
+        let (cancel_tx, mut cancel_rx) = watch::channel(false);
         let symbol = config.symbol.clone();
         let cfg = config.clone();
         let exchange = self.exchange.clone();
@@ -67,7 +69,11 @@ impl BotDriver {
         let latency_tx = self.latency_tx.clone();
 
         tokio::spawn(async move {
-            run_driver(exchange, trade_tx, latency_tx, cfg, cancel_rx).await;
+            let mut seed = cfg.seed.unwrap_or_else(Rng::entropy_seed);
+            // A chaos restart starts over on a new seed, so order ids don't repeat
+            while run_driver(&exchange, &trade_tx, &latency_tx, &cfg, seed, &mut cancel_rx).await {
+                seed = Rng::seed_from_u64(seed).next_u64();
+            }
         });
 
         drivers.insert(
@@ -107,17 +113,23 @@ impl BotDriver {
     }
 }
 
+/// Drives `config.symbol` until cancelled. Returns true if it stopped for a
+/// forced chaos restart instead.
 async fn run_driver(
-    exchange: Arc<Exchange>,
-    trade_tx: broadcast::Sender<TradeEvent>,
-    latency_tx: broadcast::Sender<LatencySample>,
-    config: BotConfig,
-    mut cancel_rx: watch::Receiver<bool>,
-) {
+    exchange: &Exchange,
+    trade_tx: &broadcast::Sender<TradeEvent>,
+    latency_tx: &broadcast::Sender<LatencySample>,
+    config: &BotConfig,
+    seed: u64,
+    cancel_rx: &mut watch::Receiver<bool>,
+) -> bool {
     let symbol = config.symbol.clone();
     let mut tick = interval(Duration::from_millis(config.tick_ms.max(1)));
-    let mut rng = XorShiftRng::seed();
+    let mut rng = Rng::seed_from_u64(seed);
+    info!("bot_driver: {} seeded with {}", symbol, seed);
 
+    let restart = chaos::restart(&symbol);
+    tokio::pin!(restart);
     loop {
         tokio::select! {
             _ = cancel_rx.changed() => {
@@ -125,6 +137,10 @@ async fn run_driver(
                     break;
                 }
             }
+            _ = &mut restart => {
+                warn!("bot_driver: chaos restart for {}", symbol);
+                return true;
+            }
             _ = tick.tick() => {
                 let (best_bid, best_ask) = exchange
                     .get_best_prices(&symbol)
@@ -132,7 +148,7 @@ async fn run_driver(
                     .unwrap_or((None, None));
 
                 let reference_mid = match (best_bid, best_ask) {
-                    (Some(b), Some(a)) => (b + a) / 2,
+                    (Some(b), Some(a)) => b.midpoint(a),
                     _ => SEED_MID_TICKS,
                 };
                 let aggr = (config.aggression as f64 / 100.0).clamp(0.0, 1.0);
@@ -153,7 +169,7 @@ async fn run_driver(
                         Side::Ask => reference_mid + offset,
                     };
                     let qty = 10 + (rng.next_f64() * 80.0) as i64;
-                    orders.push(make_order(&symbol, side, price, qty));
+                    orders.push(make_order(&mut rng, &symbol, side, price, qty));
                 }
 
                 for _ in 0..config.takers {
@@ -177,7 +193,7 @@ async fn run_driver(
                         }
                     };
                     let qty = 5 + (rng.next_f64() * 50.0) as i64;
-                    orders.push(make_order(&symbol, side, price, qty));
+                    orders.push(make_order(&mut rng, &symbol, side, price, qty));
                 }
 
                 let Some(per_order) = exchange.submit_order_batch(&symbol, orders).await else {
@@ -190,9 +206,12 @@ async fn run_driver(
                     .map(|d| d.as_millis() as u64)
                     .unwrap_or(0);
 
-                for (trades, latency_ns) in per_order {
-                    let filled = !trades.is_empty();
-                    for trade in trades {
+                for (report, latency_ns) in per_order {
+                    let filled = report.filled_qty > 0;
+                    for trade in report.trades {
+                        if chaos::drop_message(&symbol) {
+                            continue;
+                        }
                         let _ = trade_tx.send(TradeEvent {
                             symbol: symbol.clone(),
                             trade,
@@ -210,45 +229,27 @@ async fn run_driver(
     }
 
     info!("bot_driver: task exited for {}", symbol);
+    false
 }
 
-fn make_order(symbol: &str, side: Side, price: i64, qty: i64) -> Order {
+/// Order ids come from the driver's RNG so a seeded run is fully reproducible.
+fn make_order(rng: &mut Rng, symbol: &str, side: Side, price: i64, qty: i64) -> Order {
     let now_ns = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_nanos())
         .unwrap_or(0);
     Order {
-        id: OrderId(uuid::Uuid::new_v4().as_u128()),
-        symbol: symbol.to_string(),
+        id: OrderId(((rng.next_u64() as u128) << 64) | rng.next_u64() as u128),
+        symbol: symbol.into(),
         side,
         px_ticks: price,
         qty,
         ts_ns: now_ns,
-    }
-}
-
-struct XorShiftRng(u64);
-
-impl XorShiftRng {
-    fn seed() -> Self {
-        let s = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_nanos() as u64)
-            .unwrap_or(0x9E37_79B9_7F4A_7C15)
-            ^ 0xDEAD_BEEF_CAFE_BABE;
-        Self(s | 1)
-    }
-
-    fn next_u64(&mut self) -> u64 {
-        let mut x = self.0;
-        x ^= x << 13;
-        x ^= x >> 7;
-        x ^= x << 17;
-        self.0 = x;
-        x
-    }
-
-    fn next_f64(&mut self) -> f64 {
-        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+        tif: TimeInForce::Day,
+        expires_ns: None,
+        display_qty: None,
+        min_qty: None,
+        hidden: false,
+        owner: ParticipantId::NONE,
     }
 }
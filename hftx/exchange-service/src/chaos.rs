@@ -0,0 +1,144 @@
+//! Fault injection for exercising recovery, resync, and backpressure.
+//!
+//! Only built with the `chaos` feature; without it every hook below is a
+//! no-op the compiler removes, so release builds carry none of it. With the
+//! feature, `EXCHANGE_CHAOS` names a JSON file:
+//!
+//! ```json
+//! {"symbols": ["AAPL"], "lock_hold_ms": 5, "lock_hold_rate": 0.01,
+//!  "drop_rate": 0.02, "storage_delay_ms": 250, "restart_every_secs": 30,
+//!  "seed": 7}
+//! ```
+//!
+//! - `lock_hold_ms` / `lock_hold_rate`: that share of submits and cancels
+//!   keep the book's write lock that much longer, blocking the thread as a
+//!   slow critical section would.
+//! - `drop_rate`: share of trade broadcasts and sequenced events dropped,
+//!   so stream and NATS consumers see lost messages and `seq` gaps.
+//! - `storage_delay_ms`: added before each trade store and audit log write,
+//!   so their queues back up.
+//! - `restart_every_secs`: every symbol stream (trades, depth, orders) is
+//!   closed and its bot driver restarted at that period, at an offset per
+//!   symbol, so clients must reconnect and resync.
+//!
+//! `symbols` limits the per-symbol faults to those symbols (all if empty);
+//! storage delays apply to every write. Draws come from one seeded
+//! generator (`seed`, or the clock, logged at startup).
+//!
+//! The faults are process-wide rather than threaded through each component,
+//! since the storage writers and stream handlers they reach have no other
+//! shared state.
+
+#[cfg(feature = "chaos")]
+use orderbook::rng::Rng;
+#[cfg(feature = "chaos")]
+use serde::Deserialize;
+#[cfg(feature = "chaos")]
+use std::sync::{Mutex, OnceLock};
+#[cfg(feature = "chaos")]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "chaos")]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ChaosConfig {
+    pub symbols: Vec<String>,
+    pub lock_hold_ms: u64,
+    pub lock_hold_rate: f64,
+    pub drop_rate: f64,
+    pub storage_delay_ms: u64,
+    pub restart_every_secs: u64,
+    pub seed: Option<u64>,
+}
+
+#[cfg(feature = "chaos")]
+impl ChaosConfig {
+    /// Reads the config from a JSON file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+#[cfg(feature = "chaos")]
+struct Chaos {
+    config: ChaosConfig,
+    rng: Mutex<Rng>,
+}
+
+#[cfg(feature = "chaos")]
+static CHAOS: OnceLock<Chaos> = OnceLock::new();
+
+/// Turns the faults on; later calls are ignored.
+#[cfg(feature = "chaos")]
+pub fn install(config: ChaosConfig) {
+    let seed = config.seed.unwrap_or_else(Rng::entropy_seed);
+    tracing::warn!("Chaos faults enabled (seed {}): {:?}", seed, config);
+    let _ = CHAOS.set(Chaos { config, rng: Mutex::new(Rng::seed_from_u64(seed)) });
+}
+
+/// The installed faults, if they cover `symbol`.
+#[cfg(feature = "chaos")]
+fn targeting(symbol: &str) -> Option<&'static Chaos> {
+    CHAOS.get().filter(|chaos| chaos.config.symbols.is_empty() || chaos.config.symbols.iter().any(|s| s == symbol))
+}
+
+#[cfg(feature = "chaos")]
+fn chance(chaos: &Chaos, rate: f64) -> bool {
+    rate > 0.0 && chaos.rng.lock().unwrap().next_f64() < rate
+}
+
+/// Sometimes sleeps while the caller holds `symbol`'s write lock.
+#[cfg(feature = "chaos")]
+pub fn hold_lock(symbol: &str) {
+    let Some(chaos) = targeting(symbol) else { return };
+    if chance(chaos, chaos.config.lock_hold_rate) {
+        std::thread::sleep(Duration::from_millis(chaos.config.lock_hold_ms));
+    }
+}
+
+/// Whether to drop a broadcast or event for `symbol`.
+#[cfg(feature = "chaos")]
+pub fn drop_message(symbol: &str) -> bool {
+    targeting(symbol).is_some_and(|chaos| chance(chaos, chaos.config.drop_rate))
+}
+
+/// Delays a storage write.
+#[cfg(feature = "chaos")]
+pub async fn slow_write() {
+    if let Some(chaos) = CHAOS.get().filter(|chaos| chaos.config.storage_delay_ms > 0) {
+        tokio::time::sleep(Duration::from_millis(chaos.config.storage_delay_ms)).await;
+    }
+}
+
+/// Resolves at `symbol`'s next forced restart; never, if none are due.
+#[cfg(feature = "chaos")]
+pub async fn restart(symbol: &str) {
+    let Some(period) = targeting(symbol).map(|c| c.config.restart_every_secs).filter(|&p| p > 0) else {
+        return std::future::pending().await;
+    };
+    let period_ms = period * 1_000;
+    // Spread symbols over the period so they don't all restart at once
+    let offset_ms = symbol.bytes().fold(0u64, |h, b| h.wrapping_mul(31).wrapping_add(b as u64)) % period_ms;
+    let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let wait_ms = period_ms - (now_ms + period_ms - offset_ms) % period_ms;
+    tokio::time::sleep(Duration::from_millis(wait_ms)).await;
+}
+
+#[cfg(not(feature = "chaos"))]
+#[inline(always)]
+pub fn hold_lock(_symbol: &str) {}
+
+#[cfg(not(feature = "chaos"))]
+#[inline(always)]
+pub fn drop_message(_symbol: &str) -> bool {
+    false
+}
+
+#[cfg(not(feature = "chaos"))]
+pub async fn slow_write() {}
+
+#[cfg(not(feature = "chaos"))]
+pub async fn restart(_symbol: &str) {
+    std::future::pending().await
+}
@@ -10,11 +10,25 @@
 //! - Designed for microsecond-level latency in order processing
 
 use dashmap::DashMap;
-use orderbook::{OrderBook, Order, OrderId, Side, Trade};
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
-use tokio::sync::RwLock;
+use orderbook::hotlog::Entry;
+use orderbook::lifecycle::OrderLifecycle;
+use orderbook::listener::BookListener;
+use orderbook::quotes::{Quote, QuoteError, QuoteReport};
+use orderbook::{BookLevel, ExecutionReport, OrderBook, Order, OrderId, OrderKind, OrderStatus, RejectReason, Side, SymbolConfig, SymbolId, TimeInForce, Trade};
+use std::fmt;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
+use tracing::{debug, warn};
 
-use crate::types::{OrderBookState, MarketDepth, PriceLevel};
+use crate::accounts::{AccountEvent, AccountView, Accounts, MarginConfig, MarginView, Rejection};
+use crate::chaos;
+use crate::hotlog::HotLog;
+use crate::surveillance::Surveillance;
+use crate::tca::{self, Tca};
+use crate::trade_store::TradeStore;
+use crate::types::{EventKind, LevelUpdate, OrderBookState, MarketDepth, PriceLevel, SequencedEvent, SubmitStats};
 
 /// Core exchange engine managing multiple trading symbols concurrently.
 ///
@@ -24,14 +38,142 @@ use crate::types::{OrderBookState, MarketDepth, PriceLevel};
 ///
 /// # Concurrency Model
 /// - `DashMap`: Provides lock-free access to the symbol-to-orderbook mapping
-/// - `RwLock<OrderBook>`: Allows multiple concurrent readers or exclusive writers per symbol
+/// - `RwLock<OrderBook>`: Allows multiple concurrent readers or exclusive writers per symbol,
+///   each in its own cache-line-aligned slot so symbols don't false-share
 /// - This design enables parallel processing of orders across different symbols
-/// while maintaining consistency within each symbol's order book
+///   while maintaining consistency within each symbol's order book
 pub struct Exchange {
     /// Concurrent hashmap storing order books for each trading symbol.
     /// Key: Symbol string (e.g., "AAPL", "TSLA")
     /// Value: RwLock-protected OrderBook for thread-safe access
-    orderbooks: DashMap<String, RwLock<OrderBook>>,
+    orderbooks: DashMap<String, BookSlot>,
+    /// Whether concurrent single-order submits are combined under one lock
+    batch_submits: bool,
+    /// Where sequenced events go, if anything consumes them
+    events: Vec<mpsc::Sender<SequencedEvent>>,
+    /// Events lost because the consumer fell behind
+    dropped_events: AtomicU64,
+    /// Order owners and the positions their fills build
+    accounts: Accounts,
+    /// Oldest a client-stamped order may be on arrival, if limited
+    latency_budget_ns: Option<u64>,
+    /// Orders refused for exceeding the latency budget
+    stale_rejects: AtomicU64,
+    /// Wash-trade flags
+    surveillance: Surveillance,
+    /// Every trade, for analytics
+    trade_store: TradeStore,
+    /// Arrival prices and fills of account orders
+    tca: Tca,
+    /// Binary log of the matching path, if enabled
+    hot_log: Option<Arc<HotLog>>,
+    /// Level changes of every book, as the books make them
+    level_updates: broadcast::Sender<LevelUpdate>,
+}
+
+/// What a single-order submit hears back: its report, or why the book
+/// refused it.
+type Submitted = Result<ExecutionReport, RejectReason>;
+
+/// One symbol's lock-protected book, padded out to its own cache lines.
+///
+/// DashMap stores values inline in its shard tables, so unpadded books sit
+/// back to back: the lock word of one symbol shares a line with the tail of
+/// its neighbour, and writers on different symbols keep invalidating each
+/// other's caches. 128 bytes also covers the adjacent-line prefetcher on x86.
+#[repr(align(128))]
+struct BookSlot {
+    book: RwLock<OrderBook>,
+    /// Single-order submits waiting for whoever next holds the write lock
+    pending: Mutex<Vec<(Order, oneshot::Sender<Submitted>)>>,
+    /// Orders matched through `submit_order`
+    submits: AtomicU64,
+    /// Write-lock acquisitions that matched at least one of those orders
+    submit_batches: AtomicU64,
+    /// Price of the latest trade, `NO_TRADE` before the first
+    last_px: AtomicI64,
+    /// Symbol id in the hot-path log, once the book has logged anything
+    log_id: OnceLock<u16>,
+}
+
+const NO_TRADE: i64 = i64::MIN;
+
+/// Level changes buffered per subscriber
+const LEVEL_BUFFER: usize = 4096;
+
+/// Canceled orders a book side holds queued before it compacts
+const COMPACT_AT: usize = 65_536;
+
+/// Ended orders a book still answers status lookups for
+const STATUS_RETAINED: usize = 100_000;
+
+/// Forwards a book's level changes to the exchange's subscribers.
+struct LevelFeed {
+    symbol: String,
+    updates: broadcast::Sender<LevelUpdate>,
+}
+
+impl BookListener for LevelFeed {
+    fn on_level_change(&mut self, side: Side, px_ticks: i64, qty: i64) {
+        // Nobody subscribed is not an error
+        let _ = self.updates.send(LevelUpdate { symbol: self.symbol.clone(), side, price: px_ticks, quantity: qty });
+    }
+}
+
+/// Why [`Exchange::modify_order`] left an order as it was.
+#[derive(Debug)]
+pub enum AmendError {
+    /// Not resting on the book (filled, cancelled, or never placed), or
+    /// another account's
+    NotFound,
+    Rejected(Rejection),
+    /// Not a positive price, or off the symbol's ticks or lots, or outside
+    /// its size range
+    Refused(RejectReason),
+}
+
+/// Why [`Exchange::submit_quote`] left the owner's quote as it was.
+#[derive(Debug)]
+pub enum QuoteRefusal {
+    /// Not a quote, or a side the book would refuse as an order
+    Invalid(QuoteError),
+    Rejected(Rejection),
+}
+
+impl From<QuoteError> for QuoteRefusal {
+    fn from(e: QuoteError) -> Self {
+        QuoteRefusal::Invalid(e)
+    }
+}
+
+impl fmt::Display for QuoteRefusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteRefusal::Invalid(e) => e.fmt(f),
+            QuoteRefusal::Rejected(rejection) => rejection.fmt(f),
+        }
+    }
+}
+
+impl BookSlot {
+    fn new(book: OrderBook) -> Self {
+        Self {
+            book: RwLock::new(book),
+            pending: Mutex::new(Vec::new()),
+            submits: AtomicU64::new(0),
+            submit_batches: AtomicU64::new(0),
+            last_px: AtomicI64::new(NO_TRADE),
+            log_id: OnceLock::new(),
+        }
+    }
+}
+
+impl std::ops::Deref for BookSlot {
+    type Target = RwLock<OrderBook>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.book
+    }
 }
 
 impl Exchange {
@@ -43,19 +185,297 @@ impl Exchange {
     pub fn new() -> Self {
         let exchange = Self {
             orderbooks: DashMap::new(),
+            batch_submits: true,
+            events: Vec::new(),
+            dropped_events: AtomicU64::new(0),
+            accounts: Accounts::default(),
+            latency_budget_ns: None,
+            stale_rejects: AtomicU64::new(0),
+            surveillance: Surveillance::default(),
+            trade_store: TradeStore::default(),
+            tca: Tca::new(TradeStore::default().retention_ns()),
+            hot_log: None,
+            level_updates: broadcast::channel(LEVEL_BUFFER).0,
         };
         
         // Pre-populate with high-volume tech stocks for demo purposes
         // In production, symbols would be loaded from a database or configuration
-        exchange.orderbooks.insert("AAPL".to_string(), RwLock::new(OrderBook::new()));
-        exchange.orderbooks.insert("TSLA".to_string(), RwLock::new(OrderBook::new()));
-        exchange.orderbooks.insert("MSFT".to_string(), RwLock::new(OrderBook::new()));
-        exchange.orderbooks.insert("NVDA".to_string(), RwLock::new(OrderBook::new()));
-        exchange.orderbooks.insert("GOOGL".to_string(), RwLock::new(OrderBook::new()));
+        exchange.orderbooks.insert("AAPL".to_string(), exchange.book_slot("AAPL"));
+        exchange.orderbooks.insert("TSLA".to_string(), exchange.book_slot("TSLA"));
+        exchange.orderbooks.insert("MSFT".to_string(), exchange.book_slot("MSFT"));
+        exchange.orderbooks.insert("NVDA".to_string(), exchange.book_slot("NVDA"));
+        exchange.orderbooks.insert("GOOGL".to_string(), exchange.book_slot("GOOGL"));
         
         exchange
     }
 
+    /// Enables or disables combining concurrent `submit_order` calls on the
+    /// same symbol into one write-lock acquisition (on by default).
+    pub fn with_submit_batching(mut self, enabled: bool) -> Self {
+        self.batch_submits = enabled;
+        self
+    }
+
+    /// Sends every accepted submit, trade, and cancel to `sink` as a
+    /// [`SequencedEvent`], alongside any sinks added before. Sending never
+    /// waits: when a sink is full the event is dropped for that sink only,
+    /// so its consumer sees a gap.
+    pub fn with_event_sink(mut self, sink: mpsc::Sender<SequencedEvent>) -> Self {
+        self.events.push(sink);
+        self
+    }
+
+    /// Refuses orders whose client timestamp is more than `budget_ns` older
+    /// than their arrival (see [`check_fresh`](Self::check_fresh)).
+    pub fn with_latency_budget(mut self, budget_ns: u64) -> Self {
+        self.latency_budget_ns = Some(budget_ns);
+        self
+    }
+
+    /// Runs surveillance with `surveillance`'s linked accounts, spoofing
+    /// thresholds, and audit store (the default has none of them, but
+    /// still flags same-account trades and spoofing at default thresholds).
+    pub fn with_surveillance(mut self, surveillance: Surveillance) -> Self {
+        self.surveillance = surveillance;
+        self
+    }
+
+    /// Records trades to `store` (by default, a day in memory). Orders are
+    /// kept for cost analysis as long as the trades that benchmark them.
+    pub fn with_trade_store(mut self, store: TradeStore) -> Self {
+        self.tca = Tca::new(store.retention_ns());
+        self.trade_store = store;
+        self
+    }
+
+    /// Records the matching path of every book to `log`.
+    pub fn with_hot_log(mut self, log: Arc<HotLog>) -> Self {
+        self.hot_log = Some(log);
+        self
+    }
+
+    /// The hot-path log and `slot`'s id in it, if logging is on.
+    fn hot_log(&self, slot: &BookSlot, symbol: &str) -> Option<(&HotLog, u16)> {
+        let log = self.hot_log.as_deref()?;
+        Some((log, *slot.log_id.get_or_init(|| log.register(symbol))))
+    }
+
+    /// Margin-checks account-tagged orders against `config`.
+    pub fn with_margin(mut self, config: MarginConfig) -> Self {
+        self.accounts = Accounts::new(config);
+        self
+    }
+
+    /// Hands `kind` to the event sinks as the book numbered it, `seq` (see
+    /// [`OrderBook::seq`]). Callers hold the book's write lock, so events go
+    /// out in matching order.
+    fn sequence(&self, symbol: &str, seq: u64, kind: EventKind) {
+        let Some((last, rest)) = self.events.split_last() else { return };
+        let event = SequencedEvent {
+            symbol: symbol.to_string(),
+            seq,
+            ts_ns: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64,
+            kind,
+        };
+        // After numbering, so the loss shows as a gap
+        if chaos::drop_message(symbol) {
+            return;
+        }
+        for sink in rest {
+            self.send_event(sink, event.clone());
+        }
+        self.send_event(last, event);
+    }
+
+    fn send_event(&self, sink: &mpsc::Sender<SequencedEvent>, event: SequencedEvent) {
+        if sink.try_send(event).is_err() {
+            let dropped = self.dropped_events.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                warn!("Event sink full or closed; {} events dropped so far", dropped);
+            }
+        }
+    }
+
+    /// Matches `order` under the write lock and sequences it with its
+    /// trades, which include those of any stops they set off. With `stop`
+    /// it goes in through `submit_stop` instead of `submit_limit`, and a
+    /// stop still held counts as resting. Also returns the time spent
+    /// inside the matcher alone. Orders the book would refuse (see
+    /// [`OrderBook::check_order`]) are dropped here unmatched with the
+    /// book's reason, and those of a killed account reported rejected.
+    fn match_order(
+        &self,
+        slot: &BookSlot,
+        book: &mut OrderBook,
+        symbol: &str,
+        order: Order,
+        stop: Option<(i64, OrderKind)>,
+    ) -> Result<(ExecutionReport, u128), RejectReason> {
+        let hot_log = self.hot_log(slot, symbol);
+        let (id, side, px_ticks, qty, tif) = (order.id, order.side, order.px_ticks, order.qty, order.tif);
+        if let Some((log, sym)) = hot_log {
+            log.push(sym, Entry::OrderIn { id, side, px_ticks, qty });
+        }
+        let checked = match stop {
+            Some((stop_px, kind)) => book.check_stop(&order, stop_px, kind),
+            None => book.check_order(&order, OrderKind::Limit),
+        };
+        if checked.is_err() {
+            self.accounts.forget(order.id);
+        }
+        if checked.is_err() || self.accounts.refuses(&order) {
+            if let Some((log, sym)) = hot_log {
+                log.push(sym, Entry::Reject { id });
+            }
+            checked?;
+            return Ok((rejected(&order), 0));
+        }
+        if self.accounts.in_use() {
+            self.surveillance.on_submit(book, &order, || self.accounts.owner(order.id));
+            if let Some(account) = self.accounts.owner(order.id) {
+                let last_px = slot.last_px.load(Ordering::Relaxed);
+                self.tca.arrive(&order, account, tca::mid_or_last(book, (last_px != NO_TRADE).then_some(last_px)));
+            }
+        }
+        let submitted = (!self.events.is_empty()).then(|| match stop {
+            Some((stop_px, kind)) => EventKind::Stop { order: order.clone(), stop_px, kind },
+            None => EventKind::Submit(order.clone()),
+        });
+        // The book numbers the submit first, then each of its trades
+        let seq = book.seq() + 1;
+        let t0 = Instant::now();
+        let report = match stop {
+            Some((stop_px, kind)) => {
+                let trades = book.submit_stop(order, stop_px, kind).expect("checked above");
+                let resting = book.stops.contains(id) || book.bids.contains(id) || book.asks.contains(id);
+                ExecutionReport::new(id, qty, tif, resting, trades)
+            }
+            None => book.submit_limit(order).expect("checked above"),
+        };
+        let engine_ns = t0.elapsed().as_nanos();
+        let trades = &report.trades;
+        let filled: i64 = trades.iter().filter(|t| t.taker == id).map(|t| t.qty).sum();
+        if let Some((log, sym)) = hot_log {
+            let ts_ns = log.now_ns();
+            for trade in trades {
+                log.push_at(ts_ns, sym, Entry::Fill { maker: trade.maker, taker: trade.taker, px_ticks: trade.px_ticks, qty: trade.qty });
+            }
+            let rested = book.levels(side).contains(id);
+            if rested {
+                log.push_at(ts_ns, sym, Entry::Rest { id, side, px_ticks, qty: qty - filled });
+            }
+        }
+        chaos::hold_lock(symbol);
+        if let Some(last) = trades.last() {
+            slot.last_px.store(last.px_ticks, Ordering::Relaxed);
+            self.trade_store.record(trades);
+        }
+        if !trades.is_empty() && self.accounts.in_use() {
+            // Before on_trades, which retires filled orders and their owners
+            self.surveillance.check(trades, |id| self.accounts.owner(id));
+            self.tca.fill(trades);
+        }
+        self.accounts.on_trades(trades);
+        if let Some(event) = submitted {
+            self.sequence(symbol, seq, event);
+            for trade in trades {
+                self.sequence(symbol, trade.seq, EventKind::Trade(trade.clone()));
+            }
+        }
+        if let Some((log, sym)) = hot_log {
+            log.push(sym, Entry::Out { taker: id, fills: trades.len() as u32, filled });
+        }
+        Ok((report, engine_ns))
+    }
+
+    /// Checks a client send time against the latency budget, so a delayed
+    /// or replayed order can't execute on a market that has since moved.
+    /// Orders without a timestamp, or stamped after arrival (clock skew),
+    /// always pass.
+    /// # Returns
+    /// * `Err(age_ns)` - The order arrived `age_ns` after it was sent, over budget
+    pub fn check_fresh(&self, client_ts_ns: Option<u64>, received_ns: u64) -> Result<(), u64> {
+        let (Some(budget_ns), Some(sent_ns)) = (self.latency_budget_ns, client_ts_ns) else {
+            return Ok(());
+        };
+        let age_ns = received_ns.saturating_sub(sent_ns);
+        if age_ns > budget_ns {
+            self.stale_rejects.fetch_add(1, Ordering::Relaxed);
+            return Err(age_ns);
+        }
+        Ok(())
+    }
+
+    /// The configured latency budget.
+    pub fn latency_budget_ns(&self) -> Option<u64> {
+        self.latency_budget_ns
+    }
+
+    /// Owners of account-tagged orders and their positions.
+    pub fn accounts(&self) -> &Accounts {
+        &self.accounts
+    }
+
+    /// Trade history within the store's retention.
+    pub fn trade_store(&self) -> &TradeStore {
+        &self.trade_store
+    }
+
+    /// Transaction costs of account orders.
+    pub fn tca(&self) -> &Tca {
+        &self.tca
+    }
+
+    /// Wash-trade report and the operator alert channel.
+    pub fn surveillance(&self) -> &Surveillance {
+        &self.surveillance
+    }
+
+    /// Makes `account` the owner of `order`, which must not have been
+    /// submitted yet. Fails if the account is blocked or can't margin it.
+    pub fn assign_order(&self, account: &str, order: &mut Order) -> Result<(), Rejection> {
+        self.accounts.assign(account, order, |symbol| self.last_trade_price(symbol).flatten())
+    }
+
+    /// [`assign_order`](Self::assign_order) for the exchange's own orders,
+    /// which a kill doesn't block.
+    pub fn assign_forced_order(&self, account: &str, order: &mut Order) -> Result<(), Rejection> {
+        self.accounts.assign_forced(account, order, |symbol| self.last_trade_price(symbol).flatten())
+    }
+
+    /// Blocks `account` and cancels every order it has resting, on every
+    /// symbol. The block lands first, so no new order slips in behind the
+    /// cancels. Returns how many orders were cancelled.
+    pub async fn kill_account(&self, account: &str) -> usize {
+        self.accounts.block(account);
+        let mut cancelled = 0;
+        for (symbol, order_id) in self.accounts.open_orders(account) {
+            if let Some(Some(_)) = self.cancel_order(&symbol, order_id, None).await {
+                cancelled += 1;
+            }
+        }
+        let ts_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        self.accounts.publish(AccountEvent::Killed { account: account.to_string(), cancelled_orders: cancelled, ts_ns });
+        cancelled
+    }
+
+    /// Drops ownership of orders that were assigned but never submitted.
+    pub fn release_orders(&self, orders: &[Order]) {
+        for order in orders {
+            self.accounts.forget(order.id);
+        }
+    }
+
+    /// Accounts below maintenance margin at current prices.
+    pub fn margin_calls(&self) -> Vec<(String, MarginView)> {
+        self.accounts.margin_calls(|symbol| self.last_trade_price(symbol).flatten())
+    }
+
+    /// `account`'s positions and margin, valued at current prices.
+    pub fn account_view(&self, account: &str) -> Option<AccountView> {
+        self.accounts.view(account, |symbol| self.last_trade_price(symbol).flatten())
+    }
+
     /// Returns all trading symbols currently supported by the exchange.
     /// This operation is lock-free thanks to DashMap's concurrent iteration.
     /// The returned vector contains symbol strings in arbitrary order.
@@ -108,88 +528,130 @@ impl Exchange {
         let orderbook_lock = self.orderbooks.get(symbol)?;
         let orderbook = orderbook_lock.read().await;
         
-        let mut bids = Vec::new();
-        let mut asks = Vec::new();
-        
-        // Process bid side: highest prices first (best bids)
-        let bid_iter = orderbook.bids.iter_levels_best_first();
-        for (price, qty) in bid_iter.take(levels) {
-            if qty > 0 {  // Only include levels with actual quantity
-                let orders = orderbook.bids.get_price_levels()
-                    .get(&price)
-                    .map(|q| q.len())
-                    .unwrap_or(0);
-                
-                bids.push(PriceLevel {
-                    price,
-                    quantity: qty,
-                    orders,
-                });
-            }
-        }
-        
-        // Process ask side: lowest prices first (best asks)
-        let ask_iter = orderbook.asks.iter_levels_best_first();
-        for (price, qty) in ask_iter.take(levels) {
-            if qty > 0 {  // Only include levels with actual quantity
-                let orders = orderbook.asks.get_price_levels()
-                    .get(&price)
-                    .map(|q| q.len())
-                    .unwrap_or(0);
-                
-                asks.push(PriceLevel {
-                    price,
-                    quantity: qty,
-                    orders,
-                });
-            }
-        }
+        let depth = orderbook.depth(levels);
+        let level = |level: &BookLevel| PriceLevel { price: level.px_ticks, quantity: level.qty, orders: level.orders };
+        let bids = depth.bids.iter().map(level).collect();
+        let asks = depth.asks.iter().map(level).collect();
         
         Some(MarketDepth {
             symbol: symbol.to_string(),
             bids,
             asks,
             timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64,
+            seq: depth.seq,
         })
     }
 
     /// Submits a limit order to the specified symbol's order book.
+    ///
+    /// Concurrent submits to one symbol are combined: each caller queues its
+    /// order, and whoever next gets the write lock matches everything queued
+    /// so far in arrival order and hands each caller its report. Callers whose
+    /// order was matched by someone else return without touching the lock, so
+    /// a burst of N submits costs a few lock acquisitions instead of N.
     /// # Arguments
     /// * `symbol` - Trading symbol for the order
     /// * `order` - Complete order details including price, quantity, and side
     /// # Returns
-    /// * `Some(Vec<Trade>)` - Vector of trades executed immediately (if any)
+    /// * `Some(Ok(report))` - What became of the order, with any trades
+    ///   executed immediately
+    /// * `Some(Err(reason))` - Refused by the book (see
+    ///   [`OrderBook::check_order`]), e.g. for another symbol or off tick
     /// * `None` - If symbol doesn't exist
-    pub async fn submit_order(&self, symbol: String, order: Order) -> Option<Vec<Trade>> {
-        let orderbook_lock = self.orderbooks.get(&symbol)?;
+    pub async fn submit_order(&self, symbol: String, order: Order) -> Option<Result<ExecutionReport, RejectReason>> {
+        let slot = self.orderbooks.get(&symbol)?;
 
-        // Acquire write lock
-        let mut orderbook = orderbook_lock.write().await;
+        if !self.batch_submits {
+            let mut orderbook = slot.write().await;
+            slot.submits.fetch_add(1, Ordering::Relaxed);
+            slot.submit_batches.fetch_add(1, Ordering::Relaxed);
+            return Some(self.match_order(&slot, &mut orderbook, &symbol, order, None).map(|(report, _)| report));
+        }
+
+        let (reply_tx, mut reply_rx) = oneshot::channel();
+        slot.pending.lock().unwrap().push((order, reply_tx));
 
-        // Submit limit order
-        let trades = orderbook.submit_limit(order);
-        Some(trades)
+        // Our order is queued before we wait on the lock, so either a holder
+        // that drains after this point matches it, or we drain it ourselves
+        tokio::select! {
+            biased;
+            report = &mut reply_rx => return report.ok(),
+            mut orderbook = slot.write() => {
+                let batch = std::mem::take(&mut *slot.pending.lock().unwrap());
+                if !batch.is_empty() {
+                    slot.submits.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                    slot.submit_batches.fetch_add(1, Ordering::Relaxed);
+                }
+                for (order, reply) in batch {
+                    let _ = reply.send(self.match_order(&slot, &mut orderbook, &symbol, order, None).map(|(report, _)| report));
+                }
+            }
+        }
+        reply_rx.await.ok()
+    }
+
+    /// Submits a stop (`kind` market) or stop-limit (`kind` limit, at the
+    /// order's price) that the book holds until a trade prints at or
+    /// through `stop_px`. Stops skip submit batching.
+    /// # Returns
+    /// * `Some(Ok((report, held)))` - Its report, with trades if the last
+    ///   trade price had already set it off, and whether it is now held
+    ///   waiting for one
+    /// * `Some(Err(reason))` - Refused by the book (see
+    ///   [`OrderBook::check_stop`])
+    /// * `None` - If symbol doesn't exist
+    pub async fn submit_stop(
+        &self,
+        symbol: &str,
+        order: Order,
+        stop_px: i64,
+        kind: OrderKind,
+    ) -> Option<Result<(ExecutionReport, bool), RejectReason>> {
+        let slot = self.orderbooks.get(symbol)?;
+        let mut orderbook = slot.write().await;
+        slot.submits.fetch_add(1, Ordering::Relaxed);
+        slot.submit_batches.fetch_add(1, Ordering::Relaxed);
+        let id = order.id;
+        let result = self.match_order(&slot, &mut orderbook, symbol, order, Some((stop_px, kind)));
+        Some(result.map(|(report, _)| (report, orderbook.stops.contains(id))))
+    }
+
+    /// Submit counters summed over every symbol.
+    pub fn submit_stats(&self) -> SubmitStats {
+        let (submits, submit_batches) = self.orderbooks.iter().fold((0, 0), |(orders, batches), entry| {
+            (
+                orders + entry.submits.load(Ordering::Relaxed),
+                batches + entry.submit_batches.load(Ordering::Relaxed),
+            )
+        });
+        SubmitStats {
+            batching: self.batch_submits,
+            submits,
+            submit_batches,
+            stale_rejects: self.stale_rejects.load(Ordering::Relaxed),
+        }
     }
 
     /// Submits a batch of orders to a single symbol's order book under one
-    /// write lock. Returns per-order (trades, engine_ns) where engine_ns is
+    /// write lock. Returns per-order (report, engine_ns) where engine_ns is
     /// the monotonic time spent inside `submit_limit` for that order only —
-    /// the number to plot in a "true engine latency" histogram.
+    /// the number to plot in a "true engine latency" histogram. Orders the
+    /// book refuses are reported rejected.
     pub async fn submit_order_batch(
         &self,
         symbol: &str,
         orders: Vec<Order>,
-    ) -> Option<Vec<(Vec<Trade>, u128)>> {
+    ) -> Option<Vec<(ExecutionReport, u128)>> {
         let orderbook_lock = self.orderbooks.get(symbol)?;
         let mut orderbook = orderbook_lock.write().await;
 
-        let mut out = Vec::with_capacity(orders.len());
-        for order in orders {
-            let t0 = Instant::now();
-            let trades = orderbook.submit_limit(order);
-            let latency_ns = t0.elapsed().as_nanos();
-            out.push((trades, latency_ns));
-        }
+        let out = orders
+            .into_iter()
+            .map(|order| {
+                let refused = rejected(&order);
+                self.match_order(&orderbook_lock, &mut orderbook, symbol, order, None).unwrap_or((refused, 0))
+            })
+            .collect();
         Some(out)
     }
 
@@ -197,22 +659,327 @@ impl Exchange {
     /// # Arguments
     /// * `symbol` - Trading symbol containing the order
     /// * `order_id` - Unique identifier of the order to cancel
+    /// * `account` - If given, only that account's order is cancelled
     /// # Returns
-    /// * `Some(true)` - Order was found and cancelled successfully
-    /// * `Some(false)` - Order was not found (may have already filled/cancelled)
+    /// * `Some(Some(order))` - Order was found and cancelled; `order.qty` is
+    ///   what it had left unfilled
+    /// * `Some(None)` - Order was not found (may have already filled/cancelled,
+    ///   or belong to another account)
     /// * `None` - Symbol doesn't exist
-    pub async fn cancel_order(&self, symbol: &str, order_id: OrderId) -> Option<bool> {
+    pub async fn cancel_order(&self, symbol: &str, order_id: OrderId, account: Option<&str>) -> Option<Option<Order>> {
         let orderbook_lock = self.orderbooks.get(symbol)?;
         
         // Acquire write lock
         let mut orderbook = orderbook_lock.write().await;
         
-        // Search both sides
-        let cancelled_from_bids = orderbook.bids.cancel(order_id);
-        let cancelled_from_asks = orderbook.asks.cancel(order_id);
-        
-        // Return true if cancelled from either side
-        Some(cancelled_from_bids || cancelled_from_asks)
+        // Another account's order is as good as missing
+        let theirs = account.is_some_and(|account| orderbook.owner_of(order_id) != self.accounts.participant_of(account));
+        // Taken out eagerly, so the unfilled size can be reported
+        let order = if theirs {
+            None
+        } else if let Some(order) = orderbook.remove(order_id) {
+            Some(order)
+        } else {
+            orderbook.cancel_stop(order_id).map(|stop| stop.order)
+        };
+        let Some(order) = order else {
+            if let Some((log, sym)) = self.hot_log(&orderbook_lock, symbol) {
+                log.push(sym, Entry::Cancel { id: order_id, side: Side::Bid, found: false });
+            }
+            return Some(None);
+        };
+        let seq = orderbook.seq();
+        self.cancelled(&orderbook_lock, symbol, &mut orderbook, order_id, order.side, seq);
+        self.repriced(&orderbook_lock, symbol, &mut orderbook, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+        chaos::hold_lock(symbol);
+        Some(Some(order))
+    }
+
+    /// What became of an order: its quantities and where it stands. The
+    /// book answers for live orders and the last ones to end.
+    /// # Returns
+    /// * `Some(Some(lifecycle))` - The order's lifecycle
+    /// * `Some(None)` - Not known (never seen, long ended, or, with
+    ///   `account`, another account's)
+    /// * `None` - Symbol doesn't exist
+    pub async fn order_status(&self, symbol: &str, order_id: OrderId, account: Option<&str>) -> Option<Option<OrderLifecycle>> {
+        let slot = self.orderbooks.get(symbol)?;
+        let orderbook = slot.read().await;
+        let lifecycle = orderbook.order_status(order_id).copied();
+        Some(lifecycle.filter(|l| account.is_none_or(|account| self.accounts.participant_of(account) == Some(l.owner))))
+    }
+
+    /// A symbol's trading parameters, or `None` if it isn't listed.
+    pub async fn symbol_config(&self, symbol: &str) -> Option<SymbolConfig> {
+        let slot = self.orderbooks.get(symbol)?;
+        let config = *slot.read().await.config();
+        Some(config)
+    }
+
+    /// Replaces a symbol's trading parameters; new submissions and amends
+    /// must keep to them, while orders already resting stay.
+    /// # Returns
+    /// * `Some(Ok(()))` - The book keeps to `config` from now on
+    /// * `Some(Err(_))` - Why `config` isn't valid; nothing was changed
+    /// * `None` - Symbol doesn't exist
+    pub async fn set_symbol_config(&self, symbol: &str, config: SymbolConfig) -> Option<Result<(), String>> {
+        let slot = self.orderbooks.get(symbol)?;
+        let set = slot.write().await.set_config(config);
+        Some(set)
+    }
+
+    /// Runs the checks the book makes on every limit order (see
+    /// [`OrderBook::check_order`]) without submitting, so a caller can
+    /// refuse a batch before any of it matches. `None` if the symbol isn't
+    /// listed.
+    pub async fn check_order(&self, symbol: &str, order: &Order) -> Option<Result<(), RejectReason>> {
+        let slot = self.orderbooks.get(symbol)?;
+        let orderbook = slot.read().await;
+        Some(orderbook.check_order(order, OrderKind::Limit))
+    }
+
+    /// Replaces `owner`'s two-sided quote on a symbol's book under one
+    /// write lock, as [`OrderBook::submit_quote`] does: what is left of
+    /// the last quote is cancelled, then each quoted side is matched like
+    /// a submitted order. Both sides are checked first (see
+    /// [`OrderBook::check_quote`]), so a side the book or the account would
+    /// refuse leaves the last quote standing. A side may reuse an id of the
+    /// quote it replaces.
+    /// # Returns
+    /// * `Some(Ok(report))` - The replaced orders and each side's report
+    /// * `Some(Err(_))` - Not a valid quote, or a side refused; nothing
+    ///   was changed
+    /// * `None` - Symbol doesn't exist
+    pub async fn submit_quote(&self, symbol: &str, owner: &str, bid: Order, ask: Order) -> Option<Result<QuoteReport, QuoteRefusal>> {
+        let slot = self.orderbooks.get(symbol)?;
+        let mut orderbook = slot.write().await;
+        if let Err(e) = orderbook.check_quote(owner, &bid, &ask) {
+            return Some(Err(e.into()));
+        }
+        let quote = Quote { bid: (bid.qty > 0).then_some(bid.id), ask: (ask.qty > 0).then_some(ask.id) };
+        // A kill that landed since the orders were assigned
+        if [&bid, &ask].into_iter().any(|order| order.qty > 0 && self.accounts.refuses(order)) {
+            return Some(Err(QuoteRefusal::Rejected(Rejection::Blocked(owner.to_string()))));
+        }
+        let last = orderbook.quotes.get(owner).copied().unwrap_or_default();
+        let mut replaced = Vec::new();
+        for id in [last.bid, last.ask].into_iter().flatten() {
+            if let Some(order) = orderbook.remove(id) {
+                let seq = orderbook.seq();
+                if [quote.bid, quote.ask].contains(&Some(id)) {
+                    // The side reusing the id is already tracked in its place
+                    self.report_cancel(&slot, symbol, &mut orderbook, id, order.side, seq);
+                    self.accounts.forget_replaced(&order);
+                } else {
+                    self.cancelled(&slot, symbol, &mut orderbook, id, order.side, seq);
+                }
+                replaced.push(order);
+            }
+        }
+        let [bid, ask] = [bid, ask].map(|order| {
+            (order.qty > 0).then(|| {
+                slot.submits.fetch_add(1, Ordering::Relaxed);
+                self.match_order(&slot, &mut orderbook, symbol, order, None).expect("checked above").0
+            })
+        });
+        // A side entered reprices the pegs itself; a pulled quote doesn't
+        if bid.is_none() && ask.is_none() {
+            self.repriced(&slot, symbol, &mut orderbook, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+        }
+        // Recorded once entered, as submitting forgets quoted ids
+        orderbook.quotes.replace(owner, quote);
+        slot.submit_batches.fetch_add(1, Ordering::Relaxed);
+        Some(Ok(QuoteReport { replaced, bid, ask }))
+    }
+
+    /// Cancels every resting order on `side` (both when None) priced from
+    /// `lo` to `hi` inclusive, under one write lock, each reported like a
+    /// client cancel. Held stops keep waiting.
+    /// # Returns
+    /// * `Some(ids)` - The cancelled orders, bids first
+    /// * `None` - Symbol doesn't exist
+    pub async fn cancel_orders(&self, symbol: &str, side: Option<Side>, lo: i64, hi: i64) -> Option<Vec<OrderId>> {
+        let slot = self.orderbooks.get(symbol)?;
+        let mut orderbook = slot.write().await;
+        let mut cancelled = Vec::new();
+        for side in [Side::Bid, Side::Ask].into_iter().filter(|&s| side.is_none_or(|side| side == s)) {
+            // The book numbers each cancel in turn
+            let from = orderbook.seq();
+            for (seq, id) in (from + 1..).zip(orderbook.cancel_side_by_price_range(side, lo, hi)) {
+                self.cancelled(&slot, symbol, &mut orderbook, id, side, seq);
+                cancelled.push(id);
+            }
+        }
+        self.repriced(&slot, symbol, &mut orderbook, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+        chaos::hold_lock(symbol);
+        Some(cancelled)
+    }
+
+    /// Cancels every order `account` has resting on a symbol's book under
+    /// one write lock, bids first. Held stops stay held.
+    /// # Returns
+    /// * `Some(ids)` - The cancelled orders, empty if it had none
+    /// * `None` - Symbol doesn't exist
+    pub async fn cancel_account_orders(&self, symbol: &str, account: &str) -> Option<Vec<OrderId>> {
+        let slot = self.orderbooks.get(symbol)?;
+        let Some(owner) = self.accounts.participant_of(account) else { return Some(Vec::new()) };
+        let mut orderbook = slot.write().await;
+        // The bids come back first, each numbered in turn
+        let bids = orderbook.bids.orders_of(owner).count();
+        let from = orderbook.seq();
+        let cancelled = orderbook.cancel_all_for(owner);
+        for (i, (seq, &id)) in (from + 1..).zip(&cancelled).enumerate() {
+            let side = if i < bids { Side::Bid } else { Side::Ask };
+            self.cancelled(&slot, symbol, &mut orderbook, id, side, seq);
+        }
+        self.repriced(&slot, symbol, &mut orderbook, SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+        chaos::hold_lock(symbol);
+        Some(cancelled)
+    }
+
+    /// `account`'s orders resting across every symbol, as (symbol, order);
+    /// an iceberg shows its displayed slice. Held stops aren't included.
+    pub async fn account_orders(&self, account: &str) -> Vec<(String, Order)> {
+        let Some(owner) = self.accounts.participant_of(account) else { return Vec::new() };
+        let mut orders = Vec::new();
+        for symbol in self.list_symbols().await {
+            let Some(slot) = self.orderbooks.get(&symbol) else { continue };
+            let orderbook = slot.read().await;
+            orders.extend(orderbook.orders_of(owner).map(|order| (symbol.clone(), order.clone())));
+        }
+        orders
+    }
+
+    /// Amends a resting order to `qty` (what should remain of it) at
+    /// `px_ticks`. A size reduction at the same price keeps its place in
+    /// the queue; anything else takes it out and re-enters it behind its
+    /// new level, where it can trade like a new order. With `account`,
+    /// only that account's orders can be amended, as with cancels.
+    /// # Returns
+    /// * `Some(Ok(report))` - The amended order's report: trades the
+    ///   re-entry made, if any, and what is left of it
+    /// * `Some(Err(_))` - Not resting (or another account's), refused by
+    ///   the book (see [`OrderBook::check_amend`]), or refused for margin
+    /// * `None` - Symbol doesn't exist
+    pub async fn modify_order(
+        &self,
+        symbol: &str,
+        order_id: OrderId,
+        px_ticks: i64,
+        qty: i64,
+        account: Option<&str>,
+    ) -> Option<Result<ExecutionReport, AmendError>> {
+        let slot = self.orderbooks.get(symbol)?;
+        let mut orderbook = slot.write().await;
+        // Another account's order is as good as missing
+        if account.is_some_and(|account| orderbook.owner_of(order_id) != self.accounts.participant_of(account)) {
+            return Some(Err(AmendError::NotFound));
+        }
+        let Some(side) = orderbook.side_of(order_id) else {
+            return Some(Err(AmendError::NotFound));
+        };
+        let ts_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let seq = orderbook.seq() + 1;
+        // The book refuses terms it wouldn't take, and the account is then
+        // left as it was
+        let amended = self.accounts.amend(order_id, px_ticks, qty, |s| self.last_trade_price(s).flatten(), || {
+            orderbook.modify(order_id, px_ticks, qty, ts_ns)
+        });
+        let trades = match amended {
+            Ok(Ok(trades)) => trades.unwrap_or_default(),
+            Ok(Err(reason)) => return Some(Err(AmendError::Refused(reason))),
+            Err(e) => return Some(Err(AmendError::Rejected(e))),
+        };
+        chaos::hold_lock(symbol);
+
+        self.sequence(symbol, seq, EventKind::Modify { id: order_id, side, px_ticks, qty, ts_ns: ts_ns as u64 });
+        self.traded(&slot, symbol, &trades);
+        // Only Day orders rest, so that is what an amend re-enters as
+        let resting = orderbook.bids.contains(order_id) || orderbook.asks.contains(order_id);
+        Some(Ok(ExecutionReport::new(order_id, qty, TimeInForce::Day, resting, trades)))
+    }
+
+    /// Reports an order that just left `side` of the book unfilled, the
+    /// book's event `seq`, to the hot-path log, the event stream, and the
+    /// account, surveillance and TCA records. Called under the book's write
+    /// lock.
+    fn cancelled(&self, slot: &BookSlot, symbol: &str, orderbook: &mut OrderBook, order_id: OrderId, side: Side, seq: u64) {
+        self.report_cancel(slot, symbol, orderbook, order_id, side, seq);
+        self.accounts.forget(order_id);
+    }
+
+    /// [`cancelled`](Self::cancelled), but leaving the account's record of
+    /// the order alone.
+    fn report_cancel(&self, slot: &BookSlot, symbol: &str, orderbook: &mut OrderBook, order_id: OrderId, side: Side, seq: u64) {
+        if let Some((log, sym)) = self.hot_log(slot, symbol) {
+            log.push(sym, Entry::Cancel { id: order_id, side, found: true });
+        }
+        self.sequence(symbol, seq, EventKind::Cancel { id: order_id, side });
+        if self.accounts.in_use() {
+            self.surveillance.on_cancel(order_id);
+            let last_px = slot.last_px.load(Ordering::Relaxed);
+            self.tca.end(order_id, tca::mid_or_last(orderbook, (last_px != NO_TRADE).then_some(last_px)));
+        }
+    }
+
+    /// Reports trades no new order made, an amend's re-entry or pegs
+    /// repriced, to the hot-path log, the trade store, the account,
+    /// surveillance and TCA records, and the event stream. Called under the
+    /// book's write lock.
+    fn traded(&self, slot: &BookSlot, symbol: &str, trades: &[Trade]) {
+        if trades.is_empty() {
+            return;
+        }
+        if let Some((log, sym)) = self.hot_log(slot, symbol) {
+            let ts_ns = log.now_ns();
+            for trade in trades {
+                log.push_at(ts_ns, sym, Entry::Fill { maker: trade.maker, taker: trade.taker, px_ticks: trade.px_ticks, qty: trade.qty });
+            }
+        }
+        let last = trades.last().expect("not empty");
+        slot.last_px.store(last.px_ticks, Ordering::Relaxed);
+        self.trade_store.record(trades);
+        if self.accounts.in_use() {
+            self.surveillance.check(trades, |id| self.accounts.owner(id));
+            self.tca.fill(trades);
+        }
+        self.accounts.on_trades(trades);
+        for trade in trades {
+            self.sequence(symbol, trade.seq, EventKind::Trade(trade.clone()));
+        }
+    }
+
+    /// Moves the book's pegs after cancels or expiries that may have moved
+    /// its best prices (see [`OrderBook::reprice`]), stamped `ts_ns`, and
+    /// reports what they trade. Called under the book's write lock.
+    fn repriced(&self, slot: &BookSlot, symbol: &str, orderbook: &mut OrderBook, ts_ns: u128) {
+        let trades = orderbook.reprice(ts_ns);
+        self.traded(slot, symbol, &trades);
+    }
+
+    /// Cancels every good-til-date order whose `expires_ns` is at or before
+    /// `now_ns`, each reported like a client cancel. Books with nothing due
+    /// are only read-locked. Returns how many orders expired.
+    pub async fn expire_orders(&self, now_ns: u128) -> usize {
+        let symbols: Vec<String> = self.orderbooks.iter().map(|entry| entry.key().clone()).collect();
+        let mut expired = 0;
+        for symbol in symbols {
+            let Some(slot) = self.orderbooks.get(&symbol) else { continue };
+            if slot.read().await.next_expiry().is_none_or(|at| at > now_ns) {
+                continue;
+            }
+            let mut orderbook = slot.write().await;
+            for side in [Side::Bid, Side::Ask] {
+                // The book numbers each expiry in turn
+                let from = orderbook.seq();
+                for (seq, id) in (from + 1..).zip(orderbook.expire_side(side, now_ns)) {
+                    self.cancelled(&slot, &symbol, &mut orderbook, id, side, seq);
+                    expired += 1;
+                }
+            }
+            self.repriced(&slot, &symbol, &mut orderbook, now_ns);
+        }
+        expired
     }
 
     /// Retrieves the current best bid and ask prices for a symbol.
@@ -236,14 +1003,57 @@ impl Exchange {
     /// * `symbol` - New symbol to add (e.g., "AMZN")
     pub async fn add_symbol(&self, symbol: String) {
         // Insert new order book for this symbol
-        self.orderbooks.insert(symbol, RwLock::new(OrderBook::new()));
+        let slot = self.book_slot(&symbol);
+        self.orderbooks.insert(symbol, slot);
+    }
+
+    /// An empty book bound to `symbol`, so it refuses orders for any
+    /// other. It publishes its level changes, reclaims canceled orders as
+    /// they pile up, and tracks what becomes of each order. The symbol is
+    /// interned, so requests naming it resolve to its id.
+    fn book_slot(&self, symbol: &str) -> BookSlot {
+        let symbol_id = SymbolId::intern(symbol);
+        let feed = LevelFeed { symbol: symbol.to_string(), updates: self.level_updates.clone() };
+        let book = OrderBook::new().with_symbol(symbol_id).with_listener(feed).with_compact_threshold(COMPACT_AT);
+        BookSlot::new(book.with_order_status(STATUS_RETAINED))
+    }
+
+    /// Displayed-quantity changes at each price, for every symbol, straight
+    /// from the books as they match.
+    pub fn subscribe_levels(&self) -> broadcast::Receiver<LevelUpdate> {
+        self.level_updates.subscribe()
     }
     
-    /// Returns the total number of active orders on each side for a symbol.
+    /// Delists a symbol, dropping its book. Returns how many live orders
+    /// were resting, or `None` if the symbol wasn't listed. Submits still
+    /// queued for the book get no trades and see the symbol as missing.
+    pub async fn remove_symbol(&self, symbol: &str) -> Option<usize> {
+        let resting = {
+            let slot = self.orderbooks.get(symbol)?;
+            let orderbook = slot.write().await;
+            orderbook.bids.total_len() + orderbook.asks.total_len()
+        };
+        self.orderbooks.remove(symbol)?;
+        self.accounts.forget_symbol(symbol);
+        self.surveillance.forget_symbol(symbol);
+        Some(resting)
+    }
+
+    /// Price of the symbol's latest trade.
+    /// # Returns
+    /// * `Some(None)` - Listed but not traded yet
+    /// * `None` - If symbol doesn't exist
+    pub fn last_trade_price(&self, symbol: &str) -> Option<Option<i64>> {
+        let px = self.orderbooks.get(symbol)?.last_px.load(Ordering::Relaxed);
+        Some((px != NO_TRADE).then_some(px))
+    }
+
+    /// Returns the total displayed quantity resting on each side for a symbol.
+    /// Hidden orders and iceberg reserves aren't counted.
     /// # Arguments
     /// * `symbol` - Trading symbol to query
     /// # Returns
-    /// * `Some((bid_count, ask_count))` - Number of active orders on each side
+    /// * `Some((bid_qty, ask_qty))` - Displayed quantity on each side
     /// * `None` - If symbol doesn't exist
     pub async fn get_total_volume(&self, symbol: &str) -> Option<(i64, i64)> {
         let orderbook_lock = self.orderbooks.get(symbol)?;
@@ -251,10 +1061,314 @@ impl Exchange {
         // Read lock
         let orderbook = orderbook_lock.read().await;
         
-        // Count active orders
-        let bid_volume = orderbook.bids.total_len() as i64;
-        let ask_volume = orderbook.asks.total_len() as i64;
+        let bid_volume = orderbook.bids.total_qty();
+        let ask_volume = orderbook.asks.total_qty();
         
         Some((bid_volume, ask_volume))
     }
-} 
\ No newline at end of file
+
+    /// Checksum of the top `levels` displayed levels of each side (see
+    /// [`OrderBook::book_checksum`]).
+    /// # Returns
+    /// * `None` - If symbol doesn't exist
+    pub async fn get_book_checksum(&self, symbol: &str, levels: usize) -> Option<u32> {
+        let orderbook_lock = self.orderbooks.get(symbol)?;
+        let orderbook = orderbook_lock.read().await;
+        Some(orderbook.book_checksum(levels))
+    }
+} 
+
+/// The report of an order dropped unmatched.
+fn rejected(order: &Order) -> ExecutionReport {
+    ExecutionReport {
+        status: OrderStatus::Rejected,
+        ..ExecutionReport::new(order.id, order.qty, order.tif, false, Vec::new())
+    }
+}
+
+/// How often good-til-date orders are checked for expiry
+const EXPIRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Expires good-til-date orders as their time passes.
+pub async fn run_order_expiry(exchange: Arc<Exchange>) {
+    let mut ticker = tokio::time::interval(EXPIRY_INTERVAL);
+    loop {
+        ticker.tick().await;
+        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let expired = exchange.expire_orders(now_ns).await;
+        if expired > 0 {
+            debug!("Expired {} good-til-date orders", expired);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surveillance::SurveillanceConfig;
+    use orderbook::margin::{MarginError, MarginRates};
+    use orderbook::pegs::PegKind;
+    use orderbook::ParticipantId;
+
+    fn order(id: u128, side: Side, px_ticks: i64, qty: i64) -> Order {
+        Order {
+            id: OrderId(id),
+            symbol: "AAPL".into(),
+            side,
+            px_ticks,
+            qty,
+            ts_ns: id,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }
+    }
+
+    /// A side the book refuses leaves the last quote resting, untouched.
+    #[tokio::test]
+    async fn a_refused_quote_leaves_the_last_one_standing() {
+        let exchange = Exchange::new();
+        exchange.set_symbol_config("AAPL", SymbolConfig { tick_size: 5, ..SymbolConfig::default() }).await.unwrap().unwrap();
+        exchange.submit_quote("AAPL", "mm", order(1, Side::Bid, 95, 10), order(2, Side::Ask, 105, 10)).await.unwrap().unwrap();
+
+        let refused = exchange.submit_quote("AAPL", "mm", order(3, Side::Bid, 90, 10), order(4, Side::Ask, 103, 10)).await.unwrap();
+        assert!(matches!(refused, Err(QuoteRefusal::Invalid(QuoteError::Rejected(RejectReason::OffTick { px_ticks: 103, .. })))));
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(95), Some(105))));
+
+        // Both sides under one id would leave the second refused
+        let refused = exchange.submit_quote("AAPL", "mm", order(5, Side::Bid, 90, 10), order(5, Side::Ask, 110, 10)).await.unwrap();
+        assert!(matches!(refused, Err(QuoteRefusal::Invalid(QuoteError::Rejected(RejectReason::DuplicateId(_))))));
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(95), Some(105))));
+
+        let report = exchange.submit_quote("AAPL", "mm", order(6, Side::Bid, 90, 10), order(7, Side::Ask, 110, 10)).await.unwrap().unwrap();
+        assert_eq!(report.replaced.len(), 2);
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(90), Some(110))));
+    }
+
+    /// A quote reusing the ids of the one it replaces is entered, and the
+    /// account works only the new pair.
+    #[tokio::test]
+    async fn a_quote_may_reuse_the_ids_it_replaces() {
+        let exchange = Exchange::new();
+        for (bid_qty, ask_qty) in [(10, 10), (4, 6)] {
+            let (mut bid, mut ask) = (order(1, Side::Bid, 95, bid_qty), order(2, Side::Ask, 105, ask_qty));
+            exchange.assign_order("mm", &mut bid).unwrap();
+            exchange.assign_order("mm", &mut ask).unwrap();
+            exchange.submit_quote("AAPL", "mm", bid, ask).await.unwrap().unwrap();
+        }
+
+        let view = exchange.account_view("mm").unwrap();
+        assert_eq!((view.positions[0].position.open_bids, view.positions[0].position.open_asks), (4, 6));
+        assert_eq!(view.open_orders, 2);
+        let slot = exchange.orderbooks.get("AAPL").unwrap();
+        assert_eq!((slot.read().await.bids.total_qty(), slot.read().await.asks.total_qty()), (4, 6));
+    }
+
+    /// Cancelling the best bid moves a midpoint peg to the new midpoint.
+    #[tokio::test]
+    async fn a_cancel_reprices_the_pegs() {
+        let exchange = Exchange::new();
+        for (id, side, px_ticks) in [(1, Side::Bid, 99), (2, Side::Bid, 97), (3, Side::Ask, 103)] {
+            exchange.submit_order("AAPL".to_string(), order(id, side, px_ticks, 10)).await.unwrap().unwrap();
+        }
+        let slot = exchange.orderbooks.get("AAPL").unwrap();
+        slot.write().await.submit_peg(order(4, Side::Bid, 105, 10), PegKind::Midpoint).unwrap();
+        assert_eq!(slot.read().await.bids.price_of(OrderId(4)), Some(101));
+
+        exchange.cancel_order("AAPL", OrderId(1), None).await.unwrap().unwrap();
+        assert_eq!(slot.read().await.bids.price_of(OrderId(4)), Some(100));
+    }
+
+    /// An amend naming an account reaches only that account's orders.
+    #[tokio::test]
+    async fn amends_are_scoped_to_their_account() {
+        let exchange = Exchange::new();
+        let mut bid = order(1, Side::Bid, 100, 10);
+        exchange.assign_order("alice", &mut bid).unwrap();
+        exchange.submit_order("AAPL".to_string(), bid).await.unwrap().unwrap();
+        // Assigning one gives bob a participant number of its own
+        let mut other = order(2, Side::Bid, 90, 10);
+        exchange.assign_order("bob", &mut other).unwrap();
+
+        let refused = exchange.modify_order("AAPL", OrderId(1), 101, 10, Some("bob")).await.unwrap();
+        assert!(matches!(refused, Err(AmendError::NotFound)));
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(100), None)));
+        exchange.modify_order("AAPL", OrderId(1), 101, 10, Some("alice")).await.unwrap().unwrap();
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(101), None)));
+    }
+
+    /// An amend the book would refuse leaves the order where it was.
+    #[tokio::test]
+    async fn amends_are_checked_by_the_book() {
+        let exchange = Exchange::new();
+        exchange.set_symbol_config("AAPL", SymbolConfig { tick_size: 5, ..SymbolConfig::default() }).await.unwrap().unwrap();
+        exchange.submit_order("AAPL".to_string(), order(1, Side::Bid, 100, 10)).await.unwrap().unwrap();
+
+        let refused = exchange.modify_order("AAPL", OrderId(1), -5, 10, None).await.unwrap();
+        assert!(matches!(refused, Err(AmendError::Refused(RejectReason::InvalidPrice(-5)))));
+        let refused = exchange.modify_order("AAPL", OrderId(1), 103, 10, None).await.unwrap();
+        assert!(matches!(refused, Err(AmendError::Refused(RejectReason::OffTick { px_ticks: 103, .. }))));
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(100), None)));
+    }
+
+    /// An amend the book refuses leaves the owner's working quantity as it
+    /// was, so the account isn't charged for size that never rested.
+    #[tokio::test]
+    async fn a_refused_amend_leaves_the_account_alone() {
+        let exchange = Exchange::new();
+        exchange.set_symbol_config("AAPL", SymbolConfig { tick_size: 5, ..SymbolConfig::default() }).await.unwrap().unwrap();
+        let mut bid = order(1, Side::Bid, 100, 10);
+        exchange.assign_order("alice", &mut bid).unwrap();
+        exchange.submit_order("AAPL".to_string(), bid).await.unwrap().unwrap();
+        let open_bids = || exchange.accounts().view("alice", |_| None).unwrap().positions[0].position.open_bids;
+
+        let refused = exchange.modify_order("AAPL", OrderId(1), 103, 50, Some("alice")).await.unwrap();
+        assert!(matches!(refused, Err(AmendError::Refused(RejectReason::OffTick { px_ticks: 103, .. }))));
+        assert_eq!(open_bids(), 10);
+        exchange.modify_order("AAPL", OrderId(1), 105, 50, Some("alice")).await.unwrap().unwrap();
+        assert_eq!(open_bids(), 50);
+    }
+
+    /// A kill pulls the account's orders on every symbol, leaves others'
+    /// standing, and refuses its new orders until it is unblocked.
+    #[tokio::test]
+    async fn a_kill_cancels_everywhere_and_blocks_until_unblocked() {
+        let exchange = Exchange::new();
+        for mut order in [order(1, Side::Bid, 100, 10), Order { symbol: "MSFT".into(), ..order(2, Side::Ask, 300, 5) }] {
+            exchange.assign_order("alice", &mut order).unwrap();
+            exchange.submit_order(order.symbol.to_string(), order).await.unwrap().unwrap();
+        }
+        let mut bob = order(3, Side::Bid, 99, 10);
+        exchange.assign_order("bob", &mut bob).unwrap();
+        exchange.submit_order("AAPL".to_string(), bob).await.unwrap().unwrap();
+
+        assert_eq!(exchange.kill_account("alice").await, 2);
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(99), None)));
+        assert_eq!(exchange.get_best_prices("MSFT").await, Some((None, None)));
+        assert!(exchange.account_view("alice").unwrap().blocked);
+        let mut next = order(4, Side::Bid, 100, 10);
+        assert_eq!(exchange.assign_order("alice", &mut next), Err(Rejection::Blocked("alice".to_string())));
+
+        assert!(exchange.accounts().unblock("alice"));
+        exchange.assign_order("alice", &mut next).unwrap();
+        exchange.submit_order("AAPL".to_string(), next).await.unwrap().unwrap();
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(100), None)));
+    }
+
+    /// An order that would take a margined account's initial margin past
+    /// its equity is refused before it reaches the book; accounts without
+    /// collateral and symbols without rates go unchecked.
+    #[tokio::test]
+    async fn orders_past_an_accounts_margin_are_refused() {
+        let rates = MarginRates { initial: 0.1, maintenance: 0.05 };
+        let exchange = Exchange::new().with_margin(MarginConfig {
+            symbols: [("AAPL".to_string(), rates)].into(),
+            accounts: [("alice".to_string(), 1000.0)].into(),
+            ..MarginConfig::default()
+        });
+        let mut bid = order(1, Side::Bid, 100, 50);
+        exchange.assign_order("alice", &mut bid).unwrap();
+        exchange.submit_order("AAPL".to_string(), bid).await.unwrap().unwrap();
+        assert_eq!(exchange.account_view("alice").unwrap().margin.unwrap().utilization, Some(0.5));
+
+        let mut more = order(2, Side::Bid, 100, 60);
+        let refused = exchange.assign_order("alice", &mut more);
+        assert_eq!(refused, Err(Rejection::Margin(MarginError::Insufficient { required: 1100.0, equity: 1000.0 })));
+        assert_eq!(exchange.account_view("alice").unwrap().positions[0].position.open_bids, 50);
+
+        let mut unmargined = Order { symbol: "MSFT".into(), ..order(3, Side::Bid, 100, 60) };
+        assert!(exchange.assign_order("alice", &mut unmargined).is_ok());
+        let mut uncollateralized = order(4, Side::Bid, 100, 60);
+        assert!(exchange.assign_order("bob", &mut uncollateralized).is_ok());
+    }
+
+    /// Trades between one account's orders, or between linked accounts,
+    /// are flagged; trades with anyone else aren't.
+    #[tokio::test]
+    async fn trades_within_an_account_or_its_links_are_flagged() {
+        let linked = vec![vec!["alice".to_string(), "alice-hedge".to_string()]];
+        let surveillance = Surveillance::new(SurveillanceConfig { linked_accounts: linked, ..SurveillanceConfig::default() }).unwrap();
+        let exchange = Exchange::new().with_surveillance(surveillance);
+        for (account, order) in [
+            ("alice", order(1, Side::Bid, 100, 10)),
+            ("alice", order(2, Side::Ask, 100, 2)),
+            ("alice-hedge", order(3, Side::Ask, 100, 3)),
+            ("bob", order(4, Side::Ask, 100, 5)),
+        ] {
+            let mut order = order;
+            exchange.assign_order(account, &mut order).unwrap();
+            exchange.submit_order("AAPL".to_string(), order).await.unwrap().unwrap();
+        }
+
+        let report = exchange.surveillance().wash_trade_report(None);
+        assert_eq!((report.wash_trades, report.qty), (2, 5));
+        let flagged: Vec<_> = report.recent.iter().map(|w| (w.taker_order_id, w.taker_account.as_str(), w.linked)).collect();
+        assert_eq!(flagged, [(3, "alice-hedge", true), (2, "alice", false)]);
+        assert_eq!(exchange.surveillance().wash_trade_report(Some("alice-hedge")).wash_trades, 1);
+        assert_eq!(exchange.surveillance().wash_trade_report(Some("bob")).wash_trades, 0);
+    }
+
+    /// An account's order is measured from the mid it arrived to: its fills
+    /// cost what they paid over it, and its cancelled remainder what the
+    /// market moved away.
+    #[tokio::test]
+    async fn an_orders_costs_are_measured_from_its_arrival() {
+        let exchange = Exchange::new();
+        exchange.submit_order("AAPL".to_string(), order(1, Side::Ask, 101, 5)).await.unwrap().unwrap();
+        exchange.submit_order("AAPL".to_string(), order(2, Side::Bid, 99, 5)).await.unwrap().unwrap();
+        let mut bid = order(3, Side::Bid, 101, 10);
+        exchange.assign_order("alice", &mut bid).unwrap();
+        exchange.submit_order("AAPL".to_string(), bid).await.unwrap().unwrap();
+        exchange.cancel_order("AAPL", OrderId(3), Some("alice")).await.unwrap().unwrap();
+
+        let report = exchange.tca().report(exchange.trade_store(), Some("alice"), 0, u64::MAX, |_| None);
+        let [order] = &report.orders[..] else { panic!("one order, got {:?}", report.orders) };
+        assert_eq!((order.filled, order.arrival_px, order.end_px, order.interval_vwap), (5, Some(100.0), Some(101.0), Some(101.0)));
+        let metrics = order.metrics.unwrap();
+        assert_eq!((metrics.execution_cost, metrics.opportunity_cost, metrics.implementation_shortfall), (5.0, 5.0, 10.0));
+        assert_eq!((metrics.shortfall_bps, metrics.slippage_bps, metrics.participation), (Some(100.0), Some(0.0), Some(1.0)));
+        assert_eq!(report.accounts[0].shortfall_bps, Some(100.0));
+        assert!(exchange.tca().report(exchange.trade_store(), Some("bob"), 0, u64::MAX, |_| None).orders.is_empty());
+    }
+
+    /// A cancel naming an account reaches only that account's orders.
+    #[tokio::test]
+    async fn cancels_are_scoped_to_their_account() {
+        let exchange = Exchange::new();
+        let mut bid = order(1, Side::Bid, 100, 10);
+        exchange.assign_order("alice", &mut bid).unwrap();
+        exchange.submit_order("AAPL".to_string(), bid).await.unwrap().unwrap();
+        let mut other = order(2, Side::Bid, 90, 10);
+        exchange.assign_order("bob", &mut other).unwrap();
+
+        assert_eq!(exchange.cancel_order("AAPL", OrderId(1), Some("bob")).await, Some(None));
+        assert_eq!(exchange.cancel_order("AAPL", OrderId(1), Some("carol")).await, Some(None));
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(100), None)));
+        let cancelled = exchange.cancel_order("AAPL", OrderId(1), Some("alice")).await.unwrap().unwrap();
+        assert_eq!(cancelled.qty, 10);
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((None, None)));
+    }
+
+    /// A mass cancel takes the orders priced within its range, inclusive,
+    /// on the side asked for, and leaves the rest.
+    #[tokio::test]
+    async fn mass_cancels_keep_to_their_side_and_range() {
+        let exchange = Exchange::new();
+        for (id, side, px_ticks) in [(1, Side::Bid, 97), (2, Side::Bid, 98), (3, Side::Bid, 99), (4, Side::Ask, 101), (5, Side::Ask, 102), (6, Side::Ask, 103)] {
+            exchange.submit_order("AAPL".to_string(), order(id, side, px_ticks, 10)).await.unwrap().unwrap();
+        }
+
+        let mut cancelled = exchange.cancel_orders("AAPL", Some(Side::Bid), 98, 101).await.unwrap();
+        cancelled.sort();
+        assert_eq!(cancelled, [OrderId(2), OrderId(3)]);
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((Some(97), Some(101))));
+
+        assert_eq!(exchange.cancel_orders("AAPL", None, 97, 102).await.unwrap(), [OrderId(1), OrderId(4), OrderId(5)]);
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((None, Some(103))));
+        assert_eq!(exchange.cancel_orders("AAPL", None, 0, 100).await.unwrap(), []);
+        assert_eq!(exchange.cancel_orders("NOPE", None, 0, 100).await, None);
+    }
+}
@@ -0,0 +1,389 @@
+//! Consolidated market data from external venues.
+//!
+//! Connects to other exchanges' WebSocket feeds, normalizes their depth and
+//! trade messages into ticks and lots, and keeps one mirrored `OrderBook`
+//! per venue and symbol. `GET /consolidated/:symbol` merges the mirrors
+//! into one book, with each venue's BBO and last trade alongside. Off
+//! unless `EXCHANGE_FEEDS` names a JSON config file:
+//!
+//! ```json
+//! [{
+//!   "venue": "example",
+//!   "url": "wss://feed.example.com/ws",
+//!   "subscribe": [{"op": "subscribe", "channels": ["book", "trades"]}],
+//!   "tick_size": 0.01,
+//!   "lot_size": 0.0001,
+//!   "symbols": {"BTCUSD": "BTC-USD"},
+//!   "schema": {"kind": "/type", "bids": "/data/b", "asks": "/data/a"}
+//! }]
+//! ```
+//!
+//! `subscribe` messages are sent after every connect. Prices and sizes may
+//! be JSON numbers or decimal strings; they are divided by `tick_size` and
+//! `lot_size` and rounded. `symbols` renames venue symbols, so the same
+//! instrument from several venues lands in one consolidated book; unlisted
+//! symbols keep their venue name.
+//!
+//! Every `schema` field is a JSON Pointer into the message, or the value a
+//! pointer must hold, and defaults to the generic schema:
+//!
+//! | field      | default      | meaning                                        |
+//! |------------|--------------|------------------------------------------------|
+//! | `kind`     | `/type`      | message type                                   |
+//! | `snapshot` | `snapshot`   | type replacing the venue's book for the symbol |
+//! | `update`   | `update`     | type setting levels, quantity 0 removing one   |
+//! | `trade`    | `trade`      | type of a trade print                          |
+//! | `symbol`   | `/symbol`    | venue symbol                                   |
+//! | `bids`     | `/bids`      | array of bid levels                            |
+//! | `asks`     | `/asks`      | array of ask levels                            |
+//! | `level_px` | `/0`         | price within a level (`[px, qty]` arrays)      |
+//! | `level_qty`| `/1`         | quantity within a level                        |
+//! | `price`    | `/price`     | trade price                                    |
+//! | `qty`      | `/qty`       | trade quantity                                 |
+//! | `side`     | `/side`      | trade aggressor side                           |
+//! | `buy`      | `buy`        | `side` value for a buying aggressor            |
+//!
+//! Other message types (heartbeats, subscription acks) are ignored. A
+//! dropped connection clears that venue's mirrors, since updates were
+//! missed, and reconnects with backoff up to 30 s.
+
+use dashmap::DashMap;
+use futures::{SinkExt, StreamExt};
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, PriceLevels, Side, TimeInForce};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{info, warn};
+
+use crate::types::{ConsolidatedBook, PriceLevel, VenueQuote};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// One external venue's connection and message schema.
+#[derive(Debug, Deserialize)]
+pub struct VenueConfig {
+    pub venue: String,
+    pub url: String,
+    /// Sent as text frames after each connect
+    #[serde(default)]
+    pub subscribe: Vec<Value>,
+    #[serde(default = "unit")]
+    pub tick_size: f64,
+    #[serde(default = "unit")]
+    pub lot_size: f64,
+    /// Venue symbol to consolidated symbol
+    #[serde(default)]
+    pub symbols: HashMap<String, String>,
+    #[serde(default)]
+    pub schema: Schema,
+}
+
+fn unit() -> f64 {
+    1.0
+}
+
+/// Where a venue's messages keep each field; see the module docs.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Schema {
+    pub kind: String,
+    pub snapshot: String,
+    pub update: String,
+    pub trade: String,
+    pub symbol: String,
+    pub bids: String,
+    pub asks: String,
+    pub level_px: String,
+    pub level_qty: String,
+    pub price: String,
+    pub qty: String,
+    pub side: String,
+    pub buy: String,
+}
+
+impl Default for Schema {
+    fn default() -> Self {
+        Self {
+            kind: "/type".to_string(),
+            snapshot: "snapshot".to_string(),
+            update: "update".to_string(),
+            trade: "trade".to_string(),
+            symbol: "/symbol".to_string(),
+            bids: "/bids".to_string(),
+            asks: "/asks".to_string(),
+            level_px: "/0".to_string(),
+            level_qty: "/1".to_string(),
+            price: "/price".to_string(),
+            qty: "/qty".to_string(),
+            side: "/side".to_string(),
+            buy: "buy".to_string(),
+        }
+    }
+}
+
+/// A venue message in internal units.
+#[derive(Debug)]
+enum FeedEvent {
+    Depth { symbol: String, snapshot: bool, bids: Vec<(i64, i64)>, asks: Vec<(i64, i64)> },
+    Trade { symbol: String, px_ticks: i64, qty: i64, taker_side: Side },
+}
+
+impl VenueConfig {
+    /// Reads the venue list from a JSON file.
+    pub fn load(path: &str) -> Result<Vec<Self>, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Normalizes one message; `None` for types the schema doesn't name or
+    /// messages missing a field.
+    fn parse(&self, message: &Value) -> Option<FeedEvent> {
+        let schema = &self.schema;
+        let kind = text(message.pointer(&schema.kind)?)?;
+        let symbol = text(message.pointer(&schema.symbol)?)?;
+        let symbol = self.symbols.get(&symbol).cloned().unwrap_or(symbol);
+        if kind == schema.trade {
+            let taker_side = match message.pointer(&schema.side).and_then(text) {
+                Some(side) if side == schema.buy => Side::Bid,
+                _ => Side::Ask,
+            };
+            return Some(FeedEvent::Trade {
+                symbol,
+                px_ticks: self.ticks(message.pointer(&schema.price)?)?,
+                qty: self.lots(message.pointer(&schema.qty)?)?,
+                taker_side,
+            });
+        }
+        if kind != schema.snapshot && kind != schema.update {
+            return None;
+        }
+        Some(FeedEvent::Depth {
+            symbol,
+            snapshot: kind == schema.snapshot,
+            bids: self.levels(message.pointer(&schema.bids))?,
+            asks: self.levels(message.pointer(&schema.asks))?,
+        })
+    }
+
+    /// A missing side is an empty side; a malformed level drops the message.
+    fn levels(&self, levels: Option<&Value>) -> Option<Vec<(i64, i64)>> {
+        let Some(levels) = levels else { return Some(Vec::new()) };
+        levels
+            .as_array()?
+            .iter()
+            .map(|level| {
+                let px = self.ticks(level.pointer(&self.schema.level_px)?)?;
+                let qty = self.lots(level.pointer(&self.schema.level_qty)?)?;
+                Some((px, qty))
+            })
+            .collect()
+    }
+
+    fn ticks(&self, value: &Value) -> Option<i64> {
+        Some((number(value)? / self.tick_size).round() as i64)
+    }
+
+    fn lots(&self, value: &Value) -> Option<i64> {
+        Some((number(value)? / self.lot_size).round() as i64)
+    }
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// One venue's view of one symbol.
+struct Mirror {
+    /// One resting order per price level, with the level's quantity
+    book: OrderBook,
+    /// Price, quantity, and aggressor side
+    last_trade: Option<(i64, i64, Side)>,
+    trades: u64,
+    updated_ns: u64,
+}
+
+impl Mirror {
+    fn new() -> Self {
+        Self { book: OrderBook::new(), last_trade: None, trades: 0, updated_ns: 0 }
+    }
+}
+
+/// Sets the level at `px_ticks` to `qty`, removing it at 0. Each level is a
+/// single order whose id is its price, so replacing it keeps one per level.
+fn set_level(levels: &mut PriceLevels, symbol: &str, side: Side, px_ticks: i64, qty: i64, ts_ns: u128) {
+    let id = OrderId(px_ticks as u64 as u128);
+    levels.remove(id);
+    if qty > 0 {
+        levels.push(Order { id, symbol: symbol.into(), side, px_ticks, qty, ts_ns, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE });
+    }
+}
+
+/// Mirrored books for every configured venue, keyed by consolidated symbol.
+#[derive(Default)]
+pub struct FeedAggregator {
+    mirrors: DashMap<String, HashMap<String, Mirror>>,
+}
+
+impl FeedAggregator {
+    /// Symbols at least one venue has sent.
+    pub fn list_symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.mirrors.iter().map(|e| e.key().clone()).collect();
+        symbols.sort();
+        symbols
+    }
+
+    /// Merges every venue's mirror of `symbol`, summing quantity per price.
+    /// A level's `orders` counts the venues quoting that price.
+    pub fn consolidated(&self, symbol: &str, levels: usize) -> Option<ConsolidatedBook> {
+        let venues = self.mirrors.get(symbol)?;
+        let mut bids: BTreeMap<i64, (i64, usize)> = BTreeMap::new();
+        let mut asks: BTreeMap<i64, (i64, usize)> = BTreeMap::new();
+        let mut quotes = Vec::with_capacity(venues.len());
+        for (venue, mirror) in venues.iter() {
+            for (merged, side) in [(&mut bids, &mirror.book.bids), (&mut asks, &mirror.book.asks)] {
+                for (px, qty) in side.iter_levels_best_first().take(levels) {
+                    let level = merged.entry(px).or_default();
+                    level.0 += qty;
+                    level.1 += 1;
+                }
+            }
+            quotes.push(VenueQuote {
+                venue: venue.clone(),
+                best_bid: mirror.book.best_bid(),
+                best_ask: mirror.book.best_ask(),
+                last_trade_px: mirror.last_trade.map(|t| t.0),
+                last_trade_qty: mirror.last_trade.map(|t| t.1),
+                last_trade_side: mirror.last_trade.map(|t| t.2),
+                trades: mirror.trades,
+                last_update: mirror.updated_ns,
+            });
+        }
+        quotes.sort_by(|a, b| a.venue.cmp(&b.venue));
+        let level = |(price, (quantity, orders)): (&i64, &(i64, usize))| PriceLevel {
+            price: *price,
+            quantity: *quantity,
+            orders: *orders,
+        };
+        Some(ConsolidatedBook {
+            symbol: symbol.to_string(),
+            bids: bids.iter().rev().take(levels).map(level).collect(),
+            asks: asks.iter().take(levels).map(level).collect(),
+            venues: quotes,
+            timestamp: now_ns(),
+        })
+    }
+
+    /// One venue's mirrored `side` of `symbol`, best first, if it has one.
+    pub fn venue_levels(&self, symbol: &str, venue: &str, side: Side, levels: usize) -> Option<Vec<(i64, i64)>> {
+        let venues = self.mirrors.get(symbol)?;
+        let book = &venues.get(venue)?.book;
+        Some(book.levels(side).iter_levels_best_first().take(levels).collect())
+    }
+
+    fn apply(&self, venue: &str, event: FeedEvent) {
+        let ts_ns = now_ns();
+        match event {
+            FeedEvent::Depth { symbol, snapshot, bids, asks } => {
+                let mut venues = self.mirrors.entry(symbol.clone()).or_default();
+                let mirror = venues.entry(venue.to_string()).or_insert_with(Mirror::new);
+                if snapshot {
+                    mirror.book = OrderBook::new();
+                }
+                for (px, qty) in bids {
+                    set_level(&mut mirror.book.bids, &symbol, Side::Bid, px, qty, ts_ns as u128);
+                }
+                for (px, qty) in asks {
+                    set_level(&mut mirror.book.asks, &symbol, Side::Ask, px, qty, ts_ns as u128);
+                }
+                mirror.updated_ns = ts_ns;
+            }
+            FeedEvent::Trade { symbol, px_ticks, qty, taker_side } => {
+                let mut venues = self.mirrors.entry(symbol).or_default();
+                let mirror = venues.entry(venue.to_string()).or_insert_with(Mirror::new);
+                mirror.last_trade = Some((px_ticks, qty, taker_side));
+                mirror.trades += 1;
+                mirror.updated_ns = ts_ns;
+            }
+        }
+    }
+
+    /// Drops a venue's mirrors after its connection is lost.
+    fn clear_venue(&self, venue: &str) {
+        for mut venues in self.mirrors.iter_mut() {
+            venues.remove(venue);
+        }
+        self.mirrors.retain(|_, venues| !venues.is_empty());
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+}
+
+/// Follows one venue forever, reconnecting with backoff.
+pub async fn run(config: VenueConfig, aggregator: std::sync::Arc<FeedAggregator>) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match follow(&config, &aggregator).await {
+            Ok(()) => {
+                warn!("{} feed closed", config.venue);
+                backoff = Duration::from_secs(1);
+            }
+            Err(e) => warn!("{} feed failed: {}", config.venue, e),
+        }
+        aggregator.clear_venue(&config.venue);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// One connection: subscribe, then apply messages until it ends.
+async fn follow(config: &VenueConfig, aggregator: &FeedAggregator) -> Result<(), String> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(config.url.as_str())
+        .await
+        .map_err(|e| format!("connect {}: {}", config.url, e))?;
+    for request in &config.subscribe {
+        socket.send(Message::Text(request.to_string())).await.map_err(|e| e.to_string())?;
+    }
+    info!("Mirroring {} from {}", config.venue, config.url);
+
+    while let Some(message) = socket.next().await {
+        let payload = match message.map_err(|e| e.to_string())? {
+            Message::Text(text) => text.into_bytes(),
+            Message::Binary(bytes) => bytes,
+            Message::Ping(data) => {
+                socket.send(Message::Pong(data)).await.map_err(|e| e.to_string())?;
+                continue;
+            }
+            Message::Close(_) => break,
+            _ => continue,
+        };
+        let Ok(value) = serde_json::from_slice::<Value>(&payload) else { continue };
+        // Some venues batch several messages into one array frame
+        let messages = match value {
+            Value::Array(items) => items,
+            other => vec![other],
+        };
+        for message in &messages {
+            if let Some(event) = config.parse(message) {
+                aggregator.apply(&config.venue, event);
+            }
+        }
+    }
+    Ok(())
+}
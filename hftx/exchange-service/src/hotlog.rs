@@ -0,0 +1,125 @@
+//! Binary log of the matching path, for latency runs.
+//!
+//! With `EXCHANGE_HOTLOG` naming a file, the exchange records each order
+//! as it enters matching, every fill the matcher decides on, what rests,
+//! the moment its trades leave, and each cancel, as 64-byte
+//! `orderbook::hotlog` records with nanosecond timestamps. Pushing one is a
+//! few stores into a lock-free ring buffer; a writer thread drains the ring
+//! to the file. Nothing is formatted until the log is decoded offline with
+//! `hftx-cli hotlog <file>`.
+//!
+//! When the writer falls behind and the ring fills, new records are dropped
+//! rather than slowing matching, and the file gets a `Dropped` record with
+//! the count where they went missing.
+//!
+//! Timestamps come from a monotonic clock anchored to wall time at startup,
+//! so they order correctly across threads and still line up with other logs.
+
+use crossbeam_queue::ArrayQueue;
+use orderbook::hotlog::{header, Entry, Record};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{error, warn};
+
+/// Records drained and written at a time
+const BATCH: usize = 4096;
+/// How long the writer sleeps when the ring is empty
+const IDLE: Duration = Duration::from_millis(1);
+
+pub struct HotLog {
+    ring: ArrayQueue<Record>,
+    /// Records lost to a full ring since the writer last looked
+    dropped: AtomicU64,
+    /// Symbol names by id
+    symbols: Mutex<Vec<String>>,
+    started: Instant,
+    started_ns: u64,
+}
+
+impl HotLog {
+    /// A log buffering up to `capacity` records.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            ring: ArrayQueue::new(capacity.max(1)),
+            dropped: AtomicU64::new(0),
+            symbols: Mutex::new(Vec::new()),
+            started: Instant::now(),
+            started_ns: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64,
+        }
+    }
+
+    pub fn now_ns(&self) -> u64 {
+        self.started_ns + self.started.elapsed().as_nanos() as u64
+    }
+
+    /// Numbers a symbol for its records. Call once per symbol; the
+    /// exchange keeps the id with the book.
+    pub fn register(&self, symbol: &str) -> u16 {
+        let mut symbols = self.symbols.lock().unwrap();
+        symbols.push(symbol.to_string());
+        (symbols.len() - 1) as u16
+    }
+
+    /// Records `entry` at `ts_ns`, or drops it if the ring is full.
+    pub fn push_at(&self, ts_ns: u64, symbol: u16, entry: Entry) {
+        if self.ring.push(Record { ts_ns, symbol, entry }).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records `entry` now.
+    pub fn push(&self, symbol: u16, entry: Entry) {
+        self.push_at(self.now_ns(), symbol, entry);
+    }
+
+    /// Creates `path` and starts the thread that writes the ring to it.
+    pub fn spawn_writer(self: &Arc<Self>, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(&header())?;
+        let log = self.clone();
+        let path = path.to_string();
+        std::thread::Builder::new().name("hotlog".to_string()).spawn(move || {
+            if let Err(e) = log.write(&mut out) {
+                error!("Hot-path log {} failed, stopped writing: {}", path, e);
+            }
+        })?;
+        Ok(())
+    }
+
+    fn write(&self, out: &mut BufWriter<File>) -> io::Result<()> {
+        let mut batch = Vec::with_capacity(BATCH);
+        let mut named = 0;
+        loop {
+            while batch.len() < BATCH {
+                match self.ring.pop() {
+                    Some(record) => batch.push(record),
+                    None => break,
+                }
+            }
+            let dropped = self.dropped.swap(0, Ordering::Relaxed);
+            if batch.is_empty() && dropped == 0 {
+                std::thread::sleep(IDLE);
+                continue;
+            }
+            // Every id in the batch was registered before its record was
+            // pushed, so naming all registered symbols covers them
+            let symbols = self.symbols.lock().unwrap()[named..].to_vec();
+            for (i, name) in symbols.iter().enumerate() {
+                let record = Record { ts_ns: self.now_ns(), symbol: (named + i) as u16, entry: Entry::symbol(name) };
+                out.write_all(&record.encode())?;
+            }
+            named += symbols.len();
+            for record in batch.drain(..) {
+                out.write_all(&record.encode())?;
+            }
+            if dropped > 0 {
+                warn!("Hot-path log full; dropped {} records", dropped);
+                out.write_all(&Record { ts_ns: self.now_ns(), symbol: 0, entry: Entry::Dropped { records: dropped } }.encode())?;
+            }
+            out.flush()?;
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Composite index prices computed from the exchange's own books.
+//!
+//! Indices are defined in the JSON list named by `EXCHANGE_INDICES`, each an
+//! `orderbook::index::IndexDefinition`. Ten times a second every index is
+//! recomputed from its constituents' last trades and BBOs; a value that
+//! changed (including a constituent going stale) is stored for
+//! `GET /indices/:name` and pushed to `/indices/:name/stream` subscribers.
+
+use orderbook::index::{IndexCalculator, IndexDefinition, IndexError, IndexValue, Quote};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::time::interval;
+
+use crate::exchange::Exchange;
+
+const UPDATE_BUFFER: usize = 1024;
+
+pub struct IndexEngine {
+    calculators: Mutex<Vec<IndexCalculator>>,
+    latest: RwLock<HashMap<String, IndexValue>>,
+    updates: broadcast::Sender<IndexValue>,
+}
+
+impl IndexEngine {
+    pub fn new(definitions: Vec<IndexDefinition>) -> Result<Self, IndexError> {
+        let calculators = definitions.into_iter().map(IndexCalculator::new).collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            calculators: Mutex::new(calculators),
+            latest: RwLock::new(HashMap::new()),
+            updates: broadcast::channel(UPDATE_BUFFER).0,
+        })
+    }
+
+    /// Reads index definitions from a JSON file.
+    pub fn load(path: &str) -> Result<Vec<IndexDefinition>, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calculators.lock().unwrap().is_empty()
+    }
+
+    /// Whether `name` is a defined index, computed yet or not.
+    pub fn contains(&self, name: &str) -> bool {
+        self.calculators.lock().unwrap().iter().any(|c| c.definition().name == name)
+    }
+
+    /// Latest value of every computed index, by name.
+    pub fn list(&self) -> Vec<IndexValue> {
+        let mut values: Vec<IndexValue> = self.latest.read().unwrap().values().cloned().collect();
+        values.sort_by(|a, b| a.name.cmp(&b.name));
+        values
+    }
+
+    pub fn latest(&self, name: &str) -> Option<IndexValue> {
+        self.latest.read().unwrap().get(name).cloned()
+    }
+
+    /// Every changed value, for all indices.
+    pub fn subscribe(&self) -> broadcast::Receiver<IndexValue> {
+        self.updates.subscribe()
+    }
+
+    /// Recomputes every index and publishes the ones that changed.
+    async fn update(&self, exchange: &Exchange) {
+        let symbols: Vec<String> = {
+            let calculators = self.calculators.lock().unwrap();
+            let mut symbols: Vec<String> = calculators
+                .iter()
+                .flat_map(|c| c.definition().constituents.iter().map(|k| k.symbol.clone()))
+                .collect();
+            symbols.sort();
+            symbols.dedup();
+            symbols
+        };
+        let mut quotes = HashMap::with_capacity(symbols.len());
+        for symbol in symbols {
+            if let Some(quote) = quote(exchange, &symbol).await {
+                quotes.insert(symbol, quote);
+            }
+        }
+
+        let ts_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        let values: Vec<IndexValue> = self
+            .calculators
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .map(|c| c.compute(|symbol| quotes.get(symbol).copied(), ts_ns))
+            .collect();
+        let mut latest = self.latest.write().unwrap();
+        for value in values {
+            let changed = latest
+                .get(&value.name)
+                .is_none_or(|prev| prev.value != value.value || prev.constituents != value.constituents);
+            if changed {
+                let _ = self.updates.send(value.clone());
+                latest.insert(value.name.clone(), value);
+            }
+        }
+    }
+}
+
+async fn quote(exchange: &Exchange, symbol: &str) -> Option<Quote> {
+    let last = exchange.last_trade_price(symbol)?;
+    let (bid, ask) = exchange.get_best_prices(symbol).await?;
+    Some(Quote { last, bid, ask })
+}
+
+/// Recomputes indices at 10 Hz, forever.
+pub async fn run(engine: Arc<IndexEngine>, exchange: Arc<Exchange>) {
+    let mut ticker = interval(Duration::from_millis(100));
+    loop {
+        ticker.tick().await;
+        engine.update(&exchange).await;
+    }
+}
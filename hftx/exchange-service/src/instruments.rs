@@ -0,0 +1,188 @@
+//! Futures and options listed on the exchange, and their expiry.
+//!
+//! Symbols can carry an `orderbook::instruments::Instrument`: registered
+//! through `POST /instruments` or loaded at startup from the JSON list in
+//! `EXCHANGE_INSTRUMENTS`. Registering lists the symbol if it isn't listed
+//! yet.
+//!
+//! A background task checks expiries once a second. An expired instrument
+//! is delisted (its book and any resting orders are dropped) and settled:
+//! the settlement price is the underlying's last trade, or its mid if it
+//! hasn't traded, and is `None` if the underlying isn't listed or has no
+//! prices. Each [`Settlement`] is kept for `GET /settlements` and passed to
+//! every [`SettlementHook`]. Settlements are always logged, and also POSTed
+//! as JSON to `EXCHANGE_SETTLEMENT_WEBHOOK` when that is set (plain http).
+
+use orderbook::instruments::{Instrument, InstrumentRegistry, RegistryError};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use tokio::time::interval;
+use tracing::{info, warn};
+
+use crate::exchange::Exchange;
+
+/// One expired instrument's final state.
+#[derive(Debug, Clone, Serialize)]
+pub struct Settlement {
+    pub instrument: Instrument,
+    /// Underlying price the instrument settled against
+    pub settlement_px: Option<i64>,
+    /// Per-unit payout in ticks (see `Instrument::settlement_value`)
+    pub value_ticks: Option<i64>,
+    /// Live orders dropped with the book
+    pub cancelled_orders: usize,
+    pub ts_ns: u64,
+}
+
+/// Called once per settled instrument, after it has been delisted. Hooks
+/// run on the expiry task, so slow work belongs on a task of its own.
+pub trait SettlementHook: Send + Sync {
+    fn on_settlement(&self, settlement: &Settlement);
+}
+
+struct LogHook;
+
+impl SettlementHook for LogHook {
+    fn on_settlement(&self, settlement: &Settlement) {
+        info!(
+            "Settled {} at {:?} (value {:?} ticks x {}), {} resting orders cancelled",
+            settlement.instrument.symbol,
+            settlement.settlement_px,
+            settlement.value_ticks,
+            settlement.instrument.multiplier(),
+            settlement.cancelled_orders
+        );
+    }
+}
+
+/// POSTs each settlement as JSON; failures are logged, never retried.
+pub struct WebhookHook {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookHook {
+    pub fn new(url: String) -> Self {
+        Self { client: reqwest::Client::new(), url }
+    }
+}
+
+impl SettlementHook for WebhookHook {
+    fn on_settlement(&self, settlement: &Settlement) {
+        let request = self
+            .client
+            .post(&self.url)
+            .header("content-type", "application/json")
+            .body(serde_json::to_vec(settlement).expect("settlements serialize"));
+        let symbol = settlement.instrument.symbol.clone();
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => {
+                    warn!("Settlement webhook for {} returned {}", symbol, response.status())
+                }
+                Err(e) => warn!("Settlement webhook for {} failed: {}", symbol, e),
+                Ok(_) => {}
+            }
+        });
+    }
+}
+
+/// The registry plus what has settled so far.
+pub struct Instruments {
+    registry: RwLock<InstrumentRegistry>,
+    settlements: RwLock<Vec<Settlement>>,
+    hooks: Vec<Box<dyn SettlementHook>>,
+}
+
+impl Instruments {
+    pub fn new() -> Self {
+        Self {
+            registry: RwLock::new(InstrumentRegistry::new()),
+            settlements: RwLock::new(Vec::new()),
+            hooks: vec![Box::new(LogHook)],
+        }
+    }
+
+    /// Adds a hook run after every settlement.
+    pub fn with_hook(mut self, hook: impl SettlementHook + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Reads an instrument list from a JSON file.
+    pub fn load(path: &str) -> Result<Vec<Instrument>, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Registers `instrument` and lists its symbol if needed.
+    pub async fn register(&self, exchange: &Exchange, instrument: Instrument) -> Result<(), RegistryError> {
+        let symbol = instrument.symbol.clone();
+        self.registry.write().await.register(instrument)?;
+        if exchange.get_best_prices(&symbol).await.is_none() {
+            exchange.add_symbol(symbol).await;
+        }
+        Ok(())
+    }
+
+    pub async fn list(&self) -> Vec<Instrument> {
+        self.registry.read().await.iter().cloned().collect()
+    }
+
+    pub async fn get(&self, symbol: &str) -> Option<Instrument> {
+        self.registry.read().await.get(symbol).cloned()
+    }
+
+    pub async fn settlements(&self) -> Vec<Settlement> {
+        self.settlements.read().await.clone()
+    }
+
+    /// Delists and settles everything expired at `now_ns`.
+    async fn settle_expired(&self, exchange: &Exchange, now_ns: u64) {
+        let expired = self.registry.write().await.take_expired(now_ns);
+        for instrument in expired {
+            let cancelled_orders = exchange.remove_symbol(&instrument.symbol).await.unwrap_or(0);
+            let settlement_px = match instrument.underlying() {
+                Some(underlying) => reference_price(exchange, underlying).await,
+                None => None,
+            };
+            let settlement = Settlement {
+                value_ticks: settlement_px.and_then(|px| instrument.settlement_value(px)),
+                instrument,
+                settlement_px,
+                cancelled_orders,
+                ts_ns: now_ns,
+            };
+            if settlement.settlement_px.is_none() {
+                warn!("{} expired with no underlying price to settle against", settlement.instrument.symbol);
+            }
+            for hook in &self.hooks {
+                hook.on_settlement(&settlement);
+            }
+            self.settlements.write().await.push(settlement);
+        }
+    }
+}
+
+/// Last trade, else the mid of a two-sided book.
+async fn reference_price(exchange: &Exchange, symbol: &str) -> Option<i64> {
+    if let Some(px) = exchange.last_trade_price(symbol)? {
+        return Some(px);
+    }
+    match exchange.get_best_prices(symbol).await? {
+        (Some(bid), Some(ask)) => Some(bid.midpoint(ask)),
+        _ => None,
+    }
+}
+
+/// Settles expiries once a second, forever.
+pub async fn run_expiry(instruments: Arc<Instruments>, exchange: Arc<Exchange>) {
+    let mut ticker = interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        instruments.settle_expired(&exchange, now_ns).await;
+    }
+}
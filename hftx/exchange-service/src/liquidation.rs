@@ -0,0 +1,219 @@
+//! Forced reduction of accounts that breach maintenance margin.
+//!
+//! Enabled by a `liquidation` section in the `EXCHANGE_MARGIN` file. On
+//! every check, any margined account whose maintenance margin exceeds its
+//! equity has its working orders cancelled and each margined position cut
+//! by a reduce-only order on the other side: at most the position's size
+//! (and `max_order_qty`), priced `slippage` past the position's mark so it
+//! crosses the book. Whatever doesn't fill at once is cancelled rather than
+//! left resting, so a liquidation order can never add to a position. The
+//! next check tries again with fresh prices until the account is back above
+//! maintenance or flat.
+//!
+//! Each order is reported as a `liquidation` event on the account stream,
+//! and its trades go out on the trade stream like any other.
+
+use orderbook::{Order, OrderId, ParticipantId, Side, TimeInForce};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::time::interval;
+use tracing::warn;
+
+use crate::accounts::AccountEvent;
+use crate::chaos;
+use crate::exchange::Exchange;
+use crate::types::TradeEvent;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LiquidationConfig {
+    /// How far past the mark liquidation orders are priced, as a fraction
+    #[serde(default = "default_slippage")]
+    pub slippage: f64,
+    #[serde(default = "default_interval")]
+    pub interval_ms: u64,
+    /// Largest single liquidation order; unset closes in one order
+    #[serde(default)]
+    pub max_order_qty: Option<i64>,
+}
+
+fn default_slippage() -> f64 {
+    0.01
+}
+
+fn default_interval() -> u64 {
+    1000
+}
+
+/// Limit price `slippage` through `mark`: below it to sell, above to buy,
+/// rounded outward so it is never less aggressive than asked.
+fn limit_price(side: Side, mark: f64, slippage: f64) -> i64 {
+    match side {
+        Side::Ask => (mark * (1.0 - slippage)).floor() as i64,
+        Side::Bid => (mark * (1.0 + slippage)).ceil() as i64,
+    }
+}
+
+/// Cancels the account's orders and submits one reduce-only order per
+/// margined position.
+async fn liquidate(
+    config: &LiquidationConfig,
+    exchange: &Exchange,
+    trades: &broadcast::Sender<TradeEvent>,
+    account: &str,
+    equity: f64,
+    maintenance_margin: f64,
+) {
+    for (symbol, order_id) in exchange.accounts().open_orders(account) {
+        exchange.cancel_order(&symbol, order_id, None).await;
+    }
+    let Some(view) = exchange.account_view(account) else { return };
+    for position in view.positions {
+        let qty = position.position.qty;
+        if qty == 0 || !exchange.accounts().margined(&position.symbol) {
+            continue;
+        }
+        let Some(mark) = position.mark else { continue };
+        let side = if qty > 0 { Side::Ask } else { Side::Bid };
+        let qty = config.max_order_qty.map_or(qty.abs(), |max| qty.abs().min(max));
+        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let mut order = Order {
+            id: OrderId(uuid::Uuid::new_v4().as_u128()),
+            symbol: position.symbol.as_str().into(),
+            side,
+            px_ticks: limit_price(side, mark, config.slippage),
+            qty,
+            ts_ns: now_ns,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        if let Err(e) = exchange.assign_forced_order(account, &mut order) {
+            warn!("Liquidation order for {} on {} refused: {}", account, position.symbol, e);
+            continue;
+        }
+        let (order_id, px_ticks) = (order.id, order.px_ticks);
+        let report = match exchange.submit_order(position.symbol.clone(), order).await {
+            Some(Ok(report)) => report,
+            Some(Err(reason)) => {
+                warn!("Liquidation order for {} on {} refused: {}", account, position.symbol, reason);
+                continue;
+            }
+            None => continue,
+        };
+        // Reduce-only: nothing is left resting
+        exchange.cancel_order(&position.symbol, order_id, None).await;
+
+        let filled = report.filled_qty;
+        warn!(
+            "Liquidating {}: {:?} {} {} at {}, {} filled (equity {:.2}, maintenance {:.2})",
+            account, side, qty, position.symbol, px_ticks, filled, equity, maintenance_margin
+        );
+        exchange.accounts().publish(AccountEvent::Liquidation {
+            account: account.to_string(),
+            symbol: position.symbol.clone(),
+            order_id: order_id.0,
+            side,
+            px_ticks,
+            qty,
+            filled,
+            equity,
+            maintenance_margin,
+            ts_ns: now_ns as u64,
+        });
+        for trade in report.trades {
+            if chaos::drop_message(&position.symbol) {
+                continue;
+            }
+            let _ = trades.send(TradeEvent {
+                symbol: position.symbol.clone(),
+                trade,
+                timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+            });
+        }
+    }
+}
+
+/// Checks margin every `interval_ms` and liquidates breaches, forever.
+pub async fn run(config: LiquidationConfig, exchange: Arc<Exchange>, trades: broadcast::Sender<TradeEvent>) {
+    let mut ticker = interval(Duration::from_millis(config.interval_ms.max(1)));
+    loop {
+        ticker.tick().await;
+        for (account, margin) in exchange.margin_calls() {
+            liquidate(&config, &exchange, &trades, &account, margin.equity, margin.maintenance_margin).await;
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounts::MarginConfig;
+    use orderbook::margin::MarginRates;
+
+    fn order(id: u128, side: Side, px_ticks: i64, qty: i64) -> Order {
+        Order {
+            id: OrderId(id),
+            symbol: "AAPL".into(),
+            side,
+            px_ticks,
+            qty,
+            ts_ns: id,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }
+    }
+
+    /// An account below maintenance has its orders pulled and its position
+    /// sold into the book through the mark, and the sale is reported.
+    #[tokio::test]
+    async fn a_breach_is_cancelled_and_sold_down() {
+        let config = LiquidationConfig { slippage: 0.01, interval_ms: 1000, max_order_qty: None };
+        let exchange = Exchange::new().with_margin(MarginConfig {
+            symbols: [("AAPL".to_string(), MarginRates { initial: 0.1, maintenance: 0.05 })].into(),
+            accounts: [("alice".to_string(), 100.0)].into(),
+            liquidation: Some(config.clone()),
+            ..MarginConfig::default()
+        });
+        let (trades, _) = broadcast::channel(16);
+        let mut events = exchange.accounts().subscribe();
+        let submit = |order: Order, account: Option<&str>| {
+            let mut order = order;
+            if let Some(account) = account {
+                exchange.assign_order(account, &mut order).unwrap();
+            }
+            exchange.submit_order("AAPL".to_string(), order)
+        };
+        // Alice buys 10 at 100 and offers them at 200
+        submit(order(1, Side::Ask, 100, 10), None).await.unwrap().unwrap();
+        submit(order(2, Side::Bid, 100, 10), Some("alice")).await.unwrap().unwrap();
+        submit(order(3, Side::Ask, 200, 10), Some("alice")).await.unwrap().unwrap();
+        assert!(exchange.margin_calls().is_empty());
+
+        // A print at 92 leaves her 20 of equity against 46 of maintenance
+        submit(order(4, Side::Bid, 92, 1), None).await.unwrap().unwrap();
+        submit(order(5, Side::Ask, 92, 1), None).await.unwrap().unwrap();
+        submit(order(6, Side::Bid, 91, 10), None).await.unwrap().unwrap();
+        let calls = exchange.margin_calls();
+        assert_eq!(calls.len(), 1);
+        let (account, margin) = &calls[0];
+        assert_eq!((account.as_str(), margin.equity, margin.maintenance_margin), ("alice", 20.0, 46.0));
+
+        liquidate(&config, &exchange, &trades, account, margin.equity, margin.maintenance_margin).await;
+        let view = exchange.account_view("alice").unwrap();
+        assert_eq!((view.positions[0].position.qty, view.open_orders), (0, 0));
+        assert_eq!(exchange.get_best_prices("AAPL").await, Some((None, None)));
+        assert!(exchange.margin_calls().is_empty());
+        let liquidation = std::iter::from_fn(|| events.try_recv().ok()).find(|e| matches!(e, AccountEvent::Liquidation { .. }));
+        assert!(matches!(liquidation, Some(AccountEvent::Liquidation { side: Side::Ask, px_ticks: 91, qty: 10, filled: 10, .. })));
+    }
+}
@@ -5,83 +5,442 @@
 
 use axum::{
     extract::{Path, Query, State, WebSocketUpgrade},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
-    routing::{delete, get, post},
+    routing::{get, post},
     Json, Router,
 };
-use orderbook::{OrderBook, Order, OrderId, Side, Trade};
-use serde::{Deserialize, Serialize};
+use orderbook::instruments::Instrument;
+use orderbook::profile::VolumeProfile;
+use orderbook::{Order, OrderId, OrderKind, OrderStatus, ParticipantId, RejectReason, SymbolConfig, SymbolId, SymbolRegistry, TimeInForce};
 use std::{
     collections::HashMap,
     sync::Arc,
-    time::{Instant, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, mpsc};
 use tower_http::cors::CorsLayer;
-use tracing::{info, warn};
+use tracing::{error, info};
 
+mod accounts;
+mod audit;
+mod book_history;
 mod bot_driver;
+mod chaos;
 mod exchange;
+mod feeds;
+mod hotlog;
+mod indices;
+mod instruments;
+mod liquidation;
+mod nats;
+mod perpetuals;
+mod router;
+mod shadow;
+mod surveillance;
+mod tca;
+mod trade_store;
+mod tsdb;
 mod websocket;
 mod types;
+mod ws_connections;
+mod zmq;
 
+use book_history::BookHistory;
 use bot_driver::BotDriver;
-use exchange::Exchange;
+use exchange::{AmendError, Exchange, QuoteRefusal};
+use feeds::FeedAggregator;
+use hotlog::HotLog;
+use indices::IndexEngine;
+use instruments::Instruments;
+use perpetuals::Perpetuals;
+use router::SmartRouter;
+use shadow::ShadowMonitor;
 use types::*;
+use ws_connections::WsConnections;
+
+#[cfg(all(feature = "jemalloc", feature = "mimalloc"))]
+compile_error!("features `jemalloc` and `mimalloc` are mutually exclusive");
+
+#[cfg(feature = "jemalloc")]
+#[global_allocator]
+static GLOBAL: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+/// Sequenced events buffered for the publisher before the exchange drops them
+const EVENT_BUFFER: usize = 65_536;
 
 #[tokio::main]
 async fn main() {
     tracing_subscriber::fmt::init();
 
-    let exchange = Arc::new(Exchange::new());
+    // EXCHANGE_SUBMIT_BATCHING=0 turns off submit combining for A/B runs
+    let batch_submits = std::env::var("EXCHANGE_SUBMIT_BATCHING").map_or(true, |v| v != "0" && v != "off");
+    // EXCHANGE_CHAOS=path injects the faults in a JSON file (`chaos` feature only)
+    if let Ok(path) = std::env::var("EXCHANGE_CHAOS") {
+        #[cfg(feature = "chaos")]
+        chaos::install(chaos::ChaosConfig::load(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_CHAOS {}", e);
+            std::process::exit(1);
+        }));
+        #[cfg(not(feature = "chaos"))]
+        {
+            error!("EXCHANGE_CHAOS {}: built without the chaos feature", path);
+            std::process::exit(1);
+        }
+    }
+    let mut exchange = Exchange::new().with_submit_batching(batch_submits);
+    // EXCHANGE_LATENCY_BUDGET_US=N refuses orders whose ts_ns is over N us old on arrival
+    if let Ok(budget) = std::env::var("EXCHANGE_LATENCY_BUDGET_US") {
+        let budget_us: u64 = budget.parse().unwrap_or_else(|_| {
+            error!("EXCHANGE_LATENCY_BUDGET_US must be a whole number of microseconds");
+            std::process::exit(1);
+        });
+        exchange = exchange.with_latency_budget(budget_us * 1_000);
+    }
+    // EXCHANGE_MARGIN=path margin-checks account orders (rates + collateral),
+    // liquidating maintenance breaches if the file has a liquidation section
+    let mut liquidation = None;
+    if let Ok(path) = std::env::var("EXCHANGE_MARGIN") {
+        let config = accounts::MarginConfig::load(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_MARGIN {}", e);
+            std::process::exit(1);
+        });
+        liquidation = config.liquidation.clone();
+        exchange = exchange.with_margin(config);
+    }
+    // EXCHANGE_SURVEILLANCE=path links accounts for wash-trade checks;
+    // EXCHANGE_AUDIT_LOG=path appends each flagged trade as a JSON line
+    let surveillance_config = match std::env::var("EXCHANGE_SURVEILLANCE") {
+        Ok(path) => surveillance::SurveillanceConfig::load(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_SURVEILLANCE {}", e);
+            std::process::exit(1);
+        }),
+        Err(_) => Default::default(),
+    };
+    let mut surveillance = surveillance::Surveillance::new(surveillance_config).unwrap_or_else(|e| {
+        error!("EXCHANGE_SURVEILLANCE {}", e);
+        std::process::exit(1);
+    });
+    if let Ok(path) = std::env::var("EXCHANGE_AUDIT_LOG") {
+        let audit = audit::AuditStore::open(&path).await.unwrap_or_else(|e| {
+            error!("EXCHANGE_AUDIT_LOG {}", e);
+            std::process::exit(1);
+        });
+        surveillance = surveillance.with_audit(audit);
+    }
+    exchange = exchange.with_surveillance(surveillance);
+    // EXCHANGE_TRADE_STORE=path persists trade history across restarts;
+    // EXCHANGE_TRADE_RETENTION_SECS bounds what is kept (default a day)
+    let retention = match std::env::var("EXCHANGE_TRADE_RETENTION_SECS") {
+        Ok(secs) => Duration::from_secs(secs.parse().unwrap_or_else(|_| {
+            error!("EXCHANGE_TRADE_RETENTION_SECS must be a whole number of seconds");
+            std::process::exit(1);
+        })),
+        Err(_) => Duration::from_secs(24 * 3600),
+    };
+    let trade_store = match std::env::var("EXCHANGE_TRADE_STORE") {
+        Ok(path) => trade_store::TradeStore::open(&path, retention).await.unwrap_or_else(|e| {
+            error!("EXCHANGE_TRADE_STORE {}", e);
+            std::process::exit(1);
+        }),
+        Err(_) => trade_store::TradeStore::new(retention),
+    };
+    exchange = exchange.with_trade_store(trade_store);
+    // EXCHANGE_NATS_URL publishes the sequenced event stream to JetStream
+    if let Some(config) = nats::NatsConfig::from_env() {
+        let (event_tx, event_rx) = mpsc::channel(EVENT_BUFFER);
+        exchange = exchange.with_event_sink(event_tx);
+        tokio::spawn(nats::run(config, event_rx));
+    }
+    // EXCHANGE_SHADOW=candidate diffs a second matcher against the live event stream
+    let mut shadow = None;
+    if let Ok(name) = std::env::var("EXCHANGE_SHADOW") {
+        let monitor = Arc::new(ShadowMonitor::new(&name).unwrap_or_else(|| {
+            error!("EXCHANGE_SHADOW {}: not one of {:?}", name, orderbook::shadow::CANDIDATES);
+            std::process::exit(1);
+        }));
+        let (event_tx, event_rx) = mpsc::channel(EVENT_BUFFER);
+        exchange = exchange.with_event_sink(event_tx);
+        let runner = monitor.clone();
+        std::thread::spawn(move || runner.run(event_rx));
+        shadow = Some(monitor);
+    }
+    // EXCHANGE_HOTLOG=path writes the matching path as binary records (decode
+    // with `hftx-cli hotlog`); EXCHANGE_HOTLOG_CAPACITY sizes the ring buffer
+    if let Ok(path) = std::env::var("EXCHANGE_HOTLOG") {
+        let capacity = match std::env::var("EXCHANGE_HOTLOG_CAPACITY") {
+            Ok(v) => v.parse().unwrap_or_else(|_| {
+                error!("EXCHANGE_HOTLOG_CAPACITY must be a whole number of records");
+                std::process::exit(1);
+            }),
+            Err(_) => 1 << 16,
+        };
+        let log = Arc::new(HotLog::new(capacity));
+        if let Err(e) = log.spawn_writer(&path) {
+            error!("EXCHANGE_HOTLOG {}: {}", path, e);
+            std::process::exit(1);
+        }
+        info!("Hot-path log to {} ({} record ring)", path, capacity);
+        exchange = exchange.with_hot_log(log);
+    }
+    let exchange = Arc::new(exchange);
+    // EXCHANGE_SEED_CSV=path rests the orders in a CSV file before serving
+    if let Ok(path) = std::env::var("EXCHANGE_SEED_CSV") {
+        seed_books(&exchange, &path).await;
+    }
+    // EXCHANGE_INSTRUMENTS=path registers futures and options from a JSON
+    // list; EXCHANGE_SETTLEMENT_WEBHOOK receives each expiry's settlement
+    let mut instruments = Instruments::new();
+    if let Ok(url) = std::env::var("EXCHANGE_SETTLEMENT_WEBHOOK") {
+        instruments = instruments.with_hook(instruments::WebhookHook::new(url));
+    }
+    let instruments = Arc::new(instruments);
+    if let Ok(path) = std::env::var("EXCHANGE_INSTRUMENTS") {
+        let listed = Instruments::load(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_INSTRUMENTS {}", e);
+            std::process::exit(1);
+        });
+        for instrument in listed {
+            if let Err(e) = instruments.register(&exchange, instrument).await {
+                error!("EXCHANGE_INSTRUMENTS {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    tokio::spawn(instruments::run_expiry(instruments.clone(), exchange.clone()));
+    tokio::spawn(exchange::run_order_expiry(exchange.clone()));
+    // EXCHANGE_INDICES=path computes the composite indices defined in a JSON list
+    let definitions = match std::env::var("EXCHANGE_INDICES") {
+        Ok(path) => IndexEngine::load(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_INDICES {}", e);
+            std::process::exit(1);
+        }),
+        Err(_) => Vec::new(),
+    };
+    let indices = Arc::new(IndexEngine::new(definitions).unwrap_or_else(|e| {
+        error!("EXCHANGE_INDICES {}", e);
+        std::process::exit(1);
+    }));
+    if !indices.is_empty() {
+        tokio::spawn(indices::run(indices.clone(), exchange.clone()));
+    }
+    // EXCHANGE_PERPETUALS=path marks and funds perpetuals tracking those indices
+    let configs = match std::env::var("EXCHANGE_PERPETUALS") {
+        Ok(path) => Perpetuals::load(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_PERPETUALS {}", e);
+            std::process::exit(1);
+        }),
+        Err(_) => Vec::new(),
+    };
+    for config in &configs {
+        if !indices.contains(&config.index) {
+            error!("EXCHANGE_PERPETUALS {}: no index named {}", config.symbol, config.index);
+            std::process::exit(1);
+        }
+        if exchange.get_best_prices(&config.symbol).await.is_none() {
+            exchange.add_symbol(config.symbol.clone()).await;
+        }
+    }
+    let perpetuals = Arc::new(Perpetuals::new(configs).unwrap_or_else(|e| {
+        error!("EXCHANGE_PERPETUALS {}", e);
+        std::process::exit(1);
+    }));
+    if !perpetuals.is_empty() {
+        tokio::spawn(perpetuals::run(perpetuals.clone(), exchange.clone(), indices.clone()));
+    }
+    // EXCHANGE_BOOK_HISTORY_MS / _SECS: depth sample interval (0 = off) and retention
+    let env_u64 = |name: &str, default: u64| match std::env::var(name) {
+        Ok(v) => v.parse().unwrap_or_else(|_| {
+            error!("{} must be a whole number", name);
+            std::process::exit(1);
+        }),
+        Err(_) => default,
+    };
+    let sample_ms = env_u64("EXCHANGE_BOOK_HISTORY_MS", 1000);
+    let book_history = Arc::new(BookHistory::new(
+        Duration::from_millis(sample_ms.max(1)),
+        Duration::from_secs(env_u64("EXCHANGE_BOOK_HISTORY_SECS", 3600)),
+    ));
+    if sample_ms > 0 {
+        tokio::spawn(book_history::run(book_history.clone(), exchange.clone()));
+    }
     let (trade_tx, _) = broadcast::channel(1000);
     let (latency_tx, _) = broadcast::channel::<LatencySample>(4096);
     let bot_driver = BotDriver::new(exchange.clone(), trade_tx.clone(), latency_tx.clone());
+    if let Some(config) = liquidation {
+        tokio::spawn(liquidation::run(config, exchange.clone(), trade_tx.clone()));
+    }
+    // EXCHANGE_TSDB_URL streams trades and BBO to QuestDB / InfluxDB
+    if let Some(config) = tsdb::TsdbConfig::from_env() {
+        tokio::spawn(tsdb::run(config, exchange.clone(), trade_tx.subscribe()));
+    }
+    // EXCHANGE_ZMQ_BIND publishes SBE market data on a ZeroMQ PUB socket
+    if let Ok(endpoint) = std::env::var("EXCHANGE_ZMQ_BIND") {
+        tokio::spawn(zmq::run(endpoint, exchange.clone(), trade_tx.subscribe()));
+    }
+    // EXCHANGE_FEEDS=path mirrors the external venues listed in a JSON file
+    let feeds = Arc::new(FeedAggregator::default());
+    if let Ok(path) = std::env::var("EXCHANGE_FEEDS") {
+        let venues = feeds::VenueConfig::load(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_FEEDS {}", e);
+            std::process::exit(1);
+        });
+        for venue in venues {
+            tokio::spawn(feeds::run(venue, feeds.clone()));
+        }
+    }
+    // EXCHANGE_ROUTES=path lists the venues routed orders may be split across
+    let routes = match std::env::var("EXCHANGE_ROUTES") {
+        Ok(path) => router::RouteVenue::load(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_ROUTES {}", e);
+            std::process::exit(1);
+        }),
+        Err(_) => Vec::new(),
+    };
+    let router = Arc::new(SmartRouter::new(routes));
+    // EXCHANGE_WS_SLOW_POLICY / _MAX_QUEUE: what happens to trade and depth
+    // subscribers whose send queue fills (conflate, gap, or disconnect)
+    let slow_policy = match std::env::var("EXCHANGE_WS_SLOW_POLICY") {
+        Ok(policy) => policy.parse().unwrap_or_else(|e| {
+            error!("EXCHANGE_WS_SLOW_POLICY {}", e);
+            std::process::exit(1);
+        }),
+        Err(_) => ws_connections::SlowPolicy::Conflate,
+    };
+    let ws_connections = Arc::new(WsConnections::new(slow_policy, env_u64("EXCHANGE_WS_MAX_QUEUE", 1024) as usize));
+    // EXCHANGE_ACCOUNT_TOKENS=path gives each account a bearer token of its own
+    let account_tokens = match std::env::var("EXCHANGE_ACCOUNT_TOKENS") {
+        Ok(path) => accounts::load_tokens(&path).unwrap_or_else(|e| {
+            error!("EXCHANGE_ACCOUNT_TOKENS {}", e);
+            std::process::exit(1);
+        }),
+        Err(_) => Default::default(),
+    };
+    let account_tokens = Arc::new(account_tokens);
 
     let app = Router::new()
         .route("/health", get(health_check))
+        .route("/stats", get(submit_stats))
+        .route("/shadow", get(shadow_report))
         .route("/symbols", get(list_symbols))
+        .route("/symbols/:symbol/config", get(get_symbol_config).put(set_symbol_config))
         .route("/symbols/:symbol/orderbook", get(get_orderbook))
         .route("/symbols/:symbol/depth", get(get_depth))
-        .route("/symbols/:symbol/orders", post(submit_order))
+        .route("/symbols/:symbol/book-history", get(get_book_history))
+        .route("/symbols/:symbol/volume-profile", get(get_volume_profile))
+        .route("/symbols/:symbol/trades", get(get_trades))
+        .route("/symbols/:symbol/orders", post(submit_order).delete(cancel_orders))
         .route("/symbols/:symbol/orders/batch", post(submit_order_batch))
-        .route("/symbols/:symbol/orders/:order_id", delete(cancel_order))
+        .route("/symbols/:symbol/orders/:order_id", get(get_order_status).delete(cancel_order).put(modify_order))
+        .route("/symbols/:symbol/stops", post(submit_stop))
+        .route("/symbols/:symbol/quotes", post(submit_quote))
+        .route("/route/:symbol/orders", post(route_order))
         .route("/symbols/:symbol/trades/stream", get(trade_stream))
         .route("/symbols/:symbol/depth/stream", get(depth_stream))
+        .route("/symbols/:symbol/levels/stream", get(level_stream))
         .route("/symbols/:symbol/orders/stream", get(order_stream))
+        .route("/ws/connections", get(ws_connection_report))
         .route("/sim/start", post(sim_start))
         .route("/sim/stop", post(sim_stop))
         .route("/sim/status", get(sim_status))
         .route("/sim/latency/stream", get(sim_latency_stream))
+        .route("/instruments", get(list_instruments).post(register_instrument))
+        .route("/instruments/:symbol", get(get_instrument))
+        .route("/settlements", get(list_settlements))
+        .route("/indices", get(list_indices))
+        .route("/indices/:name", get(get_index))
+        .route("/indices/:name/stream", get(index_stream))
+        .route("/perpetuals", get(list_perpetuals))
+        .route("/perpetuals/:symbol", get(get_perpetual))
+        .route("/accounts/:account", get(get_account))
+        .route("/accounts/:account/orders", get(get_account_orders))
+        .route("/accounts/:account/stream", get(account_stream))
+        .route("/accounts/:account/kill", post(kill_account))
+        .route("/accounts/:account/unblock", post(unblock_account))
+        .route("/tca", get(get_tca))
+        .route("/surveillance/wash-trades", get(wash_trade_report))
+        .route("/surveillance/alerts/stream", get(surveillance_stream))
+        .route("/consolidated", get(list_consolidated))
+        .route("/consolidated/:symbol", get(get_consolidated))
         .layer(CorsLayer::permissive())
         .with_state(AppState {
             exchange: exchange.clone(),
             trade_broadcaster: trade_tx,
             bot_driver,
             latency_broadcaster: latency_tx,
+            feeds,
+            instruments,
+            indices,
+            perpetuals,
+            book_history,
+            router,
+            shadow,
+            ws_connections,
+            // EXCHANGE_ADMIN_TOKEN guards admin actions (bearer token)
+            admin_token: std::env::var("EXCHANGE_ADMIN_TOKEN").ok().map(Arc::from),
+            account_tokens,
         });
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:8080")
+    // EXCHANGE_ADDR lets harnesses run an instance on a private port
+    let addr = std::env::var("EXCHANGE_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
+    let listener = tokio::net::TcpListener::bind(&addr)
         .await
         .unwrap();
 
-    info!("HFT Exchange Service starting on http://0.0.0.0:8080");
+    info!("HFT Exchange Service starting on http://{}", addr);
     info!("Available endpoints:");
     info!("  GET  /health - Health check");
+    info!("  GET  /stats - Submit / lock-batching counters");
+    info!("  GET  /shadow - Shadow matcher divergences");
     info!("  GET  /symbols - List available symbols");
+    info!("  GET  /symbols/:symbol/config - Tick size, lot size and quantity limits");
+    info!("  PUT  /symbols/:symbol/config - Replace a symbol's trading parameters (admin)");
     info!("  GET  /symbols/:symbol/orderbook - Get order book state");
     info!("  GET  /symbols/:symbol/depth - Get market depth");
+    info!("  GET  /symbols/:symbol/book-history - Time x price depth matrix");
+    info!("  GET  /symbols/:symbol/volume-profile - Traded volume by price");
+    info!("  GET  /symbols/:symbol/trades - Trade history, for resyncing a stream");
     info!("  POST /symbols/:symbol/orders - Submit order");
     info!("  POST /symbols/:symbol/orders/batch - Submit batch of orders");
-    info!("  DEL  /symbols/:symbol/orders/:id - Cancel order");
+    info!("  DEL  /symbols/:symbol/orders - Cancel all resting orders (?side=, ?min_price=, ?max_price=, or ?account=)");
+    info!("  GET  /symbols/:symbol/orders/:id - Order status (?account= for only its own)");
+    info!("  DEL  /symbols/:symbol/orders/:id - Cancel order (?account= to cancel only its own)");
+    info!("  PUT  /symbols/:symbol/orders/:id - Amend order");
+    info!("  POST /symbols/:symbol/stops - Submit stop or stop-limit order");
+    info!("  POST /symbols/:symbol/quotes - Replace an account's two-sided quote");
+    info!("  POST /route/:symbol/orders - Split an order across venues");
     info!("  WS   /symbols/:symbol/trades/stream - Trade stream");
     info!("  WS   /symbols/:symbol/depth/stream - Depth stream");
+    info!("  WS   /symbols/:symbol/levels/stream - Level changes as the book makes them");
     info!("  WS   /symbols/:symbol/orders/stream - Order submission stream");
+    info!("  GET  /ws/connections - Stream send queues and slow-consumer counters");
     info!("  POST /sim/start - Start server-side bot driver");
     info!("  POST /sim/stop - Stop server-side bot driver");
     info!("  GET  /sim/status - Bot driver status");
     info!("  WS   /sim/latency/stream - Per-order latency samples");
+    info!("  GET  /instruments - Registered futures and options");
+    info!("  POST /instruments - Register an instrument");
+    info!("  GET  /instruments/:symbol - One instrument's terms");
+    info!("  GET  /settlements - Expired instruments and their settlement");
+    info!("  GET  /indices - Latest composite index values");
+    info!("  GET  /indices/:name - One index with its constituents");
+    info!("  WS   /indices/:name/stream - Index value changes");
+    info!("  GET  /perpetuals - Perpetual marks and funding rates");
+    info!("  GET  /perpetuals/:symbol - One perpetual's mark and funding");
+    info!("  GET  /accounts/:account - Positions and PnL");
+    info!("  GET  /accounts/:account/orders - An account's resting orders");
+    info!("  WS   /accounts/:account/stream - Fills, marks, and funding for an account");
+    info!("  POST /accounts/:account/kill - Block an account and cancel its orders (admin)");
+    info!("  POST /accounts/:account/unblock - Lift a kill (admin)");
+    info!("  GET  /tca - Transaction costs per order and account");
+    info!("  GET  /surveillance/wash-trades - Flagged same-owner trades");
+    info!("  WS   /surveillance/alerts/stream - Wash-trade and spoofing alerts");
+    info!("  GET  /consolidated - Symbols mirrored from external venues");
+    info!("  GET  /consolidated/:symbol - Book merged across venues");
 
     axum::serve(listener, app).await.unwrap();
 }
@@ -97,6 +456,26 @@ pub struct AppState {
     pub bot_driver: BotDriver,
     /// Broadcast channel for per-order latency samples produced by the driver
     pub latency_broadcaster: broadcast::Sender<LatencySample>,
+    /// Books mirrored from external venues
+    pub feeds: Arc<FeedAggregator>,
+    /// Instrument terms and settlements
+    pub instruments: Arc<Instruments>,
+    /// Composite index values
+    pub indices: Arc<IndexEngine>,
+    /// Perpetual marks and funding
+    pub perpetuals: Arc<Perpetuals>,
+    /// Sampled depth for heatmaps
+    pub book_history: Arc<BookHistory>,
+    /// Venues routed orders are split across
+    pub router: Arc<SmartRouter>,
+    /// Candidate matcher diffed against the live books, if enabled
+    pub shadow: Option<Arc<ShadowMonitor>>,
+    /// Send queues of the trade and depth stream connections
+    pub ws_connections: Arc<WsConnections>,
+    /// Bearer token admin endpoints require, if set
+    pub admin_token: Option<Arc<str>>,
+    /// Bearer tokens by account, for requests acting for one
+    pub account_tokens: Arc<HashMap<String, String>>,
 }
 
 /// Health check endpoint returning service status.
@@ -109,6 +488,19 @@ async fn health_check() -> impl IntoResponse {
     }))
 }
 
+/// Reports how many single-order submits shared each book-lock acquisition.
+async fn submit_stats(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.exchange.submit_stats())
+}
+
+/// Shadow matcher counters and its latest divergences.
+async fn shadow_report(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let shadow = state.shadow.as_ref()
+        .ok_or_else(|| AppError::Disabled("Shadow matching is off (set EXCHANGE_SHADOW)".to_string()))?;
+
+    Ok(Json(shadow.report()))
+}
+
 /// Lists all available trading symbols.
 async fn list_symbols(State(state): State<AppState>) -> impl IntoResponse {
     let symbols = state.exchange.list_symbols().await;
@@ -138,28 +530,163 @@ async fn get_depth(
     Ok(Json(depth))
 }
 
+/// Time x price matrix of resting quantity, for book heatmaps. Defaults to
+/// the whole retained history in 60 buckets.
+async fn get_book_history(
+    Path(symbol): Path<String>,
+    Query(params): Query<BookHistoryQuery>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    if state.exchange.last_trade_price(&symbol).is_none() && !state.book_history.contains(&symbol) {
+        return Err(AppError::SymbolNotFound);
+    }
+    let to = params.to.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+    let from = params.from.unwrap_or_else(|| to.saturating_sub(state.book_history.retention_ns()));
+    let buckets = params.buckets.unwrap_or(60);
+    if from >= to {
+        return Err(AppError::InvalidQuery("from must be before to".to_string()));
+    }
+    if buckets == 0 || buckets > book_history::MAX_BUCKETS {
+        return Err(AppError::InvalidQuery(format!("buckets must be 1 to {}", book_history::MAX_BUCKETS)));
+    }
+
+    Ok(Json(state.book_history.heatmap(&symbol, from, to, buckets)))
+}
+
+/// Traded volume by price bucket, with VWAP, point of control and value
+/// area. Defaults to the whole retained history in 1-tick buckets.
+async fn get_volume_profile(
+    Path(symbol): Path<String>,
+    Query(params): Query<VolumeProfileQuery>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.exchange.trade_store();
+    let to = params.to.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+    let from = params.from.unwrap_or_else(|| to.saturating_sub(store.retention_ns()));
+    let bucket = params.bucket.unwrap_or(1);
+    if from >= to {
+        return Err(AppError::InvalidQuery("from must be before to".to_string()));
+    }
+    if bucket <= 0 {
+        return Err(AppError::InvalidQuery("bucket must be a positive number of ticks".to_string()));
+    }
+    let mut fills = Vec::new();
+    store.for_each_in(&symbol, from, to, |t| fills.push((t.px_ticks, t.qty)));
+    if fills.is_empty() && state.exchange.last_trade_price(&symbol).is_none() {
+        return Err(AppError::SymbolNotFound);
+    }
+
+    Ok(Json(SymbolVolumeProfile { symbol, from_ns: from, to_ns: to, profile: VolumeProfile::build(bucket, fills) }))
+}
+
+/// A symbol's trades in a time range, oldest first, from the trade store.
+/// Stream subscribers resync from here after a gap notice.
+async fn get_trades(
+    Path(symbol): Path<String>,
+    Query(params): Query<TradesQuery>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.exchange.trade_store();
+    let to = params.to.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+    let from = params.from.unwrap_or_else(|| to.saturating_sub(store.retention_ns()));
+    let limit = params.limit.unwrap_or(1000).min(MAX_TRADES);
+    if from >= to {
+        return Err(AppError::InvalidQuery("from must be before to".to_string()));
+    }
+    let mut trades = Vec::new();
+    let mut truncated = false;
+    store.for_each_in(&symbol, from, to, |t| {
+        if trades.len() < limit {
+            trades.push(*t);
+        } else {
+            truncated = true;
+        }
+    });
+    if trades.is_empty() && state.exchange.last_trade_price(&symbol).is_none() {
+        return Err(AppError::SymbolNotFound);
+    }
+
+    Ok(Json(SymbolTrades { symbol, from_ns: from, to_ns: to, trades, truncated }))
+}
+
+/// Send queue depth, broadcast lag, and slow-consumer counters of every
+/// trade and depth stream connection.
+async fn ws_connection_report(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.ws_connections.report())
+}
+
+/// Submits every order in an `orderbook::csv` orders file, adding symbols
+/// the exchange doesn't list yet. Exits on an unreadable file.
+async fn seed_books(exchange: &Exchange, path: &str) {
+    let orders = std::fs::File::open(path)
+        .map_err(orderbook::csv::CsvError::Io)
+        .and_then(|f| orderbook::csv::read_orders(std::io::BufReader::new(f)));
+    let orders = match orders {
+        Ok(orders) => orders,
+        Err(e) => {
+            error!("EXCHANGE_SEED_CSV {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    let count = orders.len();
+    let mut trades = 0;
+    for order in orders {
+        let symbol = order.symbol.as_str();
+        if exchange.get_best_prices(symbol).await.is_none() {
+            exchange.add_symbol(symbol.to_string()).await;
+        }
+        trades += exchange.submit_order(symbol.to_string(), order).await.and_then(Result::ok).map_or(0, |report| report.trades.len());
+    }
+    info!("Seeded {} orders from {} ({} trades while loading)", count, path, trades);
+}
+
 /// Submits a new limit order to the exchange.
 async fn submit_order(
     Path(symbol): Path<String>,
     State(state): State<AppState>,
     Json(request): Json<SubmitOrderRequest>,
 ) -> Result<impl IntoResponse, AppError> {
+    let response = place_order(&state, symbol, request).await?;
+
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Checks, assigns and matches one order, broadcasting its trades. Shared
+/// by `POST /symbols/:symbol/orders` and the local leg of routed orders.
+async fn place_order(state: &AppState, symbol: String, request: SubmitOrderRequest) -> Result<SubmitOrderResponse, AppError> {
     let order_id = OrderId(uuid::Uuid::new_v4().as_u128());
     
-    let order = Order {
+    let mut order = Order {
         id: order_id,
-        symbol: symbol.clone(),
+        symbol: symbol_id(&symbol)?,
         side: request.side,
         px_ticks: request.price,
         qty: request.quantity,
         ts_ns: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos(),
+        tif: TimeInForce::Day,
+        expires_ns: request.expires_ns.map(u128::from),
+        display_qty: request.display_qty,
+        min_qty: request.min_qty,
+        hidden: request.hidden,
+        owner: ParticipantId::NONE,
     };
+    state.exchange.check_fresh(request.ts_ns, order.ts_ns as u64)
+        .map_err(|age_ns| AppError::stale(state, age_ns))?;
+    check_terms(&order)?;
+    if let Some(account) = &request.account {
+        state.exchange.assign_order(account, &mut order)?;
+    }
 
-    let trades = state.exchange.submit_order(symbol.clone(), order).await
-        .ok_or(AppError::SymbolNotFound)?;
+    // The book checks the order as it matches it and gives its reason
+    let report = state.exchange.submit_order(symbol.clone(), order).await
+        .ok_or(AppError::SymbolNotFound)?
+        .map_err(refused)?;
 
     // Broadcast trades via WebSocket
-    for trade in &trades {
+    for trade in &report.trades {
+        if chaos::drop_message(&symbol) {
+            continue;
+        }
         let trade_event = TradeEvent {
             symbol: symbol.clone(),
             trade: trade.clone(),
@@ -168,13 +695,153 @@ async fn submit_order(
         let _ = state.trade_broadcaster.send(trade_event);
     }
 
-    let response = SubmitOrderResponse {
-        order_id: order_id.0,
-        status: if trades.is_empty() { "rested".to_string() } else { "filled".to_string() },
-        trades,
+    Ok(report.into())
+}
+
+/// Replaces an account's two-sided quote: what is left of its last one on
+/// the symbol is cancelled and the new bid and ask entered, under one
+/// lock. Either side may trade on arrival; its trades are broadcast.
+async fn submit_quote(
+    Path(symbol): Path<String>,
+    State(state): State<AppState>,
+    Json(request): Json<QuoteRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let ts_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    state.exchange.check_fresh(request.ts_ns, ts_ns as u64)
+        .map_err(|age_ns| AppError::stale(&state, age_ns))?;
+    let [mut bid, mut ask] = request.orders(symbol_id(&symbol)?, ts_ns);
+    let mut orders: Vec<Order> = Vec::with_capacity(2);
+    for order in [&mut bid, &mut ask].into_iter().filter(|order| order.qty > 0) {
+        if let Err(e) = state.exchange.assign_order(&request.account, order) {
+            state.exchange.release_orders(&orders);
+            return Err(e.into());
+        }
+        orders.push(order.clone());
+    }
+
+    let report = state.exchange.submit_quote(&symbol, &request.account, bid, ask).await
+        .ok_or(AppError::SymbolNotFound)?
+        .map_err(|e| {
+            state.exchange.release_orders(&orders);
+            match e {
+                QuoteRefusal::Invalid(e) => AppError::InvalidQuery(e.to_string()),
+                QuoteRefusal::Rejected(rejection) => rejection.into(),
+            }
+        })?;
+
+    for trade in report.bid.iter().chain(&report.ask).flat_map(|report| &report.trades) {
+        if chaos::drop_message(&symbol) {
+            continue;
+        }
+        let _ = state.trade_broadcaster.send(TradeEvent {
+            symbol: symbol.clone(),
+            trade: trade.clone(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+        });
+    }
+
+    Ok((StatusCode::CREATED, Json(QuoteResponse::from(report))))
+}
+
+/// Submits a stop, or a stop-limit when the request has a `price`. It is
+/// held off the book, with status `held`, until a trade prints at or
+/// through `stop_price`; if the last trade already has, it trades now.
+/// Cancel it like any order. A `ts_ns` older than the latency budget is
+/// refused, as for limit orders.
+async fn submit_stop(
+    Path(symbol): Path<String>,
+    State(state): State<AppState>,
+    Json(request): Json<StopOrderRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let order_id = OrderId(uuid::Uuid::new_v4().as_u128());
+    let kind = if request.price.is_some() { OrderKind::Limit } else { OrderKind::Market };
+    let mut order = Order {
+        id: order_id,
+        symbol: symbol_id(&symbol)?,
+        side: request.side,
+        px_ticks: request.price.unwrap_or(request.stop_price),
+        qty: request.quantity,
+        ts_ns: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos(),
+        tif: TimeInForce::Day,
+        expires_ns: None,
+        display_qty: None,
+        min_qty: None,
+        hidden: false,
+        owner: ParticipantId::NONE,
     };
+    state.exchange.check_fresh(request.ts_ns, order.ts_ns as u64)
+        .map_err(|age_ns| AppError::stale(&state, age_ns))?;
+    check_terms(&order)?;
+    if let Some(account) = &request.account {
+        state.exchange.assign_order(account, &mut order)?;
+    }
 
-    Ok((StatusCode::CREATED, Json(response)))
+    let (report, held) = state.exchange.submit_stop(&symbol, order, request.stop_price, kind).await
+        .ok_or(AppError::SymbolNotFound)?
+        .map_err(refused)?;
+
+    // Trades of stops it set off on arrival included
+    for trade in &report.trades {
+        if chaos::drop_message(&symbol) {
+            continue;
+        }
+        let _ = state.trade_broadcaster.send(TradeEvent {
+            symbol: symbol.clone(),
+            trade: trade.clone(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+        });
+    }
+
+    let status = if held { "held" } else { report.status.as_str() };
+    Ok((StatusCode::CREATED, Json(SubmitOrderResponse { status: status.to_string(), ..report.into() })))
+}
+
+/// Refuses a batch order that the symbol's book would, with the book's
+/// reason: no positive quantity or price, or off the symbol's ticks, lots
+/// or size range (see [`Exchange::check_order`]). Checked before any of the
+/// batch is matched, so one bad order refuses the whole batch.
+async fn check_order(state: &AppState, symbol: &str, order: &Order) -> Result<(), AppError> {
+    state.exchange.check_order(symbol, order).await
+        .ok_or(AppError::SymbolNotFound)?
+        .map_err(refused)
+}
+
+/// An order the book refused, answered 400 with its reason.
+fn refused(reason: RejectReason) -> AppError {
+    AppError::InvalidQuery(reason.to_string())
+}
+
+/// Refuses an order that has expired before it arrives, an iceberg that
+/// would show nothing or is also hidden, and a minimum fill the order
+/// itself couldn't meet.
+fn check_terms(order: &Order) -> Result<(), AppError> {
+    match (order.expires_ns, order.display_qty, order.min_qty) {
+        (Some(expires_ns), _, _) if expires_ns <= order.ts_ns => {
+            Err(AppError::InvalidQuery(format!("expires_ns {} has already passed", expires_ns)))
+        }
+        (_, Some(display_qty), _) if display_qty <= 0 => {
+            Err(AppError::InvalidQuery(format!("display_qty {} must be positive", display_qty)))
+        }
+        (_, Some(_), _) if order.hidden => {
+            Err(AppError::InvalidQuery("a hidden order can't have a display_qty".to_string()))
+        }
+        (_, _, Some(min_qty)) if min_qty <= 0 || min_qty > order.qty => {
+            Err(AppError::InvalidQuery(format!("min_qty {} must be between 1 and the quantity", min_qty)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Splits an order across this exchange and the `EXCHANGE_ROUTES` venues
+/// by displayed liquidity, and sums up the children's fills.
+async fn route_order(
+    Path(symbol): Path<String>,
+    State(state): State<AppState>,
+    Json(request): Json<SubmitOrderRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let summary = state.router.route(&state, &symbol, request).await?;
+
+    Ok((StatusCode::CREATED, Json(summary)))
 }
 
 /// Submits a batch of orders to a single symbol under one write lock.
@@ -188,19 +855,47 @@ async fn submit_order_batch(
 ) -> Result<impl IntoResponse, AppError> {
     let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
 
+    let symbol_id = symbol_id(&symbol)?;
     let mut order_ids = Vec::with_capacity(request.orders.len());
     let mut orders = Vec::with_capacity(request.orders.len());
     for req in request.orders {
+        // One stale order fails the batch, like a margin rejection
+        if let Err(age_ns) = state.exchange.check_fresh(req.ts_ns, now_ns as u64) {
+            state.exchange.release_orders(&orders);
+            return Err(AppError::stale(&state, age_ns));
+        }
         let order_id = OrderId(uuid::Uuid::new_v4().as_u128());
         order_ids.push(order_id.0);
-        orders.push(Order {
+        let mut order = Order {
             id: order_id,
-            symbol: symbol.clone(),
+            symbol: symbol_id,
             side: req.side,
             px_ticks: req.price,
             qty: req.quantity,
             ts_ns: now_ns,
-        });
+            tif: TimeInForce::Day,
+            expires_ns: req.expires_ns.map(u128::from),
+            display_qty: req.display_qty,
+            min_qty: req.min_qty,
+            hidden: req.hidden,
+            owner: ParticipantId::NONE,
+        };
+        if let Err(e) = check_terms(&order) {
+            state.exchange.release_orders(&orders);
+            return Err(e);
+        }
+        if let Err(e) = check_order(&state, &symbol, &order).await {
+            state.exchange.release_orders(&orders);
+            return Err(e);
+        }
+        if let Some(account) = &req.account {
+            // One rejection fails the batch before any of it is submitted
+            if let Err(e) = state.exchange.assign_order(account, &mut order) {
+                state.exchange.release_orders(&orders);
+                return Err(e.into());
+            }
+        }
+        orders.push(order);
     }
 
     let batch_t0 = Instant::now();
@@ -212,11 +907,15 @@ async fn submit_order_batch(
     let engine_ns = batch_t0.elapsed().as_nanos() as u64;
 
     let mut results = Vec::with_capacity(per_order.len());
-    for (idx, (trades, latency_ns)) in per_order.into_iter().enumerate() {
-        let trade_count = trades.len();
-        let filled = trade_count > 0;
+    for (idx, (report, latency_ns)) in per_order.into_iter().enumerate() {
+        let trade_count = report.trades.len();
+        let filled = report.filled_qty > 0;
+        let status = report.status.as_str().to_string();
 
-        for trade in trades {
+        for trade in report.trades {
+            if chaos::drop_message(&symbol) {
+                continue;
+            }
             let _ = state.trade_broadcaster.send(TradeEvent {
                 symbol: symbol.clone(),
                 trade,
@@ -226,6 +925,7 @@ async fn submit_order_batch(
 
         results.push(BatchOrderResult {
             order_id: order_ids[idx],
+            status,
             filled,
             trade_count,
             latency_ns: latency_ns as u64,
@@ -238,40 +938,162 @@ async fn submit_order_batch(
     ))
 }
 
-/// Cancels an existing order by ID.
+/// What became of an order: filled and original quantity, and whether it
+/// is new, partially filled, filled, canceled or expired. Scoped to
+/// `account`, with its token; without one it takes the admin token, if
+/// configured.
+async fn get_order_status(
+    Path((symbol, order_id)): Path<(String, String)>,
+    Query(params): Query<OrderQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_scope(&state, &headers, params.account.as_deref())?;
+    let order_id = order_id.parse::<u128>()
+        .map_err(|_| AppError::InvalidOrderId)?;
+
+    let lifecycle = state.exchange.order_status(&symbol, OrderId(order_id), params.account.as_deref()).await
+        .ok_or(AppError::SymbolNotFound)?
+        .ok_or(AppError::OrderNotFound)?;
+
+    Ok(Json(OrderStatusResponse {
+        order_id,
+        status: lifecycle.state.as_str().to_string(),
+        orig_qty: lifecycle.orig_qty,
+        filled_qty: lifecycle.filled_qty,
+        leaves_qty: lifecycle.leaves_qty(),
+    }))
+}
+
+/// Cancels an existing order by ID. Scoped to `account`, with its token;
+/// without one it takes the admin token, if configured.
 async fn cancel_order(
     Path((symbol, order_id)): Path<(String, String)>,
+    Query(params): Query<OrderQuery>,
+    headers: HeaderMap,
     State(state): State<AppState>,
 ) -> Result<impl IntoResponse, AppError> {
+    authorize_scope(&state, &headers, params.account.as_deref())?;
     let order_id = order_id.parse::<u128>()
         .map_err(|_| AppError::InvalidOrderId)?;
     
-    let cancelled = state.exchange.cancel_order(&symbol, OrderId(order_id)).await
+    // With an account, someone else's order is reported as not found
+    let cancelled = state.exchange.cancel_order(&symbol, OrderId(order_id), params.account.as_deref()).await
         .ok_or(AppError::SymbolNotFound)?;
 
-    if cancelled {
-        Ok(Json(serde_json::json!({"status": "cancelled", "order_id": order_id})))
-    } else {
-        Err(AppError::OrderNotFound)
+    match cancelled {
+        // A typed response: `json!` can't hold ids past u64::MAX
+        Some(order) => Ok(Json(CancelOrderResponse { status: "cancelled".to_string(), order_id, remaining_qty: order.qty })),
+        None => Err(AppError::OrderNotFound),
+    }
+}
+
+/// Pulls every resting order on the book, or those on one side or in a
+/// price range, or one account's, under a single lock. One account's
+/// orders take its token; anything wider takes the admin token, if
+/// configured.
+async fn cancel_orders(
+    Path(symbol): Path<String>,
+    Query(params): Query<MassCancelQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_scope(&state, &headers, params.account.as_deref())?;
+    if let Some(account) = &params.account {
+        if params.side.is_some() || params.min_price.is_some() || params.max_price.is_some() {
+            return Err(AppError::InvalidQuery("account can't be combined with side or a price range".to_string()));
+        }
+        let cancelled = state.exchange.cancel_account_orders(&symbol, account).await
+            .ok_or(AppError::SymbolNotFound)?;
+        info!("Mass cancel on {} for {}: {} resting orders cancelled", symbol, account, cancelled.len());
+        return Ok(Json(MassCancelResponse { status: "cancelled".to_string(), order_ids: cancelled.into_iter().map(|id| id.0).collect() }));
+    }
+
+    let (lo, hi) = (params.min_price.unwrap_or(i64::MIN), params.max_price.unwrap_or(i64::MAX));
+    if lo > hi {
+        return Err(AppError::InvalidQuery(format!("min_price {} is above max_price {}", lo, hi)));
+    }
+
+    let cancelled = state.exchange.cancel_orders(&symbol, params.side, lo, hi).await
+        .ok_or(AppError::SymbolNotFound)?;
+    info!("Mass cancel on {}: {} resting orders cancelled", symbol, cancelled.len());
+
+    Ok(Json(MassCancelResponse { status: "cancelled".to_string(), order_ids: cancelled.into_iter().map(|id| id.0).collect() }))
+}
+
+/// Amends a resting order's price and remaining quantity. A smaller
+/// quantity at the same price keeps its queue position; anything else
+/// re-enters it, and it may trade. With `account` (and its token), another
+/// account's order is reported as not found; without one it takes the
+/// admin token, if configured. Terms the book refuses (see
+/// [`orderbook::OrderBook::check_amend`]) answer 400 with its reason.
+async fn modify_order(
+    Path((symbol, order_id)): Path<(String, String)>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(request): Json<ModifyOrderRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_scope(&state, &headers, request.account.as_deref())?;
+    let order_id = order_id.parse::<u128>()
+        .map_err(|_| AppError::InvalidOrderId)?;
+    // Cancels go through DELETE, which also reaches held stops
+    if request.quantity <= 0 {
+        return Err(AppError::InvalidQuery(format!("quantity {} must be positive", request.quantity)));
+    }
+
+    let report = state.exchange.modify_order(&symbol, OrderId(order_id), request.price, request.quantity, request.account.as_deref()).await
+        .ok_or(AppError::SymbolNotFound)??;
+
+    for trade in &report.trades {
+        if chaos::drop_message(&symbol) {
+            continue;
+        }
+        let _ = state.trade_broadcaster.send(TradeEvent {
+            symbol: symbol.clone(),
+            trade: trade.clone(),
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+        });
     }
+
+    let status = match report.status {
+        OrderStatus::Rested => "amended",
+        status => status.as_str(),
+    };
+    Ok(Json(SubmitOrderResponse { status: status.to_string(), ..report.into() }))
 }
 
 /// WebSocket handler for real-time trade streaming.
+/// `?format=bin` switches trade messages to compact binary frames,
+/// `?format=sbe` to SBE trade messages.
 async fn trade_stream(
     Path(symbol): Path<String>,
+    Query(params): Query<StreamQuery>,
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
 ) -> Response {
-    ws.on_upgrade(move |socket| websocket::handle_trade_stream(socket, symbol, state))
+    let format = params.feed_format();
+    ws.on_upgrade(move |socket| websocket::handle_trade_stream(socket, symbol, format, state))
 }
 
 /// WebSocket handler for real-time market depth streaming.
+/// `?format=sbe` switches to SBE BBO and depth-diff messages.
 async fn depth_stream(
+    Path(symbol): Path<String>,
+    Query(params): Query<StreamQuery>,
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> Response {
+    let sbe = params.feed_format() == FeedFormat::Sbe;
+    ws.on_upgrade(move |socket| websocket::handle_depth_stream(socket, symbol, sbe, state))
+}
+
+/// WebSocket handler for per-level book changes.
+async fn level_stream(
     Path(symbol): Path<String>,
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
 ) -> Response {
-    ws.on_upgrade(move |socket| websocket::handle_depth_stream(socket, symbol, state))
+    ws.on_upgrade(move |socket| websocket::handle_level_stream(socket, symbol, state))
 }
 
 /// WebSocket handler for the persistent order-submission channel.
@@ -297,6 +1119,7 @@ async fn sim_start(
         takers: req.takers,
         aggression: req.aggression,
         tick_ms: req.tick_ms,
+        seed: req.seed,
     };
     state.bot_driver.start(config).await;
     Ok(StatusCode::ACCEPTED)
@@ -328,21 +1151,372 @@ async fn sim_latency_stream(
     ws.on_upgrade(move |socket| websocket::handle_latency_stream(socket, state))
 }
 
+/// Lists registered instruments.
+async fn list_instruments(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.instruments.list().await)
+}
+
+/// Registers an instrument, listing its symbol if needed (admin token,
+/// if configured).
+async fn register_instrument(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(instrument): Json<Instrument>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_admin(&state, &headers)?;
+    state.instruments.register(&state.exchange, instrument.clone()).await
+        .map_err(|e| AppError::InvalidInstrument(e.to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(instrument)))
+}
+
+/// Gets one instrument's terms.
+async fn get_instrument(
+    Path(symbol): Path<String>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let instrument = state.instruments.get(&symbol).await
+        .ok_or(AppError::SymbolNotFound)?;
+
+    Ok(Json(instrument))
+}
+
+/// Lists settled instruments, oldest first.
+async fn list_settlements(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.instruments.settlements().await)
+}
+
+/// Lists the latest value of every computed index.
+async fn list_indices(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.indices.list())
+}
+
+/// Gets an index's latest value with its constituent prices.
+async fn get_index(
+    Path(name): Path<String>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let value = state.indices.latest(&name)
+        .ok_or(AppError::IndexNotFound)?;
+
+    Ok(Json(value))
+}
+
+/// WebSocket handler for one index's value changes.
+async fn index_stream(
+    Path(name): Path<String>,
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    if !state.indices.contains(&name) {
+        return Err(AppError::IndexNotFound);
+    }
+    Ok(ws.on_upgrade(move |socket| websocket::handle_index_stream(socket, name, state)))
+}
+
+/// Lists every perpetual's mark, basis, and funding rates.
+async fn list_perpetuals(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.perpetuals.list())
+}
+
+/// Gets one perpetual's mark and funding.
+async fn get_perpetual(
+    Path(symbol): Path<String>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let status = state.perpetuals.get(&symbol)
+        .ok_or(AppError::SymbolNotFound)?;
+
+    Ok(Json(status))
+}
+
+/// Gets an account's positions and margin, valued at mark where there is
+/// one. Needs the account's token or the admin token, if configured.
+async fn get_account(
+    Path(account): Path<String>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_account(&state, &headers, &account)?;
+    let view = state.exchange.account_view(&account)
+        .ok_or(AppError::AccountNotFound)?;
+
+    Ok(Json(view))
+}
+
+/// An account's orders resting across every symbol, grouped by symbol.
+/// Held stops aren't listed. Needs the account's token or the admin
+/// token, if configured.
+async fn get_account_orders(
+    Path(account): Path<String>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_account(&state, &headers, &account)?;
+    let mut orders: Vec<RestingOrder> = state.exchange.account_orders(&account).await
+        .into_iter()
+        .map(|(symbol, order)| RestingOrder {
+            symbol,
+            order_id: order.id.0,
+            side: order.side,
+            price: order.px_ticks,
+            quantity: order.qty,
+            hidden: order.hidden,
+        })
+        .collect();
+    orders.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    Ok(Json(AccountOrders { account, orders }))
+}
+
+/// Transaction cost analysis of account orders that arrived in a time
+/// range, optionally one account's. Defaults to everything retained.
+/// One account's orders take its token; everyone's the admin token, if
+/// configured.
+async fn get_tca(
+    Query(params): Query<TcaQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_scope(&state, &headers, params.account.as_deref())?;
+    let exchange = &state.exchange;
+    let to = params.to.unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64);
+    let from = params.from.unwrap_or_else(|| to.saturating_sub(exchange.trade_store().retention_ns()));
+    if from >= to {
+        return Err(AppError::InvalidQuery("from must be before to".to_string()));
+    }
+    let report = exchange.tca().report(exchange.trade_store(), params.account.as_deref(), from, to, |symbol| {
+        exchange.last_trade_price(symbol).flatten()
+    });
+
+    Ok(Json(report))
+}
+
+/// Kill switch: blocks the account from submitting and cancels all of its
+/// resting orders across symbols. It stays blocked until an admin unblocks it.
+/// Needs the admin token, when one is set, as unblocking does.
+async fn kill_account(
+    Path(account): Path<String>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_admin(&state, &headers)?;
+    let cancelled_orders = state.exchange.kill_account(&account).await;
+    info!("Killed account {}: {} resting orders cancelled", account, cancelled_orders);
+
+    Ok(Json(KillResponse { account, blocked: true, cancelled_orders }))
+}
+
+/// Lifts a kill. Needs `Authorization: Bearer <EXCHANGE_ADMIN_TOKEN>` when
+/// the token is set.
+async fn unblock_account(
+    Path(account): Path<String>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_admin(&state, &headers)?;
+    if !state.exchange.accounts().unblock(&account) {
+        return Err(AppError::AccountNotFound);
+    }
+    info!("Unblocked account {}", account);
+
+    Ok(Json(KillResponse { account, blocked: false, cancelled_orders: 0 }))
+}
+
+/// A symbol's tick size, lot size and quantity limits.
+async fn get_symbol_config(
+    Path(symbol): Path<String>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let config = state.exchange.symbol_config(&symbol).await
+        .ok_or(AppError::SymbolNotFound)?;
+
+    Ok(Json(config))
+}
+
+/// Replaces a symbol's trading parameters (admin token, if configured).
+/// Orders already resting are left alone; new ones and amends must fit.
+async fn set_symbol_config(
+    Path(symbol): Path<String>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(config): Json<SymbolConfig>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_admin(&state, &headers)?;
+    state.exchange.set_symbol_config(&symbol, config).await
+        .ok_or(AppError::SymbolNotFound)?
+        .map_err(AppError::InvalidQuery)?;
+    info!("Set {} config: {:?}", symbol, config);
+
+    Ok(Json(config))
+}
+
+/// Checks the admin bearer token, when one is configured.
+fn authorize_admin(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    let Some(token) = &state.admin_token else { return Ok(()) };
+    if bearer(headers) != Some(&**token) {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// Lets a request acting for `account` through with that account's token
+/// or the admin token. An account without a token of its own is open only
+/// while no admin token is set either.
+fn authorize_account(state: &AppState, headers: &HeaderMap, account: &str) -> Result<(), AppError> {
+    if let Some(token) = state.account_tokens.get(account) {
+        if bearer(headers) == Some(token.as_str()) {
+            return Ok(());
+        }
+        if state.admin_token.is_none() {
+            return Err(AppError::Unauthorized);
+        }
+    }
+    authorize_admin(state, headers)
+}
+
+/// Lets a request scoped to an account through with that account's
+/// credentials, and one reaching every account's orders only with the
+/// admin token, if configured.
+fn authorize_scope(state: &AppState, headers: &HeaderMap, account: Option<&str>) -> Result<(), AppError> {
+    match account {
+        Some(account) => authorize_account(state, headers, account),
+        None => authorize_admin(state, headers),
+    }
+}
+
+/// The token in an `Authorization: Bearer` header.
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Wash trades flagged by surveillance (admin token, if configured).
+async fn wash_trade_report(
+    Query(params): Query<SurveillanceQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    authorize_admin(&state, &headers)?;
+    Ok(Json(state.exchange.surveillance().wash_trade_report(params.account.as_deref())))
+}
+
+/// WebSocket handler for the operator alert channel (admin token, if configured).
+async fn surveillance_stream(
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    authorize_admin(&state, &headers)?;
+    Ok(ws.on_upgrade(move |socket| websocket::handle_surveillance_stream(socket, state)))
+}
+
+/// WebSocket handler for one account's private stream (the account's
+/// token or the admin token, if configured).
+async fn account_stream(
+    Path(account): Path<String>,
+    ws: WebSocketUpgrade,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    authorize_account(&state, &headers, &account)?;
+    Ok(ws.on_upgrade(move |socket| websocket::handle_account_stream(socket, account, state)))
+}
+
+/// Lists symbols mirrored from external venues.
+async fn list_consolidated(State(state): State<AppState>) -> impl IntoResponse {
+    Json(SymbolsResponse { symbols: state.feeds.list_symbols() })
+}
+
+/// Gets one symbol's book merged across external venues.
+async fn get_consolidated(
+    Path(symbol): Path<String>,
+    Query(params): Query<DepthQuery>,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let book = state.feeds.consolidated(&symbol, params.levels.unwrap_or(10))
+        .ok_or(AppError::SymbolNotFound)?;
+
+    Ok(Json(book))
+}
+
+/// The id of a symbol named in a request. Books intern their symbol as
+/// they are listed, so a name that was never interned has no book, and a
+/// request can't grow the registry.
+fn symbol_id(symbol: &str) -> Result<SymbolId, AppError> {
+    SymbolRegistry::global().lookup(symbol).ok_or(AppError::SymbolNotFound)
+}
+
 /// Application error types for HTTP responses.
 #[derive(Debug)]
 enum AppError {
     SymbolNotFound,
     OrderNotFound,
     InvalidOrderId,
+    InvalidInstrument(String),
+    InvalidQuery(String),
+    IndexNotFound,
+    AccountNotFound,
+    MarginRejected(String),
+    AccountBlocked(String),
+    Unauthorized,
+    StaleOrder { age_ns: u64, budget_ns: u64 },
+    Disabled(String),
+}
+
+impl AppError {
+    fn stale(state: &AppState, age_ns: u64) -> Self {
+        AppError::StaleOrder { age_ns, budget_ns: state.exchange.latency_budget_ns().unwrap_or(0) }
+    }
+}
+
+impl From<accounts::Rejection> for AppError {
+    fn from(rejection: accounts::Rejection) -> Self {
+        match rejection {
+            accounts::Rejection::Blocked(_) => AppError::AccountBlocked(rejection.to_string()),
+            accounts::Rejection::Margin(e) => AppError::MarginRejected(e.to_string()),
+            accounts::Rejection::TooLarge { .. } => AppError::InvalidQuery(rejection.to_string()),
+        }
+    }
+}
+
+impl From<AmendError> for AppError {
+    fn from(e: AmendError) -> Self {
+        match e {
+            AmendError::NotFound => AppError::OrderNotFound,
+            AmendError::Rejected(rejection) => rejection.into(),
+            AmendError::Refused(reason) => refused(reason),
+        }
+    }
+}
+
+impl AppError {
+    fn status_and_message(self) -> (StatusCode, String) {
+        match self {
+            AppError::SymbolNotFound => (StatusCode::NOT_FOUND, "Symbol not found".to_string()),
+            AppError::OrderNotFound => (StatusCode::NOT_FOUND, "Order not found".to_string()),
+            AppError::InvalidOrderId => (StatusCode::BAD_REQUEST, "Invalid order ID".to_string()),
+            AppError::InvalidInstrument(reason) => (StatusCode::BAD_REQUEST, reason),
+            AppError::InvalidQuery(reason) => (StatusCode::BAD_REQUEST, reason),
+            AppError::IndexNotFound => (StatusCode::NOT_FOUND, "Index not found".to_string()),
+            AppError::AccountNotFound => (StatusCode::NOT_FOUND, "Account not found".to_string()),
+            AppError::MarginRejected(reason) => (StatusCode::UNPROCESSABLE_ENTITY, reason),
+            AppError::AccountBlocked(reason) => (StatusCode::FORBIDDEN, reason),
+            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Admin or account token required".to_string()),
+            AppError::StaleOrder { age_ns, budget_ns } => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Stale order: sent {} us before arrival, budget {} us", age_ns / 1_000, budget_ns / 1_000),
+            ),
+            AppError::Disabled(reason) => (StatusCode::NOT_FOUND, reason),
+        }
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            AppError::SymbolNotFound => (StatusCode::NOT_FOUND, "Symbol not found"),
-            AppError::OrderNotFound => (StatusCode::NOT_FOUND, "Order not found"),
-            AppError::InvalidOrderId => (StatusCode::BAD_REQUEST, "Invalid order ID"),
-        };
+        let (status, message) = self.status_and_message();
 
         let body = Json(serde_json::json!({
             "error": message,
@@ -351,4 +1525,289 @@ impl IntoResponse for AppError {
 
         (status, body).into_response()
     }
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A service with nothing configured beyond `admin_token`, and a token
+    /// for the account `acct`.
+    fn app_state(admin_token: Option<&str>) -> AppState {
+        let exchange = Arc::new(Exchange::new());
+        let (trade_tx, _) = broadcast::channel(16);
+        let (latency_tx, _) = broadcast::channel(16);
+        AppState {
+            bot_driver: BotDriver::new(exchange.clone(), trade_tx.clone(), latency_tx.clone()),
+            exchange,
+            trade_broadcaster: trade_tx,
+            latency_broadcaster: latency_tx,
+            feeds: Arc::new(FeedAggregator::default()),
+            instruments: Arc::new(Instruments::new()),
+            indices: Arc::new(IndexEngine::new(Vec::new()).unwrap()),
+            perpetuals: Arc::new(Perpetuals::new(Vec::new()).unwrap()),
+            book_history: Arc::new(BookHistory::new(Duration::from_secs(1), Duration::from_secs(60))),
+            router: Arc::new(SmartRouter::new(Vec::new())),
+            shadow: None,
+            ws_connections: Arc::new(WsConnections::new(ws_connections::SlowPolicy::Conflate, 16)),
+            admin_token: admin_token.map(Arc::from),
+            account_tokens: Arc::new([("acct".to_string(), "acct-token".to_string())].into()),
+        }
+    }
+
+    /// Headers presenting `token` as a bearer token.
+    fn bearer_headers(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    /// Killing an account takes the admin token when one is set.
+    #[tokio::test]
+    async fn kill_needs_the_admin_token() {
+        let state = app_state(Some("s3cret"));
+        let mut order = Order {
+            id: OrderId(1),
+            symbol: "AAPL".into(),
+            side: orderbook::Side::Bid,
+            px_ticks: 100,
+            qty: 1,
+            ts_ns: 0,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        let refused = kill_account(Path("acct".to_string()), HeaderMap::new(), State(state.clone())).await;
+        assert_eq!(refused.err().map(|e| e.into_response().status()), Some(StatusCode::UNAUTHORIZED));
+        assert!(state.exchange.assign_order("acct", &mut order.clone()).is_ok());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(kill_account(Path("acct".to_string()), headers, State(state.clone())).await.is_ok());
+        order.id = OrderId(2);
+        assert!(state.exchange.assign_order("acct", &mut order).is_err());
+    }
+
+    /// Only the admin may pull orders across accounts; an account may pull
+    /// its own with its token.
+    #[tokio::test]
+    async fn mass_cancel_across_accounts_needs_the_admin_token() {
+        let state = app_state(Some("s3cret"));
+        state.exchange.add_symbol("AAPL".to_string()).await;
+        let cancel = |account: Option<&str>, headers| {
+            let params = MassCancelQuery { side: None, min_price: None, max_price: None, account: account.map(str::to_string) };
+            cancel_orders(Path("AAPL".to_string()), Query(params), headers, State(state.clone()))
+        };
+        let refused = cancel(None, HeaderMap::new()).await;
+        assert_eq!(refused.err().map(|e| e.into_response().status()), Some(StatusCode::UNAUTHORIZED));
+        assert!(cancel(Some("acct"), HeaderMap::new()).await.is_err());
+        assert!(cancel(Some("acct"), bearer_headers("acct-token")).await.is_ok());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(cancel(None, headers).await.is_ok());
+    }
+
+    /// Looking up, cancelling or amending an order without naming its
+    /// account takes the admin token; naming one takes that account's.
+    #[tokio::test]
+    async fn unscoped_order_requests_need_the_admin_token() {
+        let state = app_state(Some("s3cret"));
+        state.exchange.add_symbol("AAPL".to_string()).await;
+        fn status<T>(result: Result<T, AppError>) -> Option<StatusCode> {
+            result.err().map(|e| e.into_response().status())
+        }
+        let path = || Path(("AAPL".to_string(), "1".to_string()));
+        let amend = |account: Option<&str>| Json(ModifyOrderRequest { price: 100, quantity: 5, account: account.map(str::to_string) });
+        let unauthorized = Some(StatusCode::UNAUTHORIZED);
+
+        assert_eq!(status(get_order_status(path(), Query(OrderQuery { account: None }), HeaderMap::new(), State(state.clone())).await), unauthorized);
+        assert_eq!(status(cancel_order(path(), Query(OrderQuery { account: None }), HeaderMap::new(), State(state.clone())).await), unauthorized);
+        assert_eq!(status(modify_order(path(), HeaderMap::new(), State(state.clone()), amend(None)).await), unauthorized);
+
+        // Scoped, they get as far as finding nothing
+        let scoped = cancel_order(path(), Query(OrderQuery { account: Some("acct".to_string()) }), bearer_headers("acct-token"), State(state.clone())).await;
+        assert_eq!(status(scoped), Some(StatusCode::NOT_FOUND));
+        assert_eq!(status(modify_order(path(), bearer_headers("acct-token"), State(state.clone()), amend(Some("acct"))).await), Some(StatusCode::NOT_FOUND));
+    }
+
+    /// A routed order takes the best displayed prices across venues, rests
+    /// what none display at home, and skips a venue it can't reach.
+    #[tokio::test]
+    async fn routing_splits_by_displayed_liquidity() {
+        let request = |side, price, quantity| SubmitOrderRequest {
+            side,
+            price,
+            quantity,
+            account: None,
+            ts_ns: None,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+        };
+        let remote = app_state(None);
+        place_order(&remote, "AAPL".to_string(), request(orderbook::Side::Ask, 100, 4)).await.unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let venue = Router::new()
+            .route("/symbols/:symbol/depth", get(get_depth))
+            .route("/symbols/:symbol/orders", post(submit_order))
+            .with_state(remote.clone());
+        tokio::spawn(async move { axum::serve(listener, venue).await.unwrap() });
+
+        let mut state = app_state(None);
+        state.router = Arc::new(SmartRouter::new(vec![
+            router::RouteVenue { venue: "shard-b".to_string(), url },
+            // Nothing listens on the discard port
+            router::RouteVenue { venue: "down".to_string(), url: "http://127.0.0.1:9".to_string() },
+        ]));
+        place_order(&state, "AAPL".to_string(), request(orderbook::Side::Ask, 101, 5)).await.unwrap();
+
+        let summary = state.router.route(&state, "AAPL", request(orderbook::Side::Bid, 101, 12)).await.unwrap();
+        assert_eq!((summary.filled, summary.unfilled), (9, 3));
+        assert_eq!(summary.avg_px, Some(905.0 / 9.0));
+        let mut children: Vec<_> = summary.children.iter().map(|c| (c.venue.as_str(), c.qty, c.routed, c.filled, c.status.as_str())).collect();
+        children.sort();
+        assert_eq!(children, [("local", 8, 5, 5, "partial"), ("shard-b", 4, 4, 4, "filled")]);
+        assert_eq!(summary.skipped.iter().map(|s| s.venue.as_str()).collect::<Vec<_>>(), ["down"]);
+        assert_eq!(state.exchange.get_best_prices("AAPL").await, Some((Some(101), None)));
+        assert_eq!(remote.exchange.get_best_prices("AAPL").await, Some((None, None)));
+    }
+    /// Registering an instrument takes the admin token when one is set.
+    #[tokio::test]
+    async fn registering_an_instrument_needs_the_admin_token() {
+        let state = app_state(Some("s3cret"));
+        let future = || Json(serde_json::from_str::<Instrument>(r#"{"symbol": "AAPLZ6", "type": "future", "underlying": "AAPL", "expiry_ns": 1798675200000000000, "multiplier": 100}"#).unwrap());
+        let refused = register_instrument(HeaderMap::new(), State(state.clone()), future()).await;
+        assert_eq!(refused.err().map(|e| e.into_response().status()), Some(StatusCode::UNAUTHORIZED));
+        assert!(state.instruments.get("AAPLZ6").await.is_none());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(register_instrument(headers, State(state.clone()), future()).await.is_ok());
+        assert!(state.instruments.get("AAPLZ6").await.is_some());
+    }
+    /// Naming an account takes that account's token: one account can't
+    /// cancel, amend or read another's orders by naming it.
+    #[tokio::test]
+    async fn an_account_cant_act_for_another() {
+        let mut state = app_state(Some("s3cret"));
+        state.account_tokens = Arc::new([("alice", "alice-token"), ("bob", "bob-token")].map(|(a, t)| (a.to_string(), t.to_string())).into());
+        fn status<T>(result: Result<T, AppError>) -> Option<StatusCode> {
+            result.err().map(|e| e.into_response().status())
+        }
+        let mut order = Order {
+            id: OrderId(1),
+            symbol: "AAPL".into(),
+            side: orderbook::Side::Bid,
+            px_ticks: 100,
+            qty: 10,
+            ts_ns: 0,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        state.exchange.assign_order("alice", &mut order).unwrap();
+        state.exchange.submit_order("AAPL".to_string(), order).await.unwrap().unwrap();
+        let path = || Path(("AAPL".to_string(), "1".to_string()));
+        let as_alice = || Query(OrderQuery { account: Some("alice".to_string()) });
+        let unauthorized = Some(StatusCode::UNAUTHORIZED);
+
+        // Bob's token doesn't let him pass as alice
+        assert_eq!(status(cancel_order(path(), as_alice(), bearer_headers("bob-token"), State(state.clone())).await), unauthorized);
+        assert_eq!(status(cancel_order(path(), as_alice(), HeaderMap::new(), State(state.clone())).await), unauthorized);
+        let amend = Json(ModifyOrderRequest { price: 101, quantity: 10, account: Some("alice".to_string()) });
+        assert_eq!(status(modify_order(path(), bearer_headers("bob-token"), State(state.clone()), amend).await), unauthorized);
+        assert_eq!(status(get_account(Path("alice".to_string()), bearer_headers("bob-token"), State(state.clone())).await), unauthorized);
+        assert_eq!(status(get_account_orders(Path("alice".to_string()), bearer_headers("bob-token"), State(state.clone())).await), unauthorized);
+        // As himself, alice's order is as good as missing
+        let as_bob = Query(OrderQuery { account: Some("bob".to_string()) });
+        assert_eq!(status(cancel_order(path(), as_bob, bearer_headers("bob-token"), State(state.clone())).await), Some(StatusCode::NOT_FOUND));
+        assert_eq!(state.exchange.get_best_prices("AAPL").await, Some((Some(100), None)));
+
+        assert!(get_account_orders(Path("alice".to_string()), bearer_headers("alice-token"), State(state.clone())).await.is_ok());
+        assert!(cancel_order(path(), as_alice(), bearer_headers("alice-token"), State(state.clone())).await.is_ok());
+        assert_eq!(state.exchange.get_best_prices("AAPL").await, Some((None, None)));
+    }
+    /// A stop stamped longer ago than the latency budget is refused before
+    /// it is held; a fresh one is held.
+    #[tokio::test]
+    async fn a_stale_stop_is_refused() {
+        let mut state = app_state(None);
+        state.exchange = Arc::new(Exchange::new().with_latency_budget(1_000_000));
+        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        let stop = |ts_ns: u64| Json(StopOrderRequest { side: orderbook::Side::Bid, stop_price: 105, price: None, quantity: 10, account: None, ts_ns: Some(ts_ns) });
+
+        let refused = submit_stop(Path("AAPL".to_string()), State(state.clone()), stop(now_ns - 5_000_000)).await;
+        assert_eq!(refused.err().map(|e| e.into_response().status()), Some(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(submit_stop(Path("AAPL".to_string()), State(state.clone()), stop(now_ns + 1_000_000_000)).await.is_ok());
+    }
+    /// Orders on the order stream take the same terms as over HTTP: an
+    /// iceberg rests showing its slice, and terms or a book refusal fail
+    /// the batch with an error frame.
+    #[tokio::test]
+    async fn the_order_stream_checks_terms_like_http() {
+        use futures::{SinkExt, StreamExt};
+        use serde::Deserialize;
+        use tokio_tungstenite::tungstenite::Message;
+
+        // Replies, leaving out the u128 order ids rmp_serde can't read back
+        #[derive(Deserialize)]
+        #[serde(tag = "type", rename_all = "snake_case")]
+        enum Reply {
+            Result { seq: u64 },
+            Error { seq: Option<u64>, message: String },
+            Ping {},
+        }
+
+        let state = app_state(None);
+        state.exchange.set_symbol_config("AAPL", SymbolConfig { tick_size: 5, ..SymbolConfig::default() }).await.unwrap().unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/symbols/AAPL/orders/stream", listener.local_addr().unwrap());
+        let app = Router::new().route("/symbols/:symbol/orders/stream", get(order_stream)).with_state(state.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+        let order = |price, display_qty| SubmitOrderRequest {
+            side: orderbook::Side::Bid,
+            price,
+            quantity: 100,
+            account: None,
+            ts_ns: None,
+            expires_ns: None,
+            display_qty,
+            min_qty: None,
+            hidden: false,
+        };
+        let mut send = async |seq, orders| {
+            let frame = rmp_serde::to_vec_named(&OrderStreamMessage::Batch(OrderStreamRequest { seq, orders })).unwrap();
+            socket.send(Message::Binary(frame)).await.unwrap();
+            loop {
+                if let Message::Binary(reply) = socket.next().await.unwrap().unwrap() {
+                    match rmp_serde::from_slice::<Reply>(&reply).unwrap() {
+                        Reply::Ping {} => continue,
+                        reply => return reply,
+                    }
+                }
+            }
+        };
+
+        assert!(matches!(send(1, vec![order(100, Some(10))]).await, Reply::Result { seq: 1 }));
+        let depth = state.exchange.get_market_depth("AAPL", 1).await.unwrap();
+        assert_eq!((depth.bids[0].price, depth.bids[0].quantity), (100, 10));
+
+        // A fine order ahead of a bad one doesn't rest either
+        let refused = send(2, vec![order(95, None), order(90, Some(0))]).await;
+        assert!(matches!(refused, Reply::Error { seq: Some(2), message } if message.contains("display_qty")));
+        let refused = send(3, vec![order(103, None)]).await;
+        assert!(matches!(refused, Reply::Error { seq: Some(3), .. }));
+        assert_eq!(state.exchange.get_market_depth("AAPL", 5).await.unwrap().bids.len(), 1);
+    }
+}
@@ -0,0 +1,160 @@
+//! Optional NATS JetStream publisher for the sequenced event stream.
+//!
+//! A lighter-weight way to fan the exchange's events out to other
+//! processes: one NATS server instead of a broker cluster, with JetStream
+//! keeping the stream durable and replayable. Off unless
+//! `EXCHANGE_NATS_URL` is set (e.g. `nats://127.0.0.1:4222`).
+//!
+//! Every accepted submit, stop, amend, trade, and cancel is published as a
+//! protobuf `hftx.v1.SequencedEvent` on `hftx.events.<SYMBOL>`, so a
+//! consumer can follow one symbol with a subject filter or everything with
+//! `hftx.events.>`. Messages carry two headers:
+//!
+//! - `Nats-Msg-Id`: `<session>:<symbol>:<seq>`, which JetStream uses to
+//!   drop duplicates
+//! - `Hftx-Seq`: the per-symbol sequence number, for gap checks without
+//!   decoding the payload
+//!
+//! On startup the publisher creates the stream (`EXCHANGE_NATS_STREAM`,
+//! default `HFTX_EVENTS`) if it doesn't exist, plus a durable pull consumer
+//! for each entry in `EXCHANGE_NATS_DURABLES`, a comma-separated list of
+//! `name` or `name:SYMBOL`; the second form only receives that symbol.
+//! Durables keep their position across consumer restarts.
+//!
+//! Publishing runs on a background task with up to 256 acks outstanding. A
+//! failed publish is logged and skipped; the exchange never waits on NATS.
+
+use async_nats::jetstream::{self, consumer::pull, context::PublishAckFuture, stream};
+use async_nats::HeaderMap;
+use hftx_proto::{v1, Message as _};
+use orderbook::OrderKind;
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::types::{EventKind, SequencedEvent};
+
+const DEFAULT_STREAM: &str = "HFTX_EVENTS";
+const SUBJECT_PREFIX: &str = "hftx.events";
+const MAX_PENDING_ACKS: usize = 256;
+
+pub struct NatsConfig {
+    pub url: String,
+    pub stream: String,
+    /// Durable consumer names, each with an optional symbol filter
+    pub durables: Vec<(String, Option<String>)>,
+}
+
+impl NatsConfig {
+    /// The publisher's settings, if `EXCHANGE_NATS_URL` is set.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("EXCHANGE_NATS_URL").ok()?;
+        let durables = std::env::var("EXCHANGE_NATS_DURABLES").unwrap_or_default();
+        Some(Self {
+            url,
+            stream: std::env::var("EXCHANGE_NATS_STREAM").unwrap_or_else(|_| DEFAULT_STREAM.to_string()),
+            durables: durables
+                .split(',')
+                .map(str::trim)
+                .filter(|d| !d.is_empty())
+                .map(|d| match d.split_once(':') {
+                    Some((name, symbol)) => (name.to_string(), Some(symbol.to_string())),
+                    None => (d.to_string(), None),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Connects, sets up the stream and durables, and publishes until the
+/// event channel closes.
+pub async fn run(config: NatsConfig, mut events: mpsc::Receiver<SequencedEvent>) {
+    let context = match setup(&config).await {
+        Ok(context) => context,
+        Err(e) => {
+            warn!("NATS publisher disabled: {}", e);
+            return;
+        }
+    };
+    info!("Publishing sequenced events to {} (stream {})", config.url, config.stream);
+
+    // Distinguishes this run's message ids from an earlier run's, since
+    // sequence numbers restart with the process
+    let session = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+    let mut pending: VecDeque<PublishAckFuture> = VecDeque::with_capacity(MAX_PENDING_ACKS);
+
+    while let Some(event) = events.recv().await {
+        if pending.len() == MAX_PENDING_ACKS {
+            check_ack(pending.pop_front().unwrap()).await;
+        }
+        let mut headers = HeaderMap::new();
+        headers.insert("Nats-Msg-Id", format!("{}:{}:{}", session, event.symbol, event.seq).as_str());
+        headers.insert("Hftx-Seq", event.seq.to_string().as_str());
+        let subject = format!("{}.{}", SUBJECT_PREFIX, event.symbol);
+        let payload = encode(session, event);
+        match context.publish_with_headers(subject, headers, payload.into()).await {
+            Ok(ack) => pending.push_back(ack),
+            Err(e) => warn!("NATS publish failed, skipping event: {}", e),
+        }
+    }
+    for ack in pending {
+        check_ack(ack).await;
+    }
+}
+
+async fn setup(config: &NatsConfig) -> Result<jetstream::Context, String> {
+    let client = async_nats::connect(&config.url).await.map_err(|e| e.to_string())?;
+    let context = jetstream::new(client);
+    let stream = context
+        .get_or_create_stream(stream::Config {
+            name: config.stream.clone(),
+            subjects: vec![format!("{}.>", SUBJECT_PREFIX)],
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| format!("stream {}: {}", config.stream, e))?;
+    for (name, symbol) in &config.durables {
+        let consumer = pull::Config {
+            durable_name: Some(name.clone()),
+            filter_subject: symbol.as_ref().map(|s| format!("{}.{}", SUBJECT_PREFIX, s)).unwrap_or_default(),
+            ..Default::default()
+        };
+        stream
+            .get_or_create_consumer(name, consumer)
+            .await
+            .map_err(|e| format!("durable consumer {}: {}", name, e))?;
+    }
+    Ok(context)
+}
+
+async fn check_ack(ack: PublishAckFuture) {
+    if let Err(e) = ack.await {
+        warn!("NATS did not acknowledge an event: {}", e);
+    }
+}
+
+fn encode(session: u64, event: SequencedEvent) -> Vec<u8> {
+    use v1::sequenced_event::Event;
+    let kind = match &event.kind {
+        EventKind::Submit(order) => Event::Submit(order.into()),
+        EventKind::Cancel { id, side } => {
+            Event::Cancel(v1::Cancel { id: Some((*id).into()), side: v1::Side::from(*side).into() })
+        }
+        EventKind::Trade(trade) => Event::Trade(trade.into()),
+        EventKind::Stop { order, stop_px, kind } => Event::Stop(v1::Stop {
+            order: Some(order.into()),
+            stop_px: *stop_px,
+            limit: *kind == OrderKind::Limit,
+        }),
+        EventKind::Modify { id, side, px_ticks, qty, ts_ns } => Event::Modify(v1::Modify {
+            id: Some((*id).into()),
+            side: v1::Side::from(*side).into(),
+            px_ticks: *px_ticks,
+            qty: *qty,
+            ts_ns: *ts_ns,
+        }),
+    };
+    v1::SequencedEvent { session, symbol: event.symbol, seq: event.seq, ts_ns: event.ts_ns, event: Some(kind) }
+        .encode_to_vec()
+}
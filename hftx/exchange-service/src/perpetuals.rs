@@ -0,0 +1,195 @@
+//! Mark prices and funding for perpetual symbols.
+//!
+//! Perpetuals are listed in the JSON file named by `EXCHANGE_PERPETUALS`,
+//! each tied to a composite index from `EXCHANGE_INDICES`. Once a second
+//! every perpetual's mark is recomputed from its index value and book mid
+//! (`orderbook::funding::MarkPrice`), the premium is sampled toward the
+//! current funding rate, and holders are sent the new mark on their account
+//! stream. At each funding time (multiples of the interval since the epoch)
+//! the interval's rate is settled and applied to every open position at the
+//! current mark.
+//!
+//! Nothing is marked or sampled while the index has no value; a funding
+//! time reached without a mark settles the rate but moves no money.
+
+use orderbook::funding::{FundingRate, MarkPrice};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::interval;
+use tracing::info;
+
+use crate::exchange::Exchange;
+use crate::indices::IndexEngine;
+
+const NANOS: u64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PerpetualConfig {
+    pub symbol: String,
+    /// Name of the index the perpetual tracks
+    pub index: String,
+    #[serde(default = "default_funding_interval")]
+    pub funding_interval_secs: u64,
+    #[serde(default = "default_half_life")]
+    pub basis_half_life_secs: u64,
+    /// Cap on the funding rate either way
+    #[serde(default = "default_max_rate")]
+    pub max_funding_rate: f64,
+}
+
+fn default_funding_interval() -> u64 {
+    8 * 3600
+}
+
+fn default_half_life() -> u64 {
+    300
+}
+
+fn default_max_rate() -> f64 {
+    0.0075
+}
+
+/// A perpetual's latest state, as served by `GET /perpetuals`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PerpetualStatus {
+    pub symbol: String,
+    pub index: String,
+    pub index_value: Option<f64>,
+    pub mark: Option<f64>,
+    /// `mark - index` the mark is carrying
+    pub basis: f64,
+    /// Rate if the current interval settled now
+    pub predicted_rate: f64,
+    pub last_funding_rate: Option<f64>,
+    pub last_funding_ns: Option<u64>,
+    pub next_funding_ns: u64,
+    pub ts_ns: u64,
+}
+
+struct Perpetual {
+    config: PerpetualConfig,
+    mark: MarkPrice,
+    funding: FundingRate,
+    status: PerpetualStatus,
+}
+
+pub struct Perpetuals {
+    perpetuals: Mutex<BTreeMap<String, Perpetual>>,
+}
+
+impl Perpetuals {
+    pub fn new(configs: Vec<PerpetualConfig>) -> Result<Self, String> {
+        let now_ns = now_ns();
+        let mut perpetuals = BTreeMap::new();
+        for config in configs {
+            if config.funding_interval_secs == 0 || config.basis_half_life_secs == 0 {
+                return Err(format!("{}: intervals must be positive", config.symbol));
+            }
+            let status = PerpetualStatus {
+                symbol: config.symbol.clone(),
+                index: config.index.clone(),
+                index_value: None,
+                mark: None,
+                basis: 0.0,
+                predicted_rate: 0.0,
+                last_funding_rate: None,
+                last_funding_ns: None,
+                next_funding_ns: next_boundary(now_ns, config.funding_interval_secs * NANOS),
+                ts_ns: now_ns,
+            };
+            let perpetual = Perpetual {
+                mark: MarkPrice::new(config.basis_half_life_secs * NANOS),
+                funding: FundingRate::new(config.max_funding_rate),
+                config,
+                status,
+            };
+            let symbol = perpetual.config.symbol.clone();
+            if perpetuals.insert(symbol.clone(), perpetual).is_some() {
+                return Err(format!("{} is listed twice", symbol));
+            }
+        }
+        Ok(Self { perpetuals: Mutex::new(perpetuals) })
+    }
+
+    /// Reads perpetual definitions from a JSON file.
+    pub fn load(path: &str) -> Result<Vec<PerpetualConfig>, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.perpetuals.lock().unwrap().is_empty()
+    }
+
+    pub fn list(&self) -> Vec<PerpetualStatus> {
+        self.perpetuals.lock().unwrap().values().map(|p| p.status.clone()).collect()
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<PerpetualStatus> {
+        self.perpetuals.lock().unwrap().get(symbol).map(|p| p.status.clone())
+    }
+
+    /// Re-marks every perpetual and settles any funding that is due.
+    async fn update(&self, exchange: &Exchange, indices: &IndexEngine) {
+        let symbols: Vec<String> = self.perpetuals.lock().unwrap().keys().cloned().collect();
+        let mut mids = BTreeMap::new();
+        for symbol in symbols {
+            let mid = match exchange.get_best_prices(&symbol).await {
+                Some((Some(bid), Some(ask))) => Some((bid as f64 + ask as f64) / 2.0),
+                _ => None,
+            };
+            mids.insert(symbol, mid);
+        }
+
+        let now_ns = now_ns();
+        let accounts = exchange.accounts();
+        let mut perpetuals = self.perpetuals.lock().unwrap();
+        for (symbol, perpetual) in perpetuals.iter_mut() {
+            let status = &mut perpetual.status;
+            status.ts_ns = now_ns;
+            status.index_value = indices.latest(&perpetual.config.index).and_then(|v| v.value);
+            if let Some(index) = status.index_value {
+                let mid = mids.get(symbol).copied().flatten();
+                let mark = perpetual.mark.update(now_ns, index, mid);
+                perpetual.funding.sample(index, mark);
+                status.mark = Some(mark);
+                status.basis = perpetual.mark.basis();
+                status.predicted_rate = perpetual.funding.predicted();
+                accounts.publish_mark(symbol, index, mark, status.predicted_rate);
+            }
+
+            if now_ns >= status.next_funding_ns {
+                let rate = perpetual.funding.settle();
+                if let Some(mark) = status.mark {
+                    accounts.apply_funding(symbol, mark, rate);
+                }
+                info!("Funding {} at {:.6} (mark {:?})", symbol, rate, status.mark);
+                status.last_funding_rate = Some(rate);
+                status.last_funding_ns = Some(status.next_funding_ns);
+                status.next_funding_ns =
+                    next_boundary(now_ns, perpetual.config.funding_interval_secs * NANOS);
+                status.predicted_rate = 0.0;
+            }
+        }
+    }
+}
+
+/// First multiple of `interval_ns` strictly after `now_ns`.
+fn next_boundary(now_ns: u64, interval_ns: u64) -> u64 {
+    (now_ns / interval_ns + 1) * interval_ns
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+}
+
+/// Marks perpetuals once a second and settles funding, forever.
+pub async fn run(perpetuals: Arc<Perpetuals>, exchange: Arc<Exchange>, indices: Arc<IndexEngine>) {
+    let mut ticker = interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        perpetuals.update(&exchange, &indices).await;
+    }
+}
@@ -0,0 +1,289 @@
+//! Smart order routing across exchange-service shards and venues.
+//!
+//! `POST /route/:symbol/orders` takes an ordinary order and splits it
+//! between this exchange (venue `local`) and the venues listed in the JSON
+//! file named by `EXCHANGE_ROUTES`:
+//!
+//! ```json
+//! [{"venue": "shard-b", "url": "http://10.0.0.2:8080"}]
+//! ```
+//!
+//! A venue must take orders on the exchange-service REST API (plain http),
+//! in the same ticks and lots as this one. Its displayed liquidity comes
+//! from the `EXCHANGE_FEEDS` mirror of the same venue name when there is
+//! one, otherwise from its `/symbols/:symbol/depth`. The books are split
+//! with `orderbook::routing::split`, and one child order per venue is sent
+//! at the parent's limit price, all at once. Quantity no venue displays
+//! goes to the local book to rest, or to the first venue listing the
+//! symbol if this exchange doesn't. A venue that can't be reached is
+//! skipped and reported. Children carry the parent's `account`; each venue
+//! checks margin against its own positions.
+
+use futures::future::join_all;
+use orderbook::routing::split;
+use orderbook::Side;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::types::{MarketDepth, PriceLevel, SubmitOrderRequest, SubmitOrderResponse};
+use crate::{place_order, AppError, AppState};
+
+/// Levels per venue the split looks at
+const DEPTH: usize = 50;
+/// Limit on each depth fetch and child order to a remote venue
+const TIMEOUT: Duration = Duration::from_secs(2);
+/// Name the routing report gives this exchange
+const LOCAL: &str = "local";
+
+/// A venue orders can be routed to.
+#[derive(Debug, Deserialize)]
+pub struct RouteVenue {
+    pub venue: String,
+    /// Base URL of its REST API
+    pub url: String,
+}
+
+impl RouteVenue {
+    /// Reads the venue list from a JSON file.
+    pub fn load(path: &str) -> Result<Vec<Self>, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+/// One child order's outcome.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChildFill {
+    pub venue: String,
+    /// Quantity sent
+    pub qty: i64,
+    /// Part of `qty` sized on the venue's displayed liquidity; the rest is
+    /// the remainder sent to rest
+    pub routed: i64,
+    /// Absent if the venue rejected the order
+    pub order_id: Option<u128>,
+    pub filled: i64,
+    pub avg_px: Option<f64>,
+    /// `filled`, `partial`, `resting`, or `rejected`
+    pub status: String,
+    pub error: Option<String>,
+}
+
+/// A venue left out of the split.
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedVenue {
+    pub venue: String,
+    pub error: String,
+}
+
+/// Served by `POST /route/:symbol/orders`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteSummary {
+    pub symbol: String,
+    pub side: Side,
+    pub price: i64,
+    pub quantity: i64,
+    pub filled: i64,
+    pub avg_px: Option<f64>,
+    /// Quantity resting or rejected across the children
+    pub unfilled: i64,
+    pub children: Vec<ChildFill>,
+    pub skipped: Vec<SkippedVenue>,
+    pub timestamp: u64,
+}
+
+/// What a depth lookup found on one venue.
+enum Displayed {
+    Levels(Vec<(i64, i64)>),
+    NotListed,
+    Failed(String),
+}
+
+pub struct SmartRouter {
+    venues: Vec<RouteVenue>,
+    client: reqwest::Client,
+}
+
+impl SmartRouter {
+    pub fn new(venues: Vec<RouteVenue>) -> Self {
+        let client = reqwest::Client::builder().timeout(TIMEOUT).build().unwrap_or_default();
+        Self { venues, client }
+    }
+
+    /// Splits `request` across the venues, sends the children, and sums
+    /// up their fills. Fails only if no venue lists `symbol`.
+    pub(crate) async fn route(&self, state: &AppState, symbol: &str, request: SubmitOrderRequest) -> Result<RouteSummary, AppError> {
+        let received_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        state.exchange.check_fresh(request.ts_ns, received_ns).map_err(|age_ns| AppError::stale(state, age_ns))?;
+        // A buy takes asks, a sell takes bids
+        let take = match request.side {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        };
+
+        // Venue 0 is this exchange, then the configured ones in order
+        let local = match state.exchange.get_market_depth(symbol, DEPTH).await {
+            Some(depth) => Displayed::Levels(levels(&depth, take)),
+            None => Displayed::NotListed,
+        };
+        let remote = join_all(self.venues.iter().map(|venue| self.displayed(state, venue, symbol, take))).await;
+        let names: Vec<&str> = std::iter::once(LOCAL).chain(self.venues.iter().map(|v| v.venue.as_str())).collect();
+        let mut books = Vec::with_capacity(names.len());
+        let mut listed = Vec::with_capacity(names.len());
+        let mut skipped = Vec::new();
+        for (name, displayed) in names.iter().zip(std::iter::once(local).chain(remote)) {
+            match displayed {
+                Displayed::Levels(levels) => {
+                    books.push(levels);
+                    listed.push(true);
+                }
+                Displayed::NotListed => {
+                    books.push(Vec::new());
+                    listed.push(false);
+                }
+                Displayed::Failed(error) => {
+                    books.push(Vec::new());
+                    listed.push(false);
+                    skipped.push(SkippedVenue { venue: name.to_string(), error });
+                }
+            }
+        }
+        let home = listed.iter().position(|&l| l).ok_or(AppError::SymbolNotFound)?;
+
+        let plan = split(request.side, request.price, request.quantity, &books);
+        // (venue, qty to send, qty routed to its displayed liquidity)
+        let mut children: Vec<(usize, i64, i64)> = plan.allocations.iter().map(|a| (a.venue, a.qty, a.qty)).collect();
+        if plan.unrouted > 0 {
+            match children.iter_mut().find(|c| c.0 == home) {
+                Some(child) => child.1 += plan.unrouted,
+                None => children.push((home, plan.unrouted, 0)),
+            }
+        }
+
+        let sent = join_all(children.iter().map(|&(venue, qty, _)| {
+            let child = SubmitOrderRequest {
+                side: request.side,
+                price: request.price,
+                quantity: qty,
+                account: request.account.clone(),
+                ts_ns: None,
+                expires_ns: request.expires_ns,
+                display_qty: request.display_qty,
+                min_qty: request.min_qty.map(|min_qty| min_qty.min(qty)),
+                hidden: request.hidden,
+            };
+            async move {
+                match venue {
+                    0 => place_order(state, symbol.to_string(), child).await.map_err(|e| e.status_and_message().1),
+                    v => self.send(&self.venues[v - 1], symbol, &child).await,
+                }
+            }
+        }))
+        .await;
+
+        let mut summary = RouteSummary {
+            symbol: symbol.to_string(),
+            side: request.side,
+            price: request.price,
+            quantity: request.quantity,
+            filled: 0,
+            avg_px: None,
+            unfilled: 0,
+            children: Vec::with_capacity(children.len()),
+            skipped,
+            timestamp: received_ns,
+        };
+        let mut notional = 0.0;
+        for (&(venue, qty, routed), result) in children.iter().zip(sent) {
+            let mut child = ChildFill {
+                venue: names[venue].to_string(),
+                qty,
+                routed,
+                order_id: None,
+                filled: 0,
+                avg_px: None,
+                status: "rejected".to_string(),
+                error: None,
+            };
+            match result {
+                Ok(response) => {
+                    // Trades of a fresh order all have it as the taker
+                    let (filled, paid) =
+                        response.trades.iter().fold((0, 0.0), |(q, n), t| (q + t.qty, n + t.px_ticks as f64 * t.qty as f64));
+                    child.order_id = Some(response.order_id);
+                    child.filled = filled;
+                    child.avg_px = (filled > 0).then(|| paid / filled as f64);
+                    child.status = match filled {
+                        0 => "resting",
+                        f if f < qty => "partial",
+                        _ => "filled",
+                    }
+                    .to_string();
+                    notional += paid;
+                }
+                Err(error) => child.error = Some(error),
+            }
+            summary.filled += child.filled;
+            summary.children.push(child);
+        }
+        summary.avg_px = (summary.filled > 0).then(|| notional / summary.filled as f64);
+        summary.unfilled = request.quantity - summary.filled;
+        Ok(summary)
+    }
+
+    async fn displayed(&self, state: &AppState, venue: &RouteVenue, symbol: &str, take: Side) -> Displayed {
+        if let Some(levels) = state.feeds.venue_levels(symbol, &venue.venue, take, DEPTH) {
+            return Displayed::Levels(levels);
+        }
+        let url = format!("{}/symbols/{}/depth?levels={}", venue.url.trim_end_matches('/'), symbol, DEPTH);
+        let response = match self.client.get(&url).send().await {
+            Ok(response) => response,
+            Err(e) => return Displayed::Failed(e.to_string()),
+        };
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Displayed::NotListed;
+        }
+        if !response.status().is_success() {
+            return Displayed::Failed(format!("depth returned {}", response.status()));
+        }
+        match response.bytes().await.map_err(|e| e.to_string()).and_then(|body| {
+            serde_json::from_slice::<MarketDepth>(&body).map_err(|e| e.to_string())
+        }) {
+            Ok(depth) => Displayed::Levels(levels(&depth, take)),
+            Err(e) => Displayed::Failed(e),
+        }
+    }
+
+    /// Sends one child order to a remote venue.
+    async fn send(&self, venue: &RouteVenue, symbol: &str, child: &SubmitOrderRequest) -> Result<SubmitOrderResponse, String> {
+        let url = format!("{}/symbols/{}/orders", venue.url.trim_end_matches('/'), symbol);
+        let body = serde_json::to_vec(child).map_err(|e| e.to_string())?;
+        let response = self
+            .client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let status = response.status();
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        if !status.is_success() {
+            // The venue's `{"error": ..}` body, if it sent one
+            let reason = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string));
+            return Err(reason.unwrap_or_else(|| format!("order returned {}", status)));
+        }
+        serde_json::from_slice(&body).map_err(|e| e.to_string())
+    }
+}
+
+/// `side` of a depth snapshot as `(px, qty)` levels, best first.
+fn levels(depth: &MarketDepth, side: Side) -> Vec<(i64, i64)> {
+    let levels: &[PriceLevel] = match side {
+        Side::Bid => &depth.bids,
+        Side::Ask => &depth.asks,
+    };
+    levels.iter().map(|l| (l.price, l.quantity)).collect()
+}
@@ -0,0 +1,112 @@
+//! Shadow-matching canary mode.
+//!
+//! With `EXCHANGE_SHADOW` naming a candidate matcher (see
+//! `orderbook::shadow::CANDIDATES`), every symbol's sequenced event stream
+//! is replayed on a dedicated thread into an `orderbook::shadow::Shadow`,
+//! which runs the candidate beside a replica of the production book and
+//! diffs their trades, cancel results, and top `DEPTH` levels after each
+//! event. Each divergence is logged and counted, and the latest are kept
+//! for `GET /shadow`. Matching never waits on the shadow.
+//!
+//! The stream drops events when the shadow falls behind (a slow candidate
+//! on a busy book). A symbol whose sequence skips is started over from an
+//! empty book on both sides, so the comparison stays valid but only covers
+//! orders from then on. A delisted and relisted symbol starts over the same
+//! way.
+
+use orderbook::shadow::{Divergence, Shadow};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::types::{EventKind, SequencedEvent};
+
+/// Levels per side compared after each event
+const DEPTH: usize = 10;
+/// Divergences kept for the report
+const RECENT: usize = 100;
+
+/// One divergence and where it happened.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShadowDivergence {
+    pub symbol: String,
+    pub seq: u64,
+    pub ts_ns: u64,
+    pub divergence: Divergence,
+}
+
+/// Served by `GET /shadow`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShadowReport {
+    pub candidate: String,
+    pub events: u64,
+    /// Times a symbol started over after missed events
+    pub resets: u64,
+    pub divergences: u64,
+    /// Newest first
+    pub recent: VecDeque<ShadowDivergence>,
+}
+
+pub struct ShadowMonitor {
+    make: fn() -> Box<dyn orderbook::shadow::Matcher>,
+    report: Mutex<ShadowReport>,
+}
+
+impl ShadowMonitor {
+    /// A monitor for the named candidate, or `None` if there's no such one.
+    pub fn new(candidate: &str) -> Option<Self> {
+        let make = orderbook::shadow::candidate(candidate)?;
+        Some(Self { make, report: Mutex::new(ShadowReport { candidate: candidate.to_string(), ..ShadowReport::default() }) })
+    }
+
+    pub fn report(&self) -> ShadowReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Replays `events` until the channel closes; blocks, so run it on its
+    /// own thread.
+    pub fn run(&self, mut events: mpsc::Receiver<SequencedEvent>) {
+        info!("Shadow matching against {}", self.report.lock().unwrap().candidate);
+        // Per symbol: the shadow and the last sequence number applied
+        let mut shadows: HashMap<String, (Shadow, u64)> = HashMap::new();
+        while let Some(event) = events.blocking_recv() {
+            let (shadow, last_seq) =
+                shadows.entry(event.symbol.clone()).or_insert_with(|| (Shadow::new(self.make, DEPTH), 0));
+            let skipped = event.seq != *last_seq + 1;
+            if skipped {
+                shadow.reset();
+            }
+            *last_seq = event.seq;
+            let divergences = match event.kind {
+                EventKind::Submit(order) => shadow.submit(order),
+                EventKind::Cancel { id, side } => shadow.cancel(id, side),
+                EventKind::Modify { id, px_ticks, qty, ts_ns, .. } => shadow.modify(id, px_ticks, qty, ts_ns as u128),
+                // The replica makes its own
+                EventKind::Trade(_) => Vec::new(),
+                // Candidate backends don't hold stops; neither replica sees
+                // them or the trades they make
+                EventKind::Stop { .. } => Vec::new(),
+            };
+
+            let mut report = self.report.lock().unwrap();
+            report.events += 1;
+            // The first event of a symbol has nothing to have missed
+            if skipped && event.seq > 1 {
+                report.resets += 1;
+            }
+            for divergence in divergences {
+                warn!("Shadow divergence on {} at seq {}: {:?}", event.symbol, event.seq, divergence);
+                report.divergences += 1;
+                report.recent.push_front(ShadowDivergence {
+                    symbol: event.symbol.clone(),
+                    seq: event.seq,
+                    ts_ns: event.ts_ns,
+                    divergence,
+                });
+                report.recent.truncate(RECENT);
+            }
+        }
+    }
+}
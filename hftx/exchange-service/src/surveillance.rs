@@ -0,0 +1,411 @@
+//! Trade surveillance: wash trades and spoofing.
+//!
+//! A trade is a wash trade when its maker and taker orders belong to the
+//! same account, or to two accounts linked in the `EXCHANGE_SURVEILLANCE`
+//! file (e.g. `{"linked_accounts": [["alice", "alice-hedge"]]}`). Flagged
+//! trades still execute; they are counted per account and kept in a bounded
+//! recent list for `GET /surveillance/wash-trades`.
+//!
+//! Spoofing and layering are scored from the order flow as it is matched.
+//! An order is a candidate when it is large (`min_qty`) and rests at least
+//! `min_away_ticks` behind its side's touch, where it is unlikely to trade.
+//! A candidate that fills is dropped; one cancelled within
+//! `max_lifetime_ms` of placement counts against its account, symbol, and
+//! side. When `alert_orders` of those land within `window_secs`, a spoofing
+//! alert is raised and the count starts over. The alert's `score` is the
+//! cancelled quantity in units of `min_qty`, and `price_levels` the number
+//! of distinct prices used, which is what separates layering (many levels)
+//! from repeated spoofing at one price.
+//!
+//! Only orders placed with an `account` can be attributed, so anonymous
+//! flow is never flagged. Every alert goes out on the operator channel
+//! (`/surveillance/alerts/stream`) and to the audit store when one is
+//! configured.
+
+use orderbook::{Order, OrderBook, OrderId, Side, Trade};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::audit::{AuditRecord, AuditStore};
+
+/// Flagged trades kept for the report
+const RECENT: usize = 1000;
+/// Alerts buffered per operator subscriber
+const ALERT_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SurveillanceConfig {
+    /// Groups of accounts under common control
+    #[serde(default)]
+    pub linked_accounts: Vec<Vec<String>>,
+    #[serde(default)]
+    pub spoofing: SpoofingConfig,
+}
+
+/// Thresholds for the spoofing pass; missing fields take the defaults.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SpoofingConfig {
+    /// Smallest order worth watching
+    pub min_qty: i64,
+    /// How far behind the touch an order must rest, in ticks
+    pub min_away_ticks: i64,
+    /// Cancels slower than this don't count
+    pub max_lifetime_ms: u64,
+    pub window_secs: u64,
+    /// Quick cancels within the window that raise an alert
+    pub alert_orders: usize,
+}
+
+impl Default for SpoofingConfig {
+    fn default() -> Self {
+        Self { min_qty: 100, min_away_ticks: 1, max_lifetime_ms: 2000, window_secs: 60, alert_orders: 5 }
+    }
+}
+
+impl SurveillanceConfig {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WashTrade {
+    pub symbol: String,
+    pub px_ticks: i64,
+    pub qty: i64,
+    pub maker_order_id: u128,
+    pub taker_order_id: u128,
+    pub maker_account: String,
+    pub taker_account: String,
+    /// Different accounts matched through a link
+    pub linked: bool,
+    pub ts_ns: u64,
+}
+
+/// Large orders placed away from the touch and pulled quickly, again and
+/// again, by one account on one side of a symbol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpoofingAlert {
+    pub account: String,
+    pub symbol: String,
+    pub side: Side,
+    /// Quick cancels in the window
+    pub orders: usize,
+    pub cancelled_qty: i64,
+    /// Distinct prices among them
+    pub price_levels: usize,
+    pub mean_away_ticks: f64,
+    pub mean_lifetime_ms: f64,
+    /// `cancelled_qty / min_qty`
+    pub score: f64,
+    pub window_secs: u64,
+    pub ts_ns: u64,
+}
+
+/// What the operator channel carries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "alert", rename_all = "snake_case")]
+pub enum SurveillanceAlert {
+    WashTrade(WashTrade),
+    Spoofing(SpoofingAlert),
+}
+
+/// Wash trades an account took part in, on either side.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AccountWashStats {
+    pub account: String,
+    pub wash_trades: u64,
+    pub qty: i64,
+    pub last_ts_ns: u64,
+}
+
+/// Served by `GET /surveillance/wash-trades`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WashTradeReport {
+    pub wash_trades: u64,
+    pub qty: i64,
+    pub accounts: Vec<AccountWashStats>,
+    /// Newest first
+    pub recent: Vec<WashTrade>,
+}
+
+#[derive(Default)]
+struct Flagged {
+    total: u64,
+    qty: i64,
+    accounts: BTreeMap<String, AccountWashStats>,
+    recent: VecDeque<WashTrade>,
+}
+
+/// A resting order that looks like it isn't meant to trade.
+struct Candidate {
+    account: String,
+    symbol: String,
+    side: Side,
+    px_ticks: i64,
+    qty: i64,
+    away_ticks: i64,
+    placed_ns: u64,
+}
+
+/// A candidate cancelled soon after placement.
+struct Pulled {
+    px_ticks: i64,
+    qty: i64,
+    away_ticks: i64,
+    lifetime_ns: u64,
+    ts_ns: u64,
+}
+
+pub struct Surveillance {
+    /// Account to the index of its link group
+    groups: HashMap<String, usize>,
+    flagged: Mutex<Flagged>,
+    spoofing: SpoofingConfig,
+    candidates: Mutex<HashMap<OrderId, Candidate>>,
+    /// Recent quick cancels per account, symbol, and side
+    pulled: Mutex<HashMap<(String, String, Side), VecDeque<Pulled>>>,
+    alerts: broadcast::Sender<SurveillanceAlert>,
+    audit: Option<AuditStore>,
+}
+
+impl Default for Surveillance {
+    fn default() -> Self {
+        Self {
+            groups: HashMap::new(),
+            flagged: Mutex::default(),
+            spoofing: SpoofingConfig::default(),
+            candidates: Mutex::default(),
+            pulled: Mutex::default(),
+            alerts: broadcast::channel(ALERT_BUFFER).0,
+            audit: None,
+        }
+    }
+}
+
+impl Surveillance {
+    /// Fails if an account appears in two link groups or a spoofing
+    /// threshold is zero.
+    pub fn new(config: SurveillanceConfig) -> Result<Self, String> {
+        let spoofing = config.spoofing;
+        if spoofing.min_qty <= 0 || spoofing.window_secs == 0 || spoofing.alert_orders == 0 {
+            return Err("spoofing min_qty, window_secs and alert_orders must be positive".to_string());
+        }
+        let mut groups = HashMap::new();
+        for (i, group) in config.linked_accounts.into_iter().enumerate() {
+            for account in group {
+                if let Some(other) = groups.insert(account.clone(), i) {
+                    if other != i {
+                        return Err(format!("{} is in more than one linked group", account));
+                    }
+                }
+            }
+        }
+        Ok(Self { groups, spoofing, ..Self::default() })
+    }
+
+    /// Also writes every wash trade to `audit`.
+    pub fn with_audit(mut self, audit: AuditStore) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Operator alerts as they are raised.
+    pub fn subscribe(&self) -> broadcast::Receiver<SurveillanceAlert> {
+        self.alerts.subscribe()
+    }
+
+    fn linked(&self, a: &str, b: &str) -> bool {
+        matches!((self.groups.get(a), self.groups.get(b)), (Some(x), Some(y)) if x == y)
+    }
+
+    /// Flags the wash trades among `trades`. `owner` names an order's
+    /// account, so call this before fills retire the orders.
+    /// Also drops spoofing candidates that traded.
+    pub(crate) fn check(&self, trades: &[Trade], owner: impl Fn(OrderId) -> Option<String>) {
+        {
+            let mut candidates = self.candidates.lock().unwrap();
+            if !candidates.is_empty() {
+                for trade in trades {
+                    candidates.remove(&trade.maker);
+                }
+            }
+        }
+        for trade in trades {
+            let (Some(maker_account), Some(taker_account)) = (owner(trade.maker), owner(trade.taker)) else {
+                continue;
+            };
+            let linked = maker_account != taker_account;
+            if linked && !self.linked(&maker_account, &taker_account) {
+                continue;
+            }
+            warn!(
+                "Wash trade on {}: {} at {} between {} and {}",
+                trade.symbol, trade.qty, trade.px_ticks, maker_account, taker_account
+            );
+            self.flag(WashTrade {
+                symbol: trade.symbol.to_string(),
+                px_ticks: trade.px_ticks,
+                qty: trade.qty,
+                maker_order_id: trade.maker.0,
+                taker_order_id: trade.taker.0,
+                maker_account,
+                taker_account,
+                linked,
+                ts_ns: trade.ts_ns as u64,
+            });
+        }
+    }
+
+    fn flag(&self, wash: WashTrade) {
+        {
+            let mut flagged = self.flagged.lock().unwrap();
+            flagged.total += 1;
+            flagged.qty = flagged.qty.saturating_add(wash.qty);
+            let mut credit = |account: &str| {
+                let stats = flagged.accounts.entry(account.to_string()).or_insert_with(|| AccountWashStats {
+                    account: account.to_string(),
+                    ..AccountWashStats::default()
+                });
+                stats.wash_trades += 1;
+                stats.qty = stats.qty.saturating_add(wash.qty);
+                stats.last_ts_ns = wash.ts_ns;
+            };
+            credit(&wash.maker_account);
+            if wash.linked {
+                credit(&wash.taker_account);
+            }
+            if flagged.recent.len() == RECENT {
+                flagged.recent.pop_back();
+            }
+            flagged.recent.push_front(wash.clone());
+        }
+        if let Some(audit) = &self.audit {
+            audit.record(AuditRecord::WashTrade(wash.clone()));
+        }
+        let _ = self.alerts.send(SurveillanceAlert::WashTrade(wash));
+    }
+
+    /// Watches `order` if it is large and rests behind its side's touch.
+    /// Call under the book's write lock, before matching it.
+    pub(crate) fn on_submit(&self, book: &mut OrderBook, order: &Order, owner: impl FnOnce() -> Option<String>) {
+        if order.qty < self.spoofing.min_qty {
+            return;
+        }
+        let away_ticks = match order.side {
+            Side::Bid => book.bids.best_live_price().map(|touch| touch - order.px_ticks),
+            Side::Ask => book.asks.best_live_price().map(|touch| order.px_ticks - touch),
+        };
+        let Some(away_ticks) = away_ticks else { return };
+        if away_ticks < self.spoofing.min_away_ticks {
+            return;
+        }
+        let Some(account) = owner() else { return };
+        self.candidates.lock().unwrap().insert(
+            order.id,
+            Candidate {
+                account,
+                symbol: order.symbol.to_string(),
+                side: order.side,
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                away_ticks,
+                placed_ns: order.ts_ns as u64,
+            },
+        );
+    }
+
+    /// Counts a cancelled candidate pulled within the lifetime, raising an
+    /// alert once its account, symbol, and side reach `alert_orders`.
+    pub(crate) fn on_cancel(&self, order_id: OrderId) {
+        let Some(candidate) = self.candidates.lock().unwrap().remove(&order_id) else { return };
+        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+        let lifetime_ns = now_ns.saturating_sub(candidate.placed_ns);
+        if lifetime_ns > self.spoofing.max_lifetime_ms * 1_000_000 {
+            return;
+        }
+
+        let alert = {
+            let mut pulled = self.pulled.lock().unwrap();
+            let key = (candidate.account, candidate.symbol, candidate.side);
+            let recent = pulled.entry(key.clone()).or_default();
+            recent.push_back(Pulled {
+                px_ticks: candidate.px_ticks,
+                qty: candidate.qty,
+                away_ticks: candidate.away_ticks,
+                lifetime_ns,
+                ts_ns: now_ns,
+            });
+            let window_ns = self.spoofing.window_secs * 1_000_000_000;
+            while recent.front().is_some_and(|p| now_ns - p.ts_ns > window_ns) {
+                recent.pop_front();
+            }
+            if recent.len() < self.spoofing.alert_orders {
+                return;
+            }
+            let orders = recent.len();
+            let cancelled_qty: i64 = recent.iter().map(|p| p.qty).sum();
+            let (account, symbol, side) = key;
+            let alert = SpoofingAlert {
+                account,
+                symbol,
+                side,
+                orders,
+                cancelled_qty,
+                price_levels: recent.iter().map(|p| p.px_ticks).collect::<BTreeSet<_>>().len(),
+                mean_away_ticks: recent.iter().map(|p| p.away_ticks as f64).sum::<f64>() / orders as f64,
+                mean_lifetime_ms: recent.iter().map(|p| p.lifetime_ns as f64).sum::<f64>() / orders as f64 / 1e6,
+                score: cancelled_qty as f64 / self.spoofing.min_qty as f64,
+                window_secs: self.spoofing.window_secs,
+                ts_ns: now_ns,
+            };
+            recent.clear();
+            alert
+        };
+        warn!(
+            "Possible spoofing by {} on {} {:?}: {} orders ({} qty over {} levels) pulled within {}s",
+            alert.account, alert.symbol, alert.side, alert.orders, alert.cancelled_qty, alert.price_levels, alert.window_secs
+        );
+        if let Some(audit) = &self.audit {
+            audit.record(AuditRecord::Spoofing(alert.clone()));
+        }
+        let _ = self.alerts.send(SurveillanceAlert::Spoofing(alert));
+    }
+
+    /// Drops a delisted symbol's candidates.
+    pub(crate) fn forget_symbol(&self, symbol: &str) {
+        self.candidates.lock().unwrap().retain(|_, c| c.symbol != symbol);
+        self.pulled.lock().unwrap().retain(|(_, s, _), _| s != symbol);
+    }
+
+    /// Totals and recent wash trades, narrowed to one account if given.
+    pub fn wash_trade_report(&self, account: Option<&str>) -> WashTradeReport {
+        let flagged = self.flagged.lock().unwrap();
+        let Some(account) = account else {
+            return WashTradeReport {
+                wash_trades: flagged.total,
+                qty: flagged.qty,
+                accounts: flagged.accounts.values().cloned().collect(),
+                recent: flagged.recent.iter().cloned().collect(),
+            };
+        };
+        let stats = flagged.accounts.get(account).cloned();
+        WashTradeReport {
+            wash_trades: stats.as_ref().map_or(0, |s| s.wash_trades),
+            qty: stats.as_ref().map_or(0, |s| s.qty),
+            accounts: stats.into_iter().collect(),
+            recent: flagged
+                .recent
+                .iter()
+                .filter(|w| w.maker_account == account || w.taker_account == account)
+                .cloned()
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Transaction cost analysis of account orders.
+//!
+//! Every order placed with an `account` is tracked from arrival: the
+//! arrival price is the book's mid just before the order is matched (the
+//! last trade if the book is one-sided), then each fill is added as it
+//! happens. An order ends when it fills completely or is cancelled, and
+//! the mid at that moment prices its unfilled part. Orders still working
+//! are priced at the last trade when a report is asked for.
+//!
+//! Reports add the market benchmarks from the trade store (VWAP and volume
+//! over each order's life) and compute `orderbook::tca` metrics per order,
+//! then roll them up per account. The orders kept are those that arrived
+//! within the trade store's retention.
+
+use dashmap::DashMap;
+use orderbook::tca::{Execution, TcaMetrics};
+use orderbook::{Order, OrderBook, OrderId, Side, Trade};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::trade_store::TradeStore;
+
+struct TrackedOrder {
+    account: String,
+    symbol: String,
+    side: Side,
+    qty: i64,
+    filled: i64,
+    filled_notional: f64,
+    arrival_ns: u64,
+    arrival_px: Option<f64>,
+    end_ns: Option<u64>,
+    end_px: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderTca {
+    pub order_id: u128,
+    pub account: String,
+    pub symbol: String,
+    pub side: Side,
+    pub qty: i64,
+    pub filled: i64,
+    pub arrival_ns: u64,
+    /// `None` while the order is working
+    pub end_ns: Option<u64>,
+    pub arrival_px: Option<f64>,
+    pub end_px: Option<f64>,
+    pub interval_vwap: Option<f64>,
+    pub market_volume: i64,
+    /// Absent when there was no arrival price to measure against
+    #[serde(flatten)]
+    pub metrics: Option<TcaMetrics>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AccountTca {
+    pub account: String,
+    pub orders: usize,
+    pub qty: i64,
+    pub filled: i64,
+    /// `arrival * qty` summed over measured orders
+    pub paper_notional: f64,
+    pub execution_cost: f64,
+    pub opportunity_cost: f64,
+    pub implementation_shortfall: f64,
+    pub shortfall_bps: Option<f64>,
+    /// Fill-weighted mean of the orders' slippage
+    pub slippage_bps: Option<f64>,
+}
+
+/// Served by `GET /tca`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TcaReport {
+    pub from_ns: u64,
+    pub to_ns: u64,
+    pub orders: Vec<OrderTca>,
+    pub accounts: Vec<AccountTca>,
+}
+
+pub struct Tca {
+    orders: DashMap<OrderId, TrackedOrder>,
+    /// Arrival order, for dropping orders past retention
+    arrivals: Mutex<VecDeque<(u64, OrderId)>>,
+    retention_ns: u64,
+}
+
+/// The book's mid, or `last_px` when a side is empty.
+pub(crate) fn mid_or_last(book: &mut OrderBook, last_px: Option<i64>) -> Option<f64> {
+    match (book.bids.best_live_price(), book.asks.best_live_price()) {
+        (Some(bid), Some(ask)) => Some((bid as f64 + ask as f64) / 2.0),
+        _ => last_px.map(|px| px as f64),
+    }
+}
+
+impl Tca {
+    /// Tracks orders for `retention_ns` after they arrive.
+    pub fn new(retention_ns: u64) -> Self {
+        Self { orders: DashMap::new(), arrivals: Mutex::new(VecDeque::new()), retention_ns }
+    }
+
+    /// Starts tracking `order` for `account`; call under the book's write
+    /// lock, before matching it.
+    pub(crate) fn arrive(&self, order: &Order, account: String, arrival_px: Option<f64>) {
+        let arrival_ns = order.ts_ns as u64;
+        {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            let cutoff = arrival_ns.saturating_sub(self.retention_ns);
+            while let Some(&(ts_ns, id)) = arrivals.front() {
+                if ts_ns >= cutoff {
+                    break;
+                }
+                arrivals.pop_front();
+                self.orders.remove(&id);
+            }
+            arrivals.push_back((arrival_ns, order.id));
+        }
+        self.orders.insert(
+            order.id,
+            TrackedOrder {
+                account,
+                symbol: order.symbol.to_string(),
+                side: order.side,
+                qty: order.qty,
+                filled: 0,
+                filled_notional: 0.0,
+                arrival_ns,
+                arrival_px,
+                end_ns: None,
+                end_px: None,
+            },
+        );
+    }
+
+    /// Adds fills to tracked orders on both sides, ending any that
+    /// completed at their last fill's price.
+    pub(crate) fn fill(&self, trades: &[Trade]) {
+        for trade in trades {
+            for id in [trade.maker, trade.taker] {
+                let Some(mut order) = self.orders.get_mut(&id) else { continue };
+                order.filled += trade.qty;
+                order.filled_notional += trade.px_ticks as f64 * trade.qty as f64;
+                if order.filled >= order.qty {
+                    order.end_ns = Some(trade.ts_ns as u64);
+                    order.end_px = Some(trade.px_ticks as f64);
+                }
+            }
+        }
+    }
+
+    /// Ends a cancelled order, pricing its remainder at `end_px`.
+    pub(crate) fn end(&self, order_id: OrderId, end_px: Option<f64>) {
+        let Some(mut order) = self.orders.get_mut(&order_id) else { return };
+        if order.end_ns.is_none() {
+            order.end_ns = Some(now_ns());
+            order.end_px = end_px;
+        }
+    }
+
+    /// Orders that arrived in `[from_ns, to_ns)`, narrowed to one account
+    /// if given, with per-account totals. Working orders are measured up to
+    /// now at `last_px`.
+    pub fn report(
+        &self,
+        trades: &TradeStore,
+        account: Option<&str>,
+        from_ns: u64,
+        to_ns: u64,
+        last_px: impl Fn(&str) -> Option<i64>,
+    ) -> TcaReport {
+        let now_ns = now_ns();
+        let mut orders: Vec<OrderTca> = Vec::new();
+        for entry in self.orders.iter() {
+            let order = entry.value();
+            if order.arrival_ns < from_ns || order.arrival_ns >= to_ns || account.is_some_and(|a| a != order.account) {
+                continue;
+            }
+            let end_px = match order.end_ns {
+                Some(_) => order.end_px,
+                None => last_px(&order.symbol).map(|px| px as f64),
+            };
+            // Fills share the order's matching timestamp, so the window closes just after its end
+            let until_ns = order.end_ns.map_or(now_ns, |end| end + 1);
+            let (mut volume, mut notional) = (0i64, 0.0);
+            trades.for_each_in(&order.symbol, order.arrival_ns, until_ns, |t| {
+                volume = volume.saturating_add(t.qty);
+                notional += t.px_ticks as f64 * t.qty as f64;
+            });
+            let interval_vwap = (volume > 0).then(|| notional / volume as f64);
+            let metrics = order.arrival_px.map(|arrival_px| {
+                Execution {
+                    side: order.side,
+                    qty: order.qty,
+                    filled: order.filled,
+                    filled_notional: order.filled_notional,
+                    arrival_px,
+                    end_px,
+                    interval_vwap,
+                    market_volume: volume,
+                }
+                .analyze()
+            });
+            orders.push(OrderTca {
+                order_id: entry.key().0,
+                account: order.account.clone(),
+                symbol: order.symbol.to_string(),
+                side: order.side,
+                qty: order.qty,
+                filled: order.filled,
+                arrival_ns: order.arrival_ns,
+                end_ns: order.end_ns,
+                arrival_px: order.arrival_px,
+                end_px,
+                interval_vwap,
+                market_volume: volume,
+                metrics,
+            });
+        }
+        orders.sort_by_key(|o| (o.arrival_ns, o.order_id));
+
+        // Per account: totals, and slippage summed and weighted by fills
+        let mut accounts: BTreeMap<&str, (AccountTca, f64, i64)> = BTreeMap::new();
+        for order in &orders {
+            let (totals, slipped, weight) = accounts.entry(&order.account).or_insert_with(|| {
+                (AccountTca { account: order.account.clone(), ..AccountTca::default() }, 0.0, 0)
+            });
+            totals.orders += 1;
+            totals.qty = totals.qty.saturating_add(order.qty);
+            totals.filled = totals.filled.saturating_add(order.filled);
+            let (Some(metrics), Some(arrival_px)) = (order.metrics, order.arrival_px) else { continue };
+            totals.paper_notional += arrival_px * order.qty as f64;
+            totals.execution_cost += metrics.execution_cost;
+            totals.opportunity_cost += metrics.opportunity_cost;
+            totals.implementation_shortfall += metrics.implementation_shortfall;
+            if let Some(slippage) = metrics.slippage_bps {
+                *slipped += slippage * order.filled as f64;
+                *weight += order.filled;
+            }
+        }
+        let accounts = accounts
+            .into_values()
+            .map(|(mut totals, slipped, weight)| {
+                totals.shortfall_bps =
+                    (totals.paper_notional > 0.0).then(|| totals.implementation_shortfall / totals.paper_notional * 1e4);
+                totals.slippage_bps = (weight > 0).then(|| slipped / weight as f64);
+                totals
+            })
+            .collect();
+
+        TcaReport { from_ns, to_ns, orders, accounts }
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+}
@@ -0,0 +1,168 @@
+//! Trade history for analytics such as the volume profile.
+//!
+//! Every trade is recorded as it is matched, so the history has no gaps.
+//! Trades older than `EXCHANGE_TRADE_RETENTION_SECS` (default one day) are
+//! dropped from memory. With `EXCHANGE_TRADE_STORE` set, trades are also
+//! appended to that file and read back at startup, so the history survives
+//! restarts. The file is a sequence of frames, each a little-endian `u32`
+//! length and an `orderbook::codec` trade message, which carries its own
+//! format version. A torn final frame (from a crash mid-write) is ignored
+//! on load and overwritten by the next trade.
+
+use dashmap::DashMap;
+use orderbook::{codec, Trade};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::chaos;
+
+/// Match batches queued for the file before new ones are dropped
+const BUFFER: usize = 65_536;
+
+/// What analytics need of a trade.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct StoredTrade {
+    pub ts_ns: u64,
+    pub px_ticks: i64,
+    pub qty: i64,
+}
+
+impl From<&Trade> for StoredTrade {
+    fn from(trade: &Trade) -> Self {
+        Self { ts_ns: trade.ts_ns as u64, px_ticks: trade.px_ticks, qty: trade.qty }
+    }
+}
+
+pub struct TradeStore {
+    trades: DashMap<String, VecDeque<StoredTrade>>,
+    retention_ns: u64,
+    file: Option<mpsc::Sender<Vec<u8>>>,
+    dropped: AtomicU64,
+}
+
+impl Default for TradeStore {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(24 * 3600))
+    }
+}
+
+impl TradeStore {
+    /// A store kept in memory only.
+    pub fn new(retention: Duration) -> Self {
+        Self { trades: DashMap::new(), retention_ns: retention.as_nanos() as u64, file: None, dropped: AtomicU64::new(0) }
+    }
+
+    /// Loads the trades in `path` that are still within retention, then
+    /// appends new trades to it.
+    pub async fn open(path: &str, retention: Duration) -> Result<Self, String> {
+        let mut store = Self::new(retention);
+        let (loaded, valid_len) = match std::fs::File::open(path) {
+            Ok(file) => store.load(std::io::BufReader::new(file)).map_err(|e| format!("{}: {}", path, e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => (0, 0),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+        info!("Loaded {} trades from {}", loaded, path);
+
+        let file = OpenOptions::new().create(true).write(true).truncate(false).open(path).await.map_err(|e| format!("{}: {}", path, e))?;
+        // Cut a torn final frame so appends start on a frame boundary
+        file.set_len(valid_len).await.map_err(|e| format!("{}: {}", path, e))?;
+        let (tx, rx) = mpsc::channel(BUFFER);
+        tokio::spawn(write(file, valid_len, rx, path.to_string()));
+        store.file = Some(tx);
+        Ok(store)
+    }
+
+    /// Reads frames until the end or a torn frame, returning the trades
+    /// kept and the length of the well-formed prefix.
+    fn load(&mut self, mut input: impl Read) -> Result<(usize, u64), codec::CodecError> {
+        let cutoff = now_ns().saturating_sub(self.retention_ns);
+        let (mut loaded, mut valid_len) = (0, 0u64);
+        let mut frame = Vec::new();
+        loop {
+            let mut len = [0u8; 4];
+            match input.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            frame.resize(u32::from_le_bytes(len) as usize, 0);
+            match input.read_exact(&mut frame) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            let trade = codec::decode_trade(&frame)?;
+            valid_len += 4 + frame.len() as u64;
+            if trade.ts_ns as u64 >= cutoff {
+                self.trades.entry(trade.symbol.to_string()).or_default().push_back(StoredTrade::from(&trade));
+                loaded += 1;
+            }
+        }
+        Ok((loaded, valid_len))
+    }
+
+    /// Adds `trades` in match order; call under the book's write lock.
+    pub(crate) fn record(&self, trades: &[Trade]) {
+        let Some(first) = trades.first() else { return };
+        let cutoff = (first.ts_ns as u64).saturating_sub(self.retention_ns);
+        {
+            let mut history = self.trades.entry(first.symbol.to_string()).or_default();
+            while history.front().is_some_and(|t| t.ts_ns < cutoff) {
+                history.pop_front();
+            }
+            history.extend(trades.iter().map(StoredTrade::from));
+        }
+        let Some(file) = &self.file else { return };
+        let mut frames = Vec::with_capacity(trades.len() * 64);
+        for trade in trades {
+            let message = codec::encode_trade(trade);
+            frames.extend_from_slice(&(message.len() as u32).to_le_bytes());
+            frames.extend_from_slice(&message);
+        }
+        if file.try_send(frames).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                warn!("Trade store file behind or closed; {} batches not persisted so far", dropped);
+            }
+        }
+    }
+
+    /// Calls `f` on each of `symbol`'s trades in `[from_ns, to_ns)`, oldest first.
+    pub fn for_each_in(&self, symbol: &str, from_ns: u64, to_ns: u64, f: impl FnMut(&StoredTrade)) {
+        let Some(history) = self.trades.get(symbol) else { return };
+        let start = history.partition_point(|t| t.ts_ns < from_ns);
+        history.range(start..).take_while(|t| t.ts_ns < to_ns).for_each(f);
+    }
+
+    pub fn retention_ns(&self) -> u64 {
+        self.retention_ns
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64
+}
+
+/// Appends frames at `offset`, flushing whenever the queue drains.
+async fn write(mut file: tokio::fs::File, offset: u64, mut rx: mpsc::Receiver<Vec<u8>>, path: String) {
+    if let Err(e) = file.seek(std::io::SeekFrom::Start(offset)).await {
+        warn!("Trade store {}: {}", path, e);
+        return;
+    }
+    while let Some(mut frames) = rx.recv().await {
+        while let Ok(more) = rx.try_recv() {
+            frames.extend_from_slice(&more);
+        }
+        chaos::slow_write().await;
+        if let Err(e) = file.write_all(&frames).await.and(file.flush().await) {
+            warn!("Trade store {}: {}", path, e);
+        }
+    }
+}
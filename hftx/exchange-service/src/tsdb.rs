@@ -0,0 +1,212 @@
+//! Optional time-series database sink for trades and BBO updates.
+//!
+//! Writes InfluxDB line protocol, which QuestDB and InfluxDB both ingest,
+//! so operators can chart the exchange without writing a consumer. Off
+//! unless `EXCHANGE_TSDB_URL` is set:
+//!
+//! - `tcp://host:9009`: QuestDB's ILP TCP listener
+//! - `http://host:9000/write`: QuestDB over HTTP
+//! - `http://host:8086/api/v2/write?org=..&bucket=..`: InfluxDB 2, with
+//!   `EXCHANGE_TSDB_TOKEN` sent as the API token
+//!
+//! Two measurements, tagged by symbol and timestamped in nanoseconds:
+//!
+//! - `trades`: `px_ticks`, `qty` (integers), `maker_id`, `taker_id` (strings,
+//!   since ids are 128-bit)
+//! - `bbo`: `bid_px`, `bid_qty`, `ask_px`, `ask_qty`, a price left out while
+//!   its side is empty
+//!
+//! Lines are batched and sent from a background task, flushed every
+//! `EXCHANGE_TSDB_BATCH` lines (default 1000) or `EXCHANGE_TSDB_FLUSH_MS`
+//! (default 250), whichever comes first. BBO is sampled per symbol at each
+//! flush and after every trade, and written only when it changed. A failed
+//! write drops its batch and is logged; the sink never slows matching.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::interval;
+use tracing::{info, warn};
+
+use crate::exchange::Exchange;
+use crate::types::TradeEvent;
+
+const DEFAULT_BATCH: usize = 1000;
+const DEFAULT_FLUSH_MS: u64 = 250;
+
+/// Where lines go.
+enum Transport {
+    Tcp { addr: String, stream: Option<TcpStream> },
+    Http { client: reqwest::Client, url: String, token: Option<String> },
+}
+
+pub struct TsdbConfig {
+    pub url: String,
+    pub token: Option<String>,
+    pub batch_lines: usize,
+    pub flush_every: Duration,
+}
+
+impl TsdbConfig {
+    /// The sink's settings, if `EXCHANGE_TSDB_URL` is set.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("EXCHANGE_TSDB_URL").ok()?;
+        let number = |name: &str, default: u64| std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Some(Self {
+            url,
+            token: std::env::var("EXCHANGE_TSDB_TOKEN").ok(),
+            batch_lines: number("EXCHANGE_TSDB_BATCH", DEFAULT_BATCH as u64).max(1) as usize,
+            flush_every: Duration::from_millis(number("EXCHANGE_TSDB_FLUSH_MS", DEFAULT_FLUSH_MS).max(1)),
+        })
+    }
+
+    fn transport(&self) -> Result<Transport, String> {
+        if let Some(addr) = self.url.strip_prefix("tcp://") {
+            Ok(Transport::Tcp { addr: addr.trim_end_matches('/').to_string(), stream: None })
+        } else if self.url.starts_with("http://") {
+            Ok(Transport::Http { client: reqwest::Client::new(), url: self.url.clone(), token: self.token.clone() })
+        } else {
+            Err(format!("EXCHANGE_TSDB_URL must start with tcp:// or http://, got {}", self.url))
+        }
+    }
+}
+
+/// Best bid / ask with the quantity at each.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Bbo {
+    bid: Option<(i64, i64)>,
+    ask: Option<(i64, i64)>,
+}
+
+/// Runs the sink until the trade channel closes.
+pub async fn run(config: TsdbConfig, exchange: Arc<Exchange>, mut trades: broadcast::Receiver<TradeEvent>) {
+    let mut transport = match config.transport() {
+        Ok(transport) => transport,
+        Err(e) => {
+            warn!("TSDB sink disabled: {}", e);
+            return;
+        }
+    };
+    info!("TSDB sink writing to {}", config.url);
+
+    let mut batch = String::new();
+    let mut lines = 0usize;
+    let mut last_bbo: HashMap<String, Bbo> = HashMap::new();
+    let mut flush = interval(config.flush_every);
+
+    loop {
+        tokio::select! {
+            trade = trades.recv() => {
+                match trade {
+                    Ok(event) => {
+                        trade_line(&mut batch, &event);
+                        lines += 1;
+                        lines += bbo_lines(&mut batch, &exchange, std::slice::from_ref(&event.symbol), &mut last_bbo).await;
+                    }
+                    Err(RecvError::Lagged(missed)) => warn!("TSDB sink fell behind and skipped {} trades", missed),
+                    Err(RecvError::Closed) => break,
+                }
+                if lines < config.batch_lines {
+                    continue;
+                }
+            }
+            _ = flush.tick() => {
+                let symbols = exchange.list_symbols().await;
+                lines += bbo_lines(&mut batch, &exchange, &symbols, &mut last_bbo).await;
+            }
+        }
+        if lines > 0 {
+            if let Err(e) = transport.send(&batch).await {
+                warn!("TSDB write of {} lines failed, dropping them: {}", lines, e);
+            }
+            batch.clear();
+            lines = 0;
+        }
+    }
+}
+
+impl Transport {
+    async fn send(&mut self, batch: &str) -> Result<(), String> {
+        match self {
+            Transport::Tcp { addr, stream } => {
+                if stream.is_none() {
+                    *stream = Some(TcpStream::connect(&*addr).await.map_err(|e| e.to_string())?);
+                }
+                let result = stream.as_mut().unwrap().write_all(batch.as_bytes()).await;
+                // Reconnect on the next batch rather than retrying this one
+                result.map_err(|e| {
+                    *stream = None;
+                    e.to_string()
+                })
+            }
+            Transport::Http { client, url, token } => {
+                let mut request = client.post(url.as_str()).body(batch.to_string());
+                if let Some(token) = token {
+                    request = request.header("Authorization", format!("Token {}", token));
+                }
+                let response = request.send().await.map_err(|e| e.to_string())?;
+                match response.status() {
+                    status if status.is_success() => Ok(()),
+                    status => Err(format!("{}: {}", status, response.text().await.unwrap_or_default())),
+                }
+            }
+        }
+    }
+}
+
+/// Tag values escape commas, spaces, and equals signs.
+fn tag(value: &str) -> String {
+    value.replace(',', "\\,").replace(' ', "\\ ").replace('=', "\\=")
+}
+
+fn trade_line(out: &mut String, event: &TradeEvent) {
+    let trade = &event.trade;
+    let _ = writeln!(
+        out,
+        "trades,symbol={} px_ticks={}i,qty={}i,maker_id=\"{}\",taker_id=\"{}\" {}",
+        tag(&event.symbol),
+        trade.px_ticks,
+        trade.qty,
+        trade.maker.0,
+        trade.taker.0,
+        trade.ts_ns
+    );
+}
+
+/// Writes a `bbo` line for each symbol whose top changed; returns how many.
+async fn bbo_lines(out: &mut String, exchange: &Exchange, symbols: &[String], last: &mut HashMap<String, Bbo>) -> usize {
+    let mut written = 0;
+    for symbol in symbols {
+        let Some(depth) = exchange.get_market_depth(symbol, 1).await else { continue };
+        let bbo = Bbo {
+            bid: depth.bids.first().map(|l| (l.price, l.quantity)),
+            ask: depth.asks.first().map(|l| (l.price, l.quantity)),
+        };
+        if last.get(symbol) == Some(&bbo) {
+            continue;
+        }
+        last.insert(symbol.clone(), bbo);
+
+        let _ = write!(out, "bbo,symbol={} ", tag(symbol));
+        if let Some((px, _)) = bbo.bid {
+            let _ = write!(out, "bid_px={}i,", px);
+        }
+        if let Some((px, _)) = bbo.ask {
+            let _ = write!(out, "ask_px={}i,", px);
+        }
+        let ts_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+        let _ = writeln!(
+            out,
+            "bid_qty={}i,ask_qty={}i {}",
+            bbo.bid.map_or(0, |(_, qty)| qty),
+            bbo.ask.map_or(0, |(_, qty)| qty),
+            ts_ns
+        );
+        written += 1;
+    }
+    written
+}
@@ -1,6 +1,12 @@
 //! API types for REST and WebSocket interfaces.
 
-use orderbook::{Side, Trade};
+use crate::accounts::{AccountEvent, AccountView};
+use crate::surveillance::SurveillanceAlert;
+use crate::trade_store::StoredTrade;
+use orderbook::index::IndexValue;
+use orderbook::profile::VolumeProfile;
+use orderbook::quotes::QuoteReport;
+use orderbook::{ExecutionReport, Order, OrderId, OrderKind, ParticipantId, Side, SymbolId, TimeInForce, Trade};
 use serde::{Deserialize, Serialize};
 
 /// Request to submit a new limit order.
@@ -9,16 +15,222 @@ pub struct SubmitOrderRequest {
     pub side: Side,
     pub price: i64,
     pub quantity: i64,
+    /// Owner of the order; its fills update this account's positions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Client send time in ns since the epoch, checked against the latency budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ts_ns: Option<u64>,
+    /// Good-til-date: ns since the epoch at which any resting remainder is cancelled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_ns: Option<u64>,
+    /// Iceberg: the most of the order shown on the book at a time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_qty: Option<i64>,
+    /// Smallest fill to take on arrival; smaller makers are passed over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_qty: Option<i64>,
+    /// Dark: matches, but never shows in depth or best prices
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+/// Request to submit a stop, or with `price` a stop-limit, held until a
+/// trade prints at or through `stop_price`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StopOrderRequest {
+    pub side: Side,
+    pub stop_price: i64,
+    /// Limit price once triggered; without one the stop trades at market
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<i64>,
+    pub quantity: i64,
+    /// Owner of the order; its fills update this account's positions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Client send time in ns since the epoch, checked against the latency budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ts_ns: Option<u64>,
+}
+
+/// Request to amend a resting order. `quantity` is what should remain of
+/// it; a smaller one at the same price keeps its queue position.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModifyOrderRequest {
+    pub price: i64,
+    pub quantity: i64,
+    /// Amends only this account's order; another's is reported not found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+}
+
+/// Request to replace an account's two-sided quote. A side with zero
+/// quantity isn't quoted; zero on both sides pulls the quote.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuoteRequest {
+    /// Owner of the quote; its last one on the symbol is replaced
+    pub account: String,
+    pub bid_price: i64,
+    pub bid_quantity: i64,
+    pub ask_price: i64,
+    pub ask_quantity: i64,
+    /// Client send time in ns since the epoch, checked against the latency budget
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ts_ns: Option<u64>,
+}
+
+impl QuoteRequest {
+    /// The quote's bid and ask as fresh Day orders stamped `ts_ns`.
+    pub fn orders(&self, symbol: SymbolId, ts_ns: u128) -> [Order; 2] {
+        let order = |side, px_ticks, qty| Order {
+            id: OrderId(uuid::Uuid::new_v4().as_u128()),
+            symbol,
+            side,
+            px_ticks,
+            qty,
+            ts_ns,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        [order(Side::Bid, self.bid_price, self.bid_quantity), order(Side::Ask, self.ask_price, self.ask_quantity)]
+    }
+}
+
+/// Response after replacing a quote.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuoteResponse {
+    pub status: String, // "quoted", or "pulled" when neither side is
+    /// Orders of the last quote that were still resting, now cancelled
+    pub replaced: Vec<u128>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bid: Option<SubmitOrderResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ask: Option<SubmitOrderResponse>,
+}
+
+impl From<QuoteReport> for QuoteResponse {
+    fn from(report: QuoteReport) -> Self {
+        let status = if report.bid.is_none() && report.ask.is_none() { "pulled" } else { "quoted" };
+        QuoteResponse {
+            status: status.to_string(),
+            replaced: report.replaced.iter().map(|order| order.id.0).collect(),
+            bid: report.bid.map(Into::into),
+            ask: report.ask.map(Into::into),
+        }
+    }
 }
 
 /// Response after submitting an order.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SubmitOrderResponse {
     pub order_id: u128,
-    pub status: String, // "accepted", "filled", "partial", "rejected"
+    pub status: String, // "rested", "partially_filled", "filled", "cancelled", "rejected", "held", "amended"
+    /// Filled by this request
+    #[serde(default)]
+    pub filled_qty: i64,
+    /// Left working on the book
+    #[serde(default)]
+    pub remaining_qty: i64,
+    /// Quantity-weighted price of what filled, in ticks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_px: Option<f64>,
     pub trades: Vec<Trade>, // Any immediate executions
 }
 
+impl From<ExecutionReport> for SubmitOrderResponse {
+    fn from(report: ExecutionReport) -> Self {
+        Self {
+            order_id: report.order_id.0,
+            status: report.status.as_str().to_string(),
+            filled_qty: report.filled_qty,
+            remaining_qty: report.remaining_qty,
+            avg_px: report.avg_px,
+            trades: report.trades,
+        }
+    }
+}
+
+/// What became of an order.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderStatusResponse {
+    pub order_id: u128,
+    pub status: String, // "new", "partially_filled", "filled", "canceled", "expired"
+    /// Quantity submitted, or after an amend what had filled plus the new
+    /// remaining quantity
+    pub orig_qty: i64,
+    pub filled_qty: i64,
+    /// Still working on the book
+    pub leaves_qty: i64,
+}
+
+/// Response after cancelling an order.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelOrderResponse {
+    pub status: String, // "cancelled"
+    pub order_id: u128,
+    /// Quantity the order still had unfilled
+    #[serde(default)]
+    pub remaining_qty: i64,
+}
+
+/// Response after a mass cancel.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MassCancelResponse {
+    pub status: String, // "cancelled"
+    /// The cancelled orders, bids first
+    pub order_ids: Vec<u128>,
+}
+
+/// Which resting orders a mass cancel takes: one side or both, priced
+/// from `min_price` to `max_price` inclusive (unbounded when absent), or
+/// else every one `account` has.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MassCancelQuery {
+    pub side: Option<Side>,
+    pub min_price: Option<i64>,
+    pub max_price: Option<i64>,
+    pub account: Option<String>,
+}
+
+/// Query for looking up or cancelling one order: with `account`, only if
+/// it is that account's.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderQuery {
+    pub account: Option<String>,
+}
+
+/// One of an account's orders resting on a book.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RestingOrder {
+    pub symbol: String,
+    pub order_id: u128,
+    pub side: Side,
+    pub price: i64,
+    /// Quantity showing; an iceberg shows its current slice
+    pub quantity: i64,
+    pub hidden: bool,
+}
+
+/// An account's resting orders across every symbol.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountOrders {
+    pub account: String,
+    pub orders: Vec<RestingOrder>,
+}
+
+/// Outcome of killing or unblocking an account.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct KillResponse {
+    pub account: String,
+    pub blocked: bool,
+    /// Resting orders cancelled by the kill
+    pub cancelled_orders: usize,
+}
+
 /// Batch order submission. Orders are processed in array order under a single
 /// write lock per book, amortizing lock + JSON-parse cost across the batch.
 #[derive(Debug, Serialize, Deserialize)]
@@ -32,6 +244,7 @@ pub struct BatchSubmitRequest {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BatchOrderResult {
     pub order_id: u128,
+    pub status: String, // As in `SubmitOrderResponse`
     pub filled: bool,
     pub trade_count: usize,
     /// Engine-side processing time for this order in nanoseconds.
@@ -46,6 +259,17 @@ pub struct BatchSubmitResponse {
     pub engine_ns: u64,
 }
 
+/// Single-order submit counters, for checking how well submits amortize the
+/// book lock. `submits / submit_batches` is the mean orders per acquisition.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitStats {
+    pub batching: bool,
+    pub submits: u64,
+    pub submit_batches: u64,
+    /// Orders refused as older than the latency budget
+    pub stale_rejects: u64,
+}
+
 /// Inbound frame on the order WS: a batch plus a client-assigned `seq` so the
 /// caller can match the response back to the originating tick.
 #[derive(Debug, Serialize, Deserialize)]
@@ -62,9 +286,10 @@ pub struct OrderStreamResponse {
     pub engine_ns: u64,
 }
 
-/// Tagged message envelope for the order stream. Inbound clients send either
-/// `batch` (a sequenced order batch) or `ping`. Outbound the server emits
-/// `result` (the matching response), `error`, or `ping`/`pong`.
+/// Tagged message envelope for the order stream. Inbound clients send
+/// `batch` (a sequenced order batch), `quote` (a sequenced quote
+/// replacement) or `ping`. Outbound the server emits `result` or
+/// `quote_result` (the matching response), `error`, or `ping`/`pong`.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum OrderStreamMessage {
@@ -72,6 +297,10 @@ pub enum OrderStreamMessage {
     Batch(OrderStreamRequest),
     #[serde(rename = "result")]
     Result(OrderStreamResponse),
+    #[serde(rename = "quote")]
+    Quote { seq: u64, quote: QuoteRequest },
+    #[serde(rename = "quote_result")]
+    QuoteResult { seq: u64, result: QuoteResponse },
     #[serde(rename = "error")]
     Error { seq: Option<u64>, message: String },
     #[serde(rename = "ping")]
@@ -86,6 +315,100 @@ pub struct DepthQuery {
     pub levels: Option<usize>,
 }
 
+/// Query parameters for book history; times are ns since the epoch.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BookHistoryQuery {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub buckets: Option<usize>,
+}
+
+/// Query parameters for the volume profile; times are ns since the epoch
+/// and `bucket` is the price bucket width in ticks.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VolumeProfileQuery {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub bucket: Option<i64>,
+}
+
+/// Query for `GET /symbols/:symbol/trades`: nanosecond range (default the
+/// store's retention up to now) and the most trades to return.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TradesQuery {
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+/// Most trades one `GET /symbols/:symbol/trades` returns
+pub const MAX_TRADES: usize = 10_000;
+
+/// A symbol's trades over a time range, oldest first.
+#[derive(Debug, Serialize)]
+pub struct SymbolTrades {
+    pub symbol: String,
+    pub from_ns: u64,
+    pub to_ns: u64,
+    pub trades: Vec<StoredTrade>,
+    /// More trades in the range than `limit`; ask again from the last one
+    pub truncated: bool,
+}
+
+/// Traded volume by price over a time range.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SymbolVolumeProfile {
+    pub symbol: String,
+    pub from_ns: u64,
+    pub to_ns: u64,
+    #[serde(flatten)]
+    pub profile: VolumeProfile,
+}
+
+/// Query parameters for transaction cost analysis; times are ns since the
+/// epoch and select orders by arrival.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TcaQuery {
+    pub account: Option<String>,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+}
+
+/// Query parameters for surveillance reports.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SurveillanceQuery {
+    /// Only this account's flags
+    pub account: Option<String>,
+}
+
+/// Query parameters for the trade and depth streams.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StreamQuery {
+    /// `bin` sends each trade as an `orderbook::codec` binary frame instead
+    /// of JSON; `sbe` sends `orderbook::sbe` frames on either stream
+    pub format: Option<String>,
+}
+
+impl StreamQuery {
+    pub fn feed_format(&self) -> FeedFormat {
+        match self.format.as_deref() {
+            Some("bin") => FeedFormat::Codec,
+            Some("sbe") => FeedFormat::Sbe,
+            _ => FeedFormat::Json,
+        }
+    }
+}
+
+/// Wire format of a market data stream. Pings stay JSON text in all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Json,
+    /// `orderbook::codec` trade frames (trade stream only)
+    Codec,
+    /// `orderbook::sbe` frames: trades, or BBO plus depth diffs
+    Sbe,
+}
+
 /// List of available trading symbols.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SymbolsResponse {
@@ -118,6 +441,37 @@ pub struct MarketDepth {
     pub bids: Vec<PriceLevel>, // Highest to lowest price
     pub asks: Vec<PriceLevel>, // Lowest to highest price
     pub timestamp: u64,
+    /// Book event number the levels are current to; compare with the
+    /// event stream's `seq`. 0 from venues that don't number their books
+    #[serde(default)]
+    pub seq: u64,
+}
+
+/// One symbol merged across external venues' mirrored books.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsolidatedBook {
+    pub symbol: String,
+    /// Quantity summed across venues; `orders` is the number of venues
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    pub venues: Vec<VenueQuote>,
+    pub timestamp: u64,
+}
+
+/// One venue's top of book and last print for a consolidated symbol.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VenueQuote {
+    pub venue: String,
+    pub best_bid: Option<i64>,
+    pub best_ask: Option<i64>,
+    pub last_trade_px: Option<i64>,
+    pub last_trade_qty: Option<i64>,
+    /// Aggressor side of the last trade
+    pub last_trade_side: Option<Side>,
+    /// Trades seen since the venue connected
+    pub trades: u64,
+    /// Nanoseconds since the epoch of the venue's last message
+    pub last_update: u64,
 }
 
 /// Trade execution event for WebSocket streaming.
@@ -128,6 +482,32 @@ pub struct TradeEvent {
     pub timestamp: u64,
 }
 
+/// One accepted book mutation, numbered per symbol in matching order.
+///
+/// `seq` is the book's own number for the event (`OrderBook::seq`), so it
+/// starts at 1, matches a trade's `seq`, and each symbol's events replay
+/// its book exactly. A submit's trades directly follow the submit.
+#[derive(Debug, Clone)]
+pub struct SequencedEvent {
+    pub symbol: String,
+    pub seq: u64,
+    pub ts_ns: u64,
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum EventKind {
+    Submit(Order),
+    /// Only cancels that removed a live order are sequenced
+    Cancel { id: OrderId, side: Side },
+    Trade(Trade),
+    /// A stop or stop-limit taken in; its trades are sequenced after the
+    /// submit whose trades set it off
+    Stop { order: Order, stop_px: i64, kind: OrderKind },
+    /// An amend of a resting order; any trades its re-entry made follow it
+    Modify { id: OrderId, side: Side, px_ticks: i64, qty: i64, ts_ns: u64 },
+}
+
 /// Market depth update for WebSocket streaming.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DepthUpdate {
@@ -137,6 +517,19 @@ pub struct DepthUpdate {
     pub bid_size: i64,
     pub ask_size: i64,
     pub timestamp: u64,
+    /// `OrderBook::book_checksum` over the top levels of each side, for
+    /// clients to check the books they keep against
+    pub checksum: u32,
+}
+
+/// The displayed quantity now at one price of a book; 0 once the level is
+/// gone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelUpdate {
+    pub symbol: String,
+    pub side: Side,
+    pub price: i64,
+    pub quantity: i64,
 }
 
 /// WebSocket message types.
@@ -147,10 +540,24 @@ pub enum WebSocketMessage {
     Trade(TradeEvent),
     #[serde(rename = "depth")]
     Depth(DepthUpdate),
+    #[serde(rename = "level")]
+    Level(LevelUpdate),
     #[serde(rename = "latency")]
     Latency(LatencySample),
+    #[serde(rename = "index")]
+    Index(IndexValue),
+    #[serde(rename = "account")]
+    Account(AccountView),
+    #[serde(rename = "account_event")]
+    AccountEvent(AccountEvent),
+    #[serde(rename = "surveillance_alert")]
+    SurveillanceAlert(SurveillanceAlert),
     #[serde(rename = "error")]
     Error { message: String },
+    /// Messages this subscriber missed; `resync` is the REST path to catch
+    /// up from, from `from_ns` (its last trade, on the trade stream)
+    #[serde(rename = "gap")]
+    Gap { missed: u64, resync: String, from_ns: Option<u64> },
     #[serde(rename = "ping")]
     Ping { timestamp: u64 },
     #[serde(rename = "pong")]
@@ -166,6 +573,9 @@ pub struct BotConfig {
     /// 0-100; higher = tighter maker spread, more taker crossing.
     pub aggression: u32,
     pub tick_ms: u64,
+    /// RNG seed for the synthesized flow; random when absent.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 /// Request body for `POST /sim/start`.
@@ -176,6 +586,9 @@ pub struct SimStartRequest {
     pub takers: u32,
     pub aggression: u32,
     pub tick_ms: u64,
+    /// Optional seed so a driver run can be reproduced exactly.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
 /// Request body for `POST /sim/stop`.
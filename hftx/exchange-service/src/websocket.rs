@@ -5,46 +5,57 @@
 
 use axum::extract::ws::{Message, WebSocket};
 use futures::{sink::SinkExt, stream::StreamExt};
-use orderbook::{Order, OrderId};
+use orderbook::sbe::{self, BboMessage, DepthDiffMessage, DepthLevel, TradeMessage};
+use orderbook::{codec, Order, OrderId, ParticipantId, Side, SymbolRegistry, TimeInForce};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::broadcast;
 use tokio::time::interval;
 use tracing::{error, info, warn};
 
-use crate::{types::*, AppState};
+use crate::accounts::AccountView;
+use crate::chaos;
+use crate::ws_connections::{Delivery, SlowPolicy};
+use crate::{check_order, check_terms, types::*, AppState};
 
 /// Handles real-time trade streaming for a symbol.
 /// 
 /// Streams trade executions immediately as they occur. Includes ping/pong
-/// heartbeat for connection health monitoring.
-pub async fn handle_trade_stream(socket: WebSocket, symbol: String, state: AppState) {
+/// heartbeat for connection health monitoring. `format` picks JSON, an
+/// `orderbook::codec` trade frame, or an SBE trade message per trade; pings
+/// and gap notices stay JSON text. A subscriber that falls behind is
+/// handled per the slow-consumer policy (see `ws_connections`); gap notices
+/// point at `/symbols/:symbol/trades` from its last trade.
+pub async fn handle_trade_stream(socket: WebSocket, symbol: String, format: FeedFormat, state: AppState) {
     info!("New trade stream connection for {}", symbol);
     
-    let (mut sender, mut receiver) = socket.split();
+    let (sender, mut receiver) = socket.split();
+    let mut out = state.ws_connections.open("trades", &symbol, format!("/symbols/{}/trades", symbol), sender);
     let mut trade_rx = state.trade_broadcaster.subscribe();
     let mut ping_interval = interval(Duration::from_secs(30));
 
+    let restart = chaos::restart(&symbol);
+    tokio::pin!(restart);
     loop {
         tokio::select! {
+            // Chaos: forced restart, so the client has to reconnect and resync
+            _ = &mut restart => {
+                warn!("Chaos restart of trade stream for {}", symbol);
+                break;
+            }
             // Handle incoming WebSocket messages
             msg = receiver.next() => {
                 match msg {
                     Some(Ok(Message::Text(text))) => {
-                        if let Ok(ws_msg) = serde_json::from_str::<WebSocketMessage>(&text) {
-                            match ws_msg {
-                                WebSocketMessage::Ping { timestamp } => {
-                                    let pong = WebSocketMessage::Pong { timestamp };
-                                    if let Ok(pong_json) = serde_json::to_string(&pong) {
-                                        let _ = sender.send(Message::Text(pong_json)).await;
-                                    }
-                                }
-                                _ => {}
+                        if let Ok(WebSocketMessage::Ping { timestamp }) = serde_json::from_str::<WebSocketMessage>(&text) {
+                            let pong = WebSocketMessage::Pong { timestamp };
+                            if let Ok(pong_json) = serde_json::to_string(&pong) {
+                                out.send(Message::Text(pong_json));
                             }
                         }
                     }
                     Some(Ok(Message::Binary(_))) => {} // Ignore binary
                     Some(Ok(Message::Ping(data))) => {
-                        let _ = sender.send(Message::Pong(data)).await;
+                        out.send(Message::Pong(data));
                     }
                     Some(Ok(Message::Pong(_))) => {} // Ignore pong
                     Some(Ok(Message::Close(_))) => {
@@ -61,19 +72,39 @@ pub async fn handle_trade_stream(socket: WebSocket, symbol: String, state: AppSt
             
             // Forward trade broadcasts for this symbol
             trade_result = trade_rx.recv() => {
-                match trade_result {
-                    Ok(trade_event) => {
-                        if trade_event.symbol == symbol {
-                            let ws_msg = WebSocketMessage::Trade(trade_event);
-                            if let Ok(json) = serde_json::to_string(&ws_msg) {
-                                if sender.send(Message::Text(json)).await.is_err() {
-                                    warn!(" Failed to send trade update for {}", symbol);
-                                    break;
-                                }
-                            }
+                let trade_event = match trade_result {
+                    Ok(trade_event) => trade_event,
+                    // Counts skipped trades of every symbol, so it may overstate
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        if out.lost(skipped) == Delivery::Closed {
+                            break;
                         }
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                out.set_lag(trade_rx.len());
+                if trade_event.symbol != symbol {
+                    continue;
+                }
+                let ts_ns = trade_event.trade.ts_ns as u64;
+                let msg = match format {
+                    FeedFormat::Codec => Some(Message::Binary(codec::encode_trade(&trade_event.trade))),
+                    FeedFormat::Sbe => {
+                        let mut frame = Vec::with_capacity(sbe::HEADER_LEN + TradeMessage::BLOCK_LENGTH);
+                        TradeMessage::from(&trade_event.trade).encode(&mut frame);
+                        Some(Message::Binary(frame))
+                    }
+                    FeedFormat::Json => {
+                        serde_json::to_string(&WebSocketMessage::Trade(trade_event)).ok().map(Message::Text)
+                    }
+                };
+                if let Some(msg) = msg {
+                    match out.send(msg) {
+                        Delivery::Queued => out.set_resync_from(ts_ns),
+                        Delivery::Dropped => {}
+                        Delivery::Closed => break,
                     }
-                    Err(_) => break, // Channel closed/lagged
                 }
             }
             
@@ -86,7 +117,7 @@ pub async fn handle_trade_stream(socket: WebSocket, symbol: String, state: AppSt
                         .as_millis() as u64
                 };
                 if let Ok(ping_json) = serde_json::to_string(&ping) {
-                    if sender.send(Message::Text(ping_json)).await.is_err() {
+                    if out.send(Message::Text(ping_json)) == Delivery::Closed {
                         break; // Connection broken
                     }
                 }
@@ -97,19 +128,28 @@ pub async fn handle_trade_stream(socket: WebSocket, symbol: String, state: AppSt
     info!("Trade stream handler ended for {}", symbol);
 }
 
+/// Levels per side the depth stream's `checksum` covers.
+const CHECKSUM_LEVELS: usize = 10;
+
 /// Handles real-time market depth streaming for a symbol.
 /// 
 /// Sends depth updates at 10 Hz (every 100ms) but only when prices change.
-/// Includes initial snapshot on connection.
-pub async fn handle_depth_stream(socket: WebSocket, symbol: String, state: AppState) {
+/// Includes initial snapshot on connection. With `sbe`, see
+/// `handle_sbe_depth_stream` instead.
+pub async fn handle_depth_stream(socket: WebSocket, symbol: String, sbe: bool, state: AppState) {
+    if sbe {
+        return handle_sbe_depth_stream(socket, symbol, state).await;
+    }
     info!("New depth stream connection for {}", symbol);
     
-    let (mut sender, mut receiver) = socket.split();
+    let (sender, mut receiver) = socket.split();
+    let mut out = state.ws_connections.open("depth", &symbol, format!("/symbols/{}/depth", symbol), sender);
     let mut update_interval = interval(Duration::from_millis(100)); // 10 Hz
     let mut ping_interval = interval(Duration::from_secs(30));
     
     // Send initial depth snapshot
     if let Some(depth) = state.exchange.get_market_depth(&symbol, 10).await {
+        let checksum = state.exchange.get_book_checksum(&symbol, CHECKSUM_LEVELS).await.unwrap_or(0);
         let depth_update = DepthUpdate {
             symbol: symbol.clone(),
             best_bid: depth.bids.first().map(|b| b.price),
@@ -120,11 +160,12 @@ pub async fn handle_depth_stream(socket: WebSocket, symbol: String, state: AppSt
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
                 .as_millis() as u64,
+            checksum,
         };
         
         let ws_msg = WebSocketMessage::Depth(depth_update);
         if let Ok(json) = serde_json::to_string(&ws_msg) {
-            let _ = sender.send(Message::Text(json)).await;
+            out.send(Message::Text(json));
         }
     }
 
@@ -132,27 +173,29 @@ pub async fn handle_depth_stream(socket: WebSocket, symbol: String, state: AppSt
     let mut last_best_bid: Option<i64> = None;
     let mut last_best_ask: Option<i64> = None;
 
+    let restart = chaos::restart(&symbol);
+    tokio::pin!(restart);
     loop {
         tokio::select! {
+            // Chaos: forced restart, so the client has to reconnect and resync
+            _ = &mut restart => {
+                warn!("Chaos restart of depth stream for {}", symbol);
+                break;
+            }
             // Handle incoming messages
             msg = receiver.next() => {
                 match msg {
                     Some(Ok(Message::Text(text))) => {
-                        if let Ok(ws_msg) = serde_json::from_str::<WebSocketMessage>(&text) {
-                            match ws_msg {
-                                WebSocketMessage::Ping { timestamp } => {
-                                    let pong = WebSocketMessage::Pong { timestamp };
-                                    if let Ok(pong_json) = serde_json::to_string(&pong) {
-                                        let _ = sender.send(Message::Text(pong_json)).await;
-                                    }
-                                }
-                                _ => {}
+                        if let Ok(WebSocketMessage::Ping { timestamp }) = serde_json::from_str::<WebSocketMessage>(&text) {
+                            let pong = WebSocketMessage::Pong { timestamp };
+                            if let Ok(pong_json) = serde_json::to_string(&pong) {
+                                out.send(Message::Text(pong_json));
                             }
                         }
                     }
                     Some(Ok(Message::Binary(_))) => {} // Ignore
                     Some(Ok(Message::Ping(data))) => {
-                        let _ = sender.send(Message::Pong(data)).await;
+                        out.send(Message::Pong(data));
                     }
                     Some(Ok(Message::Pong(_))) => {} // Ignore
                     Some(Ok(Message::Close(_))) => {
@@ -171,10 +214,16 @@ pub async fn handle_depth_stream(socket: WebSocket, symbol: String, state: AppSt
             _ = update_interval.tick() => {
                 if let Some((best_bid, best_ask)) = state.exchange.get_best_prices(&symbol).await {
                     if best_bid != last_best_bid || best_ask != last_best_ask {
+                        // The next tick sends whatever the book is by then
+                        if out.policy() == SlowPolicy::Conflate && out.queued() > 0 {
+                            out.conflated();
+                            continue;
+                        }
                         let (bid_volume, ask_volume) = state.exchange
                             .get_total_volume(&symbol)
                             .await
                             .unwrap_or((0, 0));
+                        let checksum = state.exchange.get_book_checksum(&symbol, CHECKSUM_LEVELS).await.unwrap_or(0);
                         
                         let depth_update = DepthUpdate {
                             symbol: symbol.clone(),
@@ -186,18 +235,21 @@ pub async fn handle_depth_stream(socket: WebSocket, symbol: String, state: AppSt
                                 .duration_since(UNIX_EPOCH)
                                 .unwrap()
                                 .as_millis() as u64,
+                            checksum,
                         };
                         
                         let ws_msg = WebSocketMessage::Depth(depth_update);
                         if let Ok(json) = serde_json::to_string(&ws_msg) {
-                            if sender.send(Message::Text(json)).await.is_err() {
-                                warn!(" Failed to send depth update for {}", symbol);
-                                break;
+                            match out.send(Message::Text(json)) {
+                                Delivery::Queued => {
+                                    last_best_bid = best_bid;
+                                    last_best_ask = best_ask;
+                                }
+                                // Left as they were, so the next change goes out
+                                Delivery::Dropped => {}
+                                Delivery::Closed => break,
                             }
                         }
-                        
-                        last_best_bid = best_bid;
-                        last_best_ask = best_ask;
                     }
                 }
             }
@@ -211,7 +263,7 @@ pub async fn handle_depth_stream(socket: WebSocket, symbol: String, state: AppSt
                         .as_millis() as u64
                 };
                 if let Ok(ping_json) = serde_json::to_string(&ping) {
-                    if sender.send(Message::Text(ping_json)).await.is_err() {
+                    if out.send(Message::Text(ping_json)) == Delivery::Closed {
                         break; // Connection broken
                     }
                 }
@@ -222,21 +274,253 @@ pub async fn handle_depth_stream(socket: WebSocket, symbol: String, state: AppSt
     info!(" Depth stream handler ended for {}", symbol);
 }
 
+/// Handles the level stream for a symbol: every price level on connect,
+/// then each change the book makes, as it makes it, rather than on a timer.
+/// Each message holds the displayed quantity now at one price, 0 once the
+/// level is gone, so applying them in order keeps a full book. A
+/// subscriber that falls behind gets a gap notice and resyncs from the
+/// depth snapshot.
+pub async fn handle_level_stream(socket: WebSocket, symbol: String, state: AppState) {
+    info!("New level stream connection for {}", symbol);
+
+    let (sender, mut receiver) = socket.split();
+    let mut out = state.ws_connections.open("levels", &symbol, format!("/symbols/{}/depth", symbol), sender);
+    // Subscribed before the snapshot, so no change falls between the two
+    let mut level_rx = state.exchange.subscribe_levels();
+    let mut ping_interval = interval(Duration::from_secs(30));
+
+    if let Some(depth) = state.exchange.get_market_depth(&symbol, usize::MAX).await {
+        let bids = depth.bids.iter().map(|level| (Side::Bid, level));
+        for (side, level) in bids.chain(depth.asks.iter().map(|level| (Side::Ask, level))) {
+            let update = LevelUpdate { symbol: symbol.clone(), side, price: level.price, quantity: level.quantity };
+            if let Ok(json) = serde_json::to_string(&WebSocketMessage::Level(update)) {
+                out.send(Message::Text(json));
+            }
+        }
+    }
+
+    let restart = chaos::restart(&symbol);
+    tokio::pin!(restart);
+    loop {
+        tokio::select! {
+            // Chaos: forced restart, so the client has to reconnect and resync
+            _ = &mut restart => {
+                warn!("Chaos restart of level stream for {}", symbol);
+                break;
+            }
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(WebSocketMessage::Ping { timestamp }) = serde_json::from_str::<WebSocketMessage>(&text) {
+                            if let Ok(pong_json) = serde_json::to_string(&WebSocketMessage::Pong { timestamp }) {
+                                out.send(Message::Text(pong_json));
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Ping(data))) => {
+                        out.send(Message::Pong(data));
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        error!(" WebSocket error in level stream: {}", e);
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+
+            update = level_rx.recv() => {
+                let update = match update {
+                    Ok(update) => update,
+                    // Counts skipped changes of every symbol, so it may overstate
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        if out.lost(skipped) == Delivery::Closed {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                out.set_lag(level_rx.len());
+                if update.symbol != symbol {
+                    continue;
+                }
+                if let Ok(json) = serde_json::to_string(&WebSocketMessage::Level(update)) {
+                    if out.send(Message::Text(json)) == Delivery::Closed {
+                        break;
+                    }
+                }
+            }
+
+            _ = ping_interval.tick() => {
+                let ping = WebSocketMessage::Ping {
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64
+                };
+                if let Ok(ping_json) = serde_json::to_string(&ping) {
+                    if out.send(Message::Text(ping_json)) == Delivery::Closed {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    info!("Level stream handler ended for {}", symbol);
+}
+
+/// Levels sent per side on the SBE depth stream.
+const SBE_DEPTH_LEVELS: usize = 10;
+
+/// SBE flavour of the depth stream. Checks the top `SBE_DEPTH_LEVELS`
+/// levels per side at 10 Hz and sends a `BboMessage` when the best prices
+/// or their sizes change, then a `DepthDiffMessage` of the levels that
+/// changed. The first diff holds every level, so a subscriber builds its
+/// book from the diffs alone. Conflating a slow subscriber just skips
+/// ticks, since the next diff is taken against what it was last sent. A
+/// dropped diff is followed by a gap notice, after which the subscriber
+/// clears its book and the next diff again holds every level.
+async fn handle_sbe_depth_stream(socket: WebSocket, symbol: String, state: AppState) {
+    info!("New SBE depth stream connection for {}", symbol);
+
+    let (sender, mut receiver) = socket.split();
+    let mut out = state.ws_connections.open("sbe_depth", &symbol, format!("/symbols/{}/depth", symbol), sender);
+    let mut update_interval = interval(Duration::from_millis(100)); // 10 Hz
+    let mut ping_interval = interval(Duration::from_secs(30));
+    let mut last_levels: Vec<DepthLevel> = Vec::new();
+    let mut last_bbo: Option<BboMessage> = None;
+
+    let restart = chaos::restart(&symbol);
+    tokio::pin!(restart);
+    loop {
+        tokio::select! {
+            // Chaos: forced restart, so the client has to reconnect and resync
+            _ = &mut restart => {
+                warn!("Chaos restart of SBE depth stream for {}", symbol);
+                break;
+            }
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(WebSocketMessage::Ping { timestamp }) = serde_json::from_str::<WebSocketMessage>(&text) {
+                            if let Ok(pong_json) = serde_json::to_string(&WebSocketMessage::Pong { timestamp }) {
+                                out.send(Message::Text(pong_json));
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Ping(data))) => {
+                        out.send(Message::Pong(data));
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        error!(" WebSocket error in depth stream: {}", e);
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                }
+            }
+
+            // The first tick fires immediately, which sends the initial book
+            _ = update_interval.tick() => {
+                let Some(depth) = state.exchange.get_market_depth(&symbol, SBE_DEPTH_LEVELS).await else { continue };
+                let ts_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+                let levels = sbe_levels(&depth);
+                let bbo = BboMessage {
+                    ts_ns,
+                    symbol: symbol.clone(),
+                    bid_px: depth.bids.first().map(|l| l.price),
+                    bid_qty: depth.bids.first().map_or(0, |l| l.quantity),
+                    ask_px: depth.asks.first().map(|l| l.price),
+                    ask_qty: depth.asks.first().map_or(0, |l| l.quantity),
+                };
+
+                let bbo_changed = last_bbo.as_ref().map(|b| (b.bid_px, b.bid_qty, b.ask_px, b.ask_qty))
+                    != Some((bbo.bid_px, bbo.bid_qty, bbo.ask_px, bbo.ask_qty));
+                let diff = DepthDiffMessage::between(ts_ns, &symbol, &last_levels, &levels);
+                if !bbo_changed && diff.levels.is_empty() {
+                    continue;
+                }
+                if out.policy() == SlowPolicy::Conflate && out.queued() > 0 {
+                    out.conflated();
+                    continue;
+                }
+
+                // BBO and diff travel in one frame; `sbe::decode` reads them in turn
+                let mut frame = Vec::new();
+                if bbo_changed {
+                    bbo.encode(&mut frame);
+                }
+                if !diff.levels.is_empty() {
+                    diff.encode(&mut frame);
+                }
+                match out.send(Message::Binary(frame)) {
+                    Delivery::Queued => {
+                        if bbo_changed {
+                            last_bbo = Some(bbo);
+                        }
+                        last_levels = levels;
+                    }
+                    Delivery::Dropped => {
+                        last_levels.clear();
+                        last_bbo = None;
+                    }
+                    Delivery::Closed => break,
+                }
+            }
+
+            _ = ping_interval.tick() => {
+                let ping = WebSocketMessage::Ping {
+                    timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+                };
+                if let Ok(ping_json) = serde_json::to_string(&ping) {
+                    if out.send(Message::Text(ping_json)) == Delivery::Closed {
+                        break; // Connection broken
+                    }
+                }
+            }
+        }
+    }
+
+    info!(" SBE depth stream handler ended for {}", symbol);
+}
+
+/// The levels of a depth snapshot, bids then asks, as SBE depth levels.
+pub(crate) fn sbe_levels(depth: &MarketDepth) -> Vec<DepthLevel> {
+    let side = |levels: &[PriceLevel], side: Side| -> Vec<DepthLevel> {
+        levels.iter().map(|l| DepthLevel { side, px_ticks: l.price, qty: l.quantity }).collect()
+    };
+    let mut levels = side(&depth.bids, Side::Bid);
+    levels.extend(side(&depth.asks, Side::Ask));
+    levels
+}
+
 /// Handles a persistent order-submission WebSocket for one symbol. Clients
 /// send `batch` frames carrying a sequence number; the server replies with a
-/// `result` frame per batch echoing the same `seq`. Trades produced by the
-/// matched orders are broadcast on the trade stream as usual.
+/// `result` frame per batch echoing the same `seq`, and `quote` frames
+/// replacing an account's quote are answered with `quote_result`. Trades
+/// produced by the matched orders are broadcast on the trade stream as
+/// usual.
 ///
-/// This is the ONLY binary (MessagePack) WebSocket on the service. The trade,
-/// depth, and latency streams stay JSON; do not assume binary on those.
+/// This is the ONLY MessagePack WebSocket on the service. The trade and
+/// depth streams are JSON unless a binary `format` is asked for, and the
+/// latency stream is always JSON.
 pub async fn handle_order_stream(socket: WebSocket, symbol: String, state: AppState) {
     info!("New order stream connection for {}", symbol);
 
     let (mut sender, mut receiver) = socket.split();
     let mut ping_interval = interval(Duration::from_secs(30));
 
+    let restart = chaos::restart(&symbol);
+    tokio::pin!(restart);
     loop {
         tokio::select! {
+            // Chaos: forced restart, so the client has to reconnect and resync
+            _ = &mut restart => {
+                warn!("Chaos restart of order stream for {}", symbol);
+                break;
+            }
             msg = receiver.next() => {
                 match msg {
                     Some(Ok(Message::Binary(bytes))) => {
@@ -257,6 +541,17 @@ pub async fn handle_order_stream(socket: WebSocket, symbol: String, state: AppSt
                                     }
                                 }
                             }
+                            Ok(OrderStreamMessage::Quote { seq, quote }) => {
+                                let envelope = match process_quote(&symbol, &state, quote).await {
+                                    Ok(result) => OrderStreamMessage::QuoteResult { seq, result },
+                                    Err(message) => OrderStreamMessage::Error { seq: Some(seq), message },
+                                };
+                                if let Ok(buf) = rmp_serde::to_vec_named(&envelope) {
+                                    if sender.send(Message::Binary(buf)).await.is_err() {
+                                        break;
+                                    }
+                                }
+                            }
                             Ok(OrderStreamMessage::Ping { timestamp }) => {
                                 let pong = OrderStreamMessage::Pong { timestamp };
                                 if let Ok(buf) = rmp_serde::to_vec_named(&pong) {
@@ -321,19 +616,48 @@ async fn process_batch(
         .unwrap()
         .as_nanos();
 
+    // Listed symbols are interned as their books are created
+    let symbol_id = SymbolRegistry::global().lookup(symbol).ok_or((req.seq, "symbol not found".to_string()))?;
     let mut order_ids = Vec::with_capacity(req.orders.len());
     let mut orders = Vec::with_capacity(req.orders.len());
     for o in req.orders {
+        if let Err(age_ns) = state.exchange.check_fresh(o.ts_ns, now_ns as u64) {
+            state.exchange.release_orders(&orders);
+            let budget_us = state.exchange.latency_budget_ns().unwrap_or(0) / 1_000;
+            return Err((req.seq, format!("Stale order: sent {} us before arrival, budget {} us", age_ns / 1_000, budget_us)));
+        }
         let order_id = OrderId(uuid::Uuid::new_v4().as_u128());
         order_ids.push(order_id.0);
-        orders.push(Order {
+        let mut order = Order {
             id: order_id,
-            symbol: symbol.to_string(),
+            symbol: symbol_id,
             side: o.side,
             px_ticks: o.price,
             qty: o.quantity,
             ts_ns: now_ns,
-        });
+            tif: TimeInForce::Day,
+            expires_ns: o.expires_ns.map(u128::from),
+            display_qty: o.display_qty,
+            min_qty: o.min_qty,
+            hidden: o.hidden,
+            owner: ParticipantId::NONE,
+        };
+        // Refused as the HTTP batch refuses it, failing the whole batch
+        let checked = match check_terms(&order) {
+            Ok(()) => check_order(state, symbol, &order).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = checked {
+            state.exchange.release_orders(&orders);
+            return Err((req.seq, e.status_and_message().1));
+        }
+        if let Some(account) = &o.account {
+            if let Err(e) = state.exchange.assign_order(account, &mut order) {
+                state.exchange.release_orders(&orders);
+                return Err((req.seq, e.to_string()));
+            }
+        }
+        orders.push(order);
     }
 
     let batch_t0 = Instant::now();
@@ -345,11 +669,15 @@ async fn process_batch(
     let engine_ns = batch_t0.elapsed().as_nanos() as u64;
 
     let mut results = Vec::with_capacity(per_order.len());
-    for (idx, (trades, latency_ns)) in per_order.into_iter().enumerate() {
-        let trade_count = trades.len();
-        let filled = trade_count > 0;
+    for (idx, (report, latency_ns)) in per_order.into_iter().enumerate() {
+        let trade_count = report.trades.len();
+        let filled = report.filled_qty > 0;
+        let status = report.status.as_str().to_string();
 
-        for trade in trades {
+        for trade in report.trades {
+            if chaos::drop_message(symbol) {
+                continue;
+            }
             let _ = state.trade_broadcaster.send(TradeEvent {
                 symbol: symbol.to_string(),
                 trade,
@@ -362,6 +690,7 @@ async fn process_batch(
 
         results.push(BatchOrderResult {
             order_id: order_ids[idx],
+            status,
             filled,
             trade_count,
             latency_ns: latency_ns as u64,
@@ -375,6 +704,56 @@ async fn process_batch(
     })
 }
 
+/// Replaces the quote `req.account` has on `symbol`, as
+/// `POST /symbols/:symbol/quotes` does, broadcasting any trades.
+async fn process_quote(symbol: &str, state: &AppState, req: QuoteRequest) -> Result<QuoteResponse, String> {
+    let now_ns = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    if let Err(age_ns) = state.exchange.check_fresh(req.ts_ns, now_ns as u64) {
+        let budget_us = state.exchange.latency_budget_ns().unwrap_or(0) / 1_000;
+        return Err(format!("Stale order: sent {} us before arrival, budget {} us", age_ns / 1_000, budget_us));
+    }
+
+    let symbol_id = SymbolRegistry::global().lookup(symbol).ok_or("symbol not found".to_string())?;
+    let [mut bid, mut ask] = req.orders(symbol_id, now_ns);
+    let mut orders: Vec<Order> = Vec::with_capacity(2);
+    for order in [&mut bid, &mut ask].into_iter().filter(|order| order.qty > 0) {
+        if let Err(e) = state.exchange.assign_order(&req.account, order) {
+            state.exchange.release_orders(&orders);
+            return Err(e.to_string());
+        }
+        orders.push(order.clone());
+    }
+
+    let report = state
+        .exchange
+        .submit_quote(symbol, &req.account, bid, ask)
+        .await
+        .ok_or("symbol not found".to_string())?
+        .map_err(|e| {
+            state.exchange.release_orders(&orders);
+            e.to_string()
+        })?;
+
+    for trade in report.bid.iter().chain(&report.ask).flat_map(|report| &report.trades) {
+        if chaos::drop_message(symbol) {
+            continue;
+        }
+        let _ = state.trade_broadcaster.send(TradeEvent {
+            symbol: symbol.to_string(),
+            trade: trade.clone(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+        });
+    }
+
+    Ok(report.into())
+}
+
 /// Streams per-order latency samples produced by the server-side bot driver.
 /// Mirrors `handle_trade_stream`: split socket, `tokio::select!` over input +
 /// broadcast + 30s ping.
@@ -390,12 +769,10 @@ pub async fn handle_latency_stream(socket: WebSocket, state: AppState) {
             msg = receiver.next() => {
                 match msg {
                     Some(Ok(Message::Text(text))) => {
-                        if let Ok(ws_msg) = serde_json::from_str::<WebSocketMessage>(&text) {
-                            if let WebSocketMessage::Ping { timestamp } = ws_msg {
-                                let pong = WebSocketMessage::Pong { timestamp };
-                                if let Ok(pong_json) = serde_json::to_string(&pong) {
-                                    let _ = sender.send(Message::Text(pong_json)).await;
-                                }
+                        if let Ok(WebSocketMessage::Ping { timestamp }) = serde_json::from_str::<WebSocketMessage>(&text) {
+                            let pong = WebSocketMessage::Pong { timestamp };
+                            if let Ok(pong_json) = serde_json::to_string(&pong) {
+                                let _ = sender.send(Message::Text(pong_json)).await;
                             }
                         }
                     }
@@ -452,3 +829,262 @@ pub async fn handle_latency_stream(socket: WebSocket, state: AppState) {
 
     info!("Latency stream handler ended");
 }
+
+/// Streams one index: its latest value on connect, then every change.
+pub async fn handle_index_stream(socket: WebSocket, name: String, state: AppState) {
+    info!("New index stream connection for {}", name);
+
+    let (mut sender, mut receiver) = socket.split();
+    let mut index_rx = state.indices.subscribe();
+    let mut ping_interval = interval(Duration::from_secs(30));
+
+    if let Some(value) = state.indices.latest(&name) {
+        if let Ok(json) = serde_json::to_string(&WebSocketMessage::Index(value)) {
+            if sender.send(Message::Text(json)).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    loop {
+        tokio::select! {
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(WebSocketMessage::Ping { timestamp }) = serde_json::from_str::<WebSocketMessage>(&text) {
+                            let pong = WebSocketMessage::Pong { timestamp };
+                            if let Ok(pong_json) = serde_json::to_string(&pong) {
+                                let _ = sender.send(Message::Text(pong_json)).await;
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(data))) => {
+                        let _ = sender.send(Message::Pong(data)).await;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(_))) => {
+                        info!("Index stream connection closed for {}", name);
+                        break;
+                    }
+                    Some(Err(e)) => {
+                        error!("WebSocket error in index stream for {}: {}", name, e);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+
+            value = index_rx.recv() => {
+                match value {
+                    Ok(value) if value.name == name => {
+                        if let Ok(json) = serde_json::to_string(&WebSocketMessage::Index(value)) {
+                            if sender.send(Message::Text(json)).await.is_err() {
+                                warn!("Failed to send index value for {}", name);
+                                break;
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    // The next change carries the full value, so skipped ones don't matter
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+
+            _ = ping_interval.tick() => {
+                let ping = WebSocketMessage::Ping {
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64
+                };
+                if let Ok(ping_json) = serde_json::to_string(&ping) {
+                    if sender.send(Message::Text(ping_json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    info!("Index stream handler ended for {}", name);
+}
+
+/// Streams every surveillance alert to an operator as it is raised.
+pub async fn handle_surveillance_stream(socket: WebSocket, state: AppState) {
+    info!("New surveillance alert stream connection");
+
+    let (mut sender, mut receiver) = socket.split();
+    let mut alert_rx = state.exchange.surveillance().subscribe();
+    let mut ping_interval = interval(Duration::from_secs(30));
+
+    loop {
+        tokio::select! {
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(WebSocketMessage::Ping { timestamp }) = serde_json::from_str::<WebSocketMessage>(&text) {
+                            let pong = WebSocketMessage::Pong { timestamp };
+                            if let Ok(pong_json) = serde_json::to_string(&pong) {
+                                let _ = sender.send(Message::Text(pong_json)).await;
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(data))) => {
+                        let _ = sender.send(Message::Pong(data)).await;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(_))) => {
+                        info!("Surveillance alert stream connection closed");
+                        break;
+                    }
+                    Some(Err(e)) => {
+                        error!("WebSocket error in surveillance alert stream: {}", e);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+
+            alert = alert_rx.recv() => {
+                match alert {
+                    Ok(alert) => {
+                        if let Ok(json) = serde_json::to_string(&WebSocketMessage::SurveillanceAlert(alert)) {
+                            if sender.send(Message::Text(json)).await.is_err() {
+                                warn!("Failed to send surveillance alert");
+                                break;
+                            }
+                        }
+                    }
+                    // Alerts are also in the audit log; say how many this operator missed
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        let error = WebSocketMessage::Error { message: format!("{} alerts dropped; see the audit log", missed) };
+                        if let Ok(json) = serde_json::to_string(&error) {
+                            if sender.send(Message::Text(json)).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+
+            _ = ping_interval.tick() => {
+                let ping = WebSocketMessage::Ping {
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64
+                };
+                if let Ok(ping_json) = serde_json::to_string(&ping) {
+                    if sender.send(Message::Text(ping_json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    info!("Surveillance alert stream handler ended");
+}
+
+/// `account`'s current positions as an `account` message; empty for an
+/// account that hasn't traded yet.
+fn account_snapshot(account: &str, state: &AppState) -> Message {
+    let view = state
+        .exchange
+        .account_view(account)
+        .unwrap_or_else(|| AccountView { account: account.to_string(), positions: Vec::new(), open_orders: 0, blocked: false, margin: None });
+    Message::Text(serde_json::to_string(&WebSocketMessage::Account(view)).expect("account views serialize"))
+}
+
+/// WebSocket handler for one account's private stream: a position snapshot
+/// on connect, then its fills and funding payments plus mark updates for
+/// symbols it holds. A subscriber that falls behind gets a fresh snapshot
+/// in place of the events it missed.
+pub async fn handle_account_stream(socket: WebSocket, account: String, state: AppState) {
+    info!("New account stream connection for {}", account);
+
+    let (mut sender, mut receiver) = socket.split();
+    let mut account_rx = state.exchange.accounts().subscribe();
+    let mut ping_interval = interval(Duration::from_secs(30));
+
+    if sender.send(account_snapshot(&account, &state)).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(WebSocketMessage::Ping { timestamp }) = serde_json::from_str::<WebSocketMessage>(&text) {
+                            let pong = WebSocketMessage::Pong { timestamp };
+                            if let Ok(pong_json) = serde_json::to_string(&pong) {
+                                let _ = sender.send(Message::Text(pong_json)).await;
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {}
+                    Some(Ok(Message::Ping(data))) => {
+                        let _ = sender.send(Message::Pong(data)).await;
+                    }
+                    Some(Ok(Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(_))) => {
+                        info!("Account stream connection closed for {}", account);
+                        break;
+                    }
+                    Some(Err(e)) => {
+                        error!("WebSocket error in account stream for {}: {}", account, e);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+
+            event = account_rx.recv() => {
+                match event {
+                    Ok(event) => {
+                        let ours = match event.account() {
+                            Some(owner) => owner == account,
+                            None => event.symbol().is_some_and(|symbol| state.exchange.accounts().holds(&account, symbol)),
+                        };
+                        if !ours {
+                            continue;
+                        }
+                        if let Ok(json) = serde_json::to_string(&WebSocketMessage::AccountEvent(event)) {
+                            if sender.send(Message::Text(json)).await.is_err() {
+                                warn!("Failed to send account event for {}", account);
+                                break;
+                            }
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        if sender.send(account_snapshot(&account, &state)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+
+            _ = ping_interval.tick() => {
+                let ping = WebSocketMessage::Ping {
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_millis() as u64
+                };
+                if let Ok(ping_json) = serde_json::to_string(&ping) {
+                    if sender.send(Message::Text(ping_json)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    info!("Account stream handler ended for {}", account);
+}
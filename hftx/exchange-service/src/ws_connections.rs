@@ -0,0 +1,328 @@
+//! Send queues for the trade and depth streams, and slow subscribers.
+//!
+//! Each trade or depth stream connection writes through a bounded queue
+//! (`EXCHANGE_WS_MAX_QUEUE` messages) that its own task drains into the
+//! socket, so a slow client backs up its queue rather than its handler. A
+//! subscriber is behind when its queue is full, or when the shared trade
+//! broadcast overtook it. What happens then is `EXCHANGE_WS_SLOW_POLICY`:
+//!
+//! - `conflate` (default): a depth update isn't queued while the previous
+//!   one is still waiting, and the next tick sends the book as it is then,
+//!   so only intermediate states are lost. Trades can't be merged, so the
+//!   trade stream does as `gap`.
+//! - `gap`: messages are dropped until there is room again, then a `gap`
+//!   message goes first, with how many were missed and where to resync.
+//! - `disconnect`: the connection is closed with code 1008.
+//!
+//! `GET /ws/connections` reports every connection's queue depth, broadcast
+//! lag, and counters.
+
+use axum::extract::ws::{CloseFrame, Message, WebSocket};
+use dashmap::DashMap;
+use futures::stream::SplitSink;
+use futures::SinkExt;
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+use crate::types::WebSocketMessage;
+
+/// Policy-violation close code, sent to subscribers cut off for lagging
+const SLOW_CLOSE_CODE: u16 = 1008;
+/// Longest a slow client gets to take the close frame
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowPolicy {
+    Conflate,
+    Gap,
+    Disconnect,
+}
+
+impl FromStr for SlowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "conflate" => Ok(SlowPolicy::Conflate),
+            "gap" => Ok(SlowPolicy::Gap),
+            "disconnect" => Ok(SlowPolicy::Disconnect),
+            other => Err(format!("{}: expected conflate, gap, or disconnect", other)),
+        }
+    }
+}
+
+/// One connection's counters.
+struct ConnStats {
+    stream: &'static str,
+    symbol: String,
+    opened_ms: u64,
+    queued: AtomicUsize,
+    max_queued: AtomicUsize,
+    lag: AtomicUsize,
+    sent: AtomicU64,
+    dropped: AtomicU64,
+    conflated: AtomicU64,
+    gaps: AtomicU64,
+}
+
+/// Served by `GET /ws/connections`, one per open connection.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionView {
+    pub id: u64,
+    /// `trades`, `depth`, or `sbe_depth`
+    pub stream: &'static str,
+    pub symbol: String,
+    pub opened_ms: u64,
+    /// Messages waiting for, or being written to, the socket
+    pub queued: usize,
+    pub max_queued: usize,
+    /// Trade broadcasts not yet read (trade stream only)
+    pub lag: usize,
+    pub sent: u64,
+    /// Dropped for a full queue or a lagged broadcast
+    pub dropped: u64,
+    /// Depth updates merged into a later one
+    pub conflated: u64,
+    /// Gap notices sent
+    pub gaps: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionsReport {
+    pub policy: SlowPolicy,
+    pub max_queue: usize,
+    pub connections: Vec<ConnectionView>,
+}
+
+pub struct WsConnections {
+    policy: SlowPolicy,
+    max_queue: usize,
+    next_id: AtomicU64,
+    open: DashMap<u64, Arc<ConnStats>>,
+}
+
+impl WsConnections {
+    pub fn new(policy: SlowPolicy, max_queue: usize) -> Self {
+        Self { policy, max_queue: max_queue.max(1), next_id: AtomicU64::new(1), open: DashMap::new() }
+    }
+
+    /// Takes over `sink` for a new `stream` connection on `symbol`. Gap
+    /// notices point at `resync`.
+    pub fn open(
+        self: &Arc<Self>,
+        stream: &'static str,
+        symbol: &str,
+        resync: String,
+        sink: SplitSink<WebSocket, Message>,
+    ) -> Outbound {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let stats = Arc::new(ConnStats {
+            stream,
+            symbol: symbol.to_string(),
+            opened_ms: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64,
+            queued: AtomicUsize::new(0),
+            max_queued: AtomicUsize::new(0),
+            lag: AtomicUsize::new(0),
+            sent: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            conflated: AtomicU64::new(0),
+            gaps: AtomicU64::new(0),
+        });
+        self.open.insert(id, stats.clone());
+        let (tx, rx) = mpsc::channel(self.max_queue);
+        let (close_tx, close_rx) = oneshot::channel();
+        tokio::spawn(write(sink, rx, close_rx, stats.clone()));
+        Outbound {
+            id,
+            registry: self.clone(),
+            stats,
+            tx,
+            close: Some(close_tx),
+            resync,
+            resync_from: None,
+            missed: 0,
+        }
+    }
+
+    pub fn report(&self) -> ConnectionsReport {
+        let mut connections: Vec<ConnectionView> = self
+            .open
+            .iter()
+            .map(|entry| {
+                let s = entry.value();
+                ConnectionView {
+                    id: *entry.key(),
+                    stream: s.stream,
+                    symbol: s.symbol.clone(),
+                    opened_ms: s.opened_ms,
+                    queued: s.queued.load(Ordering::Relaxed),
+                    max_queued: s.max_queued.load(Ordering::Relaxed),
+                    lag: s.lag.load(Ordering::Relaxed),
+                    sent: s.sent.load(Ordering::Relaxed),
+                    dropped: s.dropped.load(Ordering::Relaxed),
+                    conflated: s.conflated.load(Ordering::Relaxed),
+                    gaps: s.gaps.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        connections.sort_by_key(|c| c.id);
+        ConnectionsReport { policy: self.policy, max_queue: self.max_queue, connections }
+    }
+}
+
+/// Drains the queue into the socket until either side goes away, or sends
+/// a close frame in its place when asked to.
+async fn write(
+    mut sink: SplitSink<WebSocket, Message>,
+    mut rx: mpsc::Receiver<Message>,
+    mut close_rx: oneshot::Receiver<CloseFrame<'static>>,
+    stats: Arc<ConnStats>,
+) {
+    let close = loop {
+        let msg = tokio::select! {
+            biased;
+            close = &mut close_rx => break close.ok(),
+            msg = rx.recv() => match msg {
+                Some(msg) => msg,
+                None => break None,
+            },
+        };
+        // A client too slow to take this message must still be closable
+        let sent = tokio::select! {
+            biased;
+            close = &mut close_rx => break close.ok(),
+            sent = sink.send(msg) => sent,
+        };
+        stats.queued.fetch_sub(1, Ordering::Relaxed);
+        if sent.is_err() {
+            break None;
+        }
+        stats.sent.fetch_add(1, Ordering::Relaxed);
+    };
+    if let Some(frame) = close {
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, sink.send(Message::Close(Some(frame)))).await;
+    }
+}
+
+/// What became of a message handed to [`Outbound::send`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Queued,
+    /// The queue was full; a gap notice will precede the next message
+    Dropped,
+    /// The connection is gone or was cut off; stop serving it
+    Closed,
+}
+
+/// A connection's side of its send queue. Dropping it unregisters the
+/// connection and lets the writer finish the queue.
+pub struct Outbound {
+    id: u64,
+    registry: Arc<WsConnections>,
+    stats: Arc<ConnStats>,
+    tx: mpsc::Sender<Message>,
+    close: Option<oneshot::Sender<CloseFrame<'static>>>,
+    resync: String,
+    /// Where the subscriber can resync from, e.g. its last trade's `ts_ns`
+    resync_from: Option<u64>,
+    /// Messages lost since the last gap notice
+    missed: u64,
+}
+
+impl Outbound {
+    pub fn policy(&self) -> SlowPolicy {
+        self.registry.policy
+    }
+
+    /// Messages waiting for the socket.
+    pub fn queued(&self) -> usize {
+        self.stats.queued.load(Ordering::Relaxed)
+    }
+
+    /// Records how far behind the subscriber's broadcast receiver is.
+    pub fn set_lag(&self, lag: usize) {
+        self.stats.lag.store(lag, Ordering::Relaxed);
+    }
+
+    pub fn set_resync_from(&mut self, from: u64) {
+        self.resync_from = Some(from);
+    }
+
+    /// Counts a depth update merged into the next one.
+    pub fn conflated(&self) {
+        self.stats.conflated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Queues `msg`, after a gap notice if messages were lost since the
+    /// last one. A full queue drops it, or closes the connection under
+    /// `disconnect`.
+    pub fn send(&mut self, msg: Message) -> Delivery {
+        if self.missed > 0 {
+            let gap = WebSocketMessage::Gap { missed: self.missed, resync: self.resync.clone(), from_ns: self.resync_from };
+            let gap = Message::Text(serde_json::to_string(&gap).expect("gap notices serialize"));
+            match self.enqueue(gap) {
+                Delivery::Queued => {
+                    self.stats.gaps.fetch_add(1, Ordering::Relaxed);
+                    self.missed = 0;
+                }
+                other => return other,
+            }
+        }
+        self.enqueue(msg)
+    }
+
+    /// Counts `n` messages lost before reaching the queue (a lagged
+    /// broadcast). Under `disconnect` that closes the connection.
+    pub fn lost(&mut self, n: u64) -> Delivery {
+        self.stats.dropped.fetch_add(n, Ordering::Relaxed);
+        if self.registry.policy == SlowPolicy::Disconnect {
+            return self.disconnect(format!("slow consumer: {} messages behind", n));
+        }
+        self.missed += n;
+        Delivery::Dropped
+    }
+
+    fn enqueue(&mut self, msg: Message) -> Delivery {
+        // Counted first, so the writer never takes it below zero
+        let queued = self.stats.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        match self.tx.try_send(msg) {
+            Ok(()) => {
+                self.stats.max_queued.fetch_max(queued, Ordering::Relaxed);
+                Delivery::Queued
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.stats.queued.fetch_sub(1, Ordering::Relaxed);
+                match self.lost(1) {
+                    Delivery::Closed => Delivery::Closed,
+                    _ => Delivery::Dropped,
+                }
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                self.stats.queued.fetch_sub(1, Ordering::Relaxed);
+                Delivery::Closed
+            }
+        }
+    }
+
+    /// Closes the connection with a policy-violation frame, skipping
+    /// whatever is still queued.
+    fn disconnect(&mut self, reason: String) -> Delivery {
+        if let Some(close) = self.close.take() {
+            warn!("Disconnecting {} stream subscriber for {}: {}", self.stats.stream, self.stats.symbol, reason);
+            let _ = close.send(CloseFrame { code: SLOW_CLOSE_CODE, reason: reason.into() });
+        }
+        Delivery::Closed
+    }
+}
+
+impl Drop for Outbound {
+    fn drop(&mut self) {
+        self.registry.open.remove(&self.id);
+    }
+}
@@ -0,0 +1,101 @@
+//! Optional ZeroMQ PUB socket broadcasting SBE market data.
+//!
+//! For tooling that already speaks ZeroMQ pub/sub: subscribe to a symbol
+//! and read the same SBE messages the WebSocket feeds carry, with no HTTP
+//! or WebSocket client. Off unless `EXCHANGE_ZMQ_BIND` is set (e.g.
+//! `tcp://0.0.0.0:5556`).
+//!
+//! Every message has two frames: the symbol as the topic, then one or more
+//! SBE messages back to back, read in turn with `orderbook::sbe::decode`.
+//!
+//! - each trade: a `TradeMessage`
+//! - depth, checked per symbol at 10 Hz: a `BboMessage` then a
+//!   `DepthDiffMessage` holding the complete top `DEPTH_LEVELS` per side,
+//!   so each one replaces the subscriber's book. Sent when the levels
+//!   changed, and for every symbol once a second so late joiners catch up.
+//!
+//! ZeroMQ matches topics by prefix, so subscribing to `AAPL` also delivers
+//! `AAPLX`; compare the topic frame to filter exactly. A subscriber that
+//! can't keep up loses messages rather than slowing the publisher.
+
+use orderbook::sbe::{BboMessage, DepthDiffMessage, DepthLevel, TradeMessage, HEADER_LEN};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::interval;
+use tracing::{info, warn};
+use zeromq::{PubSocket, Socket, SocketSend, ZmqMessage};
+
+use crate::exchange::Exchange;
+use crate::types::{MarketDepth, TradeEvent};
+use crate::websocket::sbe_levels;
+
+/// Levels per side in each depth message.
+const DEPTH_LEVELS: usize = 10;
+
+/// Binds the PUB socket and publishes until the trade channel closes.
+pub async fn run(endpoint: String, exchange: Arc<Exchange>, mut trades: broadcast::Receiver<TradeEvent>) {
+    let mut socket = PubSocket::new();
+    if let Err(e) = socket.bind(&endpoint).await {
+        warn!("ZeroMQ publisher disabled, cannot bind {}: {}", endpoint, e);
+        return;
+    }
+    info!("Publishing SBE market data over ZeroMQ on {}", endpoint);
+
+    let mut depth_interval = interval(Duration::from_millis(100)); // 10 Hz
+    let mut refresh_interval = interval(Duration::from_secs(1));
+    let mut last_levels: HashMap<String, Vec<DepthLevel>> = HashMap::new();
+
+    loop {
+        tokio::select! {
+            trade = trades.recv() => {
+                match trade {
+                    Ok(event) => {
+                        let mut frame = Vec::with_capacity(HEADER_LEN + TradeMessage::BLOCK_LENGTH);
+                        TradeMessage::from(&event.trade).encode(&mut frame);
+                        publish(&mut socket, &event.symbol, frame).await;
+                    }
+                    Err(RecvError::Lagged(missed)) => warn!("ZeroMQ publisher fell behind and skipped {} trades", missed),
+                    Err(RecvError::Closed) => break,
+                }
+            }
+            // Forgetting what was sent makes the next depth tick resend everything
+            _ = refresh_interval.tick() => last_levels.clear(),
+            _ = depth_interval.tick() => {
+                for symbol in exchange.list_symbols().await {
+                    let Some(depth) = exchange.get_market_depth(&symbol, DEPTH_LEVELS).await else { continue };
+                    let levels = sbe_levels(&depth);
+                    if last_levels.get(&symbol) == Some(&levels) {
+                        continue;
+                    }
+                    let ts_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as u64;
+                    let mut frame = Vec::new();
+                    bbo(ts_ns, &depth).encode(&mut frame);
+                    DepthDiffMessage { ts_ns, symbol: symbol.clone(), levels: levels.clone() }.encode(&mut frame);
+                    publish(&mut socket, &symbol, frame).await;
+                    last_levels.insert(symbol, levels);
+                }
+            }
+        }
+    }
+}
+
+async fn publish(socket: &mut PubSocket, symbol: &str, frame: Vec<u8>) {
+    let mut message = ZmqMessage::from(symbol.to_string());
+    message.push_back(frame.into());
+    if let Err(e) = socket.send(message).await {
+        warn!("ZeroMQ publish for {} failed: {}", symbol, e);
+    }
+}
+
+fn bbo(ts_ns: u64, depth: &MarketDepth) -> BboMessage {
+    BboMessage {
+        ts_ns,
+        symbol: depth.symbol.clone(),
+        bid_px: depth.bids.first().map(|l| l.price),
+        bid_qty: depth.bids.first().map_or(0, |l| l.quantity),
+        ask_px: depth.asks.first().map(|l| l.price),
+        ask_qty: depth.asks.first().map_or(0, |l| l.quantity),
+    }
+}
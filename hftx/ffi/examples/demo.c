@@ -0,0 +1,52 @@
+/* Minimal embedding example.
+ *
+ *   cargo build --release -p hftx-ffi
+ *   cc -I ffi/include ffi/examples/demo.c -L target/release -lhftx_ffi -o demo
+ *   LD_LIBRARY_PATH=target/release ./demo
+ */
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "hftx.h"
+
+int main(void) {
+    HftxBook *book = hftx_book_new("AAPL");
+    size_t n = 0;
+
+    hftx_submit(book, 1, HFTX_SIDE_ASK, 15000, 100, 1, &n);
+    hftx_submit(book, 2, HFTX_SIDE_ASK, 15001, 50, 2, &n);
+    if (hftx_submit(book, 3, HFTX_SIDE_BID, 15001, 120, 3, &n) != HFTX_STATUS_OK) {
+        fprintf(stderr, "submit failed\n");
+        return 1;
+    }
+
+    const HftxTrade *trades = hftx_trades(book, &n);
+    for (size_t i = 0; i < n; i++) {
+        printf("trade maker=%llu taker=%llu px=%lld qty=%lld\n",
+               (unsigned long long)trades[i].maker, (unsigned long long)trades[i].taker,
+               (long long)trades[i].px_ticks, (long long)trades[i].qty);
+    }
+
+    hftx_submit(book, 4, HFTX_SIDE_BID, 14990, 10, 4, NULL);
+    hftx_cancel(book, 4);
+
+    /* Size the snapshot first, then fill it */
+    size_t len = 0;
+    hftx_snapshot(book, NULL, 0, &len);
+    HftxOrder *orders = malloc(len * sizeof *orders);
+    if (hftx_snapshot(book, orders, len, &len) == HFTX_STATUS_OK) {
+        for (size_t i = 0; i < len; i++) {
+            printf("resting id=%llu %s px=%lld qty=%lld\n", (unsigned long long)orders[i].id,
+                   orders[i].side == HFTX_SIDE_BID ? "bid" : "ask",
+                   (long long)orders[i].px_ticks, (long long)orders[i].qty);
+        }
+    }
+    free(orders);
+
+    int64_t px;
+    if (hftx_best_ask(book, &px)) {
+        printf("best ask %lld, %zu resting\n", (long long)px, hftx_resting_count(book));
+    }
+    hftx_book_free(book);
+    return 0;
+}
@@ -0,0 +1,168 @@
+#ifndef HFTX_H
+#define HFTX_H
+
+/* Generated by cbindgen from hftx/ffi/src/lib.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define HFTX_SIDE_BID 0
+
+#define HFTX_SIDE_ASK 1
+
+/**
+ * Result of a fallible call.
+ */
+typedef enum HftxStatus {
+  HFTX_STATUS_OK = 0,
+  HFTX_STATUS_NULL_POINTER = 1,
+  HFTX_STATUS_INVALID_SIDE = 2,
+  /**
+   * A quantity at or below zero, or one the book's totals can't hold
+   */
+  HFTX_STATUS_INVALID_QTY = 3,
+  /**
+   * The id is already resting in the book or held as a stop
+   */
+  HFTX_STATUS_DUPLICATE_ID = 4,
+  /**
+   * The output buffer can't hold the result; the needed length was written
+   */
+  HFTX_STATUS_BUFFER_TOO_SMALL = 5,
+  HFTX_STATUS_PANIC = 6,
+  /**
+   * A price at or below zero
+   */
+  HFTX_STATUS_INVALID_PRICE = 7,
+} HftxStatus;
+
+/**
+ * Opaque order book handle.
+ */
+typedef struct HftxBook HftxBook;
+
+/**
+ * One fill, at the maker's price.
+ */
+typedef struct HftxTrade {
+  uint64_t maker;
+  uint64_t taker;
+  int64_t px_ticks;
+  int64_t qty;
+  uint64_t ts_ns;
+} HftxTrade;
+
+/**
+ * One live resting order, as reported by `hftx_snapshot`.
+ */
+typedef struct HftxOrder {
+  uint64_t id;
+  /**
+   * `HFTX_SIDE_BID` or `HFTX_SIDE_ASK`
+   */
+  uint8_t side;
+  int64_t px_ticks;
+  int64_t qty;
+  uint64_t ts_ns;
+} HftxOrder;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an empty book. `symbol` may be null (defaults to "BOOK").
+ * Returns null if `symbol` is not valid UTF-8.
+ *
+ * # Safety
+ * `symbol` must be null or a NUL-terminated string.
+ */
+struct HftxBook *hftx_book_new(const char *symbol);
+
+/**
+ * Frees a book. Null is a no-op.
+ *
+ * # Safety
+ * `book` must be null or come from `hftx_book_new`, and not be used again.
+ */
+void hftx_book_free(struct HftxBook *book);
+
+/**
+ * Submits a limit order. On success the number of trades it printed is
+ * written to `trade_count` (may be null); read them with `hftx_trades`.
+ *
+ * # Safety
+ * `book` must be a live handle; `trade_count` null or writable.
+ */
+enum HftxStatus hftx_submit(struct HftxBook *book,
+                            uint64_t id,
+                            uint8_t side,
+                            int64_t px_ticks,
+                            int64_t qty,
+                            uint64_t ts_ns,
+                            size_t *trade_count);
+
+/**
+ * Trades printed by the most recent submit; their count goes to `len`.
+ * Valid until the next `hftx_submit` or `hftx_book_free` on this book.
+ *
+ * # Safety
+ * `book` must be null or a live handle; `len` null or writable.
+ */
+const struct HftxTrade *hftx_trades(const struct HftxBook *book, size_t *len);
+
+/**
+ * Cancels a resting order on either side; true if it was live.
+ *
+ * # Safety
+ * `book` must be null or a live handle.
+ */
+bool hftx_cancel(struct HftxBook *book, uint64_t id);
+
+/**
+ * Writes the best bid to `px_ticks`; false if there are no bids.
+ *
+ * # Safety
+ * `book` must be null or a live handle; `px_ticks` null or writable.
+ */
+bool hftx_best_bid(const struct HftxBook *book, int64_t *px_ticks);
+
+/**
+ * Writes the best ask to `px_ticks`; false if there are no asks.
+ *
+ * # Safety
+ * `book` must be null or a live handle; `px_ticks` null or writable.
+ */
+bool hftx_best_ask(const struct HftxBook *book, int64_t *px_ticks);
+
+/**
+ * Number of live resting orders on both sides.
+ *
+ * # Safety
+ * `book` must be null or a live handle.
+ */
+size_t hftx_resting_count(const struct HftxBook *book);
+
+/**
+ * Copies every live resting order into `out` (bids then asks, each in
+ * priority order) and writes how many there are to `len`. If `capacity`
+ * is too small nothing is copied and `HFTX_STATUS_BUFFER_TOO_SMALL` is
+ * returned, so callers can size the buffer by passing null and 0 first.
+ *
+ * # Safety
+ * `book` must be a live handle; `len` writable; `out` valid for
+ * `capacity` writes when `capacity` is non-zero.
+ */
+enum HftxStatus hftx_snapshot(const struct HftxBook *book,
+                              struct HftxOrder *out,
+                              size_t capacity,
+                              size_t *len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HFTX_H */
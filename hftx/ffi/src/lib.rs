@@ -0,0 +1,285 @@
+//! C API for embedding the matching engine.
+//!
+//! Built as `libhftx_ffi.{so,dylib,a}`. The header `include/hftx.h` is
+//! regenerated from this file by the build script. The API is stable: new
+//! functions may be added, existing signatures and struct layouts don't change.
+//!
+//! Conventions:
+//! - A book is an opaque `HftxBook*` from `hftx_book_new`, released with
+//!   `hftx_book_free`. One book must not be used from two threads at once.
+//! - Fallible calls return an `HftxStatus`; anything else reports through
+//!   out-pointers. Panics are caught and reported as `HFTX_STATUS_PANIC`.
+//! - Ids are `uint64_t`, prices integer ticks, sizes integer quantities.
+//! - Trades from a submit stay in a buffer owned by the book; the pointer
+//!   from `hftx_trades` is valid until the next submit or free.
+
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, RejectReason, Side, SymbolId, TimeInForce, Trade};
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+pub const HFTX_SIDE_BID: u8 = 0;
+pub const HFTX_SIDE_ASK: u8 = 1;
+
+/// Result of a fallible call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HftxStatus {
+    Ok = 0,
+    NullPointer = 1,
+    InvalidSide = 2,
+    /// A quantity at or below zero, or one the book's totals can't hold
+    InvalidQty = 3,
+    /// The id is already resting in the book or held as a stop
+    DuplicateId = 4,
+    /// The output buffer can't hold the result; the needed length was written
+    BufferTooSmall = 5,
+    Panic = 6,
+    /// A price at or below zero
+    InvalidPrice = 7,
+}
+
+/// One fill, at the maker's price.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HftxTrade {
+    pub maker: u64,
+    pub taker: u64,
+    pub px_ticks: i64,
+    pub qty: i64,
+    pub ts_ns: u64,
+}
+
+/// One live resting order, as reported by `hftx_snapshot`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HftxOrder {
+    pub id: u64,
+    /// `HFTX_SIDE_BID` or `HFTX_SIDE_ASK`
+    pub side: u8,
+    pub px_ticks: i64,
+    pub qty: i64,
+    pub ts_ns: u64,
+}
+
+/// Opaque order book handle.
+pub struct HftxBook {
+    book: OrderBook,
+    symbol: SymbolId,
+    /// Trades printed by the most recent submit
+    trades: Vec<HftxTrade>,
+}
+
+fn guard(f: impl FnOnce() -> HftxStatus) -> HftxStatus {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or(HftxStatus::Panic)
+}
+
+fn side_from(side: u8) -> Option<Side> {
+    match side {
+        HFTX_SIDE_BID => Some(Side::Bid),
+        HFTX_SIDE_ASK => Some(Side::Ask),
+        _ => None,
+    }
+}
+
+fn c_trade(trade: &Trade) -> HftxTrade {
+    HftxTrade {
+        maker: trade.maker.0 as u64,
+        taker: trade.taker.0 as u64,
+        px_ticks: trade.px_ticks,
+        qty: trade.qty,
+        ts_ns: trade.ts_ns as u64,
+    }
+}
+
+/// Creates an empty book. `symbol` may be null (defaults to "BOOK").
+/// Returns null if `symbol` is not valid UTF-8.
+///
+/// # Safety
+/// `symbol` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_book_new(symbol: *const c_char) -> *mut HftxBook {
+    let symbol = if symbol.is_null() {
+        SymbolId::intern("BOOK")
+    } else {
+        match CStr::from_ptr(symbol).to_str() {
+            Ok(s) => SymbolId::intern(s),
+            Err(_) => return ptr::null_mut(),
+        }
+    };
+    Box::into_raw(Box::new(HftxBook { book: OrderBook::new(), symbol, trades: Vec::new() }))
+}
+
+/// Frees a book. Null is a no-op.
+///
+/// # Safety
+/// `book` must be null or come from `hftx_book_new`, and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_book_free(book: *mut HftxBook) {
+    if !book.is_null() {
+        drop(Box::from_raw(book));
+    }
+}
+
+/// Submits a limit order. On success the number of trades it printed is
+/// written to `trade_count` (may be null); read them with `hftx_trades`.
+///
+/// # Safety
+/// `book` must be a live handle; `trade_count` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_submit(
+    book: *mut HftxBook,
+    id: u64,
+    side: u8,
+    px_ticks: i64,
+    qty: i64,
+    ts_ns: u64,
+    trade_count: *mut usize,
+) -> HftxStatus {
+    let Some(book) = book.as_mut() else {
+        return HftxStatus::NullPointer;
+    };
+    guard(|| {
+        let Some(side) = side_from(side) else {
+            return HftxStatus::InvalidSide;
+        };
+        let report = book.book.submit_limit(Order {
+            id: OrderId(id as u128),
+            symbol: book.symbol,
+            side,
+            px_ticks,
+            qty,
+            ts_ns: ts_ns as u128,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        });
+        let report = match report {
+            Ok(report) => report,
+            Err(RejectReason::InvalidQuantity(_) | RejectReason::QuantityOverflow(_)) => return HftxStatus::InvalidQty,
+            Err(RejectReason::InvalidPrice(_)) => return HftxStatus::InvalidPrice,
+            Err(RejectReason::DuplicateId(_)) => return HftxStatus::DuplicateId,
+            // The FFI book has no throttle or config and takes any symbol
+            Err(other) => unreachable!("refused by an unconfigured book: {}", other),
+        };
+        book.trades.clear();
+        book.trades.extend(report.trades.iter().map(c_trade));
+        if let Some(count) = trade_count.as_mut() {
+            *count = book.trades.len();
+        }
+        HftxStatus::Ok
+    })
+}
+
+/// Trades printed by the most recent submit; their count goes to `len`.
+/// Valid until the next `hftx_submit` or `hftx_book_free` on this book.
+///
+/// # Safety
+/// `book` must be null or a live handle; `len` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_trades(book: *const HftxBook, len: *mut usize) -> *const HftxTrade {
+    let trades = book.as_ref().map_or(&[][..], |b| &b.trades[..]);
+    if let Some(len) = len.as_mut() {
+        *len = trades.len();
+    }
+    trades.as_ptr()
+}
+
+/// Cancels a resting order on either side; true if it was live.
+///
+/// # Safety
+/// `book` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_cancel(book: *mut HftxBook, id: u64) -> bool {
+    let Some(book) = book.as_mut() else {
+        return false;
+    };
+    let id = OrderId(id as u128);
+    book.book.cancel(id)
+}
+
+/// Writes the best bid to `px_ticks`; false if there are no bids.
+///
+/// # Safety
+/// `book` must be null or a live handle; `px_ticks` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_best_bid(book: *const HftxBook, px_ticks: *mut i64) -> bool {
+    write_price(book.as_ref().and_then(|b| b.book.best_bid()), px_ticks)
+}
+
+/// Writes the best ask to `px_ticks`; false if there are no asks.
+///
+/// # Safety
+/// `book` must be null or a live handle; `px_ticks` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_best_ask(book: *const HftxBook, px_ticks: *mut i64) -> bool {
+    write_price(book.as_ref().and_then(|b| b.book.best_ask()), px_ticks)
+}
+
+unsafe fn write_price(px: Option<i64>, out: *mut i64) -> bool {
+    match (px, out.as_mut()) {
+        (Some(px), Some(out)) => {
+            *out = px;
+            true
+        }
+        (px, _) => px.is_some(),
+    }
+}
+
+/// Number of live resting orders on both sides.
+///
+/// # Safety
+/// `book` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_resting_count(book: *const HftxBook) -> usize {
+    book.as_ref().map_or(0, |b| b.book.bids.total_len() + b.book.asks.total_len())
+}
+
+/// Copies every live resting order into `out` (bids then asks, each in
+/// priority order) and writes how many there are to `len`. If `capacity`
+/// is too small nothing is copied and `HFTX_STATUS_BUFFER_TOO_SMALL` is
+/// returned, so callers can size the buffer by passing null and 0 first.
+///
+/// # Safety
+/// `book` must be a live handle; `len` writable; `out` valid for
+/// `capacity` writes when `capacity` is non-zero.
+#[no_mangle]
+pub unsafe extern "C" fn hftx_snapshot(
+    book: *const HftxBook,
+    out: *mut HftxOrder,
+    capacity: usize,
+    len: *mut usize,
+) -> HftxStatus {
+    let (Some(book), Some(len)) = (book.as_ref(), len.as_mut()) else {
+        return HftxStatus::NullPointer;
+    };
+    guard(|| {
+        let orders: Vec<HftxOrder> = [(Side::Bid, &book.book.bids), (Side::Ask, &book.book.asks)]
+            .into_iter()
+            .flat_map(|(side, levels)| {
+                levels.live_orders().map(move |o| HftxOrder {
+                    id: o.id.0 as u64,
+                    side: match side {
+                        Side::Bid => HFTX_SIDE_BID,
+                        Side::Ask => HFTX_SIDE_ASK,
+                    },
+                    px_ticks: o.px_ticks,
+                    qty: o.qty,
+                    ts_ns: o.ts_ns as u64,
+                })
+            })
+            .collect();
+        *len = orders.len();
+        if orders.len() > capacity {
+            return HftxStatus::BufferTooSmall;
+        }
+        if out.is_null() {
+            return if orders.is_empty() { HftxStatus::Ok } else { HftxStatus::NullPointer };
+        }
+        ptr::copy_nonoverlapping(orders.as_ptr(), out, orders.len());
+        HftxStatus::Ok
+    })
+}
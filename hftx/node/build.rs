@@ -0,0 +1,6 @@
+//! Sets the linker flags Node addons need (undefined N-API symbols resolve
+//! against the host `node` process at load time).
+
+fn main() {
+    napi_build::setup();
+}
@@ -0,0 +1,51 @@
+/* tslint:disable */
+/* eslint-disable */
+
+/* auto-generated by NAPI-RS */
+
+export interface Trade {
+  maker: number
+  taker: number
+  symbol: string
+  px_ticks: number
+  qty: number
+  ts_ns: number
+}
+export interface PriceLevel {
+  price: number
+  quantity: number
+  orders: number
+}
+export interface MarketDepth {
+  symbol: string
+  bids: Array<PriceLevel>
+  asks: Array<PriceLevel>
+}
+/** A live resting order, as listed by `snapshot`. */
+export interface RestingOrder {
+  id: number
+  side: string
+  price: number
+  quantity: number
+  ts_ns: number
+}
+/** A single-symbol limit order book with price-time priority. */
+export class OrderBook {
+  constructor(symbol: string)
+  get symbol(): string
+  /**
+   * Submits a limit order and returns the trades it printed.
+   * `tsNs` defaults to 0; pass a clock if trade times matter.
+   */
+  submit(id: number, side: string, price: number, quantity: number, tsNs?: number | undefined | null): Array<Trade>
+  /** Cancels a resting order on either side; true if it was live. */
+  cancel(id: number): boolean
+  /** Top `levels` price levels per side. */
+  depth(levelsPerSide: number): MarketDepth
+  /** Live resting orders, bids then asks, each side in priority order. */
+  snapshot(): Array<RestingOrder>
+  get bestBid(): number | null
+  get bestAsk(): number | null
+  /** Live resting orders on both sides. */
+  get resting(): number
+}
@@ -0,0 +1,3 @@
+// Loads the addon built by `make node` (`napi build` copies the cdylib
+// here as hftx-node.node).
+module.exports = require('./hftx-node.node')
@@ -0,0 +1,202 @@
+//! Node.js bindings for the matching engine (napi-rs).
+//!
+//! Lets JavaScript and TypeScript strategy code run against the exact book
+//! the exchange-service uses, in-process and without a server. The API
+//! mirrors `hftx-wasm`, and results are plain objects shaped like the
+//! service's JSON (snake_case fields, as in `web/lib/types.ts`).
+//! `index.d.ts` holds the TypeScript definitions.
+//!
+//! ```js
+//! const { OrderBook } = require("hftx-node");
+//! const book = new OrderBook("AAPL");
+//! book.submit(1, "ask", 15000, 100);
+//! book.submit(2, "bid", 15000, 40);  // [{ maker: 1, taker: 2, px_ticks: 15000, qty: 40, ... }]
+//! book.depth(10);                    // { symbol, bids: [{ price, quantity, orders }], asks }
+//! ```
+//!
+//! Ids, prices, and sizes are JS numbers; they must be integers within
+//! `Number.MAX_SAFE_INTEGER`, and anything else throws an `Error`.
+
+use napi::{Error, Result};
+use napi_derive::napi;
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, PriceLevels, Side, SymbolId, TimeInForce, Trade};
+
+/// Largest integer a JS number holds exactly (2^53 - 1).
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+#[napi(object, js_name = "Trade")]
+pub struct JsTrade {
+    pub maker: i64,
+    pub taker: i64,
+    pub symbol: String,
+    #[napi(js_name = "px_ticks")]
+    pub px_ticks: i64,
+    pub qty: i64,
+    #[napi(js_name = "ts_ns")]
+    pub ts_ns: i64,
+}
+
+#[napi(object, js_name = "PriceLevel")]
+pub struct JsPriceLevel {
+    pub price: i64,
+    pub quantity: i64,
+    pub orders: u32,
+}
+
+#[napi(object, js_name = "MarketDepth")]
+pub struct JsMarketDepth {
+    pub symbol: String,
+    pub bids: Vec<JsPriceLevel>,
+    pub asks: Vec<JsPriceLevel>,
+}
+
+/// A live resting order, as listed by `snapshot`.
+#[napi(object, js_name = "RestingOrder")]
+pub struct JsRestingOrder {
+    pub id: i64,
+    pub side: String,
+    pub price: i64,
+    pub quantity: i64,
+    #[napi(js_name = "ts_ns")]
+    pub ts_ns: i64,
+}
+
+/// A JS number that must be an exact integer.
+fn integer(name: &str, value: f64) -> Result<i64> {
+    if value.is_finite() && value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER {
+        Ok(value as i64)
+    } else {
+        Err(Error::from_reason(format!("{} must be a safe integer, got {}", name, value)))
+    }
+}
+
+fn order_id(value: f64) -> Result<OrderId> {
+    match integer("id", value)? {
+        id if id >= 0 => Ok(OrderId(id as u128)),
+        id => Err(Error::from_reason(format!("id must not be negative, got {}", id))),
+    }
+}
+
+fn parse_side(side: &str) -> Result<Side> {
+    match side.to_ascii_lowercase().as_str() {
+        "bid" | "buy" => Ok(Side::Bid),
+        "ask" | "sell" => Ok(Side::Ask),
+        _ => Err(Error::from_reason(format!("side must be \"bid\" or \"ask\", got {:?}", side))),
+    }
+}
+
+/// Top `levels` live price levels of one side, best first.
+fn levels(side: &PriceLevels, levels: usize) -> Vec<JsPriceLevel> {
+    side.depth(levels)
+        .into_iter()
+        .map(|level| JsPriceLevel {
+            price: level.px_ticks,
+            quantity: level.qty,
+            orders: level.orders as u32,
+        })
+        .collect()
+}
+
+fn js_trade(trade: &Trade) -> JsTrade {
+    JsTrade {
+        maker: trade.maker.0 as i64,
+        taker: trade.taker.0 as i64,
+        symbol: trade.symbol.to_string(),
+        px_ticks: trade.px_ticks,
+        qty: trade.qty,
+        ts_ns: trade.ts_ns as i64,
+    }
+}
+
+/// A single-symbol limit order book with price-time priority.
+#[napi(js_name = "OrderBook")]
+pub struct NodeOrderBook {
+    book: OrderBook,
+    symbol: SymbolId,
+}
+
+#[napi]
+impl NodeOrderBook {
+    #[napi(constructor)]
+    pub fn new(symbol: String) -> Self {
+        NodeOrderBook { book: OrderBook::new(), symbol: symbol.into() }
+    }
+
+    #[napi(getter)]
+    pub fn symbol(&self) -> String {
+        self.symbol.to_string()
+    }
+
+    /// Submits a limit order and returns the trades it printed.
+    /// `tsNs` defaults to 0; pass a clock if trade times matter.
+    #[napi]
+    pub fn submit(&mut self, id: f64, side: String, price: f64, quantity: f64, ts_ns: Option<f64>) -> Result<Vec<JsTrade>> {
+        let id = order_id(id)?;
+        let qty = integer("quantity", quantity)?;
+        let report = self.book.submit_limit(Order {
+            id,
+            symbol: self.symbol,
+            side: parse_side(&side)?,
+            px_ticks: integer("price", price)?,
+            qty,
+            ts_ns: integer("tsNs", ts_ns.unwrap_or(0.0))?.max(0) as u128,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }).map_err(|e| Error::from_reason(e.to_string()))?;
+        Ok(report.trades.iter().map(js_trade).collect())
+    }
+
+    /// Cancels a resting order on either side; true if it was live.
+    #[napi]
+    pub fn cancel(&mut self, id: f64) -> Result<bool> {
+        let id = order_id(id)?;
+        Ok(self.book.cancel(id))
+    }
+
+    /// Top `levels` price levels per side.
+    #[napi]
+    pub fn depth(&self, levels_per_side: u32) -> JsMarketDepth {
+        JsMarketDepth {
+            symbol: self.symbol.to_string(),
+            bids: levels(&self.book.bids, levels_per_side as usize),
+            asks: levels(&self.book.asks, levels_per_side as usize),
+        }
+    }
+
+    /// Live resting orders, bids then asks, each side in priority order.
+    #[napi]
+    pub fn snapshot(&self) -> Vec<JsRestingOrder> {
+        [(&self.book.bids, "bid"), (&self.book.asks, "ask")]
+            .into_iter()
+            .flat_map(|(side, name)| {
+                side.live_orders().map(move |o| JsRestingOrder {
+                    id: o.id.0 as i64,
+                    side: name.to_string(),
+                    price: o.px_ticks,
+                    quantity: o.qty,
+                    ts_ns: o.ts_ns as i64,
+                })
+            })
+            .collect()
+    }
+
+    #[napi(getter)]
+    pub fn best_bid(&self) -> Option<i64> {
+        self.book.best_bid()
+    }
+
+    #[napi(getter)]
+    pub fn best_ask(&self) -> Option<i64> {
+        self.book.best_ask()
+    }
+
+    /// Live resting orders on both sides.
+    #[napi(getter)]
+    pub fn resting(&self) -> u32 {
+        (self.book.bids.total_len() + self.book.asks.total_len()) as u32
+    }
+}
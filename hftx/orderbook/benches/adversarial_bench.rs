@@ -0,0 +1,194 @@
+//! Cancel/amend-heavy and worst-case sweep benchmarks.
+//!
+//! These model market-maker flow (90%+ cancel/replace over a deep book) and
+//! sweeps through levels whose queues are mostly lazily-cancelled tombstones,
+//! the shapes where lazy cancellation costs the most.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use orderbook::flow::{FlowConfig, FlowEvent, FlowGenerator, SizeDistribution};
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, Side, TimeInForce};
+
+fn create_order(id: u128, side: Side, price: i64, qty: i64) -> Order {
+    Order {
+        id: OrderId(id),
+        symbol: "AAPL".into(),
+        side,
+        px_ticks: price,
+        qty,
+        ts_ns: id,
+        tif: TimeInForce::Day,
+        expires_ns: None,
+        display_qty: None,
+        min_qty: None,
+        hidden: false,
+        owner: ParticipantId::NONE,
+    }
+}
+
+fn cancel(ob: &mut OrderBook, id: OrderId, side: Side) -> bool {
+    ob.levels_mut(side).cancel(id)
+}
+
+fn apply(ob: &mut OrderBook, event: FlowEvent) {
+    match event {
+        FlowEvent::Submit(order) => {
+            black_box(ob.submit_limit(order).unwrap().trades);
+        }
+        FlowEvent::Cancel { id, side, .. } => {
+            black_box(cancel(ob, id, side));
+        }
+        FlowEvent::Replace { id, side, order } => {
+            black_box(cancel(ob, id, side));
+            black_box(ob.submit_limit(order).unwrap().trades);
+        }
+    }
+}
+
+/// Market-maker flow: ~60% cancel, ~33% cancel/replace, few marketable orders.
+fn market_maker_flow(live_orders: usize) -> FlowConfig {
+    FlowConfig {
+        size: SizeDistribution::Uniform { min: 1, max: 200 },
+        marketable_ratio: 0.02,
+        cancel_ratio: 0.60,
+        replace_ratio: 0.33,
+        mean_depth_ticks: 50.0,
+        volatility_ticks: 5.0,
+        max_live_orders: live_orders,
+        ..FlowConfig::default()
+    }
+}
+
+/// Deep book warmed up by the market-maker flow, plus the generator to continue it.
+fn warmed_book(live_orders: usize) -> (OrderBook, FlowGenerator) {
+    let mut ob = OrderBook::new();
+    let mut flow = FlowGenerator::new(market_maker_flow(live_orders), 0xC0FFEE);
+    for event in flow.by_ref().take(live_orders * 4) {
+        apply(&mut ob, event);
+    }
+    (ob, flow)
+}
+
+fn bench_cancel_replace_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cancel_replace_heavy");
+    let events_per_iter = 10_000;
+    group.throughput(criterion::Throughput::Elements(events_per_iter as u64));
+
+    for &live_orders in [1_000, 10_000, 50_000].iter() {
+        group.bench_with_input(
+            BenchmarkId::new("market_maker", live_orders),
+            &live_orders,
+            |b, &live_orders| {
+                b.iter_batched(
+                    || {
+                        let (ob, mut flow) = warmed_book(live_orders);
+                        let events: Vec<FlowEvent> = flow.by_ref().take(events_per_iter).collect();
+                        (ob, events)
+                    },
+                    |(mut ob, events)| {
+                        for event in events {
+                            apply(&mut ob, event);
+                        }
+                        ob
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_tombstone_heavy(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tombstone_heavy");
+
+    // Every level holds one live order behind many cancelled ones at the front
+    for &tombstones in [1_000, 10_000, 100_000].iter() {
+        group.bench_with_input(
+            BenchmarkId::new("match_behind_tombstones", tombstones),
+            &tombstones,
+            |b, &tombstones| {
+                b.iter_batched(
+                    || {
+                        let mut ob = OrderBook::new();
+                        for i in 0..tombstones as u128 {
+                            ob.submit_limit(create_order(i, Side::Ask, 10_000, 10)).unwrap();
+                            ob.asks.cancel(OrderId(i));
+                        }
+                        ob.submit_limit(create_order(tombstones as u128, Side::Ask, 10_000, 10)).unwrap();
+                        ob
+                    },
+                    |mut ob| {
+                        black_box(ob.submit_limit(create_order(u128::MAX, Side::Bid, 10_000, 10)).unwrap().trades)
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("depth_query_with_tombstones", tombstones),
+            &tombstones,
+            |b, &tombstones| {
+                let mut ob = OrderBook::new();
+                for i in 0..tombstones as u128 {
+                    let px = 10_000 + (i % 100) as i64;
+                    ob.submit_limit(create_order(i, Side::Ask, px, 10)).unwrap();
+                    if i % 10 != 0 {
+                        ob.asks.cancel(OrderId(i));
+                    }
+                }
+                b.iter(|| black_box(ob.asks.iter_levels_best_first().take(10).count()))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn bench_part_canceled_sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("part_canceled_sweep");
+    let orders_per_level = 10u128;
+
+    // Sweep every level of a book where 9 of 10 orders per level are cancelled
+    for &levels in [100, 1_000, 5_000].iter() {
+        group.bench_with_input(
+            BenchmarkId::new("sweep_all_levels", levels),
+            &levels,
+            |b, &levels| {
+                b.iter_batched(
+                    || {
+                        let mut ob = OrderBook::new();
+                        let mut id = 0u128;
+                        for level in 0..levels as i64 {
+                            for k in 0..orders_per_level {
+                                ob.submit_limit(create_order(id, Side::Ask, 10_000 + level, 10)).unwrap();
+                                if k != orders_per_level - 1 {
+                                    ob.asks.cancel(OrderId(id));
+                                }
+                                id += 1;
+                            }
+                        }
+                        ob
+                    },
+                    |mut ob| {
+                        let sweep = create_order(u128::MAX, Side::Bid, 10_000 + levels as i64, 10 * levels as i64);
+                        black_box(ob.submit_limit(sweep).unwrap().trades)
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_cancel_replace_heavy,
+    bench_tombstone_heavy,
+    bench_part_canceled_sweep
+);
+
+criterion_main!(benches);
@@ -0,0 +1,200 @@
+//! Side-by-side comparison of price-level storage backends.
+//!
+//! Every backend implementing `BookBackend` runs the exact same pre-generated
+//! event streams. Results land in one criterion group per workload with one
+//! function per backend, so the HTML report plots them against each other.
+//!
+//! To add a storage variant: implement `BookBackend` for it and append it to
+//! the `backends!` invocation at the bottom.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use orderbook::flow::{FlowConfig, FlowEvent, FlowGenerator, SizeDistribution};
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, Side, TimeInForce};
+
+/// Minimal book surface the workloads need.
+trait BookBackend {
+    const NAME: &'static str;
+    fn new_book() -> Self;
+    fn submit(&mut self, order: Order) -> usize;
+    fn cancel(&mut self, id: OrderId, side: Side) -> bool;
+    fn best_bid(&self) -> Option<i64>;
+    fn best_ask(&self) -> Option<i64>;
+}
+
+impl BookBackend for OrderBook {
+    const NAME: &'static str = "btreemap";
+
+    fn new_book() -> Self {
+        OrderBook::new()
+    }
+
+    fn submit(&mut self, order: Order) -> usize {
+        self.submit_limit(order).unwrap().trades.len()
+    }
+
+    fn cancel(&mut self, id: OrderId, side: Side) -> bool {
+        self.levels_mut(side).cancel(id)
+    }
+
+    fn best_bid(&self) -> Option<i64> {
+        OrderBook::best_bid(self)
+    }
+
+    fn best_ask(&self) -> Option<i64> {
+        OrderBook::best_ask(self)
+    }
+}
+
+/// `OrderBook` with its levels in price ladders over a band covering
+/// every workload's prices.
+struct Ladder(OrderBook);
+
+impl BookBackend for Ladder {
+    const NAME: &'static str = "ladder";
+
+    fn new_book() -> Self {
+        Ladder(OrderBook::new().with_price_band(8_000, 12_000))
+    }
+
+    fn submit(&mut self, order: Order) -> usize {
+        BookBackend::submit(&mut self.0, order)
+    }
+
+    fn cancel(&mut self, id: OrderId, side: Side) -> bool {
+        BookBackend::cancel(&mut self.0, id, side)
+    }
+
+    fn best_bid(&self) -> Option<i64> {
+        self.0.best_bid()
+    }
+
+    fn best_ask(&self) -> Option<i64> {
+        self.0.best_ask()
+    }
+}
+
+/// A named, pre-generated event stream.
+struct Workload {
+    name: &'static str,
+    events: Vec<FlowEvent>,
+}
+
+fn order(id: u128, side: Side, px_ticks: i64, qty: i64) -> Order {
+    Order {
+        id: OrderId(id),
+        symbol: "AAPL".into(),
+        side,
+        px_ticks,
+        qty,
+        ts_ns: id,
+        tif: TimeInForce::Day,
+        expires_ns: None,
+        display_qty: None,
+        min_qty: None,
+        hidden: false,
+        owner: ParticipantId::NONE,
+    }
+}
+
+fn workloads() -> Vec<Workload> {
+    let n = 20_000;
+
+    // Non-crossing ladder: pure insertion cost
+    let ladder = (0..n as u128)
+        .map(|i| {
+            let side = if i % 2 == 0 { Side::Bid } else { Side::Ask };
+            let px = match side {
+                Side::Bid => 9_999 - (i % 100) as i64,
+                Side::Ask => 10_001 + (i % 100) as i64,
+            };
+            FlowEvent::Submit(order(i, side, px, 100))
+        })
+        .collect();
+
+    // Realistic mixed flow
+    let poisson = FlowGenerator::new(FlowConfig::default(), 0xBEEF).take(n).collect();
+
+    // Market-maker churn over a wide book
+    let churn = FlowGenerator::new(
+        FlowConfig {
+            size: SizeDistribution::Uniform { min: 1, max: 200 },
+            marketable_ratio: 0.02,
+            cancel_ratio: 0.60,
+            replace_ratio: 0.33,
+            mean_depth_ticks: 50.0,
+            ..FlowConfig::default()
+        },
+        0xFEED,
+    )
+    .take(n)
+    .collect();
+
+    // Build 1000 one-order levels, then sweep them all with one order
+    let mut sweep: Vec<FlowEvent> = (0..1_000u128)
+        .map(|i| FlowEvent::Submit(order(i, Side::Ask, 10_000 + i as i64, 10)))
+        .collect();
+    sweep.push(FlowEvent::Submit(order(1_000, Side::Bid, 11_000, 10_000)));
+
+    vec![
+        Workload { name: "ladder_insert", events: ladder },
+        Workload { name: "poisson_mixed", events: poisson },
+        Workload { name: "mm_churn", events: churn },
+        Workload { name: "deep_sweep", events: sweep },
+    ]
+}
+
+fn run<B: BookBackend>(events: &[FlowEvent]) -> usize {
+    let mut book = B::new_book();
+    let mut trades = 0;
+    for event in events {
+        match event.clone() {
+            FlowEvent::Submit(order) => trades += book.submit(order),
+            FlowEvent::Cancel { id, side, .. } => {
+                black_box(book.cancel(id, side));
+            }
+            FlowEvent::Replace { id, side, order } => {
+                black_box(book.cancel(id, side));
+                trades += book.submit(order);
+            }
+        }
+    }
+    black_box((book.best_bid(), book.best_ask()));
+    trades
+}
+
+macro_rules! backends {
+    ($($backend:ty),+ $(,)?) => {
+        fn bench_backends(c: &mut Criterion) {
+            let workloads = workloads();
+
+            // Sanity: every backend must agree on the trade count per workload
+            for workload in &workloads {
+                let counts = [$(run::<$backend>(&workload.events)),+];
+                assert!(
+                    counts.windows(2).all(|w| w[0] == w[1]),
+                    "backends disagree on {}: {:?}",
+                    workload.name,
+                    counts
+                );
+            }
+
+            for workload in &workloads {
+                let mut group = c.benchmark_group(format!("backend/{}", workload.name));
+                group.throughput(Throughput::Elements(workload.events.len() as u64));
+                $(
+                    group.bench_with_input(
+                        BenchmarkId::from_parameter(<$backend as BookBackend>::NAME),
+                        &workload.events,
+                        |b, events| b.iter(|| run::<$backend>(events)),
+                    );
+                )+
+                group.finish();
+            }
+        }
+    };
+}
+
+backends!(OrderBook, Ladder);
+
+criterion_group!(benches, bench_backends);
+criterion_main!(benches);
@@ -1,11 +1,11 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use orderbook::{OrderBook, Order, OrderId, Side};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, BenchmarkId, Throughput};
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, Side, TimeInForce};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 fn create_order(id: u128, symbol: &str, side: Side, price: i64, qty: i64) -> Order {
     Order {
         id: OrderId(id),
-        symbol: symbol.to_string(),
+        symbol: symbol.into(),
         side,
         px_ticks: price,
         qty,
@@ -13,6 +13,12 @@ fn create_order(id: u128, symbol: &str, side: Side, price: i64, qty: i64) -> Ord
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_nanos(),
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
     }
 }
 
@@ -34,7 +40,7 @@ fn bench_order_submission(c: &mut Criterion) {
                             if i % 2 == 0 { 10000 - (i as i64) } else { 10100 + (i as i64) },
                             100,
                         );
-                        black_box(ob.submit_limit(order));
+                        black_box(ob.submit_limit(order).unwrap().trades);
                     }
                 })
             },
@@ -65,7 +71,7 @@ fn bench_order_matching(c: &mut Criterion) {
                                 10000 + i as i64,
                                 100,
                             );
-                            ob.submit_limit(ask);
+                            ob.submit_limit(ask).unwrap();
                             
                             // Add bids at decreasing prices
                             let bid = create_order(
@@ -75,7 +81,7 @@ fn bench_order_matching(c: &mut Criterion) {
                                 9999 - i as i64,
                                 100,
                             );
-                            ob.submit_limit(bid);
+                            ob.submit_limit(bid).unwrap();
                         }
                         ob
                     },
@@ -88,7 +94,7 @@ fn bench_order_matching(c: &mut Criterion) {
                             10000 + depth as i64,
                             (depth * 50) as i64,
                         );
-                        black_box(ob.submit_limit(crossing_order))
+                        black_box(ob.submit_limit(crossing_order).unwrap().trades)
                     },
                     criterion::BatchSize::SmallInput,
                 )
@@ -106,9 +112,9 @@ fn bench_market_data_access(c: &mut Criterion) {
     let mut ob = OrderBook::new();
     for i in 0..1000 {
         let ask = create_order(i, "AAPL", Side::Ask, 10000 + (i as i64), 100);
-        ob.submit_limit(ask);
+        ob.submit_limit(ask).unwrap();
         let bid = create_order(i + 1000, "AAPL", Side::Bid, 9999 - (i as i64), 100);
-        ob.submit_limit(bid);
+        ob.submit_limit(bid).unwrap();
     }
     
     group.bench_function("best_bid", |b| {
@@ -239,17 +245,17 @@ fn bench_high_frequency_scenario(c: &mut Criterion) {
                 for i in 0..5 {
                     let ask = create_order(order_id, "AAPL", Side::Ask, 10000 + i, 100);
                     order_id += 1;
-                    ob.submit_limit(ask);
+                    ob.submit_limit(ask).unwrap();
                     
                     let bid = create_order(order_id, "AAPL", Side::Bid, 9999 - i, 100);
                     order_id += 1;
-                    ob.submit_limit(bid);
+                    ob.submit_limit(bid).unwrap();
                 }
                 
                 // Submit crossing orders
                 let crossing = create_order(order_id, "AAPL", Side::Bid, 10002, 300);
                 order_id += 1;
-                black_box(ob.submit_limit(crossing));
+                black_box(ob.submit_limit(crossing).unwrap().trades);
                 
                 // Check market data
                 black_box(ob.best_bid());
@@ -261,6 +267,64 @@ fn bench_high_frequency_scenario(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_deep_sweep(c: &mut Criterion) {
+    let mut group = c.benchmark_group("deep_sweep");
+    let orders_per_level = 4i64;
+
+    // One taker sweeps every order on every level
+    for &levels in [100i64, 1_000, 10_000].iter() {
+        group.throughput(Throughput::Elements((levels * orders_per_level) as u64));
+        group.bench_with_input(
+            BenchmarkId::new("full_fills", levels),
+            &levels,
+            |b, &levels| {
+                b.iter_batched(
+                    || {
+                        let mut ob = OrderBook::new();
+                        let mut id = 0u128;
+                        for level in 0..levels {
+                            for _ in 0..orders_per_level {
+                                ob.submit_limit(create_order(id, "AAPL", Side::Ask, 10_000 + level, 10)).unwrap();
+                                id += 1;
+                            }
+                        }
+                        ob
+                    },
+                    |mut ob| {
+                        let sweep = create_order(u128::MAX, "AAPL", Side::Bid, 10_000 + levels, 10 * orders_per_level * levels);
+                        black_box(ob.submit_limit(sweep).unwrap().trades)
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    // Many small takers each partially filling the order at the front
+    let takers = 10_000u128;
+    group.throughput(Throughput::Elements(takers as u64));
+    group.bench_function("partial_fills", |b| {
+        b.iter_batched(
+            || {
+                let mut ob = OrderBook::new();
+                for level in 0..100 {
+                    ob.submit_limit(create_order(level as u128, "AAPL", Side::Ask, 10_000 + level, 1_000_000)).unwrap();
+                }
+                ob
+            },
+            |mut ob| {
+                for i in 0..takers {
+                    black_box(ob.submit_limit(create_order(1_000 + i, "AAPL", Side::Bid, 10_050, 7)).unwrap().trades);
+                }
+                ob
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_order_submission,
@@ -268,7 +332,8 @@ criterion_group!(
     bench_market_data_access,
     bench_price_levels_operations,
     bench_order_cancellation,
-    bench_high_frequency_scenario
+    bench_high_frequency_scenario,
+    bench_deep_sweep
 );
 
 criterion_main!(benches);
@@ -0,0 +1,225 @@
+//! Arbitrary submit / cancel / amend / modify / remove sequences against
+//! `OrderBook`.
+//!
+//! Alongside the book, the target keeps a ledger of every order's remaining
+//! quantity derived purely from the trades the book reports. After each
+//! operation it checks the book against that ledger:
+//! - the book passes `OrderBook::check_invariants`: it is never crossed,
+//!   levels queue in time order, and the index and level totals match
+//! - every trade is positive, priced at the maker's price within the taker's
+//!   limit, and fills a live maker on the opposite side
+//! - resting order counts and membership agree with the ledger
+//! - IOC and FOK orders never rest, and FOK orders fill completely or not at all
+//! - icebergs trade their hidden quantity but never show more than one slice
+//! - a modify that only shrinks an order trades nothing and keeps it resting
+//! - level aggregation never panics, even with quantities near `i64::MAX`
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, Side, TimeInForce};
+use std::collections::HashMap;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    /// `tif` picks Day, IOC or FOK; a nonzero `display` makes an iceberg
+    Submit { bid: bool, px: u16, qty: u64, tif: u8, display: u16 },
+    /// Lazy cancel of the n-th order ever submitted
+    Cancel { nth: u16 },
+    /// Cancel then resubmit under a new id
+    Amend { nth: u16, px: u16, qty: u64 },
+    /// Amend the n-th order in place with `OrderBook::modify`
+    Modify { nth: u16, px: u16, qty: u64 },
+    /// Eager removal of the n-th order ever submitted
+    Remove { nth: u16 },
+    /// Query depth on both sides
+    Depth,
+}
+
+struct Harness {
+    book: OrderBook,
+    /// Ids in submission order, so ops can name earlier orders
+    ids: Vec<(OrderId, Side)>,
+    /// Side, price, and remaining quantity of every order believed to be resting
+    live: HashMap<OrderId, (Side, i64, i64)>,
+}
+
+impl Harness {
+    fn submit(&mut self, side: Side, px: u16, qty: u64, tif: TimeInForce, display_qty: Option<i64>) {
+        // Prices cluster in a small band so orders actually cross; quantities
+        // span the full positive i64 range to exercise huge fills
+        let px_ticks = 1 + (px % 512) as i64;
+        let qty = (qty % i64::MAX as u64) as i64 + 1;
+        let id = OrderId(self.ids.len() as u128);
+        let order = Order {
+            id,
+            symbol: "FUZZ".into(),
+            side,
+            px_ticks,
+            qty,
+            ts_ns: self.ids.len() as u128,
+            tif,
+            expires_ns: None,
+            display_qty,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        self.ids.push((id, side));
+
+        let trades = self.book.submit_limit(order).expect("harness orders are valid").trades;
+        self.settle(id, side, px_ticks, qty, tif, &trades);
+    }
+
+    /// Checks `trades` against the ledger and applies them, for an order
+    /// `id` of `qty` at `px_ticks` that just went in.
+    fn settle(&mut self, id: OrderId, side: Side, px_ticks: i64, qty: i64, tif: TimeInForce, trades: &[orderbook::Trade]) {
+        let mut remaining = qty;
+        for trade in trades {
+            assert_eq!(trade.taker, id);
+            assert!(trade.qty > 0, "non-positive fill {:?}", trade);
+            match side {
+                Side::Bid => assert!(trade.px_ticks <= px_ticks, "bid filled above limit"),
+                Side::Ask => assert!(trade.px_ticks >= px_ticks, "ask filled below limit"),
+            }
+            remaining = remaining.checked_sub(trade.qty).expect("taker overfilled");
+
+            let (maker_side, maker_px, maker_qty) = self
+                .live
+                .get_mut(&trade.maker)
+                .unwrap_or_else(|| panic!("trade against dead maker {:?}", trade.maker));
+            assert_ne!(*maker_side, side, "matched own side");
+            assert_eq!(trade.px_ticks, *maker_px, "trade not at maker price");
+            *maker_qty = maker_qty.checked_sub(trade.qty).expect("maker overfilled");
+            if *maker_qty == 0 {
+                self.live.remove(&trade.maker);
+            }
+        }
+        assert!(remaining >= 0);
+        if tif == TimeInForce::FOK {
+            assert!(trades.is_empty() || remaining == 0, "FOK partially filled");
+        }
+        if remaining > 0 && tif == TimeInForce::Day {
+            self.live.insert(id, (side, px_ticks, remaining));
+        }
+    }
+
+    fn modify(&mut self, nth: u16, px: u16, qty: u64) {
+        let Some((id, side)) = self.nth(nth) else {
+            return;
+        };
+        let px_ticks = 1 + (px % 512) as i64;
+        let qty = (qty % i64::MAX as u64) as i64 + 1;
+        let trades = self.book.modify(id, px_ticks, qty, self.ids.len() as u128).expect("harness amends are valid");
+        let Some((_, old_px, old_qty)) = self.live.remove(&id) else {
+            assert!(trades.is_none(), "modified dead order {:?}", id);
+            return;
+        };
+        let trades = trades.unwrap_or_else(|| panic!("modify lost {:?}", id));
+        if px_ticks == old_px && qty <= old_qty {
+            assert!(trades.is_empty(), "shrinking {:?} traded", id);
+            self.live.insert(id, (side, px_ticks, qty));
+        } else {
+            self.settle(id, side, px_ticks, qty, TimeInForce::Day, &trades);
+        }
+    }
+
+    fn nth(&self, nth: u16) -> Option<(OrderId, Side)> {
+        if self.ids.is_empty() {
+            return None;
+        }
+        Some(self.ids[nth as usize % self.ids.len()])
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut orderbook::PriceLevels {
+        match side {
+            Side::Bid => &mut self.book.bids,
+            Side::Ask => &mut self.book.asks,
+        }
+    }
+
+    fn cancel(&mut self, nth: u16) -> Option<Side> {
+        let (id, side) = self.nth(nth)?;
+        let cancelled = self.side_mut(side).cancel(id);
+        assert_eq!(cancelled, self.live.remove(&id).is_some(), "cancel disagrees for {:?}", id);
+        Some(side)
+    }
+
+    fn remove(&mut self, nth: u16) {
+        let Some((id, side)) = self.nth(nth) else {
+            return;
+        };
+        let removed = self.side_mut(side).remove(id);
+        match self.live.remove(&id) {
+            Some((_, _, qty)) => assert_eq!(removed.map(|o| o.qty), Some(qty), "remove lost {:?}", id),
+            None => assert!(removed.is_none(), "removed dead order {:?}", id),
+        }
+    }
+
+    fn depth(&self) {
+        for side in [&self.book.bids, &self.book.asks] {
+            for (_, qty) in side.iter_levels_best_first() {
+                assert!(qty >= 0);
+            }
+            for order in side.live_orders() {
+                assert!(order.display_qty.is_none_or(|display| order.qty <= display), "iceberg shows {:?}", order);
+            }
+            if let Some(px) = side.best_price() {
+                assert!(side.qty_at_price(px) >= 0);
+            }
+        }
+    }
+
+    fn check(&self) {
+        if let Err(violation) = self.book.check_invariants() {
+            panic!("{}", violation);
+        }
+
+        let live_bids = self.live.values().filter(|(s, _, _)| *s == Side::Bid).count();
+        assert_eq!(self.book.bids.total_len(), live_bids);
+        assert_eq!(self.book.asks.total_len(), self.live.len() - live_bids);
+
+        for (id, side) in &self.ids {
+            let resting = match side {
+                Side::Bid => self.book.bids.contains(*id),
+                Side::Ask => self.book.asks.contains(*id),
+            };
+            assert_eq!(resting, self.live.contains_key(id), "membership of {:?}", id);
+        }
+    }
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut h = Harness {
+        book: OrderBook::new(),
+        ids: Vec::new(),
+        live: HashMap::new(),
+    };
+
+    for op in ops {
+        match op {
+            Op::Submit { bid, px, qty, tif, display } => {
+                let tif = match tif % 3 {
+                    0 => TimeInForce::Day,
+                    1 => TimeInForce::IOC,
+                    _ => TimeInForce::FOK,
+                };
+                let display_qty = (display > 0).then_some(display as i64);
+                h.submit(if bid { Side::Bid } else { Side::Ask }, px, qty, tif, display_qty)
+            }
+            Op::Cancel { nth } => {
+                h.cancel(nth);
+            }
+            Op::Amend { nth, px, qty } => {
+                if let Some(side) = h.cancel(nth) {
+                    h.submit(side, px, qty, TimeInForce::Day, None);
+                }
+            }
+            Op::Modify { nth, px, qty } => h.modify(nth, px, qty),
+            Op::Remove { nth } => h.remove(nth),
+            Op::Depth => h.depth(),
+        }
+        h.check();
+    }
+});
@@ -0,0 +1,596 @@
+//! Agent-based market simulation in virtual time.
+//!
+//! Populations of trading agents act on one book through a
+//! [`Simulation`], so their orders pay its link latency and match in the
+//! real engine. The prices, spreads, and crashes that come out are
+//! emergent: nothing but the agents' own rules sets them. Three agent
+//! types ship with the module, and anything implementing [`Agent`] can
+//! join them:
+//!
+//! - [`NoiseTrader`]: random side, size, and price around the market,
+//!   sometimes crossing; a source of uninformed flow
+//! - [`MomentumTrader`]: buys when the last price is above its moving
+//!   average and sells when below, reinforcing moves
+//! - [`MarketMaker`]: quotes both sides around the last price, skewing
+//!   away from its inventory and pulling a side at its inventory limit
+//!
+//! [`AgentSimulation::shock`] injects one large sweeping order at a chosen
+//! time, the usual trigger for flash-crash studies. The book is sampled at
+//! a fixed interval ([`MarketSample`]) and each agent's position and PnL
+//! are kept ([`AgentSummary`]).
+//!
+//! ```
+//! use orderbook::agents::{AgentSimulation, MarketConfig, MakerParams, MomentumParams, NoiseParams};
+//! use orderbook::sim::{MINUTE, SECOND};
+//! use orderbook::Side;
+//!
+//! let mut market = AgentSimulation::new(MarketConfig::default());
+//! market.add_noise_traders(20, NoiseParams::default());
+//! market.add_momentum_traders(5, MomentumParams::default());
+//! market.add_market_makers(2, MakerParams::default());
+//! market.shock(5 * MINUTE, Side::Ask, 5_000, 1);
+//! market.run_for(10 * MINUTE);
+//!
+//! let widest = market.samples().iter().filter_map(|s| s.spread()).max();
+//! assert!(market.samples().len() >= 600 && widest.is_some());
+//! ```
+
+use crate::rng::Rng;
+use crate::sim::{SimEvent, Simulation, MILLISECOND, SECOND};
+use crate::symbols::SymbolId;
+use crate::types::{Order, OrderId, ParticipantId, Side, TimeInForce, Trade};
+use crate::OrderBook;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A trading rule woken up by the simulation.
+pub trait Agent {
+    /// Short label for summaries, e.g. `"noise"`.
+    fn kind(&self) -> &'static str;
+
+    /// Acts on the market and returns the delay until the next wake-up
+    /// (at least 1 ns).
+    fn act(&mut self, market: &mut Market) -> u128;
+}
+
+/// Parameters of the simulated market.
+#[derive(Clone, Debug)]
+pub struct MarketConfig {
+    pub symbol: String,
+    /// Reference price until the first trade
+    pub initial_px: i64,
+    /// Seeds every agent's random stream
+    pub seed: u64,
+    /// Interval between [`MarketSample`]s
+    pub sample_every_ns: u128,
+}
+
+impl Default for MarketConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "AAPL".to_string(),
+            initial_px: 10_000,
+            seed: 1,
+            sample_every_ns: SECOND,
+        }
+    }
+}
+
+/// The book and trade tape at one sample time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarketSample {
+    pub ts_ns: u128,
+    pub best_bid: Option<i64>,
+    pub best_ask: Option<i64>,
+    pub last_px: i64,
+    /// Quantity traded since the previous sample
+    pub volume: i128,
+}
+
+impl MarketSample {
+    pub fn spread(&self) -> Option<i64> {
+        Some(self.best_ask? - self.best_bid?)
+    }
+
+    pub fn mid(&self) -> Option<i64> {
+        Some(self.best_bid?.midpoint(self.best_ask?))
+    }
+}
+
+/// One agent's results, money in ticks times quantity. Kept in `i128`,
+/// which no run of `i64` prices and quantities can overflow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AgentSummary {
+    pub kind: &'static str,
+    pub position: i128,
+    pub cash: i128,
+    /// Cash plus the position marked at the last trade price
+    pub pnl: i128,
+    pub fills: u64,
+}
+
+#[derive(Default)]
+struct Account {
+    position: i128,
+    cash: i128,
+    fills: u64,
+    /// Resting orders it sent: id, side, send time
+    open: Vec<(OrderId, Side, u128)>,
+}
+
+/// State the agents, the trade subscriber, and the sampler share.
+struct Shared {
+    symbol: SymbolId,
+    next_id: u128,
+    last_px: i64,
+    volume: i128,
+    /// Order id to sending agent and side; shocks have no owner
+    owners: HashMap<OrderId, (usize, Side)>,
+    accounts: Vec<Account>,
+    samples: Vec<MarketSample>,
+}
+
+impl Shared {
+    fn record(&mut self, trade: &Trade) {
+        self.last_px = trade.px_ticks;
+        self.volume += trade.qty as i128;
+        for id in [trade.maker, trade.taker] {
+            let Some(&(agent, side)) = self.owners.get(&id) else { continue };
+            let account = &mut self.accounts[agent];
+            let signed = if side == Side::Bid { trade.qty as i128 } else { -(trade.qty as i128) };
+            account.position += signed;
+            account.cash -= signed * trade.px_ticks as i128;
+            account.fills += 1;
+        }
+    }
+}
+
+/// What an agent sees and can do while it acts.
+pub struct Market<'a> {
+    sim: &'a mut Simulation,
+    shared: &'a mut Shared,
+    agent: usize,
+}
+
+impl Market<'_> {
+    pub fn now_ns(&self) -> u128 {
+        self.sim.now_ns()
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.sim.book
+    }
+
+    /// Last trade price, or the configured initial price before any trade.
+    pub fn last_px(&self) -> i64 {
+        self.shared.last_px
+    }
+
+    /// Mid if both sides are quoted, else the last trade price.
+    pub fn reference_px(&self) -> i64 {
+        match (self.sim.book.best_bid(), self.sim.book.best_ask()) {
+            (Some(bid), Some(ask)) => bid.midpoint(ask),
+            _ => self.shared.last_px,
+        }
+    }
+
+    /// This agent's net quantity, as of the trades it has heard about.
+    pub fn position(&self) -> i128 {
+        self.shared.accounts[self.agent].position
+    }
+
+    /// This agent's resting orders: id, side, and send time. Orders that
+    /// have since filled stay listed until cancelled.
+    pub fn open_orders(&self) -> &[(OrderId, Side, u128)] {
+        &self.shared.accounts[self.agent].open
+    }
+
+    fn send(&mut self, side: Side, px_ticks: i64, qty: i64) -> OrderId {
+        let id = OrderId(self.shared.next_id);
+        self.shared.next_id += 1;
+        self.shared.owners.insert(id, (self.agent, side));
+        let order = Order {
+            id,
+            symbol: self.shared.symbol,
+            side,
+            px_ticks: px_ticks.max(1),
+            qty,
+            ts_ns: 0,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        let now = self.sim.now_ns();
+        self.sim.send(now, SimEvent::Submit(order));
+        id
+    }
+
+    /// Sends a limit order that rests until cancelled.
+    pub fn limit(&mut self, side: Side, px_ticks: i64, qty: i64) -> OrderId {
+        let id = self.send(side, px_ticks, qty);
+        let now = self.sim.now_ns();
+        self.shared.accounts[self.agent].open.push((id, side, now));
+        id
+    }
+
+    /// Sends an immediate-or-cancel order: whatever doesn't match at
+    /// `px_ticks` or better on arrival is cancelled.
+    pub fn ioc(&mut self, side: Side, px_ticks: i64, qty: i64) -> OrderId {
+        let id = self.send(side, px_ticks, qty);
+        let now = self.sim.now_ns();
+        self.sim.send(now, SimEvent::Cancel { id, side });
+        id
+    }
+
+    /// Cancels this agent's resting orders sent at or before `sent_ns`.
+    pub fn cancel_sent_before(&mut self, sent_ns: u128) {
+        let now = self.sim.now_ns();
+        let open = &mut self.shared.accounts[self.agent].open;
+        for &(id, side, _) in open.iter().filter(|&&(_, _, at)| at <= sent_ns) {
+            self.sim.send(now, SimEvent::Cancel { id, side });
+        }
+        open.retain(|&(_, _, at)| at > sent_ns);
+    }
+
+    /// Cancels all of this agent's resting orders.
+    pub fn cancel_all(&mut self) {
+        self.cancel_sent_before(u128::MAX);
+    }
+}
+
+/// Exponential wait with the given mean, at least 1 ns.
+fn exponential(rng: &mut Rng, mean_ns: u128) -> u128 {
+    ((-rng.next_f64_open().ln() * mean_ns as f64) as u128).max(1)
+}
+
+#[derive(Clone, Debug)]
+pub struct NoiseParams {
+    /// Mean time between actions (exponential)
+    pub mean_interval_ns: u128,
+    /// Chance an order crosses the spread instead of resting
+    pub marketable_prob: f64,
+    /// Sizes are uniform over `1..=max_qty`
+    pub max_qty: i64,
+    /// Mean distance of resting orders from the reference price (exponential)
+    pub mean_depth_ticks: f64,
+    /// Resting orders older than this are cancelled at the next action
+    pub order_lifetime_ns: u128,
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self {
+            mean_interval_ns: 200 * MILLISECOND,
+            marketable_prob: 0.3,
+            max_qty: 100,
+            mean_depth_ticks: 5.0,
+            order_lifetime_ns: 5 * SECOND,
+        }
+    }
+}
+
+/// Uninformed flow: random side, size, and price.
+pub struct NoiseTrader {
+    params: NoiseParams,
+    rng: Rng,
+}
+
+impl NoiseTrader {
+    pub fn new(params: NoiseParams, rng: Rng) -> Self {
+        Self { params, rng }
+    }
+}
+
+impl Agent for NoiseTrader {
+    fn kind(&self) -> &'static str {
+        "noise"
+    }
+
+    fn act(&mut self, market: &mut Market) -> u128 {
+        market.cancel_sent_before(market.now_ns().saturating_sub(self.params.order_lifetime_ns));
+        let side = if self.rng.next_bool() { Side::Bid } else { Side::Ask };
+        let qty = 1 + self.rng.below(self.params.max_qty.max(1) as u64) as i64;
+        let depth = 1 + (-self.rng.next_f64_open().ln() * self.params.mean_depth_ticks).round() as i64;
+        let px = market.reference_px();
+        // Crossing orders reach `depth` through the reference price
+        let toward = if side == Side::Bid { 1 } else { -1 };
+        if self.rng.next_f64() < self.params.marketable_prob {
+            market.ioc(side, px + toward * depth, qty);
+        } else {
+            market.limit(side, px - toward * depth, qty);
+        }
+        exponential(&mut self.rng, self.params.mean_interval_ns)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MomentumParams {
+    /// Mean time between looks at the price (exponential)
+    pub mean_interval_ns: u128,
+    /// Weight of the newest price in the moving average (0.0-1.0)
+    pub ema_alpha: f64,
+    /// Distance from the average, in ticks, that triggers a trade
+    pub threshold_ticks: f64,
+    pub qty: i64,
+    /// Ticks through the last price an order is willing to pay
+    pub max_slippage_ticks: i64,
+    /// Largest absolute position it builds
+    pub max_position: i64,
+}
+
+impl Default for MomentumParams {
+    fn default() -> Self {
+        Self {
+            mean_interval_ns: SECOND,
+            ema_alpha: 0.1,
+            threshold_ticks: 3.0,
+            qty: 50,
+            max_slippage_ticks: 5,
+            max_position: 500,
+        }
+    }
+}
+
+/// Trend follower on an exponential moving average of the last price.
+pub struct MomentumTrader {
+    params: MomentumParams,
+    rng: Rng,
+    ema: Option<f64>,
+}
+
+impl MomentumTrader {
+    pub fn new(params: MomentumParams, rng: Rng) -> Self {
+        Self { params, rng, ema: None }
+    }
+}
+
+impl Agent for MomentumTrader {
+    fn kind(&self) -> &'static str {
+        "momentum"
+    }
+
+    fn act(&mut self, market: &mut Market) -> u128 {
+        let px = market.last_px();
+        let ema = self.ema.unwrap_or(px as f64);
+        let ema = ema + self.params.ema_alpha * (px as f64 - ema);
+        self.ema = Some(ema);
+
+        let signal = px as f64 - ema;
+        let position = market.position();
+        let slippage = self.params.max_slippage_ticks;
+        let max_position = self.params.max_position as i128;
+        if signal > self.params.threshold_ticks && position < max_position {
+            market.ioc(Side::Bid, px + slippage, self.params.qty);
+        } else if signal < -self.params.threshold_ticks && position > -max_position {
+            market.ioc(Side::Ask, px - slippage, self.params.qty);
+        }
+        exponential(&mut self.rng, self.params.mean_interval_ns)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MakerParams {
+    /// Time between requotes (fixed)
+    pub interval_ns: u128,
+    /// Distance of each quote from the last price, in ticks
+    pub half_spread_ticks: i64,
+    pub qty: i64,
+    /// Position beyond which the side that would grow it is pulled
+    pub max_inventory: i64,
+    /// Ticks both quotes move against each unit of inventory
+    pub skew_ticks_per_unit: f64,
+}
+
+impl Default for MakerParams {
+    fn default() -> Self {
+        Self {
+            interval_ns: 100 * MILLISECOND,
+            half_spread_ticks: 2,
+            qty: 100,
+            max_inventory: 1_000,
+            skew_ticks_per_unit: 0.01,
+        }
+    }
+}
+
+/// Two-sided quoter with inventory skew.
+pub struct MarketMaker {
+    params: MakerParams,
+}
+
+impl MarketMaker {
+    pub fn new(params: MakerParams) -> Self {
+        Self { params }
+    }
+}
+
+impl Agent for MarketMaker {
+    fn kind(&self) -> &'static str {
+        "market_maker"
+    }
+
+    fn act(&mut self, market: &mut Market) -> u128 {
+        market.cancel_all();
+        let position = market.position();
+        let skew = (position as f64 * self.params.skew_ticks_per_unit).round() as i64;
+        let fair = market.last_px() - skew;
+        let max_inventory = self.params.max_inventory as i128;
+        if position < max_inventory {
+            market.limit(Side::Bid, fair - self.params.half_spread_ticks, self.params.qty);
+        }
+        if position > -max_inventory {
+            market.limit(Side::Ask, fair + self.params.half_spread_ticks, self.params.qty);
+        }
+        self.params.interval_ns.max(1)
+    }
+}
+
+/// A population of agents trading one book in virtual time.
+pub struct AgentSimulation {
+    /// The underlying simulation; set its latency to model slow agents
+    pub sim: Simulation,
+    shared: Rc<RefCell<Shared>>,
+    kinds: Vec<&'static str>,
+    rng: Rng,
+}
+
+impl AgentSimulation {
+    pub fn new(config: MarketConfig) -> Self {
+        let shared = Rc::new(RefCell::new(Shared {
+            symbol: config.symbol.into(),
+            next_id: 1,
+            last_px: config.initial_px,
+            volume: 0,
+            owners: HashMap::new(),
+            accounts: Vec::new(),
+            samples: Vec::new(),
+        }));
+        let mut sim = Simulation::new();
+
+        let tape = shared.clone();
+        sim.on_trade(move |_, trade| tape.borrow_mut().record(trade));
+        let sampler = shared.clone();
+        sim.every(0, config.sample_every_ns, move |sim| {
+            let mut shared = sampler.borrow_mut();
+            let sample = MarketSample {
+                ts_ns: sim.now_ns(),
+                best_bid: sim.book.best_bid(),
+                best_ask: sim.book.best_ask(),
+                last_px: shared.last_px,
+                volume: std::mem::take(&mut shared.volume),
+            };
+            shared.samples.push(sample);
+        });
+
+        Self { sim, shared, kinds: Vec::new(), rng: Rng::seed_from_u64(config.seed) }
+    }
+
+    /// Adds an agent, first woken at the current time. Returns its index
+    /// in [`summaries`](Self::summaries).
+    pub fn add_agent(&mut self, agent: impl Agent + 'static) -> usize {
+        let index = self.kinds.len();
+        self.kinds.push(agent.kind());
+        self.shared.borrow_mut().accounts.push(Account::default());
+
+        let shared = self.shared.clone();
+        let mut agent = agent;
+        let now = self.sim.now_ns();
+        self.sim.schedule(
+            now,
+            SimEvent::Timer(Box::new(move |sim| {
+                let now = sim.now_ns();
+                let mut shared = shared.borrow_mut();
+                let delay = agent.act(&mut Market { sim, shared: &mut shared, agent: index });
+                Some(now + delay.max(1))
+            })),
+        );
+        index
+    }
+
+    pub fn add_noise_traders(&mut self, count: usize, params: NoiseParams) {
+        for _ in 0..count {
+            let rng = self.rng.fork();
+            self.add_agent(NoiseTrader::new(params.clone(), rng));
+        }
+    }
+
+    pub fn add_momentum_traders(&mut self, count: usize, params: MomentumParams) {
+        for _ in 0..count {
+            let rng = self.rng.fork();
+            self.add_agent(MomentumTrader::new(params.clone(), rng));
+        }
+    }
+
+    pub fn add_market_makers(&mut self, count: usize, params: MakerParams) {
+        for _ in 0..count {
+            self.add_agent(MarketMaker::new(params.clone()));
+        }
+    }
+
+    /// Sends one immediate-or-cancel order of `qty` at `at_ns` that sweeps
+    /// the book down (an ask) or up (a bid) as far as `limit_px`.
+    pub fn shock(&mut self, at_ns: u128, side: Side, qty: i64, limit_px: i64) {
+        let mut shared = self.shared.borrow_mut();
+        let id = OrderId(shared.next_id);
+        shared.next_id += 1;
+        let order = Order { id, symbol: shared.symbol, side, px_ticks: limit_px, qty, ts_ns: 0, tif: TimeInForce::IOC, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE };
+        self.sim.send(at_ns, SimEvent::Submit(order));
+    }
+
+    /// Advances virtual time by `d_ns`, letting every agent act.
+    pub fn run_for(&mut self, d_ns: u128) {
+        self.sim.advance_by(d_ns);
+    }
+
+    pub fn samples(&self) -> Vec<MarketSample> {
+        self.shared.borrow().samples.clone()
+    }
+
+    pub fn trades(&self) -> &[Trade] {
+        self.sim.trades()
+    }
+
+    /// Each agent's results in the order they were added.
+    pub fn summaries(&self) -> Vec<AgentSummary> {
+        let shared = self.shared.borrow();
+        self.kinds
+            .iter()
+            .zip(&shared.accounts)
+            .map(|(&kind, account)| AgentSummary {
+                kind,
+                position: account.position,
+                cash: account.cash,
+                pnl: account.cash + account.position * shared.last_px as i128,
+                fills: account.fills,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::MINUTE;
+
+    fn market(seed: u64) -> AgentSimulation {
+        let mut market = AgentSimulation::new(MarketConfig { seed, ..MarketConfig::default() });
+        market.add_noise_traders(10, NoiseParams::default());
+        market.add_momentum_traders(3, MomentumParams::default());
+        market.add_market_makers(1, MakerParams::default());
+        market
+    }
+
+    #[test]
+    fn same_seed_same_market() {
+        let run = |seed| {
+            let mut market = market(seed);
+            market.run_for(MINUTE);
+            (market.samples(), market.summaries())
+        };
+        let (samples, summaries) = run(4);
+        assert_eq!(samples.len(), 61);
+        assert_eq!((samples.clone(), summaries.clone()), run(4));
+        assert_ne!(samples, run(5).0);
+
+        // Every fill has a counterparty among the agents
+        assert_eq!(summaries.iter().map(|s| s.position).sum::<i128>(), 0);
+        assert_eq!(summaries.iter().map(|s| s.cash).sum::<i128>(), 0);
+        assert!(summaries.iter().all(|s| s.fills > 0), "{:?}", summaries);
+    }
+
+    #[test]
+    fn shock_sweeps_the_book() {
+        let mut market = market(7);
+        market.run_for(MINUTE);
+        let before = market.samples().last().unwrap().best_bid.unwrap();
+
+        market.shock(MINUTE + 1, Side::Ask, 1_000_000, 1);
+        market.run_for(2);
+        assert_eq!(market.sim.book.best_bid(), None);
+        assert!(market.trades().last().unwrap().px_ticks < before);
+    }
+}
@@ -0,0 +1,304 @@
+//! Trades, depth snapshots, and candles as Arrow `RecordBatch`es (`arrow`
+//! feature).
+//!
+//! For in-process analytics: a batch can go straight to DataFusion
+//! (`MemTable`), polars, or anything else speaking Arrow, with no JSON in
+//! between. Columns are built once and shared by reference count, so
+//! handing a batch on copies nothing. The Parquet export writes these same
+//! batches, so the column layouts documented in `export` apply here too:
+//!
+//! - trades: `seq`, `ts_ns`, `symbol`, `px_ticks`, `qty`, `maker_id`, `taker_id`
+//! - depth: `ts_ns`, `symbol`, `side`, `level`, `px_ticks`, `qty`, `orders`
+//! - candles: `start_ns`, `interval_ns`, `symbol`, `open`, `high`, `low`,
+//!   `close`, `volume` (int64 apart from `symbol`), `trades` (uint64)
+//!
+//! Order ids are 128-bit and stored as `decimal(38, 0)`. Timestamps are
+//! int64 nanoseconds.
+
+use crate::candles::Candle;
+use crate::types::Trade;
+use crate::{BookLevel, OrderBook};
+use arrow_array::builder::{
+    ArrayBuilder, Decimal128Builder, Int64Builder, StringBuilder, UInt16Builder, UInt32Builder, UInt64Builder,
+};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use std::sync::Arc;
+
+fn id_type() -> DataType {
+    DataType::Decimal128(38, 0)
+}
+
+fn batch(schema: SchemaRef, columns: Vec<ArrayRef>) -> RecordBatch {
+    RecordBatch::try_new(schema, columns).expect("columns are built to the schema")
+}
+
+pub fn trade_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("seq", DataType::UInt64, false),
+        Field::new("ts_ns", DataType::Int64, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("px_ticks", DataType::Int64, false),
+        Field::new("qty", DataType::Int64, false),
+        Field::new("maker_id", id_type(), false),
+        Field::new("taker_id", id_type(), false),
+    ]))
+}
+
+pub fn depth_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("ts_ns", DataType::Int64, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("side", DataType::Utf8, false),
+        Field::new("level", DataType::UInt16, false),
+        Field::new("px_ticks", DataType::Int64, false),
+        Field::new("qty", DataType::Int64, false),
+        Field::new("orders", DataType::UInt32, false),
+    ]))
+}
+
+pub fn candle_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("start_ns", DataType::Int64, false),
+        Field::new("interval_ns", DataType::Int64, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("open", DataType::Int64, false),
+        Field::new("high", DataType::Int64, false),
+        Field::new("low", DataType::Int64, false),
+        Field::new("close", DataType::Int64, false),
+        Field::new("volume", DataType::Int64, false),
+        Field::new("trades", DataType::UInt64, false),
+    ]))
+}
+
+/// Accumulates trades into batches. `seq` keeps counting across batches.
+pub struct TradeBatchBuilder {
+    next_seq: u64,
+    seq: UInt64Builder,
+    ts_ns: Int64Builder,
+    symbol: StringBuilder,
+    px_ticks: Int64Builder,
+    qty: Int64Builder,
+    maker_id: Decimal128Builder,
+    taker_id: Decimal128Builder,
+}
+
+impl Default for TradeBatchBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TradeBatchBuilder {
+    pub fn new() -> Self {
+        Self {
+            next_seq: 0,
+            seq: UInt64Builder::new(),
+            ts_ns: Int64Builder::new(),
+            symbol: StringBuilder::new(),
+            px_ticks: Int64Builder::new(),
+            qty: Int64Builder::new(),
+            maker_id: Decimal128Builder::new().with_data_type(id_type()),
+            taker_id: Decimal128Builder::new().with_data_type(id_type()),
+        }
+    }
+
+    pub fn append(&mut self, trade: &Trade) {
+        self.seq.append_value(self.next_seq);
+        self.ts_ns.append_value(trade.ts_ns as i64);
+        self.symbol.append_value(trade.symbol.as_str());
+        self.px_ticks.append_value(trade.px_ticks);
+        self.qty.append_value(trade.qty);
+        self.maker_id.append_value(trade.maker.0 as i128);
+        self.taker_id.append_value(trade.taker.0 as i128);
+        self.next_seq += 1;
+    }
+
+    /// Rows appended since the last `finish`.
+    pub fn len(&self) -> usize {
+        self.seq.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Rows appended since the builder was created.
+    pub fn total_rows(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// The rows appended since the last call, as one batch.
+    pub fn finish(&mut self) -> RecordBatch {
+        batch(
+            trade_schema(),
+            vec![
+                Arc::new(self.seq.finish()),
+                Arc::new(self.ts_ns.finish()),
+                Arc::new(self.symbol.finish()),
+                Arc::new(self.px_ticks.finish()),
+                Arc::new(self.qty.finish()),
+                Arc::new(self.maker_id.finish()),
+                Arc::new(self.taker_id.finish()),
+            ],
+        )
+    }
+}
+
+/// Accumulates top-of-book snapshots into batches, one row per live level.
+#[derive(Default)]
+pub struct DepthBatchBuilder {
+    ts_ns: Int64Builder,
+    symbol: StringBuilder,
+    side: StringBuilder,
+    level: UInt16Builder,
+    px_ticks: Int64Builder,
+    qty: Int64Builder,
+    orders: UInt32Builder,
+}
+
+impl DepthBatchBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the best `levels` live price levels of each side at `ts_ns`,
+    /// bids first. Returns the rows added.
+    pub fn append_book(&mut self, ts_ns: u128, symbol: &str, book: &OrderBook, levels: usize) -> usize {
+        let mut rows = 0;
+        let depth = book.depth(levels.min(u16::MAX as usize));
+        for (side, name) in [(depth.bids, "bid"), (depth.asks, "ask")] {
+            for (level, BookLevel { px_ticks: px, qty, orders }) in side.into_iter().enumerate() {
+                self.ts_ns.append_value(ts_ns as i64);
+                self.symbol.append_value(symbol);
+                self.side.append_value(name);
+                self.level.append_value(level as u16);
+                self.px_ticks.append_value(px);
+                self.qty.append_value(qty);
+                self.orders.append_value(orders as u32);
+                rows += 1;
+            }
+        }
+        rows
+    }
+
+    /// Rows appended since the last `finish`.
+    pub fn len(&self) -> usize {
+        self.ts_ns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The rows appended since the last call, as one batch.
+    pub fn finish(&mut self) -> RecordBatch {
+        batch(
+            depth_schema(),
+            vec![
+                Arc::new(self.ts_ns.finish()),
+                Arc::new(self.symbol.finish()),
+                Arc::new(self.side.finish()),
+                Arc::new(self.level.finish()),
+                Arc::new(self.px_ticks.finish()),
+                Arc::new(self.qty.finish()),
+                Arc::new(self.orders.finish()),
+            ],
+        )
+    }
+}
+
+/// Trades as one batch, `seq` from 0.
+pub fn trades_to_batch(trades: &[Trade]) -> RecordBatch {
+    let mut builder = TradeBatchBuilder::new();
+    trades.iter().for_each(|t| builder.append(t));
+    builder.finish()
+}
+
+/// One depth snapshot of `book` as a batch.
+pub fn depth_to_batch(ts_ns: u128, symbol: &str, book: &OrderBook, levels: usize) -> RecordBatch {
+    let mut builder = DepthBatchBuilder::new();
+    builder.append_book(ts_ns, symbol, book, levels);
+    builder.finish()
+}
+
+pub fn candles_to_batch(candles: &[Candle]) -> RecordBatch {
+    let int64 = |f: fn(&Candle) -> i64| -> ArrayRef {
+        Arc::new(candles.iter().map(f).collect::<arrow_array::Int64Array>())
+    };
+    batch(
+        candle_schema(),
+        vec![
+            int64(|c| c.start_ns as i64),
+            int64(|c| c.interval_ns as i64),
+            Arc::new(candles.iter().map(|c| Some(c.symbol.as_str())).collect::<arrow_array::StringArray>()),
+            int64(|c| c.open),
+            int64(|c| c.high),
+            int64(|c| c.low),
+            int64(|c| c.close),
+            int64(|c| c.volume),
+            Arc::new(candles.iter().map(|c| c.trades).collect::<arrow_array::UInt64Array>()),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candles::candles;
+    use crate::types::{Order, OrderId, ParticipantId, Side, TimeInForce};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Decimal128Type, Int64Type, UInt32Type, UInt64Type};
+
+    fn order(id: u128, side: Side, px_ticks: i64, qty: i64) -> Order {
+        Order { id: OrderId(id), symbol: "AAPL".into(), side, px_ticks, qty, ts_ns: id % 1_000, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE }
+    }
+
+    #[test]
+    fn builds_trade_depth_and_candle_batches() {
+        let mut book = OrderBook::new();
+        book.submit_limit(order(1, Side::Ask, 101, 5)).unwrap();
+        book.submit_limit(order(2, Side::Ask, 102, 5)).unwrap();
+        book.submit_limit(order(3, Side::Bid, 99, 4)).unwrap();
+        book.submit_limit(order(4, Side::Bid, 99, 6)).unwrap();
+        book.bids.cancel(OrderId(4));
+        let trades = book.submit_limit(order(u128::MAX >> 1, Side::Bid, 102, 7)).unwrap().trades;
+
+        let batch = trades_to_batch(&trades);
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.column(3).as_primitive::<Int64Type>().values(), &[101, 102]);
+        assert_eq!(batch.column(6).as_primitive::<Decimal128Type>().value(0), (u128::MAX >> 1) as i128);
+
+        let depth = depth_to_batch(5_000, "AAPL", &book, 10);
+        assert_eq!(depth.schema(), depth_schema());
+        assert_eq!(depth.column(2).as_string::<i32>().iter().collect::<Vec<_>>(), [Some("bid"), Some("ask")]);
+        assert_eq!(depth.column(6).as_primitive::<UInt32Type>().value(0), 1);
+        assert_eq!(depth.column(5).as_primitive::<Int64Type>().value(1), 3);
+
+        let candles = candles_to_batch(&candles(&trades, 1_000_000));
+        assert_eq!(candles.num_rows(), 1);
+        assert_eq!(candles.column(4).as_primitive::<Int64Type>().value(0), 102);
+        assert_eq!(candles.column(8).as_primitive::<UInt64Type>().value(0), 2);
+    }
+
+    #[test]
+    fn trade_builder_numbers_rows_across_batches() {
+        let trade = Trade {
+            maker: OrderId(1),
+            taker: OrderId(2),
+            symbol: "AAPL".into(),
+            px_ticks: 100,
+            qty: 1,
+            ts_ns: 0,
+            seq: 0,
+        };
+        let mut builder = TradeBatchBuilder::new();
+        builder.append(&trade);
+        builder.append(&trade);
+        assert_eq!(builder.finish().num_rows(), 2);
+        assert!(builder.is_empty());
+        builder.append(&trade);
+        let seq = builder.finish().column(0).as_primitive::<UInt64Type>().value(0);
+        assert_eq!((seq, builder.total_rows()), (2, 3));
+    }
+}
@@ -0,0 +1,598 @@
+//! Strategy backtesting through the real matching engine.
+//!
+//! Implement [`Strategy`] and run it in a [`Backtest`] over a stream of
+//! [`FlowEvent`]s: synthetic from [`FlowGenerator`](crate::flow::FlowGenerator),
+//! or recorded, e.g. CSV events through [`recorded_flow`]. The flow and the
+//! strategy's own orders meet in one [`OrderBook`], so queue position,
+//! partial fills, and the strategy's impact on the book come from the
+//! engine rather than from a fill model.
+//!
+//! For each market event the engine applies it to the book, then calls
+//! `on_trade` for every trade printed (and `on_fill` right after, for those
+//! the strategy was party to), then `on_tick`. Orders and cancels placed
+//! through the [`Context`] reach the book `latency_ns` later (0 by default),
+//! ahead of any flow event at or after that time; their trades are
+//! reported the same way. Actions still in flight when the flow ends are
+//! dropped.
+//!
+//! ```
+//! use orderbook::backtest::{Backtest, Context, Strategy};
+//! use orderbook::flow::{FlowConfig, FlowGenerator};
+//! use orderbook::Side;
+//!
+//! /// Keeps one bid resting a tick under the best bid.
+//! struct Joiner;
+//!
+//! impl Strategy for Joiner {
+//!     fn on_tick(&mut self, ctx: &mut Context) {
+//!         if let (Some(bid), 0) = (ctx.book().best_bid(), ctx.open_orders().count()) {
+//!             ctx.submit(Side::Bid, bid - 1, 10);
+//!         }
+//!     }
+//! }
+//!
+//! let mut backtest = Backtest::new("AAPL", Joiner);
+//! backtest.run(FlowGenerator::new(FlowConfig::default(), 1).take(10_000));
+//! let report = backtest.report();
+//! assert!(report.orders_submitted > 0);
+//! ```
+
+use crate::csv::CsvEvent;
+use crate::codec::BookEvent;
+use crate::flow::FlowEvent;
+use crate::symbols::SymbolId;
+use crate::types::{Order, OrderId, ParticipantId, Side, TimeInForce, Trade};
+use crate::OrderBook;
+use std::collections::{HashMap, VecDeque};
+
+/// Strategy order ids start here, far above any id a feed or generator
+/// hands out, so they never collide with the flow's.
+pub const STRATEGY_ID_BASE: u128 = 1 << 127;
+
+/// Trading logic driven by a [`Backtest`]. Every callback is optional.
+pub trait Strategy {
+    /// After each market event has been applied.
+    fn on_tick(&mut self, _ctx: &mut Context) {}
+
+    /// For every trade printed, the strategy's included.
+    fn on_trade(&mut self, _ctx: &mut Context, _trade: &Trade) {}
+
+    /// For every execution of one of the strategy's orders.
+    fn on_fill(&mut self, _ctx: &mut Context, _fill: &Fill) {}
+}
+
+/// One execution of a strategy order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fill {
+    pub order: OrderId,
+    pub side: Side,
+    pub px_ticks: i64,
+    pub qty: i64,
+    pub ts_ns: u128,
+    /// Whether the order was resting (maker) rather than crossing (taker)
+    pub maker: bool,
+    /// Mid price when the order reached the book, if both sides had one
+    pub arrival_mid: Option<i64>,
+}
+
+impl Fill {
+    /// Execution cost against the arrival mid, in ticks times quantity;
+    /// positive is worse than mid. `None` without an arrival mid.
+    pub fn slippage(&self) -> Option<i128> {
+        let (px_ticks, mid) = (self.px_ticks as i128, self.arrival_mid? as i128);
+        Some(match self.side {
+            Side::Bid => (px_ticks - mid) * self.qty as i128,
+            Side::Ask => (mid - px_ticks) * self.qty as i128,
+        })
+    }
+}
+
+enum Action {
+    Submit(Order),
+    Cancel(OrderId),
+}
+
+/// A live strategy order.
+#[derive(Clone, Debug)]
+pub struct OpenOrder {
+    pub id: OrderId,
+    pub side: Side,
+    pub px_ticks: i64,
+    /// Quantity not yet filled
+    pub qty: i64,
+    arrival_mid: Option<i64>,
+}
+
+/// What a strategy sees and can do from inside a callback.
+pub struct Context<'a> {
+    book: &'a OrderBook,
+    symbol: SymbolId,
+    now_ns: u128,
+    account: &'a Account,
+    open: &'a HashMap<OrderId, OpenOrder>,
+    actions: &'a mut Vec<Action>,
+    next_id: &'a mut u128,
+}
+
+impl Context<'_> {
+    pub fn book(&self) -> &OrderBook {
+        self.book
+    }
+
+    pub fn now_ns(&self) -> u128 {
+        self.now_ns
+    }
+
+    /// Net quantity held: positive long, negative short.
+    pub fn position(&self) -> i128 {
+        self.account.position
+    }
+
+    /// Cash from fills in ticks times quantity: sells add, buys subtract.
+    pub fn cash(&self) -> i128 {
+        self.account.cash
+    }
+
+    /// Strategy orders resting in the book, in no particular order. Orders
+    /// and cancels still in flight are not reflected yet.
+    pub fn open_orders(&self) -> impl Iterator<Item = &OpenOrder> {
+        self.open.values()
+    }
+
+    /// Sends a limit order; it reaches the book after the backtest's latency.
+    pub fn submit(&mut self, side: Side, px_ticks: i64, qty: i64) -> OrderId {
+        let id = OrderId(*self.next_id);
+        *self.next_id += 1;
+        self.actions.push(Action::Submit(Order {
+            id,
+            symbol: self.symbol,
+            side,
+            px_ticks,
+            qty,
+            ts_ns: self.now_ns,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }));
+        id
+    }
+
+    /// Sends a cancel for one of the strategy's orders. Whatever fills
+    /// before it arrives stays filled.
+    pub fn cancel(&mut self, id: OrderId) {
+        self.actions.push(Action::Cancel(id));
+    }
+}
+
+/// Position and cash, marked to market as the book moves. Kept in
+/// `i128`, which no run of `i64` prices and quantities can overflow.
+#[derive(Default)]
+struct Account {
+    position: i128,
+    cash: i128,
+    last_px: Option<i64>,
+    peak_equity: i128,
+    max_drawdown: i128,
+    max_position: i128,
+}
+
+impl Account {
+    fn fill(&mut self, fill: &Fill) {
+        let (qty, notional) = (fill.qty as i128, fill.px_ticks as i128 * fill.qty as i128);
+        match fill.side {
+            Side::Bid => {
+                self.position += qty;
+                self.cash -= notional;
+            }
+            Side::Ask => {
+                self.position -= qty;
+                self.cash += notional;
+            }
+        }
+        self.max_position = self.max_position.max(self.position.abs());
+    }
+
+    /// Mid if both sides are quoted, else the last trade price.
+    fn mark(&self, book: &OrderBook) -> Option<i64> {
+        mid(book).or(self.last_px)
+    }
+
+    fn equity(&self, book: &OrderBook) -> i128 {
+        self.cash + self.mark(book).map_or(0, |px| px as i128 * self.position)
+    }
+
+    fn update_drawdown(&mut self, book: &OrderBook) {
+        let equity = self.equity(book);
+        self.peak_equity = self.peak_equity.max(equity);
+        self.max_drawdown = self.max_drawdown.max(self.peak_equity - equity);
+    }
+}
+
+fn mid(book: &OrderBook) -> Option<i64> {
+    Some(book.best_bid()?.midpoint(book.best_ask()?))
+}
+
+/// Results of a backtest so far. Money is in ticks times quantity, and
+/// money and quantity totals are `i128` so a long run can't overflow them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BacktestReport {
+    pub fills: Vec<Fill>,
+    /// Flow events applied
+    pub events: u64,
+    pub orders_submitted: u64,
+    /// Cancels that removed a live order
+    pub orders_cancelled: u64,
+    pub submitted_qty: i128,
+    pub maker_qty: i128,
+    pub taker_qty: i128,
+    pub position: i128,
+    pub cash: i128,
+    /// Cash plus the position marked at mid, or at the last trade price
+    /// while a side is empty
+    pub pnl: i128,
+    /// Largest drop of `pnl` from its running peak
+    pub max_drawdown: i128,
+    /// Largest absolute position held
+    pub max_position: i128,
+    /// Sum of [`Fill::slippage`] over fills with an arrival mid
+    pub slippage: i128,
+}
+
+impl BacktestReport {
+    pub fn filled_qty(&self) -> i128 {
+        self.maker_qty + self.taker_qty
+    }
+
+    /// Filled quantity over submitted quantity; 0 before any submit.
+    pub fn fill_ratio(&self) -> f64 {
+        match self.submitted_qty {
+            0 => 0.0,
+            submitted => self.filled_qty() as f64 / submitted as f64,
+        }
+    }
+}
+
+/// A strategy trading one symbol's book against a stream of flow.
+pub struct Backtest<S> {
+    strategy: S,
+    book: OrderBook,
+    symbol: SymbolId,
+    latency_ns: u128,
+    now_ns: u128,
+    next_id: u128,
+    /// Actions in flight, by arrival time; one latency keeps them sorted
+    in_flight: VecDeque<(u128, Action)>,
+    open: HashMap<OrderId, OpenOrder>,
+    account: Account,
+    report: BacktestReport,
+}
+
+impl<S: Strategy> Backtest<S> {
+    /// Starts on an empty book with the clock at zero.
+    pub fn new(symbol: &str, strategy: S) -> Self {
+        Self {
+            strategy,
+            book: OrderBook::new(),
+            symbol: symbol.into(),
+            latency_ns: 0,
+            now_ns: 0,
+            next_id: STRATEGY_ID_BASE,
+            in_flight: VecDeque::new(),
+            open: HashMap::new(),
+            account: Account::default(),
+            report: BacktestReport::default(),
+        }
+    }
+
+    /// Starts from an existing book instead, e.g. a loaded snapshot.
+    pub fn with_book(mut self, book: OrderBook) -> Self {
+        self.book = book;
+        self
+    }
+
+    /// Delay between the strategy sending an order or cancel and the book
+    /// receiving it.
+    pub fn with_latency(mut self, latency_ns: u128) -> Self {
+        self.latency_ns = latency_ns;
+        self
+    }
+
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn now_ns(&self) -> u128 {
+        self.now_ns
+    }
+
+    /// Feeds `flow` through the book and the strategy. May be called again
+    /// to continue with more flow; events must not go back in time.
+    pub fn run(&mut self, flow: impl IntoIterator<Item = FlowEvent>) {
+        for event in flow {
+            let ts_ns = event.ts_ns().max(self.now_ns);
+            self.deliver_until(ts_ns);
+            self.now_ns = ts_ns;
+
+            let mut trades = Vec::new();
+            match event {
+                FlowEvent::Submit(order) => trades = self.book.submit_limit(order).map(|report| report.trades).unwrap_or_default(),
+                FlowEvent::Cancel { id, .. } => {
+                    self.book.cancel(id);
+                }
+                FlowEvent::Replace { id, order, .. } => {
+                    self.book.cancel(id);
+                    trades = self.book.submit_limit(order).map(|report| report.trades).unwrap_or_default();
+                }
+            }
+            self.report.events += 1;
+            self.on_trades(&trades, None);
+            self.callback(|strategy, ctx| strategy.on_tick(ctx));
+            self.account.update_drawdown(&self.book);
+        }
+    }
+
+    /// The results so far, marked against the current book.
+    pub fn report(&self) -> BacktestReport {
+        BacktestReport {
+            position: self.account.position,
+            cash: self.account.cash,
+            pnl: self.account.equity(&self.book),
+            max_drawdown: self.account.max_drawdown,
+            max_position: self.account.max_position,
+            ..self.report.clone()
+        }
+    }
+
+    /// Applies the strategy's actions arriving at or before `t_ns`,
+    /// including any its callbacks send along the way.
+    fn deliver_until(&mut self, t_ns: u128) {
+        while self.in_flight.front().is_some_and(|&(at, _)| at <= t_ns) {
+            let (at, action) = self.in_flight.pop_front().unwrap();
+            self.now_ns = self.now_ns.max(at);
+            match action {
+                Action::Submit(mut order) => {
+                    order.ts_ns = self.now_ns;
+                    let arrival_mid = mid(&self.book);
+                    let mut open = OpenOrder {
+                        id: order.id,
+                        side: order.side,
+                        px_ticks: order.px_ticks,
+                        qty: order.qty,
+                        arrival_mid,
+                    };
+                    // A refused order never reaches the book
+                    let Ok(report) = self.book.submit_limit(order) else { continue };
+                    self.report.orders_submitted += 1;
+                    self.report.submitted_qty += open.qty as i128;
+                    let trades = report.trades;
+                    open.qty -= trades.iter().filter(|t| t.taker == open.id).map(|t| t.qty).sum::<i64>();
+                    let taker = open.clone();
+                    if open.qty > 0 {
+                        self.open.insert(open.id, open);
+                    }
+                    self.on_trades(&trades, Some(&taker));
+                }
+                Action::Cancel(id) => {
+                    if !self.open.contains_key(&id) {
+                        continue;
+                    }
+                    if self.book.cancel(id) {
+                        self.open.remove(&id);
+                        self.report.orders_cancelled += 1;
+                    }
+                }
+            }
+            self.account.update_drawdown(&self.book);
+        }
+    }
+
+    /// Books fills and runs `on_trade` / `on_fill`. `taker` is the strategy
+    /// order that printed `trades`, if it was one.
+    fn on_trades(&mut self, trades: &[Trade], taker: Option<&OpenOrder>) {
+        for trade in trades {
+            self.account.last_px = Some(trade.px_ticks);
+            self.callback(|strategy, ctx| strategy.on_trade(ctx, trade));
+
+            let mut maker_fill = None;
+            if let Some(maker) = self.open.get_mut(&trade.maker) {
+                maker.qty -= trade.qty;
+                maker_fill = Some(fill(maker, trade, true));
+                if maker.qty <= 0 {
+                    self.open.remove(&trade.maker);
+                }
+            }
+            let taker_fill = taker.filter(|t| t.id == trade.taker).map(|t| fill(t, trade, false));
+            for fill in maker_fill.into_iter().chain(taker_fill) {
+                self.account.fill(&fill);
+                if fill.maker {
+                    self.report.maker_qty += fill.qty as i128;
+                } else {
+                    self.report.taker_qty += fill.qty as i128;
+                }
+                self.report.slippage += fill.slippage().unwrap_or(0);
+                self.callback(|strategy, ctx| strategy.on_fill(ctx, &fill));
+                self.report.fills.push(fill);
+            }
+        }
+    }
+
+    /// Runs one strategy callback and queues whatever it sent.
+    fn callback(&mut self, f: impl FnOnce(&mut S, &mut Context)) {
+        let mut actions = Vec::new();
+        let mut ctx = Context {
+            book: &self.book,
+            symbol: self.symbol,
+            now_ns: self.now_ns,
+            account: &self.account,
+            open: &self.open,
+            actions: &mut actions,
+            next_id: &mut self.next_id,
+        };
+        f(&mut self.strategy, &mut ctx);
+        let arrival = self.now_ns + self.latency_ns;
+        self.in_flight.extend(actions.into_iter().map(|action| (arrival, action)));
+    }
+}
+
+fn fill(order: &OpenOrder, trade: &Trade, maker: bool) -> Fill {
+    Fill {
+        order: order.id,
+        side: order.side,
+        px_ticks: trade.px_ticks,
+        qty: trade.qty,
+        ts_ns: trade.ts_ns,
+        maker,
+        arrival_mid: order.arrival_mid,
+    }
+}
+
+/// Turns recorded book events into flow, keeping `symbol` only. A cancel
+/// carries no time of its own, so it takes the time of the event before it.
+/// Flow has only limit submits and cancels, so other events are left out.
+pub fn recorded_flow(symbol: &str, events: impl IntoIterator<Item = CsvEvent>) -> Vec<FlowEvent> {
+    let mut ts_ns = 0;
+    events
+        .into_iter()
+        .filter(|e| e.symbol == symbol)
+        .filter_map(|e| match e.event {
+            BookEvent::Submit(order) => {
+                ts_ns = order.ts_ns;
+                Some(FlowEvent::Submit(order))
+            }
+            BookEvent::Cancel { id, side } => Some(FlowEvent::Cancel { id, side, ts_ns }),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(id: u128, side: Side, px_ticks: i64, qty: i64, ts_ns: u128) -> FlowEvent {
+        FlowEvent::Submit(Order { id: OrderId(id), symbol: "AAPL".into(), side, px_ticks, qty, ts_ns, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE })
+    }
+
+    /// Bids once at a fixed price and logs what it hears.
+    #[derive(Default)]
+    struct OneBid {
+        sent: Option<OrderId>,
+        trades: usize,
+        fills: Vec<(i64, bool)>,
+    }
+
+    impl Strategy for OneBid {
+        fn on_tick(&mut self, ctx: &mut Context) {
+            if self.sent.is_none() {
+                self.sent = Some(ctx.submit(Side::Bid, 100, 10));
+            }
+        }
+
+        fn on_trade(&mut self, _ctx: &mut Context, _trade: &Trade) {
+            self.trades += 1;
+        }
+
+        fn on_fill(&mut self, ctx: &mut Context, fill: &Fill) {
+            self.fills.push((fill.qty, fill.maker));
+            assert_eq!(ctx.position(), self.fills.iter().map(|f| f.0 as i128).sum::<i128>());
+        }
+    }
+
+    #[test]
+    fn strategy_orders_rest_and_fill_in_the_real_book() {
+        let mut backtest = Backtest::new("AAPL", OneBid::default()).with_latency(5);
+        backtest.run([
+            order(1, Side::Bid, 99, 5, 0),
+            order(2, Side::Ask, 102, 5, 1),
+            // Our bid (sent at 0, in the book at 5) is ahead of this one
+            order(3, Side::Bid, 100, 5, 10),
+            order(4, Side::Ask, 100, 12, 20),
+        ]);
+
+        let report = backtest.report();
+        assert_eq!(backtest.strategy().fills, vec![(10, true)]);
+        assert_eq!(backtest.strategy().trades, 2);
+        assert_eq!(report.fills[0].arrival_mid, Some(100));
+        assert_eq!((report.position, report.cash), (10, -1_000));
+        // Marked at the 100 x 102 mid left behind
+        assert_eq!(report.pnl, 10);
+        assert_eq!((report.maker_qty, report.fill_ratio()), (10, 1.0));
+    }
+
+    /// Crosses the spread once there is an ask, then cancels its leftover.
+    struct Taker(Option<OrderId>);
+
+    impl Strategy for Taker {
+        fn on_tick(&mut self, ctx: &mut Context) {
+            match self.0 {
+                None if ctx.book().best_ask().is_some() => self.0 = Some(ctx.submit(Side::Bid, 101, 8)),
+                None => {}
+                Some(id) if ctx.open_orders().any(|o| o.id == id) => ctx.cancel(id),
+                Some(_) => {}
+            }
+        }
+    }
+
+    #[test]
+    fn taker_fills_report_slippage_and_cancels_land() {
+        let mut backtest = Backtest::new("AAPL", Taker(None));
+        backtest.run([
+            order(1, Side::Bid, 98, 5, 0),
+            order(2, Side::Ask, 101, 5, 1),
+            order(3, Side::Ask, 105, 1, 2),
+            order(4, Side::Ask, 106, 1, 3),
+        ]);
+
+        let report = backtest.report();
+        assert_eq!(report.fills.len(), 1);
+        // Bought 5 at 101 against a 98 x 101 mid of 99
+        assert_eq!((report.taker_qty, report.slippage), (5, 10));
+        assert_eq!(report.orders_cancelled, 1);
+        assert!(!backtest.book().bids.contains(report.fills[0].order));
+    }
+
+    /// Bids once for a size whose cost is past what an i64 holds.
+    struct BigBid;
+
+    impl Strategy for BigBid {
+        fn on_tick(&mut self, ctx: &mut Context) {
+            if ctx.open_orders().next().is_none() && ctx.position() == 0 {
+                ctx.submit(Side::Bid, i64::MAX / 2, 4);
+            }
+        }
+    }
+
+    /// Cash and PnL past the range of an i64 come out exact, not capped.
+    #[test]
+    fn money_past_i64_is_counted_exactly() {
+        let mut backtest = Backtest::new("AAPL", BigBid);
+        backtest.run([order(1, Side::Bid, 1, 1, 0), order(2, Side::Ask, i64::MAX / 2, 4, 1)]);
+
+        let report = backtest.report();
+        let cost = (i64::MAX / 2) as i128 * 4;
+        assert_eq!((report.position, report.cash), (4, -cost));
+        assert!(report.cash < i64::MIN as i128);
+        assert_eq!(report.pnl, 0);
+    }
+
+    #[test]
+    fn recorded_cancels_take_the_previous_time() {
+        let submit = |id: u128, ts_ns: u128| CsvEvent {
+            symbol: "AAPL".into(),
+            event: BookEvent::Submit(Order { id: OrderId(id), symbol: "AAPL".into(), side: Side::Bid, px_ticks: 1, qty: 1, ts_ns, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE }),
+        };
+        let cancel = CsvEvent { symbol: "AAPL".into(), event: BookEvent::Cancel { id: OrderId(1), side: Side::Bid } };
+        let other = CsvEvent { symbol: "MSFT".into(), ..submit(9, 50) };
+
+        let flow = recorded_flow("AAPL", [submit(1, 7), other, cancel]);
+        assert_eq!(flow.len(), 2);
+        assert_eq!(flow[1], FlowEvent::Cancel { id: OrderId(1), side: Side::Bid, ts_ns: 7 });
+    }
+}
@@ -0,0 +1,142 @@
+//! OHLCV candles aggregated from trades.
+//!
+//! A candle covers `[start_ns, start_ns + interval_ns)`, with `start_ns` a
+//! multiple of the interval, so candles from different runs line up. Trades
+//! must arrive in time order per symbol; intervals without trades produce no
+//! candle rather than a flat one.
+
+use crate::symbols::SymbolId;
+use crate::types::Trade;
+use std::collections::HashMap;
+
+/// One interval of trading in one symbol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Candle {
+    pub symbol: SymbolId,
+    pub start_ns: u128,
+    pub interval_ns: u128,
+    pub open: i64,
+    pub high: i64,
+    pub low: i64,
+    pub close: i64,
+    /// Shares traded
+    pub volume: i64,
+    pub trades: u64,
+}
+
+impl Candle {
+    fn open_with(trade: &Trade, start_ns: u128, interval_ns: u128) -> Self {
+        Self {
+            symbol: trade.symbol,
+            start_ns,
+            interval_ns,
+            open: trade.px_ticks,
+            high: trade.px_ticks,
+            low: trade.px_ticks,
+            close: trade.px_ticks,
+            volume: trade.qty,
+            trades: 1,
+        }
+    }
+
+    fn add(&mut self, trade: &Trade) {
+        self.high = self.high.max(trade.px_ticks);
+        self.low = self.low.min(trade.px_ticks);
+        self.close = trade.px_ticks;
+        self.volume = self.volume.saturating_add(trade.qty);
+        self.trades += 1;
+    }
+}
+
+/// Builds candles incrementally from a trade stream, one open candle per
+/// symbol.
+pub struct CandleAggregator {
+    interval_ns: u128,
+    open: HashMap<SymbolId, Candle>,
+}
+
+impl CandleAggregator {
+    /// Panics if `interval_ns` is zero.
+    pub fn new(interval_ns: u128) -> Self {
+        assert!(interval_ns > 0, "candle interval must be positive");
+        Self { interval_ns, open: HashMap::new() }
+    }
+
+    /// Adds a trade; returns its symbol's previous candle if this trade
+    /// starts a new interval.
+    pub fn push(&mut self, trade: &Trade) -> Option<Candle> {
+        let start_ns = trade.ts_ns - trade.ts_ns % self.interval_ns;
+        match self.open.get_mut(&trade.symbol) {
+            Some(candle) if candle.start_ns == start_ns => {
+                candle.add(trade);
+                None
+            }
+            Some(candle) => Some(std::mem::replace(candle, Candle::open_with(trade, start_ns, self.interval_ns))),
+            None => {
+                self.open.insert(trade.symbol, Candle::open_with(trade, start_ns, self.interval_ns));
+                None
+            }
+        }
+    }
+
+    /// The candles still open, by symbol then start time.
+    pub fn finish(self) -> Vec<Candle> {
+        let mut candles: Vec<Candle> = self.open.into_values().collect();
+        candles.sort_by(|a, b| (&a.symbol, a.start_ns).cmp(&(&b.symbol, b.start_ns)));
+        candles
+    }
+}
+
+/// Every candle for `trades`, ordered by symbol, then start time.
+pub fn candles(trades: &[Trade], interval_ns: u128) -> Vec<Candle> {
+    let mut aggregator = CandleAggregator::new(interval_ns);
+    let mut closed: Vec<Candle> = trades.iter().filter_map(|t| aggregator.push(t)).collect();
+    closed.extend(aggregator.finish());
+    closed.sort_by(|a, b| (&a.symbol, a.start_ns).cmp(&(&b.symbol, b.start_ns)));
+    closed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::OrderId;
+
+    fn trade(symbol: &str, ts_ns: u128, px_ticks: i64, qty: i64) -> Trade {
+        Trade { maker: OrderId(1), taker: OrderId(2), symbol: symbol.into(), px_ticks, qty, ts_ns, seq: 0 }
+    }
+
+    #[test]
+    fn buckets_trades_per_symbol_on_aligned_intervals() {
+        let trades = [
+            trade("AAPL", 1_005, 100, 10),
+            trade("MSFT", 1_010, 400, 1),
+            trade("AAPL", 1_500, 104, 5),
+            trade("AAPL", 1_999, 98, 5),
+            trade("AAPL", 2_000, 101, 7),
+            trade("AAPL", 4_100, 99, 2),
+        ];
+        let candles = candles(&trades, 1_000);
+        let summary: Vec<_> =
+            candles.iter().map(|c| (c.symbol.as_str(), c.start_ns, c.open, c.high, c.low, c.close, c.volume)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("AAPL", 1_000, 100, 104, 98, 98, 20),
+                ("AAPL", 2_000, 101, 101, 101, 101, 7),
+                ("AAPL", 4_000, 99, 99, 99, 99, 2),
+                ("MSFT", 1_000, 400, 400, 400, 400, 1),
+            ]
+        );
+        assert_eq!(candles[0].trades, 3);
+    }
+
+    #[test]
+    fn aggregator_closes_a_candle_when_the_next_interval_starts() {
+        let mut aggregator = CandleAggregator::new(10);
+        assert_eq!(aggregator.push(&trade("AAPL", 3, 100, 1)), None);
+        assert_eq!(aggregator.push(&trade("AAPL", 9, 101, 1)), None);
+        let closed = aggregator.push(&trade("AAPL", 10, 102, 1)).unwrap();
+        assert_eq!((closed.start_ns, closed.close, closed.volume), (0, 101, 2));
+        assert_eq!(aggregator.finish()[0].open, 102);
+    }
+}
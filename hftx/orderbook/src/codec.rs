@@ -0,0 +1,1635 @@
+//! Compact, versioned binary encoding for snapshots, journals, and feeds.
+//!
+//! JSON is readable but slow and bulky for deep books. Every binary payload
+//! starts with a 5-byte header, followed by a bincode body (varint integers):
+//!
+//! | bytes | field                                   |
+//! |-------|-----------------------------------------|
+//! | 0..3  | magic `HXB`                             |
+//! | 3     | format version (currently 10)           |
+//! | 4     | payload kind (snapshot, journal, trade) |
+//!
+//! A journal is one header, then one frame per event: a little-endian `u32`
+//! body length and the bincode-encoded [`BookEvent`]. Readers reject other
+//! magics, kinds, and versions newer than [`FORMAT_VERSION`], so a format
+//! change bumps the version and keeps old readers from misparsing.
+//!
+//! Bodies are not the in-memory structs: each format version has its own
+//! frozen wire structs (`mod v1`), so `Order` or `Trade` can gain fields
+//! without changing what old files mean. Writers always use the current
+//! version; readers decode whichever version the header names and convert
+//! to the in-memory types. Migrating is reading vN and writing the current
+//! version: [`migrate_snapshot`] and [`migrate_journal`] do that for whole
+//! files. A new version adds its wire structs in `mod vN`, bumps
+//! [`FORMAT_VERSION`], and keeps the old module for reading.
+
+use crate::pegs::{Peg, PegKind};
+use crate::quotes::Quote;
+use crate::symbols::SymbolId;
+use crate::triggers::StopOrder;
+use crate::types::{Order, OrderId, OrderKind, Side, SymbolConfig, Trade};
+use crate::{OrderBook, PriceLevels};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+pub const MAGIC: [u8; 3] = *b"HXB";
+pub const FORMAT_VERSION: u8 = 10;
+const HEADER_LEN: usize = 5;
+
+/// What a binary payload holds (header byte 4).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PayloadKind {
+    Snapshot = 1,
+    Journal = 2,
+    Trade = 3,
+}
+
+impl TryFrom<u8> for PayloadKind {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Self, u8> {
+        match byte {
+            1 => Ok(PayloadKind::Snapshot),
+            2 => Ok(PayloadKind::Journal),
+            3 => Ok(PayloadKind::Trade),
+            other => Err(other),
+        }
+    }
+}
+
+/// One inbound event, as journaled and replayed. Applying the same events
+/// in the same order to an empty book always builds the same book and
+/// prints the same trades, so a journal is a complete record of a book.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BookEvent {
+    Submit(Order),
+    Cancel { id: OrderId, side: Side },
+    /// [`OrderBook::modify`] of a resting order
+    Modify { id: OrderId, side: Side, px_ticks: i64, qty: i64, ts_ns: u128 },
+    /// [`OrderBook::expire`] at `now_ns`
+    Expire { now_ns: u128 },
+    /// [`OrderBook::submit_market`]
+    Market(Order),
+    /// [`OrderBook::submit_stop`], held until a trade prints at or through
+    /// `stop_px`
+    Stop { order: Order, stop_px: i64, kind: OrderKind },
+    /// [`OrderBook::cancel_stop`] of a held stop
+    CancelStop { id: OrderId },
+    /// [`OrderBook::submit_peg`]
+    Peg { order: Order, kind: PegKind },
+    /// [`OrderBook::submit_quote`], replacing `owner`'s last quote
+    Quote { owner: String, bid: Order, ask: Order },
+    /// [`OrderBook::reprice`] stamped `ts_ns`
+    Reprice { ts_ns: u128 },
+}
+
+impl BookEvent {
+    /// Applies the event, returning any trades it printed (see
+    /// [`OrderBook::apply`]).
+    pub fn apply(self, book: &mut OrderBook) -> Vec<Trade> {
+        book.apply(self)
+    }
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    Io(io::Error),
+    /// Not an HXB payload
+    BadMagic,
+    /// Written by a newer format than this build reads
+    UnsupportedVersion(u8),
+    WrongKind { expected: PayloadKind, found: u8 },
+    /// Input ended mid-header or mid-frame
+    Truncated,
+    Decode(String),
+    /// A snapshot that decodes but that no book could be in: an id twice,
+    /// an order no book would hold, or a broken invariant
+    Invalid(String),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Io(e) => write!(f, "i/o error: {}", e),
+            CodecError::BadMagic => write!(f, "not an HXB payload"),
+            CodecError::UnsupportedVersion(v) => {
+                write!(f, "format version {} is newer than supported version {}", v, FORMAT_VERSION)
+            }
+            CodecError::WrongKind { expected, found } => {
+                write!(f, "expected a {:?} payload, found kind {}", expected, found)
+            }
+            CodecError::Truncated => write!(f, "payload truncated"),
+            CodecError::Decode(e) => write!(f, "decode error: {}", e),
+            CodecError::Invalid(e) => write!(f, "invalid snapshot: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<io::Error> for CodecError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => CodecError::Truncated,
+            _ => CodecError::Io(e),
+        }
+    }
+}
+
+impl From<bincode::Error> for CodecError {
+    fn from(e: bincode::Error) -> Self {
+        match *e {
+            bincode::ErrorKind::Io(e) => e.into(),
+            e => CodecError::Decode(e.to_string()),
+        }
+    }
+}
+
+/// Varint integers, little endian: u128 ids and small sizes shrink to a few
+/// bytes. Part of format version 1; changing it needs a version bump.
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+fn header(kind: PayloadKind) -> [u8; HEADER_LEN] {
+    [MAGIC[0], MAGIC[1], MAGIC[2], FORMAT_VERSION, kind as u8]
+}
+
+/// Checks magic and version, returning the format version.
+fn check_version(bytes: &[u8]) -> Result<u8, CodecError> {
+    if bytes.len() < HEADER_LEN {
+        return Err(CodecError::Truncated);
+    }
+    if bytes[..3] != MAGIC {
+        return Err(CodecError::BadMagic);
+    }
+    if bytes[3] == 0 || bytes[3] > FORMAT_VERSION {
+        return Err(CodecError::UnsupportedVersion(bytes[3]));
+    }
+    Ok(bytes[3])
+}
+
+/// Reads a payload header: the format version it was written with and what
+/// it holds. Fails on anything this build can't read.
+pub fn read_header(bytes: &[u8]) -> Result<(u8, PayloadKind), CodecError> {
+    let version = check_version(bytes)?;
+    let kind = PayloadKind::try_from(bytes[4])
+        .map_err(|found| CodecError::Decode(format!("unknown payload kind {}", found)))?;
+    Ok((version, kind))
+}
+
+/// Validates a header and returns the format version it was written with.
+fn check_header(bytes: &[u8], expected: PayloadKind) -> Result<u8, CodecError> {
+    let version = check_version(bytes)?;
+    if bytes[4] != expected as u8 {
+        return Err(CodecError::WrongKind { expected, found: bytes[4] });
+    }
+    Ok(version)
+}
+
+/// Wire structs of format version 1. Frozen: changing a field, its type, or
+/// a variant's position changes what existing files mean.
+mod v1 {
+    use super::Cow;
+    use crate::symbols::SymbolId;
+    use crate::types;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    pub enum Side {
+        Bid,
+        Ask,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Order<'a> {
+        pub id: u128,
+        pub symbol: Cow<'a, str>,
+        pub side: Side,
+        pub px_ticks: i64,
+        pub qty: i64,
+        pub ts_ns: u128,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Trade<'a> {
+        pub maker: u128,
+        pub taker: u128,
+        pub symbol: Cow<'a, str>,
+        pub px_ticks: i64,
+        pub qty: i64,
+        pub ts_ns: u128,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum BookEvent<'a> {
+        Submit(Order<'a>),
+        Cancel { id: u128, side: Side },
+    }
+
+    /// Each side's live orders in priority order.
+    #[derive(Serialize, Deserialize)]
+    pub struct Snapshot<'a> {
+        pub bids: Vec<Order<'a>>,
+        pub asks: Vec<Order<'a>>,
+    }
+
+    impl From<types::Side> for Side {
+        fn from(side: types::Side) -> Self {
+            match side {
+                types::Side::Bid => Side::Bid,
+                types::Side::Ask => Side::Ask,
+            }
+        }
+    }
+
+    impl From<Side> for types::Side {
+        fn from(side: Side) -> Self {
+            match side {
+                Side::Bid => types::Side::Bid,
+                Side::Ask => types::Side::Ask,
+            }
+        }
+    }
+
+    impl<'a> From<&'a types::Order> for Order<'a> {
+        fn from(order: &'a types::Order) -> Self {
+            Order {
+                id: order.id.0,
+                symbol: Cow::Borrowed(order.symbol.as_str()),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+            }
+        }
+    }
+
+    impl From<Order<'_>> for types::Order {
+        fn from(order: Order) -> Self {
+            types::Order {
+                id: types::OrderId(order.id),
+                symbol: SymbolId::intern(&order.symbol),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: types::TimeInForce::Day,
+                expires_ns: None,
+                display_qty: None,
+                min_qty: None,
+                hidden: false,
+                owner: types::ParticipantId::NONE,
+            }
+        }
+    }
+
+    impl From<Trade<'_>> for types::Trade {
+        fn from(trade: Trade) -> Self {
+            types::Trade {
+                maker: types::OrderId(trade.maker),
+                taker: types::OrderId(trade.taker),
+                symbol: SymbolId::intern(&trade.symbol),
+                px_ticks: trade.px_ticks,
+                qty: trade.qty,
+                ts_ns: trade.ts_ns,
+                seq: 0,
+            }
+        }
+    }
+
+    impl From<BookEvent<'_>> for super::BookEvent {
+        fn from(event: BookEvent) -> Self {
+            match event {
+                BookEvent::Submit(order) => super::BookEvent::Submit(order.into()),
+                BookEvent::Cancel { id, side } => super::BookEvent::Cancel { id: types::OrderId(id), side: side.into() },
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 2, which added `tif` and `expires_ns` to
+/// orders. Trades are as in version 1. Frozen like `v1`.
+mod v2 {
+    use super::{v1, Cow};
+    use crate::symbols::SymbolId;
+    use crate::types;
+    use serde::{Deserialize, Serialize};
+
+    pub use v1::Side;
+
+    #[derive(Serialize, Deserialize)]
+    pub enum TimeInForce {
+        Day,
+        Ioc,
+        Fok,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Order<'a> {
+        pub id: u128,
+        pub symbol: Cow<'a, str>,
+        pub side: Side,
+        pub px_ticks: i64,
+        pub qty: i64,
+        pub ts_ns: u128,
+        pub tif: TimeInForce,
+        pub expires_ns: Option<u128>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum BookEvent<'a> {
+        Submit(Order<'a>),
+        Cancel { id: u128, side: Side },
+    }
+
+    /// Each side's live orders in priority order.
+    #[derive(Serialize, Deserialize)]
+    pub struct Snapshot<'a> {
+        pub bids: Vec<Order<'a>>,
+        pub asks: Vec<Order<'a>>,
+    }
+
+    impl From<types::TimeInForce> for TimeInForce {
+        fn from(tif: types::TimeInForce) -> Self {
+            match tif {
+                types::TimeInForce::Day => TimeInForce::Day,
+                types::TimeInForce::IOC => TimeInForce::Ioc,
+                types::TimeInForce::FOK => TimeInForce::Fok,
+            }
+        }
+    }
+
+    impl From<TimeInForce> for types::TimeInForce {
+        fn from(tif: TimeInForce) -> Self {
+            match tif {
+                TimeInForce::Day => types::TimeInForce::Day,
+                TimeInForce::Ioc => types::TimeInForce::IOC,
+                TimeInForce::Fok => types::TimeInForce::FOK,
+            }
+        }
+    }
+
+    impl<'a> From<&'a types::Order> for Order<'a> {
+        fn from(order: &'a types::Order) -> Self {
+            Order {
+                id: order.id.0,
+                symbol: Cow::Borrowed(order.symbol.as_str()),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+            }
+        }
+    }
+
+    impl From<Order<'_>> for types::Order {
+        fn from(order: Order) -> Self {
+            types::Order {
+                id: types::OrderId(order.id),
+                symbol: SymbolId::intern(&order.symbol),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: None,
+                min_qty: None,
+                hidden: false,
+                owner: types::ParticipantId::NONE,
+            }
+        }
+    }
+
+    impl From<BookEvent<'_>> for super::BookEvent {
+        fn from(event: BookEvent) -> Self {
+            match event {
+                BookEvent::Submit(order) => super::BookEvent::Submit(order.into()),
+                BookEvent::Cancel { id, side } => super::BookEvent::Cancel { id: types::OrderId(id), side: side.into() },
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 3, which added `display_qty` to orders.
+/// Trades are as in version 1. Frozen like `v1`.
+mod v3 {
+    use super::{v2, Cow};
+    use crate::symbols::SymbolId;
+    use crate::types;
+    use serde::{Deserialize, Serialize};
+
+    pub use v2::{Side, TimeInForce};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Order<'a> {
+        pub id: u128,
+        pub symbol: Cow<'a, str>,
+        pub side: Side,
+        pub px_ticks: i64,
+        pub qty: i64,
+        pub ts_ns: u128,
+        pub tif: TimeInForce,
+        pub expires_ns: Option<u128>,
+        pub display_qty: Option<i64>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum BookEvent<'a> {
+        Submit(Order<'a>),
+        Cancel { id: u128, side: Side },
+    }
+
+    /// Each side's live orders in priority order. An iceberg's `qty`
+    /// includes what it holds back.
+    #[derive(Serialize, Deserialize)]
+    pub struct Snapshot<'a> {
+        pub bids: Vec<Order<'a>>,
+        pub asks: Vec<Order<'a>>,
+    }
+
+    impl<'a> From<&'a types::Order> for Order<'a> {
+        fn from(order: &'a types::Order) -> Self {
+            Order {
+                id: order.id.0,
+                symbol: Cow::Borrowed(order.symbol.as_str()),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: order.display_qty,
+            }
+        }
+    }
+
+    impl From<Order<'_>> for types::Order {
+        fn from(order: Order) -> Self {
+            types::Order {
+                id: types::OrderId(order.id),
+                symbol: SymbolId::intern(&order.symbol),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: order.display_qty,
+                min_qty: None,
+                hidden: false,
+                owner: types::ParticipantId::NONE,
+            }
+        }
+    }
+
+    impl From<BookEvent<'_>> for super::BookEvent {
+        fn from(event: BookEvent) -> Self {
+            match event {
+                BookEvent::Submit(order) => super::BookEvent::Submit(order.into()),
+                BookEvent::Cancel { id, side } => super::BookEvent::Cancel { id: types::OrderId(id), side: side.into() },
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 4, which added `min_qty` to orders.
+/// Trades are as in version 1. Frozen like `v1`.
+mod v4 {
+    use super::{v2, Cow};
+    use crate::symbols::SymbolId;
+    use crate::types;
+    use serde::{Deserialize, Serialize};
+
+    pub use v2::{Side, TimeInForce};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Order<'a> {
+        pub id: u128,
+        pub symbol: Cow<'a, str>,
+        pub side: Side,
+        pub px_ticks: i64,
+        pub qty: i64,
+        pub ts_ns: u128,
+        pub tif: TimeInForce,
+        pub expires_ns: Option<u128>,
+        pub display_qty: Option<i64>,
+        pub min_qty: Option<i64>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum BookEvent<'a> {
+        Submit(Order<'a>),
+        Cancel { id: u128, side: Side },
+    }
+
+    /// Each side's live orders in priority order. An iceberg's `qty`
+    /// includes what it holds back.
+    #[derive(Serialize, Deserialize)]
+    pub struct Snapshot<'a> {
+        pub bids: Vec<Order<'a>>,
+        pub asks: Vec<Order<'a>>,
+    }
+
+    impl<'a> From<&'a types::Order> for Order<'a> {
+        fn from(order: &'a types::Order) -> Self {
+            Order {
+                id: order.id.0,
+                symbol: Cow::Borrowed(order.symbol.as_str()),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: order.display_qty,
+                min_qty: order.min_qty,
+            }
+        }
+    }
+
+    impl From<Order<'_>> for types::Order {
+        fn from(order: Order) -> Self {
+            types::Order {
+                id: types::OrderId(order.id),
+                symbol: SymbolId::intern(&order.symbol),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: order.display_qty,
+                min_qty: order.min_qty,
+                hidden: false,
+                owner: types::ParticipantId::NONE,
+            }
+        }
+    }
+
+    impl From<BookEvent<'_>> for super::BookEvent {
+        fn from(event: BookEvent) -> Self {
+            match event {
+                BookEvent::Submit(order) => super::BookEvent::Submit(order.into()),
+                BookEvent::Cancel { id, side } => super::BookEvent::Cancel { id: types::OrderId(id), side: side.into() },
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 5, which added `hidden` to orders.
+/// Trades are as in version 1. Frozen like `v1`.
+mod v5 {
+    use super::{v2, Cow};
+    use crate::symbols::SymbolId;
+    use crate::types;
+    use serde::{Deserialize, Serialize};
+
+    pub use v2::{Side, TimeInForce};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Order<'a> {
+        pub id: u128,
+        pub symbol: Cow<'a, str>,
+        pub side: Side,
+        pub px_ticks: i64,
+        pub qty: i64,
+        pub ts_ns: u128,
+        pub tif: TimeInForce,
+        pub expires_ns: Option<u128>,
+        pub display_qty: Option<i64>,
+        pub min_qty: Option<i64>,
+        pub hidden: bool,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum BookEvent<'a> {
+        Submit(Order<'a>),
+        Cancel { id: u128, side: Side },
+    }
+
+    /// Each side's live orders in priority order. An iceberg's `qty`
+    /// includes what it holds back.
+    #[derive(Serialize, Deserialize)]
+    pub struct Snapshot<'a> {
+        pub bids: Vec<Order<'a>>,
+        pub asks: Vec<Order<'a>>,
+    }
+
+    impl<'a> From<&'a types::Order> for Order<'a> {
+        fn from(order: &'a types::Order) -> Self {
+            Order {
+                id: order.id.0,
+                symbol: Cow::Borrowed(order.symbol.as_str()),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: order.display_qty,
+                min_qty: order.min_qty,
+                hidden: order.hidden,
+            }
+        }
+    }
+
+    impl From<Order<'_>> for types::Order {
+        fn from(order: Order) -> Self {
+            types::Order {
+                id: types::OrderId(order.id),
+                symbol: SymbolId::intern(&order.symbol),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: order.display_qty,
+                min_qty: order.min_qty,
+                hidden: order.hidden,
+                owner: types::ParticipantId::NONE,
+            }
+        }
+    }
+
+    impl From<BookEvent<'_>> for super::BookEvent {
+        fn from(event: BookEvent) -> Self {
+            match event {
+                BookEvent::Submit(order) => super::BookEvent::Submit(order.into()),
+                BookEvent::Cancel { id, side } => super::BookEvent::Cancel { id: types::OrderId(id), side: side.into() },
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 6, which added amends and expiries to
+/// journaled events. Orders, snapshots and trades are as in version 5.
+/// Frozen like `v1`.
+mod v6 {
+    use super::v5;
+    use crate::types;
+    use serde::{Deserialize, Serialize};
+
+    pub use v5::{Order, Side, Snapshot};
+
+    #[derive(Serialize, Deserialize)]
+    pub enum BookEvent<'a> {
+        Submit(Order<'a>),
+        Cancel { id: u128, side: Side },
+        Modify { id: u128, side: Side, px_ticks: i64, qty: i64, ts_ns: u128 },
+        Expire { now_ns: u128 },
+    }
+
+    impl From<BookEvent<'_>> for super::BookEvent {
+        fn from(event: BookEvent) -> Self {
+            match event {
+                BookEvent::Submit(order) => super::BookEvent::Submit(order.into()),
+                BookEvent::Cancel { id, side } => super::BookEvent::Cancel { id: types::OrderId(id), side: side.into() },
+                BookEvent::Modify { id, side, px_ticks, qty, ts_ns } => {
+                    super::BookEvent::Modify { id: types::OrderId(id), side: side.into(), px_ticks, qty, ts_ns }
+                }
+                BookEvent::Expire { now_ns } => super::BookEvent::Expire { now_ns },
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 7, which added the book's event number
+/// to trades and snapshots. Orders and journaled events are as in version
+/// 6. Frozen like `v1`.
+mod v7 {
+    use super::{v6, Cow};
+    use crate::symbols::SymbolId;
+    use crate::types;
+    use serde::{Deserialize, Serialize};
+
+    pub use v6::{BookEvent, Order};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Trade<'a> {
+        pub maker: u128,
+        pub taker: u128,
+        pub symbol: Cow<'a, str>,
+        pub px_ticks: i64,
+        pub qty: i64,
+        pub ts_ns: u128,
+        pub seq: u64,
+    }
+
+    /// Each side's live orders in priority order, and the number of the
+    /// book's last event. An iceberg's `qty` includes what it holds back.
+    #[derive(Serialize, Deserialize)]
+    pub struct Snapshot<'a> {
+        pub seq: u64,
+        pub bids: Vec<Order<'a>>,
+        pub asks: Vec<Order<'a>>,
+    }
+
+    impl<'a> From<&'a types::Trade> for Trade<'a> {
+        fn from(trade: &'a types::Trade) -> Self {
+            Trade {
+                maker: trade.maker.0,
+                taker: trade.taker.0,
+                symbol: Cow::Borrowed(trade.symbol.as_str()),
+                px_ticks: trade.px_ticks,
+                qty: trade.qty,
+                ts_ns: trade.ts_ns,
+                seq: trade.seq,
+            }
+        }
+    }
+
+    impl From<Trade<'_>> for types::Trade {
+        fn from(trade: Trade) -> Self {
+            types::Trade {
+                maker: types::OrderId(trade.maker),
+                taker: types::OrderId(trade.taker),
+                symbol: SymbolId::intern(&trade.symbol),
+                px_ticks: trade.px_ticks,
+                qty: trade.qty,
+                ts_ns: trade.ts_ns,
+                seq: trade.seq,
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 8, which added `owner` to orders.
+/// Trades are as in version 7. Frozen like `v1`.
+mod v8 {
+    use super::{v2, v7, Cow};
+    use crate::symbols::SymbolId;
+    use crate::types;
+    use serde::{Deserialize, Serialize};
+
+    pub use v2::{Side, TimeInForce};
+    pub use v7::Trade;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Order<'a> {
+        pub id: u128,
+        pub symbol: Cow<'a, str>,
+        pub side: Side,
+        pub px_ticks: i64,
+        pub qty: i64,
+        pub ts_ns: u128,
+        pub tif: TimeInForce,
+        pub expires_ns: Option<u128>,
+        pub display_qty: Option<i64>,
+        pub min_qty: Option<i64>,
+        pub hidden: bool,
+        pub owner: u32,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum BookEvent<'a> {
+        Submit(Order<'a>),
+        Cancel { id: u128, side: Side },
+        Modify { id: u128, side: Side, px_ticks: i64, qty: i64, ts_ns: u128 },
+        Expire { now_ns: u128 },
+    }
+
+    /// Each side's live orders in priority order, and the number of the
+    /// book's last event. An iceberg's `qty` includes what it holds back.
+    #[derive(Serialize, Deserialize)]
+    pub struct Snapshot<'a> {
+        pub seq: u64,
+        pub bids: Vec<Order<'a>>,
+        pub asks: Vec<Order<'a>>,
+    }
+
+    impl<'a> From<&'a types::Order> for Order<'a> {
+        fn from(order: &'a types::Order) -> Self {
+            Order {
+                id: order.id.0,
+                symbol: Cow::Borrowed(order.symbol.as_str()),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: order.display_qty,
+                min_qty: order.min_qty,
+                hidden: order.hidden,
+                owner: order.owner.0,
+            }
+        }
+    }
+
+    impl From<Order<'_>> for types::Order {
+        fn from(order: Order) -> Self {
+            types::Order {
+                id: types::OrderId(order.id),
+                symbol: SymbolId::intern(&order.symbol),
+                side: order.side.into(),
+                px_ticks: order.px_ticks,
+                qty: order.qty,
+                ts_ns: order.ts_ns,
+                tif: order.tif.into(),
+                expires_ns: order.expires_ns,
+                display_qty: order.display_qty,
+                min_qty: order.min_qty,
+                hidden: order.hidden,
+                owner: types::ParticipantId(order.owner),
+            }
+        }
+    }
+
+    impl From<BookEvent<'_>> for super::BookEvent {
+        fn from(event: BookEvent) -> Self {
+            match event {
+                BookEvent::Submit(order) => super::BookEvent::Submit(order.into()),
+                BookEvent::Cancel { id, side } => super::BookEvent::Cancel { id: types::OrderId(id), side: side.into() },
+                BookEvent::Modify { id, side, px_ticks, qty, ts_ns } => {
+                    super::BookEvent::Modify { id: types::OrderId(id), side: side.into(), px_ticks, qty, ts_ns }
+                }
+                BookEvent::Expire { now_ns } => super::BookEvent::Expire { now_ns },
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 9, which added market, stop, pegged and
+/// quote submissions, stop cancels and reprices to journaled events.
+/// Orders, snapshots and trades are as in version 8. Frozen like `v1`.
+mod v9 {
+    use super::{v8, Cow};
+    use crate::{pegs, types};
+    use serde::{Deserialize, Serialize};
+
+    pub use v8::{Order, Side};
+
+    #[derive(Serialize, Deserialize)]
+    pub enum OrderKind {
+        Limit,
+        Market,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum PegKind {
+        Primary,
+        Midpoint,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum BookEvent<'a> {
+        Submit(Order<'a>),
+        Cancel { id: u128, side: Side },
+        Modify { id: u128, side: Side, px_ticks: i64, qty: i64, ts_ns: u128 },
+        Expire { now_ns: u128 },
+        Market(Order<'a>),
+        Stop { order: Order<'a>, stop_px: i64, kind: OrderKind },
+        CancelStop { id: u128 },
+        Peg { order: Order<'a>, kind: PegKind },
+        Quote { owner: Cow<'a, str>, bid: Order<'a>, ask: Order<'a> },
+        Reprice { ts_ns: u128 },
+    }
+
+    impl From<types::OrderKind> for OrderKind {
+        fn from(kind: types::OrderKind) -> Self {
+            match kind {
+                types::OrderKind::Limit => OrderKind::Limit,
+                types::OrderKind::Market => OrderKind::Market,
+            }
+        }
+    }
+
+    impl From<OrderKind> for types::OrderKind {
+        fn from(kind: OrderKind) -> Self {
+            match kind {
+                OrderKind::Limit => types::OrderKind::Limit,
+                OrderKind::Market => types::OrderKind::Market,
+            }
+        }
+    }
+
+    impl From<pegs::PegKind> for PegKind {
+        fn from(kind: pegs::PegKind) -> Self {
+            match kind {
+                pegs::PegKind::Primary => PegKind::Primary,
+                pegs::PegKind::Midpoint => PegKind::Midpoint,
+            }
+        }
+    }
+
+    impl From<PegKind> for pegs::PegKind {
+        fn from(kind: PegKind) -> Self {
+            match kind {
+                PegKind::Primary => pegs::PegKind::Primary,
+                PegKind::Midpoint => pegs::PegKind::Midpoint,
+            }
+        }
+    }
+
+    impl<'a> From<&'a super::BookEvent> for BookEvent<'a> {
+        fn from(event: &'a super::BookEvent) -> Self {
+            match event {
+                super::BookEvent::Submit(order) => BookEvent::Submit(order.into()),
+                super::BookEvent::Cancel { id, side } => BookEvent::Cancel { id: id.0, side: (*side).into() },
+                super::BookEvent::Modify { id, side, px_ticks, qty, ts_ns } => {
+                    BookEvent::Modify { id: id.0, side: (*side).into(), px_ticks: *px_ticks, qty: *qty, ts_ns: *ts_ns }
+                }
+                super::BookEvent::Expire { now_ns } => BookEvent::Expire { now_ns: *now_ns },
+                super::BookEvent::Market(order) => BookEvent::Market(order.into()),
+                super::BookEvent::Stop { order, stop_px, kind } => {
+                    BookEvent::Stop { order: order.into(), stop_px: *stop_px, kind: (*kind).into() }
+                }
+                super::BookEvent::CancelStop { id } => BookEvent::CancelStop { id: id.0 },
+                super::BookEvent::Peg { order, kind } => BookEvent::Peg { order: order.into(), kind: (*kind).into() },
+                super::BookEvent::Quote { owner, bid, ask } => {
+                    BookEvent::Quote { owner: Cow::Borrowed(owner), bid: bid.into(), ask: ask.into() }
+                }
+                super::BookEvent::Reprice { ts_ns } => BookEvent::Reprice { ts_ns: *ts_ns },
+            }
+        }
+    }
+
+    impl From<BookEvent<'_>> for super::BookEvent {
+        fn from(event: BookEvent) -> Self {
+            match event {
+                BookEvent::Submit(order) => super::BookEvent::Submit(order.into()),
+                BookEvent::Cancel { id, side } => super::BookEvent::Cancel { id: types::OrderId(id), side: side.into() },
+                BookEvent::Modify { id, side, px_ticks, qty, ts_ns } => {
+                    super::BookEvent::Modify { id: types::OrderId(id), side: side.into(), px_ticks, qty, ts_ns }
+                }
+                BookEvent::Expire { now_ns } => super::BookEvent::Expire { now_ns },
+                BookEvent::Market(order) => super::BookEvent::Market(order.into()),
+                BookEvent::Stop { order, stop_px, kind } => super::BookEvent::Stop { order: order.into(), stop_px, kind: kind.into() },
+                BookEvent::CancelStop { id } => super::BookEvent::CancelStop { id: types::OrderId(id) },
+                BookEvent::Peg { order, kind } => super::BookEvent::Peg { order: order.into(), kind: kind.into() },
+                BookEvent::Quote { owner, bid, ask } => {
+                    super::BookEvent::Quote { owner: owner.into_owned(), bid: bid.into(), ask: ask.into() }
+                }
+                BookEvent::Reprice { ts_ns } => super::BookEvent::Reprice { ts_ns },
+            }
+        }
+    }
+}
+
+/// Wire structs of format version 10, which added the rest of a book's
+/// state to snapshots: held stops, pegs, quotes, the last trade price, the
+/// bound symbol and the config. Orders, journaled events and trades are as
+/// in version 9. Frozen like `v1`.
+mod v10 {
+    use super::{v8, v9, Cow};
+    use crate::{pegs, quotes, triggers, types};
+    use serde::{Deserialize, Serialize};
+
+    pub use v8::Trade;
+    pub use v9::{BookEvent, Order, OrderKind, PegKind, Side};
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Stop<'a> {
+        pub order: Order<'a>,
+        pub stop_px: i64,
+        pub kind: OrderKind,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Peg {
+        pub id: u128,
+        pub kind: PegKind,
+        pub side: Side,
+        pub limit_px: i64,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Quote<'a> {
+        pub owner: Cow<'a, str>,
+        pub bid: Option<u128>,
+        pub ask: Option<u128>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub struct SymbolConfig {
+        pub tick_size: i64,
+        pub lot_size: i64,
+        pub min_qty: i64,
+        pub max_qty: i64,
+    }
+
+    /// Each side's live orders in priority order, held stops by side then
+    /// stop price then arrival, pegs in arrival order, quotes by owner,
+    /// and the number of the book's last event. An iceberg's `qty`
+    /// includes what it holds back.
+    #[derive(Serialize, Deserialize)]
+    pub struct Snapshot<'a> {
+        pub seq: u64,
+        pub bids: Vec<Order<'a>>,
+        pub asks: Vec<Order<'a>>,
+        pub stops: Vec<Stop<'a>>,
+        pub pegs: Vec<Peg>,
+        pub quotes: Vec<Quote<'a>>,
+        pub last_px: Option<i64>,
+        pub symbol: Option<Cow<'a, str>>,
+        pub config: SymbolConfig,
+    }
+
+    impl<'a> From<&'a triggers::StopOrder> for Stop<'a> {
+        fn from(stop: &'a triggers::StopOrder) -> Self {
+            Stop { order: (&stop.order).into(), stop_px: stop.stop_px, kind: stop.kind.into() }
+        }
+    }
+
+    impl From<Stop<'_>> for triggers::StopOrder {
+        fn from(stop: Stop) -> Self {
+            triggers::StopOrder { order: stop.order.into(), stop_px: stop.stop_px, kind: stop.kind.into() }
+        }
+    }
+
+    impl From<(types::OrderId, &pegs::Peg)> for Peg {
+        fn from((id, peg): (types::OrderId, &pegs::Peg)) -> Self {
+            Peg { id: id.0, kind: peg.kind.into(), side: peg.side.into(), limit_px: peg.limit_px }
+        }
+    }
+
+    impl From<Peg> for (types::OrderId, pegs::Peg) {
+        fn from(peg: Peg) -> Self {
+            (types::OrderId(peg.id), pegs::Peg { kind: peg.kind.into(), side: peg.side.into(), limit_px: peg.limit_px })
+        }
+    }
+
+    impl<'a> From<(&'a str, &quotes::Quote)> for Quote<'a> {
+        fn from((owner, quote): (&'a str, &quotes::Quote)) -> Self {
+            Quote { owner: Cow::Borrowed(owner), bid: quote.bid.map(|id| id.0), ask: quote.ask.map(|id| id.0) }
+        }
+    }
+
+    impl From<Quote<'_>> for (String, quotes::Quote) {
+        fn from(quote: Quote) -> Self {
+            let (bid, ask) = (quote.bid.map(types::OrderId), quote.ask.map(types::OrderId));
+            (quote.owner.into_owned(), quotes::Quote { bid, ask })
+        }
+    }
+
+    impl From<&types::SymbolConfig> for SymbolConfig {
+        fn from(config: &types::SymbolConfig) -> Self {
+            SymbolConfig {
+                tick_size: config.tick_size,
+                lot_size: config.lot_size,
+                min_qty: config.min_qty,
+                max_qty: config.max_qty,
+            }
+        }
+    }
+
+    impl From<SymbolConfig> for types::SymbolConfig {
+        fn from(config: SymbolConfig) -> Self {
+            types::SymbolConfig {
+                tick_size: config.tick_size,
+                lot_size: config.lot_size,
+                min_qty: config.min_qty,
+                max_qty: config.max_qty,
+            }
+        }
+    }
+}
+
+/// Encodes `book` whole: its live resting orders, each side in priority
+/// order, its held stops, pegs and quotes, the price of its last trade,
+/// the symbol it is bound to and its config. Tombstones are not written,
+/// so a snapshot is also a compaction. An iceberg is written with its
+/// hidden quantity, and restored showing a full slice. The book's event
+/// number goes with them, so a restored book numbers on from where this
+/// one is. Order status, listeners and the throttle are left out.
+pub fn encode_snapshot(book: &OrderBook) -> Vec<u8> {
+    let mut out = header(PayloadKind::Snapshot).to_vec();
+    fn orders(side: &PriceLevels) -> Vec<v10::Order<'_>> {
+        side.live_orders()
+            .map(|order| v10::Order { qty: order.qty + side.hidden_qty(order.id), ..order.into() })
+            .collect()
+    }
+    // Sells come out of `held` highest stop first
+    let mut sells: Vec<_> = book.stops.held(Side::Ask).map(v10::Stop::from).collect();
+    sells.reverse();
+    let mut quotes: Vec<_> = book.quotes.iter().map(v10::Quote::from).collect();
+    quotes.sort_by(|a, b| a.owner.cmp(&b.owner));
+    let snapshot = v10::Snapshot {
+        seq: book.seq(),
+        bids: orders(&book.bids),
+        asks: orders(&book.asks),
+        stops: book.stops.held(Side::Bid).map(v10::Stop::from).chain(sells).collect(),
+        pegs: book.pegs.iter().map(v10::Peg::from).collect(),
+        quotes,
+        last_px: book.last_px,
+        symbol: book.symbol().map(|symbol| Cow::Borrowed(symbol.as_str())),
+        config: book.config().into(),
+    };
+    bincode_options().serialize_into(&mut out, &snapshot).expect("writing to a Vec cannot fail");
+    out
+}
+
+/// What a snapshot holds, whichever version wrote it. Versions before 10
+/// hold only orders and, from 7, the event number.
+#[derive(Default)]
+struct Contents {
+    seq: u64,
+    bids: Vec<Order>,
+    asks: Vec<Order>,
+    stops: Vec<StopOrder>,
+    pegs: Vec<(OrderId, Peg)>,
+    quotes: Vec<(String, Quote)>,
+    last_px: Option<i64>,
+    symbol: Option<SymbolId>,
+    config: SymbolConfig,
+}
+
+/// Rebuilds a book from `encode_snapshot` output of any readable version,
+/// with the same priority. Refused with [`CodecError::Invalid`] unless it
+/// makes a book: each id once, every order one a book would take (bound
+/// to the book's symbol, a positive quantity, a positive price unless it
+/// is a market stop), each peg on a resting order of its side, a valid
+/// config, and the book's invariants holding once it is built.
+pub fn decode_snapshot(bytes: &[u8]) -> Result<OrderBook, CodecError> {
+    let version = check_header(bytes, PayloadKind::Snapshot)?;
+    let body = &bytes[HEADER_LEN..];
+    fn orders<T: Into<Order>>(orders: Vec<T>) -> Vec<Order> {
+        orders.into_iter().map(Into::into).collect()
+    }
+    // Snapshots before version 7 don't number events; a book restored from
+    // one numbers from 1 again
+    let contents = match version {
+        1 => {
+            let snapshot: v1::Snapshot = bincode_options().deserialize(body)?;
+            Contents { bids: orders(snapshot.bids), asks: orders(snapshot.asks), ..Contents::default() }
+        }
+        2 => {
+            let snapshot: v2::Snapshot = bincode_options().deserialize(body)?;
+            Contents { bids: orders(snapshot.bids), asks: orders(snapshot.asks), ..Contents::default() }
+        }
+        3 => {
+            let snapshot: v3::Snapshot = bincode_options().deserialize(body)?;
+            Contents { bids: orders(snapshot.bids), asks: orders(snapshot.asks), ..Contents::default() }
+        }
+        4 => {
+            let snapshot: v4::Snapshot = bincode_options().deserialize(body)?;
+            Contents { bids: orders(snapshot.bids), asks: orders(snapshot.asks), ..Contents::default() }
+        }
+        5 | 6 => {
+            let snapshot: v6::Snapshot = bincode_options().deserialize(body)?;
+            Contents { bids: orders(snapshot.bids), asks: orders(snapshot.asks), ..Contents::default() }
+        }
+        7 => {
+            let snapshot: v7::Snapshot = bincode_options().deserialize(body)?;
+            Contents { seq: snapshot.seq, bids: orders(snapshot.bids), asks: orders(snapshot.asks), ..Contents::default() }
+        }
+        8 | 9 => {
+            let snapshot: v8::Snapshot = bincode_options().deserialize(body)?;
+            Contents { seq: snapshot.seq, bids: orders(snapshot.bids), asks: orders(snapshot.asks), ..Contents::default() }
+        }
+        _ => {
+            let snapshot: v10::Snapshot = bincode_options().deserialize(body)?;
+            Contents {
+                seq: snapshot.seq,
+                bids: orders(snapshot.bids),
+                asks: orders(snapshot.asks),
+                stops: snapshot.stops.into_iter().map(StopOrder::from).collect(),
+                pegs: snapshot.pegs.into_iter().map(<(OrderId, Peg)>::from).collect(),
+                quotes: snapshot.quotes.into_iter().map(<(String, Quote)>::from).collect(),
+                last_px: snapshot.last_px,
+                symbol: snapshot.symbol.map(|symbol| SymbolId::intern(&symbol)),
+                config: snapshot.config.into(),
+            }
+        }
+    };
+    restore(contents)
+}
+
+/// Builds the book `contents` describe, checking it as
+/// [`decode_snapshot`] says.
+fn restore(contents: Contents) -> Result<OrderBook, CodecError> {
+    let mut book = OrderBook::new().with_config(contents.config).map_err(CodecError::Invalid)?;
+    book.symbol = contents.symbol;
+    book.seq = contents.seq;
+    book.last_px = contents.last_px;
+    for (side, orders) in [(Side::Bid, contents.bids), (Side::Ask, contents.asks)] {
+        for order in orders {
+            check_restored(&book, &order, OrderKind::Limit)?;
+            if order.side != side {
+                return Err(CodecError::Invalid(format!("order {} is listed on the wrong side", order.id.0)));
+            }
+            book.levels_mut(side).push(order);
+        }
+    }
+    for stop in contents.stops {
+        check_restored(&book, &stop.order, stop.kind)?;
+        if stop.stop_px <= 0 {
+            return Err(CodecError::Invalid(format!("stop {} has stop price {}", stop.order.id.0, stop.stop_px)));
+        }
+        book.stops.hold(stop);
+    }
+    for (id, peg) in contents.pegs {
+        if book.side_of(id) != Some(peg.side) || book.pegs.contains(id) {
+            return Err(CodecError::Invalid(format!("peg {} isn't one resting order of its side", id.0)));
+        }
+        book.pegs.insert(id, peg);
+    }
+    for (owner, quote) in contents.quotes {
+        book.quotes.replace(&owner, quote);
+    }
+    book.check_invariants().map_err(|violation| CodecError::Invalid(violation.to_string()))?;
+    Ok(book)
+}
+
+/// Refuses an order no book would hold as `kind`, or one whose id the
+/// book being restored already has.
+fn check_restored(book: &OrderBook, order: &Order, kind: OrderKind) -> Result<(), CodecError> {
+    if book.is_live(order.id) {
+        return Err(CodecError::Invalid(format!("order {} appears twice", order.id.0)));
+    }
+    let unpriced = kind == OrderKind::Limit && order.px_ticks <= 0;
+    let symbol = book.symbol().is_some_and(|symbol| symbol != order.symbol);
+    if order.qty <= 0 || unpriced || symbol || order.display_qty.is_some_and(|qty| qty <= 0) {
+        return Err(CodecError::Invalid(format!("order {} isn't one a book would hold", order.id.0)));
+    }
+    Ok(())
+}
+
+/// Encodes one trade as a standalone message, e.g. a binary feed frame.
+pub fn encode_trade(trade: &Trade) -> Vec<u8> {
+    let mut out = header(PayloadKind::Trade).to_vec();
+    bincode_options().serialize_into(&mut out, &v10::Trade::from(trade)).expect("writing to a Vec cannot fail");
+    out
+}
+
+/// Decodes an `encode_trade` message of any readable version. Trades
+/// before version 7 come back with `seq` 0.
+pub fn decode_trade(bytes: &[u8]) -> Result<Trade, CodecError> {
+    let version = check_header(bytes, PayloadKind::Trade)?;
+    let body = &bytes[HEADER_LEN..];
+    Ok(match version {
+        1..=6 => bincode_options().deserialize::<v1::Trade>(body)?.into(),
+        _ => bincode_options().deserialize::<v10::Trade>(body)?.into(),
+    })
+}
+
+/// Rewrites a snapshot of any readable version at [`FORMAT_VERSION`].
+pub fn migrate_snapshot(bytes: &[u8]) -> Result<Vec<u8>, CodecError> {
+    Ok(encode_snapshot(&decode_snapshot(bytes)?))
+}
+
+/// Copies a journal of any readable version to `output` at
+/// [`FORMAT_VERSION`], returning the number of events copied.
+pub fn migrate_journal<R: Read, W: Write>(input: R, output: W) -> Result<u64, CodecError> {
+    let mut writer = JournalWriter::new(output)?;
+    let mut copied = 0;
+    for event in JournalReader::new(input)? {
+        writer.append(&event?)?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+/// Applies a journal of any readable version to `book`, event by event,
+/// and returns how many it applied. On an empty book this rebuilds the
+/// book the journal was written from; on one from [`decode_snapshot`] it
+/// replays what happened after the snapshot was taken.
+pub fn replay_journal<R: Read>(book: &mut OrderBook, input: R) -> Result<u64, CodecError> {
+    let mut applied = 0;
+    for event in JournalReader::new(input)? {
+        book.apply(event?);
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+/// Appends length-prefixed events to a journal. Buffer `W` yourself.
+pub struct JournalWriter<W: Write> {
+    out: W,
+    frame: Vec<u8>,
+}
+
+impl<W: Write> JournalWriter<W> {
+    /// Writes the journal header and returns a writer for its events.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(&header(PayloadKind::Journal))?;
+        Ok(Self { out, frame: Vec::with_capacity(128) })
+    }
+
+    pub fn append(&mut self, event: &BookEvent) -> io::Result<()> {
+        self.frame.clear();
+        bincode_options().serialize_into(&mut self.frame, &v10::BookEvent::from(event)).map_err(io::Error::other)?;
+        self.out.write_all(&(self.frame.len() as u32).to_le_bytes())?;
+        self.out.write_all(&self.frame)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Reads events back from a journal, in order.
+pub struct JournalReader<R: Read> {
+    input: R,
+    frame: Vec<u8>,
+    version: u8,
+}
+
+impl<R: Read> JournalReader<R> {
+    /// Reads and checks the journal header.
+    pub fn new(mut input: R) -> Result<Self, CodecError> {
+        let mut head = [0u8; HEADER_LEN];
+        input.read_exact(&mut head)?;
+        let version = check_header(&head, PayloadKind::Journal)?;
+        Ok(Self { input, frame: Vec::new(), version })
+    }
+
+    /// Format version the journal was written with.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// The next event, `None` at a clean end of input.
+    pub fn next_event(&mut self) -> Result<Option<BookEvent>, CodecError> {
+        let mut len = [0u8; 4];
+        match self.input.read(&mut len[..1])? {
+            0 => return Ok(None),
+            _ => self.input.read_exact(&mut len[1..])?,
+        }
+        self.frame.resize(u32::from_le_bytes(len) as usize, 0);
+        self.input.read_exact(&mut self.frame)?;
+        Ok(Some(match self.version {
+            1 => bincode_options().deserialize::<v1::BookEvent>(&self.frame)?.into(),
+            2 => bincode_options().deserialize::<v2::BookEvent>(&self.frame)?.into(),
+            3 => bincode_options().deserialize::<v3::BookEvent>(&self.frame)?.into(),
+            4 => bincode_options().deserialize::<v4::BookEvent>(&self.frame)?.into(),
+            5 => bincode_options().deserialize::<v5::BookEvent>(&self.frame)?.into(),
+            6 | 7 => bincode_options().deserialize::<v7::BookEvent>(&self.frame)?.into(),
+            8 => bincode_options().deserialize::<v8::BookEvent>(&self.frame)?.into(),
+            _ => bincode_options().deserialize::<v10::BookEvent>(&self.frame)?.into(),
+        }))
+    }
+}
+
+impl<R: Read> Iterator for JournalReader<R> {
+    type Item = Result<BookEvent, CodecError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ParticipantId, TimeInForce};
+
+    fn order(id: u128, side: Side, px_ticks: i64, qty: i64) -> Order {
+        Order {
+            id: OrderId(id),
+            symbol: "AAPL".into(),
+            side,
+            px_ticks,
+            qty,
+            ts_ns: id,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trip_keeps_priority_and_drops_tombstones() {
+        let mut book = OrderBook::new();
+        for (id, px) in [(1, 99), (2, 100), (3, 100), (4, 98)] {
+            book.submit_limit(order(id, Side::Bid, px, 10)).unwrap();
+        }
+        book.submit_limit(Order { owner: ParticipantId(7), ..order(5, Side::Ask, 105, 7) }).unwrap();
+        book.bids.cancel(OrderId(2));
+
+        let restored = decode_snapshot(&encode_snapshot(&book)).unwrap();
+        let ids = |b: &OrderBook| b.bids.live_orders().map(|o| o.id.0).collect::<Vec<_>>();
+        assert_eq!(ids(&restored), vec![3, 1, 4]);
+        assert_eq!(restored.bids.tombstone_count(), 0);
+        assert_eq!(restored.asks.live_orders().next(), Some(&Order { owner: ParticipantId(7), ..order(5, Side::Ask, 105, 7) }));
+    }
+
+    #[test]
+    fn journal_replays_to_the_same_book() {
+        let events = vec![
+            BookEvent::Submit(order(1, Side::Ask, 100, 10)),
+            BookEvent::Submit(order(2, Side::Ask, 101, 10)),
+            BookEvent::Cancel { id: OrderId(1), side: Side::Ask },
+            BookEvent::Submit(order(3, Side::Bid, 101, 4)),
+            BookEvent::Submit(Order { tif: TimeInForce::IOC, ..order(4, Side::Bid, 99, 4) }),
+            BookEvent::Submit(Order { expires_ns: Some(u128::MAX), ..order(5, Side::Bid, 98, 4) }),
+            BookEvent::Submit(Order { display_qty: Some(2), ..order(6, Side::Ask, 103, 5) }),
+            BookEvent::Submit(Order { min_qty: Some(3), ..order(7, Side::Bid, 97, 6) }),
+            BookEvent::Submit(Order { hidden: true, ..order(8, Side::Ask, 104, 5) }),
+            BookEvent::Modify { id: OrderId(5), side: Side::Bid, px_ticks: 98, qty: 2, ts_ns: 10 },
+            BookEvent::Submit(Order { expires_ns: Some(50), ..order(9, Side::Bid, 96, 4) }),
+            BookEvent::Expire { now_ns: 60 },
+        ];
+        let mut writer = JournalWriter::new(Vec::new()).unwrap();
+        for event in &events {
+            writer.append(event).unwrap();
+        }
+        let bytes = writer.into_inner();
+
+        let read: Vec<BookEvent> = JournalReader::new(&bytes[..]).unwrap().map(Result::unwrap).collect();
+        assert_eq!(read, events);
+
+        let mut book = OrderBook::new();
+        let trades: Vec<Trade> = read.into_iter().flat_map(|e| e.apply(&mut book)).collect();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].maker, OrderId(2));
+        assert!(!book.bids.contains(OrderId(4)));
+        assert_eq!(book.bids.get(OrderId(5)).map(|o| o.qty), Some(2));
+        assert!(!book.bids.contains(OrderId(9)));
+
+        let mut replayed = OrderBook::new();
+        assert_eq!(replay_journal(&mut replayed, &bytes[..]).unwrap(), events.len() as u64);
+        assert_eq!(encode_snapshot(&replayed), encode_snapshot(&book));
+        assert_eq!(replayed.seq(), book.seq());
+
+        let restored = decode_snapshot(&encode_snapshot(&book)).unwrap();
+        assert_eq!(restored.seq(), book.seq());
+        assert_eq!(restored.bids.live_orders().next().unwrap().expires_ns, Some(u128::MAX));
+        assert_eq!(restored.asks.qty_at_price(103), 2);
+        assert_eq!(restored.asks.hidden_qty(OrderId(6)), 3);
+        assert!(restored.asks.get(OrderId(8)).is_some_and(|o| o.hidden));
+        assert_eq!(restored.asks.qty_at_price(104), 0);
+    }
+
+    #[test]
+    fn journal_replays_stops_pegs_markets_and_quotes() {
+        let events = vec![
+            BookEvent::Submit(order(1, Side::Ask, 100, 10)),
+            BookEvent::Submit(order(2, Side::Bid, 98, 10)),
+            BookEvent::Peg { order: order(3, Side::Bid, 105, 5), kind: PegKind::Midpoint },
+            BookEvent::Stop { order: order(4, Side::Bid, 100, 5), stop_px: 100, kind: OrderKind::Market },
+            BookEvent::Stop { order: order(5, Side::Ask, 90, 5), stop_px: 90, kind: OrderKind::Limit },
+            BookEvent::CancelStop { id: OrderId(5) },
+            BookEvent::Quote { owner: "mm".into(), bid: order(6, Side::Bid, 97, 5), ask: order(7, Side::Ask, 102, 5) },
+            // Sets off stop 4, which takes all but one of what is left
+            BookEvent::Market(order(8, Side::Bid, 0, 4)),
+            BookEvent::Cancel { id: OrderId(1), side: Side::Ask },
+            BookEvent::Reprice { ts_ns: 20 },
+        ];
+        let mut writer = JournalWriter::new(Vec::new()).unwrap();
+        for event in &events {
+            writer.append(event).unwrap();
+        }
+        let bytes = writer.into_inner();
+        let read: Vec<BookEvent> = JournalReader::new(&bytes[..]).unwrap().map(Result::unwrap).collect();
+        assert_eq!(read, events);
+
+        let mut book = OrderBook::new();
+        let trades: Vec<Trade> = read.into_iter().flat_map(|e| e.apply(&mut book)).collect();
+        let fills: Vec<_> = trades.iter().map(|t| (t.taker.0, t.px_ticks, t.qty)).collect();
+        assert_eq!(fills, vec![(8, 100, 4), (4, 100, 5)]);
+        assert_eq!(book.stops.len(), 0);
+        assert_eq!(book.bids.get(OrderId(3)).map(|o| o.px_ticks), Some(100));
+        assert_eq!(book.quotes.get("mm").and_then(|quote| quote.ask), Some(OrderId(7)));
+
+        let mut replayed = OrderBook::new();
+        assert_eq!(replay_journal(&mut replayed, &bytes[..]).unwrap(), events.len() as u64);
+        assert_eq!(encode_snapshot(&replayed), encode_snapshot(&book));
+        assert_eq!(replayed.seq(), book.seq());
+    }
+
+    #[test]
+    fn snapshot_keeps_stops_pegs_quotes_and_config() {
+        let config = SymbolConfig { max_qty: 1_000, ..SymbolConfig::default() };
+        let mut book = OrderBook::new().with_symbol(SymbolId::intern("AAPL")).with_config(config).unwrap();
+        book.submit_limit(order(1, Side::Ask, 100, 10)).unwrap();
+        book.submit_limit(order(2, Side::Bid, 98, 10)).unwrap();
+        book.submit_peg(order(3, Side::Bid, 105, 5), PegKind::Midpoint).unwrap();
+        book.submit_stop(order(4, Side::Bid, 101, 5), 101, OrderKind::Limit).unwrap();
+        book.submit_stop(order(5, Side::Ask, 0, 5), 95, OrderKind::Market).unwrap();
+        book.submit_stop(order(6, Side::Ask, 0, 5), 95, OrderKind::Market).unwrap();
+        book.submit_quote("mm", order(7, Side::Bid, 97, 5), order(8, Side::Ask, 103, 5)).unwrap();
+        book.submit_market(order(9, Side::Bid, 0, 2)).unwrap();
+
+        let bytes = encode_snapshot(&book);
+        let mut restored = decode_snapshot(&bytes).unwrap();
+        assert_eq!(encode_snapshot(&restored), bytes);
+        assert_eq!(restored.symbol(), Some(SymbolId::intern("AAPL")));
+        assert_eq!(restored.config(), &config);
+        assert_eq!(restored.pegs.get(OrderId(3)), book.pegs.get(OrderId(3)));
+        assert_eq!(restored.quotes.get("mm"), book.quotes.get("mm"));
+        let held = |book: &OrderBook| book.stops.held(Side::Ask).map(|stop| stop.order.id.0).collect::<Vec<_>>();
+        assert_eq!(held(&restored), held(&book));
+
+        // The restored book goes on as the saved one would: the last trade
+        // is known, and a print through 101 sets off the buy stop
+        let lift = order(10, Side::Bid, 103, 10);
+        assert_eq!(restored.submit_limit(lift.clone()).unwrap().trades, book.submit_limit(lift).unwrap().trades);
+        assert_eq!(encode_snapshot(&restored), encode_snapshot(&book));
+        assert!(!restored.stops.contains(OrderId(4)));
+    }
+
+    #[test]
+    fn snapshots_no_book_could_be_in_are_refused() {
+        let (bid, ask) = (order(1, Side::Bid, 99, 10), order(2, Side::Ask, 101, 10));
+        let valid = || v10::Snapshot {
+            seq: 0,
+            bids: vec![(&bid).into()],
+            asks: vec![(&ask).into()],
+            stops: Vec::new(),
+            pegs: Vec::new(),
+            quotes: Vec::new(),
+            last_px: None,
+            symbol: None,
+            config: (&SymbolConfig::default()).into(),
+        };
+        fn decode(snapshot: v10::Snapshot) -> Result<OrderBook, CodecError> {
+            let mut bytes = header(PayloadKind::Snapshot).to_vec();
+            bincode_options().serialize_into(&mut bytes, &snapshot).unwrap();
+            decode_snapshot(&bytes)
+        }
+        fn refused(snapshot: v10::Snapshot) -> bool {
+            matches!(decode(snapshot), Err(CodecError::Invalid(_)))
+        }
+        assert!(decode(valid()).is_ok());
+
+        // An id twice, on the book or held as a stop
+        assert!(refused(v10::Snapshot { asks: vec![(&ask).into(), (&Order { px_ticks: 102, ..ask.clone() }).into()], ..valid() }));
+        let stop = v10::Stop { order: (&bid).into(), stop_px: 90, kind: v10::OrderKind::Market };
+        assert!(refused(v10::Snapshot { stops: vec![stop], ..valid() }));
+        // Orders no book would hold
+        assert!(refused(v10::Snapshot { bids: vec![(&ask).into()], asks: Vec::new(), ..valid() }));
+        assert!(refused(v10::Snapshot { bids: vec![(&Order { qty: 0, ..bid.clone() }).into()], ..valid() }));
+        assert!(refused(v10::Snapshot { bids: vec![(&Order { px_ticks: 0, ..bid.clone() }).into()], ..valid() }));
+        assert!(refused(v10::Snapshot { symbol: Some("MSFT".into()), ..valid() }));
+        let unpriced = order(3, Side::Ask, 0, 5);
+        let stop = v10::Stop { order: (&unpriced).into(), stop_px: 0, kind: v10::OrderKind::Market };
+        assert!(refused(v10::Snapshot { stops: vec![stop], ..valid() }));
+        // A peg of an order that isn't resting on its side
+        for (id, side) in [(3, v10::Side::Bid), (2, v10::Side::Bid)] {
+            let peg = v10::Peg { id, kind: v10::PegKind::Midpoint, side, limit_px: 99 };
+            assert!(refused(v10::Snapshot { pegs: vec![peg], ..valid() }));
+        }
+        // A config that can't be kept to, and a crossed book
+        let config = v10::SymbolConfig { tick_size: 0, lot_size: 1, min_qty: 1, max_qty: 10 };
+        assert!(refused(v10::Snapshot { config, ..valid() }));
+        assert!(refused(v10::Snapshot { asks: vec![(&Order { px_ticks: 98, ..ask.clone() }).into()], ..valid() }));
+    }
+
+    #[test]
+    fn rejects_foreign_newer_and_truncated_input() {
+        let trade = Trade {
+            maker: OrderId(1),
+            taker: OrderId(2),
+            symbol: "AAPL".into(),
+            px_ticks: 100,
+            qty: 5,
+            ts_ns: 9,
+            seq: 3,
+        };
+        let mut bytes = encode_trade(&trade);
+        assert_eq!(decode_trade(&bytes).unwrap(), trade);
+        // Before version 7 trades weren't numbered
+        let mut old = header(PayloadKind::Trade).to_vec();
+        old[3] = 6;
+        let wire = v1::Trade { maker: 1, taker: 2, symbol: "AAPL".into(), px_ticks: 100, qty: 5, ts_ns: 9 };
+        bincode_options().serialize_into(&mut old, &wire).unwrap();
+        assert_eq!(decode_trade(&old).unwrap(), Trade { seq: 0, ..trade });
+        assert!(matches!(decode_snapshot(&bytes), Err(CodecError::WrongKind { .. })));
+        assert!(matches!(decode_trade(&bytes[..bytes.len() - 1]), Err(CodecError::Truncated)));
+
+        bytes[3] = FORMAT_VERSION + 1;
+        assert!(matches!(decode_trade(&bytes), Err(CodecError::UnsupportedVersion(_))));
+        bytes[0] = b'{';
+        assert!(matches!(decode_trade(&bytes), Err(CodecError::BadMagic)));
+
+        let mut journal = JournalWriter::new(Vec::new()).unwrap();
+        journal.append(&BookEvent::Cancel { id: OrderId(1), side: Side::Bid }).unwrap();
+        let journal = journal.into_inner();
+        let mut reader = JournalReader::new(&journal[..journal.len() - 2]).unwrap();
+        assert!(matches!(reader.next_event(), Err(CodecError::Truncated)));
+    }
+
+    #[test]
+    fn version_1_files_still_read_and_migrate() {
+        // Written by the version 1 encoder; must decode for as long as
+        // version 1 is readable
+        let snapshot = include_bytes!("../tests/data/v1_snapshot.hxb");
+        assert_eq!(read_header(snapshot).unwrap(), (1, PayloadKind::Snapshot));
+        let book = decode_snapshot(snapshot).unwrap();
+        let bids: Vec<_> = book.bids.live_orders().map(|o| (o.id.0, o.px_ticks)).collect();
+        assert_eq!(bids, vec![(u128::MAX - 1, 100), (1, 99)]);
+        assert_eq!(book.asks.live_orders().next(), Some(&Order { ts_ns: 3_000, ..order(3, Side::Ask, 105, 7) }));
+        assert_eq!(read_header(&migrate_snapshot(snapshot).unwrap()).unwrap(), (FORMAT_VERSION, PayloadKind::Snapshot));
+
+        let journal = include_bytes!("../tests/data/v1_journal.hxb");
+        assert_eq!(JournalReader::new(&journal[..]).unwrap().version(), 1);
+        let mut migrated = Vec::new();
+        assert_eq!(migrate_journal(&journal[..], &mut migrated).unwrap(), 3);
+        let events: Vec<BookEvent> = JournalReader::new(&migrated[..]).unwrap().map(Result::unwrap).collect();
+        assert_eq!(events[1], BookEvent::Cancel { id: OrderId(1), side: Side::Ask });
+        assert_eq!(events[2], BookEvent::Submit(Order { ts_ns: 2_000, ..order(2, Side::Bid, 101, 4) }));
+    }
+}
@@ -0,0 +1,282 @@
+//! CSV loading of resting orders and timestamped events.
+//!
+//! For standing up realistic books quickly in tests, demos, and the CLI.
+//! Both formats start with a header row; columns are matched by name, in
+//! any order, and unknown columns are ignored:
+//!
+//! | file   | required columns             | optional                   |
+//! |--------|------------------------------|----------------------------|
+//! | orders | `symbol,side,px_ticks,qty`   | `id`, `ts_ns`              |
+//! | events | `action,symbol,id,side`      | `px_ticks`, `qty`, `ts_ns` |
+//!
+//! `side` is `bid`/`buy` or `ask`/`sell`; `action` is `submit` or `cancel`
+//! (a cancel needs no price or quantity). Without an `id` column, an order
+//! takes its 1-based row number; without `ts_ns`, its row number as well,
+//! so file order is time priority. Blank lines and lines starting with `#`
+//! are skipped. Fields are plain: no quoting, so none may contain a comma.
+
+use crate::codec::BookEvent;
+use crate::symbols::SymbolId;
+use crate::types::{Order, OrderId, ParticipantId, RejectReason, Side, TimeInForce, Trade};
+use crate::OrderBook;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum CsvError {
+    Io(io::Error),
+    /// The header lacks a required column
+    MissingColumn(&'static str),
+    /// A field that doesn't parse; `line` is 1-based and counts the header
+    Invalid { line: usize, column: &'static str, value: String },
+    /// An order the book refused
+    Rejected(RejectReason),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(e) => write!(f, "i/o error: {}", e),
+            CsvError::MissingColumn(column) => write!(f, "header has no `{}` column", column),
+            CsvError::Invalid { line, column, value } => {
+                write!(f, "line {}: invalid {} {:?}", line, column, value)
+            }
+            CsvError::Rejected(reason) => write!(f, "order refused: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl From<io::Error> for CsvError {
+    fn from(e: io::Error) -> Self {
+        CsvError::Io(e)
+    }
+}
+
+/// A book event with the symbol it applies to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvEvent {
+    pub symbol: String,
+    pub event: BookEvent,
+}
+
+/// Data rows of a CSV with a header, as (line number, fields).
+struct Rows<R> {
+    lines: io::Lines<R>,
+    line: usize,
+    header: Vec<String>,
+}
+
+impl<R: BufRead> Rows<R> {
+    fn new(input: R) -> Result<Self, CsvError> {
+        let mut rows = Rows { lines: input.lines(), line: 0, header: Vec::new() };
+        if let Some((_, header)) = rows.next_row()? {
+            rows.header = header.into_iter().map(|c| c.to_ascii_lowercase()).collect();
+        }
+        Ok(rows)
+    }
+
+    fn next_row(&mut self) -> Result<Option<(usize, Vec<String>)>, CsvError> {
+        for line in self.lines.by_ref() {
+            let line = line?;
+            self.line += 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            return Ok(Some((self.line, trimmed.split(',').map(|f| f.trim().to_string()).collect())));
+        }
+        Ok(None)
+    }
+
+    fn column(&self, name: &'static str) -> Option<usize> {
+        self.header.iter().position(|c| c == name)
+    }
+
+    fn required(&self, name: &'static str) -> Result<usize, CsvError> {
+        self.column(name).ok_or(CsvError::MissingColumn(name))
+    }
+}
+
+/// Field `at` of a row; empty if the row is short or `at` is `None`.
+fn field(row: &[String], at: Option<usize>) -> &str {
+    at.and_then(|i| row.get(i)).map_or("", String::as_str)
+}
+
+fn parse<T: FromStr>(line: usize, column: &'static str, value: &str) -> Result<T, CsvError> {
+    value.parse().map_err(|_| CsvError::Invalid { line, column, value: value.to_string() })
+}
+
+fn parse_qty(line: usize, value: &str) -> Result<i64, CsvError> {
+    match parse(line, "qty", value)? {
+        qty if qty > 0 => Ok(qty),
+        _ => Err(CsvError::Invalid { line, column: "qty", value: value.to_string() }),
+    }
+}
+
+fn parse_symbol(line: usize, value: &str) -> Result<String, CsvError> {
+    match value {
+        "" => Err(CsvError::Invalid { line, column: "symbol", value: String::new() }),
+        symbol => Ok(symbol.to_string()),
+    }
+}
+
+fn parse_side(line: usize, value: &str) -> Result<Side, CsvError> {
+    match value.to_ascii_lowercase().as_str() {
+        "bid" | "buy" => Ok(Side::Bid),
+        "ask" | "sell" => Ok(Side::Ask),
+        _ => Err(CsvError::Invalid { line, column: "side", value: value.to_string() }),
+    }
+}
+
+/// Parses an orders file, rows in file order.
+pub fn read_orders<R: BufRead>(input: R) -> Result<Vec<Order>, CsvError> {
+    let mut rows = Rows::new(input)?;
+    let (symbol, side, px, qty) =
+        (rows.required("symbol")?, rows.required("side")?, rows.required("px_ticks")?, rows.required("qty")?);
+    let (id, ts) = (rows.column("id"), rows.column("ts_ns"));
+
+    let mut orders = Vec::new();
+    while let Some((line, row)) = rows.next_row()? {
+        let n = orders.len() as u128 + 1;
+        orders.push(Order {
+            id: match field(&row, id) {
+                "" => OrderId(n),
+                v => OrderId(parse(line, "id", v)?),
+            },
+            symbol: parse_symbol(line, field(&row, Some(symbol)))?.into(),
+            side: parse_side(line, field(&row, Some(side)))?,
+            px_ticks: parse(line, "px_ticks", field(&row, Some(px)))?,
+            qty: parse_qty(line, field(&row, Some(qty)))?,
+            ts_ns: match field(&row, ts) {
+                "" => n,
+                v => parse(line, "ts_ns", v)?,
+            },
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        });
+    }
+    Ok(orders)
+}
+
+/// Parses an events file, rows in file order.
+pub fn read_events<R: BufRead>(input: R) -> Result<Vec<CsvEvent>, CsvError> {
+    let mut rows = Rows::new(input)?;
+    let (action, symbol, id, side) =
+        (rows.required("action")?, rows.required("symbol")?, rows.required("id")?, rows.required("side")?);
+    let (px, qty, ts) = (rows.column("px_ticks"), rows.column("qty"), rows.column("ts_ns"));
+
+    let mut events = Vec::new();
+    while let Some((line, row)) = rows.next_row()? {
+        let order_id = OrderId(parse(line, "id", field(&row, Some(id)))?);
+        let symbol = parse_symbol(line, field(&row, Some(symbol)))?;
+        let side = parse_side(line, field(&row, Some(side)))?;
+        let event = match field(&row, Some(action)).to_ascii_lowercase().as_str() {
+            "submit" => BookEvent::Submit(Order {
+                id: order_id,
+                symbol: SymbolId::intern(&symbol),
+                side,
+                px_ticks: parse(line, "px_ticks", field(&row, px))?,
+                qty: parse_qty(line, field(&row, qty))?,
+                ts_ns: match field(&row, ts) {
+                    "" => events.len() as u128 + 1,
+                    v => parse(line, "ts_ns", v)?,
+                },
+                tif: TimeInForce::Day,
+                expires_ns: None,
+                display_qty: None,
+                min_qty: None,
+                hidden: false,
+                owner: ParticipantId::NONE,
+            }),
+            "cancel" => BookEvent::Cancel { id: order_id, side },
+            other => return Err(CsvError::Invalid { line, column: "action", value: other.to_string() }),
+        };
+        events.push(CsvEvent { symbol, event });
+    }
+    Ok(events)
+}
+
+/// Builds one book per symbol by submitting every order in file order.
+/// A row that crosses earlier rows matches against them like any order.
+/// Fails at the first order a book refuses.
+pub fn load_books<R: BufRead>(input: R) -> Result<BTreeMap<String, OrderBook>, CsvError> {
+    let mut books: BTreeMap<String, OrderBook> = BTreeMap::new();
+    for order in read_orders(input)? {
+        books.entry(order.symbol.to_string()).or_default().submit_limit(order).map_err(CsvError::Rejected)?;
+    }
+    Ok(books)
+}
+
+/// Applies events to `books`, creating books for new symbols, and returns
+/// the trades printed.
+pub fn replay_events(books: &mut BTreeMap<String, OrderBook>, events: Vec<CsvEvent>) -> Vec<Trade> {
+    let mut trades = Vec::new();
+    for CsvEvent { symbol, event } in events {
+        trades.extend(event.apply(books.entry(symbol).or_default()));
+    }
+    trades
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_books_per_symbol_in_file_order() {
+        let csv = "\
+# resting book
+symbol,side,px_ticks,qty,note
+AAPL,bid,14990,100,first
+AAPL,BUY,14990,50,
+AAPL,ask,15010,70,
+
+MSFT,sell,40000,10,
+";
+        let books = load_books(csv.as_bytes()).unwrap();
+        assert_eq!(books.keys().collect::<Vec<_>>(), ["AAPL", "MSFT"]);
+        let aapl = &books["AAPL"];
+        assert_eq!(aapl.bids.qty_at_price(14990), 150);
+        assert_eq!(aapl.bids.peek_best().map(|o| (o.id, o.ts_ns)), Some((OrderId(1), 1)));
+        assert_eq!(aapl.best_ask(), Some(15010));
+        assert_eq!(books["MSFT"].asks.peek_best().map(|o| o.id), Some(OrderId(4)));
+    }
+
+    #[test]
+    fn replays_submits_and_cancels() {
+        let mut books = load_books("id,symbol,side,px_ticks,qty\n7,AAPL,ask,15000,100\n8,AAPL,ask,15000,40\n".as_bytes())
+            .unwrap();
+        let events = read_events(
+            "ts_ns,action,id,symbol,side,px_ticks,qty\n\
+             10,cancel,7,AAPL,ask,,\n\
+             11,submit,9,AAPL,bid,15000,60\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(events[0].event, BookEvent::Cancel { id: OrderId(7), side: Side::Ask });
+
+        let trades = replay_events(&mut books, events);
+        assert_eq!(trades.len(), 1);
+        assert_eq!((trades[0].maker, trades[0].qty, trades[0].ts_ns), (OrderId(8), 40, 11));
+        assert_eq!(books["AAPL"].best_bid(), Some(15000));
+    }
+
+    #[test]
+    fn reports_missing_columns_and_bad_fields_by_line() {
+        let err = read_orders("symbol,side,qty\nAAPL,bid,5\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, CsvError::MissingColumn("px_ticks")));
+
+        let err = read_orders("symbol,side,px_ticks,qty\nAAPL,bid,100,5\nAAPL,hold,100,5\n".as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: invalid side \"hold\"");
+
+        let err = read_orders("symbol,side,px_ticks,qty\nAAPL,bid,100,0\n".as_bytes()).unwrap_err();
+        assert!(matches!(err, CsvError::Invalid { line: 2, column: "qty", .. }));
+    }
+}
@@ -0,0 +1,192 @@
+//! Parquet export of trade history and periodic book snapshots (`parquet`
+//! feature), readable directly from pandas, polars, or DuckDB.
+//!
+//! Trades file, one row per fill:
+//!
+//! | column     | type            | notes                                 |
+//! |------------|-----------------|---------------------------------------|
+//! | `seq`      | uint64          | 0-based order the trade printed in    |
+//! | `ts_ns`    | int64           | engine timestamp, nanoseconds         |
+//! | `symbol`   | string          |                                       |
+//! | `px_ticks` | int64           | execution (maker) price               |
+//! | `qty`      | int64           |                                       |
+//! | `maker_id` | decimal(38, 0)  | order ids are 128-bit                 |
+//! | `taker_id` | decimal(38, 0)  |                                       |
+//!
+//! Book file, one row per price level per snapshot, best level first:
+//!
+//! | column     | type    | notes                                  |
+//! |------------|---------|----------------------------------------|
+//! | `ts_ns`    | int64   | time the snapshot was taken            |
+//! | `symbol`   | string  |                                        |
+//! | `side`     | string  | `bid` or `ask`                         |
+//! | `level`    | uint16  | 0 = best                               |
+//! | `px_ticks` | int64   |                                        |
+//! | `qty`      | int64   | live quantity at the level             |
+//! | `orders`   | uint32  | live orders at the level               |
+//!
+//! Rows are the `arrow` module's batches. Files are Snappy-compressed and
+//! carry `hftx.schema` / `hftx.schema_version` key-value metadata. Rows are
+//! buffered and written in row groups, so call `finish` to flush the tail
+//! and write the footer.
+
+use crate::arrow::{depth_schema, trade_schema, DepthBatchBuilder, TradeBatchBuilder};
+use crate::types::Trade;
+use crate::OrderBook;
+use arrow_schema::SchemaRef;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::Result;
+use parquet::file::properties::WriterProperties;
+use parquet::format::KeyValue;
+use std::fs::File;
+use std::path::Path;
+
+/// Bumped whenever a column is added, removed, or retyped.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Rows buffered before a record batch is handed to the writer.
+const BATCH_ROWS: usize = 64 * 1024;
+
+fn open(path: &Path, schema: SchemaRef, name: &str) -> Result<ArrowWriter<File>> {
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(vec![
+            KeyValue::new("hftx.schema".to_string(), name.to_string()),
+            KeyValue::new("hftx.schema_version".to_string(), SCHEMA_VERSION.to_string()),
+        ]))
+        .build();
+    ArrowWriter::try_new(File::create(path)?, schema, Some(props))
+}
+
+/// Writes trades to a Parquet file.
+pub struct TradeWriter {
+    writer: ArrowWriter<File>,
+    rows: TradeBatchBuilder,
+}
+
+impl TradeWriter {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self { writer: open(path.as_ref(), trade_schema(), "trades")?, rows: TradeBatchBuilder::new() })
+    }
+
+    pub fn write(&mut self, trades: &[Trade]) -> Result<()> {
+        for trade in trades {
+            self.rows.append(trade);
+            if self.rows.len() >= BATCH_ROWS {
+                self.writer.write(&self.rows.finish())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes buffered rows, writes the footer, and returns the row count.
+    pub fn finish(mut self) -> Result<u64> {
+        if !self.rows.is_empty() {
+            self.writer.write(&self.rows.finish())?;
+        }
+        self.writer.close()?;
+        Ok(self.rows.total_rows())
+    }
+}
+
+/// Writes periodic top-of-book snapshots to a Parquet file.
+pub struct BookWriter {
+    writer: ArrowWriter<File>,
+    rows: DepthBatchBuilder,
+    total: u64,
+}
+
+impl BookWriter {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self { writer: open(path.as_ref(), depth_schema(), "book")?, rows: DepthBatchBuilder::new(), total: 0 })
+    }
+
+    /// Records the best `levels` live price levels of each side at `ts_ns`.
+    pub fn record(&mut self, ts_ns: u128, symbol: &str, book: &OrderBook, levels: usize) -> Result<()> {
+        self.total += self.rows.append_book(ts_ns, symbol, book, levels) as u64;
+        if self.rows.len() >= BATCH_ROWS {
+            self.writer.write(&self.rows.finish())?;
+        }
+        Ok(())
+    }
+
+    /// Flushes buffered rows, writes the footer, and returns the row count.
+    pub fn finish(mut self) -> Result<u64> {
+        if !self.rows.is_empty() {
+            self.writer.write(&self.rows.finish())?;
+        }
+        self.writer.close()?;
+        Ok(self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Order, OrderId, ParticipantId, Side, TimeInForce};
+    use arrow_array::RecordBatch;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Decimal128Type, Int64Type, UInt32Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn order(id: u128, side: Side, px_ticks: i64, qty: i64) -> Order {
+        Order {
+            id: OrderId(id),
+            symbol: "AAPL".into(),
+            side,
+            px_ticks,
+            qty,
+            ts_ns: 7,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }
+    }
+
+    fn read(path: &Path) -> RecordBatch {
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap()).unwrap().build().unwrap();
+        let mut batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(batches.len(), 1, "test files fit in one batch");
+        batches.remove(0)
+    }
+
+    #[test]
+    fn trades_and_book_round_trip_through_parquet() {
+        let dir = std::env::temp_dir().join(format!("hftx-export-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut book = OrderBook::new();
+        book.submit_limit(order(1, Side::Ask, 101, 5)).unwrap();
+        book.submit_limit(order(2, Side::Ask, 102, 5)).unwrap();
+        book.submit_limit(order(3, Side::Bid, 99, 4)).unwrap();
+        book.submit_limit(order(4, Side::Bid, 99, 6)).unwrap();
+        let trades = book.submit_limit(order(u128::MAX >> 1, Side::Bid, 102, 7)).unwrap().trades;
+
+        let mut trade_writer = TradeWriter::create(dir.join("trades.parquet")).unwrap();
+        trade_writer.write(&trades).unwrap();
+        assert_eq!(trade_writer.finish().unwrap(), 2);
+
+        let mut book_writer = BookWriter::create(dir.join("book.parquet")).unwrap();
+        book_writer.record(1_000, "AAPL", &book, 10).unwrap();
+        assert_eq!(book_writer.finish().unwrap(), 2);
+
+        let trades = read(&dir.join("trades.parquet"));
+        assert_eq!(trades.schema(), trade_schema());
+        let px = trades.column(3).as_primitive::<Int64Type>();
+        assert_eq!((px.value(0), px.value(1)), (101, 102));
+        let taker = trades.column(6).as_primitive::<Decimal128Type>();
+        assert_eq!(taker.value(0), (u128::MAX >> 1) as i128);
+
+        let levels = read(&dir.join("book.parquet"));
+        let sides = levels.column(2).as_string::<i32>();
+        assert_eq!((sides.value(0), sides.value(1)), ("bid", "ask"));
+        assert_eq!(levels.column(6).as_primitive::<UInt32Type>().value(0), 2);
+        assert_eq!(levels.column(5).as_primitive::<Int64Type>().value(1), 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,293 @@
+//! Synthetic order-flow generation for benchmarks and simulations.
+//!
+//! `FlowGenerator` produces a stream of submit / cancel / replace events with:
+//! - Poisson arrivals (exponential inter-arrival times, virtual nanosecond clock)
+//! - A Gaussian random-walk fair price that passive orders cluster around
+//! - Configurable order-size distributions
+//! - Cancel and cancel/replace behavior against the generator's own live orders
+//!
+//! The generator does not observe the book, so it may cancel orders that have
+//! already been filled; `cancel` simply returns false for those.
+
+use crate::rng::Rng;
+use crate::symbols::SymbolId;
+use crate::types::{Order, OrderId, ParticipantId, Side, TimeInForce};
+
+/// Distribution order quantities are drawn from.
+#[derive(Clone, Debug)]
+pub enum SizeDistribution {
+    /// Every order has the same quantity
+    Fixed(i64),
+    /// Uniform over `min..=max`
+    Uniform { min: i64, max: i64 },
+    /// Log-normal with the given median; heavy right tail clipped at `max`
+    LogNormal { median: f64, sigma: f64, max: i64 },
+}
+
+impl SizeDistribution {
+    fn sample(&self, rng: &mut Rng) -> i64 {
+        match *self {
+            SizeDistribution::Fixed(qty) => qty,
+            SizeDistribution::Uniform { min, max } => {
+                let span = (max - min).max(0) as u64 + 1;
+                min + rng.below(span) as i64
+            }
+            SizeDistribution::LogNormal { median, sigma, max } => {
+                let qty = (median.ln() + sigma * rng.next_gaussian()).exp();
+                (qty.round() as i64).clamp(1, max)
+            }
+        }
+    }
+}
+
+/// Parameters for a generated order stream.
+#[derive(Clone, Debug)]
+pub struct FlowConfig {
+    pub symbol: String,
+    /// Mean events per second of virtual time
+    pub arrival_rate: f64,
+    /// Starting fair price in ticks
+    pub initial_px: i64,
+    /// Standard deviation of the fair price walk per second, in ticks
+    pub volatility_ticks: f64,
+    pub size: SizeDistribution,
+    /// Fraction of new orders priced through the fair price (0.0-1.0)
+    pub marketable_ratio: f64,
+    /// Fraction of events that cancel a live order (0.0-1.0)
+    pub cancel_ratio: f64,
+    /// Fraction of events that cancel/replace a live order (0.0-1.0)
+    pub replace_ratio: f64,
+    /// Mean distance from fair, in ticks, of passive order prices
+    pub mean_depth_ticks: f64,
+    /// Live orders tracked before the generator forces cancels
+    pub max_live_orders: usize,
+}
+
+impl Default for FlowConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "AAPL".to_string(),
+            arrival_rate: 100_000.0,
+            initial_px: 10_000,
+            volatility_ticks: 20.0,
+            size: SizeDistribution::LogNormal {
+                median: 100.0,
+                sigma: 0.8,
+                max: 10_000,
+            },
+            marketable_ratio: 0.15,
+            cancel_ratio: 0.35,
+            replace_ratio: 0.10,
+            mean_depth_ticks: 8.0,
+            max_live_orders: 10_000,
+        }
+    }
+}
+
+/// One generated operation against the book.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FlowEvent {
+    /// New limit order
+    Submit(Order),
+    /// Cancel a previously submitted order
+    Cancel { id: OrderId, side: Side, ts_ns: u128 },
+    /// Cancel `id` and submit `order` in its place (new id, new priority)
+    Replace { id: OrderId, side: Side, order: Order },
+}
+
+impl FlowEvent {
+    /// Virtual arrival time of the event.
+    pub fn ts_ns(&self) -> u128 {
+        match self {
+            FlowEvent::Submit(order) => order.ts_ns,
+            FlowEvent::Cancel { ts_ns, .. } => *ts_ns,
+            FlowEvent::Replace { order, .. } => order.ts_ns,
+        }
+    }
+}
+
+/// Endless iterator of synthetic order-flow events.
+pub struct FlowGenerator {
+    config: FlowConfig,
+    /// `config.symbol`, interned once
+    symbol: SymbolId,
+    rng: Rng,
+    /// Fair price in fractional ticks
+    fair_px: f64,
+    now_ns: u128,
+    next_id: u128,
+    /// Orders this generator believes are resting
+    live: Vec<(OrderId, Side)>,
+}
+
+impl FlowGenerator {
+    /// Creates a generator; identical config and seed yield identical streams.
+    pub fn new(config: FlowConfig, seed: u64) -> Self {
+        Self {
+            fair_px: config.initial_px as f64,
+            symbol: SymbolId::intern(&config.symbol),
+            config,
+            rng: Rng::seed_from_u64(seed),
+            now_ns: 0,
+            next_id: 1,
+            live: Vec::new(),
+        }
+    }
+
+    /// Current fair price, rounded to ticks.
+    pub fn fair_px(&self) -> i64 {
+        self.fair_px.round() as i64
+    }
+
+    /// Current virtual time in nanoseconds.
+    pub fn now_ns(&self) -> u128 {
+        self.now_ns
+    }
+
+    /// Advances the clock by one exponential inter-arrival and walks the price.
+    fn advance(&mut self) {
+        let dt_secs = -self.rng.next_f64_open().ln() / self.config.arrival_rate;
+        self.now_ns += (dt_secs * 1e9) as u128;
+        self.fair_px += self.rng.next_gaussian() * self.config.volatility_ticks * dt_secs.sqrt();
+        self.fair_px = self.fair_px.max(1.0);
+    }
+
+    fn random_side(&mut self) -> Side {
+        if self.rng.next_bool() { Side::Bid } else { Side::Ask }
+    }
+
+    /// Builds a new order on `side` around the current fair price.
+    fn new_order(&mut self, side: Side) -> Order {
+        let depth = -self.rng.next_f64_open().ln() * self.config.mean_depth_ticks;
+        let marketable = self.rng.next_f64() < self.config.marketable_ratio;
+
+        // Passive orders sit behind fair; marketable ones reach through it
+        let offset = if marketable {
+            -(depth + self.config.mean_depth_ticks).round() as i64
+        } else {
+            1 + depth.round() as i64
+        };
+        let px_ticks = match side {
+            Side::Bid => self.fair_px.floor() as i64 - offset,
+            Side::Ask => self.fair_px.ceil() as i64 + offset,
+        }
+        .max(1);
+
+        let id = OrderId(self.next_id);
+        self.next_id += 1;
+        if !marketable {
+            self.live.push((id, side));
+        }
+
+        Order {
+            id,
+            symbol: self.symbol,
+            side,
+            px_ticks,
+            qty: self.config.size.sample(&mut self.rng),
+            ts_ns: self.now_ns,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }
+    }
+
+    /// Removes a random live order, returning its id and side.
+    fn take_live(&mut self) -> Option<(OrderId, Side)> {
+        if self.live.is_empty() {
+            return None;
+        }
+        let idx = self.rng.below(self.live.len() as u64) as usize;
+        Some(self.live.swap_remove(idx))
+    }
+}
+
+impl Iterator for FlowGenerator {
+    type Item = FlowEvent;
+
+    fn next(&mut self) -> Option<FlowEvent> {
+        self.advance();
+
+        let roll = self.rng.next_f64();
+        let force_cancel = self.live.len() >= self.config.max_live_orders;
+
+        if force_cancel || roll < self.config.cancel_ratio {
+            if let Some((id, side)) = self.take_live() {
+                return Some(FlowEvent::Cancel { id, side, ts_ns: self.now_ns });
+            }
+        } else if roll < self.config.cancel_ratio + self.config.replace_ratio {
+            if let Some((id, side)) = self.take_live() {
+                // Replacements stay on the side of the order they replace
+                let order = self.new_order(side);
+                return Some(FlowEvent::Replace { id, side, order });
+            }
+        }
+
+        let side = self.random_side();
+        Some(FlowEvent::Submit(self.new_order(side)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_stream() {
+        let a: Vec<FlowEvent> = FlowGenerator::new(FlowConfig::default(), 42).take(1_000).collect();
+        let b: Vec<FlowEvent> = FlowGenerator::new(FlowConfig::default(), 42).take(1_000).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn poisson_arrivals_match_rate() {
+        let config = FlowConfig {
+            arrival_rate: 1_000.0,
+            ..FlowConfig::default()
+        };
+        let mut flow = FlowGenerator::new(config, 7);
+        let n = 20_000;
+        let last = flow.by_ref().take(n).last().unwrap();
+        let mean_gap_ns = last.ts_ns() as f64 / n as f64;
+        // 1000/s -> 1ms mean gap; allow a few percent of sampling noise
+        assert!((mean_gap_ns - 1_000_000.0).abs() < 50_000.0, "mean gap {}", mean_gap_ns);
+    }
+
+    #[test]
+    fn sizes_respect_distribution_bounds() {
+        let config = FlowConfig {
+            size: SizeDistribution::Uniform { min: 5, max: 15 },
+            cancel_ratio: 0.0,
+            replace_ratio: 0.0,
+            ..FlowConfig::default()
+        };
+        for event in FlowGenerator::new(config, 3).take(5_000) {
+            let FlowEvent::Submit(order) = event else {
+                panic!("only submits expected");
+            };
+            assert!((5..=15).contains(&order.qty));
+            assert!(order.px_ticks > 0);
+        }
+    }
+
+    #[test]
+    fn cancels_only_reference_live_orders() {
+        let mut submitted = std::collections::HashSet::new();
+        for event in FlowGenerator::new(FlowConfig::default(), 11).take(10_000) {
+            match event {
+                FlowEvent::Submit(order) => {
+                    submitted.insert(order.id);
+                }
+                FlowEvent::Cancel { id, .. } => assert!(submitted.remove(&id)),
+                FlowEvent::Replace { id, side, order } => {
+                    assert!(submitted.remove(&id));
+                    assert_eq!(order.side, side);
+                    submitted.insert(order.id);
+                }
+            }
+        }
+    }
+}
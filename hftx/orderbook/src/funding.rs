@@ -0,0 +1,134 @@
+//! Mark price and funding for perpetual contracts.
+//!
+//! A perpetual never expires, so two numbers keep it anchored to its
+//! index:
+//!
+//! - the mark price, `index + basis`, where the basis is an exponential
+//!   moving average of `book mid - index` with a configurable half-life.
+//!   Without a two-sided book the basis decays toward zero at the same
+//!   rate, so the mark falls back to the index rather than freezing. Using
+//!   the mark instead of the last trade for PnL and funding keeps one thin
+//!   print from moving either.
+//! - the funding rate, the average premium `(mark - index) / index` over
+//!   the funding interval, clamped to a maximum. At each funding time a
+//!   position pays `qty * mark * rate`: longs pay shorts when the perpetual
+//!   trades above its index, and shorts pay longs below it.
+
+/// Index plus a decaying basis.
+#[derive(Clone, Debug)]
+pub struct MarkPrice {
+    half_life_ns: u64,
+    basis: Option<f64>,
+    last_ns: u64,
+}
+
+impl MarkPrice {
+    /// Panics if `half_life_ns` is zero.
+    pub fn new(half_life_ns: u64) -> Self {
+        assert!(half_life_ns > 0, "basis half-life must be positive");
+        Self { half_life_ns, basis: None, last_ns: 0 }
+    }
+
+    /// Folds in one observation and returns the mark. `mid` is `None` when
+    /// the book is one-sided or empty.
+    pub fn update(&mut self, ts_ns: u64, index: f64, mid: Option<f64>) -> f64 {
+        let elapsed = ts_ns.saturating_sub(self.last_ns) as f64;
+        // Weight kept by the old basis after `elapsed`
+        let keep = 0.5f64.powf(elapsed / self.half_life_ns as f64);
+        let target = mid.map_or(0.0, |mid| mid - index);
+        self.basis = Some(match self.basis {
+            // The first mid sets the basis outright
+            None => target,
+            Some(basis) => target + (basis - target) * keep,
+        });
+        self.last_ns = ts_ns;
+        index + self.basis()
+    }
+
+    pub fn basis(&self) -> f64 {
+        self.basis.unwrap_or(0.0)
+    }
+}
+
+/// Premium averaged over one funding interval.
+#[derive(Clone, Debug)]
+pub struct FundingRate {
+    max_rate: f64,
+    premium_sum: f64,
+    samples: u64,
+}
+
+impl FundingRate {
+    /// `max_rate` caps the rate either way, e.g. 0.0075 for 0.75%.
+    pub fn new(max_rate: f64) -> Self {
+        Self { max_rate: max_rate.abs(), premium_sum: 0.0, samples: 0 }
+    }
+
+    /// Adds one premium sample; ignored for a non-positive index.
+    pub fn sample(&mut self, index: f64, mark: f64) {
+        if index > 0.0 {
+            self.premium_sum += (mark - index) / index;
+            self.samples += 1;
+        }
+    }
+
+    /// The rate if the interval ended now.
+    pub fn predicted(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        (self.premium_sum / self.samples as f64).clamp(-self.max_rate, self.max_rate)
+    }
+
+    /// Ends the interval: returns its rate and starts the next one.
+    pub fn settle(&mut self) -> f64 {
+        let rate = self.predicted();
+        self.premium_sum = 0.0;
+        self.samples = 0;
+        rate
+    }
+}
+
+/// What a position of `qty` (positive long, negative short) receives at a
+/// funding time, in ticks times quantity; negative means it pays.
+pub fn funding_payment(qty: i64, mark: f64, rate: f64) -> f64 {
+    -(qty as f64) * mark * rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEC: u64 = 1_000_000_000;
+
+    #[test]
+    fn basis_tracks_the_premium_then_decays_without_a_book() {
+        let mut mark = MarkPrice::new(10 * SEC);
+        assert_eq!(mark.update(0, 100.0, Some(104.0)), 104.0);
+        // One half-life later the basis is halfway to the new premium
+        assert_eq!(mark.update(10 * SEC, 100.0, Some(102.0)), 103.0);
+        // With no mid it halves toward zero per half-life
+        assert_eq!(mark.update(20 * SEC, 100.0, None), 101.5);
+        assert_eq!(mark.basis(), 1.5);
+    }
+
+    #[test]
+    fn funding_averages_premium_and_clamps() {
+        let mut funding = FundingRate::new(0.01);
+        funding.sample(100.0, 100.5);
+        funding.sample(100.0, 100.1);
+        assert!((funding.predicted() - 0.003).abs() < 1e-12);
+        assert!((funding.settle() - 0.003).abs() < 1e-12);
+        assert_eq!(funding.predicted(), 0.0);
+
+        funding.sample(100.0, 90.0);
+        assert_eq!(funding.settle(), -0.01);
+    }
+
+    #[test]
+    fn longs_pay_shorts_above_the_index() {
+        assert_eq!(funding_payment(10, 100.0, 0.001), -1.0);
+        assert_eq!(funding_payment(-10, 100.0, 0.001), 1.0);
+        assert_eq!(funding_payment(10, 100.0, -0.001), 1.0);
+    }
+}
@@ -0,0 +1,278 @@
+//! Fixed-width binary records for logging the matching path.
+//!
+//! Formatting a log line per order costs more than matching it, so the hot
+//! path writes these instead: every record is 64 bytes, little endian, and
+//! encoding one is a handful of stores. A log is an 8-byte header (magic
+//! `HXHOTLG`, then the format version) followed by records:
+//!
+//! | bytes  | field                                      |
+//! |--------|--------------------------------------------|
+//! | 0..8   | `ts_ns`                                    |
+//! | 8      | kind                                       |
+//! | 9      | side (0 bid, 1 ask)                        |
+//! | 10..12 | symbol id                                  |
+//! | 12..16 | `fills` (out), `found` (cancel)            |
+//! | 16..32 | order id, or `maker` (fill)                |
+//! | 32..48 | `taker` (fill)                             |
+//! | 48..56 | `px_ticks`                                 |
+//! | 56..64 | `qty`, `filled` (out), `records` (dropped) |
+//!
+//! A symbol record holds the name in bytes 16..64 instead.
+//!
+//! Symbols are numbered by the writer, which logs a [`Entry::Symbol`]
+//! before the first record that uses an id; [`HotLogReader::symbol`] maps
+//! them back. Names longer than 48 bytes are truncated.
+
+use crate::types::{OrderId, Side};
+use std::fmt;
+use std::io::{self, Read};
+
+pub const MAGIC: [u8; 7] = *b"HXHOTLG";
+pub const FORMAT_VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 8;
+pub const RECORD_LEN: usize = 64;
+pub const NAME_LEN: usize = 48;
+
+/// What happened, as one record.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// Names the record's symbol id
+    Symbol { name: [u8; NAME_LEN] },
+    /// An order reached the matching path
+    OrderIn { id: OrderId, side: Side, px_ticks: i64, qty: i64 },
+    /// Refused before matching (risk, margin, blocked account)
+    Reject { id: OrderId },
+    /// The matcher filled `qty` of `maker` against `taker`
+    Fill { maker: OrderId, taker: OrderId, px_ticks: i64, qty: i64 },
+    /// What was left of an order after matching went on the book
+    Rest { id: OrderId, side: Side, px_ticks: i64, qty: i64 },
+    /// An order's trades left the matching path
+    Out { taker: OrderId, fills: u32, filled: i64 },
+    /// `side` is the one the order rested on, if `found`
+    Cancel { id: OrderId, side: Side, found: bool },
+    /// The writer fell behind and lost this many records here
+    Dropped { records: u64 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub ts_ns: u64,
+    /// Id from the log's [`Entry::Symbol`] records; 0 for `Dropped`
+    pub symbol: u16,
+    pub entry: Entry,
+}
+
+#[derive(Debug)]
+pub enum HotLogError {
+    Io(io::Error),
+    /// Not a hot-path log
+    BadMagic,
+    /// Written by a newer format than this build reads
+    UnsupportedVersion(u8),
+    UnknownKind(u8),
+    /// Input ended mid-header or mid-record
+    Truncated,
+}
+
+impl fmt::Display for HotLogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HotLogError::Io(e) => write!(f, "i/o error: {}", e),
+            HotLogError::BadMagic => write!(f, "not a hot-path log"),
+            HotLogError::UnsupportedVersion(v) => {
+                write!(f, "format version {} is newer than supported version {}", v, FORMAT_VERSION)
+            }
+            HotLogError::UnknownKind(kind) => write!(f, "unknown record kind {}", kind),
+            HotLogError::Truncated => write!(f, "log truncated"),
+        }
+    }
+}
+
+impl std::error::Error for HotLogError {}
+
+impl From<io::Error> for HotLogError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => HotLogError::Truncated,
+            _ => HotLogError::Io(e),
+        }
+    }
+}
+
+/// The bytes a log starts with.
+pub fn header() -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..7].copy_from_slice(&MAGIC);
+    header[7] = FORMAT_VERSION;
+    header
+}
+
+impl Entry {
+    /// A `Symbol` entry for `name`.
+    pub fn symbol(name: &str) -> Self {
+        let mut bytes = [0; NAME_LEN];
+        let len = name.len().min(NAME_LEN);
+        bytes[..len].copy_from_slice(&name.as_bytes()[..len]);
+        Entry::Symbol { name: bytes }
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Entry::Symbol { .. } => 1,
+            Entry::OrderIn { .. } => 2,
+            Entry::Reject { .. } => 3,
+            Entry::Fill { .. } => 4,
+            Entry::Rest { .. } => 5,
+            Entry::Out { .. } => 6,
+            Entry::Cancel { .. } => 7,
+            Entry::Dropped { .. } => 8,
+        }
+    }
+}
+
+fn side_byte(side: Side) -> u8 {
+    match side {
+        Side::Bid => 0,
+        Side::Ask => 1,
+    }
+}
+
+impl Record {
+    pub fn encode(&self) -> [u8; RECORD_LEN] {
+        let mut out = [0; RECORD_LEN];
+        out[0..8].copy_from_slice(&self.ts_ns.to_le_bytes());
+        out[8] = self.entry.kind();
+        out[10..12].copy_from_slice(&self.symbol.to_le_bytes());
+        let mut put = |side: Option<Side>, aux: u32, a: u128, b: u128, px: i64, qty: i64| {
+            out[9] = side.map_or(0, side_byte);
+            out[12..16].copy_from_slice(&aux.to_le_bytes());
+            out[16..32].copy_from_slice(&a.to_le_bytes());
+            out[32..48].copy_from_slice(&b.to_le_bytes());
+            out[48..56].copy_from_slice(&px.to_le_bytes());
+            out[56..64].copy_from_slice(&qty.to_le_bytes());
+        };
+        match self.entry {
+            Entry::Symbol { name } => out[16..64].copy_from_slice(&name),
+            Entry::OrderIn { id, side, px_ticks, qty } | Entry::Rest { id, side, px_ticks, qty } => {
+                put(Some(side), 0, id.0, 0, px_ticks, qty)
+            }
+            Entry::Reject { id } => put(None, 0, id.0, 0, 0, 0),
+            Entry::Fill { maker, taker, px_ticks, qty } => put(None, 0, maker.0, taker.0, px_ticks, qty),
+            Entry::Out { taker, fills, filled } => put(None, fills, taker.0, 0, 0, filled),
+            Entry::Cancel { id, side, found } => put(Some(side), found as u32, id.0, 0, 0, 0),
+            Entry::Dropped { records } => put(None, 0, 0, 0, 0, records as i64),
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8; RECORD_LEN]) -> Result<Self, HotLogError> {
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        let u128_at = |i: usize| u128::from_le_bytes(bytes[i..i + 16].try_into().unwrap());
+        let side = if bytes[9] == 0 { Side::Bid } else { Side::Ask };
+        let aux = u32::from_le_bytes(bytes[12..16].try_into().unwrap());
+        let (px_ticks, qty) = (u64_at(48) as i64, u64_at(56) as i64);
+        let entry = match bytes[8] {
+            1 => Entry::Symbol { name: bytes[16..64].try_into().unwrap() },
+            2 => Entry::OrderIn { id: OrderId(u128_at(16)), side, px_ticks, qty },
+            3 => Entry::Reject { id: OrderId(u128_at(16)) },
+            4 => Entry::Fill { maker: OrderId(u128_at(16)), taker: OrderId(u128_at(32)), px_ticks, qty },
+            5 => Entry::Rest { id: OrderId(u128_at(16)), side, px_ticks, qty },
+            6 => Entry::Out { taker: OrderId(u128_at(16)), fills: aux, filled: qty },
+            7 => Entry::Cancel { id: OrderId(u128_at(16)), side, found: aux != 0 },
+            8 => Entry::Dropped { records: qty as u64 },
+            kind => return Err(HotLogError::UnknownKind(kind)),
+        };
+        Ok(Record { ts_ns: u64_at(0), symbol: u16::from_le_bytes([bytes[10], bytes[11]]), entry })
+    }
+}
+
+/// Reads a log written as [`header`] then encoded records.
+pub struct HotLogReader<R: Read> {
+    input: R,
+    /// Names by symbol id, from the `Symbol` records read so far
+    symbols: Vec<Option<String>>,
+}
+
+impl<R: Read> HotLogReader<R> {
+    pub fn new(mut input: R) -> Result<Self, HotLogError> {
+        let mut header = [0; HEADER_LEN];
+        input.read_exact(&mut header)?;
+        if header[..7] != MAGIC {
+            return Err(HotLogError::BadMagic);
+        }
+        if header[7] == 0 || header[7] > FORMAT_VERSION {
+            return Err(HotLogError::UnsupportedVersion(header[7]));
+        }
+        Ok(Self { input, symbols: Vec::new() })
+    }
+
+    /// The next record, or `None` at a clean end of input.
+    pub fn next_record(&mut self) -> Result<Option<Record>, HotLogError> {
+        let mut bytes = [0; RECORD_LEN];
+        let mut read = 0;
+        while read < RECORD_LEN {
+            match self.input.read(&mut bytes[read..])? {
+                0 if read == 0 => return Ok(None),
+                0 => return Err(HotLogError::Truncated),
+                n => read += n,
+            }
+        }
+        let record = Record::decode(&bytes)?;
+        if let Entry::Symbol { name } = record.entry {
+            let id = record.symbol as usize;
+            if self.symbols.len() <= id {
+                self.symbols.resize(id + 1, None);
+            }
+            let len = name.iter().position(|&b| b == 0).unwrap_or(NAME_LEN);
+            self.symbols[id] = Some(String::from_utf8_lossy(&name[..len]).into_owned());
+        }
+        Ok(Some(record))
+    }
+
+    /// The name a `Symbol` record gave `id`, if one has been read.
+    pub fn symbol(&self, id: u16) -> Option<&str> {
+        self.symbols.get(id as usize)?.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_entry_round_trips() {
+        let entries = [
+            Entry::symbol("AAPL"),
+            Entry::OrderIn { id: OrderId(u128::MAX), side: Side::Ask, px_ticks: -5, qty: 7 },
+            Entry::Reject { id: OrderId(3) },
+            Entry::Fill { maker: OrderId(1), taker: OrderId(2), px_ticks: 100, qty: 4 },
+            Entry::Rest { id: OrderId(2), side: Side::Bid, px_ticks: 101, qty: 3 },
+            Entry::Out { taker: OrderId(2), fills: 1, filled: 4 },
+            Entry::Cancel { id: OrderId(1), side: Side::Ask, found: true },
+            Entry::Dropped { records: 12 },
+        ];
+        for (i, entry) in entries.into_iter().enumerate() {
+            let record = Record { ts_ns: 1_000 + i as u64, symbol: 9, entry };
+            assert_eq!(Record::decode(&record.encode()).unwrap(), record);
+        }
+    }
+
+    #[test]
+    fn reader_names_symbols_and_rejects_partial_records() {
+        let mut log = header().to_vec();
+        log.extend(Record { ts_ns: 1, symbol: 2, entry: Entry::symbol("MSFT") }.encode());
+        log.extend(Record { ts_ns: 2, symbol: 2, entry: Entry::Reject { id: OrderId(7) } }.encode());
+
+        let mut reader = HotLogReader::new(&log[..]).unwrap();
+        assert!(reader.next_record().unwrap().is_some());
+        assert_eq!(reader.symbol(2), Some("MSFT"));
+        assert_eq!(reader.next_record().unwrap().unwrap().entry, Entry::Reject { id: OrderId(7) });
+        assert!(reader.next_record().unwrap().is_none());
+
+        log.truncate(log.len() - 1);
+        let mut reader = HotLogReader::new(&log[..]).unwrap();
+        reader.next_record().unwrap();
+        assert!(matches!(reader.next_record(), Err(HotLogError::Truncated)));
+        assert!(matches!(HotLogReader::new(&b"HXB\x01\x02\0\0\0"[..]), Err(HotLogError::BadMagic)));
+    }
+}
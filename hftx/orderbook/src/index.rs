@@ -0,0 +1,237 @@
+//! Composite index prices over a weighted basket of symbols.
+//!
+//! An index value is `sum(weight * price) / divisor`, with each
+//! constituent's price taken from its last trade, its BBO mid, or the last
+//! trade falling back to the mid ([`PriceSource`]). Weights are units of the
+//! constituent per index unit, so equal weights give a price-weighted index
+//! and share counts give a capitalization-weighted one; the divisor rescales
+//! either to a chosen base.
+//!
+//! A constituent with no price right now keeps its previous one and is
+//! marked stale, so one quiet book doesn't blank the index. Until every
+//! constituent has priced at least once the value is `None`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+/// Which prices feed the index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    /// Last trade
+    Last,
+    /// BBO midpoint; needs both sides
+    Mid,
+    /// Last trade, or the mid before the first trade
+    #[default]
+    LastOrMid,
+}
+
+impl PriceSource {
+    pub fn price(self, quote: &Quote) -> Option<f64> {
+        let mid = match (quote.bid, quote.ask) {
+            (Some(bid), Some(ask)) => Some((bid as f64 + ask as f64) / 2.0),
+            _ => None,
+        };
+        match self {
+            PriceSource::Last => quote.last.map(|px| px as f64),
+            PriceSource::Mid => mid,
+            PriceSource::LastOrMid => quote.last.map(|px| px as f64).or(mid),
+        }
+    }
+}
+
+/// A symbol's current prices, in ticks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quote {
+    pub last: Option<i64>,
+    pub bid: Option<i64>,
+    pub ask: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Constituent {
+    pub symbol: String,
+    pub weight: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexDefinition {
+    pub name: String,
+    pub constituents: Vec<Constituent>,
+    #[serde(default)]
+    pub source: PriceSource,
+    #[serde(default = "unit_divisor")]
+    pub divisor: f64,
+}
+
+fn unit_divisor() -> f64 {
+    1.0
+}
+
+/// One computation of an index.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IndexValue {
+    pub name: String,
+    /// `None` until every constituent has a price
+    pub value: Option<f64>,
+    pub constituents: Vec<ConstituentPrice>,
+    pub ts_ns: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConstituentPrice {
+    pub symbol: String,
+    pub weight: f64,
+    pub price: Option<f64>,
+    /// The price is carried over from an earlier computation
+    pub stale: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum IndexError {
+    Empty(String),
+    DuplicateConstituent { index: String, symbol: String },
+    /// A weight or divisor that is zero, NaN, or infinite
+    BadNumber { index: String, field: &'static str },
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexError::Empty(index) => write!(f, "index {} has no constituents", index),
+            IndexError::DuplicateConstituent { index, symbol } => {
+                write!(f, "index {} lists {} more than once", index, symbol)
+            }
+            IndexError::BadNumber { index, field } => write!(f, "index {}: {} must be finite and non-zero", index, field),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+/// Computes one index, remembering each constituent's last good price.
+#[derive(Clone, Debug)]
+pub struct IndexCalculator {
+    definition: IndexDefinition,
+    last_prices: Vec<Option<f64>>,
+}
+
+impl IndexCalculator {
+    pub fn new(definition: IndexDefinition) -> Result<Self, IndexError> {
+        let index = &definition.name;
+        if definition.constituents.is_empty() {
+            return Err(IndexError::Empty(index.clone()));
+        }
+        let mut seen = HashSet::new();
+        for constituent in &definition.constituents {
+            if !seen.insert(&constituent.symbol) {
+                return Err(IndexError::DuplicateConstituent { index: index.clone(), symbol: constituent.symbol.clone() });
+            }
+            if !constituent.weight.is_finite() || constituent.weight == 0.0 {
+                return Err(IndexError::BadNumber { index: index.clone(), field: "weight" });
+            }
+        }
+        if !definition.divisor.is_finite() || definition.divisor == 0.0 {
+            return Err(IndexError::BadNumber { index: index.clone(), field: "divisor" });
+        }
+        let last_prices = vec![None; definition.constituents.len()];
+        Ok(Self { definition, last_prices })
+    }
+
+    pub fn definition(&self) -> &IndexDefinition {
+        &self.definition
+    }
+
+    /// Prices every constituent through `quote` (`None` for a symbol with no
+    /// book) and returns the index.
+    pub fn compute(&mut self, mut quote: impl FnMut(&str) -> Option<Quote>, ts_ns: u64) -> IndexValue {
+        let source = self.definition.source;
+        let mut total = Some(0.0);
+        let mut constituents = Vec::with_capacity(self.last_prices.len());
+        for (constituent, last) in self.definition.constituents.iter().zip(&mut self.last_prices) {
+            let fresh = quote(&constituent.symbol).and_then(|q| source.price(&q));
+            if fresh.is_some() {
+                *last = fresh;
+            }
+            total = match (total, *last) {
+                (Some(sum), Some(px)) => Some(sum + constituent.weight * px),
+                _ => None,
+            };
+            constituents.push(ConstituentPrice {
+                symbol: constituent.symbol.clone(),
+                weight: constituent.weight,
+                price: *last,
+                stale: fresh.is_none() && last.is_some(),
+            });
+        }
+        IndexValue {
+            name: self.definition.name.clone(),
+            value: total.map(|sum| sum / self.definition.divisor),
+            constituents,
+            ts_ns,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(source: PriceSource) -> IndexDefinition {
+        IndexDefinition {
+            name: "TECH2".into(),
+            constituents: vec![
+                Constituent { symbol: "AAPL".into(), weight: 2.0 },
+                Constituent { symbol: "MSFT".into(), weight: 1.0 },
+            ],
+            source,
+            divisor: 3.0,
+        }
+    }
+
+    #[test]
+    fn weighted_value_with_fallback_and_stale_prices() {
+        let mut calc = IndexCalculator::new(definition(PriceSource::LastOrMid)).unwrap();
+        let aapl = Quote { last: Some(150), bid: Some(149), ask: Some(151) };
+        let msft = Quote { last: None, bid: Some(299), ask: Some(301) };
+
+        // MSFT hasn't traded, so its mid stands in
+        let value = calc.compute(|s| if s == "AAPL" { Some(aapl) } else { Some(msft) }, 1);
+        assert_eq!(value.value, Some((2.0 * 150.0 + 300.0) / 3.0));
+        assert!(value.constituents.iter().all(|c| !c.stale));
+
+        // An emptied MSFT book keeps its last price, flagged stale
+        let value = calc.compute(|s| if s == "AAPL" { Some(aapl) } else { Some(Quote::default()) }, 2);
+        assert_eq!(value.value, Some(200.0));
+        assert!(value.constituents[1].stale);
+    }
+
+    #[test]
+    fn no_value_until_every_constituent_prices() {
+        let mut calc = IndexCalculator::new(definition(PriceSource::Last)).unwrap();
+        let quoted = Quote { last: None, bid: Some(1), ask: Some(3) };
+        let value = calc.compute(|_| Some(quoted), 1);
+        assert_eq!(value.value, None);
+        assert_eq!(value.constituents[0].price, None);
+
+        let traded = Quote { last: Some(10), ..quoted };
+        assert_eq!(calc.compute(|_| Some(traded), 2).value, Some(10.0));
+    }
+
+    #[test]
+    fn rejects_bad_definitions() {
+        let mut empty = definition(PriceSource::Mid);
+        empty.constituents.clear();
+        assert_eq!(IndexCalculator::new(empty).unwrap_err(), IndexError::Empty("TECH2".into()));
+
+        let mut duplicate = definition(PriceSource::Mid);
+        duplicate.constituents[1].symbol = "AAPL".into();
+        assert!(matches!(IndexCalculator::new(duplicate), Err(IndexError::DuplicateConstituent { .. })));
+
+        let mut zero = definition(PriceSource::Mid);
+        zero.divisor = 0.0;
+        assert!(matches!(IndexCalculator::new(zero), Err(IndexError::BadNumber { field: "divisor", .. })));
+    }
+}
@@ -0,0 +1,273 @@
+//! Instrument metadata for symbols: spot, futures, and options.
+//!
+//! A book only knows prices and quantities; what a symbol *is* lives here.
+//! Futures and options name an underlying symbol, an expiry, and a contract
+//! multiplier (underlying units per contract); options add a strike and a
+//! right. Prices, strikes, and settlement values are all in ticks of the
+//! underlying, so one tick means the same thing throughout.
+//!
+//! [`InstrumentRegistry::take_expired`] hands back everything at or past
+//! its expiry, removing it, so the caller can delist and settle it exactly
+//! once. [`Instrument::settlement_value`] turns the underlying's settlement
+//! price into what one unit of the instrument pays.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+/// What a symbol trades, with the terms that make it a derivative.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InstrumentKind {
+    Spot,
+    Future {
+        underlying: String,
+        expiry_ns: u64,
+        multiplier: i64,
+    },
+    Option {
+        underlying: String,
+        expiry_ns: u64,
+        multiplier: i64,
+        strike_ticks: i64,
+        right: OptionRight,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Instrument {
+    pub symbol: String,
+    #[serde(flatten)]
+    pub kind: InstrumentKind,
+}
+
+impl Instrument {
+    pub fn spot(symbol: impl Into<String>) -> Self {
+        Self { symbol: symbol.into(), kind: InstrumentKind::Spot }
+    }
+
+    pub fn future(symbol: impl Into<String>, underlying: impl Into<String>, expiry_ns: u64, multiplier: i64) -> Self {
+        Self {
+            symbol: symbol.into(),
+            kind: InstrumentKind::Future { underlying: underlying.into(), expiry_ns, multiplier },
+        }
+    }
+
+    pub fn option(
+        symbol: impl Into<String>,
+        underlying: impl Into<String>,
+        expiry_ns: u64,
+        multiplier: i64,
+        strike_ticks: i64,
+        right: OptionRight,
+    ) -> Self {
+        Self {
+            symbol: symbol.into(),
+            kind: InstrumentKind::Option { underlying: underlying.into(), expiry_ns, multiplier, strike_ticks, right },
+        }
+    }
+
+    /// `None` for spot.
+    pub fn underlying(&self) -> Option<&str> {
+        match &self.kind {
+            InstrumentKind::Spot => None,
+            InstrumentKind::Future { underlying, .. } | InstrumentKind::Option { underlying, .. } => Some(underlying),
+        }
+    }
+
+    /// `None` for spot, which never expires.
+    pub fn expiry_ns(&self) -> Option<u64> {
+        match self.kind {
+            InstrumentKind::Spot => None,
+            InstrumentKind::Future { expiry_ns, .. } | InstrumentKind::Option { expiry_ns, .. } => Some(expiry_ns),
+        }
+    }
+
+    /// Underlying units per contract; 1 for spot.
+    pub fn multiplier(&self) -> i64 {
+        match self.kind {
+            InstrumentKind::Spot => 1,
+            InstrumentKind::Future { multiplier, .. } | InstrumentKind::Option { multiplier, .. } => multiplier,
+        }
+    }
+
+    /// What one unit pays at expiry given the underlying's settlement price,
+    /// in ticks: the price itself for a future, intrinsic value for an
+    /// option. Multiply by [`multiplier`](Self::multiplier) and the position
+    /// for the cash amount. `None` for spot.
+    pub fn settlement_value(&self, underlying_px: i64) -> Option<i64> {
+        match self.kind {
+            InstrumentKind::Spot => None,
+            InstrumentKind::Future { .. } => Some(underlying_px),
+            InstrumentKind::Option { strike_ticks, right: OptionRight::Call, .. } => {
+                Some((underlying_px - strike_ticks).max(0))
+            }
+            InstrumentKind::Option { strike_ticks, right: OptionRight::Put, .. } => {
+                Some((strike_ticks - underlying_px).max(0))
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    AlreadyRegistered(String),
+    /// A derivative whose underlying is itself
+    SelfUnderlying(String),
+    /// Multipliers and strikes must be positive
+    NonPositive { symbol: String, field: &'static str },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::AlreadyRegistered(symbol) => write!(f, "{} is already registered", symbol),
+            RegistryError::SelfUnderlying(symbol) => write!(f, "{} cannot be its own underlying", symbol),
+            RegistryError::NonPositive { symbol, field } => write!(f, "{}: {} must be positive", symbol, field),
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Instruments by symbol.
+#[derive(Debug, Default)]
+pub struct InstrumentRegistry {
+    instruments: BTreeMap<String, Instrument>,
+}
+
+impl InstrumentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an instrument after checking its terms. A symbol is registered
+    /// once; remove it first to change its terms.
+    pub fn register(&mut self, instrument: Instrument) -> Result<(), RegistryError> {
+        let symbol = &instrument.symbol;
+        if self.instruments.contains_key(symbol) {
+            return Err(RegistryError::AlreadyRegistered(symbol.clone()));
+        }
+        if instrument.underlying() == Some(symbol.as_str()) {
+            return Err(RegistryError::SelfUnderlying(symbol.clone()));
+        }
+        if instrument.multiplier() <= 0 {
+            return Err(RegistryError::NonPositive { symbol: symbol.clone(), field: "multiplier" });
+        }
+        if let InstrumentKind::Option { strike_ticks, .. } = instrument.kind {
+            if strike_ticks <= 0 {
+                return Err(RegistryError::NonPositive { symbol: symbol.clone(), field: "strike_ticks" });
+            }
+        }
+        self.instruments.insert(symbol.clone(), instrument);
+        Ok(())
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&Instrument> {
+        self.instruments.get(symbol)
+    }
+
+    pub fn remove(&mut self, symbol: &str) -> Option<Instrument> {
+        self.instruments.remove(symbol)
+    }
+
+    /// Every instrument, by symbol.
+    pub fn iter(&self) -> impl Iterator<Item = &Instrument> {
+        self.instruments.values()
+    }
+
+    /// Derivatives on `underlying`.
+    pub fn derivatives_of<'a>(&'a self, underlying: &'a str) -> impl Iterator<Item = &'a Instrument> {
+        self.instruments.values().filter(move |i| i.underlying() == Some(underlying))
+    }
+
+    /// Earliest expiry still registered, for scheduling the next check.
+    pub fn next_expiry_ns(&self) -> Option<u64> {
+        self.instruments.values().filter_map(Instrument::expiry_ns).min()
+    }
+
+    /// Removes and returns every instrument expiring at or before `now_ns`,
+    /// earliest first.
+    pub fn take_expired(&mut self, now_ns: u64) -> Vec<Instrument> {
+        let expired: Vec<String> = self
+            .instruments
+            .values()
+            .filter(|i| i.expiry_ns().is_some_and(|expiry| expiry <= now_ns))
+            .map(|i| i.symbol.clone())
+            .collect();
+        let mut taken: Vec<Instrument> = expired.iter().filter_map(|s| self.instruments.remove(s)).collect();
+        taken.sort_by_key(|i| i.expiry_ns());
+        taken
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instruments.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registers_checks_terms_and_expires_once() {
+        let mut registry = InstrumentRegistry::new();
+        registry.register(Instrument::spot("ES")).unwrap();
+        registry.register(Instrument::future("ESZ6", "ES", 2_000, 50)).unwrap();
+        registry.register(Instrument::option("ESZ6C4000", "ES", 1_000, 50, 4_000, OptionRight::Call)).unwrap();
+
+        assert_eq!(registry.register(Instrument::spot("ES")), Err(RegistryError::AlreadyRegistered("ES".into())));
+        assert_eq!(
+            registry.register(Instrument::future("X", "X", 1, 1)),
+            Err(RegistryError::SelfUnderlying("X".into()))
+        );
+        assert!(matches!(
+            registry.register(Instrument::option("P", "ES", 1, 1, 0, OptionRight::Put)),
+            Err(RegistryError::NonPositive { field: "strike_ticks", .. })
+        ));
+        assert_eq!(registry.derivatives_of("ES").count(), 2);
+        assert_eq!(registry.next_expiry_ns(), Some(1_000));
+
+        assert!(registry.take_expired(999).is_empty());
+        let expired: Vec<String> = registry.take_expired(2_000).into_iter().map(|i| i.symbol).collect();
+        assert_eq!(expired, vec!["ESZ6C4000", "ESZ6"]);
+        assert!(registry.take_expired(u64::MAX).is_empty());
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn settlement_values() {
+        let call = Instrument::option("C", "U", 0, 100, 4_000, OptionRight::Call);
+        let put = Instrument::option("P", "U", 0, 100, 4_000, OptionRight::Put);
+        assert_eq!(call.settlement_value(4_150), Some(150));
+        assert_eq!(call.settlement_value(3_900), Some(0));
+        assert_eq!(put.settlement_value(3_900), Some(100));
+        assert_eq!(Instrument::future("F", "U", 0, 5).settlement_value(4_150), Some(4_150));
+        assert_eq!(Instrument::spot("U").settlement_value(4_150), None);
+    }
+
+    #[test]
+    fn json_shape() {
+        let option = Instrument::option("ESZ6C4000", "ES", 1_000, 50, 4_000, OptionRight::Call);
+        let json = serde_json::to_value(&option).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "symbol": "ESZ6C4000", "type": "option", "underlying": "ES", "expiry_ns": 1_000,
+                "multiplier": 50, "strike_ticks": 4_000, "right": "call"
+            })
+        );
+        assert_eq!(serde_json::from_value::<Instrument>(json).unwrap(), option);
+    }
+}
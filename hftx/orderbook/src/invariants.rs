@@ -0,0 +1,109 @@
+//! What [`OrderBook::check_invariants`](crate::OrderBook::check_invariants)
+//! and [`PriceLevels::check_invariants`](crate::PriceLevels::check_invariants)
+//! find wrong with a book.
+//!
+//! Each side is checked against its own queues:
+//! - every level holds at least one order, and every order sits at its own
+//!   side and price
+//! - within a level, live orders are queued in `ts_ns` order, icebergs
+//!   aside, since a reloaded slice goes to the back keeping its time
+//! - each queue is linked both ways from its head to its tail, the slab
+//!   holds nothing the levels don't queue, and its free list names its
+//!   vacant slots and nothing else
+//! - the id and owner indexes, the tombstone count and the running level
+//!   totals agree with what is queued, and a price ladder's cursors point
+//!   at its outermost levels
+//! - live orders have a positive quantity, and an iceberg shows no more
+//!   than its `display_qty`
+//!
+//! Across the two sides, no bid may rest at or above an ask unless one of
+//! them arrived with a `min_qty` and passed the other over. Hidden orders
+//! count here like any other.
+//!
+//! Timestamps are only in order if callers stamp orders as they arrive, as
+//! the exchange and the simulators do.
+//!
+//! Building with the `debug-invariants` feature checks the book after
+//! every mutation in builds with debug assertions.
+
+use crate::types::{OrderId, Side};
+use std::fmt;
+
+/// The first broken invariant a check came across.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// A price level with nothing queued
+    EmptyLevel { side: Side, px_ticks: i64 },
+    /// An order queued at a side or price other than its own
+    Misplaced { side: Side, px_ticks: i64, id: OrderId },
+    /// A live order queued behind one stamped later
+    OutOfTimeOrder { side: Side, px_ticks: i64, id: OrderId },
+    /// The id index disagrees with the queues about a live order
+    Index { side: Side, id: OrderId },
+    /// The owner index disagrees with the queues about a live order
+    Owner { side: Side, id: OrderId },
+    /// The tombstone count doesn't match the canceled orders still queued
+    Tombstones { side: Side, recorded: usize, queued: usize },
+    /// Orders held against orders linked into queues, either by the slab
+    /// as a whole or by one queue's own count
+    Slab { side: Side, held: usize, queued: usize },
+    /// A queue whose links don't run from its head to its tail through
+    /// as many orders as it counts, each once
+    Links { side: Side, px_ticks: i64 },
+    /// A free list that misses a vacant slot or lists an occupied one
+    FreeList { side: Side },
+    /// A price ladder's best-price cursors or level count out of step with
+    /// its slots
+    Ladder { side: Side },
+    /// A level's running total, as (quantity, orders), against a recount
+    LevelTotal { side: Side, px_ticks: i64, cached: (i64, usize), counted: (i64, usize) },
+    /// The side's running quantity against the sum of its levels
+    SideTotal { side: Side, cached: i64, counted: i64 },
+    /// A live order with nothing left
+    EmptyOrder { side: Side, id: OrderId },
+    /// An iceberg showing more than its slice, or a reserve held for an
+    /// order that isn't an iceberg
+    Iceberg { side: Side, id: OrderId },
+    /// A bid resting at or above an ask with no `min_qty` on either
+    Crossed { bid: OrderId, bid_px: i64, ask: OrderId, ask_px: i64 },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::EmptyLevel { side, px_ticks } => write!(f, "{:?} level {} is empty", side, px_ticks),
+            Violation::Misplaced { side, px_ticks, id } => {
+                write!(f, "order {:?} is queued at {:?} {} but isn't priced there", id, side, px_ticks)
+            }
+            Violation::OutOfTimeOrder { side, px_ticks, id } => {
+                write!(f, "order {:?} at {:?} {} is queued behind a later order", id, side, px_ticks)
+            }
+            Violation::Index { side, id } => write!(f, "{:?} index disagrees with the queues on {:?}", side, id),
+            Violation::Owner { side, id } => write!(f, "{:?} owner index disagrees with the queues on {:?}", side, id),
+            Violation::Tombstones { side, recorded, queued } => {
+                write!(f, "{:?} records {} canceled orders but {} are queued", side, recorded, queued)
+            }
+            Violation::Slab { side, held, queued } => {
+                write!(f, "{:?} slab holds {} orders but {} are queued", side, held, queued)
+            }
+            Violation::Links { side, px_ticks } => write!(f, "{:?} level {} queue is mislinked", side, px_ticks),
+            Violation::FreeList { side } => write!(f, "{:?} slab free list disagrees with its vacant slots", side),
+            Violation::Ladder { side } => write!(f, "{:?} price ladder cursors disagree with its slots", side),
+            Violation::LevelTotal { side, px_ticks, cached, counted } => write!(
+                f,
+                "{:?} level {} totals {} in {} orders but holds {} in {}",
+                side, px_ticks, cached.0, cached.1, counted.0, counted.1
+            ),
+            Violation::SideTotal { side, cached, counted } => {
+                write!(f, "{:?} side totals {} but its levels hold {}", side, cached, counted)
+            }
+            Violation::EmptyOrder { side, id } => write!(f, "{:?} order {:?} rests with nothing left", side, id),
+            Violation::Iceberg { side, id } => write!(f, "{:?} iceberg {:?} shows more than its slice", side, id),
+            Violation::Crossed { bid, bid_px, ask, ask_px } => {
+                write!(f, "bid {:?} at {} crosses ask {:?} at {}", bid, bid_px, ask, ask_px)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
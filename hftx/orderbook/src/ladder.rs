@@ -0,0 +1,390 @@
+//! The price-to-queue maps behind [`PriceLevels`](crate::PriceLevels).
+//!
+//! A side keeps its level queues in a [`Levels`]: by default a `BTreeMap`
+//! from price to queue, which takes any price at a tree lookup per access.
+//! A side built [`with_price_band`](crate::PriceLevels::with_price_band)
+//! keeps them in a [`Ladder`] instead, one slot per tick across the band,
+//! so finding a level is an index and the best price is a cursor. Prices
+//! outside the band still work, in a tree beside the slots, so the band
+//! only decides what is fast.
+
+use crate::slab::Queue;
+use crate::types::Side;
+use serde::{Deserialize, Serialize};
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// Level queues by price, best found at either end.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) enum Levels {
+    Tree(BTreeMap<i64, Queue>),
+    Ladder(Ladder),
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Levels::Tree(BTreeMap::new())
+    }
+}
+
+impl Levels {
+    /// A ladder over `low..=high` holding what these levels hold.
+    pub(crate) fn into_ladder(self, low: i64, high: i64) -> Self {
+        let mut ladder = Ladder::new(low, high);
+        for (px, q) in self.iter() {
+            *ladder.entry(px) = *q;
+        }
+        Levels::Ladder(ladder)
+    }
+
+    /// The band a ladder covers, or None for a tree.
+    pub(crate) fn band(&self) -> Option<(i64, i64)> {
+        match self {
+            Levels::Tree(_) => None,
+            Levels::Ladder(ladder) => Some((ladder.base, ladder.top())),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Levels::Tree(tree) => tree.len(),
+            Levels::Ladder(ladder) => ladder.occupied + ladder.outside.len(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn contains_key(&self, px: i64) -> bool {
+        self.get(px).is_some()
+    }
+
+    pub(crate) fn get(&self, px: i64) -> Option<&Queue> {
+        match self {
+            Levels::Tree(tree) => tree.get(&px),
+            Levels::Ladder(ladder) => match ladder.slot(px) {
+                Some(i) => Some(&ladder.slots[i]).filter(|q| !q.is_empty()),
+                None => ladder.outside.get(&px),
+            },
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self, px: i64) -> Option<&mut Queue> {
+        match self {
+            Levels::Tree(tree) => tree.get_mut(&px),
+            Levels::Ladder(ladder) => match ladder.slot(px) {
+                Some(i) => Some(&mut ladder.slots[i]).filter(|q| !q.is_empty()),
+                None => ladder.outside.get_mut(&px),
+            },
+        }
+    }
+
+    /// The queue at `px`, made if there is none; the caller queues an
+    /// order in it straight away.
+    pub(crate) fn entry(&mut self, px: i64) -> &mut Queue {
+        match self {
+            Levels::Tree(tree) => tree.entry(px).or_default(),
+            Levels::Ladder(ladder) => ladder.entry(px),
+        }
+    }
+
+    /// Drops the level at `px`, whose queue has emptied.
+    pub(crate) fn remove(&mut self, px: i64) {
+        match self {
+            Levels::Tree(tree) => {
+                tree.remove(&px);
+            }
+            Levels::Ladder(ladder) => ladder.remove(px),
+        }
+    }
+
+    /// Best price: the lowest for asks, the highest for bids.
+    pub(crate) fn best(&self, side: Side) -> Option<i64> {
+        match (self, side) {
+            (Levels::Tree(tree), Side::Ask) => tree.first_key_value().map(|(&px, _)| px),
+            (Levels::Tree(tree), Side::Bid) => tree.last_key_value().map(|(&px, _)| px),
+            (Levels::Ladder(ladder), side) => ladder.best(side),
+        }
+    }
+
+    /// The best level and its queue.
+    pub(crate) fn best_mut(&mut self, side: Side) -> Option<(i64, &mut Queue)> {
+        match (self, side) {
+            (Levels::Tree(tree), Side::Ask) => tree.first_entry().map(|level| (*level.key(), level.into_mut())),
+            (Levels::Tree(tree), Side::Bid) => tree.last_entry().map(|level| (*level.key(), level.into_mut())),
+            (Levels::Ladder(ladder), side) => {
+                let px = ladder.best(side)?;
+                let q = match ladder.slot(px) {
+                    Some(i) => &mut ladder.slots[i],
+                    None => ladder.outside.get_mut(&px)?,
+                };
+                Some((px, q))
+            }
+        }
+    }
+
+    /// Every level, lowest price first.
+    pub(crate) fn iter(&self) -> Iter<'_> {
+        match self {
+            Levels::Tree(tree) => Iter::Tree(tree.iter()),
+            Levels::Ladder(ladder) => {
+                let top = ladder.top();
+                let below = ladder.outside.range(..ladder.base);
+                let above = ladder.outside.range((Bound::Excluded(top), Bound::Unbounded));
+                let slots = match ladder.occupied {
+                    0 => &ladder.slots[..0],
+                    _ => &ladder.slots[ladder.low..=ladder.high],
+                };
+                let base = ladder.base + ladder.low as i64;
+                let slots = slots
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, q)| !q.is_empty())
+                    .map(move |(i, q)| (base + i as i64, q));
+                let inner = below.map(|(&px, q)| (px, q)).chain(slots).chain(above.map(|(&px, q)| (px, q)));
+                Iter::Ladder { inner: Box::new(inner), left: self.len() }
+            }
+        }
+    }
+
+    /// Keeps the levels `keep` says to, dropping the rest.
+    pub(crate) fn retain(&mut self, mut keep: impl FnMut(i64, &mut Queue) -> bool) {
+        match self {
+            Levels::Tree(tree) => tree.retain(|&px, q| keep(px, q)),
+            Levels::Ladder(ladder) => {
+                ladder.outside.retain(|&px, q| keep(px, q));
+                let base = ladder.base;
+                for (i, q) in ladder.slots.iter_mut().enumerate() {
+                    if !q.is_empty() && !keep(base + i as i64, q) {
+                        *q = Queue::default();
+                    }
+                }
+                ladder.recount();
+            }
+        }
+    }
+
+    /// False if a ladder's cursors or level count are out of step with its
+    /// slots; a tree has nothing to check.
+    pub(crate) fn consistent(&self) -> bool {
+        match self {
+            Levels::Tree(_) => true,
+            Levels::Ladder(ladder) => {
+                let occupied: Vec<usize> = (0..ladder.slots.len()).filter(|&i| !ladder.slots[i].is_empty()).collect();
+                occupied.len() == ladder.occupied
+                    && occupied.first().is_none_or(|&low| low == ladder.low)
+                    && occupied.last().is_none_or(|&high| high == ladder.high)
+            }
+        }
+    }
+}
+
+/// One queue per tick from `base` up, best prices tracked by cursor.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Ladder {
+    /// Price of the first slot
+    base: i64,
+    /// Queue per tick; an empty queue is no level
+    slots: Vec<Queue>,
+    /// Lowest and highest non-empty slots, while any is
+    low: usize,
+    high: usize,
+    /// Non-empty slots
+    occupied: usize,
+    /// Levels priced outside the band
+    outside: BTreeMap<i64, Queue>,
+}
+
+impl Ladder {
+    fn new(low: i64, high: i64) -> Self {
+        assert!(low <= high, "empty price band");
+        let ticks = usize::try_from(high.abs_diff(low)).ok().and_then(|n| n.checked_add(1)).expect("price band too wide");
+        Self { base: low, slots: vec![Queue::default(); ticks], low: 0, high: 0, occupied: 0, outside: BTreeMap::new() }
+    }
+
+    /// Price of the last slot.
+    fn top(&self) -> i64 {
+        self.base + (self.slots.len() - 1) as i64
+    }
+
+    /// Slot of `px`, if it is in the band.
+    fn slot(&self, px: i64) -> Option<usize> {
+        let offset = px.checked_sub(self.base)?;
+        usize::try_from(offset).ok().filter(|&i| i < self.slots.len())
+    }
+
+    fn best(&self, side: Side) -> Option<i64> {
+        let slots = (self.occupied > 0).then(|| match side {
+            Side::Ask => self.base + self.low as i64,
+            Side::Bid => self.base + self.high as i64,
+        });
+        if self.outside.is_empty() {
+            return slots;
+        }
+        let outside = match side {
+            Side::Ask => self.outside.first_key_value(),
+            Side::Bid => self.outside.last_key_value(),
+        };
+        let outside = outside.map(|(&px, _)| px);
+        match side {
+            Side::Ask => slots.into_iter().chain(outside).min(),
+            Side::Bid => slots.into_iter().chain(outside).max(),
+        }
+    }
+
+    fn entry(&mut self, px: i64) -> &mut Queue {
+        let Some(i) = self.slot(px) else {
+            return self.outside.entry(px).or_default();
+        };
+        if self.slots[i].is_empty() {
+            if self.occupied == 0 {
+                (self.low, self.high) = (i, i);
+            } else {
+                self.low = self.low.min(i);
+                self.high = self.high.max(i);
+            }
+            self.occupied += 1;
+        }
+        &mut self.slots[i]
+    }
+
+    /// Clears the slot at `px` and moves a cursor that pointed at it on to
+    /// the next level in.
+    fn remove(&mut self, px: i64) {
+        let Some(i) = self.slot(px) else {
+            self.outside.remove(&px);
+            return;
+        };
+        self.slots[i] = Queue::default();
+        self.occupied -= 1;
+        if self.occupied == 0 {
+            return;
+        }
+        if i == self.low {
+            self.low = (i + 1..=self.high).find(|&j| !self.slots[j].is_empty()).expect("occupied slot above");
+        } else if i == self.high {
+            self.high = (self.low..i).rev().find(|&j| !self.slots[j].is_empty()).expect("occupied slot below");
+        }
+    }
+
+    /// Recomputes the cursors and count from the slots.
+    fn recount(&mut self) {
+        let mut occupied = self.slots.iter().enumerate().filter(|(_, q)| !q.is_empty()).map(|(i, _)| i);
+        self.occupied = 0;
+        if let Some(low) = occupied.next() {
+            self.low = low;
+            self.high = low;
+            self.occupied = 1;
+            for i in occupied {
+                self.high = i;
+                self.occupied += 1;
+            }
+        }
+    }
+}
+
+/// Levels lowest price first, either end.
+pub(crate) enum Iter<'a> {
+    Tree(btree_map::Iter<'a, i64, Queue>),
+    Ladder {
+        inner: Box<dyn DoubleEndedIterator<Item = (i64, &'a Queue)> + 'a>,
+        left: usize,
+    },
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (i64, &'a Queue);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Tree(iter) => iter.next().map(|(&px, q)| (px, q)),
+            Iter::Ladder { inner, left } => {
+                let level = inner.next()?;
+                *left -= 1;
+                Some(level)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Tree(iter) => iter.next_back().map(|(&px, q)| (px, q)),
+            Iter::Ladder { inner, left } => {
+                let level = inner.next_back()?;
+                *left -= 1;
+                Some(level)
+            }
+        }
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {
+    fn len(&self) -> usize {
+        match self {
+            Iter::Tree(iter) => iter.len(),
+            Iter::Ladder { left, .. } => *left,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Makes a level at `px` by queueing one order there, in a slab of its
+    /// own: the ladder only looks at whether a queue is empty.
+    fn occupy(levels: &mut Levels, px: i64) {
+        let q = levels.entry(px);
+        let mut slab = crate::slab::Slab::default();
+        let order = crate::types::Order {
+            id: crate::types::OrderId(px as u128),
+            symbol: "AAPL".into(),
+            side: Side::Ask,
+            px_ticks: px,
+            qty: 1,
+            ts_ns: 0,
+            tif: crate::types::TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: crate::types::ParticipantId::NONE,
+        };
+        slab.push_back(q, order);
+    }
+
+    #[test]
+    fn cursors_follow_levels_in_and_out_of_the_band() {
+        let mut levels = Levels::default().into_ladder(100, 109);
+        assert_eq!(levels.best(Side::Ask), None);
+        for px in [105, 102, 107, 95, 120] {
+            occupy(&mut levels, px);
+        }
+        assert!(levels.consistent());
+        assert_eq!(levels.len(), 5);
+        assert_eq!(levels.iter().map(|(px, _)| px).collect::<Vec<_>>(), [95, 102, 105, 107, 120]);
+        assert_eq!(levels.iter().rev().len(), 5);
+        assert_eq!((levels.best(Side::Ask), levels.best(Side::Bid)), (Some(95), Some(120)));
+
+        levels.remove(95);
+        levels.remove(120);
+        assert_eq!((levels.best(Side::Ask), levels.best(Side::Bid)), (Some(102), Some(107)));
+        levels.remove(102);
+        levels.remove(107);
+        assert!(levels.consistent());
+        assert_eq!((levels.best(Side::Ask), levels.best_mut(Side::Bid).map(|(px, _)| px)), (Some(105), Some(105)));
+
+        levels.retain(|_, _| false);
+        assert!(levels.is_empty() && levels.consistent());
+        assert_eq!(levels.band(), Some((100, 109)));
+    }
+}
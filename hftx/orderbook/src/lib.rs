@@ -6,19 +6,102 @@
 //! - Lazy cancellation for performance
 pub mod types;
 
-pub use types::{Order, OrderId, Side, Trade};
+pub use types::{BookLevel, Depth, ExecutionReport, Order, OrderId, OrderKind, OrderStatus, ParticipantId, PriceConverter, PriceError, RejectReason, Side, SymbolConfig, TimeInForce, Trade};
 pub mod price_levels;
 pub use price_levels::PriceLevels;
+pub mod agents;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod backtest;
+pub mod candles;
+pub mod codec;
+pub mod csv;
+#[cfg(feature = "parquet")]
+pub mod export;
+pub mod flow;
+pub mod funding;
+pub mod hotlog;
+pub mod index;
+pub mod instruments;
+pub mod invariants;
+pub mod itch;
+mod ladder;
+pub mod lifecycle;
+pub mod listener;
+pub mod margin;
+pub mod pegs;
+#[cfg(feature = "polars")]
+pub mod polars;
+pub mod profile;
+pub mod quotes;
+pub mod rng;
+pub mod routing;
+pub mod sbe;
+pub mod shadow;
+pub mod sim;
+mod slab;
+pub mod symbols;
+pub use symbols::{SymbolId, SymbolRegistry};
+pub mod tca;
+pub mod throttle;
+pub mod triggers;
+
+use codec::BookEvent;
+use invariants::Violation;
+use lifecycle::{Lifecycles, OrderLifecycle, OrderState};
+use listener::BookListener;
+use pegs::{Peg, PegKind, Pegs};
+use quotes::{Quote, QuoteError, QuoteReport, Quotes};
+use throttle::{Throttle, ThrottleConfig};
+use triggers::{StopOrder, Triggers};
+use serde::{Deserialize, Serialize};
 
 /// Central limit order book with separate bid/ask sides.
 /// 
 /// Uses price-time priority: better prices match first, then earliest orders.
 /// Not thread-safe - wrap in RwLock for concurrent access.
+///
+/// Serializes with every resting order, held stop and peg, canceled orders
+/// still queued included, so a book written to disk reloads to match
+/// exactly as it would have. Listeners and the throttle are left out: a
+/// reloaded book has none until they are set up again.
+#[derive(Serialize, Deserialize)]
 pub struct OrderBook {
     /// Buy orders, highest price first
     pub bids: PriceLevels,
     /// Sell orders, lowest price first  
     pub asks: PriceLevels,
+    /// Stop and stop-limit orders waiting to be triggered
+    pub stops: Triggers,
+    /// Resting pegged orders, repriced as the best prices move
+    pub pegs: Pegs,
+    /// Each owner's current two-sided quote
+    #[serde(default)]
+    pub quotes: Quotes,
+    /// What became of each order, see [`order_status`](Self::order_status)
+    #[serde(default)]
+    lifecycles: Option<Lifecycles>,
+    /// The only symbol this book takes orders for, if bound to one
+    #[serde(default)]
+    symbol: Option<SymbolId>,
+    /// Steps and size range every submission must keep to
+    #[serde(default, deserialize_with = "checked_config")]
+    config: SymbolConfig,
+    /// Price of the last trade, which decides whether a new stop triggers
+    /// straight away
+    last_px: Option<i64>,
+    /// Number of the last event, see [`seq`](Self::seq)
+    seq: u64,
+    /// Per-owner message rate limit, see [`admit`](Self::admit)
+    #[serde(skip)]
+    throttle: Option<Throttle<ParticipantId>>,
+    /// Told of every change, see [`listener`]
+    #[serde(skip)]
+    listeners: Vec<Box<dyn BookListener>>,
+    /// Levels the mutation under way has changed, kept only while anyone
+    /// is listening
+    #[serde(skip)]
+    touched: Vec<(Side, i64)>,
 }
 
 impl OrderBook {
@@ -27,120 +110,955 @@ impl OrderBook {
         Self {
             bids: PriceLevels::new(Side::Bid),
             asks: PriceLevels::new(Side::Ask),
+            stops: Triggers::new(),
+            pegs: Pegs::new(),
+            quotes: Quotes::new(),
+            lifecycles: None,
+            symbol: None,
+            config: SymbolConfig::default(),
+            last_px: None,
+            seq: 0,
+            throttle: None,
+            listeners: Vec::new(),
+            touched: Vec::new(),
+        }
+    }
+
+    /// Binds the book to `symbol`: it refuses orders for any other with
+    /// [`RejectReason::SymbolMismatch`], so a mis-routed order can't match
+    /// here. An unbound book takes orders for any symbol.
+    pub fn with_symbol(mut self, symbol: SymbolId) -> Self {
+        self.symbol = Some(symbol);
+        self
+    }
+
+    /// The symbol the book is bound to, if any.
+    pub fn symbol(&self) -> Option<SymbolId> {
+        self.symbol
+    }
+
+    /// Refuses submissions off `config`'s ticks and lots or outside its
+    /// size range (see [`SymbolConfig::check`]). Fails, with the reason,
+    /// unless `config` [validates](SymbolConfig::validate).
+    pub fn with_config(mut self, config: SymbolConfig) -> Result<Self, String> {
+        self.set_config(config)?;
+        Ok(self)
+    }
+
+    /// [`with_config`](Self::with_config) for a book already in use.
+    /// Orders already resting or held stay as they are.
+    pub fn set_config(&mut self, config: SymbolConfig) -> Result<(), String> {
+        config.validate()?;
+        self.config = config;
+        Ok(())
+    }
+
+    pub fn config(&self) -> &SymbolConfig {
+        &self.config
+    }
+
+    /// Rate-limits each owner's submissions, of every kind (see
+    /// [`throttle`]).
+    pub fn with_throttle(mut self, config: ThrottleConfig) -> Self {
+        self.throttle = Some(Throttle::new(config));
+        self
+    }
+
+    /// Tracks what becomes of every order submitted from here on, keeping
+    /// the last `retain` to end (see [`lifecycle`]).
+    pub fn with_order_status(mut self, retain: usize) -> Self {
+        self.lifecycles = Some(Lifecycles::new(retain));
+        self
+    }
+
+    /// Order `id`'s quantities and state, if the book tracks them and
+    /// still has it.
+    pub fn order_status(&self, id: OrderId) -> Option<&OrderLifecycle> {
+        self.lifecycles.as_ref()?.get(id)
+    }
+
+    /// Tells `listener` of every change from here on (see [`listener`]).
+    pub fn with_listener(mut self, listener: impl BookListener + 'static) -> Self {
+        self.add_listener(listener);
+        self
+    }
+
+    /// [`with_listener`](Self::with_listener) for a book already in use.
+    pub fn add_listener(&mut self, listener: impl BookListener + 'static) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    /// Compacts each side on its own once a cancel leaves `tombstones`
+    /// canceled orders queued there (see [`PriceLevels::compact`]).
+    pub fn with_compact_threshold(mut self, tombstones: usize) -> Self {
+        self.bids = self.bids.with_compact_threshold(tombstones);
+        self.asks = self.asks.with_compact_threshold(tombstones);
+        self
+    }
+
+    /// Cancels and expiries take orders out at once on both sides (see
+    /// [`PriceLevels::with_eager_cancel`]).
+    pub fn with_eager_cancel(mut self) -> Self {
+        self.bids = self.bids.with_eager_cancel();
+        self.asks = self.asks.with_eager_cancel();
+        self
+    }
+
+    /// Keeps both sides' displayed levels in flat ladders over `low..=high`
+    /// (see [`PriceLevels::with_price_band`]), for a symbol whose prices
+    /// stay in that band.
+    pub fn with_price_band(mut self, low: i64, high: i64) -> Self {
+        self.bids = self.bids.with_price_band(low, high);
+        self.asks = self.asks.with_price_band(low, high);
+        self
+    }
+
+    /// Takes lazily-canceled orders out of both sides' queues and returns
+    /// how many there were. Cancels leave them queued until matching
+    /// reaches them, so a long-running book that cancels deep in its
+    /// levels should call this now and then, or set a threshold.
+    pub fn compact(&mut self) -> usize {
+        self.bids.compact() + self.asks.compact()
+    }
+
+    /// Spends one of `order`'s owner's throttle tokens at its `ts_ns`, if
+    /// the book has a throttle. Every submission comes through here once
+    /// it has passed its checks, a quote once for both sides, so an owner
+    /// can't get round its rate by switching order kind. Orders without an
+    /// owner aren't throttled.
+    fn admit(&mut self, order: &Order) -> Result<(), RejectReason> {
+        match &mut self.throttle {
+            Some(throttle) if order.owner != ParticipantId::NONE => Ok(throttle.check(order.owner, order.ts_ns)?),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks an order before it is submitted as `kind`: it must be for
+    /// the book's symbol if the book has one, its quantity must be
+    /// positive, and so must its price unless it is a market order, both
+    /// must keep to the book's [`config`](Self::config), and no
+    /// [live](Self::is_live) order may have its id. Every submission checks
+    /// this first and refuses, with the book untouched, an order that fails.
+    pub fn check_order(&self, order: &Order, kind: OrderKind) -> Result<(), RejectReason> {
+        self.check_entry(order, kind, false)
+    }
+
+    /// [`check_order`](Self::check_order), where an order `replacing` the
+    /// live one with its id may reuse that id.
+    fn check_entry(&self, order: &Order, kind: OrderKind, replacing: bool) -> Result<(), RejectReason> {
+        if let Some(book) = self.symbol.filter(|&symbol| symbol != order.symbol) {
+            return Err(RejectReason::SymbolMismatch { book, order: order.symbol });
+        }
+        if order.qty <= 0 {
+            return Err(RejectReason::InvalidQuantity(order.qty));
+        }
+        if kind == OrderKind::Limit && order.px_ticks <= 0 {
+            return Err(RejectReason::InvalidPrice(order.px_ticks));
+        }
+        self.config.check((kind == OrderKind::Limit).then_some(order.px_ticks), order.qty)?;
+        if !replacing && self.is_live(order.id) {
+            return Err(RejectReason::DuplicateId(order.id));
+        }
+        // A market order never rests, and fills no more than it asks for
+        if kind == OrderKind::Limit && self.levels(order.side).total_qty().checked_add(order.qty).is_none() {
+            return Err(RejectReason::QuantityOverflow(order.qty));
+        }
+        Ok(())
+    }
+
+    /// Checks the terms resting order `id` is amended to: a positive
+    /// price, kept to the book's [`config`](Self::config) with `new_qty`,
+    /// and a `new_qty` that takes neither its side's total nor what the
+    /// order has filled plus what it has left past `i64::MAX`. A pegged
+    /// order's `new_px` is its limit, checked the same.
+    pub fn check_amend(&self, id: OrderId, new_px: i64, new_qty: i64) -> Result<(), RejectReason> {
+        if new_px <= 0 {
+            return Err(RejectReason::InvalidPrice(new_px));
         }
+        self.config.check(Some(new_px), new_qty)?;
+        let Some(side) = self.side_of(id) else { return Ok(()) };
+        let levels = self.levels(side);
+        let others = levels.total_qty() - levels.get(id).map_or(0, |order| order.qty);
+        let filled = self.order_status(id).map_or(0, |status| status.filled_qty);
+        if others.checked_add(new_qty).is_none() || filled.checked_add(new_qty).is_none() {
+            return Err(RejectReason::QuantityOverflow(new_qty));
+        }
+        Ok(())
     }
 
-    /// Submits limit order, returns any immediate trades.
+    /// [`check_order`](Self::check_order) for a stop that turns into a
+    /// `kind` order, whose `stop_px` must be positive and on a tick too.
+    pub fn check_stop(&self, order: &Order, stop_px: i64, kind: OrderKind) -> Result<(), RejectReason> {
+        self.check_order(order, kind)?;
+        if stop_px <= 0 {
+            return Err(RejectReason::InvalidPrice(stop_px));
+        }
+        self.config.check(Some(stop_px), order.qty)
+    }
+
+    /// Submits limit order, reporting what became of it along with any
+    /// immediate trades.
     /// 
     /// Order attempts to match against opposite side first, then rests in book.
     /// Trades execute at maker's price following standard exchange rules.
-    pub fn submit_limit(&mut self, mut taker: Order) -> Vec<Trade> {
+    ///
+    /// The order's `tif` decides what happens to the rest: a `Day` order
+    /// rests it, an `IOC` order cancels it. A `FOK` order is rejected whole,
+    /// with no trades and the book untouched, unless it can fill completely.
+    ///
+    /// An order with a `min_qty` skips fills smaller than that on arrival,
+    /// so it never trades against makers showing less, and stops taking
+    /// once what it has left is under the minimum. It may then rest at a
+    /// price that crosses the makers it passed over; once resting, the
+    /// minimum no longer applies.
+    ///
+    /// A `hidden` order matches like any other but rests out of sight: it
+    /// is left out of best prices and depth, and fills only after every
+    /// displayed order at its price.
+    ///
+    /// Stops the trades set off are matched in turn, then pegs the order
+    /// moved are repriced, and their trades follow the order's own.
+    ///
+    /// Refused, with the book untouched, unless it passes
+    /// [`check_order`](Self::check_order) and its owner isn't
+    /// [throttled](Self::with_throttle).
+    pub fn submit_limit(&mut self, taker: Order) -> Result<ExecutionReport, RejectReason> {
+        let (id, qty, tif) = (taker.id, taker.qty, taker.tif);
         let mut trades = Vec::new();
-        let ts_ns = taker.ts_ns;
+        let resting = self.submit_limit_into(taker, &mut trades)?;
+        Ok(ExecutionReport::new(id, qty, tif, resting, trades))
+    }
 
-        match taker.side {
-            Side::Bid => {
-                // Match against asks (sell orders)
-                while taker.qty > 0 {
-                    let Some(best_ask_px) = self.asks.best_price() else {
-                        break; // No asks available
-                    };
-                    
-                    if taker.px_ticks < best_ask_px {
-                        break; // No cross - bid too low
-                    }
-
-                    let mut maker = match self.asks.pop_best() {
-                        Some(o) => o,
-                        None => break,
-                    };
-
-                    let fill = taker.qty.min(maker.qty);
-                    taker.qty -= fill;
-                    maker.qty -= fill;
-
-                    trades.push(Trade {
-                        maker: maker.id,
-                        taker: taker.id,
-                        symbol: taker.symbol.clone(),
-                        px_ticks: best_ask_px, // Trade at maker's price
-                        qty: fill,
-                        ts_ns,
-                    });
-
-                    // Restore partially filled maker to front of queue
-                    if maker.qty > 0 {
-                        self.asks.push_front(maker);
-                    }
-                }
+    /// [`submit_limit`](Self::submit_limit) that appends its trades to
+    /// `out` rather than returning them in a fresh `Vec`, so a caller
+    /// submitting in a loop can clear and reuse one buffer and match
+    /// without allocating for trades. Whatever `out` already holds is left
+    /// alone. Returns whether the order rests afterwards, which with the
+    /// appended trades is everything an [`ExecutionReport`] is made of.
+    pub fn submit_limit_into(&mut self, taker: Order, out: &mut Vec<Trade>) -> Result<bool, RejectReason> {
+        self.check_order(&taker, OrderKind::Limit)?;
+        self.admit(&taker)?;
+        Ok(self.enter_limit(taker, out))
+    }
+
+    /// Enters a limit order already checked and admitted.
+    fn enter_limit(&mut self, taker: Order, out: &mut Vec<Trade>) -> bool {
+        let (id, side, ts_ns) = (taker.id, taker.side, taker.ts_ns);
+        let from = out.len();
+        self.next_seq();
+        self.opened(&taker);
+        self.limit(taker, out);
+        self.settle(out, from, ts_ns);
+        self.levels(side).contains(id)
+    }
+
+    fn limit(&mut self, mut taker: Order, trades: &mut Vec<Trade>) {
+        let limit_px = taker.px_ticks;
+        if !self.fok_fillable(&taker, limit_px) {
+            self.ended(taker.id, OrderState::Canceled);
+            return;
+        }
+        self.take(&mut taker, limit_px, trades);
+        if taker.qty > 0 && taker.tif != TimeInForce::Day {
+            self.ended(taker.id, OrderState::Canceled);
+        }
 
-                // Add remaining taker quantity to bid side
-                if taker.qty > 0 {
-                    self.bids.push(taker);
+        // Add remaining taker quantity to its own side
+        if taker.qty > 0 && taker.tif == TimeInForce::Day {
+            if !self.listeners.is_empty() {
+                for listener in &mut self.listeners {
+                    listener.on_rest(&taker);
+                }
+                if !taker.hidden {
+                    self.touched.push((taker.side, taker.px_ticks));
                 }
             }
+            self.levels_mut(taker.side).push(taker);
+        }
+    }
 
-            Side::Ask => {
-                // Match against bids (buy orders)
-                while taker.qty > 0 {
-                    let Some(best_bid_px) = self.bids.best_price() else {
-                        break; // No bids available
-                    };
-                    
-                    if taker.px_ticks > best_bid_px {
-                        break; // No cross - ask too high
-                    }
-
-                    let mut maker = match self.bids.pop_best() {
-                        Some(o) => o,
-                        None => break,
-                    };
-
-                    let fill = taker.qty.min(maker.qty);
-                    taker.qty -= fill;
-                    maker.qty -= fill;
-
-                    trades.push(Trade {
-                        maker: maker.id,
-                        taker: taker.id,
-                        symbol: taker.symbol.clone(),
-                        px_ticks: best_bid_px, // Trade at maker's price
-                        qty: fill,
-                        ts_ns,
-                    });
-
-                    // Restore partially filled maker to front of queue
-                    if maker.qty > 0 {
-                        self.bids.push_front(maker);
-                    }
-                }
+    /// Submits a market order ([`OrderKind::Market`](types::OrderKind)):
+    /// it sweeps the opposite side at any price until filled or the side is
+    /// empty. Whatever is left is cancelled rather than rested, so the
+    /// order's `px_ticks` is ignored. A `FOK` market order is rejected
+    /// unless the side holds its full quantity. Stops and pegs follow, and
+    /// it is checked, as for [`submit_limit`](Self::submit_limit).
+    pub fn submit_market(&mut self, taker: Order) -> Result<Vec<Trade>, RejectReason> {
+        self.check_order(&taker, OrderKind::Market)?;
+        self.admit(&taker)?;
+        let ts_ns = taker.ts_ns;
+        let mut trades = Vec::new();
+        self.next_seq();
+        self.opened(&taker);
+        self.market(taker, &mut trades);
+        self.settle(&mut trades, 0, ts_ns);
+        Ok(trades)
+    }
 
-                // Add remaining taker quantity to ask side
-                if taker.qty > 0 {
-                    self.asks.push(taker);
-                }
+    fn market(&mut self, mut taker: Order, trades: &mut Vec<Trade>) {
+        let limit_px = taker.side.market_limit();
+        if !self.fok_fillable(&taker, limit_px) {
+            self.ended(taker.id, OrderState::Canceled);
+            return;
+        }
+        self.take(&mut taker, limit_px, trades);
+        if taker.qty > 0 {
+            self.ended(taker.id, OrderState::Canceled);
+        }
+    }
+
+    /// Amends a resting order to `new_px` and `new_qty`, its new remaining
+    /// quantity (an iceberg's hidden quantity included). A pure size
+    /// decrease is made in place and keeps the order's queue position. A
+    /// price change or size increase takes the order out and submits it
+    /// again stamped `ts_ns`, behind everything already at its new price,
+    /// where it can trade like any new order. `new_qty` at or below zero
+    /// cancels it. For a pegged order `new_px` is its new limit, and the
+    /// peg decides where it goes.
+    ///
+    /// Returns the re-entry's trades, or None if `order_id` isn't resting.
+    /// Refused, with the book untouched, unless an amend that keeps some
+    /// quantity passes [`check_amend`](Self::check_amend).
+    pub fn modify(&mut self, order_id: OrderId, new_px: i64, new_qty: i64, ts_ns: u128) -> Result<Option<Vec<Trade>>, RejectReason> {
+        if new_qty > 0 {
+            self.check_amend(order_id, new_px, new_qty)?;
+        }
+        let Some(side) = self.side_of(order_id) else { return Ok(None) };
+        let levels = self.levels(side);
+        let Some(resting) = levels.get(order_id) else { return Ok(None) };
+        let (px_ticks, qty) = (resting.px_ticks, resting.qty + levels.hidden_qty(order_id));
+        // Only once the amend is going ahead does a peg take its new limit
+        if let Some(peg) = self.pegs.get_mut(order_id) {
+            peg.limit_px = new_px;
+        }
+        let new_px = self.pegs.get(order_id).map_or(new_px, |peg| self.peg_price(peg));
+        if let Some(lifecycles) = self.lifecycles.as_mut().filter(|_| new_qty > 0) {
+            lifecycles.amend(order_id, new_qty);
+        }
+        let levels = self.levels_mut(side);
+        if new_qty <= 0 || (new_px == px_ticks && new_qty <= qty) {
+            levels.reduce(order_id, qty - new_qty.max(0));
+            if new_qty <= 0 {
+                self.cancelled(order_id, side, px_ticks);
+            } else {
+                self.next_seq();
+                self.touch(side, px_ticks);
             }
+            return Ok(Some(self.reprice(ts_ns)));
+        }
+        let Some(order) = levels.remove(order_id) else { return Ok(None) };
+        self.next_seq();
+        self.touch(side, px_ticks);
+        let mut trades = Vec::new();
+        self.limit(Order { px_ticks: new_px, qty: new_qty, ts_ns, ..order }, &mut trades);
+        self.settle(&mut trades, 0, ts_ns);
+        Ok(Some(trades))
+    }
+
+    /// Submits a stop (`kind` [`OrderKind::Market`]) or stop-limit
+    /// ([`OrderKind::Limit`], at the order's `px_ticks`) that waits in
+    /// [`stops`](Self::stops) until a trade prints at or through
+    /// `stop_px`. If the last trade already has, it is matched now and its
+    /// trades returned; otherwise it is held and this returns nothing.
+    /// Once set off, it takes the `ts_ns` of the trade that did so, for its
+    /// trades and for its place in the queue. Refused, with the book
+    /// untouched, unless it passes [`check_stop`](Self::check_stop) and its
+    /// owner isn't [throttled](Self::with_throttle).
+    pub fn submit_stop(&mut self, order: Order, stop_px: i64, kind: OrderKind) -> Result<Vec<Trade>, RejectReason> {
+        self.check_stop(&order, stop_px, kind)?;
+        self.admit(&order)?;
+        let stop = StopOrder { order, stop_px, kind };
+        self.next_seq();
+        self.opened(&stop.order);
+        if self.last_px.is_some_and(|px| stop.triggered_by(px, px)) {
+            let ts_ns = stop.order.ts_ns;
+            let mut trades = Vec::new();
+            self.trigger(stop, &mut trades);
+            self.settle(&mut trades, 0, ts_ns);
+            return Ok(trades);
+        }
+        self.stops.hold(stop);
+        Ok(Vec::new())
+    }
+
+    fn trigger(&mut self, stop: StopOrder, trades: &mut Vec<Trade>) {
+        match stop.kind {
+            OrderKind::Market => self.market(stop.order, trades),
+            OrderKind::Limit => self.limit(stop.order, trades),
+        }
+    }
+
+    /// Submits a pegged order (see [`pegs`]), priced off the best unpegged
+    /// bid and ask, with its `px_ticks` as its limit. It can trade on
+    /// arrival like a limit order at that price, and while it rests it is
+    /// moved whenever the best prices move, to the back of its new level.
+    /// Its limit is checked like a limit order's price.
+    pub fn submit_peg(&mut self, order: Order, kind: PegKind) -> Result<Vec<Trade>, RejectReason> {
+        self.check_order(&order, OrderKind::Limit)?;
+        self.admit(&order)?;
+        let (id, ts_ns) = (order.id, order.ts_ns);
+        let peg = Peg { kind, side: order.side, limit_px: order.px_ticks };
+        self.next_seq();
+        self.opened(&order);
+        let px_ticks = self.peg_price(&peg);
+        self.pegs.insert(id, peg);
+        let mut trades = Vec::new();
+        self.limit(Order { px_ticks, ..order }, &mut trades);
+        if !self.bids.contains(id) && !self.asks.contains(id) {
+            self.pegs.remove(id);
+        }
+        self.settle(&mut trades, 0, ts_ns);
+        Ok(trades)
+    }
+
+    /// Checks a quote `owner` would replace its last one with: the two
+    /// must make a quote, and each quoted side must pass
+    /// [`check_order`](Self::check_order), except that it may reuse an id
+    /// of the quote it replaces.
+    pub fn check_quote(&self, owner: &str, bid: &Order, ask: &Order) -> Result<(), QuoteError> {
+        quotes::check(bid, ask)?;
+        if bid.qty > 0 && ask.qty > 0 && bid.id == ask.id {
+            return Err(QuoteError::Rejected(RejectReason::DuplicateId(bid.id)));
         }
+        let last = self.quotes.get(owner).copied().unwrap_or_default();
+        for order in [bid, ask].into_iter().filter(|order| order.qty > 0) {
+            let replacing = [last.bid, last.ask].contains(&Some(order.id));
+            self.check_entry(order, OrderKind::Limit, replacing).map_err(QuoteError::Rejected)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces `owner`'s quote (see [`quotes`]) with `bid` and `ask`:
+    /// takes out what is left of its last one, as [`remove`](Self::remove)
+    /// does, then submits each side with quantity as a limit order, bid
+    /// first. Refused, with nothing changed, if it fails
+    /// [`check_quote`](Self::check_quote) or the bid's owner is
+    /// [throttled](Self::with_throttle); a quote spends one token.
+    pub fn submit_quote(&mut self, owner: &str, bid: Order, ask: Order) -> Result<QuoteReport, QuoteError> {
+        self.check_quote(owner, &bid, &ask)?;
+        self.admit(&bid).map_err(QuoteError::Rejected)?;
+        let quote = Quote { bid: (bid.qty > 0).then_some(bid.id), ask: (ask.qty > 0).then_some(ask.id) };
+        let last = self.quotes.get(owner).copied().unwrap_or_default();
+        let replaced = [last.bid, last.ask].into_iter().flatten().filter_map(|id| self.remove(id)).collect();
+        let bid = quote.bid.map(|_| self.enter_quoted(bid));
+        let ask = quote.ask.map(|_| self.enter_quoted(ask));
+        // Recorded once entered, as submitting forgets quoted ids
+        self.quotes.replace(owner, quote);
+        Ok(QuoteReport { replaced, bid, ask })
+    }
+
+    /// Enters one side of a quote already checked and admitted.
+    fn enter_quoted(&mut self, order: Order) -> ExecutionReport {
+        let (id, qty, tif) = (order.id, order.qty, order.tif);
+        let mut trades = Vec::new();
+        let resting = self.enter_limit(order, &mut trades);
+        ExecutionReport::new(id, qty, tif, resting, trades)
+    }
 
+    /// Moves every peg whose price changed, as the mutations here do
+    /// themselves. Call it after cancelling straight on [`bids`](Self::bids)
+    /// or [`asks`](Self::asks), or after [`expire`](Self::expire). Returns
+    /// the trades of pegs that crossed each other, stamped `ts_ns`.
+    pub fn reprice(&mut self, ts_ns: u128) -> Vec<Trade> {
+        let mut trades = Vec::new();
+        self.settle(&mut trades, 0, ts_ns);
         trades
     }
 
-    /// Returns current best bid price (highest buy price).
+    /// Where `peg` rests given the book as it is.
+    fn peg_price(&self, peg: &Peg) -> i64 {
+        peg.price(self.unpegged_best(Side::Bid), self.unpegged_best(Side::Ask))
+    }
+
+    /// Best displayed price on `side` with a live order that isn't pegged.
+    fn unpegged_best(&self, side: Side) -> Option<i64> {
+        self.levels(side).live_orders().find(|order| !order.hidden && !self.pegs.contains(order.id)).map(|order| order.px_ticks)
+    }
+
+    /// Follows a mutation that made `trades[from..]`: matches the stops
+    /// they set off, then reprices pegs, and repeats while either trades,
+    /// appending to `trades`. Pegs that move are stamped `ts_ns`.
+    /// Listeners then hear of the levels the mutation changed.
+    fn settle(&mut self, trades: &mut Vec<Trade>, from: usize, ts_ns: u128) {
+        self.fire_stops(trades, from);
+        loop {
+            let from = trades.len();
+            self.reprice_pegs(trades, ts_ns);
+            if trades.len() == from {
+                break;
+            }
+            self.fire_stops(trades, from);
+        }
+        self.publish_levels();
+    }
+
+    /// Notes a change to the level at `px_ticks` for the listeners.
+    fn touch(&mut self, side: Side, px_ticks: i64) {
+        if !self.listeners.is_empty() {
+            self.touched.push((side, px_ticks));
+        }
+    }
+
+    /// Starts tracking a submitted order's lifecycle, if the book does.
+    /// Whatever a peg or quote of the same id left behind is forgotten, so
+    /// a reused id is never taken for an order it no longer names.
+    fn opened(&mut self, order: &Order) {
+        self.pegs.remove(order.id);
+        if !self.quotes.is_empty() {
+            self.quotes.forget(order.id);
+        }
+        if let Some(lifecycles) = &mut self.lifecycles {
+            lifecycles.open(order);
+        }
+    }
+
+    /// Ends a tracked order's lifecycle as `state`.
+    fn ended(&mut self, id: OrderId, state: OrderState) {
+        if let Some(lifecycles) = &mut self.lifecycles {
+            lifecycles.end(id, state);
+        }
+    }
+
+    /// Numbers a cancelled order, forgets it if it was pegged, and tells
+    /// the listeners of it and of its level.
+    fn cancelled(&mut self, id: OrderId, side: Side, px_ticks: i64) {
+        self.next_seq();
+        self.pegs.remove(id);
+        self.ended(id, OrderState::Canceled);
+        for listener in &mut self.listeners {
+            listener.on_cancel(id, side);
+        }
+        self.touch(side, px_ticks);
+    }
+
+    /// Gives the listeners the displayed quantity at each level touched
+    /// since the last call, once per level. Every mutation ends here, so
+    /// this is also where the `debug-invariants` feature checks the book.
+    fn publish_levels(&mut self) {
+        #[cfg(feature = "debug-invariants")]
+        debug_assert_eq!(self.check_invariants(), Ok(()));
+        if self.touched.is_empty() {
+            return;
+        }
+        let mut touched = std::mem::take(&mut self.touched);
+        touched.sort_unstable_by_key(|&(side, px_ticks)| (side == Side::Ask, px_ticks));
+        touched.dedup();
+        for &(side, px_ticks) in &touched {
+            let qty = self.levels(side).qty_at_price(px_ticks);
+            for listener in &mut self.listeners {
+                listener.on_level_change(side, px_ticks, qty);
+            }
+        }
+        touched.clear();
+        self.touched = touched;
+    }
+
+    /// Moves each resting peg whose price changed to the back of its new
+    /// level, where it may cross a peg on the other side, and forgets pegs
+    /// no longer resting.
+    fn reprice_pegs(&mut self, trades: &mut Vec<Trade>, ts_ns: u128) {
+        if self.pegs.is_empty() {
+            return;
+        }
+        let (bid, ask) = (self.unpegged_best(Side::Bid), self.unpegged_best(Side::Ask));
+        let pegs: Vec<(OrderId, Peg)> = self.pegs.iter().map(|(id, peg)| (id, *peg)).collect();
+        for (id, peg) in pegs {
+            let levels = self.levels_mut(peg.side);
+            let Some(resting) = levels.get(id) else {
+                self.pegs.remove(id);
+                continue;
+            };
+            let px_ticks = peg.price(bid, ask);
+            if resting.px_ticks == px_ticks {
+                continue;
+            }
+            let order = levels.remove(id).expect("resting");
+            self.touch(peg.side, order.px_ticks);
+            self.limit(Order { px_ticks, ts_ns, ..order }, trades);
+        }
+    }
+
+    /// Matches the stops that `trades[from..]` set off, then those their
+    /// trades set off, until a round triggers none, appending every new
+    /// trade.
+    fn fire_stops(&mut self, trades: &mut Vec<Trade>, mut from: usize) {
+        while from < trades.len() && !self.stops.is_empty() {
+            let prints = trades[from..].iter().map(|t| t.px_ticks);
+            let (low, high) = prints.fold((i64::MAX, i64::MIN), |(lo, hi), px| (lo.min(px), hi.max(px)));
+            let ts_ns = trades[trades.len() - 1].ts_ns;
+            from = trades.len();
+            for mut stop in self.stops.triggered(low, high) {
+                stop.order.ts_ns = ts_ns;
+                self.trigger(stop, trades);
+            }
+        }
+    }
+
+    /// False for a `FOK` order that the opposite side can't fill in full
+    /// down to `limit_px`; true for anything else.
+    fn fok_fillable(&self, taker: &Order, limit_px: i64) -> bool {
+        if taker.tif != TimeInForce::FOK {
+            return true;
+        }
+        self.levels(taker.side.opposite()).fillable_qty(limit_px, taker.qty) >= taker.qty
+    }
+
+    /// Matches `taker` against the opposite side down to `limit_px`,
+    /// leaving its unfilled quantity in `taker.qty`. A taker with a
+    /// `min_qty` passes over fills smaller than that (a `FOK` taker has no
+    /// use for one, and ignores it). Appends its trades to `trades`.
+    fn take(&mut self, taker: &mut Order, limit_px: i64, trades: &mut Vec<Trade>) {
+        let from = trades.len();
+        let ts_ns = taker.ts_ns;
+        let min_qty = taker.min_qty.filter(|_| taker.tif != TimeInForce::FOK);
+
+        // Bids match against asks (sell orders), asks against bids. The
+        // makers are borrowed as a field, not through `levels_mut`, so the
+        // loop can number trades and tell listeners as it goes
+        let maker_side = taker.side.opposite();
+        let makers = match maker_side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+
+        while taker.qty > 0 {
+            // None once the best live maker no longer crosses the limit
+            let fill = match min_qty {
+                Some(min_qty) => makers.fill_best_min(limit_px, taker.qty, min_qty),
+                None => makers.fill_best(limit_px, taker.qty),
+            };
+            let Some((maker, px_ticks, fill)) = fill else {
+                break;
+            };
+            taker.qty -= fill;
+            if let Some(lifecycles) = &mut self.lifecycles {
+                lifecycles.fill(maker, fill);
+                lifecycles.fill(taker.id, fill);
+            }
+
+            self.seq += 1;
+            let trade = Trade {
+                maker,
+                taker: taker.id,
+                symbol: taker.symbol,
+                px_ticks, // Trade at maker's price
+                qty: fill,
+                ts_ns,
+                seq: self.seq,
+            };
+            if !self.listeners.is_empty() {
+                for listener in &mut self.listeners {
+                    listener.on_trade(&trade);
+                }
+                self.touched.push((maker_side, px_ticks));
+            }
+            trades.push(trade);
+        }
+
+        if trades.len() > from {
+            self.last_px = trades.last().map(|last| last.px_ticks);
+        }
+    }
+
+    /// The resting orders of `side`: [`bids`](Self::bids) or
+    /// [`asks`](Self::asks).
+    pub fn levels(&self, side: Side) -> &PriceLevels {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    /// [`levels`](Self::levels), mutably.
+    pub fn levels_mut(&mut self, side: Side) -> &mut PriceLevels {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    /// Whether order `id` is resting on either side or held as a stop. An
+    /// id is free again once its order fills, is cancelled or expires.
+    pub fn is_live(&self, id: OrderId) -> bool {
+        self.side_of(id).is_some() || self.stops.contains(id)
+    }
+
+    /// Side a live order rests on, from the sides' id indexes, so without
+    /// searching either book.
+    pub fn side_of(&self, id: OrderId) -> Option<Side> {
+        if self.bids.price_of(id).is_some() {
+            Some(Side::Bid)
+        } else if self.asks.price_of(id).is_some() {
+            Some(Side::Ask)
+        } else {
+            None
+        }
+    }
+
+    /// Cancels resting order `id` as [`PriceLevels::cancel`] does (lazily,
+    /// unless the book was built `with_eager_cancel`), finding its side and
+    /// price through [`side_of`](Self::side_of). False
+    /// if it isn't resting, including ids never seen, which leave nothing
+    /// behind. Unlike cancelling on [`bids`](Self::bids) or
+    /// [`asks`](Self::asks), the listeners hear of it; pegs still wait for
+    /// [`reprice`](Self::reprice).
+    pub fn cancel(&mut self, id: OrderId) -> bool {
+        let Some(side) = self.side_of(id) else {
+            return false;
+        };
+        let levels = self.levels_mut(side);
+        let px_ticks = levels.price_of(id).expect("indexed");
+        levels.cancel(id);
+        self.cancelled(id, side, px_ticks);
+        self.publish_levels();
+        true
+    }
+
+    /// Cancels held stop `id`, returning it if it was still waiting. Unlike
+    /// cancelling on [`stops`](Self::stops), this numbers the cancel.
+    pub fn cancel_stop(&mut self, id: OrderId) -> Option<StopOrder> {
+        let stop = self.stops.cancel(id)?;
+        self.next_seq();
+        self.ended(id, OrderState::Canceled);
+        Some(stop)
+    }
+
+    /// Takes resting order `id` out of the book now, rather than lazily
+    /// like [`cancel`](Self::cancel), and returns it with the quantity it
+    /// had left, an iceberg's reserve included. None if it isn't resting.
+    /// Listeners hear of it as a cancel.
+    pub fn remove(&mut self, id: OrderId) -> Option<Order> {
+        let side = self.side_of(id)?;
+        let order = self.levels_mut(side).remove(id)?;
+        self.cancelled(id, side, order.px_ticks);
+        self.publish_levels();
+        Some(order)
+    }
+
+    /// Applies one journaled event (see [`codec::BookEvent`]) as the
+    /// matching call would, returning the trades it printed. A submission
+    /// or amend the book refuses, and a cancel, amend or expiry of an
+    /// order that isn't resting or held, does nothing. A quote's trades
+    /// are its bid's, then its ask's.
+    pub fn apply(&mut self, event: BookEvent) -> Vec<Trade> {
+        match event {
+            BookEvent::Submit(order) => self.submit_limit(order).map(|report| report.trades).unwrap_or_default(),
+            BookEvent::Cancel { id, .. } => {
+                self.cancel(id);
+                Vec::new()
+            }
+            BookEvent::Modify { id, px_ticks, qty, ts_ns, .. } => self.modify(id, px_ticks, qty, ts_ns).ok().flatten().unwrap_or_default(),
+            BookEvent::Expire { now_ns } => {
+                self.expire(now_ns);
+                Vec::new()
+            }
+            BookEvent::Market(order) => self.submit_market(order).unwrap_or_default(),
+            BookEvent::Stop { order, stop_px, kind } => self.submit_stop(order, stop_px, kind).unwrap_or_default(),
+            BookEvent::CancelStop { id } => {
+                self.cancel_stop(id);
+                Vec::new()
+            }
+            BookEvent::Peg { order, kind } => self.submit_peg(order, kind).unwrap_or_default(),
+            BookEvent::Quote { owner, bid, ask } => self
+                .submit_quote(&owner, bid, ask)
+                .map(|report| report.bid.into_iter().chain(report.ask).flat_map(|side| side.trades).collect())
+                .unwrap_or_default(),
+            BookEvent::Reprice { ts_ns } => self.reprice(ts_ns),
+        }
+    }
+
+    /// Checks both sides against their queues, and that no bid rests at
+    /// or above an ask unless a `min_qty` let it (see [`invariants`]).
+    /// Walks every resting order, so it is meant for tests and fuzzing.
+    pub fn check_invariants(&self) -> Result<(), Violation> {
+        self.bids.check_invariants()?;
+        self.asks.check_invariants()?;
+        let (Some(bid_px), Some(ask_px)) = (
+            self.bids.live_orders().map(|order| order.px_ticks).max(),
+            self.asks.live_orders().map(|order| order.px_ticks).min(),
+        ) else {
+            return Ok(());
+        };
+        if bid_px < ask_px {
+            return Ok(());
+        }
+        let asks: Vec<&Order> = self.asks.live_orders().filter(|ask| ask.px_ticks <= bid_px).collect();
+        for bid in self.bids.live_orders().filter(|bid| bid.px_ticks >= ask_px) {
+            let crossed = asks.iter().find(|ask| ask.px_ticks <= bid.px_ticks && ask.min_qty.is_none() && bid.min_qty.is_none());
+            if let Some(ask) = crossed {
+                return Err(Violation::Crossed { bid: bid.id, bid_px: bid.px_ticks, ask: ask.id, ask_px: ask.px_ticks });
+            }
+        }
+        Ok(())
+    }
+
+    /// Cancels resting orders whose `expires_ns` has passed by `now_ns`
+    /// and returns their ids, bids first, so the caller can report each as
+    /// canceled.
+    pub fn expire(&mut self, now_ns: u128) -> Vec<OrderId> {
+        let mut expired = self.expire_side(Side::Bid, now_ns);
+        expired.extend(self.expire_side(Side::Ask, now_ns));
+        expired
+    }
+
+    /// [`expire`](Self::expire) for one side only.
+    pub fn expire_side(&mut self, side: Side, now_ns: u128) -> Vec<OrderId> {
+        let expired = self.levels_mut(side).expire_priced(now_ns);
+        for &(id, px_ticks) in &expired {
+            // Ended first, so the cancel doesn't count as a client's
+            self.ended(id, OrderState::Expired);
+            self.cancelled(id, side, px_ticks);
+        }
+        self.publish_levels();
+        expired.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Cancels every resting order on both sides, bids first, numbering
+    /// each and telling the listeners as [`cancel`](Self::cancel) does.
+    /// Held stops are left waiting. Returns the cancelled ids.
+    pub fn cancel_all(&mut self) -> Vec<OrderId> {
+        self.cancel_by_price_range(i64::MIN, i64::MAX)
+    }
+
+    /// [`cancel_all`](Self::cancel_all) for one side only.
+    pub fn cancel_side(&mut self, side: Side) -> Vec<OrderId> {
+        self.cancel_side_by_price_range(side, i64::MIN, i64::MAX)
+    }
+
+    /// [`cancel_all`](Self::cancel_all) for orders priced from `lo` to
+    /// `hi` inclusive.
+    pub fn cancel_by_price_range(&mut self, lo: i64, hi: i64) -> Vec<OrderId> {
+        let mut cancelled = self.cancel_side_by_price_range(Side::Bid, lo, hi);
+        cancelled.extend(self.cancel_side_by_price_range(Side::Ask, lo, hi));
+        cancelled
+    }
+
+    /// [`cancel_by_price_range`](Self::cancel_by_price_range) for one side
+    /// only, in matching priority.
+    pub fn cancel_side_by_price_range(&mut self, side: Side, lo: i64, hi: i64) -> Vec<OrderId> {
+        let cancelled = self.levels_mut(side).cancel_priced(lo, hi);
+        for &(id, px_ticks) in &cancelled {
+            self.cancelled(id, side, px_ticks);
+        }
+        self.publish_levels();
+        cancelled.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Cancels every resting order `owner` has, bids first, numbering each
+    /// and telling the listeners as [`cancel`](Self::cancel) does. Held
+    /// stops are left waiting. Returns the cancelled ids.
+    pub fn cancel_all_for(&mut self, owner: ParticipantId) -> Vec<OrderId> {
+        let mut cancelled = Vec::new();
+        for side in [Side::Bid, Side::Ask] {
+            let priced = self.levels_mut(side).cancel_owned_priced(owner);
+            for &(id, px_ticks) in &priced {
+                self.cancelled(id, side, px_ticks);
+            }
+            cancelled.extend(priced.into_iter().map(|(id, _)| id));
+        }
+        self.publish_levels();
+        cancelled
+    }
+
+    /// Who owns a resting order or held stop, if the book has it.
+    pub fn owner_of(&self, id: OrderId) -> Option<ParticipantId> {
+        let order = self.bids.get(id).or_else(|| self.asks.get(id));
+        order.or_else(|| self.stops.get(id).map(|stop| &stop.order)).map(|order| order.owner)
+    }
+
+    /// Resting orders `owner` has, bids then asks, each side by id.
+    pub fn orders_of(&self, owner: ParticipantId) -> impl Iterator<Item = &Order> + '_ {
+        self.bids.orders_of(owner).chain(self.asks.orders_of(owner))
+    }
+
+    /// Earliest expiry either side tracks; nothing expires before it.
+    pub fn next_expiry(&self) -> Option<u128> {
+        match (self.bids.next_expiry(), self.asks.next_expiry()) {
+            (Some(bid), Some(ask)) => Some(bid.min(ask)),
+            (bid, ask) => bid.or(ask),
+        }
+    }
+
+    /// The top `levels` displayed price levels of each side, best first,
+    /// with their quantity and live order count. Cancelled orders and
+    /// hidden ones don't count, and levels left with neither are skipped.
+    pub fn depth(&self, levels: usize) -> Depth {
+        Depth { bids: self.bids.depth(levels), asks: self.asks.depth(levels), seq: self.seq }
+    }
+
+    /// Checksum of the top `levels` displayed levels of each side, for
+    /// clients to check the books they keep against (see
+    /// [`Depth::checksum`]).
+    pub fn book_checksum(&self, levels: usize) -> u32 {
+        self.depth(levels).checksum()
+    }
+
+    /// Number of the book's last event. Every order taken in, amend,
+    /// cancel (expiries included) and trade is numbered, from 1, in the
+    /// order the book makes them: a submission first, then each of its
+    /// trades. Trades carry their number and [`depth`](Self::depth) the
+    /// number it is current to, so consumers of several streams off one
+    /// book can merge them in order and tell a missed event from a quiet
+    /// book. Cancelling straight on [`bids`](Self::bids),
+    /// [`asks`](Self::asks) or [`stops`](Self::stops) isn't numbered.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    fn next_seq(&mut self) {
+        self.seq += 1;
+    }
+
+    /// Returns current best bid price (highest buy price). Hidden orders
+    /// don't count.
     pub fn best_bid(&self) -> Option<i64> {
         self.bids.best_price()
     }
     
-    /// Returns current best ask price (lowest sell price).
+    /// Returns current best ask price (lowest sell price). Hidden orders
+    /// don't count.
     pub fn best_ask(&self) -> Option<i64> {
         self.asks.best_price()
     }
 }
 
+/// Decodes a book's config, refusing one [`SymbolConfig::check`] would
+/// divide by zero with.
+fn checked_config<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<SymbolConfig, D::Error> {
+    let config = SymbolConfig::deserialize(deserializer)?;
+    config.validate().map_err(serde::de::Error::custom)?;
+    Ok(config)
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod ob_tests {
     use super::*;
     use crate::types::{Order, OrderId, Side};
 
+    /// A day order for AAPL with no owner, stamped with its id.
+    fn order(id: u128, side: Side, px_ticks: i64, qty: i64) -> Order {
+        Order {
+            id: OrderId(id),
+            symbol: "AAPL".into(),
+            side,
+            px_ticks,
+            qty,
+            ts_ns: id,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }
+    }
+
     /// Tests crossing orders with partial fills - verifies price-time priority.
     #[test]
     fn crossing_and_partials() {
@@ -154,7 +1072,13 @@ mod ob_tests {
             px_ticks: 100,
             qty: 50,
             ts_ns: 1, // Earlier = higher priority
-        });
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }).unwrap();
         ob.submit_limit(Order {
             id: OrderId(2),
             symbol: "AAPL".into(),
@@ -162,7 +1086,13 @@ mod ob_tests {
             px_ticks: 100,
             qty: 40,
             ts_ns: 2, // Later = lower priority
-        });
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }).unwrap();
 
         // Crossing bid fills 50 from order 1, then 20 from order 2
         let trades = ob.submit_limit(Order {
@@ -172,7 +1102,13 @@ mod ob_tests {
             px_ticks: 100,
             qty: 70, // Will partially fill order 2
             ts_ns: 3,
-        });
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }).unwrap().trades;
 
         assert_eq!(trades.len(), 2);
         assert_eq!(trades[0].maker, OrderId(1));
@@ -197,7 +1133,13 @@ mod ob_tests {
             px_ticks: 105,
             qty: 10,
             ts_ns: 1,
-        });
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }).unwrap();
         
         // Bid doesn't cross (104 < 105)
         let trades = ob.submit_limit(Order {
@@ -207,10 +1149,919 @@ mod ob_tests {
             px_ticks: 104,
             qty: 10,
             ts_ns: 2,
-        });
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }).unwrap().trades;
         
         assert!(trades.is_empty());
         assert_eq!(ob.best_bid(), Some(104));
         assert_eq!(ob.best_ask(), Some(105));
     }
+
+    /// A level holding only canceled orders must not be treated as the best price.
+    #[test]
+    fn canceled_level_does_not_set_trade_price() {
+        let mut ob = OrderBook::new();
+
+        for (id, px) in [(1, 95), (2, 96)] {
+            ob.submit_limit(Order {
+                id: OrderId(id),
+                symbol: "AAPL".into(),
+                side: Side::Bid,
+                px_ticks: px,
+                qty: 10,
+                ts_ns: id,
+                tif: TimeInForce::Day,
+                expires_ns: None,
+                display_qty: None,
+                min_qty: None,
+                hidden: false,
+                owner: ParticipantId::NONE,
+            }).unwrap();
+        }
+        ob.bids.cancel(OrderId(2));
+
+        // Ask at 96 must not cross the live bid at 95
+        let trades = ob.submit_limit(Order {
+            id: OrderId(3),
+            symbol: "AAPL".into(),
+            side: Side::Ask,
+            px_ticks: 96,
+            qty: 10,
+            ts_ns: 3,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }).unwrap().trades;
+        assert!(trades.is_empty());
+
+        // Ask at 95 trades at the live maker's price, not the canceled 96
+        let trades = ob.submit_limit(Order {
+            id: OrderId(4),
+            symbol: "AAPL".into(),
+            side: Side::Ask,
+            px_ticks: 95,
+            qty: 10,
+            ts_ns: 4,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        }).unwrap().trades;
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].maker, OrderId(1));
+        assert_eq!(trades[0].px_ticks, 95);
+    }
+
+    /// A market order walks every ask level and never rests its remainder.
+    #[test]
+    fn market_order_sweeps_and_cancels_remainder() {
+        let mut ob = OrderBook::new();
+        ob.submit_limit(order(1, Side::Ask, 100, 10)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 150, 10)).unwrap();
+        ob.submit_limit(order(3, Side::Bid, 90, 10)).unwrap();
+
+        // Price is ignored: a bid "at 1" still lifts both levels
+        let trades = ob.submit_market(order(4, Side::Bid, 1, 25)).unwrap();
+        let fills: Vec<_> = trades.iter().map(|t| (t.maker, t.px_ticks, t.qty)).collect();
+        assert_eq!(fills, [(OrderId(1), 100, 10), (OrderId(2), 150, 10)]);
+        assert_eq!(ob.best_ask(), None);
+        assert_eq!(ob.best_bid(), Some(90));
+        assert!(!ob.bids.contains(OrderId(4)));
+
+        let trades = ob.submit_market(order(5, Side::Ask, i64::MAX, 4)).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!((trades[0].maker, trades[0].qty), (OrderId(3), 4));
+        assert_eq!(ob.bids.qty_at_price(90), 6);
+    }
+
+    /// IOC cancels what it can't fill; FOK fills completely or not at all.
+    #[test]
+    fn ioc_and_fok_never_rest() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks, qty, tif| Order { tif, ..order(id, side, px_ticks, qty) };
+        ob.submit_limit(order(1, Side::Ask, 100, 10, TimeInForce::Day)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 101, 10, TimeInForce::Day)).unwrap();
+        ob.asks.cancel(OrderId(2));
+        ob.submit_limit(order(3, Side::Ask, 102, 10, TimeInForce::Day)).unwrap();
+
+        // 20 only fills within 102, and the canceled 101 doesn't count
+        assert!(ob.submit_limit(order(4, Side::Bid, 101, 20, TimeInForce::FOK)).unwrap().trades.is_empty());
+        assert_eq!(ob.asks.qty_at_price(100), 10);
+        assert!(!ob.bids.contains(OrderId(4)));
+        let trades = ob.submit_limit(order(5, Side::Bid, 102, 20, TimeInForce::FOK)).unwrap().trades;
+        assert_eq!(trades.iter().map(|t| t.qty).sum::<i64>(), 20);
+
+        ob.submit_limit(order(6, Side::Ask, 103, 5, TimeInForce::Day)).unwrap();
+        let trades = ob.submit_limit(order(7, Side::Bid, 103, 8, TimeInForce::IOC)).unwrap().trades;
+        assert_eq!(trades.len(), 1);
+        assert_eq!(ob.best_bid(), None);
+        assert_eq!(ob.best_ask(), None);
+    }
+
+    /// Good-til-date orders leave the book once their expiry passes, and
+    /// only live ones are reported.
+    #[test]
+    fn expire_cancels_orders_past_their_date() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks, expires_ns| Order { expires_ns, ..order(id, side, px_ticks, 10) };
+        ob.submit_limit(order(1, Side::Bid, 99, Some(100))).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 101, Some(50))).unwrap();
+        ob.submit_limit(order(3, Side::Ask, 102, Some(100))).unwrap();
+        ob.submit_limit(order(4, Side::Bid, 98, None)).unwrap();
+        ob.submit_limit(order(5, Side::Ask, 103, None)).unwrap();
+        ob.asks.cancel(OrderId(3));
+
+        assert_eq!(ob.expire(49), []);
+        assert_eq!(ob.expire(50), [OrderId(2)]);
+        assert_eq!(ob.asks.best_live_price(), Some(103));
+        // Order 3 was already canceled, so it isn't expired again
+        assert_eq!(ob.expire(1_000), [OrderId(1)]);
+        assert_eq!(ob.bids.best_live_price(), Some(98));
+        assert_eq!(ob.expire(u128::MAX), []);
+    }
+
+    /// An order reusing the id of a cancelled good-til-date order keeps
+    /// its own expiry, not the one its id had before.
+    #[test]
+    fn expire_spares_an_order_reusing_a_cancelled_id() {
+        let mut ob = OrderBook::new();
+        ob.submit_limit(Order { expires_ns: Some(50), ..order(1, Side::Bid, 99, 10) }).unwrap();
+        assert!(ob.cancel(OrderId(1)));
+        ob.submit_limit(order(1, Side::Bid, 99, 10)).unwrap();
+        ob.submit_limit(Order { expires_ns: Some(80), ..order(2, Side::Bid, 98, 10) }).unwrap();
+        assert!(ob.cancel(OrderId(2)));
+        ob.submit_limit(Order { expires_ns: Some(90), ..order(2, Side::Bid, 98, 10) }).unwrap();
+
+        assert_eq!(ob.expire(80), []);
+        assert!(ob.is_live(OrderId(1)));
+        assert_eq!(ob.expire(90), [OrderId(2)]);
+    }
+
+    /// Mass cancels report what they took in matching priority, number
+    /// each cancel, and leave no tombstones or held stops behind.
+    #[test]
+    fn mass_cancels_pull_resting_orders() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks, hidden| Order { hidden, ..order(id, side, px_ticks, 10) };
+        ob.submit_limit(order(1, Side::Bid, 98, false)).unwrap();
+        ob.submit_limit(order(2, Side::Bid, 99, false)).unwrap();
+        ob.submit_limit(order(3, Side::Bid, 99, true)).unwrap();
+        ob.submit_limit(order(4, Side::Ask, 101, false)).unwrap();
+        ob.submit_limit(order(5, Side::Ask, 103, false)).unwrap();
+        ob.submit_stop(order(6, Side::Bid, 105, false), 104, OrderKind::Limit).unwrap();
+
+        let seq = ob.seq();
+        assert_eq!(ob.cancel_by_price_range(99, 101), [OrderId(2), OrderId(3), OrderId(4)]);
+        assert_eq!(ob.seq(), seq + 3);
+        assert_eq!((ob.best_bid(), ob.best_ask()), (Some(98), Some(103)));
+        assert_eq!(ob.cancel_side(Side::Ask), [OrderId(5)]);
+        assert_eq!(ob.cancel_all(), [OrderId(1)]);
+        assert_eq!(ob.cancel_all(), []);
+        assert_eq!(ob.bids.tombstone_count() + ob.asks.tombstone_count(), 0);
+        assert!(ob.stops.contains(OrderId(6)));
+    }
+
+    /// Each owner sees and cancels only its own resting orders, whether
+    /// they rest, fill or are cancelled by id in between.
+    #[test]
+    fn owners_cancel_only_their_own_orders() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks, owner| Order { owner: ParticipantId(owner), ..order(id, side, px_ticks, 10) };
+        ob.submit_limit(order(1, Side::Bid, 99, 1)).unwrap();
+        ob.submit_limit(order(2, Side::Bid, 98, 2)).unwrap();
+        ob.submit_limit(order(3, Side::Ask, 101, 1)).unwrap();
+        ob.submit_limit(order(4, Side::Ask, 102, 1)).unwrap();
+        ob.submit_limit(order(5, Side::Ask, 103, 0)).unwrap();
+        assert!(ob.cancel(OrderId(4)));
+        // Fills order 3 away
+        ob.submit_limit(order(6, Side::Bid, 101, 2)).unwrap();
+
+        let ids = |ob: &OrderBook, owner| ob.orders_of(ParticipantId(owner)).map(|o| o.id).collect::<Vec<_>>();
+        assert_eq!(ids(&ob, 1), [OrderId(1)]);
+        assert_eq!(ids(&ob, 2), [OrderId(2)]);
+        assert_eq!(ids(&ob, 0), []);
+        assert_eq!(ob.owner_of(OrderId(2)), Some(ParticipantId(2)));
+        assert_eq!(ob.owner_of(OrderId(3)), None);
+
+        let seq = ob.seq();
+        assert_eq!(ob.cancel_all_for(ParticipantId(1)), [OrderId(1)]);
+        assert_eq!(ob.seq(), seq + 1);
+        assert_eq!(ob.cancel_all_for(ParticipantId(1)), []);
+        assert_eq!((ob.best_bid(), ob.best_ask()), (Some(98), Some(103)));
+        assert_eq!(ob.check_invariants(), Ok(()));
+    }
+
+    /// The book follows each order from submission to its end, as maker
+    /// or taker, and an amend moves its original quantity.
+    #[test]
+    fn order_status_follows_each_order_to_its_end() {
+        let mut ob = OrderBook::new().with_order_status(16);
+        let order = |id, side, px_ticks, qty, tif| Order { tif, owner: ParticipantId(1), ..order(id, side, px_ticks, qty) };
+        let status = |ob: &OrderBook, id| ob.order_status(OrderId(id)).map(|l| (l.orig_qty, l.filled_qty, l.state));
+        ob.submit_limit(order(1, Side::Ask, 101, 10, TimeInForce::Day)).unwrap();
+        ob.submit_limit(Order { expires_ns: Some(50), ..order(2, Side::Ask, 105, 10, TimeInForce::Day) }).unwrap();
+        ob.submit_limit(order(3, Side::Bid, 99, 10, TimeInForce::Day)).unwrap();
+        assert_eq!(status(&ob, 1), Some((10, 0, OrderState::New)));
+
+        ob.submit_limit(order(4, Side::Bid, 101, 4, TimeInForce::Day)).unwrap();
+        assert_eq!(status(&ob, 1), Some((10, 4, OrderState::PartiallyFilled)));
+        assert_eq!(status(&ob, 4), Some((4, 4, OrderState::Filled)));
+        // Takes the 6 left and cancels the rest
+        ob.submit_limit(order(5, Side::Bid, 101, 8, TimeInForce::IOC)).unwrap();
+        assert_eq!(status(&ob, 1), Some((10, 10, OrderState::Filled)));
+        assert_eq!(status(&ob, 5), Some((8, 6, OrderState::Canceled)));
+        assert_eq!(ob.order_status(OrderId(5)).unwrap().leaves_qty(), 0);
+
+        ob.modify(OrderId(3), 99, 15, 6).unwrap();
+        assert_eq!(status(&ob, 3), Some((15, 0, OrderState::New)));
+        ob.cancel(OrderId(3));
+        assert_eq!(status(&ob, 3), Some((15, 0, OrderState::Canceled)));
+        ob.expire(50);
+        assert_eq!(status(&ob, 2), Some((10, 0, OrderState::Expired)));
+        assert_eq!(ob.order_status(OrderId(9)), None);
+        let mut untracked = OrderBook::new();
+        untracked.submit_limit(order(6, Side::Bid, 99, 1, TimeInForce::Day)).unwrap();
+        assert_eq!(untracked.order_status(OrderId(6)), None);
+    }
+
+    /// Cancel finds an order's side itself, and an id it has never seen
+    /// leaves no mark for a later order of that id.
+    #[test]
+    fn cancel_finds_the_side_by_id() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks| order(id, side, px_ticks, 10);
+        ob.submit_limit(order(1, Side::Bid, 99)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 101)).unwrap();
+        assert_eq!(ob.side_of(OrderId(2)), Some(Side::Ask));
+
+        assert!(ob.cancel(OrderId(2)));
+        assert!(!ob.cancel(OrderId(2)));
+        assert_eq!(ob.side_of(OrderId(2)), None);
+        assert_eq!(ob.asks.best_live_price(), None);
+
+        assert!(!ob.cancel(OrderId(3)));
+        ob.submit_limit(order(3, Side::Bid, 100)).unwrap();
+        assert!(ob.bids.contains(OrderId(3)));
+        assert!(ob.cancel(OrderId(3)));
+        assert_eq!(ob.bids.best_live_price(), Some(99));
+    }
+
+    /// Removing hands back what was left of the order, reserve and all.
+    #[test]
+    fn remove_returns_the_unfilled_order() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, qty, display_qty| Order { display_qty, ..order(id, side, 100, qty) };
+        ob.submit_limit(order(1, Side::Ask, 30, Some(10))).unwrap();
+        ob.submit_limit(order(2, Side::Bid, 4, None)).unwrap();
+
+        let removed = ob.remove(OrderId(1)).unwrap();
+        assert_eq!((removed.side, removed.px_ticks, removed.qty), (Side::Ask, 100, 26));
+        assert_eq!(ob.asks.total_len(), 0);
+        assert_eq!(ob.asks.get_price_levels().len(), 0);
+        assert!(ob.remove(OrderId(1)).is_none());
+        assert!(!ob.cancel(OrderId(1)));
+    }
+
+    /// Depth sums each level's live displayed orders, best first.
+    #[test]
+    fn depth_aggregates_the_top_levels() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks, qty, hidden| Order { hidden, ..order(id, side, px_ticks, qty) };
+        ob.submit_limit(order(1, Side::Bid, 99, 10, false)).unwrap();
+        ob.submit_limit(order(2, Side::Bid, 99, 5, false)).unwrap();
+        ob.submit_limit(order(3, Side::Bid, 100, 7, false)).unwrap();
+        ob.submit_limit(order(4, Side::Bid, 101, 7, false)).unwrap();
+        ob.submit_limit(order(5, Side::Bid, 98, 1, false)).unwrap();
+        ob.submit_limit(order(6, Side::Ask, 102, 3, false)).unwrap();
+        ob.submit_limit(order(7, Side::Ask, 102, 9, true)).unwrap();
+        ob.cancel(OrderId(4));
+        ob.cancel(OrderId(2));
+
+        let level = |px_ticks, qty, orders| BookLevel { px_ticks, qty, orders };
+        let depth = ob.depth(2);
+        assert_eq!(depth.bids, [level(100, 7, 1), level(99, 10, 1)]);
+        assert_eq!(depth.asks, [level(102, 3, 1)]);
+    }
+
+    /// Compaction takes canceled orders out of the queues, by hand or at
+    /// the threshold, and leaves the live ones in priority order.
+    #[test]
+    fn compact_drops_canceled_orders() {
+        let order = |id, px_ticks, qty, hidden| Order { hidden, ..order(id, Side::Ask, px_ticks, qty) };
+        let mut ob = OrderBook::new();
+        for id in 1..=6 {
+            ob.submit_limit(order(id, 100 + (id as i64 % 2), 5, id == 6)).unwrap();
+        }
+        ob.cancel(OrderId(2));
+        ob.cancel(OrderId(4));
+        ob.cancel(OrderId(5));
+        ob.cancel(OrderId(6));
+        assert_eq!(ob.compact(), 4);
+        assert_eq!(ob.asks.tombstone_count(), 0);
+        assert_eq!(ob.asks.get_price_levels().map(|(px, _)| px).collect::<Vec<_>>(), [101]);
+        assert_eq!(ob.compact(), 0);
+        let ids: Vec<OrderId> = ob.asks.live_orders().map(|o| o.id).collect();
+        assert_eq!(ids, [OrderId(1), OrderId(3)]);
+
+        let mut ob = OrderBook::new().with_compact_threshold(2);
+        for id in 1..=3 {
+            ob.submit_limit(order(id, 100, 5, false)).unwrap();
+        }
+        ob.cancel(OrderId(1));
+        assert_eq!(ob.asks.tombstone_count(), 1);
+        ob.cancel(OrderId(2));
+        assert_eq!(ob.asks.tombstone_count(), 0);
+        assert_eq!(ob.asks.total_len(), 1);
+        assert_eq!(ob.depth(1).asks, [BookLevel { px_ticks: 100, qty: 5, orders: 1 }]);
+    }
+
+    /// A reloaded book keeps its tombstones, iceberg reserves, hidden
+    /// orders, stops and pegs, and trades exactly as the saved one does.
+    #[test]
+    fn book_reloads_from_json_and_bincode() {
+        let mut ob = OrderBook::new();
+        ob.submit_limit(order(1, Side::Ask, 101, 5)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 101, 4)).unwrap();
+        ob.submit_limit(Order { display_qty: Some(2), ..order(3, Side::Ask, 101, 7) }).unwrap();
+        ob.submit_limit(Order { hidden: true, ..order(4, Side::Ask, 100, 3) }).unwrap();
+        ob.submit_limit(Order { expires_ns: Some(50), ..order(5, Side::Ask, 102, 6) }).unwrap();
+        ob.submit_limit(order(6, Side::Bid, 97, 5)).unwrap();
+        ob.submit_peg(order(7, Side::Bid, 99, 2), PegKind::Primary).unwrap();
+        ob.submit_stop(order(8, Side::Bid, 103, 4), 102, OrderKind::Market).unwrap();
+        ob.submit_limit(order(9, Side::Bid, 101, 5)).unwrap();
+        ob.cancel(OrderId(2));
+        assert_eq!(ob.asks.tombstone_count(), 1);
+
+        let json: OrderBook = serde_json::from_str(&serde_json::to_string(&ob).unwrap()).unwrap();
+        let bin: OrderBook = bincode::deserialize(&bincode::serialize(&ob).unwrap()).unwrap();
+        for loaded in [json, bin] {
+            assert_eq!(loaded.check_invariants(), Ok(()));
+            assert_eq!(loaded.asks.tombstone_count(), 1);
+            assert_eq!(loaded.asks.hidden_qty(OrderId(3)), 5);
+            assert_eq!(loaded.depth(5), ob.depth(5));
+            assert_eq!(loaded.next_expiry(), ob.next_expiry());
+            assert!(loaded.stops.contains(OrderId(8)) && loaded.pegs.contains(OrderId(7)));
+        }
+
+        let mut loaded: OrderBook = serde_json::from_str(&serde_json::to_string(&ob).unwrap()).unwrap();
+        let flow = |book: &mut OrderBook| {
+            let expired = book.expire(50);
+            let mut trades = book.submit_limit(order(10, Side::Bid, 102, 12)).unwrap().trades;
+            trades.extend(book.submit_market(order(11, Side::Ask, 0, 20)).unwrap());
+            (expired, trades, book.depth(5))
+        };
+        assert_eq!(flow(&mut loaded), flow(&mut ob));
+    }
+
+    /// The checksum covers each side's top levels, interleaved from the
+    /// best, and leaves out hidden and cancelled orders.
+    #[test]
+    fn book_checksum_covers_the_top_levels() {
+        let mut ob = OrderBook::new();
+        assert_eq!(ob.book_checksum(10), 0);
+        ob.submit_limit(order(1, Side::Bid, 100, 5)).unwrap();
+        ob.submit_limit(order(2, Side::Bid, 99, 2)).unwrap();
+        ob.submit_limit(order(3, Side::Ask, 101, 3)).unwrap();
+        ob.submit_limit(Order { hidden: true, ..order(4, Side::Ask, 102, 9) }).unwrap();
+        ob.submit_limit(order(5, Side::Bid, 98, 1)).unwrap();
+        ob.cancel(OrderId(5));
+        // zlib.crc32(b"100:5:101:3:99:2")
+        assert_eq!(ob.book_checksum(10), 1_631_522_821);
+        // zlib.crc32(b"100:5:101:3")
+        assert_eq!(ob.book_checksum(1), 1_554_833_166);
+    }
+
+    /// Appending into a reused buffer leaves what it held alone and
+    /// produces the same trades, stops included, as `submit_limit`.
+    #[test]
+    fn submit_limit_into_appends_to_a_reused_buffer() {
+        let setup = |ob: &mut OrderBook| {
+            ob.submit_limit(order(1, Side::Ask, 100, 5)).unwrap();
+            ob.submit_limit(order(2, Side::Ask, 101, 5)).unwrap();
+            ob.submit_stop(order(3, Side::Bid, 101, 2), 100, OrderKind::Limit).unwrap();
+        };
+        let (mut a, mut b) = (OrderBook::new(), OrderBook::new());
+        setup(&mut a);
+        setup(&mut b);
+
+        let expected = a.submit_limit(order(4, Side::Bid, 100, 4)).unwrap();
+        let mut out = vec![expected.trades[0].clone()];
+        let resting = b.submit_limit_into(order(4, Side::Bid, 100, 4), &mut out);
+        assert!(!resting.unwrap());
+        assert_eq!(out[1..], expected.trades[..]);
+        assert_eq!(out.len(), 4, "the taker's fill and the stop's two");
+
+        // Cleared and reused, the buffer keeps its capacity
+        let capacity = out.capacity();
+        out.clear();
+        assert!(b.submit_limit_into(order(5, Side::Bid, 99, 3), &mut out).unwrap());
+        assert!(out.is_empty());
+        assert_eq!(out.capacity(), capacity);
+        assert_eq!(a.submit_limit(order(5, Side::Bid, 99, 3)).unwrap().status, OrderStatus::Rested);
+        assert_eq!(a.depth(5), b.depth(5));
+    }
+
+    /// Orders, amends, cancels and trades are numbered one after another,
+    /// a submission before its trades, and depth says where it stands.
+    #[test]
+    fn events_are_numbered_in_book_order() {
+        let mut ob = OrderBook::new();
+        ob.submit_limit(order(1, Side::Ask, 100, 5)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 101, 5)).unwrap();
+        let seqs = |trades: Vec<Trade>| trades.iter().map(|t| t.seq).collect::<Vec<_>>();
+        assert_eq!(seqs(ob.submit_limit(order(3, Side::Bid, 101, 7)).unwrap().trades), [4, 5]);
+        assert_eq!(ob.seq(), 5);
+
+        // A rejected FOK was still taken in; unknown ids change nothing
+        ob.submit_limit(Order { tif: TimeInForce::FOK, ..order(4, Side::Bid, 101, 50) }).unwrap();
+        assert!(!ob.cancel(OrderId(99)));
+        assert_eq!(ob.seq(), 6);
+
+        ob.submit_limit(order(5, Side::Bid, 95, 5)).unwrap();
+        assert_eq!(seqs(ob.modify(OrderId(5), 101, 5, 10).unwrap().unwrap()), [9]);
+        ob.submit_stop(order(6, Side::Bid, 110, 1), 120, OrderKind::Market).unwrap();
+        assert!(ob.cancel_stop(OrderId(6)).is_some());
+        ob.submit_limit(Order { expires_ns: Some(20), ..order(7, Side::Ask, 105, 1) }).unwrap();
+        assert_eq!(ob.expire(20), [OrderId(7)]);
+        assert_eq!(ob.depth(1).seq, 13);
+
+        // A reload numbers on from where the book was
+        let mut loaded: OrderBook = serde_json::from_str(&serde_json::to_string(&ob).unwrap()).unwrap();
+        loaded.submit_limit(order(8, Side::Bid, 90, 1)).unwrap();
+        assert_eq!(loaded.seq(), 14);
+    }
+
+    /// A cross is allowed only where a minimum passed a maker over, and a
+    /// queue written out of time order is caught.
+    #[test]
+    fn check_invariants_finds_crossed_and_misordered_books() {
+        let order = |id, side, px_ticks, qty, min_qty| Order { min_qty, ..order(id, side, px_ticks, qty) };
+        let mut ob = OrderBook::new();
+        ob.submit_limit(order(1, Side::Ask, 100, 2, None)).unwrap();
+        ob.submit_limit(order(2, Side::Bid, 101, 10, Some(5))).unwrap();
+        assert_eq!(ob.best_bid(), Some(101));
+        assert_eq!(ob.check_invariants(), Ok(()));
+
+        ob.asks.push(order(3, Side::Ask, 99, 1, None));
+        assert_eq!(ob.check_invariants(), Ok(()));
+        ob.bids.push(order(4, Side::Bid, 99, 1, None));
+        let crossed = Violation::Crossed { bid: OrderId(4), bid_px: 99, ask: OrderId(3), ask_px: 99 };
+        assert_eq!(ob.check_invariants(), Err(crossed));
+
+        let mut ob = OrderBook::new();
+        ob.bids.push(order(6, Side::Bid, 100, 1, None));
+        ob.bids.push(order(5, Side::Bid, 100, 1, None));
+        let misordered = Violation::OutOfTimeOrder { side: Side::Bid, px_ticks: 100, id: OrderId(5) };
+        assert_eq!(ob.check_invariants(), Err(misordered));
+    }
+
+    /// A trade through a stop's price matches it, and its own trades can
+    /// set off the next stop.
+    #[test]
+    fn stops_wait_for_a_trade_through_their_price() {
+        let mut ob = OrderBook::new();
+        ob.submit_limit(order(1, Side::Bid, 100, 5)).unwrap();
+        ob.submit_limit(order(2, Side::Bid, 98, 5)).unwrap();
+        ob.submit_limit(order(3, Side::Bid, 95, 5)).unwrap();
+
+        // A sell stop at 99, and a sell stop-limit at 96 limited to 97
+        assert!(ob.submit_stop(order(10, Side::Ask, 99, 5), 99, OrderKind::Market).unwrap().is_empty());
+        assert!(ob.submit_stop(order(11, Side::Ask, 97, 5), 96, OrderKind::Limit).unwrap().is_empty());
+        assert_eq!(ob.stops.len(), 2);
+
+        // 100 is above both stops, so they stay put
+        let trades = ob.submit_limit(order(4, Side::Ask, 100, 5)).unwrap().trades;
+        assert_eq!(trades.len(), 1);
+        assert_eq!(ob.stops.len(), 2);
+
+        // A print at 99 sets off the stop, which sells into 98
+        assert!(ob.submit_limit(order(5, Side::Ask, 99, 1)).unwrap().trades.is_empty());
+        let trades = ob.submit_limit(order(6, Side::Bid, 99, 1)).unwrap().trades;
+        let fills: Vec<_> = trades.iter().map(|t| (t.maker, t.taker, t.px_ticks)).collect();
+        assert_eq!(fills, [(OrderId(5), OrderId(6), 99), (OrderId(2), OrderId(10), 98)]);
+        assert!(ob.stops.contains(OrderId(11)));
+
+        // A print at 95 sets off the stop-limit, which can't sell below 97
+        // and rests there
+        let trades = ob.submit_limit(order(7, Side::Ask, 95, 2)).unwrap().trades;
+        assert_eq!(trades.len(), 1);
+        assert!(ob.stops.is_empty());
+        assert_eq!(ob.asks.best_live_price(), Some(97));
+        assert_eq!(ob.asks.qty_at_price(97), 5);
+
+        // With the last trade at 95, a buy stop at 90 triggers on arrival
+        let trades = ob.submit_stop(order(12, Side::Bid, 0, 5), 90, OrderKind::Market).unwrap();
+        assert_eq!(trades.iter().map(|t| (t.maker, t.qty)).collect::<Vec<_>>(), [(OrderId(11), 5)]);
+    }
+
+    /// Shrinking an order keeps its place; repricing or growing it sends
+    /// it to the back, and a reprice that crosses trades.
+    #[test]
+    fn modify_keeps_priority_only_on_size_reductions() {
+        let mut ob = OrderBook::new();
+        for id in 1..=3 {
+            ob.submit_limit(order(id, Side::Bid, 100, 10)).unwrap();
+        }
+        let queue = |ob: &OrderBook| ob.bids.live_orders().map(|o| (o.id.0, o.qty)).collect::<Vec<_>>();
+
+        assert_eq!(ob.modify(OrderId(1), 100, 4, 10), Ok(Some(vec![])));
+        assert_eq!(queue(&ob), [(1, 4), (2, 10), (3, 10)]);
+        assert_eq!(ob.modify(OrderId(1), 100, 6, 11), Ok(Some(vec![])));
+        assert_eq!(queue(&ob), [(2, 10), (3, 10), (1, 6)]);
+        assert_eq!(ob.bids.get(OrderId(1)).map(|o| o.ts_ns), Some(11));
+
+        ob.submit_limit(order(4, Side::Ask, 102, 5)).unwrap();
+        let trades = ob.modify(OrderId(2), 102, 8, 12).unwrap().unwrap();
+        assert_eq!(trades.iter().map(|t| (t.maker, t.taker, t.qty, t.ts_ns)).collect::<Vec<_>>(), [(OrderId(4), OrderId(2), 5, 12)]);
+        assert_eq!(ob.bids.best_live_price(), Some(102));
+        assert_eq!(ob.bids.qty_at_price(102), 3);
+
+        assert_eq!(ob.modify(OrderId(3), 100, 0, 13), Ok(Some(vec![])));
+        assert_eq!(ob.modify(OrderId(3), 100, 5, 14), Ok(None));
+        assert_eq!(queue(&ob), [(2, 3), (1, 6)]);
+    }
+
+    /// An amend is checked like a new order's terms, and one refused leaves
+    /// the order as it was.
+    #[test]
+    fn amends_are_checked_before_they_touch_the_order() {
+        let mut ob = OrderBook::new();
+        ob.submit_limit(order(1, Side::Bid, 100, 10)).unwrap();
+        assert_eq!(ob.modify(OrderId(1), -5, 10, 2), Err(RejectReason::InvalidPrice(-5)));
+        assert_eq!(ob.modify(OrderId(1), 0, 5, 2), Err(RejectReason::InvalidPrice(0)));
+
+        ob.set_config(SymbolConfig { tick_size: 5, lot_size: 5, ..SymbolConfig::default() }).unwrap();
+        assert_eq!(ob.modify(OrderId(1), 103, 10, 3), Err(RejectReason::OffTick { px_ticks: 103, tick_size: 5 }));
+        assert_eq!(ob.modify(OrderId(1), 105, 7, 3), Err(RejectReason::OffLot { qty: 7, lot_size: 5 }));
+        assert_eq!(ob.bids.live_orders().map(|o| (o.px_ticks, o.qty, o.ts_ns)).collect::<Vec<_>>(), [(100, 10, 1)]);
+        assert_eq!(ob.check_invariants(), Ok(()));
+
+        assert_eq!(ob.modify(OrderId(1), 105, 5, 4), Ok(Some(vec![])));
+        // Amending to nothing cancels, whatever the price
+        assert_eq!(ob.modify(OrderId(1), -5, 0, 5), Ok(Some(vec![])));
+        assert_eq!(ob.bids.total_len(), 0);
+    }
+
+    /// A minimum passes over small makers, then stops taking once the
+    /// order's remainder falls under it.
+    #[test]
+    fn min_qty_skips_fills_under_the_minimum() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks, qty, min_qty| Order { min_qty, ..order(id, side, px_ticks, qty) };
+        for (id, px, qty) in [(1, 100, 1), (2, 100, 30), (3, 101, 2), (4, 101, 40)] {
+            ob.submit_limit(order(id, Side::Ask, px, qty, None)).unwrap();
+        }
+
+        let trades = ob.submit_limit(order(5, Side::Bid, 101, 60, Some(20))).unwrap().trades;
+        assert_eq!(trades.iter().map(|t| (t.maker.0, t.px_ticks, t.qty)).collect::<Vec<_>>(), [(2, 100, 30), (4, 101, 30)]);
+        assert_eq!(ob.asks.qty_at_price(101), 12);
+
+        // Nothing left shows 20: an IOC cancels, a Day order rests
+        let ioc = Order { tif: TimeInForce::IOC, ..order(6, Side::Bid, 101, 50, Some(20)) };
+        assert!(ob.submit_limit(ioc).unwrap().trades.is_empty());
+        assert_eq!(ob.bids.total_len(), 0);
+        assert!(ob.submit_limit(order(7, Side::Bid, 101, 25, Some(20))).unwrap().trades.is_empty());
+        assert_eq!(ob.bids.best_live_price(), Some(101));
+    }
+
+    /// The report says whether the order rested, filled in part or whole,
+    /// or was cancelled or rejected for its time in force.
+    #[test]
+    fn submit_limit_reports_what_became_of_the_order() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks, qty, tif| Order { tif, ..order(id, side, px_ticks, qty) };
+        let report = ob.submit_limit(order(1, Side::Ask, 100, 4, TimeInForce::Day)).unwrap();
+        assert_eq!((report.status, report.filled_qty, report.remaining_qty, report.avg_px), (OrderStatus::Rested, 0, 4, None));
+        ob.submit_limit(order(2, Side::Ask, 101, 4, TimeInForce::Day)).unwrap();
+
+        let report = ob.submit_limit(order(3, Side::Bid, 101, 10, TimeInForce::Day)).unwrap();
+        assert_eq!((report.status, report.filled_qty, report.remaining_qty, report.avg_px), (OrderStatus::PartiallyFilled, 8, 2, Some(100.5)));
+        assert_eq!(report.trades.len(), 2);
+
+        let report = ob.submit_limit(order(4, Side::Ask, 101, 2, TimeInForce::Day)).unwrap();
+        assert_eq!((report.status, report.remaining_qty), (OrderStatus::Filled, 0));
+        let report = ob.submit_limit(order(5, Side::Bid, 100, 1, TimeInForce::IOC)).unwrap();
+        assert_eq!(report.status, OrderStatus::Cancelled);
+        let report = ob.submit_limit(order(6, Side::Bid, 100, 1, TimeInForce::FOK)).unwrap();
+        assert_eq!(report.status, OrderStatus::Rejected);
+    }
+
+    /// Hidden orders stay out of best prices and depth, and fill after the
+    /// displayed orders at their price.
+    #[test]
+    fn hidden_orders_match_unseen_behind_displayed_ones() {
+        let mut ob = OrderBook::new();
+        let order = |id, side, px_ticks, qty, hidden| Order { hidden, ..order(id, side, px_ticks, qty) };
+        ob.submit_limit(order(1, Side::Ask, 99, 5, true)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 100, 5, true)).unwrap();
+        ob.submit_limit(order(3, Side::Ask, 100, 5, false)).unwrap();
+        ob.submit_limit(order(4, Side::Ask, 101, 5, false)).unwrap();
+
+        // Out of best prices and depth, but still there to trade
+        assert_eq!(ob.best_ask(), Some(100));
+        assert_eq!(ob.asks.iter_levels_best_first().collect::<Vec<_>>(), [(100, 5), (101, 5)]);
+        assert_eq!(ob.asks.fillable_qty(100, i64::MAX), 15);
+        assert_eq!(ob.asks.total_len(), 4);
+
+        // The better hidden price first, then the displayed order at 100
+        // ahead of the hidden one that arrived before it
+        let trades = ob.submit_limit(order(5, Side::Bid, 101, 12, false)).unwrap().trades;
+        assert_eq!(trades.iter().map(|t| (t.maker.0, t.px_ticks, t.qty)).collect::<Vec<_>>(), [(1, 99, 5), (3, 100, 5), (2, 100, 2)]);
+        assert_eq!(ob.best_ask(), Some(101));
+
+        assert!(ob.asks.cancel(OrderId(2)));
+        assert_eq!(ob.asks.fillable_qty(100, i64::MAX), 0);
+    }
+
+    /// Listeners hear of trades, rests and cancels as they happen, then
+    /// of each level left changed.
+    #[test]
+    fn listeners_follow_every_change() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+        impl listener::BookListener for Recorder {
+            fn on_trade(&mut self, trade: &Trade) {
+                self.0.lock().unwrap().push(format!("trade {} {}@{}", trade.maker.0, trade.qty, trade.px_ticks));
+            }
+            fn on_rest(&mut self, order: &Order) {
+                self.0.lock().unwrap().push(format!("rest {}", order.id.0));
+            }
+            fn on_cancel(&mut self, id: OrderId, _side: Side) {
+                self.0.lock().unwrap().push(format!("cancel {}", id.0));
+            }
+            fn on_level_change(&mut self, side: Side, px_ticks: i64, qty: i64) {
+                self.0.lock().unwrap().push(format!("{:?} {} {}", side, px_ticks, qty));
+            }
+        }
+
+        let recorder = Recorder::default();
+        let mut ob = OrderBook::new().with_listener(recorder.clone());
+        let events = || std::mem::take(&mut *recorder.0.lock().unwrap());
+
+        ob.submit_limit(order(1, Side::Ask, 101, 5)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 102, 5)).unwrap();
+        assert_eq!(events(), ["rest 1", "Ask 101 5", "rest 2", "Ask 102 5"]);
+
+        // A sweep through both levels, its rest resting on the other side
+        ob.submit_limit(order(3, Side::Bid, 102, 12)).unwrap();
+        assert_eq!(events(), ["trade 1 5@101", "trade 2 5@102", "rest 3", "Bid 102 2", "Ask 101 0", "Ask 102 0"]);
+
+        assert!(ob.cancel(OrderId(3)));
+        assert!(!ob.cancel(OrderId(3)));
+        assert_eq!(events(), ["cancel 3", "Bid 102 0"]);
+    }
+
+    /// Pegs follow the unpegged best prices, and can meet each other.
+    #[test]
+    fn pegs_reprice_as_the_best_prices_move() {
+        let mut ob = OrderBook::new();
+        let fills = |trades: Vec<Trade>| trades.iter().map(|t| (t.maker.0, t.taker.0, t.px_ticks, t.qty, t.ts_ns)).collect::<Vec<_>>();
+        ob.submit_limit(order(1, Side::Bid, 100, 10)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 104, 10)).unwrap();
+
+        assert_eq!(ob.submit_peg(order(3, Side::Bid, 103, 10), PegKind::Midpoint).unwrap(), []);
+        assert_eq!(ob.bids.get(OrderId(3)).map(|o| o.px_ticks), Some(102));
+        assert_eq!(ob.submit_peg(order(4, Side::Ask, 103, 10), PegKind::Primary).unwrap(), []);
+        assert_eq!(ob.asks.get(OrderId(4)).map(|o| o.px_ticks), Some(104));
+
+        // A better bid moves the midpoint, up to the peg's limit
+        ob.submit_limit(order(6, Side::Bid, 102, 10)).unwrap();
+        assert_eq!(ob.bids.get(OrderId(3)).map(|o| (o.px_ticks, o.ts_ns)), Some((103, 6)));
+
+        assert_eq!(fills(ob.submit_peg(order(5, Side::Ask, 100, 4), PegKind::Midpoint).unwrap()), [(3, 5, 103, 4, 5)]);
+        // With no unpegged ask left, the primary peg falls back to its limit
+        ob.asks.cancel(OrderId(2));
+        assert_eq!(fills(ob.reprice(20)), [(3, 4, 103, 6, 20)]);
+        assert_eq!(ob.asks.get(OrderId(4)).map(|o| (o.px_ticks, o.qty)), Some((103, 4)));
+        assert_eq!(ob.pegs.len(), 1);
+    }
+
+    /// A cancelled peg is forgotten, so an order reusing its id rests at
+    /// its own price and stays there.
+    #[test]
+    fn a_reused_peg_id_rests_as_a_plain_order() {
+        let mut ob = OrderBook::new();
+        ob.submit_limit(order(1, Side::Bid, 100, 10)).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 110, 10)).unwrap();
+        ob.submit_peg(order(3, Side::Bid, 108, 10), PegKind::Midpoint).unwrap();
+        assert_eq!(ob.bids.price_of(OrderId(3)), Some(105));
+        assert!(ob.cancel(OrderId(3)));
+        assert!(ob.pegs.is_empty());
+
+        ob.submit_limit(order(3, Side::Bid, 90, 10)).unwrap();
+        assert_eq!(ob.bids.price_of(OrderId(3)), Some(90));
+        ob.submit_limit(order(4, Side::Bid, 104, 10)).unwrap();
+        assert_eq!(ob.bids.price_of(OrderId(3)), Some(90));
+    }
+
+    /// A new quote takes out what is left of the owner's last one before
+    /// entering, and a crossed one changes nothing.
+    #[test]
+    fn quotes_replace_the_owners_last_pair() {
+        let mut ob = OrderBook::new();
+        let report = ob.submit_quote("mm", order(1, Side::Bid, 99, 10), order(2, Side::Ask, 101, 10)).unwrap();
+        assert!(report.replaced.is_empty());
+        assert_eq!((ob.best_bid(), ob.best_ask()), (Some(99), Some(101)));
+        ob.submit_limit(order(3, Side::Bid, 101, 4)).unwrap();
+
+        let report = ob.submit_quote("mm", order(4, Side::Bid, 100, 10), order(5, Side::Ask, 102, 10)).unwrap();
+        assert_eq!(report.replaced.iter().map(|o| (o.id, o.qty)).collect::<Vec<_>>(), [(OrderId(1), 10), (OrderId(2), 6)]);
+        assert_eq!(report.bid.map(|r| r.status), Some(OrderStatus::Rested));
+        assert_eq!((ob.best_bid(), ob.best_ask()), (Some(100), Some(102)));
+        assert_eq!(ob.bids.total_len() + ob.asks.total_len(), 2);
+
+        assert_eq!(ob.submit_quote("mm", order(6, Side::Bid, 103, 1), order(7, Side::Ask, 102, 1)), Err(QuoteError::Crossed { bid_px: 103, ask_px: 102 }));
+        assert_eq!(ob.quotes.get("mm"), Some(&Quote { bid: Some(OrderId(4)), ask: Some(OrderId(5)) }));
+        // Zero on both sides pulls the quote
+        let report = ob.submit_quote("mm", order(8, Side::Bid, 0, 0), order(9, Side::Ask, 0, 0)).unwrap();
+        assert_eq!((report.replaced.len(), report.bid, report.ask), (2, None, None));
+        assert!(ob.quotes.is_empty());
+        assert_eq!(ob.best_bid(), None);
+    }
+
+    /// Replacing a quote leaves alone an order that reused the id of one
+    /// of its sides after that side filled.
+    #[test]
+    fn a_new_quote_spares_orders_reusing_its_filled_ids() {
+        let mut ob = OrderBook::new();
+        ob.submit_quote("mm", order(1, Side::Bid, 99, 10), order(2, Side::Ask, 101, 10)).unwrap();
+        ob.submit_limit(order(3, Side::Ask, 99, 10)).unwrap();
+        assert!(!ob.is_live(OrderId(1)));
+        ob.submit_limit(Order { owner: ParticipantId(7), ..order(1, Side::Bid, 98, 5) }).unwrap();
+
+        let report = ob.submit_quote("mm", order(4, Side::Bid, 97, 10), order(5, Side::Ask, 102, 10)).unwrap();
+        assert_eq!(report.replaced.iter().map(|o| o.id).collect::<Vec<_>>(), [OrderId(2)]);
+        assert_eq!(ob.owner_of(OrderId(1)), Some(ParticipantId(7)));
+        assert_eq!(ob.quotes.get("mm"), Some(&Quote { bid: Some(OrderId(4)), ask: Some(OrderId(5)) }));
+    }
+
+    /// A quote may reuse the ids of the one it replaces, and is still
+    /// refused whole if a side fails its checks.
+    #[test]
+    fn a_new_quote_may_reuse_the_ids_it_replaces() {
+        let mut ob = OrderBook::new().with_config(SymbolConfig { tick_size: 5, ..SymbolConfig::default() }).unwrap();
+        ob.submit_quote("mm", order(1, Side::Bid, 95, 10), order(2, Side::Ask, 105, 10)).unwrap();
+
+        let report = ob.submit_quote("mm", order(1, Side::Bid, 90, 5), order(2, Side::Ask, 110, 5)).unwrap();
+        assert_eq!(report.replaced.iter().map(|o| (o.id, o.px_ticks)).collect::<Vec<_>>(), [(OrderId(1), 95), (OrderId(2), 105)]);
+        assert_eq!((ob.best_bid(), ob.best_ask()), (Some(90), Some(110)));
+        assert_eq!(ob.quotes.get("mm"), Some(&Quote { bid: Some(OrderId(1)), ask: Some(OrderId(2)) }));
+
+        // Another owner can't take them over
+        assert_eq!(ob.submit_quote("other", order(1, Side::Bid, 85, 5), order(3, Side::Ask, 115, 5)), Err(QuoteError::Rejected(RejectReason::DuplicateId(OrderId(1)))));
+        assert_eq!(ob.submit_quote("mm", order(2, Side::Bid, 85, 5), order(1, Side::Ask, 113, 5)), Err(QuoteError::Rejected(RejectReason::OffTick { px_ticks: 113, tick_size: 5 })));
+        assert_eq!((ob.best_bid(), ob.best_ask()), (Some(90), Some(110)));
+        assert_eq!(ob.bids.total_len() + ob.asks.total_len(), 2);
+    }
+
+    /// A throttled owner is refused before the book sees the order.
+    #[test]
+    fn throttled_submits_leave_the_book_alone() {
+        let mut ob = OrderBook::new().with_throttle(ThrottleConfig { rate_per_sec: 1.0, burst: 1 });
+        let order = |id, owner, ts_ns| Order { ts_ns, owner: ParticipantId(owner), ..order(id, Side::Bid, 100, 1) };
+
+        assert!(ob.submit_limit(order(1, 1, 0)).is_ok());
+        assert_eq!(ob.submit_limit(order(2, 1, 500_000_000)), Err(RejectReason::Throttled(throttle::Throttled { retry_after_ns: 500_000_000 })));
+        assert!(ob.submit_limit(order(3, 2, 500_000_000)).is_ok());
+        assert_eq!(ob.bids.total_len(), 2);
+        assert!(ob.submit_limit(order(4, 1, 1_000_000_000)).is_ok());
+        // Orders without an owner aren't throttled
+        assert!(ob.submit_limit(order(5, 0, 1_000_000_000)).is_ok());
+    }
+
+    /// Switching order kind doesn't get a throttled owner past its rate.
+    #[test]
+    fn every_submission_kind_is_throttled() {
+        let mut ob = OrderBook::new().with_throttle(ThrottleConfig { rate_per_sec: 1.0, burst: 1 });
+        let order = |id, side, px_ticks| Order { ts_ns: 0, owner: ParticipantId(1), ..order(id, side, px_ticks, 1) };
+        ob.submit_limit(order(1, Side::Ask, 101)).unwrap();
+        let throttled = RejectReason::Throttled(throttle::Throttled { retry_after_ns: 1_000_000_000 });
+
+        assert_eq!(ob.submit_market(order(2, Side::Bid, 0)), Err(throttled));
+        assert_eq!(ob.submit_stop(order(3, Side::Bid, 0), 102, OrderKind::Market), Err(throttled));
+        assert_eq!(ob.submit_peg(order(4, Side::Bid, 100), PegKind::Primary), Err(throttled));
+        assert_eq!(ob.submit_quote("mm", order(5, Side::Bid, 99), order(6, Side::Ask, 102)), Err(QuoteError::Rejected(throttled)));
+        assert_eq!((ob.bids.total_len(), ob.asks.total_len(), ob.stops.len()), (0, 1, 0));
+        assert!(ob.quotes.is_empty());
+    }
+
+    /// Bad quantities, prices and live ids are refused with the book left
+    /// as it was.
+    #[test]
+    fn submissions_are_checked_before_they_touch_the_book() {
+        let mut ob = OrderBook::new();
+        ob.submit_limit(order(1, Side::Ask, 100, 10)).unwrap();
+
+        assert_eq!(ob.submit_limit(order(2, Side::Bid, 100, 0)), Err(RejectReason::InvalidQuantity(0)));
+        assert_eq!(ob.submit_limit(order(2, Side::Bid, -5, 5)), Err(RejectReason::InvalidPrice(-5)));
+        assert_eq!(ob.submit_limit(order(1, Side::Bid, 100, 5)), Err(RejectReason::DuplicateId(OrderId(1))));
+        assert_eq!(ob.submit_stop(order(2, Side::Bid, 0, 5), 0, OrderKind::Market), Err(RejectReason::InvalidPrice(0)));
+        assert_eq!(ob.asks.qty_at_price(100), 10);
+        assert!(ob.stops.is_empty());
+
+        // A market order's price is never looked at
+        assert_eq!(ob.submit_market(order(2, Side::Bid, 0, 4)).unwrap().len(), 1);
+    }
+
+    /// A resting order or held stop keeps its id taken until it fills or is
+    /// cancelled.
+    #[test]
+    fn an_id_stays_taken_while_its_order_is_live() {
+        let mut ob = OrderBook::new();
+        ob.submit_stop(order(1, Side::Bid, 0, 5), 110, OrderKind::Market).unwrap();
+        ob.submit_limit(order(2, Side::Ask, 100, 5)).unwrap();
+
+        assert_eq!(ob.submit_limit(order(1, Side::Ask, 101, 5)), Err(RejectReason::DuplicateId(OrderId(1))));
+        assert_eq!(ob.submit_stop(order(2, Side::Ask, 0, 5), 90, OrderKind::Market), Err(RejectReason::DuplicateId(OrderId(2))));
+        assert_eq!(ob.submit_peg(order(2, Side::Bid, 99, 5), PegKind::Primary), Err(RejectReason::DuplicateId(OrderId(2))));
+
+        // Filled and cancelled, both ids can be used again
+        ob.submit_limit(order(3, Side::Bid, 100, 5)).unwrap();
+        assert!(ob.cancel_stop(OrderId(1)).is_some());
+        assert!(!ob.is_live(OrderId(1)) && !ob.is_live(OrderId(2)));
+        assert_eq!(ob.submit_limit(order(2, Side::Bid, 99, 5)).unwrap().status, OrderStatus::Rested);
+        assert_eq!(ob.submit_limit(order(1, Side::Bid, 98, 5)).unwrap().status, OrderStatus::Rested);
+    }
+
+    /// A book bound to a symbol refuses orders for any other.
+    #[test]
+    fn a_bound_book_refuses_other_symbols() {
+        let mut ob = OrderBook::new().with_symbol("AAPL".into());
+        let order = |id, symbol: &str| Order { symbol: symbol.into(), ..order(id, Side::Bid, 100, 5) };
+
+        assert_eq!(ob.submit_limit(order(1, "MSFT")), Err(RejectReason::SymbolMismatch { book: "AAPL".into(), order: "MSFT".into() }));
+        assert_eq!(ob.submit_stop(order(1, "MSFT"), 90, OrderKind::Market), Err(RejectReason::SymbolMismatch { book: "AAPL".into(), order: "MSFT".into() }));
+        assert_eq!(ob.bids.total_len() + ob.stops.len(), 0);
+        assert!(ob.submit_limit(order(1, "AAPL")).is_ok());
+
+        // An unbound book takes anything
+        assert!(OrderBook::new().submit_limit(order(1, "MSFT")).is_ok());
+    }
+
+    /// Orders and stops off the configured ticks, lots or size range are
+    /// refused, and a config no book could keep to is too.
+    #[test]
+    fn a_configured_book_keeps_orders_to_its_ticks_and_lots() {
+        let config = SymbolConfig { tick_size: 5, lot_size: 10, min_qty: 10, max_qty: 1000 };
+        let mut ob = OrderBook::new().with_config(config).unwrap();
+        let order = |id, px_ticks, qty| order(id, Side::Bid, px_ticks, qty);
+
+        assert_eq!(ob.submit_limit(order(1, 102, 10)), Err(RejectReason::OffTick { px_ticks: 102, tick_size: 5 }));
+        assert_eq!(ob.submit_limit(order(1, 100, 15)), Err(RejectReason::OffLot { qty: 15, lot_size: 10 }));
+        assert_eq!(ob.submit_limit(order(1, 100, 2000)), Err(RejectReason::QuantityOutOfRange { qty: 2000, min_qty: 10, max_qty: 1000 }));
+        assert_eq!(ob.submit_stop(order(1, 100, 10), 97, OrderKind::Market), Err(RejectReason::OffTick { px_ticks: 97, tick_size: 5 }));
+        assert_eq!(ob.bids.total_len() + ob.stops.len(), 0);
+        assert!(ob.submit_limit(order(1, 100, 20)).is_ok());
+
+        // A market order has no price to keep to the tick, but still its lot
+        assert!(ob.submit_market(order(2, 3, 10)).is_ok());
+        assert_eq!(ob.submit_market(order(3, 3, 5)), Err(RejectReason::OffLot { qty: 5, lot_size: 10 }));
+
+        // Resting orders stay when the parameters change
+        ob.set_config(SymbolConfig { tick_size: 3, ..config }).unwrap();
+        assert_eq!(ob.bids.qty_at_price(100), 20);
+
+        // A config that can't be kept to is refused, however it arrives
+        assert!(ob.set_config(SymbolConfig { lot_size: 0, ..config }).is_err());
+        assert!(OrderBook::new().with_config(SymbolConfig { min_qty: 0, ..config }).is_err());
+        let json = serde_json::to_string(&ob).unwrap().replace("\"tick_size\":3", "\"tick_size\":0");
+        assert!(serde_json::from_str::<OrderBook>(&json).is_err());
+        assert_eq!(ob.config().tick_size, 3);
+    }
+
+    /// Orders and amends that would take a quantity past i64::MAX are
+    /// refused, and what rests at the top of the range never wraps.
+    #[test]
+    fn quantities_at_the_top_of_i64_never_wrap() {
+        let mut ob = OrderBook::new().with_order_status(10);
+        ob.submit_limit(order(1, Side::Ask, i64::MAX, i64::MAX)).unwrap();
+        assert_eq!(ob.submit_limit(order(2, Side::Ask, i64::MAX - 1, 1)), Err(RejectReason::QuantityOverflow(1)));
+        assert_eq!((ob.asks.qty_at_price(i64::MAX), ob.asks.total_qty()), (i64::MAX, i64::MAX));
+        assert_eq!(ob.depth(1).asks[0].qty, i64::MAX);
+
+        let report = ob.submit_limit(order(3, Side::Bid, i64::MAX, i64::MAX)).unwrap();
+        assert_eq!((report.status, report.filled_qty, report.avg_px), (OrderStatus::Filled, i64::MAX, Some(i64::MAX as f64)));
+        ob.submit_limit(order(2, Side::Ask, i64::MAX, i64::MAX)).unwrap();
+        ob.submit_market(order(4, Side::Bid, 0, 5)).unwrap();
+        assert_eq!(ob.asks.qty_at_price(i64::MAX), i64::MAX - 5);
+
+        // Amended back up, the order would have more to its name than an
+        // i64 holds
+        assert_eq!(ob.modify(OrderId(2), i64::MAX - 1, i64::MAX, 5), Err(RejectReason::QuantityOverflow(i64::MAX)));
+        ob.modify(OrderId(2), i64::MAX - 1, i64::MAX - 5, 5).unwrap();
+        let status = ob.order_status(OrderId(2)).unwrap();
+        assert_eq!((status.orig_qty, status.filled_qty, status.leaves_qty()), (i64::MAX, 5, i64::MAX - 5));
+    }
 }
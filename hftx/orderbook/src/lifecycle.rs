@@ -0,0 +1,176 @@
+//! What became of each order a book was given.
+//!
+//! A book set up with
+//! [`OrderBook::with_order_status`](crate::OrderBook::with_order_status)
+//! keeps an [`OrderLifecycle`] per order from the moment it is submitted:
+//! the quantity it came with, how much of it has filled, and where it
+//! stands. An order is [`New`](OrderState::New) until its first fill,
+//! [`PartiallyFilled`](OrderState::PartiallyFilled) until the last, and
+//! ends [`Filled`](OrderState::Filled), [`Canceled`](OrderState::Canceled)
+//! (by a cancel, or because an IOC, market or FOK order couldn't rest
+//! what was left) or [`Expired`](OrderState::Expired). Fills count for the
+//! order whether it took or rested.
+//!
+//! An amend changes `orig_qty` to what has filled plus the new remaining
+//! quantity, so `orig_qty - filled_qty` is always what the order still
+//! works. Orders that have ended are kept for lookups until `retain` later
+//! ones have ended too; live orders are always kept.
+
+use crate::types::{Order, OrderId, ParticipantId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Where an order stands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderState {
+    /// Nothing filled yet; resting, or a stop still held
+    New,
+    /// Some filled, and the rest still works
+    PartiallyFilled,
+    /// Filled completely
+    Filled,
+    /// Cancelled before it filled completely
+    Canceled,
+    /// Reached its good-til-date expiry while resting
+    Expired,
+}
+
+impl OrderState {
+    /// Whether the order is done with: filled, cancelled or expired.
+    pub fn is_final(&self) -> bool {
+        matches!(self, OrderState::Filled | OrderState::Canceled | OrderState::Expired)
+    }
+
+    /// Lower-case name, as the exchange reports it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderState::New => "new",
+            OrderState::PartiallyFilled => "partially_filled",
+            OrderState::Filled => "filled",
+            OrderState::Canceled => "canceled",
+            OrderState::Expired => "expired",
+        }
+    }
+}
+
+/// One order's quantities and state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderLifecycle {
+    pub orig_qty: i64,
+    pub filled_qty: i64,
+    pub state: OrderState,
+    pub owner: ParticipantId,
+}
+
+impl OrderLifecycle {
+    /// Quantity still working: nothing once the order has ended.
+    pub fn leaves_qty(&self) -> i64 {
+        if self.state.is_final() {
+            0
+        } else {
+            self.orig_qty - self.filled_qty
+        }
+    }
+}
+
+/// The lifecycles of one book's orders.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Lifecycles {
+    orders: HashMap<OrderId, OrderLifecycle>,
+    /// Ended orders, oldest first, for dropping past `retain`
+    ended: VecDeque<OrderId>,
+    retain: usize,
+}
+
+impl Lifecycles {
+    /// Keeps the last `retain` orders to end as well as every live one.
+    pub fn new(retain: usize) -> Self {
+        Self { orders: HashMap::new(), ended: VecDeque::new(), retain }
+    }
+
+    pub fn get(&self, id: OrderId) -> Option<&OrderLifecycle> {
+        self.orders.get(&id)
+    }
+
+    /// Orders tracked, live and ended.
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Starts tracking a submitted order as new, replacing whatever an
+    /// earlier order of the same id left.
+    pub fn open(&mut self, order: &Order) {
+        let lifecycle = OrderLifecycle { orig_qty: order.qty, filled_qty: 0, state: OrderState::New, owner: order.owner };
+        self.orders.insert(order.id, lifecycle);
+    }
+
+    /// Counts a fill of `qty` towards order `id`.
+    pub fn fill(&mut self, id: OrderId, qty: i64) {
+        let Some(lifecycle) = self.orders.get_mut(&id) else { return };
+        lifecycle.filled_qty += qty;
+        if lifecycle.filled_qty < lifecycle.orig_qty {
+            lifecycle.state = OrderState::PartiallyFilled;
+        } else {
+            lifecycle.state = OrderState::Filled;
+            self.ended(id);
+        }
+    }
+
+    /// Notes that a live order now works `remaining` more.
+    pub fn amend(&mut self, id: OrderId, remaining: i64) {
+        if let Some(lifecycle) = self.orders.get_mut(&id).filter(|l| !l.state.is_final()) {
+            lifecycle.orig_qty = lifecycle.filled_qty + remaining;
+        }
+    }
+
+    /// Ends a live order as `state` ([`Canceled`](OrderState::Canceled) or
+    /// [`Expired`](OrderState::Expired)). One that has already ended keeps
+    /// the state it ended in.
+    pub fn end(&mut self, id: OrderId, state: OrderState) {
+        let Some(lifecycle) = self.orders.get_mut(&id).filter(|l| !l.state.is_final()) else { return };
+        lifecycle.state = state;
+        self.ended(id);
+    }
+
+    /// Queues an order that just ended, dropping the oldest past `retain`.
+    /// An id queued earlier and since reused is only dropped once it has
+    /// ended again.
+    fn ended(&mut self, id: OrderId) {
+        self.ended.push_back(id);
+        while self.ended.len() > self.retain {
+            let Some(oldest) = self.ended.pop_front() else { break };
+            if self.orders.get(&oldest).is_some_and(|l| l.state.is_final()) {
+                self.orders.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Side, TimeInForce};
+
+    #[test]
+    fn ended_orders_are_kept_until_retain_more_end() {
+        let order = |id| Order { id: OrderId(id), symbol: "AAPL".into(), side: Side::Bid, px_ticks: 100, qty: 10, ts_ns: 0, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE };
+        let mut lifecycles = Lifecycles::new(2);
+        for id in 1..=4 {
+            lifecycles.open(&order(id));
+        }
+        lifecycles.fill(OrderId(1), 10);
+        lifecycles.end(OrderId(2), OrderState::Canceled);
+        // Filled already, so it stays filled
+        lifecycles.end(OrderId(1), OrderState::Expired);
+        assert_eq!(lifecycles.get(OrderId(1)).map(|l| l.state), Some(OrderState::Filled));
+
+        lifecycles.end(OrderId(3), OrderState::Expired);
+        assert_eq!(lifecycles.get(OrderId(1)), None);
+        assert_eq!(lifecycles.len(), 3);
+        assert_eq!(lifecycles.get(OrderId(4)).map(|l| l.leaves_qty()), Some(10));
+    }
+}
@@ -0,0 +1,36 @@
+//! Hooks for following an [`OrderBook`](crate::OrderBook) as it changes.
+//!
+//! A [`BookListener`] added with
+//! [`OrderBook::with_listener`](crate::OrderBook::with_listener) is called
+//! from inside every mutation, in the order things happen there: each
+//! trade as it is made, each order as it comes to rest or is cancelled.
+//! Once the mutation is done, it gets one level change for each price the
+//! mutation touched, with the quantity displayed there now, so a feed can
+//! follow the book without reading it back.
+//!
+//! Hidden orders are reported to [`on_rest`](BookListener::on_rest) like
+//! any other; a public feed should check `order.hidden` before passing one
+//! on. They never count towards a level's quantity.
+//!
+//! Listeners run inside the book's mutations, under whatever lock guards
+//! it, so they should hand events off rather than block.
+
+use crate::types::{Order, OrderId, Side, Trade};
+
+/// Receives what an order book does. Every method does nothing unless
+/// overridden.
+pub trait BookListener: Send + Sync {
+    fn on_trade(&mut self, _trade: &Trade) {}
+
+    /// `order` came to rest, with its whole remaining quantity (an
+    /// iceberg's reserve included).
+    fn on_rest(&mut self, _order: &Order) {}
+
+    /// A resting order was cancelled or expired. Orders that fill, and
+    /// those taken out to be amended or repriced, aren't reported here.
+    fn on_cancel(&mut self, _id: OrderId, _side: Side) {}
+
+    /// The displayed quantity at `px_ticks` on `side` is now `qty`; 0 once
+    /// the level shows nothing.
+    fn on_level_change(&mut self, _side: Side, _px_ticks: i64, _qty: i64) {}
+}
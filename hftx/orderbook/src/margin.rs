@@ -0,0 +1,137 @@
+//! Initial and maintenance margin for leveraged positions.
+//!
+//! A symbol's [`MarginRates`] are fractions of notional: `initial` must be
+//! posted to open exposure (so `1 / initial` is the maximum leverage), and
+//! `maintenance` must stay covered while holding it. Initial margin counts
+//! working orders too, at their worst case: if every resting buy filled, or
+//! every resting sell did, whichever leaves the larger position. Orders
+//! that only reduce a position therefore need no extra margin.
+//! Maintenance margin counts the position alone.
+//!
+//! Utilization is requirement over equity; past 1.0 the account is short
+//! of margin.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MarginRates {
+    pub initial: f64,
+    pub maintenance: f64,
+}
+
+impl MarginRates {
+    /// Checks `0 < maintenance <= initial <= 1`.
+    pub fn validate(&self) -> Result<(), MarginError> {
+        let valid = self.maintenance > 0.0 && self.maintenance <= self.initial && self.initial <= 1.0;
+        if valid {
+            Ok(())
+        } else {
+            Err(MarginError::BadRates(*self))
+        }
+    }
+
+    /// Highest leverage these rates allow.
+    pub fn max_leverage(&self) -> f64 {
+        1.0 / self.initial
+    }
+
+    /// Margin to post for `exposure` at `px`.
+    pub fn initial_margin(&self, exposure: &Exposure, px: f64) -> f64 {
+        self.initial * exposure.worst_case() as f64 * px.abs()
+    }
+
+    /// Margin to keep while holding `exposure`'s position at `px`.
+    pub fn maintenance_margin(&self, exposure: &Exposure, px: f64) -> f64 {
+        self.maintenance * exposure.position.unsigned_abs() as f64 * px.abs()
+    }
+}
+
+/// A position and the working orders that could grow or shrink it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Exposure {
+    /// Positive long, negative short
+    pub position: i64,
+    /// Resting buy quantity
+    pub open_bids: i64,
+    /// Resting sell quantity
+    pub open_asks: i64,
+}
+
+impl Exposure {
+    /// Largest absolute position reachable by filling one side's orders.
+    pub fn worst_case(&self) -> u64 {
+        let long = self.position.saturating_add(self.open_bids).unsigned_abs();
+        let short = self.position.saturating_sub(self.open_asks).unsigned_abs();
+        long.max(short)
+    }
+}
+
+/// Requirement over equity; `None` when equity is gone but something is
+/// still required.
+pub fn utilization(required: f64, equity: f64) -> Option<f64> {
+    if required <= 0.0 {
+        Some(0.0)
+    } else if equity > 0.0 {
+        Some(required / equity)
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MarginError {
+    BadRates(MarginRates),
+    /// An order would need more initial margin than the account's equity
+    Insufficient { required: f64, equity: f64 },
+}
+
+impl fmt::Display for MarginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarginError::BadRates(rates) => write!(
+                f,
+                "margin rates need 0 < maintenance <= initial <= 1, got initial {} maintenance {}",
+                rates.initial, rates.maintenance
+            ),
+            MarginError::Insufficient { required, equity } => {
+                write!(f, "insufficient margin: {:.2} required, {:.2} equity", required, equity)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MarginError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATES: MarginRates = MarginRates { initial: 0.1, maintenance: 0.05 };
+
+    #[test]
+    fn working_orders_count_at_their_worst_side() {
+        let long = Exposure { position: 10, open_bids: 5, open_asks: 0 };
+        assert_eq!(long.worst_case(), 15);
+        // Selling down a long needs nothing extra until it flips past flat
+        let reducing = Exposure { position: 10, open_bids: 0, open_asks: 10 };
+        assert_eq!(reducing.worst_case(), 10);
+        let flipping = Exposure { position: 10, open_bids: 0, open_asks: 25 };
+        assert_eq!(flipping.worst_case(), 15);
+
+        assert_eq!(RATES.initial_margin(&long, 100.0), 150.0);
+        assert_eq!(RATES.maintenance_margin(&long, 100.0), 50.0);
+        assert_eq!(RATES.max_leverage(), 10.0);
+    }
+
+    #[test]
+    fn utilization_and_rate_checks() {
+        assert_eq!(utilization(50.0, 200.0), Some(0.25));
+        assert_eq!(utilization(0.0, -5.0), Some(0.0));
+        assert_eq!(utilization(1.0, 0.0), None);
+
+        assert!(RATES.validate().is_ok());
+        let inverted = MarginRates { initial: 0.05, maintenance: 0.1 };
+        assert_eq!(inverted.validate(), Err(MarginError::BadRates(inverted)));
+    }
+}
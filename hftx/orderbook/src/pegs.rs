@@ -0,0 +1,126 @@
+//! Pegged orders, repriced off the best bid and offer.
+//!
+//! A pegged order rests like a limit order, but its price follows the
+//! book: a [`PegKind::Primary`] peg sits at the best price on its own
+//! side, a [`PegKind::Midpoint`] peg halfway between the best bid and
+//! ask, rounded away from the other side (down for a bid, up for an ask)
+//! when the spread is an odd number of ticks. Best prices are taken from
+//! orders that aren't pegged themselves, so pegs never chase each other.
+//!
+//! The order's own `px_ticks` is a limit the peg never goes through, and
+//! where it rests while there is nothing to peg to.
+
+use crate::types::{OrderId, Side};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PegKind {
+    /// The best price on the order's own side
+    Primary,
+    /// The middle of the spread
+    Midpoint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Peg {
+    pub kind: PegKind,
+    pub side: Side,
+    /// Worst price the peg may rest at
+    pub limit_px: i64,
+}
+
+impl Peg {
+    /// Where the peg rests when the best unpegged prices are `bid` and
+    /// `ask`.
+    pub fn price(&self, bid: Option<i64>, ask: Option<i64>) -> i64 {
+        let pegged = match (self.kind, self.side) {
+            (PegKind::Primary, Side::Bid) => bid,
+            (PegKind::Primary, Side::Ask) => ask,
+            (PegKind::Midpoint, side) => bid.zip(ask).map(|(bid, ask)| {
+                let half = (ask - bid).div_euclid(2);
+                match side {
+                    Side::Bid => bid + half,
+                    Side::Ask => ask - half,
+                }
+            }),
+        };
+        match self.side {
+            Side::Bid => pegged.map_or(self.limit_px, |px| px.min(self.limit_px)),
+            Side::Ask => pegged.map_or(self.limit_px, |px| px.max(self.limit_px)),
+        }
+    }
+}
+
+/// The pegged orders resting on one book, in arrival order.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Pegs {
+    pegs: BTreeMap<u64, (OrderId, Peg)>,
+    /// Where each peg is keyed
+    index: HashMap<OrderId, u64>,
+    next_seq: u64,
+}
+
+impl Pegs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, id: OrderId, peg: Peg) {
+        self.remove(id);
+        self.index.insert(id, self.next_seq);
+        self.pegs.insert(self.next_seq, (id, peg));
+        self.next_seq += 1;
+    }
+
+    pub fn remove(&mut self, id: OrderId) -> Option<Peg> {
+        let seq = self.index.remove(&id)?;
+        self.pegs.remove(&seq).map(|(_, peg)| peg)
+    }
+
+    pub fn get(&self, id: OrderId) -> Option<&Peg> {
+        self.pegs.get(self.index.get(&id)?).map(|(_, peg)| peg)
+    }
+
+    pub fn get_mut(&mut self, id: OrderId) -> Option<&mut Peg> {
+        self.pegs.get_mut(self.index.get(&id)?).map(|(_, peg)| peg)
+    }
+
+    pub fn contains(&self, id: OrderId) -> bool {
+        self.index.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Pegs in arrival order.
+    pub fn iter(&self) -> impl Iterator<Item = (OrderId, &Peg)> {
+        self.pegs.values().map(|(id, peg)| (*id, peg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pegs_price_off_the_spread_within_their_limit() {
+        let peg = |kind, side, limit_px| Peg { kind, side, limit_px };
+        let mid_bid = peg(PegKind::Midpoint, Side::Bid, 200);
+        let mid_ask = peg(PegKind::Midpoint, Side::Ask, 0);
+        // Odd spread: each side rounds away from the other
+        assert_eq!(mid_bid.price(Some(100), Some(103)), 101);
+        assert_eq!(mid_ask.price(Some(100), Some(103)), 102);
+        assert_eq!(mid_ask.price(Some(100), Some(102)), 101);
+        assert_eq!(peg(PegKind::Primary, Side::Ask, 0).price(Some(100), Some(103)), 103);
+
+        // Never through the limit, and at it with nothing to peg to
+        assert_eq!(peg(PegKind::Primary, Side::Bid, 99).price(Some(100), Some(103)), 99);
+        assert_eq!(mid_bid.price(Some(100), None), 200);
+    }
+}
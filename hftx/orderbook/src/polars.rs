@@ -0,0 +1,245 @@
+//! Trades, candles, and depth time series as polars `DataFrame`s (`polars`
+//! feature), and event DataFrames as replay input.
+//!
+//! For research code that lives in Rust: exchange output lands in a
+//! DataFrame without a file or JSON in between, and a DataFrame of events
+//! (say, filtered from a Parquet capture) replays straight into books.
+//! Column names match the Arrow batches and Parquet files:
+//!
+//! - trades: `seq`, `ts_ns`, `symbol`, `px_ticks`, `qty`, `maker_id`, `taker_id`
+//! - depth: `ts_ns`, `symbol`, `side`, `level`, `px_ticks`, `qty`, `orders`
+//! - candles: `start_ns`, `interval_ns`, `symbol`, `open`, `high`, `low`,
+//!   `close`, `volume`, `trades`
+//!
+//! Order ids are `Int128`. Replay input uses the CSV events layout
+//! (`action`, `symbol`, `id`, `side`, `px_ticks`, `qty`, optional `ts_ns`);
+//! numeric columns may be any integer type.
+
+use crate::candles::Candle;
+use crate::codec::BookEvent;
+use crate::csv::CsvEvent;
+use crate::symbols::SymbolId;
+use crate::types::{Order, OrderId, ParticipantId, Side, TimeInForce, Trade};
+use crate::{BookLevel, OrderBook};
+use ::polars::prelude::*;
+use std::collections::BTreeMap;
+
+fn column<T, P: ?Sized>(name: &str, values: T) -> Column
+where
+    Series: NamedFrom<T, P>,
+{
+    Column::new(name.into(), values)
+}
+
+fn id_column(name: &str, ids: impl Iterator<Item = OrderId>) -> Column {
+    Int128Chunked::from_vec(name.into(), ids.map(|id| id.0 as i128).collect()).into_column()
+}
+
+/// Trades as a frame, `seq` from 0.
+pub fn trades_frame(trades: &[Trade]) -> PolarsResult<DataFrame> {
+    let each = |f: fn(&Trade) -> i64| trades.iter().map(f).collect::<Vec<_>>();
+    DataFrame::new(vec![
+        column("seq", (0..trades.len() as u64).collect::<Vec<_>>()),
+        column("ts_ns", each(|t| t.ts_ns as i64)),
+        column("symbol", trades.iter().map(|t| t.symbol.as_str()).collect::<Vec<_>>()),
+        column("px_ticks", each(|t| t.px_ticks)),
+        column("qty", each(|t| t.qty)),
+        id_column("maker_id", trades.iter().map(|t| t.maker)),
+        id_column("taker_id", trades.iter().map(|t| t.taker)),
+    ])
+}
+
+pub fn candles_frame(candles: &[Candle]) -> PolarsResult<DataFrame> {
+    let each = |f: fn(&Candle) -> i64| candles.iter().map(f).collect::<Vec<_>>();
+    DataFrame::new(vec![
+        column("start_ns", each(|c| c.start_ns as i64)),
+        column("interval_ns", each(|c| c.interval_ns as i64)),
+        column("symbol", candles.iter().map(|c| c.symbol.as_str()).collect::<Vec<_>>()),
+        column("open", each(|c| c.open)),
+        column("high", each(|c| c.high)),
+        column("low", each(|c| c.low)),
+        column("close", each(|c| c.close)),
+        column("volume", each(|c| c.volume)),
+        column("trades", candles.iter().map(|c| c.trades).collect::<Vec<_>>()),
+    ])
+}
+
+/// Accumulates depth snapshots into one frame, one row per live level.
+#[derive(Default)]
+pub struct DepthFrameBuilder {
+    ts_ns: Vec<i64>,
+    symbol: Vec<String>,
+    side: Vec<&'static str>,
+    level: Vec<u32>,
+    px_ticks: Vec<i64>,
+    qty: Vec<i64>,
+    orders: Vec<u32>,
+}
+
+impl DepthFrameBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the best `levels` live price levels of each side at `ts_ns`,
+    /// bids first. Returns the rows added.
+    pub fn append_book(&mut self, ts_ns: u128, symbol: &str, book: &OrderBook, levels: usize) -> usize {
+        let before = self.ts_ns.len();
+        let depth = book.depth(levels);
+        for (side, name) in [(depth.bids, "bid"), (depth.asks, "ask")] {
+            for (level, BookLevel { px_ticks: px, qty, orders }) in side.into_iter().enumerate() {
+                self.ts_ns.push(ts_ns as i64);
+                self.symbol.push(symbol.to_string());
+                self.side.push(name);
+                self.level.push(level as u32);
+                self.px_ticks.push(px);
+                self.qty.push(qty);
+                self.orders.push(orders as u32);
+            }
+        }
+        self.ts_ns.len() - before
+    }
+
+    pub fn len(&self) -> usize {
+        self.ts_ns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ts_ns.is_empty()
+    }
+
+    pub fn finish(self) -> PolarsResult<DataFrame> {
+        DataFrame::new(vec![
+            column("ts_ns", self.ts_ns),
+            column("symbol", self.symbol),
+            column("side", self.side),
+            column("level", self.level),
+            column("px_ticks", self.px_ticks),
+            column("qty", self.qty),
+            column("orders", self.orders),
+        ])
+    }
+}
+
+/// Column `name` cast to `dtype`, or `None` if the frame lacks it.
+fn cast(frame: &DataFrame, name: &str, dtype: &DataType) -> PolarsResult<Option<Series>> {
+    match frame.column(name) {
+        Ok(c) => Ok(Some(c.as_materialized_series().cast(dtype)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn required(frame: &DataFrame, name: &str, dtype: &DataType) -> PolarsResult<Series> {
+    cast(frame, name, dtype)?.ok_or_else(|| polars_err!(ColumnNotFound: "events frame has no `{}` column", name))
+}
+
+/// Parses an events frame, rows in frame order. Without `ts_ns`, an event
+/// takes its 1-based row number, as in a CSV file.
+pub fn events_from_frame(frame: &DataFrame) -> PolarsResult<Vec<CsvEvent>> {
+    let action = required(frame, "action", &DataType::String)?;
+    let symbol = required(frame, "symbol", &DataType::String)?;
+    let id = required(frame, "id", &DataType::Int128)?;
+    let side = required(frame, "side", &DataType::String)?;
+    let px = cast(frame, "px_ticks", &DataType::Int64)?;
+    let qty = cast(frame, "qty", &DataType::Int64)?;
+    let ts = cast(frame, "ts_ns", &DataType::Int64)?;
+    let (action, symbol, id, side) = (action.str()?, symbol.str()?, id.i128()?, side.str()?);
+    let px = px.as_ref().map(|s| s.i64()).transpose()?;
+    let qty = qty.as_ref().map(|s| s.i64()).transpose()?;
+    let ts = ts.as_ref().map(|s| s.i64()).transpose()?;
+
+    (0..frame.height())
+        .map(|row| {
+            let bad = |column: &str| polars_err!(ComputeError: "row {}: missing or invalid `{}`", row, column);
+            let order_id = OrderId(id.get(row).filter(|&id| id >= 0).ok_or_else(|| bad("id"))? as u128);
+            let symbol = symbol.get(row).filter(|s| !s.is_empty()).ok_or_else(|| bad("symbol"))?.to_string();
+            let side = match side.get(row).map(str::to_ascii_lowercase).as_deref() {
+                Some("bid" | "buy") => Side::Bid,
+                Some("ask" | "sell") => Side::Ask,
+                _ => return Err(bad("side")),
+            };
+            let event = match action.get(row).map(str::to_ascii_lowercase).as_deref() {
+                Some("submit") => BookEvent::Submit(Order {
+                    id: order_id,
+                    symbol: SymbolId::intern(&symbol),
+                    side,
+                    px_ticks: px.and_then(|c| c.get(row)).ok_or_else(|| bad("px_ticks"))?,
+                    qty: qty.and_then(|c| c.get(row)).filter(|&q| q > 0).ok_or_else(|| bad("qty"))?,
+                    ts_ns: match ts.and_then(|c| c.get(row)) {
+                        Some(ts) => ts.max(0) as u128,
+                        None => row as u128 + 1,
+                    },
+                    tif: TimeInForce::Day,
+                    expires_ns: None,
+                    display_qty: None,
+                    min_qty: None,
+                    hidden: false,
+                    owner: ParticipantId::NONE,
+                }),
+                Some("cancel") => BookEvent::Cancel { id: order_id, side },
+                _ => return Err(bad("action")),
+            };
+            Ok(CsvEvent { symbol, event })
+        })
+        .collect()
+}
+
+/// Applies an events frame to `books`, creating books for new symbols, and
+/// returns the trades printed.
+pub fn replay_frame(books: &mut BTreeMap<String, OrderBook>, frame: &DataFrame) -> PolarsResult<Vec<Trade>> {
+    Ok(crate::csv::replay_events(books, events_from_frame(frame)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::candles::candles;
+
+    #[test]
+    fn replays_an_events_frame_and_frames_the_output() {
+        let events = df!(
+            "action" => ["submit", "submit", "cancel", "submit"],
+            "symbol" => ["AAPL", "AAPL", "AAPL", "AAPL"],
+            "id" => [1i32, 2, 1, 3],
+            "side" => ["ask", "ask", "ask", "buy"],
+            "px_ticks" => [Some(101i64), Some(102), None, Some(102)],
+            "qty" => [Some(5i64), Some(5), None, Some(3)],
+        )
+        .unwrap();
+        let mut books = BTreeMap::new();
+        let trades = replay_frame(&mut books, &events).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!((trades[0].maker, trades[0].px_ticks, trades[0].ts_ns), (OrderId(2), 102, 4));
+
+        let frame = trades_frame(&trades).unwrap();
+        assert_eq!(frame.get_column_names_str(), ["seq", "ts_ns", "symbol", "px_ticks", "qty", "maker_id", "taker_id"]);
+        assert_eq!(frame.column("taker_id").unwrap().i128().unwrap().get(0), Some(3));
+
+        let mut depth = DepthFrameBuilder::new();
+        depth.append_book(10, "AAPL", &books["AAPL"], 5);
+        depth.append_book(20, "AAPL", &books["AAPL"], 5);
+        let depth = depth.finish().unwrap();
+        assert_eq!(depth.height(), 2);
+        assert_eq!(depth.column("qty").unwrap().i64().unwrap().get(1), Some(2));
+
+        let candles = candles_frame(&candles(&trades, 1_000)).unwrap();
+        assert_eq!(candles.column("volume").unwrap().i64().unwrap().get(0), Some(3));
+    }
+
+    #[test]
+    fn rejects_incomplete_events() {
+        let no_side = df!("action" => ["cancel"], "symbol" => ["AAPL"], "id" => [1i64]).unwrap();
+        assert!(matches!(events_from_frame(&no_side), Err(PolarsError::ColumnNotFound(_))));
+
+        let no_qty = df!(
+            "action" => ["submit"],
+            "symbol" => ["AAPL"],
+            "id" => [1i64],
+            "side" => ["bid"],
+            "px_ticks" => [100i64],
+        )
+        .unwrap();
+        let err = events_from_frame(&no_qty).unwrap_err();
+        assert!(err.to_string().contains("row 0: missing or invalid `qty`"), "{}", err);
+    }
+}
@@ -1,18 +1,121 @@
-use crate::types::{Order, OrderId, Side};
-use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use crate::invariants::Violation;
+use crate::ladder::Levels;
+use crate::slab::{Node, Queue, Slab};
+use crate::types::{BookLevel, Order, OrderId, ParticipantId, Side};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
 
-// Structured price levels based, FIFO tracking with BTreeMap
+// Structured price levels based, FIFO tracking with BTreeMap, or with a
+// flat ladder of one slot per tick over a price band (see `ladder`)
 // side determines which end of the map is the best
 // - Asks: lowest price is best (front of map)
 // - Bids: highest price is best (back of map)
+// Orders live in a slab (see `slab`); levels hold only their queue's ends
+// in it, so queueing reuses freed slots and any order leaves in O(1)
+// Serializes whole, canceled orders still queued included, so a reloaded
+// side matches and cancels exactly as the saved one would have. Loading
+// refuses a side that fails `check_invariants` (see the impls below)
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct PriceLevels {
     /// Bid or ask?
     side: Side,
-    /// price ticks (i64) mapped to orders at the price
-    /// stored in a queu or orders waiting to be filled
-    levels: BTreeMap<i64, VecDeque<Order>>,
-    index: HashMap<OrderId, i64>,
-    canceled: HashSet<OrderId>,
+    /// Every queued order, displayed or hidden, canceled ones included
+    slab: Slab,
+    /// price ticks (i64) mapped to the queue of orders waiting to be
+    /// filled at the price
+    levels: Levels,
+    /// Live orders by id, to where they sit in the slab
+    index: HashMap<OrderId, usize>,
+    /// Canceled orders still queued
+    tombstones: usize,
+    /// Live displayed quantity and order count per price, kept as orders
+    /// come and go so depth never walks the queues
+    totals: Totals,
+    /// Good-til-date orders by expiry; entries for orders that have since
+    /// filled or been canceled stay until their time comes
+    expiries: BTreeSet<(u128, OrderId)>,
+    /// Quantity each live iceberg holds back behind its displayed slice
+    reserves: HashMap<OrderId, i64>,
+    /// Hidden orders, queued apart from `levels` so that depth and best
+    /// prices never see them
+    dark: Levels,
+    /// Compact once this many canceled orders are waiting in the queues
+    compact_at: Option<usize>,
+    /// Cancels take orders out at once rather than leaving tombstones
+    eager_cancel: bool,
+    /// Live orders that have an owner, by owner
+    #[serde(default)]
+    owned: BTreeSet<(ParticipantId, OrderId)>,
+}
+
+impl Serialize for PriceLevels {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PriceLevels::serialize(self, serializer)
+    }
+}
+
+/// Matching trusts a side's queues, without checking them at all under
+/// the `unchecked` feature (see `live_front`), so a side read from a file
+/// someone could have crafted is only taken once its invariants hold.
+impl<'de> Deserialize<'de> for PriceLevels {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let levels = PriceLevels::deserialize(deserializer)?;
+        levels.check_invariants().map_err(serde::de::Error::custom)?;
+        Ok(levels)
+    }
+}
+
+/// Running totals of one displayed level.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+struct LevelTotal {
+    /// Wider than an order's quantity, so huge orders can't overflow it
+    qty: i128,
+    orders: usize,
+}
+
+impl LevelTotal {
+    /// The quantity as an `i64`, saturating.
+    fn qty(&self) -> i64 {
+        self.qty.min(i64::MAX as i128) as i64
+    }
+}
+
+/// Running totals of every displayed level, and of the side as a whole.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Totals {
+    /// Prices with no live displayed order have no entry
+    levels: HashMap<i64, LevelTotal>,
+    qty: i128,
+}
+
+impl Totals {
+    /// Adds a live displayed order of `qty` at `px_ticks`.
+    fn add(&mut self, px_ticks: i64, qty: i64) {
+        let total = self.levels.entry(px_ticks).or_default();
+        total.qty += qty as i128;
+        total.orders += 1;
+        self.qty += qty as i128;
+    }
+
+    /// Takes `qty` off the level at `px_ticks`, and one order too if `leaves`.
+    fn take(&mut self, px_ticks: i64, qty: i64, leaves: bool) {
+        if let Entry::Occupied(mut total) = self.levels.entry(px_ticks) {
+            total.get_mut().qty -= qty as i128;
+            self.qty -= qty as i128;
+            if leaves {
+                total.get_mut().orders -= 1;
+                if total.get().orders == 0 {
+                    total.remove();
+                }
+            }
+        }
+    }
+
+    fn level(&self, px_ticks: i64) -> Option<&LevelTotal> {
+        self.levels.get(&px_ticks)
+    }
 }
 
 impl PriceLevels {
@@ -20,40 +123,131 @@ impl PriceLevels {
     pub fn new(side: Side) -> Self {
         Self {
             side,
-            levels: BTreeMap::new(),
+            slab: Slab::default(),
+            levels: Levels::default(),
             index: HashMap::new(),
-            canceled: HashSet::new(),
+            tombstones: 0,
+            totals: Totals::default(),
+            expiries: BTreeSet::new(),
+            reserves: HashMap::new(),
+            dark: Levels::default(),
+            compact_at: None,
+            eager_cancel: false,
+            owned: BTreeSet::new(),
         }
     }
 
+    /// Compacts on its own whenever a cancel leaves `tombstones` canceled
+    /// orders in the queues. Each compaction walks the whole side, so a
+    /// threshold near the number of resting orders keeps the cost per
+    /// cancel constant.
+    pub fn with_compact_threshold(mut self, tombstones: usize) -> Self {
+        self.compact_at = Some(tombstones.max(1));
+        self
+    }
+
+    /// Makes [`cancel`](Self::cancel), and so expiry, take an order out of
+    /// its queue at once, as [`remove`](Self::remove) does, instead of
+    /// leaving a tombstone for matching to skip. Removal from anywhere in
+    /// a queue is O(1), so this costs a cancel little and spares matching
+    /// and compaction the tombstones; lazy stays the default.
+    pub fn with_eager_cancel(mut self) -> Self {
+        self.eager_cancel = true;
+        self
+    }
+
+    /// Keeps displayed levels in a flat ladder of one slot per tick from
+    /// `low` to `high` rather than a tree, so finding a level is an index
+    /// and the best price a cursor. Meant for symbols whose prices stay in
+    /// a known band: orders priced outside it still work, at tree cost,
+    /// and the ladder holds the whole band in memory whatever rests.
+    /// Orders already resting move over. Panics if `high < low`.
+    pub fn with_price_band(mut self, low: i64, high: i64) -> Self {
+        self.levels = std::mem::take(&mut self.levels).into_ladder(low, high);
+        self
+    }
+
+    /// The band set by [`with_price_band`](Self::with_price_band), if any.
+    pub fn price_band(&self) -> Option<(i64, i64)> {
+        self.levels.band()
+    }
+
     /// Adds an order at the price level, keep FIFO intact
     /// create price level if not existing
-    pub fn push(&mut self, order: Order) {
+    /// An iceberg rests only its first `display_qty` slice; a hidden order
+    /// rests out of sight
+    pub fn push(&mut self, mut order: Order) {
         debug_assert!(
             !self.index.contains_key(&order.id),
             "duplicate order id exists"
         );
         // Inserts order to price level, defaults to empty Queue if not
-        self.index.insert(order.id, order.px_ticks);
-        self.levels
-            .entry(order.px_ticks)
-            .or_default()
-            .push_back(order);
+        self.hide_reserve(&mut order);
+        self.track(&order);
+        let id = order.id;
+        let (slab, q) = self.queue(&order);
+        let at = slab.push_back(q, order);
+        self.index.insert(id, at);
     }
 
     /// Reinsert order at front of its price level (partial fill case)
     /// Keep FIFO for same order already at front
-    pub fn push_front(&mut self, order: Order) {
-        self.index.insert(order.id, order.px_ticks);
-        self.levels
-            .entry(order.px_ticks)
-            .or_default()
-            .push_front(order);
+    pub fn push_front(&mut self, mut order: Order) {
+        self.hide_reserve(&mut order);
+        self.track(&order);
+        let id = order.id;
+        let (slab, q) = self.queue(&order);
+        let at = slab.push_front(q, order);
+        self.index.insert(id, at);
     }
 
-    /// Returns all price levels with their orders
-    pub fn get_price_levels(&self) -> &BTreeMap<i64, VecDeque<Order>> {
-        &self.levels
+    /// Counts a new order towards its level and notes when it expires and
+    /// who owns it.
+    fn track(&mut self, order: &Order) {
+        if !order.hidden {
+            self.totals.add(order.px_ticks, order.qty);
+        }
+        if let Some(expires_ns) = order.expires_ns {
+            self.expiries.insert((expires_ns, order.id));
+        }
+        if order.owner != ParticipantId::NONE {
+            self.owned.insert((order.owner, order.id));
+        }
+    }
+
+    /// The slab, and the queue `order` belongs in, created if missing.
+    fn queue(&mut self, order: &Order) -> (&mut Slab, &mut Queue) {
+        let levels = if order.hidden { &mut self.dark } else { &mut self.levels };
+        (&mut self.slab, levels.entry(order.px_ticks))
+    }
+
+    /// Holds back what an iceberg has beyond its displayed slice. A hidden
+    /// order shows nothing anyway, so it is never split.
+    fn hide_reserve(&mut self, order: &mut Order) {
+        if order.hidden {
+            return;
+        }
+        if let Some(display_qty) = order.display_qty {
+            if display_qty > 0 && order.qty > display_qty {
+                self.reserves.insert(order.id, order.qty - display_qty);
+                order.qty = display_qty;
+            }
+        }
+    }
+
+    /// Quantity an iceberg still holds back behind its displayed slice; 0
+    /// for any other order.
+    pub fn hidden_qty(&self, id: OrderId) -> i64 {
+        self.reserves.get(&id).copied().unwrap_or(0)
+    }
+
+    /// Returns all displayed price levels, lowest price first, each with
+    /// its queued orders in FIFO order. Lazily-canceled orders are still
+    /// queued; `contains` tells them apart.
+    pub fn get_price_levels(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (i64, impl Iterator<Item = &Order> + '_)> + ExactSizeIterator + '_ {
+        self.levels.iter().map(|(px, q)| (px, self.slab.iter(q).map(|(_, node)| &node.order)))
     }
 
     /// Returns the best price for the side without removing anything
@@ -61,19 +255,197 @@ impl PriceLevels {
     /// For bids: the highest price (whatever is last in the BTree)
     /// Returns None if no price levels currently exist
     pub fn best_price(&self) -> Option<i64> {
-        match self.side {
-            // grab the first item in the Bal tree for asks (cheapest)
-            Side::Ask => self.levels.first_key_value().map(|(px, _)| *px),
-            // grab the last item in the Bal tree for bids (most expensive)
-            Side::Bid => self.levels.last_key_value().map(|(px, _)| *px),
+        self.levels.best(self.side)
+    }
+
+    /// Best price that still has a live order, dropping canceled orders
+    /// queued ahead of it. Unlike `best_price`, never reports a level that
+    /// holds only lazily-canceled orders, so matching can trust it.
+    pub fn best_live_price(&mut self) -> Option<i64> {
+        live_best(self.side, &mut self.levels, &mut self.slab, &mut self.tombstones)
+    }
+
+    /// Fills up to `qty` against the first live order at the best price if
+    /// that price crosses `limit_px`. Returns (maker id, price, filled qty).
+    ///
+    /// The maker is filled in place at the front of its queue and only leaves
+    /// the book once fully filled, so a partial fill costs no hashing and no
+    /// moving the order out and back. One tree lookup per fill.
+    ///
+    /// An iceberg whose displayed slice fills reloads the next slice from
+    /// its reserve at the back of the level, behind the orders already
+    /// there.
+    ///
+    /// Hidden orders fill after every displayed order at their price, but
+    /// ahead of displayed orders at worse prices.
+    pub fn fill_best(&mut self, limit_px: i64, qty: i64) -> Option<(OrderId, i64, i64)> {
+        if !self.dark.is_empty() {
+            let dark_px = live_best(self.side, &mut self.dark, &mut self.slab, &mut self.tombstones);
+            let dark_first = match (dark_px, self.best_live_price()) {
+                (Some(dark_px), Some(px)) => self.better(dark_px, px),
+                (dark_px, _) => dark_px.is_some(),
+            };
+            if dark_first {
+                return self.fill_dark(limit_px, qty);
+            }
+        }
+        self.fill_displayed(limit_px, qty)
+    }
+
+    /// True if `px` is strictly better than `than` for this side.
+    fn better(&self, px: i64, than: i64) -> bool {
+        self.side.better(px, than)
+    }
+
+    /// True if a taker limited to `limit_px` trades at `px` on this side.
+    fn crosses(&self, px: i64, limit_px: i64) -> bool {
+        self.side.crosses(px, limit_px)
+    }
+
+    /// `fill_best` against the front hidden order at the best hidden price,
+    /// which must already be live.
+    fn fill_dark(&mut self, limit_px: i64, qty: i64) -> Option<(OrderId, i64, i64)> {
+        let px = self.dark.best(self.side)?;
+        if !self.crosses(px, limit_px) {
+            return None;
+        }
+        let q = self.dark.get_mut(px)?;
+        let maker = live_front(&mut self.slab, q);
+        let fill = qty.min(maker.qty);
+        maker.qty -= fill;
+        let (id, owner) = (maker.id, maker.owner);
+        if maker.qty == 0 {
+            self.slab.pop_front(q);
+            self.index.remove(&id);
+            disown(&mut self.owned, owner, id);
+            if q.is_empty() {
+                self.dark.remove(px);
+            }
+        }
+        Some((id, px, fill))
+    }
+
+    /// `fill_best` against the displayed orders alone.
+    fn fill_displayed(&mut self, limit_px: i64, qty: i64) -> Option<(OrderId, i64, i64)> {
+        loop {
+            let (px, q) = self.levels.best_mut(self.side)?;
+            if !self.side.crosses(px, limit_px) {
+                return None;
+            }
+
+            // Skip the tombstone check entirely while nothing is canceled
+            if self.tombstones > 0 {
+                while let Some(at) = q.front() {
+                    if !self.slab.get(at).canceled {
+                        break;
+                    }
+                    self.slab.pop_front(q);
+                    self.tombstones -= 1;
+                }
+                if q.is_empty() {
+                    self.levels.remove(px);
+                    continue;
+                }
+            }
+            let maker = live_front(&mut self.slab, q);
+
+            let fill = qty.min(maker.qty);
+            maker.qty -= fill;
+            let (id, owner) = (maker.id, maker.owner);
+            let reserve = if maker.qty > 0 || self.reserves.is_empty() { None } else { self.reserves.get_mut(&id) };
+            if let Some(reserve) = reserve {
+                maker.qty = maker.display_qty.map_or(*reserve, |display_qty| display_qty.min(*reserve));
+                *reserve -= maker.qty;
+                let shown = maker.qty;
+                if *reserve == 0 {
+                    self.reserves.remove(&id);
+                }
+                self.totals.take(px, fill - shown, false);
+                let at = q.front().expect("filled order left its level");
+                self.slab.move_to_back(q, at);
+            } else if maker.qty == 0 {
+                self.totals.take(px, fill, true);
+                self.slab.pop_front(q);
+                self.index.remove(&id);
+                disown(&mut self.owned, owner, id);
+                if q.is_empty() {
+                    self.levels.remove(px);
+                }
+            } else {
+                self.totals.take(px, fill, false);
+            }
+            return Some((id, px, fill));
+        }
+    }
+
+    /// Like [`fill_best`](Self::fill_best) for a taker that won't take a
+    /// fill under `min_qty`: makers showing less than that are passed over
+    /// wherever they sit, and None comes back once no crossing maker shows
+    /// enough, or `qty` itself is under the minimum.
+    pub fn fill_best_min(&mut self, limit_px: i64, qty: i64, min_qty: i64) -> Option<(OrderId, i64, i64)> {
+        if qty < min_qty {
+            return None;
+        }
+        let first_big_enough = |levels| {
+            self.best_first(levels)
+                .take_while(|&(px, _)| self.crosses(px, limit_px))
+                .find_map(|(px, q)| {
+                    let big_enough = |(_, node): &(usize, &Node)| {
+                        node.order.qty >= min_qty && !node.canceled
+                    };
+                    self.slab.iter(q).find(big_enough).map(|(at, _)| (px, at))
+                })
+        };
+        let shown = first_big_enough(&self.levels);
+        let (dark, (px, at)) = match (shown, first_big_enough(&self.dark)) {
+            (Some(shown), Some(dark)) if !self.better(dark.0, shown.0) => (false, shown),
+            (_, Some(dark)) => (true, dark),
+            (shown, None) => (false, shown?),
+        };
+
+        let levels = if dark { &mut self.dark } else { &mut self.levels };
+        let q = levels.get_mut(px).expect("maker found above");
+        let maker = &mut self.slab.get_mut(at).order;
+        let fill = qty.min(maker.qty);
+        maker.qty -= fill;
+        let (id, left, owner) = (maker.id, maker.qty, maker.owner);
+        let mut shown = left;
+        let mut leaves = false;
+        if left == 0 {
+            match self.reserves.get_mut(&id) {
+                Some(reserve) => {
+                    maker.qty = maker.display_qty.map_or(*reserve, |display_qty| display_qty.min(*reserve));
+                    *reserve -= maker.qty;
+                    shown = maker.qty;
+                    if *reserve == 0 {
+                        self.reserves.remove(&id);
+                    }
+                    self.slab.move_to_back(q, at);
+                }
+                None => {
+                    leaves = true;
+                    self.slab.remove(q, at);
+                    if q.is_empty() {
+                        levels.remove(px);
+                    }
+                }
+            }
+        }
+        if leaves {
+            self.index.remove(&id);
+            disown(&mut self.owned, owner, id);
         }
+        if !dark {
+            self.totals.take(px, fill - (shown - left), leaves);
+        }
+        Some((id, px, fill))
     }
 
     /// Returns how many orders are waiting at best price
     /// Returns 0 if no price levels currently
     pub fn best_level_size(&self) -> usize {
         match self.best_price() {
-            Some(px) => self.levels.get(&px).map(|q| q.len()).unwrap_or(0),
+            Some(px) => self.levels.get(px).map(|q| q.len()).unwrap_or(0),
             None => 0,
         }
     }
@@ -85,141 +457,511 @@ impl PriceLevels {
         loop {
             // grabs the bes tprice and quantity of the order passed in
             let px = self.best_price()?;
-            let q = match self.levels.get_mut(&px) {
-                Some(q) => q,
-                None => return None, // should not happen
-            };
+            let q = self.levels.get_mut(px)?; // should not fail
 
             // Remove cancelled orders at front
-            while let Some(order) = q.pop_front() {
-                if self.canceled.remove(&order.id) {
-                    self.index.remove(&order.id);
+            while let Some(node) = self.slab.pop_front(q) {
+                if node.canceled {
+                    self.tombstones -= 1;
                     continue; // keep removing
-                } else {
-                    q.push_front(order); // put back
-                    break;
                 }
-            }
-
-            // clean up empty level if one left
-            if let Some(order) = q.pop_front() {
+                let mut order = node.order;
+                self.totals.take(px, order.qty, true);
+                // An iceberg comes back whole
+                order.qty += self.reserves.remove(&order.id).unwrap_or(0);
+                self.index.remove(&order.id);
+                disown(&mut self.owned, order.owner, order.id);
                 // now empty? yes -> clean
-                self.index.remove(&order.id); // already removed if canceled
                 if q.is_empty() {
-                    self.levels.remove(&px);
+                    self.levels.remove(px);
                 }
                 return Some(order);
-            } else {
-                // it was empty already
-                self.levels.remove(&px);
             }
+            // it was empty already
+            self.levels.remove(px);
         }
     }
 
     /// Sets an order to be canceled
-    /// Lazy removal, we remove during pop_best
+    /// Lazy removal, we remove during pop_best, unless built
+    /// `with_eager_cancel`
     /// Trye if Id was not cancled before, false if already
     pub fn cancel(&mut self, id: OrderId) -> bool {
-        if self.index.remove(&id).is_some() {
-            self.canceled.insert(id)
-        } else {
+        if self.eager_cancel {
+            return self.remove(id).is_some();
+        }
+        let Some(at) = self.index.remove(&id) else {
             return false;
+        };
+        self.reserves.remove(&id);
+        let node = self.slab.get_mut(at);
+        node.canceled = true;
+        disown(&mut self.owned, node.order.owner, id);
+        if !node.order.hidden {
+            self.totals.take(node.order.px_ticks, node.order.qty, true);
+        }
+        self.tombstones += 1;
+        if self.compact_at.is_some_and(|at| self.tombstones >= at) {
+            self.compact();
+        }
+        true
+    }
+
+    /// Takes every lazily-canceled order out of its queue, drops levels
+    /// left empty, and frees their slots. Returns how many orders were
+    /// taken out. Nothing live moves, so priority is unchanged.
+    pub fn compact(&mut self) -> usize {
+        let removed = std::mem::take(&mut self.tombstones);
+        if removed == 0 {
+            return 0;
+        }
+        let slab = &mut self.slab;
+        for levels in [&mut self.levels, &mut self.dark] {
+            levels.retain(|_, q| {
+                slab.retain(q, |node| !node.canceled);
+                !q.is_empty()
+            });
         }
+        removed
+    }
+
+    /// Cancels every live order whose `expires_ns` is at or before
+    /// `now_ns`, lazily like [`cancel`](Self::cancel). Returns their ids,
+    /// earliest expiry first.
+    pub fn expire(&mut self, now_ns: u128) -> Vec<OrderId> {
+        self.expire_priced(now_ns).into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// [`expire`](Self::expire), with the price each order rested at.
+    pub(crate) fn expire_priced(&mut self, now_ns: u128) -> Vec<(OrderId, i64)> {
+        let mut expired = Vec::new();
+        while let Some(&(expires_ns, id)) = self.expiries.first() {
+            if expires_ns > now_ns {
+                break;
+            }
+            self.expiries.pop_first();
+            // An entry left by an order since gone may name an id reused
+            // by one with another date, or none
+            let live = self.get(id).filter(|order| order.expires_ns == Some(expires_ns));
+            let Some(px_ticks) = live.map(|order| order.px_ticks) else { continue };
+            self.cancel(id);
+            expired.push((id, px_ticks));
+        }
+        expired
+    }
+
+    /// Cancels every live order priced from `lo` to `hi` inclusive, hidden
+    /// ones too, like [`cancel`](Self::cancel), then compacts so a mass
+    /// cancel leaves no tombstones behind. Returns their ids in matching
+    /// priority.
+    pub fn cancel_by_price_range(&mut self, lo: i64, hi: i64) -> Vec<OrderId> {
+        self.cancel_priced(lo, hi).into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Cancels every live order on this side; see
+    /// [`cancel_by_price_range`](Self::cancel_by_price_range).
+    pub fn cancel_all(&mut self) -> Vec<OrderId> {
+        self.cancel_by_price_range(i64::MIN, i64::MAX)
+    }
+
+    /// [`cancel_by_price_range`](Self::cancel_by_price_range), with the
+    /// price each order rested at.
+    pub(crate) fn cancel_priced(&mut self, lo: i64, hi: i64) -> Vec<(OrderId, i64)> {
+        let cancelled: Vec<(OrderId, i64)> = self
+            .live_orders()
+            .filter(|order| (lo..=hi).contains(&order.px_ticks))
+            .map(|order| (order.id, order.px_ticks))
+            .collect();
+        for &(id, _) in &cancelled {
+            self.cancel(id);
+        }
+        self.compact();
+        cancelled
+    }
+
+    /// Live orders `owner` has on this side, by id.
+    pub fn orders_of(&self, owner: ParticipantId) -> impl Iterator<Item = &Order> + '_ {
+        self.owned
+            .range((owner, OrderId(0))..=(owner, OrderId(u128::MAX)))
+            .filter_map(|&(_, id)| self.get(id))
+    }
+
+    /// Cancels every live order `owner` has on this side, like
+    /// [`cancel_by_price_range`](Self::cancel_by_price_range). Returns
+    /// their ids in id order.
+    pub fn cancel_all_for(&mut self, owner: ParticipantId) -> Vec<OrderId> {
+        self.cancel_owned_priced(owner).into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// [`cancel_all_for`](Self::cancel_all_for), with the price each order
+    /// rested at.
+    pub(crate) fn cancel_owned_priced(&mut self, owner: ParticipantId) -> Vec<(OrderId, i64)> {
+        let cancelled: Vec<(OrderId, i64)> = self.orders_of(owner).map(|order| (order.id, order.px_ticks)).collect();
+        for &(id, _) in &cancelled {
+            self.cancel(id);
+        }
+        self.compact();
+        cancelled
+    }
+
+    /// Earliest good-til-date expiry still tracked. It may belong to an
+    /// order that has since left the book; [`expire`](Self::expire) skips
+    /// those.
+    pub fn next_expiry(&self) -> Option<u128> {
+        self.expiries.first().map(|&(expires_ns, _)| expires_ns)
+    }
+
+    /// Takes `qty` off a live order in place, keeping its queue position.
+    /// An iceberg gives up hidden quantity first. Returns the quantity left;
+    /// at zero the order is removed outright, so feeds that reduce orders
+    /// away (ITCH replay) leave no tombstones. None if the id is not live on
+    /// this side.
+    pub fn reduce(&mut self, id: OrderId, mut qty: i64) -> Option<i64> {
+        let &at = self.index.get(&id)?;
+        let order = &mut self.slab.get_mut(at).order;
+        let mut hidden = 0;
+        if let Some(reserve) = self.reserves.get_mut(&id) {
+            let taken = qty.clamp(0, *reserve);
+            *reserve -= taken;
+            qty -= taken;
+            hidden = *reserve;
+            if hidden == 0 {
+                self.reserves.remove(&id);
+            }
+        }
+        let shown = (order.qty - qty).max(0);
+        if !order.hidden {
+            self.totals.take(order.px_ticks, order.qty - shown, false);
+        }
+        order.qty = shown;
+        let left = shown + hidden;
+        if left == 0 {
+            self.remove(id);
+        }
+        Some(left)
+    }
+
+    /// A live order by id; an iceberg shows its displayed slice.
+    pub fn get(&self, id: OrderId) -> Option<&Order> {
+        self.index.get(&id).map(|&at| &self.slab.get(at).order)
+    }
+
+    /// Price a live order rests at, without searching its queue.
+    pub fn price_of(&self, id: OrderId) -> Option<i64> {
+        self.get(id).map(|order| order.px_ticks)
     }
 
     /// True if an order id is present in this side
     pub fn contains(&self, id: OrderId) -> bool {
-        self.index.contains_key(&id) && !self.canceled.contains(&id)
+        self.index.contains_key(&id)
     }
 
     /// Total resting orders (count of orders, not price levels).
     pub fn total_len(&self) -> usize {
-        self.levels.values().map(|q| q.len()).sum::<usize>() - self.canceled.len()
+        self.index.len()
+    }
+
+    /// Resting hidden orders, a part of `total_len`.
+    pub fn hidden_len(&self) -> usize {
+        self.dark.iter().map(|(_, q)| self.slab.live(q).count()).sum()
+    }
+
+    /// Lazily-canceled orders still sitting in level queues.
+    pub fn tombstone_count(&self) -> usize {
+        self.tombstones
+    }
+
+    /// Entries in the id -> price index (live orders).
+    pub fn index_len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Price levels held, including ones with only tombstones left and
+    /// ones holding only hidden orders.
+    pub fn level_count(&self) -> usize {
+        self.levels.len() + self.dark.len()
     }
 
     /// Peek (borrow) the best order without removing it.
+    /// There is deliberately no `peek_best_mut`: writing a queued order's
+    /// quantity or price directly would leave the level totals and the
+    /// id index stale. To take quantity off the best maker in place, as
+    /// matching does, use [`fill_best`](Self::fill_best); to shrink any
+    /// live order in place, [`reduce`](Self::reduce).
     pub fn peek_best(&self) -> Option<&Order> {
         let px = self.best_price()?;
-        let q = self.levels.get(&px)?;
-        
-        for order in q {
-            if !self.canceled.contains(&order.id) {
-                return Some(order);
-            }
-        }
-        None
+        let q = self.levels.get(px)?;
+
+        self.slab.live(q).next()
     }
 
     /// Sum quantity available at a specific price level.
+    /// Icebergs count only their displayed slice.
+    /// Saturates at i64::MAX rather than overflowing on huge resting sizes.
     pub fn qty_at_price(&self, px_ticks: i64) -> i64 {
-        self.levels.get(&px_ticks)
-            .map(|q| q.iter()
-                .filter(|order| !self.canceled.contains(&order.id))
-                .map(|order| order.qty)
-                .sum())
-            .unwrap_or(0)
+        self.totals.level(px_ticks).map_or(0, LevelTotal::qty)
+    }
+
+    /// Displayed quantity across every level of this side, the same
+    /// quantity `qty_at_price` counts. Kept as orders come and go, so it
+    /// costs nothing to read. Saturates at i64::MAX.
+    pub fn total_qty(&self) -> i64 {
+        self.totals.qty.min(i64::MAX as i128) as i64
+    }
+
+    /// The best `levels` displayed levels with a live order, best first,
+    /// each with its displayed quantity and live order count. Read from
+    /// running totals, so it costs one lookup per level whatever the
+    /// queues hold.
+    pub fn depth(&self, levels: usize) -> Vec<BookLevel> {
+        self.best_first(&self.levels)
+            .filter_map(|(px_ticks, _)| {
+                let total = self.totals.level(px_ticks)?;
+                Some(BookLevel { px_ticks, qty: total.qty(), orders: total.orders })
+            })
+            .take(levels)
+            .collect()
     }
 
     /// Iterate prices in matching priority (best→worst) with total qty per price.
+    /// Like `qty_at_price`, only displayed quantity is counted, and prices
+    /// with nothing but hidden orders are left out.
     pub fn iter_levels_best_first(&self) -> Box<dyn Iterator<Item = (i64, i64)> + '_> {
         match self.side {
             Side::Ask => {
-                Box::new(self.levels.iter().map(move |(px, q)| {
-                    let total_qty: i64 = q.iter()
-                        .filter(|order| !self.canceled.contains(&order.id))
-                        .map(|order| order.qty)
-                        .sum();
-                    (*px, total_qty)
+                Box::new(self.levels.iter().map(move |(px, _)| {
+                    (px, self.qty_at_price(px))
                 }))
             }
             Side::Bid => {
-                Box::new(self.levels.iter().rev().map(move |(px, q)| {
-                    let total_qty: i64 = q.iter()
-                        .filter(|order| !self.canceled.contains(&order.id))
-                        .map(|order| order.qty)
-                        .sum();
-                    (*px, total_qty)
+                Box::new(self.levels.iter().rev().map(move |(px, _)| {
+                    (px, self.qty_at_price(px))
                 }))
             }
         }
     }
 
-    /// Remove a specific order by id (eager cancel).
-    /// Returns the removed order if found (useful for amendments).
-    pub fn remove(&mut self, id: OrderId) -> Option<Order> {
-        let px_ticks = self.index.remove(&id)?;
-        self.canceled.remove(&id);
-        
-        let q = self.levels.get_mut(&px_ticks)?;
-        let mut found_order = None;
-        
-        let mut temp_orders = Vec::new();
-        while let Some(order) = q.pop_front() {
-            if order.id == id {
-                found_order = Some(order);
+    /// Live quantity at prices crossing `limit_px`, counted best first
+    /// and only until it reaches `up_to`. Unlike depth, this includes
+    /// what icebergs hold back and hidden orders, since a taker can fill
+    /// against them.
+    pub fn fillable_qty(&self, limit_px: i64, up_to: i64) -> i64 {
+        let mut total = 0i64;
+        let crossing = |levels| self.best_first(levels).take_while(|&(px, _)| self.crosses(px, limit_px));
+        for (_, q) in crossing(&self.levels).chain(crossing(&self.dark)) {
+            if total >= up_to {
                 break;
-            } else {
-                temp_orders.push(order);
+            }
+            total = self.slab.live(q).fold(total, |total, order| total.saturating_add(order.qty));
+            if !self.reserves.is_empty() {
+                total = self.slab.live(q).fold(total, |total, order| total.saturating_add(self.hidden_qty(order.id)));
+            }
+        }
+        total
+    }
+
+    /// Live (non-canceled) orders: the displayed ones in matching
+    /// priority, best level first and FIFO within a level, then the hidden
+    /// ones in the same order.
+    pub fn live_orders(&self) -> Box<dyn Iterator<Item = &Order> + '_> {
+        let queues = self.best_first(&self.levels).chain(self.best_first(&self.dark)).map(|(_, q)| q);
+        Box::new(queues.flat_map(|q| self.slab.live(q)))
+    }
+
+    /// Checks this side's queues against its slab, index, tombstone count
+    /// and running totals (see [`invariants`](crate::invariants)). Walks
+    /// every queued order, so it is meant for tests and fuzzing.
+    pub fn check_invariants(&self) -> Result<(), Violation> {
+        let side = self.side;
+        let mut live = 0;
+        let mut tombstones = 0;
+        let mut queued = 0;
+        let mut side_qty = 0i128;
+        let mut owned = 0;
+        if !self.slab.free_list_sound() {
+            return Err(Violation::FreeList { side });
+        }
+        for (dark, levels) in [(false, &self.levels), (true, &self.dark)] {
+            if !levels.consistent() {
+                return Err(Violation::Ladder { side });
+            }
+            for (px_ticks, q) in levels.iter() {
+                if q.is_empty() {
+                    return Err(Violation::EmptyLevel { side, px_ticks });
+                }
+                if !self.slab.linked(q) {
+                    return Err(Violation::Links { side, px_ticks });
+                }
+                let (mut qty, mut orders) = (0i128, 0);
+                let mut last_ts = 0;
+                let mut linked = 0;
+                for (at, node) in self.slab.iter(q) {
+                    linked += 1;
+                    let order = &node.order;
+                    let id = order.id;
+                    if order.side != side || order.px_ticks != px_ticks || order.hidden != dark {
+                        return Err(Violation::Misplaced { side, px_ticks, id });
+                    }
+                    if node.canceled {
+                        tombstones += 1;
+                        continue;
+                    }
+                    live += 1;
+                    if self.index.get(&id) != Some(&at) {
+                        return Err(Violation::Index { side, id });
+                    }
+                    if order.owner != ParticipantId::NONE {
+                        if !self.owned.contains(&(order.owner, id)) {
+                            return Err(Violation::Owner { side, id });
+                        }
+                        owned += 1;
+                    }
+                    if order.qty <= 0 {
+                        return Err(Violation::EmptyOrder { side, id });
+                    }
+                    let slice = order.display_qty.filter(|&display_qty| display_qty > 0 && !dark);
+                    if slice.map_or(self.reserves.contains_key(&id), |display_qty| order.qty > display_qty) {
+                        return Err(Violation::Iceberg { side, id });
+                    }
+                    if order.display_qty.is_none() {
+                        if order.ts_ns < last_ts {
+                            return Err(Violation::OutOfTimeOrder { side, px_ticks, id });
+                        }
+                        last_ts = order.ts_ns;
+                    }
+                    qty += order.qty as i128;
+                    orders += 1;
+                }
+                if linked != q.len() {
+                    return Err(Violation::Slab { side, held: q.len(), queued: linked });
+                }
+                queued += linked;
+                if dark {
+                    continue;
+                }
+                side_qty += qty;
+                let counted = (qty.min(i64::MAX as i128) as i64, orders);
+                let cached = self.totals.level(px_ticks).map_or((0, 0), |total| (total.qty(), total.orders));
+                if cached != counted {
+                    return Err(Violation::LevelTotal { side, px_ticks, cached, counted });
+                }
             }
         }
-        
-        for order in temp_orders.into_iter().rev() {
-            q.push_front(order);
+        if self.slab.len() != queued {
+            return Err(Violation::Slab { side, held: self.slab.len(), queued });
         }
-        
+        if let Some((&px_ticks, total)) = self.totals.levels.iter().find(|&(&px, _)| !self.levels.contains_key(px)) {
+            return Err(Violation::LevelTotal { side, px_ticks, cached: (total.qty(), total.orders), counted: (0, 0) });
+        }
+        if self.totals.qty != side_qty {
+            let saturate = |qty: i128| qty.min(i64::MAX as i128) as i64;
+            return Err(Violation::SideTotal { side, cached: saturate(self.totals.qty), counted: saturate(side_qty) });
+        }
+        if self.index.len() != live {
+            let queued = |id, at| self.slab.try_get(at).is_some_and(|node| node.order.id == id && !node.canceled);
+            let id = self.index.iter().find(|&(&id, &at)| !queued(id, at)).map_or(OrderId(0), |(&id, _)| id);
+            return Err(Violation::Index { side, id });
+        }
+        if let Some(&id) = self.reserves.keys().find(|id| !self.index.contains_key(id)) {
+            return Err(Violation::Iceberg { side, id });
+        }
+        if self.owned.len() != owned {
+            let stale = |&&(owner, id): &&(ParticipantId, OrderId)| self.get(id).is_none_or(|order| order.owner != owner);
+            let id = self.owned.iter().find(stale).map_or(OrderId(0), |&(_, id)| id);
+            return Err(Violation::Owner { side, id });
+        }
+        if self.tombstones != tombstones {
+            return Err(Violation::Tombstones { side, recorded: self.tombstones, queued: tombstones });
+        }
+        Ok(())
+    }
+
+    /// `levels` (displayed or hidden) best price first.
+    fn best_first<'a>(
+        &self,
+        levels: &'a Levels,
+    ) -> Box<dyn Iterator<Item = (i64, &'a Queue)> + 'a> {
+        match self.side {
+            Side::Ask => Box::new(levels.iter()),
+            Side::Bid => Box::new(levels.iter().rev()),
+        }
+    }
+
+    /// Remove a specific order by id (eager cancel), wherever it is queued.
+    /// Returns the removed order if found (useful for amendments), with an
+    /// iceberg's hidden quantity added back.
+    pub fn remove(&mut self, id: OrderId) -> Option<Order> {
+        let at = self.index.remove(&id)?;
+        let hidden = self.reserves.remove(&id).unwrap_or(0);
+        let order = &self.slab.get(at).order;
+        let (px_ticks, dark) = (order.px_ticks, order.hidden);
+        let levels = if dark { &mut self.dark } else { &mut self.levels };
+        let q = levels.get_mut(px_ticks)?;
+        let order = self.slab.remove(q, at).order;
         if q.is_empty() {
-            self.levels.remove(&px_ticks);
+            levels.remove(px_ticks);
+        }
+        if !dark {
+            self.totals.take(px_ticks, order.qty, true);
+        }
+        disown(&mut self.owned, order.owner, id);
+        Some(Order { qty: order.qty + hidden, ..order })
+    }
+
+}
+
+/// Drops a live order leaving the side from the owner index.
+fn disown(owned: &mut BTreeSet<(ParticipantId, OrderId)>, owner: ParticipantId, id: OrderId) {
+    if owner != ParticipantId::NONE {
+        owned.remove(&(owner, id));
+    }
+}
+
+/// Best price in `levels` that still has a live order, dropping canceled
+/// orders queued ahead of it and levels left empty.
+fn live_best(side: Side, levels: &mut Levels, slab: &mut Slab, tombstones: &mut usize) -> Option<i64> {
+    loop {
+        let (px, q) = levels.best_mut(side)?;
+        while let Some(at) = q.front() {
+            if !slab.get(at).canceled {
+                return Some(px);
+            }
+            slab.pop_front(q);
+            *tombstones -= 1;
         }
-        
-        found_order
+        levels.remove(px);
     }
+}
 
+/// Front order of a level known to be non-empty.
+///
+/// Levels are removed the moment their queue empties (`push` never creates
+/// an empty one) and `fill_best` drops tombstone-only levels before calling
+/// this, so the `None` case is unreachable, and the index it holds is
+/// always occupied. A deserialized side is refused unless its queues are
+/// linked as the slab links them, so loading one can't break either. With
+/// the `unchecked` feature neither is checked in release builds; debug
+/// builds, the model tests, and the `book_ops` fuzz target still assert
+/// both.
+#[inline(always)]
+fn live_front<'a>(slab: &'a mut Slab, q: &Queue) -> &'a mut Order {
+    #[cfg(feature = "unchecked")]
+    {
+        debug_assert!(!q.is_empty(), "empty price level left in the book");
+        // SAFETY: the queue is non-empty per the level invariant above, and
+        // a queued index always holds an order
+        unsafe { &mut slab.get_unchecked_mut(q.front().unwrap_unchecked()).order }
+    }
+    #[cfg(not(feature = "unchecked"))]
+    {
+        &mut slab.get_mut(q.front().expect("empty price level left in the book")).order
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{Order, OrderId, Side};
+    use crate::types::{Order, OrderId, ParticipantId, Side, TimeInForce};
 
     #[test]
     fn test_new_empty() {
@@ -241,6 +983,12 @@ mod tests {
             px_ticks: 10100,
             qty: 10,
             ts_ns: 1,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         };
         let o2 = Order {
             id: OrderId(2),
@@ -249,6 +997,12 @@ mod tests {
             px_ticks: 10100,
             qty: 20,
             ts_ns: 2,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         };
         let o3 = Order {
             id: OrderId(3),
@@ -257,14 +1011,20 @@ mod tests {
             px_ticks: 10100,
             qty: 30,
             ts_ns: 3,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         };
 
         levels.push(o1.clone());
         levels.push(o2.clone());
         levels.push(o3.clone());
 
-        let q = levels.levels.get(&10100).expect("price level exists");
-        let ids: Vec<u128> = q.iter().map(|o| o.id.0).collect();
+        let q = levels.levels.get(10100).expect("price level exists");
+        let ids: Vec<u128> = levels.slab.live(q).map(|o| o.id.0).collect();
         assert_eq!(
             ids,
             vec![1, 2, 3],
@@ -292,6 +1052,12 @@ mod tests {
             px_ticks: 10200,
             qty: 10,
             ts_ns: 1,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         // Higher price different time stamp
@@ -302,6 +1068,12 @@ mod tests {
             px_ticks: 10250,
             qty: 20,
             ts_ns: 2,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         // Same idea
@@ -312,6 +1084,12 @@ mod tests {
             px_ticks: 10300,
             qty: 30,
             ts_ns: 3,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         assert_eq!(asks.best_level_size(), 1);
@@ -323,6 +1101,12 @@ mod tests {
             px_ticks: 10200,
             qty: 40,
             ts_ns: 4,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         assert_eq!(asks.best_level_size(), 2);
@@ -340,6 +1124,12 @@ mod tests {
             px_ticks: 10100,
             qty: 10,
             ts_ns: 1,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         bids.push(Order {
@@ -349,6 +1139,12 @@ mod tests {
             px_ticks: 10050,
             qty: 20,
             ts_ns: 2,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         assert_eq!(bids.best_level_size(), 1);
@@ -360,6 +1156,12 @@ mod tests {
             px_ticks: 10100,
             qty: 30,
             ts_ns: 3,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         assert_eq!(bids.best_level_size(), 2);
@@ -386,6 +1188,12 @@ mod tests {
             px_ticks: 10200,
             qty: 10,
             ts_ns: 1,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         asks.push(Order {
@@ -395,6 +1203,12 @@ mod tests {
             px_ticks: 10200,
             qty: 20,
             ts_ns: 2,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         // add a worse order
@@ -405,6 +1219,12 @@ mod tests {
             px_ticks: 10300,
             qty: 30,
             ts_ns: 3,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         // First pop
@@ -432,6 +1252,12 @@ mod tests {
             px_ticks: 10200,
             qty: 10,
             ts_ns: 1,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         bids.push(Order {
@@ -441,6 +1267,12 @@ mod tests {
             px_ticks: 10200,
             qty: 20,
             ts_ns: 2,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         // add a worse order
@@ -451,6 +1283,12 @@ mod tests {
             px_ticks: 10100,
             qty: 30,
             ts_ns: 3,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         });
 
         // First pop
@@ -477,6 +1315,12 @@ mod tests {
             px_ticks: 10100,
             qty: 10,
             ts_ns: 1,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         };
         let o2 = Order {
             id: OrderId(2),
@@ -485,6 +1329,12 @@ mod tests {
             px_ticks: 10100,
             qty: 20,
             ts_ns: 2,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         };
         let o3 = Order {
             id: OrderId(3),
@@ -493,6 +1343,12 @@ mod tests {
             px_ticks: 10050,
             qty: 30,
             ts_ns: 3,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         };
 
         bids.push(o1.clone());
@@ -524,9 +1380,331 @@ mod tests {
             px_ticks: 10200,
             qty: 10,
             ts_ns: 1,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         };
         asks.push(o1);
         // you have something and can cancel it? returns true
         assert!(asks.cancel(OrderId(1)));
     }
+
+    #[test]
+    fn level_qty_saturates_on_huge_orders() {
+        let mut bids = PriceLevels::new(Side::Bid);
+        for id in 1..=2 {
+            bids.push(Order {
+                id: OrderId(id),
+                symbol: "AAPL".into(),
+                side: Side::Bid,
+                px_ticks: 10000,
+                qty: i64::MAX,
+                ts_ns: id,
+                tif: TimeInForce::Day,
+                expires_ns: None,
+                display_qty: None,
+                min_qty: None,
+                hidden: false,
+                owner: ParticipantId::NONE,
+            });
+        }
+        // Found by the book_ops fuzz target: summing these overflowed
+        assert_eq!(bids.qty_at_price(10000), i64::MAX);
+        assert_eq!(bids.iter_levels_best_first().next(), Some((10000, i64::MAX)));
+    }
+
+    #[test]
+    fn fill_best_partial_keeps_queue_slot() {
+        let mut asks = PriceLevels::new(Side::Ask);
+        for (id, px) in [(1, 10200), (2, 10200), (3, 10300)] {
+            asks.push(Order {
+                id: OrderId(id),
+                symbol: "NVDA".into(),
+                side: Side::Ask,
+                px_ticks: px,
+                qty: 10,
+                ts_ns: id,
+                tif: TimeInForce::Day,
+                expires_ns: None,
+                display_qty: None,
+                min_qty: None,
+                hidden: false,
+                owner: ParticipantId::NONE,
+            });
+        }
+        asks.cancel(OrderId(1));
+
+        // Skips the canceled front order, partially fills the next in place
+        assert_eq!(asks.fill_best(10200, 4), Some((OrderId(2), 10200, 4)));
+        assert_eq!(asks.peek_best().map(|o| (o.id, o.qty)), Some((OrderId(2), 6)));
+        assert!(asks.contains(OrderId(2)));
+
+        // A full fill removes it and empties the level
+        assert_eq!(asks.fill_best(10300, 100), Some((OrderId(2), 10200, 6)));
+        assert!(!asks.contains(OrderId(2)));
+        assert_eq!(asks.best_price(), Some(10300));
+
+        // Nothing left crosses a limit below the next level
+        assert_eq!(asks.fill_best(10299, 100), None);
+        assert_eq!(asks.total_len(), 1);
+    }
+
+    #[test]
+    fn reduce_keeps_queue_slot_and_cancels_at_zero() {
+        let mut bids = PriceLevels::new(Side::Bid);
+        for id in [1, 2] {
+            bids.push(Order {
+                id: OrderId(id),
+                symbol: "NVDA".into(),
+                side: Side::Bid,
+                px_ticks: 10100,
+                qty: 10,
+                ts_ns: id,
+                tif: TimeInForce::Day,
+                expires_ns: None,
+                display_qty: None,
+                min_qty: None,
+                hidden: false,
+                owner: ParticipantId::NONE,
+            });
+        }
+
+        assert_eq!(bids.reduce(OrderId(1), 3), Some(7));
+        assert_eq!(bids.peek_best().map(|o| (o.id, o.qty)), Some((OrderId(1), 7)));
+        assert_eq!(bids.qty_at_price(10100), 17);
+
+        assert_eq!(bids.reduce(OrderId(1), 9), Some(0));
+        assert!(!bids.contains(OrderId(1)));
+        assert_eq!(bids.tombstone_count(), 0);
+        assert_eq!(bids.reduce(OrderId(1), 1), None);
+        assert_eq!(bids.peek_best().map(|o| o.id), Some(OrderId(2)));
+        assert_eq!(bids.reduce(OrderId(9), 1), None);
+    }
+
+    #[test]
+    fn iceberg_shows_one_slice_and_reloads_at_the_back() {
+        let mut asks = PriceLevels::new(Side::Ask);
+        let order = |id, qty, display_qty| Order {
+            id: OrderId(id),
+            symbol: "NVDA".into(),
+            side: Side::Ask,
+            px_ticks: 10100,
+            qty,
+            ts_ns: id,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        asks.push(order(1, 25, Some(10)));
+        asks.push(order(2, 5, None));
+
+        // Depth shows the slice; a taker can still reach the reserve
+        assert_eq!(asks.qty_at_price(10100), 15);
+        assert_eq!(asks.hidden_qty(OrderId(1)), 15);
+        assert_eq!(asks.fillable_qty(10100, i64::MAX), 30);
+
+        // Filling the slice sends the next one behind order 2
+        assert_eq!(asks.fill_best(10100, 12), Some((OrderId(1), 10100, 10)));
+        assert_eq!(asks.peek_best().map(|o| o.id), Some(OrderId(2)));
+        assert_eq!(asks.fill_best(10100, 2), Some((OrderId(2), 10100, 2)));
+        assert_eq!(asks.qty_at_price(10100), 13);
+        assert_eq!(asks.hidden_qty(OrderId(1)), 5);
+
+        // Removed, it comes back with what it held back
+        assert_eq!(asks.remove(OrderId(1)).map(|o| o.qty), Some(15));
+        assert_eq!(asks.hidden_qty(OrderId(1)), 0);
+        assert_eq!(asks.fillable_qty(10100, i64::MAX), 3);
+    }
+
+    /// The running level and side totals match a recount of the queues
+    /// through every kind of change, lazily cancelled orders included.
+    #[test]
+    fn level_totals_track_every_change() {
+        let mut rng = crate::rng::Rng::seed_from_u64(7);
+        let mut asks = PriceLevels::new(Side::Ask);
+        for id in 1..=2_000u128 {
+            let px_ticks = 100 + rng.below(6) as i64;
+            // Often the first two orders of the lowest level, where one may
+            // have filled in place
+            let head: Vec<OrderId> = asks.levels.iter().next().map(|(_, q)| asks.slab.iter(q).take(2).map(|(_, node)| node.order.id).collect()).unwrap_or_default();
+            let picked = match rng.below(3) {
+                0 => head.first(),
+                1 => head.last(),
+                _ => None,
+            };
+            let target = picked.copied().unwrap_or(OrderId(rng.below(id as u64) as u128 + 1));
+            let order = Order {
+                id: OrderId(id),
+                symbol: "NVDA".into(),
+                side: Side::Ask,
+                px_ticks,
+                qty: 1 + rng.below(20) as i64,
+                ts_ns: id,
+                tif: TimeInForce::Day,
+                expires_ns: None,
+                display_qty: rng.next_bool().then_some(5),
+                min_qty: None,
+                hidden: rng.below(5) == 0,
+                owner: ParticipantId::NONE,
+            };
+            match rng.below(9) {
+                0..=1 => asks.push(order),
+                2 => asks.push_front(order),
+                3 => {
+                    asks.fill_best(px_ticks, 1 + rng.below(30) as i64);
+                }
+                4 => {
+                    asks.fill_best_min(px_ticks, 1 + rng.below(30) as i64, 1 + rng.below(8) as i64);
+                }
+                5 => {
+                    asks.cancel(target);
+                }
+                6 => {
+                    asks.reduce(target, rng.below(8) as i64);
+                }
+                _ => match asks.remove(target).or_else(|| asks.pop_best()) {
+                    Some(order) if rng.next_bool() => asks.push_front(order),
+                    _ => {}
+                },
+            }
+            if id % 97 == 0 {
+                asks.compact();
+            }
+            for (px_ticks, q) in asks.levels.iter() {
+                let live: Vec<&Order> = asks.slab.live(q).collect();
+                let level = asks.depth(usize::MAX).into_iter().find(|l| l.px_ticks == px_ticks);
+                let expected = (!live.is_empty()).then(|| BookLevel { px_ticks, qty: live.iter().map(|o| o.qty).sum(), orders: live.len() });
+                assert_eq!(level, expected, "after step {}", id);
+            }
+            let shown: i64 = asks.depth(usize::MAX).iter().map(|level| level.qty).sum();
+            assert_eq!(asks.total_qty(), shown, "after step {}", id);
+        }
+    }
+
+    /// An order that filled in place at the front and was then pushed
+    /// back by another still takes only what is left off when cancelled.
+    #[test]
+    fn displaced_front_cancels_what_is_left() {
+        let mut asks = PriceLevels::new(Side::Ask);
+        let first = Order {
+            id: OrderId(1),
+            symbol: "NVDA".into(),
+            side: Side::Ask,
+            px_ticks: 10100,
+            qty: 10,
+            ts_ns: 1,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        asks.push(first.clone());
+        asks.fill_best(10100, 4);
+        asks.push_front(Order { id: OrderId(2), qty: 5, ..first });
+        assert!(asks.cancel(OrderId(1)));
+        assert_eq!(asks.qty_at_price(10100), 5);
+    }
+
+    /// Taking an order out of the middle of its level keeps the rest in
+    /// order, and the freed slot is reused rather than the slab growing.
+    #[test]
+    fn eager_cancel_leaves_no_tombstones() {
+        let order = |id, px_ticks| Order { id: OrderId(id), symbol: "AAPL".into(), side: Side::Ask, px_ticks, qty: 5, ts_ns: id, tif: TimeInForce::Day, expires_ns: Some(50), display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE };
+        let mut asks = PriceLevels::new(Side::Ask).with_eager_cancel();
+        for id in 1..=3 {
+            asks.push(order(id, 100));
+        }
+        asks.push(Order { expires_ns: None, ..order(4, 101) });
+
+        // Out of the middle of its queue, the rest keeping their order
+        assert!(asks.cancel(OrderId(2)));
+        assert!(!asks.cancel(OrderId(2)));
+        assert_eq!(asks.tombstone_count(), 0);
+        let ids: Vec<_> = asks.live_orders().map(|o| o.id.0).collect();
+        assert_eq!(ids, [1, 3, 4]);
+
+        // Expiry cancels the same way, and the emptied level goes
+        assert_eq!(asks.expire(50), [OrderId(1), OrderId(3)]);
+        assert_eq!((asks.tombstone_count(), asks.level_count()), (0, 1));
+        assert_eq!(asks.best_price(), Some(101));
+        assert_eq!(asks.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn removal_reuses_slab_slots() {
+        let mut asks = PriceLevels::new(Side::Ask);
+        let order = |id| Order {
+            id: OrderId(id),
+            symbol: "NVDA".into(),
+            side: Side::Ask,
+            px_ticks: 10100,
+            qty: 10,
+            ts_ns: id,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        for id in 1..=3 {
+            asks.push(order(id));
+        }
+        assert_eq!(asks.remove(OrderId(2)).map(|o| o.id), Some(OrderId(2)));
+        asks.push(order(4));
+        assert!(asks.cancel(OrderId(3)));
+        assert_eq!(asks.compact(), 1);
+        asks.push(order(5));
+
+        let ids: Vec<OrderId> = asks.live_orders().map(|o| o.id).collect();
+        assert_eq!(ids, [OrderId(1), OrderId(4), OrderId(5)]);
+        assert_eq!(asks.slab.slots(), 3);
+        assert_eq!(asks.check_invariants(), Ok(()));
+    }
+
+    /// A side loaded from a file must hold together like one built by
+    /// pushes, or matching could walk its queues into a vacant slot.
+    #[test]
+    fn loading_refuses_a_side_with_broken_queues() {
+        let mut asks = PriceLevels::new(Side::Ask);
+        let order = |id| Order {
+            id: OrderId(id),
+            symbol: "NVDA".into(),
+            side: Side::Ask,
+            px_ticks: 10100,
+            qty: 10,
+            ts_ns: id,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        };
+        for id in 1..=3 {
+            asks.push(order(id));
+        }
+        let saved = serde_json::to_value(&asks).unwrap();
+        assert!(serde_json::from_value::<PriceLevels>(saved.clone()).is_ok());
+
+        // A queued slot left vacant, an order linked to itself, and an
+        // occupied slot offered for reuse
+        let mut vacated = saved.clone();
+        vacated["slab"]["nodes"][1] = serde_json::Value::Null;
+        let mut looped = saved.clone();
+        looped["slab"]["nodes"][1]["next"] = 1.into();
+        let mut reused = saved;
+        reused["slab"]["free"] = serde_json::json!([0]);
+        for broken in [vacated, looped, reused] {
+            assert!(serde_json::from_value::<PriceLevels>(broken).is_err());
+        }
+    }
 }
@@ -0,0 +1,150 @@
+//! Two-sided quotes: a bid and an ask a market maker replaces as one.
+//!
+//! Each owner has at most one quote per book. Submitting a new one first
+//! cancels whatever is left of the last, then enters the new bid and ask
+//! as ordinary limit orders, so replacing a quote never leaves both pairs
+//! resting at once. A side with zero quantity is simply not quoted, and
+//! a quote of zero on both sides pulls the owner's quote altogether.
+//!
+//! A quote may not cross itself: its bid must be below its ask whenever
+//! both sides are quoted. Either side may still trade against the book on
+//! arrival like any limit order.
+
+use crate::types::{ExecutionReport, Order, OrderId, RejectReason, Side};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The orders an owner's current quote entered, by side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quote {
+    pub bid: Option<OrderId>,
+    pub ask: Option<OrderId>,
+}
+
+/// A quote refused before anything was cancelled or entered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteError {
+    /// The bid is not below the ask
+    Crossed { bid_px: i64, ask_px: i64 },
+    /// An order on the wrong side, or with a negative quantity
+    Malformed,
+    /// A quoted side the book would refuse as an order
+    Rejected(RejectReason),
+}
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuoteError::Crossed { bid_px, ask_px } => write!(f, "quote bid {} is not below its ask {}", bid_px, ask_px),
+            QuoteError::Malformed => write!(f, "a quote takes a bid and an ask of non-negative quantity"),
+            QuoteError::Rejected(reason) => reason.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for QuoteError {}
+
+/// What [`OrderBook::submit_quote`](crate::OrderBook::submit_quote) did:
+/// the orders of the last quote it cancelled, with what they had left,
+/// and a report for each side it quoted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuoteReport {
+    pub replaced: Vec<Order>,
+    pub bid: Option<ExecutionReport>,
+    pub ask: Option<ExecutionReport>,
+}
+
+/// Checks that `bid` and `ask` make a quote: a bid and an ask, neither
+/// negative, and not crossed when both are quoted.
+pub fn check(bid: &Order, ask: &Order) -> Result<(), QuoteError> {
+    if bid.side != Side::Bid || ask.side != Side::Ask || bid.qty < 0 || ask.qty < 0 {
+        return Err(QuoteError::Malformed);
+    }
+    if bid.qty > 0 && ask.qty > 0 && bid.px_ticks >= ask.px_ticks {
+        return Err(QuoteError::Crossed { bid_px: bid.px_ticks, ask_px: ask.px_ticks });
+    }
+    Ok(())
+}
+
+/// Each owner's current quote on one book. Its ids may since have filled
+/// or been cancelled; replacing the quote skips those. An id submitted
+/// again is forgotten first (see [`forget`](Self::forget)), so replacing
+/// a quote never takes out someone else's order.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Quotes {
+    quotes: HashMap<String, Quote>,
+}
+
+impl Quotes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes `quote` `owner`'s current one, returning the one it replaces.
+    /// A quote with neither side forgets the owner.
+    pub fn replace(&mut self, owner: &str, quote: Quote) -> Option<Quote> {
+        if quote == Quote::default() {
+            return self.quotes.remove(owner);
+        }
+        self.quotes.insert(owner.to_string(), quote)
+    }
+
+    /// Drops `id` from whichever quote entered it, once the order it
+    /// named is gone and the id may name another. Linear in the owners
+    /// quoting, which are few.
+    pub fn forget(&mut self, id: OrderId) {
+        for quote in self.quotes.values_mut() {
+            for side in [&mut quote.bid, &mut quote.ask] {
+                if *side == Some(id) {
+                    *side = None;
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, owner: &str) -> Option<&Quote> {
+        self.quotes.get(owner)
+    }
+
+    /// Each owner with a quote, and the quote, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Quote)> {
+        self.quotes.iter().map(|(owner, quote)| (owner.as_str(), quote))
+    }
+
+    pub fn len(&self) -> usize {
+        self.quotes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.quotes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ParticipantId, TimeInForce};
+
+    #[test]
+    fn quotes_must_be_a_bid_below_an_ask() {
+        let order = |side, px_ticks, qty| Order { id: OrderId(1), symbol: "AAPL".into(), side, px_ticks, qty, ts_ns: 0, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE };
+        assert_eq!(check(&order(Side::Bid, 99, 5), &order(Side::Ask, 101, 5)), Ok(()));
+        assert_eq!(check(&order(Side::Bid, 101, 5), &order(Side::Ask, 101, 5)), Err(QuoteError::Crossed { bid_px: 101, ask_px: 101 }));
+        // One side pulled can't cross
+        assert_eq!(check(&order(Side::Bid, 101, 5), &order(Side::Ask, 100, 0)), Ok(()));
+        assert_eq!(check(&order(Side::Ask, 99, 5), &order(Side::Ask, 101, 5)), Err(QuoteError::Malformed));
+        assert_eq!(check(&order(Side::Bid, 99, -1), &order(Side::Ask, 101, 5)), Err(QuoteError::Malformed));
+
+        let mut quotes = Quotes::new();
+        let quote = Quote { bid: Some(OrderId(1)), ask: None };
+        assert_eq!(quotes.replace("mm", quote), None);
+        quotes.forget(OrderId(2));
+        assert_eq!(quotes.get("mm"), Some(&quote));
+        assert_eq!(quotes.replace("mm", Quote::default()), Some(quote));
+        assert!(quotes.is_empty());
+        quotes.replace("mm", quote);
+        quotes.forget(OrderId(1));
+        assert_eq!(quotes.get("mm"), Some(&Quote::default()));
+    }
+}
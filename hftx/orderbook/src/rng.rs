@@ -0,0 +1,114 @@
+//! Seedable pseudo-random generator shared by the flow generator, perf
+//! harness, and simulators.
+//!
+//! SplitMix64: tiny, fast, and statistically good enough for workload
+//! generation. Not cryptographic. The same seed always yields the same
+//! sequence on every platform, so any run can be reproduced from its seed.
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Deterministic 64-bit generator.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Generator producing the sequence identified by `seed`.
+    pub fn seed_from_u64(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Picks a seed from the wall clock, for runs that don't specify one.
+    /// Callers should log it so the run can be reproduced.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn entropy_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0x9E37_79B9_7F4A_7C15)
+            ^ 0xDEAD_BEEF_CAFE_BABE
+    }
+
+    /// Browsers have no `SystemTime`; wasm callers should pass a seed from
+    /// JS (e.g. `Date.now()`). This fixed seed keeps the API compiling.
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn entropy_seed() -> u64 {
+        0x9E37_79B9_7F4A_7C15 ^ 0xDEAD_BEEF_CAFE_BABE
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`; returns 0 when `n == 0`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        if n == 0 {
+            0
+        } else {
+            self.next_u64() % n
+        }
+    }
+
+    /// Fair coin.
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() & 1 == 0
+    }
+
+    /// Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in (0, 1], safe to take the log of.
+    pub fn next_f64_open(&mut self) -> f64 {
+        1.0 - self.next_f64()
+    }
+
+    /// Standard normal via Box-Muller.
+    pub fn next_gaussian(&mut self) -> f64 {
+        let u1 = self.next_f64_open();
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+
+    /// Derives an independent generator, e.g. one per symbol from a run seed.
+    pub fn fork(&mut self) -> Rng {
+        Rng(self.next_u64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::seed_from_u64(99);
+        let mut b = Rng::seed_from_u64(99);
+        for _ in 0..1_000 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn known_first_output() {
+        // Reference SplitMix64 output for seed 0; pins the sequence across releases
+        assert_eq!(Rng::seed_from_u64(0).next_u64(), 0xE220_A839_7B1D_CDAF);
+    }
+
+    #[test]
+    fn unit_ranges() {
+        let mut rng = Rng::seed_from_u64(5);
+        for _ in 0..10_000 {
+            let x = rng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            let y = rng.next_f64_open();
+            assert!(y > 0.0 && y <= 1.0);
+            assert!(rng.below(7) < 7);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Splitting a marketable order across venues by displayed liquidity.
+//!
+//! The router sweeps the venues' books as if they were one: levels from
+//! every venue are taken best price first, up to the order's limit, until
+//! the order is covered. At a price several venues show, the larger
+//! displayed quantity goes first (the earlier venue on a tie), so fewer
+//! child orders are needed. Whatever the books can't cover is left
+//! `unrouted` for the caller to place.
+
+use crate::types::Side;
+
+/// Quantity routed to one venue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Allocation {
+    /// Index into the venues passed to [`split`]
+    pub venue: usize,
+    pub qty: i64,
+    /// Worst price the allocation reaches on that venue's book
+    pub worst_px: i64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RoutePlan {
+    /// One per venue given any quantity, in venue order
+    pub allocations: Vec<Allocation>,
+    /// Quantity no venue displayed within the limit
+    pub unrouted: i64,
+}
+
+/// Splits an order to `side` `qty` at up to `limit_px`. `books[v]` is venue
+/// `v`'s opposite side as `(px_ticks, qty)` levels, best first.
+pub fn split(side: Side, limit_px: i64, qty: i64, books: &[Vec<(i64, i64)>]) -> RoutePlan {
+    let marketable = |px: i64| match side {
+        Side::Bid => px <= limit_px,
+        Side::Ask => px >= limit_px,
+    };
+    // (price, venue, displayed), best price first
+    let mut levels: Vec<(i64, usize, i64)> = books
+        .iter()
+        .enumerate()
+        .flat_map(|(venue, book)| book.iter().map(move |&(px, qty)| (px, venue, qty)))
+        .filter(|&(px, _, qty)| qty > 0 && marketable(px))
+        .collect();
+    levels.sort_by(|a, b| {
+        let by_price = match side {
+            Side::Bid => a.0.cmp(&b.0),
+            Side::Ask => b.0.cmp(&a.0),
+        };
+        by_price.then(b.2.cmp(&a.2)).then(a.1.cmp(&b.1))
+    });
+
+    let mut routed: Vec<Option<Allocation>> = vec![None; books.len()];
+    let mut remaining = qty.max(0);
+    for (px, venue, displayed) in levels {
+        if remaining == 0 {
+            break;
+        }
+        let take = displayed.min(remaining);
+        remaining -= take;
+        let allocation = routed[venue].get_or_insert(Allocation { venue, qty: 0, worst_px: px });
+        allocation.qty += take;
+        allocation.worst_px = px;
+    }
+    RoutePlan { allocations: routed.into_iter().flatten().collect(), unrouted: remaining }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweeps_the_combined_book_best_price_first() {
+        let books = vec![
+            vec![(100, 5), (102, 10)],
+            vec![(101, 4), (102, 3)],
+            vec![(103, 50)],
+        ];
+        let plan = split(Side::Bid, 102, 15, &books);
+        // 5 at 100 and 4 at 101, then 6 of the 13 shown at 102: venue 0's
+        // 10 is larger, so it fills there before venue 1
+        assert_eq!(
+            plan.allocations,
+            [Allocation { venue: 0, qty: 11, worst_px: 102 }, Allocation { venue: 1, qty: 4, worst_px: 101 }]
+        );
+        assert_eq!(plan.unrouted, 0);
+    }
+
+    #[test]
+    fn liquidity_past_the_limit_is_left_unrouted() {
+        let books = vec![vec![(99, 2), (97, 5)], vec![], vec![(98, 1)]];
+        let plan = split(Side::Ask, 98, 10, &books);
+        assert_eq!(
+            plan.allocations,
+            [Allocation { venue: 0, qty: 2, worst_px: 99 }, Allocation { venue: 2, qty: 1, worst_px: 98 }]
+        );
+        assert_eq!(plan.unrouted, 7);
+
+        assert_eq!(split(Side::Bid, 100, 3, &[]), RoutePlan { allocations: vec![], unrouted: 3 });
+    }
+}
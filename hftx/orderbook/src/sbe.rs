@@ -0,0 +1,513 @@
+//! SBE (Simple Binary Encoding) market data messages: trades, BBO, and
+//! depth diffs.
+//!
+//! The schema is `orderbook/sbe/market_data.xml` (schema id 7201, little
+//! endian). Subscribers in other languages generate their codecs from it;
+//! here the encoders and decoders are generated by `sbe_block!` from field
+//! lists that the tests check against the XML. Every frame is an 8-byte
+//! header, then the message's fixed block:
+//!
+//! | bytes | header field  |
+//! |-------|---------------|
+//! | 0..2  | `blockLength` |
+//! | 2..4  | `templateId`  |
+//! | 4..6  | `schemaId`    |
+//! | 6..8  | `version`     |
+//!
+//! | template | message                 | block bytes | then                 |
+//! |----------|-------------------------|-------------|----------------------|
+//! | 1        | [`TradeMessage`]        | 64          |                      |
+//! | 2        | [`BboMessage`]          | 48          |                      |
+//! | 3        | [`DepthDiffMessage`]    | 16          | `levels` group, 17 B |
+//!
+//! Decoders use the header's `blockLength` rather than their own, so a
+//! newer schema version may append fields without breaking old readers.
+//! Symbols are 8 bytes, NUL-padded (the ITCH width); longer ones are
+//! truncated on encode.
+
+use crate::types::{OrderId, Side, Trade};
+use crate::OrderBook;
+use std::fmt;
+
+pub const SCHEMA_ID: u16 = 7201;
+pub const SCHEMA_VERSION: u16 = 1;
+pub const HEADER_LEN: usize = 8;
+const GROUP_HEADER_LEN: usize = 4;
+const SYMBOL_LEN: usize = 8;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SbeError {
+    /// Input ended mid-frame
+    Truncated,
+    /// A frame from another schema
+    WrongSchema(u16),
+    UnknownTemplate(u16),
+    /// A field or block length this schema doesn't allow
+    Malformed(&'static str),
+}
+
+impl fmt::Display for SbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SbeError::Truncated => write!(f, "frame is truncated"),
+            SbeError::WrongSchema(id) => write!(f, "schema id {} is not {}", id, SCHEMA_ID),
+            SbeError::UnknownTemplate(id) => write!(f, "unknown template id {}", id),
+            SbeError::Malformed(what) => write!(f, "malformed {}", what),
+        }
+    }
+}
+
+impl std::error::Error for SbeError {}
+
+/// A fixed-size field type: its encoded size and little-endian codec.
+trait Field: Sized {
+    const SIZE: usize;
+    fn put(&self, out: &mut Vec<u8>);
+    /// `bytes` is exactly `SIZE` long.
+    fn get(bytes: &[u8]) -> Result<Self, SbeError>;
+}
+
+macro_rules! primitive_field {
+    ($($ty:ty),*) => {$(
+        impl Field for $ty {
+            const SIZE: usize = std::mem::size_of::<$ty>();
+            fn put(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+            fn get(bytes: &[u8]) -> Result<Self, SbeError> {
+                Ok(<$ty>::from_le_bytes(bytes.try_into().expect("field slice has the field's size")))
+            }
+        }
+    )*};
+}
+
+primitive_field!(u16, u64, i64, u128);
+
+/// `orderId` composite: low word, then high word, i.e. the u128 in LE.
+impl Field for OrderId {
+    const SIZE: usize = 16;
+    fn put(&self, out: &mut Vec<u8>) {
+        self.0.put(out);
+    }
+    fn get(bytes: &[u8]) -> Result<Self, SbeError> {
+        u128::get(bytes).map(OrderId)
+    }
+}
+
+/// `optionalPrice`: `i64::MIN` is null.
+impl Field for Option<i64> {
+    const SIZE: usize = 8;
+    fn put(&self, out: &mut Vec<u8>) {
+        self.unwrap_or(i64::MIN).put(out);
+    }
+    fn get(bytes: &[u8]) -> Result<Self, SbeError> {
+        i64::get(bytes).map(|px| (px != i64::MIN).then_some(px))
+    }
+}
+
+impl Field for Side {
+    const SIZE: usize = 1;
+    fn put(&self, out: &mut Vec<u8>) {
+        out.push(match self {
+            Side::Bid => 0,
+            Side::Ask => 1,
+        });
+    }
+    fn get(bytes: &[u8]) -> Result<Self, SbeError> {
+        match bytes[0] {
+            0 => Ok(Side::Bid),
+            1 => Ok(Side::Ask),
+            _ => Err(SbeError::Malformed("side")),
+        }
+    }
+}
+
+/// `symbol`: 8 bytes, NUL-padded.
+impl Field for String {
+    const SIZE: usize = SYMBOL_LEN;
+    fn put(&self, out: &mut Vec<u8>) {
+        let bytes = &self.as_bytes()[..self.len().min(SYMBOL_LEN)];
+        out.extend_from_slice(bytes);
+        out.resize(out.len() + SYMBOL_LEN - bytes.len(), 0);
+    }
+    fn get(bytes: &[u8]) -> Result<Self, SbeError> {
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(SYMBOL_LEN);
+        std::str::from_utf8(&bytes[..len]).map(str::to_string).map_err(|_| SbeError::Malformed("symbol"))
+    }
+}
+
+/// Generates a block struct with its encoder and decoder. Each field names
+/// its schema id; fields are encoded in declaration order with no padding.
+macro_rules! sbe_block {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident { $($(#[$fmeta:meta])* $field:ident: $ty:ty = $id:literal,)* }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        $vis struct $name {
+            $($(#[$fmeta])* pub $field: $ty,)*
+        }
+
+        impl $name {
+            pub const BLOCK_LENGTH: usize = 0 $(+ <$ty as Field>::SIZE)*;
+
+            /// (name, schema id, size) per field, in wire order.
+            #[cfg(test)]
+            const FIELDS: &'static [(&'static str, u16, usize)] = &[$((stringify!($field), $id, <$ty as Field>::SIZE)),*];
+
+            fn put_block(&self, out: &mut Vec<u8>) {
+                $(self.$field.put(out);)*
+            }
+
+            /// Decodes the leading `BLOCK_LENGTH` bytes of `block`.
+            fn get_block(block: &[u8]) -> Result<Self, SbeError> {
+                let mut at = 0;
+                $(
+                    let $field = <$ty as Field>::get(&block[at..at + <$ty as Field>::SIZE])?;
+                    at += <$ty as Field>::SIZE;
+                )*
+                debug_assert_eq!(at, Self::BLOCK_LENGTH);
+                Ok(Self { $($field),* })
+            }
+        }
+    };
+}
+
+sbe_block! {
+    /// Template 1: one execution.
+    pub struct TradeMessage {
+        ts_ns: u64 = 1,
+        symbol: String = 2,
+        px_ticks: i64 = 3,
+        qty: i64 = 4,
+        maker_id: OrderId = 5,
+        taker_id: OrderId = 6,
+    }
+}
+
+sbe_block! {
+    /// Template 2: best bid and offer. Prices are `None` and quantities 0
+    /// on an empty side.
+    pub struct BboMessage {
+        ts_ns: u64 = 1,
+        symbol: String = 2,
+        bid_px: Option<i64> = 3,
+        bid_qty: i64 = 4,
+        ask_px: Option<i64> = 5,
+        ask_qty: i64 = 6,
+    }
+}
+
+sbe_block! {
+    /// Fixed block of template 3.
+    struct DepthDiffBlock {
+        ts_ns: u64 = 1,
+        symbol: String = 2,
+    }
+}
+
+sbe_block! {
+    /// One entry of a depth diff's `levels` group.
+    pub struct DepthLevel {
+        side: Side = 4,
+        px_ticks: i64 = 5,
+        /// Live quantity now at the price; 0 removes the level
+        qty: i64 = 6,
+    }
+}
+
+/// Template 3: the price levels that changed since the previous diff.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepthDiffMessage {
+    pub ts_ns: u64,
+    pub symbol: String,
+    pub levels: Vec<DepthLevel>,
+}
+
+/// Any message of the schema, as decoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarketData {
+    Trade(TradeMessage),
+    Bbo(BboMessage),
+    DepthDiff(DepthDiffMessage),
+}
+
+impl MarketData {
+    pub fn template_id(&self) -> u16 {
+        match self {
+            MarketData::Trade(_) => TradeMessage::TEMPLATE_ID,
+            MarketData::Bbo(_) => BboMessage::TEMPLATE_ID,
+            MarketData::DepthDiff(_) => DepthDiffMessage::TEMPLATE_ID,
+        }
+    }
+}
+
+fn put_header(out: &mut Vec<u8>, block_length: usize, template_id: u16) {
+    (block_length as u16).put(out);
+    template_id.put(out);
+    SCHEMA_ID.put(out);
+    SCHEMA_VERSION.put(out);
+}
+
+/// The `len` bytes at `at`, or `Truncated`.
+fn take(buf: &[u8], at: usize, len: usize) -> Result<&[u8], SbeError> {
+    buf.get(at..at + len).ok_or(SbeError::Truncated)
+}
+
+/// A `blockLength` read off the wire, checked to hold at least `ours`.
+fn block_length(bytes: &[u8], ours: usize, what: &'static str) -> Result<usize, SbeError> {
+    match u16::get(bytes)? as usize {
+        len if len >= ours => Ok(len),
+        _ => Err(SbeError::Malformed(what)),
+    }
+}
+
+impl TradeMessage {
+    pub const TEMPLATE_ID: u16 = 1;
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        put_header(out, Self::BLOCK_LENGTH, Self::TEMPLATE_ID);
+        self.put_block(out);
+    }
+}
+
+impl From<&Trade> for TradeMessage {
+    fn from(trade: &Trade) -> Self {
+        Self {
+            ts_ns: trade.ts_ns as u64,
+            symbol: trade.symbol.to_string(),
+            px_ticks: trade.px_ticks,
+            qty: trade.qty,
+            maker_id: trade.maker,
+            taker_id: trade.taker,
+        }
+    }
+}
+
+impl BboMessage {
+    pub const TEMPLATE_ID: u16 = 2;
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        put_header(out, Self::BLOCK_LENGTH, Self::TEMPLATE_ID);
+        self.put_block(out);
+    }
+
+    /// The book's current top, with the live quantity at each best price.
+    pub fn from_book(ts_ns: u64, symbol: &str, book: &OrderBook) -> Self {
+        let (bid_px, ask_px) = (book.best_bid(), book.best_ask());
+        Self {
+            ts_ns,
+            symbol: symbol.to_string(),
+            bid_px,
+            bid_qty: bid_px.map_or(0, |px| book.bids.qty_at_price(px)),
+            ask_px,
+            ask_qty: ask_px.map_or(0, |px| book.asks.qty_at_price(px)),
+        }
+    }
+}
+
+impl DepthDiffMessage {
+    pub const TEMPLATE_ID: u16 = 3;
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        put_header(out, DepthDiffBlock::BLOCK_LENGTH, Self::TEMPLATE_ID);
+        DepthDiffBlock { ts_ns: self.ts_ns, symbol: self.symbol.clone() }.put_block(out);
+        (DepthLevel::BLOCK_LENGTH as u16).put(out);
+        u16::try_from(self.levels.len()).expect("at most 65535 levels per diff").put(out);
+        self.levels.iter().for_each(|level| level.put_block(out));
+    }
+
+    /// The diff that turns the levels in `before` into those in `after`:
+    /// every level whose quantity changed or that appeared, then a qty-0
+    /// entry for every level that vanished. Levels are (side, price) keyed.
+    pub fn between(ts_ns: u64, symbol: &str, before: &[DepthLevel], after: &[DepthLevel]) -> Self {
+        let key = |l: &DepthLevel| (l.side, l.px_ticks);
+        let mut levels: Vec<DepthLevel> =
+            after.iter().filter(|l| !before.iter().any(|b| b == *l)).cloned().collect();
+        levels.extend(
+            before
+                .iter()
+                .filter(|b| !after.iter().any(|a| key(a) == key(b)))
+                .map(|b| DepthLevel { qty: 0, ..b.clone() }),
+        );
+        Self { ts_ns, symbol: symbol.to_string(), levels }
+    }
+}
+
+/// The best `levels` live price levels of each side, bids first.
+pub fn depth_levels(book: &OrderBook, levels: usize) -> Vec<DepthLevel> {
+    [(&book.bids, Side::Bid), (&book.asks, Side::Ask)]
+        .into_iter()
+        .flat_map(|(prices, side)| {
+            prices
+                .iter_levels_best_first()
+                .filter(|&(_, qty)| qty > 0)
+                .take(levels)
+                .map(move |(px_ticks, qty)| DepthLevel { side, px_ticks, qty })
+        })
+        .collect()
+}
+
+/// Encodes one message as a standalone frame.
+pub fn encode(message: &MarketData) -> Vec<u8> {
+    let mut out = Vec::new();
+    match message {
+        MarketData::Trade(m) => m.encode(&mut out),
+        MarketData::Bbo(m) => m.encode(&mut out),
+        MarketData::DepthDiff(m) => m.encode(&mut out),
+    }
+    out
+}
+
+/// Decodes the frame at the start of `buf`, returning the message and the
+/// bytes it took, so concatenated frames can be read in turn.
+pub fn decode(buf: &[u8]) -> Result<(MarketData, usize), SbeError> {
+    let header = take(buf, 0, HEADER_LEN)?;
+    let schema_id = u16::get(&header[4..6])?;
+    if schema_id != SCHEMA_ID {
+        return Err(SbeError::WrongSchema(schema_id));
+    }
+    let template_id = u16::get(&header[2..4])?;
+    let ours = match template_id {
+        TradeMessage::TEMPLATE_ID => TradeMessage::BLOCK_LENGTH,
+        BboMessage::TEMPLATE_ID => BboMessage::BLOCK_LENGTH,
+        DepthDiffMessage::TEMPLATE_ID => DepthDiffBlock::BLOCK_LENGTH,
+        other => return Err(SbeError::UnknownTemplate(other)),
+    };
+    let len = block_length(&header[0..2], ours, "block length")?;
+    let block = take(buf, HEADER_LEN, len)?;
+    let mut at = HEADER_LEN + len;
+
+    let message = match template_id {
+        TradeMessage::TEMPLATE_ID => MarketData::Trade(TradeMessage::get_block(block)?),
+        BboMessage::TEMPLATE_ID => MarketData::Bbo(BboMessage::get_block(block)?),
+        _ => {
+            let DepthDiffBlock { ts_ns, symbol } = DepthDiffBlock::get_block(block)?;
+            let group = take(buf, at, GROUP_HEADER_LEN)?;
+            let entry_len = block_length(&group[0..2], DepthLevel::BLOCK_LENGTH, "group block length")?;
+            let count = u16::get(&group[2..4])? as usize;
+            at += GROUP_HEADER_LEN;
+            let mut levels = Vec::with_capacity(count);
+            for _ in 0..count {
+                levels.push(DepthLevel::get_block(take(buf, at, entry_len)?)?);
+                at += entry_len;
+            }
+            MarketData::DepthDiff(DepthDiffMessage { ts_ns, symbol, levels })
+        }
+    };
+    Ok((message, at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Order, ParticipantId, TimeInForce};
+
+    const SCHEMA_XML: &str = include_str!("../sbe/market_data.xml");
+
+    /// (name, id) of every `<field>` in the schema between `start` and `end`.
+    fn xml_fields(start: &str, end: &str) -> Vec<(String, u16)> {
+        let from = SCHEMA_XML.find(start).expect("section start");
+        let section = &SCHEMA_XML[from..from + SCHEMA_XML[from..].find(end).expect("section end")];
+        let attr = |tag: &str, name: &str| {
+            let at = tag.find(&format!("{}=\"", name)).expect("attribute") + name.len() + 2;
+            tag[at..at + tag[at..].find('"').unwrap()].to_string()
+        };
+        section
+            .split("<field ")
+            .skip(1)
+            .map(|tag| (attr(tag, "name"), attr(tag, "id").parse().unwrap()))
+            .collect()
+    }
+
+    fn camel(name: &str) -> String {
+        let mut parts = name.split('_');
+        let first = parts.next().unwrap().to_string();
+        parts.fold(first, |acc, p| acc + &p[..1].to_uppercase() + &p[1..])
+    }
+
+    fn rust_fields(fields: &[(&str, u16, usize)]) -> Vec<(String, u16)> {
+        fields.iter().map(|&(name, id, _)| (camel(name), id)).collect()
+    }
+
+    #[test]
+    fn field_lists_match_the_xml_schema() {
+        assert!(SCHEMA_XML.contains(&format!("id=\"{}\"", SCHEMA_ID)));
+        assert!(SCHEMA_XML.contains(&format!("version=\"{}\"", SCHEMA_VERSION)));
+        assert!(SCHEMA_XML.contains("byteOrder=\"littleEndian\""));
+        for (message, fields) in [
+            ("name=\"Trade\" id=\"1\"", TradeMessage::FIELDS),
+            ("name=\"Bbo\" id=\"2\"", BboMessage::FIELDS),
+        ] {
+            assert_eq!(xml_fields(message, "</sbe:message>"), rust_fields(fields), "{}", message);
+        }
+        assert_eq!(
+            xml_fields("name=\"DepthDiff\" id=\"3\"", "<group"),
+            rust_fields(DepthDiffBlock::FIELDS)
+        );
+        assert_eq!(xml_fields("<group name=\"levels\"", "</group>"), rust_fields(DepthLevel::FIELDS));
+        assert_eq!(
+            (TradeMessage::BLOCK_LENGTH, BboMessage::BLOCK_LENGTH, DepthDiffBlock::BLOCK_LENGTH, DepthLevel::BLOCK_LENGTH),
+            (64, 48, 16, 17)
+        );
+    }
+
+    #[test]
+    fn messages_round_trip_back_to_back() {
+        let mut book = OrderBook::new();
+        let order = |id: u128, side, px_ticks, qty| Order { id: OrderId(id), symbol: "AAPL".into(), side, px_ticks, qty, ts_ns: id, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE };
+        book.submit_limit(order(1, Side::Ask, 101, 5)).unwrap();
+        book.submit_limit(order(2, Side::Ask, 101, 3)).unwrap();
+        let before = depth_levels(&book, 5);
+        let trades = book.submit_limit(order(u128::MAX, Side::Bid, 101, 6)).unwrap().trades;
+
+        let messages = [
+            MarketData::Trade(TradeMessage::from(&trades[0])),
+            MarketData::Bbo(BboMessage::from_book(7, "AAPL", &book)),
+            MarketData::DepthDiff(DepthDiffMessage::between(7, "AAPL", &before, &depth_levels(&book, 5))),
+        ];
+        let wire: Vec<u8> = messages.iter().flat_map(encode).collect();
+
+        let mut at = 0;
+        for expected in &messages {
+            let (decoded, len) = decode(&wire[at..]).unwrap();
+            assert_eq!(&decoded, expected);
+            at += len;
+        }
+        assert_eq!(at, wire.len());
+
+        let MarketData::Bbo(bbo) = &messages[1] else { unreachable!() };
+        assert_eq!((bbo.bid_px, bbo.ask_px, bbo.ask_qty), (None, Some(101), 2));
+        let MarketData::DepthDiff(diff) = &messages[2] else { unreachable!() };
+        assert_eq!(diff.levels, [DepthLevel { side: Side::Ask, px_ticks: 101, qty: 2 }]);
+        let MarketData::Trade(trade) = &messages[0] else { unreachable!() };
+        assert_eq!(trade.taker_id, OrderId(u128::MAX));
+    }
+
+    #[test]
+    fn decoder_skips_appended_fields_and_rejects_bad_frames() {
+        let trade = TradeMessage {
+            ts_ns: 1,
+            symbol: "LONGSYMBOL".into(),
+            px_ticks: 100,
+            qty: 1,
+            maker_id: OrderId(1),
+            taker_id: OrderId(2),
+        };
+        let mut frame = encode(&MarketData::Trade(trade));
+        // A newer writer with one more u32 field in the block
+        frame[0] += 4;
+        frame.extend_from_slice(&[9; 4]);
+        let (MarketData::Trade(decoded), len) = decode(&frame).unwrap() else { panic!("not a trade") };
+        assert_eq!((decoded.symbol.as_str(), len), ("LONGSYMB", frame.len()));
+
+        assert_eq!(decode(&frame[..30]), Err(SbeError::Truncated));
+        let mut other = frame.clone();
+        other[4] = 0;
+        assert!(matches!(decode(&other), Err(SbeError::WrongSchema(_))));
+        other = frame.clone();
+        other[2] = 99;
+        assert_eq!(decode(&other), Err(SbeError::UnknownTemplate(99)));
+    }
+}
@@ -0,0 +1,401 @@
+//! Shadow matching: a candidate matcher run beside `OrderBook` and diffed.
+//!
+//! A [`Shadow`] feeds the same submits, cancels and amends to its own `OrderBook`
+//! (the primary) and to a candidate [`Matcher`], such as a storage rewrite,
+//! and compares them after every event: the trades a submit or amend
+//! produced, what a cancel returned, and the top levels of both sides. Each difference is
+//! a [`Divergence`]. After one, the candidate is rebuilt from the primary's
+//! resting orders, so a bug is reported where it happens rather than again
+//! on every later event.
+//!
+//! Candidates are named so a service can pick one from config; see
+//! [`candidate`]. `reference` is a flat-list matcher that scans every
+//! resting order, slow but plainly correct. `ladder` is an `OrderBook`
+//! keeping its levels in price ladders over [`LADDER_BAND`].
+
+use crate::types::{Order, OrderId, Side, TimeInForce, Trade};
+use crate::OrderBook;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// What a matching implementation must do to be shadowed.
+pub trait Matcher: Send {
+    fn submit(&mut self, order: Order) -> Vec<Trade>;
+    /// True if `id` was resting on `side` and is now gone.
+    fn cancel(&mut self, id: OrderId, side: Side) -> bool;
+    /// Amends a resting order as [`OrderBook::modify`] does; None if `id`
+    /// wasn't resting or the amend was refused.
+    fn modify(&mut self, id: OrderId, px_ticks: i64, qty: i64, ts_ns: u128) -> Option<Vec<Trade>>;
+    /// Up to `n` of `side`'s `(px_ticks, qty)` levels, best first.
+    fn levels(&self, side: Side, n: usize) -> Vec<(i64, i64)>;
+}
+
+impl Matcher for OrderBook {
+    fn submit(&mut self, order: Order) -> Vec<Trade> {
+        self.submit_limit(order).map(|report| report.trades).unwrap_or_default()
+    }
+
+    fn cancel(&mut self, id: OrderId, side: Side) -> bool {
+        self.side_of(id) == Some(side) && OrderBook::cancel(self, id)
+    }
+
+    fn modify(&mut self, id: OrderId, px_ticks: i64, qty: i64, ts_ns: u128) -> Option<Vec<Trade>> {
+        OrderBook::modify(self, id, px_ticks, qty, ts_ns).ok().flatten()
+    }
+
+    fn levels(&self, side: Side, n: usize) -> Vec<(i64, i64)> {
+        let levels = OrderBook::levels(self, side);
+        // Levels holding only cancelled orders are still in the map
+        levels.iter_levels_best_first().filter(|&(_, qty)| qty > 0).take(n).collect()
+    }
+}
+
+/// Every resting order in one list, matched by linear scan.
+#[derive(Default)]
+pub struct ReferenceBook {
+    /// Resting orders in arrival order, an iceberg by its displayed slice
+    resting: Vec<Order>,
+    /// What each iceberg holds back
+    hidden: HashMap<OrderId, i64>,
+}
+
+impl Matcher for ReferenceBook {
+    fn submit(&mut self, mut taker: Order) -> Vec<Trade> {
+        let mut trades = Vec::new();
+        let crosses = |o: &Order, taker: &Order| {
+            o.side != taker.side
+                && match taker.side {
+                    Side::Bid => o.px_ticks <= taker.px_ticks,
+                    Side::Ask => o.px_ticks >= taker.px_ticks,
+                }
+        };
+        if taker.tif == TimeInForce::FOK {
+            let available: i64 = self
+                .resting
+                .iter()
+                .filter(|o| crosses(o, &taker))
+                .map(|o| o.qty + self.hidden.get(&o.id).unwrap_or(&0))
+                .sum();
+            if available < taker.qty {
+                return trades;
+            }
+        }
+        // A FOK taker ignores its minimum
+        let min_qty = taker.min_qty.filter(|_| taker.tif != TimeInForce::FOK).unwrap_or(0);
+        while taker.qty > 0 && taker.qty >= min_qty {
+            // Best opposite price, displayed before hidden, then earliest
+            // arrival among equals
+            let best = self
+                .resting
+                .iter()
+                .enumerate()
+                .filter(|(_, o)| crosses(o, &taker) && o.qty >= min_qty)
+                .min_by_key(|(i, o)| match taker.side {
+                    Side::Bid => (o.px_ticks, o.hidden, *i),
+                    Side::Ask => (-o.px_ticks, o.hidden, *i),
+                })
+                .map(|(i, _)| i);
+            let Some(i) = best else { break };
+            let maker = &mut self.resting[i];
+            let fill = taker.qty.min(maker.qty);
+            taker.qty -= fill;
+            maker.qty -= fill;
+            trades.push(Trade {
+                maker: maker.id,
+                taker: taker.id,
+                symbol: taker.symbol,
+                px_ticks: maker.px_ticks,
+                qty: fill,
+                ts_ns: taker.ts_ns,
+                seq: 0,
+            });
+            if maker.qty == 0 {
+                let mut maker = self.resting.remove(i);
+                // An iceberg's next slice arrives anew
+                if let Some(hidden) = self.hidden.remove(&maker.id) {
+                    maker.qty = hidden;
+                    self.rest(maker);
+                }
+            }
+        }
+        if taker.qty > 0 && taker.tif == TimeInForce::Day {
+            self.rest(taker);
+        }
+        trades
+    }
+
+    fn cancel(&mut self, id: OrderId, side: Side) -> bool {
+        let Some(i) = self.resting.iter().position(|o| o.id == id && o.side == side) else { return false };
+        self.resting.remove(i);
+        self.hidden.remove(&id);
+        true
+    }
+
+    fn modify(&mut self, id: OrderId, px_ticks: i64, qty: i64, ts_ns: u128) -> Option<Vec<Trade>> {
+        let i = self.resting.iter().position(|o| o.id == id)?;
+        let total = self.resting[i].qty + self.hidden.get(&id).unwrap_or(&0);
+        if qty > 0 && (px_ticks != self.resting[i].px_ticks || qty > total) {
+            let order = self.resting.remove(i);
+            self.hidden.remove(&id);
+            return Some(self.submit(Order { px_ticks, qty, ts_ns, ..order }));
+        }
+        // Shrunk in place, hidden quantity first
+        let mut cut = total - qty.max(0);
+        if let Some(hidden) = self.hidden.get_mut(&id) {
+            let taken = cut.min(*hidden);
+            *hidden -= taken;
+            cut -= taken;
+            if *hidden == 0 {
+                self.hidden.remove(&id);
+            }
+        }
+        self.resting[i].qty -= cut;
+        if self.resting[i].qty == 0 {
+            self.resting.remove(i);
+        }
+        Some(Vec::new())
+    }
+
+    fn levels(&self, side: Side, n: usize) -> Vec<(i64, i64)> {
+        let mut levels: Vec<(i64, i64)> = Vec::new();
+        let mut orders: Vec<&Order> = self.resting.iter().filter(|o| o.side == side && !o.hidden).collect();
+        orders.sort_by_key(|o| match side {
+            Side::Bid => -o.px_ticks,
+            Side::Ask => o.px_ticks,
+        });
+        for order in orders {
+            if let Some(level) = levels.last_mut().filter(|level| level.0 == order.px_ticks) {
+                level.1 += order.qty;
+            } else if levels.len() == n {
+                break;
+            } else {
+                levels.push((order.px_ticks, order.qty));
+            }
+        }
+        levels
+    }
+}
+
+impl ReferenceBook {
+    /// Rests `order`, holding back all but the first `display_qty` of an
+    /// iceberg. A hidden order is never split.
+    fn rest(&mut self, mut order: Order) {
+        if let Some(display_qty) = order.display_qty.filter(|&d| d > 0 && d < order.qty && !order.hidden) {
+            self.hidden.insert(order.id, order.qty - display_qty);
+            order.qty = display_qty;
+        }
+        self.resting.push(order);
+    }
+}
+
+/// Names [`candidate`] accepts.
+pub const CANDIDATES: &[&str] = &["reference", "ladder"];
+
+/// Prices in ticks the `ladder` candidate lays out flat. Prices outside
+/// still match, off the ladder.
+pub const LADDER_BAND: (i64, i64) = (0, 32_767);
+
+/// A fresh, empty candidate by name.
+pub fn candidate(name: &str) -> Option<fn() -> Box<dyn Matcher>> {
+    match name {
+        "reference" => Some(|| Box::new(ReferenceBook::default())),
+        "ladder" => Some(|| Box::new(OrderBook::new().with_price_band(LADDER_BAND.0, LADDER_BAND.1))),
+        _ => None,
+    }
+}
+
+/// Where the candidate parted from the primary.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Divergence {
+    Trades { order: OrderId, primary: Vec<Trade>, candidate: Vec<Trade> },
+    Cancel { order: OrderId, primary: bool, candidate: bool },
+    /// Whether each found the order to amend
+    Modify { order: OrderId, primary: bool, candidate: bool },
+    Book { side: Side, primary: Vec<(i64, i64)>, candidate: Vec<(i64, i64)> },
+}
+
+pub struct Shadow {
+    primary: OrderBook,
+    candidate: Box<dyn Matcher>,
+    make: fn() -> Box<dyn Matcher>,
+    /// Levels per side compared after each event
+    depth: usize,
+}
+
+/// `trades` with their event numbers cleared: a candidate has to fill as
+/// the primary does, not number its events the same way.
+fn fills(mut trades: Vec<Trade>) -> Vec<Trade> {
+    for trade in &mut trades {
+        trade.seq = 0;
+    }
+    trades
+}
+
+impl Shadow {
+    /// Shadows the candidate `make` builds, comparing the top `depth`
+    /// levels of each side.
+    pub fn new(make: fn() -> Box<dyn Matcher>, depth: usize) -> Self {
+        Self { primary: OrderBook::new(), candidate: make(), make, depth }
+    }
+
+    pub fn submit(&mut self, order: Order) -> Vec<Divergence> {
+        let id = order.id;
+        let primary = fills(self.primary.submit_limit(order.clone()).map(|report| report.trades).unwrap_or_default());
+        let candidate = fills(self.candidate.submit(order));
+        let mut divergences = Vec::new();
+        if primary != candidate {
+            divergences.push(Divergence::Trades { order: id, primary, candidate });
+        }
+        self.compare(divergences)
+    }
+
+    pub fn cancel(&mut self, id: OrderId, side: Side) -> Vec<Divergence> {
+        let primary = Matcher::cancel(&mut self.primary, id, side);
+        let candidate = self.candidate.cancel(id, side);
+        let mut divergences = Vec::new();
+        if primary != candidate {
+            divergences.push(Divergence::Cancel { order: id, primary, candidate });
+        }
+        self.compare(divergences)
+    }
+
+    pub fn modify(&mut self, id: OrderId, px_ticks: i64, qty: i64, ts_ns: u128) -> Vec<Divergence> {
+        let primary = Matcher::modify(&mut self.primary, id, px_ticks, qty, ts_ns).map(fills);
+        let candidate = self.candidate.modify(id, px_ticks, qty, ts_ns).map(fills);
+        let mut divergences = Vec::new();
+        match (primary, candidate) {
+            (Some(primary), Some(candidate)) if primary != candidate => {
+                divergences.push(Divergence::Trades { order: id, primary, candidate });
+            }
+            (primary, candidate) if primary.is_some() != candidate.is_some() => {
+                divergences.push(Divergence::Modify { order: id, primary: primary.is_some(), candidate: candidate.is_some() });
+            }
+            _ => {}
+        }
+        self.compare(divergences)
+    }
+
+    /// Starts both over from an empty book, e.g. after missed events.
+    pub fn reset(&mut self) {
+        self.primary = OrderBook::new();
+        self.candidate = (self.make)();
+    }
+
+    /// Adds any book differences, and rebuilds the candidate if there
+    /// were divergences of any kind.
+    fn compare(&mut self, mut divergences: Vec<Divergence>) -> Vec<Divergence> {
+        for side in [Side::Bid, Side::Ask] {
+            let primary = Matcher::levels(&self.primary, side, self.depth);
+            let candidate = self.candidate.levels(side, self.depth);
+            if primary != candidate {
+                divergences.push(Divergence::Book { side, primary, candidate });
+            }
+        }
+        if !divergences.is_empty() {
+            // Resting orders never cross, so resubmitting them in priority
+            // order rebuilds the book without trades
+            self.candidate = (self.make)();
+            // Icebergs with their hidden quantity; each shows a full slice
+            // again, as the primary would after a reload
+            for side in [&self.primary.bids, &self.primary.asks] {
+                for order in side.live_orders() {
+                    self.candidate.submit(Order { qty: order.qty + side.hidden_qty(order.id), ..order.clone() });
+                }
+            }
+        }
+        divergences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::{FlowConfig, FlowEvent, FlowGenerator};
+    use crate::types::ParticipantId;
+
+    #[test]
+    fn candidates_match_the_order_book_on_generated_flow() {
+        for name in CANDIDATES {
+            matches_on_generated_flow(name);
+        }
+    }
+
+    fn matches_on_generated_flow(name: &str) {
+        let mut shadow = Shadow::new(candidate(name).unwrap(), 10);
+        for event in FlowGenerator::new(FlowConfig::default(), 7).take(2_000) {
+            let divergences = match event {
+                // Some of the flow immediate-or-cancel and fill-or-kill, and
+                // some icebergs, hidden, or with a minimum fill
+                FlowEvent::Submit(mut order) => {
+                    order.tif = match order.id.0 % 7 {
+                        0 => TimeInForce::IOC,
+                        1 => TimeInForce::FOK,
+                        _ => TimeInForce::Day,
+                    };
+                    if order.id.0 % 5 == 0 {
+                        order.display_qty = Some(1.max(order.qty / 3));
+                    }
+                    if order.id.0 % 3 == 0 {
+                        order.min_qty = Some(1.max(order.qty / 2));
+                    }
+                    order.hidden = order.id.0 % 4 == 0;
+                    shadow.submit(order)
+                }
+                FlowEvent::Cancel { id, side, .. } => shadow.cancel(id, side),
+                // Some replaces amend in place instead
+                FlowEvent::Replace { id, order, .. } if id.0 % 2 == 0 => {
+                    shadow.modify(id, order.px_ticks, order.qty, order.ts_ns)
+                }
+                FlowEvent::Replace { id, side, order } => {
+                    let mut divergences = shadow.cancel(id, side);
+                    divergences.extend(shadow.submit(order));
+                    divergences
+                }
+            };
+            assert_eq!(divergences, [], "{}", name);
+        }
+    }
+
+    /// Fills one lot short whenever a fill is larger than one.
+    #[derive(Default)]
+    struct ShortFill(OrderBook);
+
+    impl Matcher for ShortFill {
+        fn submit(&mut self, order: Order) -> Vec<Trade> {
+            let mut trades = self.0.submit_limit(order).unwrap().trades;
+            for trade in trades.iter_mut().filter(|t| t.qty > 1) {
+                trade.qty -= 1;
+            }
+            trades
+        }
+
+        fn cancel(&mut self, id: OrderId, side: Side) -> bool {
+            Matcher::cancel(&mut self.0, id, side)
+        }
+
+        fn modify(&mut self, id: OrderId, px_ticks: i64, qty: i64, ts_ns: u128) -> Option<Vec<Trade>> {
+            self.0.modify(id, px_ticks, qty, ts_ns).ok().flatten()
+        }
+
+        fn levels(&self, side: Side, n: usize) -> Vec<(i64, i64)> {
+            Matcher::levels(&self.0, side, n)
+        }
+    }
+
+    #[test]
+    fn divergences_are_reported_once_then_the_candidate_is_rebuilt() {
+        let mut shadow = Shadow::new(|| Box::new(ShortFill::default()), 10);
+        let order = |id, side, px_ticks, qty| Order { id: OrderId(id), symbol: "X".into(), side, px_ticks, qty, ts_ns: id, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE };
+        assert_eq!(shadow.submit(order(1, Side::Ask, 100, 5)), []);
+        assert_eq!(shadow.submit(order(2, Side::Ask, 101, 5)), []);
+
+        let divergences = shadow.submit(order(3, Side::Bid, 100, 3));
+        assert!(matches!(&divergences[..], [Divergence::Trades { order: OrderId(3), primary, candidate }]
+            if primary[0].qty == 3 && candidate[0].qty == 2));
+
+        // Rebuilt from the primary: the resting 2 at 100 agrees again
+        assert_eq!(shadow.submit(order(4, Side::Bid, 99, 1)), []);
+        assert_eq!(shadow.cancel(OrderId(1), Side::Ask), []);
+    }
+}
@@ -0,0 +1,257 @@
+//! The arena behind [`PriceLevels`](crate::PriceLevels).
+//!
+//! Every queued order lives in one `Vec`, and each price level is a
+//! doubly-linked queue threaded through it by index. An order keeps its
+//! index for as long as it is queued, so the id index points straight at
+//! it and an order leaves its queue in O(1) from anywhere in it. Freed
+//! slots are reused before the arena grows, so a warmed-up book queues and
+//! dequeues orders without touching the allocator.
+
+use crate::types::Order;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// No neighbour: the end of a queue.
+const NIL: usize = usize::MAX;
+
+/// A queued order and its neighbours in its level's queue.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Node {
+    pub(crate) order: Order,
+    /// Canceled lazily: still queued, but no longer live
+    pub(crate) canceled: bool,
+    prev: usize,
+    next: usize,
+}
+
+/// The two ends of one level's queue, and how many orders are linked in
+/// it, canceled ones included.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) struct Queue {
+    head: usize,
+    tail: usize,
+    len: usize,
+}
+
+impl Default for Queue {
+    fn default() -> Self {
+        Self { head: NIL, tail: NIL, len: 0 }
+    }
+}
+
+impl Queue {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Slab index of the order at the front.
+    pub(crate) fn front(&self) -> Option<usize> {
+        (self.head != NIL).then_some(self.head)
+    }
+}
+
+/// Orders of every level of one side, by stable index.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Slab {
+    nodes: Vec<Option<Node>>,
+    /// Vacant indices, the last freed reused first
+    free: Vec<usize>,
+}
+
+impl Slab {
+    /// Orders held, canceled ones included.
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    /// Slots allocated, vacant ones included.
+    #[cfg(test)]
+    pub(crate) fn slots(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn get(&self, at: usize) -> &Node {
+        self.nodes[at].as_ref().expect("vacant slab slot")
+    }
+
+    /// The order at `at`, or None if the slot is vacant or was never
+    /// allocated.
+    pub(crate) fn try_get(&self, at: usize) -> Option<&Node> {
+        self.nodes.get(at)?.as_ref()
+    }
+
+    pub(crate) fn get_mut(&mut self, at: usize) -> &mut Node {
+        self.nodes[at].as_mut().expect("vacant slab slot")
+    }
+
+    /// [`get_mut`](Self::get_mut) without the bounds or vacancy checks.
+    ///
+    /// # Safety
+    ///
+    /// `at` must be the index of an order still queued.
+    #[cfg(feature = "unchecked")]
+    pub(crate) unsafe fn get_unchecked_mut(&mut self, at: usize) -> &mut Node {
+        debug_assert!(self.nodes.get(at).is_some_and(Option::is_some), "vacant slab slot");
+        unsafe { self.nodes.get_unchecked_mut(at).as_mut().unwrap_unchecked() }
+    }
+
+    /// Whether `q` runs cleanly from its head to its tail: `len` orders,
+    /// each linked back to the one before it, with nothing before the
+    /// first or after the last. Walking such a queue visits each of its
+    /// orders once and never a vacant slot.
+    pub(crate) fn linked(&self, q: &Queue) -> bool {
+        let (mut prev, mut at) = (NIL, q.head);
+        for _ in 0..q.len {
+            match self.try_get(at) {
+                Some(node) if node.prev == prev => (prev, at) = (at, node.next),
+                _ => return false,
+            }
+        }
+        at == NIL && prev == q.tail
+    }
+
+    /// Whether the free list holds every vacant slot once and nothing
+    /// else, so that reusing a slot never overwrites a queued order.
+    pub(crate) fn free_list_sound(&self) -> bool {
+        let vacant = self.nodes.iter().filter(|node| node.is_none()).count();
+        let mut listed = HashSet::new();
+        vacant == self.free.len()
+            && self.free.iter().all(|&at| self.nodes.get(at).is_some_and(Option::is_none) && listed.insert(at))
+    }
+
+    /// Takes a slot for `order`, unlinked.
+    fn alloc(&mut self, order: Order) -> usize {
+        let node = Some(Node { order, canceled: false, prev: NIL, next: NIL });
+        match self.free.pop() {
+            Some(at) => {
+                self.nodes[at] = node;
+                at
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    /// Queues `order` at the back of `q`. Returns its index.
+    pub(crate) fn push_back(&mut self, q: &mut Queue, order: Order) -> usize {
+        let at = self.alloc(order);
+        self.link_back(q, at);
+        at
+    }
+
+    /// Queues `order` at the front of `q`. Returns its index.
+    pub(crate) fn push_front(&mut self, q: &mut Queue, order: Order) -> usize {
+        let at = self.alloc(order);
+        let node = self.get_mut(at);
+        node.next = q.head;
+        match q.head {
+            NIL => q.tail = at,
+            head => self.get_mut(head).prev = at,
+        }
+        q.head = at;
+        q.len += 1;
+        at
+    }
+
+    /// Takes the order at `at` out of `q` and frees its slot.
+    pub(crate) fn remove(&mut self, q: &mut Queue, at: usize) -> Node {
+        self.unlink(q, at);
+        self.free.push(at);
+        self.nodes[at].take().expect("vacant slab slot")
+    }
+
+    /// Takes the front order out of `q`.
+    pub(crate) fn pop_front(&mut self, q: &mut Queue) -> Option<Node> {
+        let at = q.front()?;
+        Some(self.remove(q, at))
+    }
+
+    /// Moves the order at `at` to the back of `q`, keeping its index.
+    pub(crate) fn move_to_back(&mut self, q: &mut Queue, at: usize) {
+        if q.tail != at {
+            self.unlink(q, at);
+            self.link_back(q, at);
+        }
+    }
+
+    /// Frees every order in `q` that `keep` turns down, in queue order.
+    pub(crate) fn retain(&mut self, q: &mut Queue, mut keep: impl FnMut(&Node) -> bool) {
+        let mut at = q.head;
+        while at != NIL {
+            let node = self.get(at);
+            let next = node.next;
+            if !keep(node) {
+                self.remove(q, at);
+            }
+            at = next;
+        }
+    }
+
+    /// The orders of `q` with their indices, front first, canceled ones
+    /// included.
+    pub(crate) fn iter<'a>(&'a self, q: &Queue) -> Iter<'a> {
+        Iter { slab: self, at: q.head, left: q.len }
+    }
+
+    /// The live orders of `q`, front first.
+    pub(crate) fn live<'a>(&'a self, q: &Queue) -> impl Iterator<Item = &'a Order> + 'a {
+        self.iter(q).filter(|(_, node)| !node.canceled).map(|(_, node)| &node.order)
+    }
+
+    fn link_back(&mut self, q: &mut Queue, at: usize) {
+        let node = self.get_mut(at);
+        node.prev = q.tail;
+        node.next = NIL;
+        match q.tail {
+            NIL => q.head = at,
+            tail => self.get_mut(tail).next = at,
+        }
+        q.tail = at;
+        q.len += 1;
+    }
+
+    fn unlink(&mut self, q: &mut Queue, at: usize) {
+        let (prev, next) = {
+            let node = self.get(at);
+            (node.prev, node.next)
+        };
+        match prev {
+            NIL => q.head = next,
+            prev => self.get_mut(prev).next = next,
+        }
+        match next {
+            NIL => q.tail = prev,
+            next => self.get_mut(next).prev = prev,
+        }
+        q.len -= 1;
+    }
+}
+
+/// Walks one queue front to back, stopping after as many orders as the
+/// queue says it holds.
+pub(crate) struct Iter<'a> {
+    slab: &'a Slab,
+    at: usize,
+    left: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (usize, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 || self.at == NIL {
+            return None;
+        }
+        let at = self.at;
+        let node = self.slab.get(at);
+        self.at = node.next;
+        self.left -= 1;
+        Some((at, node))
+    }
+}
@@ -0,0 +1,168 @@
+//! Interned symbols.
+//!
+//! Orders and trades carry a [`SymbolId`], a `u32` handed out by the
+//! process-wide [`SymbolRegistry`], rather than the symbol's name. Copying
+//! one is free, so the matching loop stamps trades without allocating, and
+//! comparing or hashing one is an integer compare.
+//!
+//! Names are interned once, where they come in (an HTTP path, a CSV row, a
+//! feed message), and never freed; a process sees a bounded set of
+//! symbols. An id converts back with [`SymbolId::as_str`] or `Display`.
+//!
+//! A `SymbolId` serializes as its name, so JSON, bincode and the binary
+//! codec write what they wrote when symbols were strings, and ids never
+//! leak into files another process would read with its own numbering.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+/// A symbol interned in the [`SymbolRegistry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SymbolId(pub u32);
+
+impl SymbolId {
+    /// The id of `name`, interning it on first sight.
+    pub fn intern(name: &str) -> Self {
+        SymbolRegistry::global().intern(name)
+    }
+
+    /// The symbol's name.
+    pub fn as_str(self) -> &'static str {
+        SymbolRegistry::global().name(self)
+    }
+}
+
+impl fmt::Display for SymbolId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for SymbolId {
+    fn from(name: &str) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<&String> for SymbolId {
+    fn from(name: &String) -> Self {
+        Self::intern(name)
+    }
+}
+
+impl From<String> for SymbolId {
+    fn from(name: String) -> Self {
+        Self::intern(&name)
+    }
+}
+
+impl From<SymbolId> for String {
+    fn from(id: SymbolId) -> Self {
+        id.as_str().to_string()
+    }
+}
+
+impl PartialEq<str> for SymbolId {
+    fn eq(&self, name: &str) -> bool {
+        self.as_str() == name
+    }
+}
+
+impl PartialEq<&str> for SymbolId {
+    fn eq(&self, name: &&str) -> bool {
+        self.as_str() == *name
+    }
+}
+
+impl Serialize for SymbolId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SymbolId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Self::intern(&name))
+    }
+}
+
+/// Every symbol this process has seen, by name and by id.
+pub struct SymbolRegistry {
+    names: RwLock<Names>,
+}
+
+#[derive(Default)]
+struct Names {
+    ids: HashMap<&'static str, SymbolId>,
+    by_id: Vec<&'static str>,
+}
+
+static SYMBOLS: OnceLock<SymbolRegistry> = OnceLock::new();
+
+impl SymbolRegistry {
+    /// The registry every [`SymbolId`] refers to.
+    pub fn global() -> &'static SymbolRegistry {
+        SYMBOLS.get_or_init(|| SymbolRegistry { names: RwLock::new(Names::default()) })
+    }
+
+    /// The id of `name`, interning it on first sight. Ids are handed out
+    /// from 0 in the order names are first seen.
+    pub fn intern(&self, name: &str) -> SymbolId {
+        if let Some(id) = self.lookup(name) {
+            return id;
+        }
+        let mut names = self.names.write().unwrap();
+        if let Some(&id) = names.ids.get(name) {
+            return id;
+        }
+        let id = SymbolId(u32::try_from(names.by_id.len()).expect("more than u32::MAX symbols"));
+        let name: &'static str = Box::leak(name.into());
+        names.ids.insert(name, id);
+        names.by_id.push(name);
+        id
+    }
+
+    /// The id of `name` if it has been interned.
+    pub fn lookup(&self, name: &str) -> Option<SymbolId> {
+        self.names.read().unwrap().ids.get(name).copied()
+    }
+
+    /// The name `id` was interned from. Panics on an id this registry
+    /// never handed out.
+    pub fn name(&self, id: SymbolId) -> &'static str {
+        let names = self.names.read().unwrap();
+        names.by_id.get(id.0 as usize).copied().expect("symbol id not from this registry")
+    }
+
+    /// Symbols interned so far.
+    pub fn len(&self) -> usize {
+        self.names.read().unwrap().by_id.len()
+    }
+
+    /// True before any symbol has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning_is_stable_and_serializes_the_name() {
+        let id = SymbolId::intern("SYMTEST");
+        assert_eq!(SymbolId::intern("SYMTEST"), id);
+        assert_ne!(SymbolId::intern("SYMTEST2"), id);
+        assert_eq!(id.as_str(), "SYMTEST");
+        assert_eq!(SymbolRegistry::global().lookup("SYMTEST"), Some(id));
+        assert_eq!(SymbolRegistry::global().lookup("SYMTEST-UNSEEN"), None);
+
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, "\"SYMTEST\"");
+        assert_eq!(serde_json::from_str::<SymbolId>(&json).unwrap(), id);
+    }
+}
@@ -0,0 +1,127 @@
+//! Transaction cost analysis for a single order.
+//!
+//! Costs are measured against the arrival price (the mid when the order
+//! reached the book) and signed so that a positive cost is money lost:
+//! buying above arrival or selling below it.
+//!
+//! - Execution cost: what the filled part paid over arrival.
+//! - Opportunity cost: what the unfilled part would have paid at the price
+//!   when the order ended, i.e. the move the order missed.
+//! - Implementation shortfall: their sum, also in basis points of the
+//!   order's paper notional (`arrival * qty`).
+//! - Slippage: average fill against the market's VWAP over the order's
+//!   life, in basis points.
+//! - Participation: the order's fills as a share of all volume traded in
+//!   the symbol over its life.
+
+use crate::types::Side;
+use serde::{Deserialize, Serialize};
+
+/// One order's execution and the benchmarks around it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Execution {
+    pub side: Side,
+    pub qty: i64,
+    pub filled: i64,
+    /// Sum of fill price times quantity
+    pub filled_notional: f64,
+    pub arrival_px: f64,
+    /// Price when the order finished, for pricing the unfilled part
+    pub end_px: Option<f64>,
+    /// Market VWAP over the order's life
+    pub interval_vwap: Option<f64>,
+    /// Everything traded in the symbol over the order's life, its own fills included
+    pub market_volume: i64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TcaMetrics {
+    pub avg_fill_px: Option<f64>,
+    /// In ticks times quantity
+    pub execution_cost: f64,
+    pub opportunity_cost: f64,
+    pub implementation_shortfall: f64,
+    pub shortfall_bps: Option<f64>,
+    pub slippage_bps: Option<f64>,
+    pub participation: Option<f64>,
+}
+
+impl Execution {
+    pub fn analyze(&self) -> TcaMetrics {
+        let sign = match self.side {
+            Side::Bid => 1.0,
+            Side::Ask => -1.0,
+        };
+        let avg_fill_px = (self.filled > 0).then(|| self.filled_notional / self.filled as f64);
+        let execution_cost = avg_fill_px.map_or(0.0, |avg| sign * (avg - self.arrival_px) * self.filled as f64);
+        let unfilled = (self.qty - self.filled).max(0) as f64;
+        let opportunity_cost = self.end_px.map_or(0.0, |end| sign * (end - self.arrival_px) * unfilled);
+        let implementation_shortfall = execution_cost + opportunity_cost;
+        let paper = self.arrival_px * self.qty as f64;
+        TcaMetrics {
+            avg_fill_px,
+            execution_cost,
+            opportunity_cost,
+            implementation_shortfall,
+            shortfall_bps: (paper > 0.0).then(|| implementation_shortfall / paper * 1e4),
+            slippage_bps: avg_fill_px
+                .zip(self.interval_vwap)
+                .filter(|(_, vwap)| *vwap > 0.0)
+                .map(|(avg, vwap)| sign * (avg - vwap) / vwap * 1e4),
+            participation: (self.market_volume > 0).then(|| self.filled as f64 / self.market_volume as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buys_above_arrival_cost_and_missed_moves_count() {
+        // Buy 10 arriving at 100: 6 fill at an average 101, the rest is
+        // cancelled with the market at 104
+        let execution = Execution {
+            side: Side::Bid,
+            qty: 10,
+            filled: 6,
+            filled_notional: 606.0,
+            arrival_px: 100.0,
+            end_px: Some(104.0),
+            interval_vwap: Some(102.0),
+            market_volume: 24,
+        };
+        let metrics = execution.analyze();
+        assert_eq!(metrics.avg_fill_px, Some(101.0));
+        assert_eq!(metrics.execution_cost, 6.0);
+        assert_eq!(metrics.opportunity_cost, 16.0);
+        assert_eq!(metrics.shortfall_bps, Some(220.0));
+        // Beat the interval VWAP by a tick
+        assert!((metrics.slippage_bps.unwrap() + 1e4 / 102.0).abs() < 1e-9);
+        assert_eq!(metrics.participation, Some(0.25));
+    }
+
+    #[test]
+    fn sells_are_mirrored_and_unfilled_orders_have_no_fill_metrics() {
+        let sell = Execution {
+            side: Side::Ask,
+            qty: 5,
+            filled: 5,
+            filled_notional: 495.0,
+            arrival_px: 100.0,
+            end_px: Some(90.0),
+            interval_vwap: Some(99.0),
+            market_volume: 5,
+        };
+        let metrics = sell.analyze();
+        // Sold at 99 against 100: a cost of 1 per unit; nothing left unfilled
+        assert_eq!((metrics.execution_cost, metrics.opportunity_cost), (5.0, 0.0));
+        assert_eq!(metrics.slippage_bps, Some(0.0));
+        assert_eq!(metrics.participation, Some(1.0));
+
+        let resting = Execution { filled: 0, filled_notional: 0.0, end_px: None, market_volume: 0, ..sell };
+        let metrics = resting.analyze();
+        assert_eq!((metrics.avg_fill_px, metrics.slippage_bps, metrics.participation), (None, None, None));
+        assert_eq!(metrics.shortfall_bps, Some(0.0));
+    }
+}
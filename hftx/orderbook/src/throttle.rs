@@ -0,0 +1,135 @@
+//! Token-bucket message-rate limits per order owner.
+//!
+//! Each owner gets a bucket holding up to `burst` tokens that refills at
+//! `rate_per_sec`; a submission spends one token, and one that finds the
+//! bucket empty is refused with [`Throttled`]. Time comes from the caller
+//! (for a book built [`with_throttle`](crate::OrderBook::with_throttle),
+//! the order's `ts_ns`) rather than the wall clock, so throttling behaves
+//! the same under simulation and replay as it does live.
+//!
+//! A clock that steps backwards refills nothing; it never drains a bucket.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleConfig {
+    /// Sustained submissions per second
+    pub rate_per_sec: f64,
+    /// Submissions allowed back to back from a full bucket
+    pub burst: u32,
+}
+
+/// A submission refused for exceeding its owner's rate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Throttled {
+    /// Nanoseconds until the next token
+    pub retry_after_ns: u64,
+}
+
+impl fmt::Display for Throttled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "message rate exceeded; retry in {} us", self.retry_after_ns.div_ceil(1_000))
+    }
+}
+
+impl std::error::Error for Throttled {}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    last_ns: u128,
+}
+
+/// One bucket per owner, all with the same config.
+#[derive(Clone, Debug)]
+pub struct Throttle<K = String> {
+    config: ThrottleConfig,
+    buckets: HashMap<K, Bucket>,
+}
+
+impl<K: Hash + Eq> Throttle<K> {
+    /// Panics unless the rate is positive and finite and the burst nonzero.
+    pub fn new(config: ThrottleConfig) -> Self {
+        assert!(config.rate_per_sec > 0.0 && config.rate_per_sec.is_finite(), "throttle rate must be positive");
+        assert!(config.burst > 0, "throttle burst must be at least 1");
+        Self { config, buckets: HashMap::new() }
+    }
+
+    pub fn config(&self) -> ThrottleConfig {
+        self.config
+    }
+
+    /// Spends one of `owner`'s tokens at `now_ns`, or says how long until
+    /// one is available. New owners start with a full bucket.
+    pub fn check(&mut self, owner: K, now_ns: u128) -> Result<(), Throttled> {
+        let ThrottleConfig { rate_per_sec, burst } = self.config;
+        let bucket = self.buckets.entry(owner).or_insert(Bucket { tokens: burst as f64, last_ns: now_ns });
+        if now_ns > bucket.last_ns {
+            let elapsed_secs = (now_ns - bucket.last_ns) as f64 / 1e9;
+            bucket.tokens = (bucket.tokens + elapsed_secs * rate_per_sec).min(burst as f64);
+            bucket.last_ns = now_ns;
+        }
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            let retry_after_ns = ((1.0 - bucket.tokens) / rate_per_sec * 1e9).ceil() as u64;
+            Err(Throttled { retry_after_ns })
+        }
+    }
+
+    /// Forgets `owner`, whose next submission starts from a full bucket.
+    pub fn remove(&mut self, owner: &K) {
+        self.buckets.remove(owner);
+    }
+
+    /// Owners with a bucket.
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: u128 = 1_000_000;
+
+    #[test]
+    fn burst_then_sustained_rate() {
+        let mut throttle = Throttle::new(ThrottleConfig { rate_per_sec: 100.0, burst: 3 });
+        for _ in 0..3 {
+            assert!(throttle.check("a", 0).is_ok());
+        }
+        assert_eq!(throttle.check("a", 0), Err(Throttled { retry_after_ns: 10_000_000 }));
+        // Owners don't share buckets
+        assert!(throttle.check("b", 0).is_ok());
+
+        // One token every 10 ms
+        assert_eq!(throttle.check("a", 4 * MS), Err(Throttled { retry_after_ns: 6_000_000 }));
+        assert!(throttle.check("a", 10 * MS).is_ok());
+        assert!(throttle.check("a", 10 * MS).is_err());
+
+        // Refills stop at the burst
+        for _ in 0..3 {
+            assert!(throttle.check("a", 10_000 * MS).is_ok());
+        }
+        assert!(throttle.check("a", 10_000 * MS).is_err());
+    }
+
+    #[test]
+    fn backwards_clock_refills_nothing() {
+        let mut throttle = Throttle::new(ThrottleConfig { rate_per_sec: 1.0, burst: 1 });
+        assert!(throttle.check(7u64, 5_000 * MS).is_ok());
+        assert!(throttle.check(7u64, 0).is_err());
+        throttle.remove(&7);
+        assert!(throttle.check(7u64, 0).is_ok());
+    }
+}
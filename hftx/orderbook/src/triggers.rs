@@ -0,0 +1,173 @@
+//! Stop and stop-limit orders held until a trade sets them off.
+//!
+//! A stop waits off the book until a trade prints at or through its stop
+//! price: at or above it for a buy stop, at or below it for a sell stop.
+//! It then enters matching as its [`OrderKind`] says: a `Market` stop
+//! sweeps like [`OrderBook::submit_market`](crate::OrderBook::submit_market),
+//! a `Limit` stop (a stop-limit) is submitted at its own `px_ticks`.
+//!
+//! Stops set off by the same trades go in the order the price reached
+//! them, so buy stops lowest first and sell stops highest first, then in
+//! arrival order.
+
+use crate::types::{Order, OrderId, OrderKind, Side};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+
+/// An order waiting for a trade at or through `stop_px`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StopOrder {
+    pub order: Order,
+    pub stop_px: i64,
+    /// `Market` for a plain stop, `Limit` for a stop-limit
+    pub kind: OrderKind,
+}
+
+impl StopOrder {
+    /// Whether trades printing from `low` to `high` set this stop off.
+    pub fn triggered_by(&self, low: i64, high: i64) -> bool {
+        match self.order.side {
+            Side::Bid => high >= self.stop_px,
+            Side::Ask => low <= self.stop_px,
+        }
+    }
+}
+
+/// Key of a held stop: its stop price, then its arrival number.
+type Key = (i64, u64);
+
+/// The holding area for one book's stops.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Triggers {
+    #[serde(with = "keyed")]
+    buys: BTreeMap<Key, StopOrder>,
+    #[serde(with = "keyed")]
+    sells: BTreeMap<Key, StopOrder>,
+    /// Where each held stop is keyed
+    index: HashMap<OrderId, (Side, Key)>,
+    next_seq: u64,
+}
+
+/// Held stops serialize as a list of (key, stop) pairs, since formats
+/// like JSON only take strings and numbers as map keys.
+mod keyed {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(stops: &BTreeMap<Key, StopOrder>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(stops)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<Key, StopOrder>, D::Error> {
+        Ok(Vec::<(Key, StopOrder)>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+impl Triggers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Holds `stop` until a trade sets it off.
+    pub fn hold(&mut self, stop: StopOrder) {
+        let key = (stop.stop_px, self.next_seq);
+        self.next_seq += 1;
+        let side = stop.order.side;
+        self.index.insert(stop.order.id, (side, key));
+        match side {
+            Side::Bid => self.buys.insert(key, stop),
+            Side::Ask => self.sells.insert(key, stop),
+        };
+    }
+
+    /// Removes a held stop, returning it if it was still waiting.
+    pub fn cancel(&mut self, id: OrderId) -> Option<StopOrder> {
+        let (side, key) = self.index.remove(&id)?;
+        match side {
+            Side::Bid => self.buys.remove(&key),
+            Side::Ask => self.sells.remove(&key),
+        }
+    }
+
+    /// A held stop by id.
+    pub fn get(&self, id: OrderId) -> Option<&StopOrder> {
+        let (side, key) = self.index.get(&id)?;
+        match side {
+            Side::Bid => self.buys.get(key),
+            Side::Ask => self.sells.get(key),
+        }
+    }
+
+    pub fn contains(&self, id: OrderId) -> bool {
+        self.index.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Held stops on `side`, in the order they would trigger.
+    pub fn held(&self, side: Side) -> Box<dyn Iterator<Item = &StopOrder> + '_> {
+        match side {
+            Side::Bid => Box::new(self.buys.values()),
+            Side::Ask => Box::new(self.sells.values().rev()),
+        }
+    }
+
+    /// Takes out every stop that trades printing from `low` to `high` set
+    /// off, buy stops first, each side in trigger order.
+    pub fn triggered(&mut self, low: i64, high: i64) -> Vec<StopOrder> {
+        // Buys with stop_px <= high, sells with stop_px >= low
+        let buys = match high.checked_add(1) {
+            Some(above) => {
+                let rest = self.buys.split_off(&(above, 0));
+                std::mem::replace(&mut self.buys, rest)
+            }
+            None => std::mem::take(&mut self.buys),
+        };
+        let sells = self.sells.split_off(&(low, 0));
+        let fired: Vec<StopOrder> = buys.into_values().chain(sells.into_values().rev()).collect();
+        for stop in &fired {
+            self.index.remove(&stop.order.id);
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ParticipantId, TimeInForce};
+
+    fn stop(id: u128, side: Side, stop_px: i64) -> StopOrder {
+        let order = Order { id: OrderId(id), symbol: "AAPL".into(), side, px_ticks: stop_px, qty: 1, ts_ns: id, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE };
+        StopOrder { order, stop_px, kind: OrderKind::Market }
+    }
+
+    #[test]
+    fn prints_through_the_stop_price_set_it_off_in_price_order() {
+        let mut triggers = Triggers::new();
+        for (id, side, px) in [(1, Side::Bid, 105), (2, Side::Bid, 103), (3, Side::Ask, 95), (4, Side::Ask, 97), (5, Side::Bid, 103)] {
+            triggers.hold(stop(id, side, px));
+        }
+
+        assert!(triggers.triggered(98, 102).is_empty());
+        let ids = |fired: Vec<StopOrder>| fired.iter().map(|s| s.order.id.0).collect::<Vec<_>>();
+        assert_eq!(ids(triggers.triggered(97, 104)), [2, 5, 4]);
+        assert_eq!(triggers.len(), 2);
+        assert_eq!(ids(triggers.triggered(i64::MIN, i64::MAX)), [1, 3]);
+        assert!(triggers.is_empty());
+    }
+
+    #[test]
+    fn cancelled_stops_never_fire() {
+        let mut triggers = Triggers::new();
+        triggers.hold(stop(1, Side::Ask, 100));
+        assert_eq!(triggers.cancel(OrderId(1)).map(|s| s.stop_px), Some(100));
+        assert_eq!(triggers.cancel(OrderId(1)), None);
+        assert!(triggers.triggered(90, 90).is_empty());
+    }
+}
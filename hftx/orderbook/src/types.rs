@@ -3,19 +3,57 @@
 //! All types use integer ticks for prices
 //! Timestamps are nanoseconds since epoch for high-precision time priority.
 
+use crate::symbols::SymbolId;
+use crate::throttle::Throttled;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Order side - Bid (buy) or Ask (sell).
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Side {
     Bid,
     Ask,
 }
 
+impl Side {
+    /// The side an order of this side trades against.
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        }
+    }
+
+    /// True if `px` is strictly better than `than` for orders resting on
+    /// this side: lower for asks, higher for bids.
+    pub fn better(self, px: i64, than: i64) -> bool {
+        match self {
+            Side::Ask => px < than,
+            Side::Bid => px > than,
+        }
+    }
+
+    /// True if an order resting on this side at `px` trades with a taker
+    /// limited to `limit_px`.
+    pub fn crosses(self, px: i64, limit_px: i64) -> bool {
+        !self.better(limit_px, px)
+    }
+
+    /// The limit at which an order of this side takes any price, as a
+    /// market order does.
+    pub fn market_limit(self) -> i64 {
+        match self {
+            Side::Bid => i64::MAX,
+            Side::Ask => i64::MIN,
+        }
+    }
+}
+
 /// Time-in-force instructions for order lifetime.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TimeInForce {
     /// Active until end of trading session
+    #[default]
     Day,
     /// Execute immediately, cancel remainder
     IOC,
@@ -33,18 +71,40 @@ pub enum OrderKind {
 }
 
 /// Unique order identifier.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct OrderId(pub u128);
 
+/// Who an order belongs to, numbered by whoever submits it (the exchange
+/// numbers its accounts). [`NONE`](Self::NONE), the default, owns nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ParticipantId(pub u32);
+
+impl ParticipantId {
+    /// No owner: an order the book doesn't index by owner
+    pub const NONE: ParticipantId = ParticipantId(0);
+}
+
 /// Complete order specification.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Order {
     pub id: OrderId,
-    pub symbol: String,
+    pub symbol: SymbolId,
     pub side: Side,
     pub px_ticks: i64, // Price in integer ticks
     pub qty: i64,      // Quantity in shares/lots
     pub ts_ns: u128,   // Timestamp in nanoseconds
+    #[serde(default)]
+    pub tif: TimeInForce, // What happens to an unfilled remainder
+    #[serde(default)]
+    pub expires_ns: Option<u128>, // Good-til-date: expires once the clock reaches this
+    #[serde(default)]
+    pub display_qty: Option<i64>, // Iceberg: the most that rests visibly at a time
+    #[serde(default)]
+    pub min_qty: Option<i64>, // Smallest fill taken on arrival
+    #[serde(default)]
+    pub hidden: bool, // Dark: matches, but never shows in depth or best prices
+    #[serde(default)]
+    pub owner: ParticipantId, // Who may see and cancel it
 }
 
 /// Trade execution record.
@@ -52,34 +112,457 @@ pub struct Order {
 pub struct Trade {
     pub maker: OrderId, // Resting order (provides liquidity)
     pub taker: OrderId, // Incoming order (takes liquidity)
-    pub symbol: String,
+    pub symbol: SymbolId,
     pub px_ticks: i64, // Execution price (always maker's price)
     pub qty: i64,      // Quantity traded
     pub ts_ns: u128,   // Execution timestamp
+    #[serde(default)]
+    pub seq: u64,      // Book event number (OrderBook::seq); 0 if not from a book
+}
+
+/// Where a submitted order stands once its submission is done.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderStatus {
+    /// Nothing filled; the whole order rests
+    Rested,
+    /// Some filled, and the rest rests
+    PartiallyFilled,
+    /// Filled completely
+    Filled,
+    /// What didn't fill was cancelled (IOC, or a minimum fill not met)
+    Cancelled,
+    /// Refused whole: a FOK that couldn't fill completely
+    Rejected,
 }
 
+impl OrderStatus {
+    /// Lower-case name, as the exchange reports it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Rested => "rested",
+            OrderStatus::PartiallyFilled => "partially_filled",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// Why a book refused an order before it touched anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectReason {
+    /// A quantity at or below zero
+    InvalidQuantity(i64),
+    /// A limit price at or below zero
+    InvalidPrice(i64),
+    /// An order with this id is already resting, or held as a stop
+    DuplicateId(OrderId),
+    /// Over its owner's message rate (see [`throttle`](crate::throttle))
+    Throttled(Throttled),
+    /// For another symbol than the one the book is bound to
+    SymbolMismatch { book: SymbolId, order: SymbolId },
+    /// A price that isn't a multiple of the symbol's tick size
+    OffTick { px_ticks: i64, tick_size: i64 },
+    /// A quantity that isn't a multiple of the symbol's lot size
+    OffLot { qty: i64, lot_size: i64 },
+    /// A quantity outside the symbol's `min_qty..=max_qty`
+    QuantityOutOfRange { qty: i64, min_qty: i64, max_qty: i64 },
+    /// A quantity that would take its side's total, or what its order has
+    /// filled plus what it has left, past `i64::MAX`
+    QuantityOverflow(i64),
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::InvalidQuantity(qty) => write!(f, "quantity {} must be positive", qty),
+            RejectReason::InvalidPrice(px_ticks) => write!(f, "price {} must be positive", px_ticks),
+            RejectReason::DuplicateId(id) => write!(f, "order id {} is already live", id.0),
+            RejectReason::Throttled(throttled) => throttled.fmt(f),
+            RejectReason::SymbolMismatch { book, order } => write!(f, "order for {} sent to the {} book", order, book),
+            RejectReason::OffTick { px_ticks, tick_size } => write!(f, "price {} is not a multiple of the tick size {}", px_ticks, tick_size),
+            RejectReason::OffLot { qty, lot_size } => write!(f, "quantity {} is not a multiple of the lot size {}", qty, lot_size),
+            RejectReason::QuantityOutOfRange { qty, min_qty, max_qty } => {
+                write!(f, "quantity {} is outside {}..={}", qty, min_qty, max_qty)
+            }
+            RejectReason::QuantityOverflow(qty) => write!(f, "quantity {} would overflow the book's totals", qty),
+        }
+    }
+}
+
+impl std::error::Error for RejectReason {}
+
+impl From<Throttled> for RejectReason {
+    fn from(throttled: Throttled) -> Self {
+        RejectReason::Throttled(throttled)
+    }
+}
+
+/// One symbol's trading parameters: the price and quantity steps orders
+/// must keep to, and the size range they must fall in. Prices are in the
+/// book's ticks, so a `tick_size` of 5 on a book in cents takes orders at
+/// whole nickels only. The default takes any positive price and quantity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolConfig {
+    pub tick_size: i64,
+    pub lot_size: i64,
+    pub min_qty: i64,
+    pub max_qty: i64,
+}
+
+impl Default for SymbolConfig {
+    fn default() -> Self {
+        Self { tick_size: 1, lot_size: 1, min_qty: 1, max_qty: i64::MAX }
+    }
+}
+
+impl SymbolConfig {
+    /// Checks the parameters make sense: positive steps, and a nonempty
+    /// positive size range.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tick_size <= 0 || self.lot_size <= 0 {
+            return Err(format!("tick_size {} and lot_size {} must be positive", self.tick_size, self.lot_size));
+        }
+        if self.min_qty <= 0 || self.max_qty < self.min_qty {
+            return Err(format!("need 0 < min_qty <= max_qty, got {} and {}", self.min_qty, self.max_qty));
+        }
+        Ok(())
+    }
+
+    /// Checks an order's quantity, and its price unless it has none (a
+    /// market order).
+    pub fn check(&self, px_ticks: Option<i64>, qty: i64) -> Result<(), RejectReason> {
+        if let Some(px_ticks) = px_ticks.filter(|px| px % self.tick_size != 0) {
+            return Err(RejectReason::OffTick { px_ticks, tick_size: self.tick_size });
+        }
+        if qty % self.lot_size != 0 {
+            return Err(RejectReason::OffLot { qty, lot_size: self.lot_size });
+        }
+        if qty < self.min_qty || qty > self.max_qty {
+            return Err(RejectReason::QuantityOutOfRange { qty, min_qty: self.min_qty, max_qty: self.max_qty });
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of submitting one order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    pub order_id: OrderId,
+    pub status: OrderStatus,
+    /// Filled so far, as taker or, once resting, as maker
+    pub filled_qty: i64,
+    /// Still working on the book; 0 unless it rests
+    pub remaining_qty: i64,
+    /// Quantity-weighted fill price in ticks; None with nothing filled
+    pub avg_px: Option<f64>,
+    /// Every trade of the submission, including those of stops and pegs it
+    /// set off
+    pub trades: Vec<Trade>,
+}
+
+impl ExecutionReport {
+    /// Reports on order `order_id` of `qty`, submitted with `tif`, given
+    /// the submission's `trades` and whether the order is `resting`
+    /// afterwards.
+    pub fn new(order_id: OrderId, qty: i64, tif: TimeInForce, resting: bool, trades: Vec<Trade>) -> Self {
+        let (filled_qty, notional) = trades
+            .iter()
+            .filter(|t| t.taker == order_id || t.maker == order_id)
+            .fold((0i64, 0i128), |(qty, notional), t| (qty + t.qty, notional + t.px_ticks as i128 * t.qty as i128));
+        let status = match (resting, filled_qty) {
+            (true, 0) => OrderStatus::Rested,
+            (true, _) => OrderStatus::PartiallyFilled,
+            (false, filled) if filled >= qty => OrderStatus::Filled,
+            (false, 0) if tif == TimeInForce::FOK => OrderStatus::Rejected,
+            (false, _) => OrderStatus::Cancelled,
+        };
+        Self {
+            order_id,
+            status,
+            filled_qty,
+            remaining_qty: if resting { qty - filled_qty } else { 0 },
+            avg_px: (filled_qty > 0).then(|| notional as f64 / filled_qty as f64),
+            trades,
+        }
+    }
+}
+
+/// One displayed price level, aggregated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BookLevel {
+    pub px_ticks: i64,
+    /// Displayed quantity of the live orders there
+    pub qty: i64,
+    /// Live displayed orders there
+    pub orders: usize,
+}
+
+/// The top displayed levels of both sides, best first.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Depth {
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+    /// Book event number the levels are current to (`OrderBook::seq`)
+    #[serde(default)]
+    pub seq: u64,
+}
+
+impl Depth {
+    /// CRC-32 (IEEE, as zlib computes it) of the levels written out as
+    /// text: level by level from the best, the bid's `px_ticks:qty` then
+    /// the ask's, all joined by `:`, with a side out of levels adding
+    /// nothing. Bids 100 x 5 and 99 x 2 against an ask of 101 x 3 read
+    /// `100:5:101:3:99:2`. A client that keeps its own book computes the
+    /// same over its top levels to check it hasn't drifted.
+    pub fn checksum(&self) -> u32 {
+        let mut text = String::new();
+        for i in 0..self.bids.len().max(self.asks.len()) {
+            for level in [self.bids.get(i), self.asks.get(i)].into_iter().flatten() {
+                if !text.is_empty() {
+                    text.push(':');
+                }
+                text.push_str(&format!("{}:{}", level.px_ticks, level.qty));
+            }
+        }
+        crc32fast::hash(text.as_bytes())
+    }
+}
+
+/// Why a price couldn't be turned into ticks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceError {
+    /// Not a number: unparseable, infinite or NaN
+    NotANumber,
+    /// Between two ticks
+    OffTick,
+    /// More ticks than an `i64` holds
+    OutOfRange,
+}
+
+impl fmt::Display for PriceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PriceError::NotANumber => write!(f, "price is not a number"),
+            PriceError::OffTick => write!(f, "price is not a whole number of ticks"),
+            PriceError::OutOfRange => write!(f, "price is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for PriceError {}
+
+/// Converts one symbol's decimal prices to integer ticks and back.
+///
+/// A symbol quoted to `decimals` places with a tick of `tick_size` has
+/// prices that are whole multiples of the tick: with a 0.01 tick, 150.00
+/// is 15000 ticks and 150.005 is refused. The tick is kept as a whole
+/// number of the smallest quoted unit (10^-`decimals`), so converting
+/// ticks back never accumulates float error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceConverter {
+    /// Tick size in units of 10^-decimals
+    tick_units: i64,
+    decimals: u32,
+}
+
+impl PriceConverter {
+    /// A converter for ticks of `tick_size`, quoted to `decimals` places.
+    /// Panics unless `tick_size` is a positive multiple of 10^-`decimals`
+    /// and `decimals` is at most 18.
+    pub fn new(tick_size: f64, decimals: u32) -> Self {
+        assert!(decimals <= 18, "at most 18 decimals");
+        let scaled = tick_size * 10f64.powi(decimals as i32);
+        let tick_units = scaled.round();
+        assert!(
+            tick_units >= 1.0 && tick_units < i64::MAX as f64 && (scaled - tick_units).abs() <= float_noise(scaled),
+            "tick size {} is not a positive multiple of 10^-{}",
+            tick_size,
+            decimals
+        );
+        Self { tick_units: tick_units as i64, decimals }
+    }
+
+    pub fn tick_size(&self) -> f64 {
+        self.tick_units as f64 / self.scale() as f64
+    }
+
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    /// 10^decimals, the smallest quoted units in 1.
+    fn scale(&self) -> i64 {
+        10i64.pow(self.decimals)
+    }
+
+    /// Ticks in `price`, refusing a price between ticks. Float noise
+    /// (150.05 being 150.04999...) is rounded away, but only the few ulps
+    /// a float can be off by; a price with more places than the symbol
+    /// quotes is off tick, however large.
+    pub fn to_ticks(&self, price: f64) -> Result<i64, PriceError> {
+        if !price.is_finite() {
+            return Err(PriceError::NotANumber);
+        }
+        let scaled = price * self.scale() as f64;
+        let units = scaled.round();
+        if (scaled - units).abs() > float_noise(scaled) {
+            return Err(PriceError::OffTick);
+        }
+        if units >= i64::MAX as f64 || units < i64::MIN as f64 {
+            return Err(PriceError::OutOfRange);
+        }
+        self.units_to_ticks(units as i64)
+    }
+
+    /// [`to_ticks`](Self::to_ticks) for a decimal string such as
+    /// `"150.05"` or `"-0.5"`, exactly: there is no float in between.
+    /// Trailing zeros past the quoted places are fine.
+    pub fn parse(&self, price: &str) -> Result<i64, PriceError> {
+        let (negative, digits) = match price.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, price.strip_prefix('+').unwrap_or(price)),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty() && fraction.is_empty() || !is_digits(whole) || !is_digits(fraction) {
+            return Err(PriceError::NotANumber);
+        }
+        let places = self.decimals as usize;
+        if fraction.len() > places && fraction[places..].bytes().any(|b| b != b'0') {
+            return Err(PriceError::OffTick);
+        }
+        let mut units: i64 = 0;
+        let quoted = fraction.bytes().take(places).chain(std::iter::repeat(b'0')).take(places);
+        for digit in whole.bytes().chain(quoted) {
+            units = units
+                .checked_mul(10)
+                .and_then(|u| u.checked_add((digit - b'0') as i64))
+                .ok_or(PriceError::OutOfRange)?;
+        }
+        self.units_to_ticks(if negative { -units } else { units })
+    }
+
+    fn units_to_ticks(&self, units: i64) -> Result<i64, PriceError> {
+        if units % self.tick_units != 0 {
+            return Err(PriceError::OffTick);
+        }
+        Ok(units / self.tick_units)
+    }
+
+    /// The price `px_ticks` stands for, as a float.
+    pub fn to_decimal(&self, px_ticks: i64) -> f64 {
+        (px_ticks as i128 * self.tick_units as i128) as f64 / self.scale() as f64
+    }
+
+    /// The price `px_ticks` stands for, written out exactly to the quoted
+    /// places: 15000 ticks of 0.01 is `"150.00"`.
+    pub fn format(&self, px_ticks: i64) -> String {
+        let units = px_ticks as i128 * self.tick_units as i128;
+        let sign = if units < 0 { "-" } else { "" };
+        let (scale, units) = (self.scale() as u128, units.unsigned_abs());
+        match self.decimals {
+            0 => format!("{}{}", sign, units),
+            places => format!("{}{}.{:0width$}", sign, units / scale, units % scale, width = places as usize),
+        }
+    }
+}
+
+/// How far `scaled`, a decimal price times a power of ten, can be from the
+/// whole number it stands for: the float error in the price and in the
+/// product, a few ulps of `scaled`.
+fn float_noise(scaled: f64) -> f64 {
+    f64::EPSILON * scaled.abs() * 4.0
+}
+
+// Orders sit by value in level queues and trades are produced per fill, so
+// their size is cache footprint on every sweep. rustc already orders fields
+// to minimise padding; these guards keep either type from silently growing
+// past its current footprint on 64-bit targets. (Order went from 80 to 112
+// bytes with `expires_ns`, to 128 with `display_qty`, and to 144 with
+// `min_qty`.)
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    assert!(std::mem::size_of::<Order>() <= 144);
+    assert!(std::mem::size_of::<Trade>() <= 96);
+};
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn side_price_comparisons() {
+        assert_eq!(Side::Bid.opposite(), Side::Ask);
+        assert!(Side::Ask.better(99, 100) && Side::Bid.better(101, 100));
+        assert!(!Side::Ask.better(100, 100));
+        // Resting asks at or under a bid's limit trade; bids at or over an ask's
+        assert!(Side::Ask.crosses(100, 100) && !Side::Ask.crosses(101, 100));
+        assert!(Side::Bid.crosses(100, 100) && !Side::Bid.crosses(99, 100));
+        assert!(Side::Ask.crosses(i64::MAX, Side::Bid.market_limit()));
+        assert!(Side::Bid.crosses(i64::MIN, Side::Ask.market_limit()));
+    }
+
+    #[test]
+    fn prices_convert_to_whole_ticks_and_back() {
+        let cents = PriceConverter::new(0.01, 2);
+        assert_eq!(cents.to_ticks(150.0), Ok(15000));
+        assert_eq!(cents.to_ticks(150.05), Ok(15005));
+        assert_eq!(cents.to_ticks(150.005), Err(PriceError::OffTick));
+        assert_eq!(cents.to_ticks(f64::NAN), Err(PriceError::NotANumber));
+        assert_eq!(cents.parse("150.050"), Ok(15005));
+        assert_eq!(cents.parse("-0.5"), Ok(-50));
+        assert_eq!(cents.parse("150.005"), Err(PriceError::OffTick));
+        assert_eq!(cents.parse("1.5e2"), Err(PriceError::NotANumber));
+        assert_eq!(cents.parse("99999999999999999999"), Err(PriceError::OutOfRange));
+        assert_eq!((cents.to_decimal(15005), cents.format(15005), cents.format(-5)), (150.05, "150.05".to_string(), "-0.05".to_string()));
+
+        // A nickel tick: 150.05 is tick 3001, 150.02 between two
+        let nickels = PriceConverter::new(0.05, 2);
+        assert_eq!(nickels.parse("150.05"), Ok(3001));
+        assert_eq!(nickels.to_ticks(150.02), Err(PriceError::OffTick));
+        assert_eq!(nickels.format(3001), "150.05");
+        assert_eq!(PriceConverter::new(5.0, 0).format(3), "15");
+    }
+
+    /// A large price a fraction of a tick off is off tick whether it comes
+    /// in as a float or a string.
+    #[test]
+    fn large_prices_between_ticks_are_refused() {
+        let cents = PriceConverter::new(0.01, 2);
+        assert_eq!(cents.to_ticks(20_000_000.004), Err(PriceError::OffTick));
+        assert_eq!(cents.parse("20000000.004"), Err(PriceError::OffTick));
+        assert_eq!(cents.to_ticks(1_000_000.000_9), Err(PriceError::OffTick));
+        assert_eq!(cents.to_ticks(20_000_000.01), Ok(2_000_000_001));
+        assert_eq!(cents.to_ticks(0.0), Ok(0));
+    }
+
     #[test]
     fn test_order_creation() {
         let o = Order {
             id: OrderId(1),
-            symbol: "AAPL".to_string(),
+            symbol: "AAPL".into(),
             side: Side::Bid,
             px_ticks: 195_430,
             qty: 100,
             ts_ns: 123_456_789,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
         };
 
         let t = Trade {
             maker: OrderId(2),
             taker: o.id,
-            symbol: o.symbol.clone(),
+            symbol: o.symbol,
             px_ticks: o.px_ticks,
             qty: 100,
             ts_ns: o.ts_ns + 10,
+            seq: 2,
         };
 
         assert_eq!(o.side, Side::Bid);
@@ -0,0 +1,29 @@
+//! Loading the sample books and events under `tests/data/`.
+
+use orderbook::csv::{load_books, read_events, replay_events};
+use orderbook::OrderId;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+fn open(name: &str) -> BufReader<File> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data").join(name);
+    BufReader::new(File::open(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e)))
+}
+
+#[test]
+fn sample_book_loads_and_replays_events() {
+    let mut books = load_books(open("book.csv")).unwrap();
+    assert_eq!(books["AAPL"].best_bid(), Some(14998));
+    assert_eq!(books["AAPL"].asks.qty_at_price(15001), 450);
+    assert_eq!(books["MSFT"].bids.total_len() + books["MSFT"].asks.total_len(), 4);
+
+    let trades = replay_events(&mut books, read_events(open("events.csv")).unwrap());
+    let fills: Vec<_> = trades.iter().map(|t| (t.maker, t.px_ticks, t.qty)).collect();
+    assert_eq!(
+        fills,
+        [(OrderId(6), 15001, 250), (OrderId(7), 15002, 250), (OrderId(9), 39990, 20)]
+    );
+    assert_eq!(books["AAPL"].asks.qty_at_price(15002), 150);
+    assert_eq!(books["MSFT"].bids.qty_at_price(39990), 30);
+}
@@ -0,0 +1,213 @@
+//! Model-based tests: `OrderBook` against a naive reference matcher.
+//!
+//! The reference keeps every resting order in one flat `Vec` and finds the
+//! best maker with a linear scan, which is slow but obviously correct. Random
+//! operation sequences are run against both, asserting identical trades,
+//! identical cancel results, and identical resting state after every step.
+//! Any storage or matching rewrite must keep this suite green. The suite
+//! runs on the default tree of levels, on a price ladder, and with eager
+//! cancels.
+
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, PriceLevels, Side, TimeInForce, Trade};
+use proptest::prelude::*;
+
+/// Obviously-correct price-time priority matcher.
+#[derive(Default)]
+struct ReferenceBook {
+    /// Resting orders in arrival order
+    resting: Vec<Order>,
+    /// Numbers a submit, then each of its trades; cancels here go straight
+    /// to the book's sides, which don't number them
+    seq: u64,
+}
+
+impl ReferenceBook {
+    fn submit(&mut self, mut taker: Order) -> Vec<Trade> {
+        let mut trades = Vec::new();
+        self.seq += 1;
+        while taker.qty > 0 {
+            // Best opposite price, earliest arrival among equals
+            let best = self
+                .resting
+                .iter()
+                .enumerate()
+                .filter(|(_, o)| o.side != taker.side)
+                .filter(|(_, o)| match taker.side {
+                    Side::Bid => o.px_ticks <= taker.px_ticks,
+                    Side::Ask => o.px_ticks >= taker.px_ticks,
+                })
+                .min_by_key(|(i, o)| match taker.side {
+                    Side::Bid => (o.px_ticks, *i),
+                    Side::Ask => (-o.px_ticks, *i),
+                })
+                .map(|(i, _)| i);
+            let Some(i) = best else {
+                break;
+            };
+
+            let maker = &mut self.resting[i];
+            let fill = taker.qty.min(maker.qty);
+            taker.qty -= fill;
+            maker.qty -= fill;
+            self.seq += 1;
+            trades.push(Trade {
+                maker: maker.id,
+                taker: taker.id,
+                symbol: taker.symbol,
+                px_ticks: maker.px_ticks,
+                qty: fill,
+                ts_ns: taker.ts_ns,
+                seq: self.seq,
+            });
+            if maker.qty == 0 {
+                self.resting.remove(i);
+            }
+        }
+        if taker.qty > 0 {
+            self.resting.push(taker);
+        }
+        trades
+    }
+
+    fn cancel(&mut self, id: OrderId) -> bool {
+        match self.resting.iter().position(|o| o.id == id) {
+            Some(i) => {
+                self.resting.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// (px, [(id, qty)]) per level, best level first, FIFO within a level.
+    fn levels(&self, side: Side) -> Vec<(i64, Vec<(OrderId, i64)>)> {
+        let mut prices: Vec<i64> = self
+            .resting
+            .iter()
+            .filter(|o| o.side == side)
+            .map(|o| o.px_ticks)
+            .collect();
+        prices.sort_unstable();
+        prices.dedup();
+        if side == Side::Bid {
+            prices.reverse();
+        }
+        prices
+            .into_iter()
+            .map(|px| {
+                let orders = self
+                    .resting
+                    .iter()
+                    .filter(|o| o.side == side && o.px_ticks == px)
+                    .map(|o| (o.id, o.qty))
+                    .collect();
+                (px, orders)
+            })
+            .collect()
+    }
+}
+
+/// Live resting state of one side of the real book, in the reference's shape.
+fn book_levels(levels: &PriceLevels, side: Side) -> Vec<(i64, Vec<(OrderId, i64)>)> {
+    let mut out: Vec<(i64, Vec<(OrderId, i64)>)> = levels
+        .get_price_levels()
+        .map(|(px, q)| {
+            let orders = q
+                .filter(|o| levels.contains(o.id))
+                .map(|o| (o.id, o.qty))
+                .collect();
+            (px, orders)
+        })
+        .filter(|(_, orders): &(i64, Vec<_>)| !orders.is_empty())
+        .collect();
+    if side == Side::Bid {
+        out.reverse();
+    }
+    out
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Submit { side: Side, px: i64, qty: i64 },
+    /// Lazy cancel of the n-th submitted order
+    Cancel { nth: usize },
+    /// Eager removal of the n-th submitted order
+    Remove { nth: usize },
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let side = prop_oneof![Just(Side::Bid), Just(Side::Ask)];
+    prop_oneof![
+        6 => (side, 95i64..=105, 1i64..=50).prop_map(|(side, px, qty)| Op::Submit { side, px, qty }),
+        2 => any::<usize>().prop_map(|nth| Op::Cancel { nth }),
+        1 => any::<usize>().prop_map(|nth| Op::Remove { nth }),
+    ]
+}
+
+fn run(mut book: OrderBook, ops: Vec<Op>) -> Result<(), TestCaseError> {
+    let mut reference = ReferenceBook::default();
+    let mut submitted: Vec<(OrderId, Side)> = Vec::new();
+
+    for (step, op) in ops.into_iter().enumerate() {
+        match op {
+            Op::Submit { side, px, qty } => {
+                let id = OrderId(submitted.len() as u128 + 1);
+                let order = Order {
+                    id,
+                    symbol: "AAPL".into(),
+                    side,
+                    px_ticks: px,
+                    qty,
+                    ts_ns: step as u128,
+                    tif: TimeInForce::Day,
+                    expires_ns: None,
+                    display_qty: None,
+                    min_qty: None,
+                    hidden: false,
+                    owner: ParticipantId::NONE,
+                };
+                submitted.push((id, side));
+                let expected = reference.submit(order.clone());
+                let actual = book.submit_limit(order).unwrap().trades;
+                prop_assert_eq!(actual, expected, "trades differ at step {}", step);
+            }
+            Op::Cancel { nth } | Op::Remove { nth } if submitted.is_empty() => {
+                let _ = nth;
+            }
+            Op::Cancel { nth } => {
+                let (id, side) = submitted[nth % submitted.len()];
+                prop_assert_eq!(book.levels_mut(side).cancel(id), reference.cancel(id), "cancel differs at step {}", step);
+            }
+            Op::Remove { nth } => {
+                let (id, side) = submitted[nth % submitted.len()];
+                prop_assert_eq!(book.levels_mut(side).remove(id).is_some(), reference.cancel(id), "remove differs at step {}", step);
+            }
+        }
+
+        prop_assert_eq!(book_levels(&book.bids, Side::Bid), reference.levels(Side::Bid), "bids differ at step {}", step);
+        prop_assert_eq!(book_levels(&book.asks, Side::Ask), reference.levels(Side::Ask), "asks differ at step {}", step);
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn matches_reference_model(ops in prop::collection::vec(op(), 1..200)) {
+        run(OrderBook::new(), ops)?;
+    }
+
+    /// Cancels that take orders out at once rather than tombstoning them.
+    #[test]
+    fn eager_cancel_matches_reference_model(ops in prop::collection::vec(op(), 1..200)) {
+        run(OrderBook::new().with_eager_cancel(), ops)?;
+    }
+
+    /// A price ladder narrower than the prices used, so levels both in and
+    /// out of its band come and go.
+    #[test]
+    fn ladder_matches_reference_model(ops in prop::collection::vec(op(), 1..200)) {
+        run(OrderBook::new().with_price_band(98, 102), ops)?;
+    }
+}
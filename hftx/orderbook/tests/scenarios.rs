@@ -0,0 +1,242 @@
+//! Declarative exchange-semantics conformance tests.
+//!
+//! Every `.yaml`, `.yml`, or `.json` file under `tests/scenarios/` describes a
+//! sequence of actions against a fresh `OrderBook`, the trades each action
+//! must produce, and the book it must leave behind. Scenarios are data, so
+//! new semantics (time in force, self-trade prevention, auctions) can be
+//! specified and shared without writing Rust.
+//!
+//! ```yaml
+//! name: partial fill keeps maker at front
+//! steps:
+//!   - submit: { id: 1, side: Ask, px: 100, qty: 50 }
+//!   - submit: { id: 2, side: Bid, px: 100, qty: 20 }
+//!     trades:
+//!       - { maker: 1, taker: 2, px: 100, qty: 20 }
+//! book:
+//!   asks:
+//!     - { px: 100, orders: [{ id: 1, qty: 30 }] }
+//! ```
+//!
+//! A step's `trades` defaults to "no trades"; `book` sides default to empty.
+//! Ids are `u64` in scenario files (YAML has no 128-bit integers).
+
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, PriceLevels, Side, TimeInForce};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scenario {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    steps: Vec<Step>,
+    #[serde(default)]
+    book: Option<ExpectedBook>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Step {
+    #[serde(flatten)]
+    action: Action,
+    /// Trades the action must produce, in order
+    #[serde(default)]
+    trades: Vec<ExpectedTrade>,
+    /// For cancels: whether the order was found
+    #[serde(default)]
+    accepted: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    Submit(SubmitAction),
+    Cancel(CancelAction),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubmitAction {
+    id: u64,
+    side: Side,
+    px: i64,
+    qty: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CancelAction {
+    id: u64,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectedTrade {
+    maker: u64,
+    taker: u64,
+    px: i64,
+    qty: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectedBook {
+    #[serde(default)]
+    bids: Vec<ExpectedLevel>,
+    #[serde(default)]
+    asks: Vec<ExpectedLevel>,
+}
+
+/// One price level, best first, orders in queue order.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectedLevel {
+    px: i64,
+    orders: Vec<ExpectedOrder>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExpectedOrder {
+    id: u64,
+    qty: i64,
+}
+
+fn load(path: &Path) -> Result<Scenario, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&text).map_err(|e| e.to_string()),
+        _ => serde_yaml::from_str(&text).map_err(|e| e.to_string()),
+    }
+}
+
+/// Live levels of one side in scenario shape, best first.
+fn levels(side: &PriceLevels, bids: bool) -> Vec<ExpectedLevel> {
+    let mut out: Vec<ExpectedLevel> = side
+        .get_price_levels()
+        .map(|(px, q)| ExpectedLevel {
+            px,
+            orders: q
+                .filter(|o| side.contains(o.id))
+                .map(|o| ExpectedOrder { id: o.id.0 as u64, qty: o.qty })
+                .collect(),
+        })
+        .filter(|level| !level.orders.is_empty())
+        .collect();
+    if bids {
+        out.reverse();
+    }
+    out
+}
+
+fn run(scenario: &Scenario) -> Result<(), String> {
+    let mut book = OrderBook::new();
+    let mut sides: HashMap<u64, Side> = HashMap::new();
+
+    for (i, step) in scenario.steps.iter().enumerate() {
+        let actual = match &step.action {
+            Action::Submit(s) => {
+                sides.insert(s.id, s.side);
+                book.submit_limit(Order {
+                    id: OrderId(s.id as u128),
+                    symbol: "TEST".into(),
+                    side: s.side,
+                    px_ticks: s.px,
+                    qty: s.qty,
+                    ts_ns: i as u128,
+                    tif: TimeInForce::Day,
+                    expires_ns: None,
+                    display_qty: None,
+                    min_qty: None,
+                    hidden: false,
+                    owner: ParticipantId::NONE,
+                }).unwrap().trades
+            }
+            Action::Cancel(c) => {
+                let found = match sides.get(&c.id) {
+                    Some(Side::Bid) => book.bids.cancel(OrderId(c.id as u128)),
+                    Some(Side::Ask) => book.asks.cancel(OrderId(c.id as u128)),
+                    None => false,
+                };
+                if let Some(expected) = step.accepted {
+                    if found != expected {
+                        return Err(format!("step {}: cancel {} returned {}, expected {}", i + 1, c.id, found, expected));
+                    }
+                }
+                Vec::new()
+            }
+        };
+
+        let actual: Vec<ExpectedTrade> = actual
+            .into_iter()
+            .map(|t| ExpectedTrade {
+                maker: t.maker.0 as u64,
+                taker: t.taker.0 as u64,
+                px: t.px_ticks,
+                qty: t.qty,
+            })
+            .collect();
+        if actual != step.trades {
+            return Err(format!(
+                "step {} ({:?}):\n    expected trades {:?}\n    actual trades   {:?}",
+                i + 1,
+                step.action,
+                step.trades,
+                actual
+            ));
+        }
+    }
+
+    if let Some(expected) = &scenario.book {
+        let bids = levels(&book.bids, true);
+        let asks = levels(&book.asks, false);
+        if bids != expected.bids || asks != expected.asks {
+            return Err(format!(
+                "final book:\n    expected bids {:?}\n    actual bids   {:?}\n    expected asks {:?}\n    actual asks   {:?}",
+                expected.bids, bids, expected.asks, asks
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn scenario_files() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+    let mut files: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("reading {}: {}", dir.display(), e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|p| matches!(p.extension().and_then(|e| e.to_str()), Some("yaml" | "yml" | "json")))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn scenarios_conform() {
+    let files = scenario_files();
+    assert!(!files.is_empty(), "no scenario files found");
+
+    let mut failures = Vec::new();
+    for path in &files {
+        let name = path.file_name().unwrap().to_string_lossy();
+        match load(path) {
+            Ok(scenario) => {
+                if let Err(e) = run(&scenario) {
+                    let about = scenario.description.as_deref().unwrap_or("");
+                    failures.push(format!("{} ({}) {}\n  {}", name, scenario.name, about, e));
+                }
+            }
+            Err(e) => failures.push(format!("{}: parse error: {}", name, e)),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} scenarios failed:\n\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n\n")
+    );
+}
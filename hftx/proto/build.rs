@@ -0,0 +1,11 @@
+//! Generates the prost types for proto/hftx/v1/*.proto.
+
+fn main() {
+    println!("cargo:rerun-if-changed=proto");
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc for this platform");
+    let mut config = prost_build::Config::new();
+    config.protoc_executable(protoc);
+    config
+        .compile_protos(&["proto/hftx/v1/events.proto"], &["proto"])
+        .expect("compiling proto/hftx/v1/events.proto");
+}
@@ -0,0 +1,424 @@
+//! Protobuf schema for engine events and market data.
+//!
+//! `proto/hftx/v1/events.proto` is the cross-language contract for Kafka
+//! topics and gRPC services: `Order`, `Trade`, `BookEvent`, the
+//! `SequencedEvent` envelope the exchange publishes to NATS, and the market
+//! data messages (`Bbo`, `DepthDiff`, and a `MarketData` envelope). The
+//! types in [`v1`] are generated from it by prost at build time; this file
+//! adds conversions to and from the engine's own structs. Market data
+//! converts from the `orderbook::sbe` messages, so both feeds carry the
+//! same fields.
+//!
+//! Native to proto is infallible. Proto to native fails on what proto3
+//! can't rule out: an absent id or order, `SIDE_UNSPECIFIED`,
+//! `PEG_KIND_UNSPECIFIED`, a time in force this build doesn't know, or an
+//! empty `oneof`.
+//! Order ids are 128-bit and travel as two `fixed64` words. Timestamps are
+//! `uint64` nanoseconds.
+
+use orderbook::codec::BookEvent;
+use orderbook::sbe::{BboMessage, DepthDiffMessage, DepthLevel, MarketData};
+use orderbook::pegs::PegKind;
+use orderbook::{Order, OrderId, OrderKind, ParticipantId, Side, TimeInForce, Trade};
+use std::fmt;
+
+pub use prost::Message;
+
+/// Generated from `proto/hftx/v1/events.proto`.
+pub mod v1 {
+    include!(concat!(env!("OUT_DIR"), "/hftx.v1.rs"));
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProtoError {
+    /// A required message field was absent
+    MissingField(&'static str),
+    /// `SIDE_UNSPECIFIED` or a value this build doesn't know
+    InvalidSide(i32),
+    /// A time in force value this build doesn't know
+    InvalidTimeInForce(i32),
+    /// `PEG_KIND_UNSPECIFIED` or a value this build doesn't know
+    InvalidPegKind(i32),
+    /// A `oneof` with no member set
+    EmptyOneof(&'static str),
+}
+
+impl fmt::Display for ProtoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtoError::MissingField(field) => write!(f, "missing field `{}`", field),
+            ProtoError::InvalidSide(side) => write!(f, "invalid side {}", side),
+            ProtoError::InvalidTimeInForce(tif) => write!(f, "invalid time in force {}", tif),
+            ProtoError::InvalidPegKind(kind) => write!(f, "invalid peg kind {}", kind),
+            ProtoError::EmptyOneof(name) => write!(f, "no `{}` set", name),
+        }
+    }
+}
+
+impl std::error::Error for ProtoError {}
+
+impl From<OrderId> for v1::OrderId {
+    fn from(id: OrderId) -> Self {
+        Self { lo: id.0 as u64, hi: (id.0 >> 64) as u64 }
+    }
+}
+
+impl From<v1::OrderId> for OrderId {
+    fn from(id: v1::OrderId) -> Self {
+        OrderId((id.hi as u128) << 64 | id.lo as u128)
+    }
+}
+
+fn order_id(id: Option<v1::OrderId>, field: &'static str) -> Result<OrderId, ProtoError> {
+    id.map(OrderId::from).ok_or(ProtoError::MissingField(field))
+}
+
+impl From<Side> for v1::Side {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Bid => v1::Side::Bid,
+            Side::Ask => v1::Side::Ask,
+        }
+    }
+}
+
+/// The native side of a proto enum field.
+fn side(value: i32) -> Result<Side, ProtoError> {
+    match v1::Side::try_from(value) {
+        Ok(v1::Side::Bid) => Ok(Side::Bid),
+        Ok(v1::Side::Ask) => Ok(Side::Ask),
+        _ => Err(ProtoError::InvalidSide(value)),
+    }
+}
+
+impl From<TimeInForce> for v1::TimeInForce {
+    fn from(tif: TimeInForce) -> Self {
+        match tif {
+            TimeInForce::Day => v1::TimeInForce::Day,
+            TimeInForce::IOC => v1::TimeInForce::Ioc,
+            TimeInForce::FOK => v1::TimeInForce::Fok,
+        }
+    }
+}
+
+/// The native time in force of a proto enum field.
+fn tif(value: i32) -> Result<TimeInForce, ProtoError> {
+    match v1::TimeInForce::try_from(value) {
+        Ok(v1::TimeInForce::Day) => Ok(TimeInForce::Day),
+        Ok(v1::TimeInForce::Ioc) => Ok(TimeInForce::IOC),
+        Ok(v1::TimeInForce::Fok) => Ok(TimeInForce::FOK),
+        Err(_) => Err(ProtoError::InvalidTimeInForce(value)),
+    }
+}
+
+impl From<PegKind> for v1::PegKind {
+    fn from(kind: PegKind) -> Self {
+        match kind {
+            PegKind::Primary => v1::PegKind::Primary,
+            PegKind::Midpoint => v1::PegKind::Midpoint,
+        }
+    }
+}
+
+/// The native peg kind of a proto enum field.
+fn peg_kind(value: i32) -> Result<PegKind, ProtoError> {
+    match v1::PegKind::try_from(value) {
+        Ok(v1::PegKind::Primary) => Ok(PegKind::Primary),
+        Ok(v1::PegKind::Midpoint) => Ok(PegKind::Midpoint),
+        _ => Err(ProtoError::InvalidPegKind(value)),
+    }
+}
+
+impl From<&Order> for v1::Order {
+    fn from(order: &Order) -> Self {
+        Self {
+            id: Some(order.id.into()),
+            symbol: order.symbol.to_string(),
+            side: v1::Side::from(order.side).into(),
+            px_ticks: order.px_ticks,
+            qty: order.qty,
+            ts_ns: order.ts_ns as u64,
+            tif: v1::TimeInForce::from(order.tif).into(),
+            expires_ns: order.expires_ns.map(|ns| ns as u64),
+            display_qty: order.display_qty,
+            min_qty: order.min_qty,
+            hidden: order.hidden,
+            owner: order.owner.0,
+        }
+    }
+}
+
+impl TryFrom<v1::Order> for Order {
+    type Error = ProtoError;
+
+    fn try_from(order: v1::Order) -> Result<Self, ProtoError> {
+        Ok(Order {
+            id: order_id(order.id, "id")?,
+            symbol: order.symbol.into(),
+            side: side(order.side)?,
+            px_ticks: order.px_ticks,
+            qty: order.qty,
+            ts_ns: order.ts_ns as u128,
+            tif: tif(order.tif)?,
+            expires_ns: order.expires_ns.map(u128::from),
+            display_qty: order.display_qty,
+            min_qty: order.min_qty,
+            hidden: order.hidden,
+            owner: ParticipantId(order.owner),
+        })
+    }
+}
+
+/// The native order of a required message field.
+fn order(order: Option<v1::Order>, field: &'static str) -> Result<Order, ProtoError> {
+    order.ok_or(ProtoError::MissingField(field))?.try_into()
+}
+
+impl From<&Trade> for v1::Trade {
+    fn from(trade: &Trade) -> Self {
+        Self {
+            maker: Some(trade.maker.into()),
+            taker: Some(trade.taker.into()),
+            symbol: trade.symbol.to_string(),
+            px_ticks: trade.px_ticks,
+            qty: trade.qty,
+            ts_ns: trade.ts_ns as u64,
+            seq: trade.seq,
+        }
+    }
+}
+
+impl TryFrom<v1::Trade> for Trade {
+    type Error = ProtoError;
+
+    fn try_from(trade: v1::Trade) -> Result<Self, ProtoError> {
+        Ok(Trade {
+            maker: order_id(trade.maker, "maker")?,
+            taker: order_id(trade.taker, "taker")?,
+            symbol: trade.symbol.into(),
+            px_ticks: trade.px_ticks,
+            qty: trade.qty,
+            ts_ns: trade.ts_ns as u128,
+            seq: trade.seq,
+        })
+    }
+}
+
+impl From<&BookEvent> for v1::BookEvent {
+    fn from(event: &BookEvent) -> Self {
+        let event = match event {
+            BookEvent::Submit(order) => v1::book_event::Event::Submit(order.into()),
+            BookEvent::Cancel { id, side } => {
+                v1::book_event::Event::Cancel(v1::Cancel { id: Some((*id).into()), side: v1::Side::from(*side).into() })
+            }
+            BookEvent::Modify { id, side, px_ticks, qty, ts_ns } => v1::book_event::Event::Modify(v1::Modify {
+                id: Some((*id).into()),
+                side: v1::Side::from(*side).into(),
+                px_ticks: *px_ticks,
+                qty: *qty,
+                ts_ns: *ts_ns as u64,
+            }),
+            BookEvent::Expire { now_ns } => v1::book_event::Event::Expire(v1::Expire { now_ns: *now_ns as u64 }),
+            BookEvent::Market(order) => v1::book_event::Event::Market(order.into()),
+            BookEvent::Stop { order, stop_px, kind } => v1::book_event::Event::Stop(v1::Stop {
+                order: Some(order.into()),
+                stop_px: *stop_px,
+                limit: *kind == OrderKind::Limit,
+            }),
+            BookEvent::CancelStop { id } => v1::book_event::Event::CancelStop(v1::CancelStop { id: Some((*id).into()) }),
+            BookEvent::Peg { order, kind } => {
+                v1::book_event::Event::Peg(v1::Peg { order: Some(order.into()), kind: v1::PegKind::from(*kind).into() })
+            }
+            BookEvent::Quote { owner, bid, ask } => v1::book_event::Event::Quote(v1::Quote {
+                owner: owner.clone(),
+                bid: Some(bid.into()),
+                ask: Some(ask.into()),
+            }),
+            BookEvent::Reprice { ts_ns } => v1::book_event::Event::Reprice(v1::Reprice { ts_ns: *ts_ns as u64 }),
+        };
+        Self { event: Some(event) }
+    }
+}
+
+impl TryFrom<v1::BookEvent> for BookEvent {
+    type Error = ProtoError;
+
+    fn try_from(event: v1::BookEvent) -> Result<Self, ProtoError> {
+        match event.event.ok_or(ProtoError::EmptyOneof("event"))? {
+            v1::book_event::Event::Submit(order) => Ok(BookEvent::Submit(order.try_into()?)),
+            v1::book_event::Event::Cancel(cancel) => {
+                Ok(BookEvent::Cancel { id: order_id(cancel.id, "id")?, side: side(cancel.side)? })
+            }
+            v1::book_event::Event::Modify(modify) => Ok(BookEvent::Modify {
+                id: order_id(modify.id, "id")?,
+                side: side(modify.side)?,
+                px_ticks: modify.px_ticks,
+                qty: modify.qty,
+                ts_ns: modify.ts_ns as u128,
+            }),
+            v1::book_event::Event::Expire(expire) => Ok(BookEvent::Expire { now_ns: expire.now_ns as u128 }),
+            v1::book_event::Event::Market(order) => Ok(BookEvent::Market(order.try_into()?)),
+            v1::book_event::Event::Stop(stop) => Ok(BookEvent::Stop {
+                order: order(stop.order, "order")?,
+                stop_px: stop.stop_px,
+                kind: if stop.limit { OrderKind::Limit } else { OrderKind::Market },
+            }),
+            v1::book_event::Event::CancelStop(cancel) => Ok(BookEvent::CancelStop { id: order_id(cancel.id, "id")? }),
+            v1::book_event::Event::Peg(peg) => Ok(BookEvent::Peg { order: order(peg.order, "order")?, kind: peg_kind(peg.kind)? }),
+            v1::book_event::Event::Quote(quote) => Ok(BookEvent::Quote {
+                owner: quote.owner,
+                bid: order(quote.bid, "bid")?,
+                ask: order(quote.ask, "ask")?,
+            }),
+            v1::book_event::Event::Reprice(reprice) => Ok(BookEvent::Reprice { ts_ns: reprice.ts_ns as u128 }),
+        }
+    }
+}
+
+impl From<&BboMessage> for v1::Bbo {
+    fn from(bbo: &BboMessage) -> Self {
+        Self {
+            ts_ns: bbo.ts_ns,
+            symbol: bbo.symbol.clone(),
+            bid_px: bbo.bid_px,
+            bid_qty: bbo.bid_qty,
+            ask_px: bbo.ask_px,
+            ask_qty: bbo.ask_qty,
+        }
+    }
+}
+
+impl From<v1::Bbo> for BboMessage {
+    fn from(bbo: v1::Bbo) -> Self {
+        Self {
+            ts_ns: bbo.ts_ns,
+            symbol: bbo.symbol,
+            bid_px: bbo.bid_px,
+            bid_qty: bbo.bid_qty,
+            ask_px: bbo.ask_px,
+            ask_qty: bbo.ask_qty,
+        }
+    }
+}
+
+impl From<&DepthDiffMessage> for v1::DepthDiff {
+    fn from(diff: &DepthDiffMessage) -> Self {
+        Self {
+            ts_ns: diff.ts_ns,
+            symbol: diff.symbol.clone(),
+            levels: diff
+                .levels
+                .iter()
+                .map(|l| v1::DepthLevel { side: v1::Side::from(l.side).into(), px_ticks: l.px_ticks, qty: l.qty })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<v1::DepthDiff> for DepthDiffMessage {
+    type Error = ProtoError;
+
+    fn try_from(diff: v1::DepthDiff) -> Result<Self, ProtoError> {
+        Ok(Self {
+            ts_ns: diff.ts_ns,
+            symbol: diff.symbol,
+            levels: diff
+                .levels
+                .into_iter()
+                .map(|l| Ok(DepthLevel { side: side(l.side)?, px_ticks: l.px_ticks, qty: l.qty }))
+                .collect::<Result<_, ProtoError>>()?,
+        })
+    }
+}
+
+impl From<&MarketData> for v1::MarketData {
+    fn from(message: &MarketData) -> Self {
+        use v1::market_data::Message;
+        let message = match message {
+            MarketData::Trade(trade) => Message::Trade(v1::Trade {
+                maker: Some(trade.maker_id.into()),
+                taker: Some(trade.taker_id.into()),
+                symbol: trade.symbol.clone(),
+                px_ticks: trade.px_ticks,
+                qty: trade.qty,
+                ts_ns: trade.ts_ns,
+                seq: 0,
+            }),
+            MarketData::Bbo(bbo) => Message::Bbo(bbo.into()),
+            MarketData::DepthDiff(diff) => Message::DepthDiff(diff.into()),
+        };
+        Self { message: Some(message) }
+    }
+}
+
+impl TryFrom<v1::MarketData> for MarketData {
+    type Error = ProtoError;
+
+    fn try_from(message: v1::MarketData) -> Result<Self, ProtoError> {
+        use v1::market_data::Message;
+        match message.message.ok_or(ProtoError::EmptyOneof("message"))? {
+            Message::Trade(trade) => Ok(MarketData::Trade((&Trade::try_from(trade)?).into())),
+            Message::Bbo(bbo) => Ok(MarketData::Bbo(bbo.into())),
+            Message::DepthDiff(diff) => Ok(MarketData::DepthDiff(diff.try_into()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use orderbook::sbe::TradeMessage;
+
+    fn order(id: u128) -> Order {
+        Order { id: OrderId(id), symbol: "AAPL".into(), side: Side::Ask, px_ticks: 15_000, qty: 100, ts_ns: 42, tif: TimeInForce::Day, expires_ns: None, display_qty: None, min_qty: None, hidden: false, owner: ParticipantId::NONE }
+    }
+
+    #[test]
+    fn events_round_trip_through_the_wire() {
+        let gtd = Order { tif: TimeInForce::IOC, expires_ns: Some(1_000), display_qty: Some(10), min_qty: Some(5), hidden: true, owner: ParticipantId(7), ..order(3) };
+        let modify = BookEvent::Modify { id: OrderId(9), side: Side::Ask, px_ticks: -3, qty: 4, ts_ns: 11 };
+        let events = [
+            BookEvent::Submit(order(u128::MAX - 7)),
+            BookEvent::Submit(gtd),
+            BookEvent::Cancel { id: OrderId(9), side: Side::Bid },
+            modify,
+            BookEvent::Expire { now_ns: 12 },
+            BookEvent::Market(Order { tif: TimeInForce::IOC, ..order(4) }),
+            BookEvent::Stop { order: order(5), stop_px: 14_990, kind: OrderKind::Limit },
+            BookEvent::Stop { order: order(6), stop_px: 14_990, kind: OrderKind::Market },
+            BookEvent::CancelStop { id: OrderId(5) },
+            BookEvent::Peg { order: order(7), kind: PegKind::Midpoint },
+            BookEvent::Quote { owner: "mm".into(), bid: Order { side: Side::Bid, ..order(8) }, ask: order(9) },
+            BookEvent::Reprice { ts_ns: 13 },
+        ];
+        for event in events {
+            let bytes = v1::BookEvent::from(&event).encode_to_vec();
+            let decoded = v1::BookEvent::decode(bytes.as_slice()).unwrap();
+            assert_eq!(BookEvent::try_from(decoded).unwrap(), event);
+        }
+
+        let trade = Trade { maker: OrderId(1), taker: OrderId(1 << 100), symbol: "AAPL".into(), px_ticks: 1, qty: 2, ts_ns: 3, seq: 4 };
+        let bytes = v1::Trade::from(&trade).encode_to_vec();
+        assert_eq!(Trade::try_from(v1::Trade::decode(bytes.as_slice()).unwrap()).unwrap(), trade);
+        let market = MarketData::Trade(TradeMessage::from(&trade));
+        let bytes = v1::MarketData::from(&market).encode_to_vec();
+        assert_eq!(MarketData::try_from(v1::MarketData::decode(bytes.as_slice()).unwrap()).unwrap(), market);
+    }
+
+    #[test]
+    fn rejects_what_proto3_cannot() {
+        let mut order = v1::Order::from(&order(1));
+        order.side = v1::Side::Unspecified.into();
+        assert_eq!(Order::try_from(order.clone()), Err(ProtoError::InvalidSide(0)));
+        order.side = v1::Side::Bid.into();
+        order.tif = 9;
+        assert_eq!(Order::try_from(order.clone()), Err(ProtoError::InvalidTimeInForce(9)));
+        order.id = None;
+        assert_eq!(Order::try_from(order), Err(ProtoError::MissingField("id")));
+        assert_eq!(BookEvent::try_from(v1::BookEvent::default()), Err(ProtoError::EmptyOneof("event")));
+        let peg = v1::book_event::Event::Peg(v1::Peg { order: None, kind: v1::PegKind::Primary.into() });
+        assert_eq!(BookEvent::try_from(v1::BookEvent { event: Some(peg) }), Err(ProtoError::MissingField("order")));
+        let peg = v1::book_event::Event::Peg(v1::Peg { order: Some(v1::Order::from(&self::order(1))), kind: 0 });
+        assert_eq!(BookEvent::try_from(v1::BookEvent { event: Some(peg) }), Err(ProtoError::InvalidPegKind(0)));
+    }
+}
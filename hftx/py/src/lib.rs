@@ -0,0 +1,223 @@
+//! Python bindings for the matching engine.
+//!
+//! Exposes one in-process `OrderBook` per Python object, so research code can
+//! drive the real matcher from pandas/numpy without the HTTP service:
+//!
+//! ```python
+//! import pandas as pd
+//! from hftx_py import OrderBook
+//!
+//! book = OrderBook("AAPL")
+//! book.submit(1, "ask", 15000, 100)
+//! trades = book.submit(2, "bid", 15000, 40)   # [{"maker": 1, "taker": 2, ...}]
+//! fills = pd.DataFrame(book.submit_many(ids, sides, prices, qtys))
+//! ```
+//!
+//! Prices are integer ticks and sizes integer quantities, as in the engine.
+//! Trades and snapshots come back as plain dicts and lists (or columns of
+//! lists from `submit_many`), which `pandas.DataFrame` takes directly.
+
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, PriceLevels, Side, SymbolId, TimeInForce, Trade};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+/// Accepts "bid"/"buy" and "ask"/"sell", any case.
+fn parse_side(side: &str) -> PyResult<Side> {
+    match side.to_ascii_lowercase().as_str() {
+        "bid" | "buy" | "b" => Ok(Side::Bid),
+        "ask" | "sell" | "s" => Ok(Side::Ask),
+        _ => Err(PyValueError::new_err(format!("side must be 'bid' or 'ask', got {:?}", side))),
+    }
+}
+
+fn side_name(side: Side) -> &'static str {
+    match side {
+        Side::Bid => "bid",
+        Side::Ask => "ask",
+    }
+}
+
+fn trade_dict<'py>(py: Python<'py>, trade: &Trade) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("maker", trade.maker.0)?;
+    dict.set_item("taker", trade.taker.0)?;
+    dict.set_item("px", trade.px_ticks)?;
+    dict.set_item("qty", trade.qty)?;
+    dict.set_item("ts_ns", trade.ts_ns)?;
+    Ok(dict)
+}
+
+/// A single-symbol limit order book with price-time priority.
+#[pyclass(name = "OrderBook")]
+struct PyOrderBook {
+    book: OrderBook,
+    symbol: SymbolId,
+}
+
+impl PyOrderBook {
+    fn side(&self, side: Side) -> &PriceLevels {
+        self.book.levels(side)
+    }
+
+    fn submit_order(&mut self, id: u128, side: Side, px: i64, qty: i64, ts_ns: u128) -> PyResult<Vec<Trade>> {
+        let report = self.book.submit_limit(Order {
+            id: OrderId(id),
+            symbol: self.symbol,
+            side,
+            px_ticks: px,
+            qty,
+            ts_ns,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        });
+        report.map(|report| report.trades).map_err(|e| PyValueError::new_err(format!("order {}: {}", id, e)))
+    }
+}
+
+#[pymethods]
+impl PyOrderBook {
+    #[new]
+    #[pyo3(signature = (symbol = "BOOK".to_string()))]
+    fn new(symbol: String) -> Self {
+        Self { book: OrderBook::new(), symbol: symbol.into() }
+    }
+
+    #[getter]
+    fn symbol(&self) -> &str {
+        self.symbol.as_str()
+    }
+
+    /// Submits a limit order; returns the trades it printed as dicts.
+    #[pyo3(signature = (id, side, px, qty, ts_ns = 0))]
+    fn submit<'py>(
+        &mut self,
+        py: Python<'py>,
+        id: u128,
+        side: &str,
+        px: i64,
+        qty: i64,
+        ts_ns: u128,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let trades = self.submit_order(id, parse_side(side)?, px, qty, ts_ns)?;
+        let dicts = trades.iter().map(|t| trade_dict(py, t)).collect::<PyResult<Vec<_>>>()?;
+        PyList::new(py, dicts)
+    }
+
+    /// Submits orders from equal-length sequences (lists, numpy arrays,
+    /// DataFrame columns) in order. Returns every trade as a dict of columns.
+    #[pyo3(signature = (ids, sides, pxs, qtys, ts_ns = None))]
+    fn submit_many<'py>(
+        &mut self,
+        py: Python<'py>,
+        ids: Vec<u128>,
+        sides: Vec<String>,
+        pxs: Vec<i64>,
+        qtys: Vec<i64>,
+        ts_ns: Option<Vec<u128>>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let n = ids.len();
+        if sides.len() != n || pxs.len() != n || qtys.len() != n || ts_ns.as_ref().is_some_and(|t| t.len() != n) {
+            return Err(PyValueError::new_err("submit_many: all columns must have the same length"));
+        }
+
+        let (mut maker, mut taker, mut px, mut qty, mut ts) = (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for i in 0..n {
+            let at = ts_ns.as_ref().map_or(0, |t| t[i]);
+            for trade in self.submit_order(ids[i], parse_side(&sides[i])?, pxs[i], qtys[i], at)? {
+                maker.push(trade.maker.0);
+                taker.push(trade.taker.0);
+                px.push(trade.px_ticks);
+                qty.push(trade.qty);
+                ts.push(trade.ts_ns);
+            }
+        }
+
+        let columns = PyDict::new(py);
+        columns.set_item("maker", maker)?;
+        columns.set_item("taker", taker)?;
+        columns.set_item("px", px)?;
+        columns.set_item("qty", qty)?;
+        columns.set_item("ts_ns", ts)?;
+        Ok(columns)
+    }
+
+    /// Cancels a resting order; True if it was live. Given a `side`, only
+    /// an order resting on that side is cancelled.
+    #[pyo3(signature = (id, side = None))]
+    fn cancel(&mut self, id: u128, side: Option<&str>) -> PyResult<bool> {
+        let id = OrderId(id);
+        if let Some(side) = side.map(parse_side).transpose()? {
+            if self.book.side_of(id) != Some(side) {
+                return Ok(false);
+            }
+        }
+        Ok(self.book.cancel(id))
+    }
+
+    /// Top `levels` price levels per side, best first, as
+    /// `{"bids": [(px, qty, orders), ...], "asks": [...]}`.
+    #[pyo3(signature = (levels = 10))]
+    fn depth<'py>(&self, py: Python<'py>, levels: usize) -> PyResult<Bound<'py, PyDict>> {
+        let depth = PyDict::new(py);
+        for side in [Side::Bid, Side::Ask] {
+            let rows: Vec<(i64, i64, usize)> =
+                self.side(side).depth(levels).into_iter().map(|level| (level.px_ticks, level.qty, level.orders)).collect();
+            depth.set_item(if side == Side::Bid { "bids" } else { "asks" }, rows)?;
+        }
+        Ok(depth)
+    }
+
+    /// Every live resting order, bids then asks, each in priority order.
+    fn snapshot<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let list = PyList::empty(py);
+        for side in [Side::Bid, Side::Ask] {
+            for order in self.side(side).live_orders() {
+                let dict = PyDict::new(py);
+                dict.set_item("id", order.id.0)?;
+                dict.set_item("side", side_name(side))?;
+                dict.set_item("px", order.px_ticks)?;
+                dict.set_item("qty", order.qty)?;
+                dict.set_item("ts_ns", order.ts_ns)?;
+                list.append(dict)?;
+            }
+        }
+        Ok(list)
+    }
+
+    #[getter]
+    fn best_bid(&self) -> Option<i64> {
+        self.book.best_bid()
+    }
+
+    #[getter]
+    fn best_ask(&self) -> Option<i64> {
+        self.book.best_ask()
+    }
+
+    /// Number of live resting orders on both sides.
+    fn __len__(&self) -> usize {
+        self.book.bids.total_len() + self.book.asks.total_len()
+    }
+
+    fn __repr__(&self) -> String {
+        let px = |p: Option<i64>| p.map_or("None".to_string(), |p| p.to_string());
+        format!(
+            "OrderBook(symbol={:?}, best_bid={}, best_ask={}, resting={})",
+            self.symbol.as_str(),
+            px(self.book.best_bid()),
+            px(self.book.best_ask()),
+            self.__len__()
+        )
+    }
+}
+
+#[pymodule]
+fn hftx_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOrderBook>()?;
+    Ok(())
+}
@@ -0,0 +1,34 @@
+#!/usr/bin/env bash
+# Run the same seeded Poisson order-flow workload against each global
+# allocator (system, jemalloc, mimalloc) and print the throughput-suite
+# percentiles side by side. Extra harness flags pass through, e.g.
+#   scripts/bench_allocators.sh --symbols 4 --book-depth 500
+
+set -euo pipefail
+
+ROOT="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+OUT="$ROOT/results/allocators"
+SEED="${SEED:-42}"
+MAX_OPS="${MAX_OPS:-2000000}"
+
+mkdir -p "$OUT"
+cd "$ROOT"
+
+for alloc in system jemalloc mimalloc; do
+  features=()
+  [[ "$alloc" != system ]] && features=(--features "$alloc")
+  echo "=== $alloc ==="
+  cargo run --release --quiet "${features[@]}" -- \
+    --skip-latency --skip-demo --flow poisson --cancel-pct 35 --crossing-pct 15 \
+    --seed "$SEED" --max-ops "$MAX_OPS" --duration 600 \
+    --output "$OUT/$alloc.csv" "$@" >/dev/null
+done
+
+echo
+printf '%-10s %-22s %10s %10s %10s %12s %14s\n' allocator test p50_ns p99_ns p999_ns max_ns ops_per_sec
+for alloc in system jemalloc mimalloc; do
+  # Columns: see RunResults::to_csv
+  awk -F, -v a="$alloc" 'NR > 1 && $7 == "throughput" {
+    printf "%-10s %-22s %10s %10s %10s %12s %14s\n", a, $8, $11, $13, $14, $15, $16
+  }' "$OUT/$alloc.csv"
+done
@@ -0,0 +1,107 @@
+//! Global allocator selection and the counting allocator for the perf harness.
+//!
+//! The backing allocator is the system allocator, or jemalloc / mimalloc with
+//! the `jemalloc` / `mimalloc` features. With `alloc-tracking` the backing
+//! allocator is wrapped to tally allocation calls and requested bytes in
+//! relaxed atomics. The harness snapshots the counters around each timed
+//! operation to report allocations per submitted order and per match.
+//! Without that feature `snapshot()` returns `None`.
+
+#[cfg(all(feature = "jemalloc", feature = "mimalloc"))]
+compile_error!("features `jemalloc` and `mimalloc` are mutually exclusive");
+
+#[cfg(feature = "alloc-tracking")]
+use std::alloc::{GlobalAlloc, Layout};
+#[cfg(feature = "alloc-tracking")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "jemalloc")]
+use tikv_jemallocator::Jemalloc as Backing;
+#[cfg(feature = "mimalloc")]
+use mimalloc::MiMalloc as Backing;
+#[cfg(all(feature = "alloc-tracking", not(any(feature = "jemalloc", feature = "mimalloc"))))]
+use std::alloc::System as Backing;
+
+/// Name of the allocator serving this process's heap.
+pub const ALLOCATOR: &str = if cfg!(feature = "jemalloc") {
+    "jemalloc"
+} else if cfg!(feature = "mimalloc") {
+    "mimalloc"
+} else {
+    "system"
+};
+
+#[cfg(all(not(feature = "alloc-tracking"), any(feature = "jemalloc", feature = "mimalloc")))]
+#[global_allocator]
+static GLOBAL: Backing = Backing;
+
+/// Cumulative allocator activity since process start.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AllocStats {
+    /// Calls to `alloc` / `alloc_zeroed` / `realloc`
+    pub allocs: u64,
+    /// Bytes requested across those calls
+    pub bytes: u64,
+}
+
+impl AllocStats {
+    /// Activity between an earlier snapshot and this one.
+    pub fn since(self, earlier: AllocStats) -> AllocStats {
+        AllocStats {
+            allocs: self.allocs - earlier.allocs,
+            bytes: self.bytes - earlier.bytes,
+        }
+    }
+}
+
+#[cfg(feature = "alloc-tracking")]
+struct CountingAllocator;
+
+#[cfg(feature = "alloc-tracking")]
+static ALLOCS: AtomicU64 = AtomicU64::new(0);
+#[cfg(feature = "alloc-tracking")]
+static BYTES: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(feature = "alloc-tracking")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        Backing.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        Backing.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        Backing.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Backing.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(feature = "alloc-tracking")]
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Current counters, or `None` when built without `alloc-tracking`.
+#[cfg(feature = "alloc-tracking")]
+pub fn snapshot() -> Option<AllocStats> {
+    Some(AllocStats {
+        allocs: ALLOCS.load(Ordering::Relaxed),
+        bytes: BYTES.load(Ordering::Relaxed),
+    })
+}
+
+/// Current counters, or `None` when built without `alloc-tracking`.
+#[cfg(not(feature = "alloc-tracking"))]
+pub fn snapshot() -> Option<AllocStats> {
+    None
+}
@@ -0,0 +1,215 @@
+//! WebSocket fan-out scalability against a real exchange-service process.
+//!
+//! Opens many trade-stream subscribers spread across symbols while the
+//! server-side bot driver trades every symbol, then reports:
+//! - broadcast lag: subscriber receive time minus the trade's server timestamp
+//! - dropped receivers: connections the server closed mid-run (lagged out)
+//! - gap notices: the server's own count of messages it dropped for slow
+//!   subscribers (under the `gap` and `conflate` slow-consumer policies)
+//! - missed trades: frames a subscriber saw fewer of than its best peer
+//! - service CPU per connection, when the harness spawned the service
+
+use crate::latency_test::new_histogram;
+use crate::results::RunResults;
+use crate::service::{parse_gap_frame, parse_trade_frame, ServiceProcess};
+use futures::StreamExt;
+use hdrhistogram::Histogram;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Subscribers opened concurrently while connecting.
+const CONNECT_CHUNK: usize = 64;
+
+/// Settings for the fan-out scenario.
+pub struct FanoutConfig {
+    /// Use an already running service instead of spawning one
+    pub url: Option<String>,
+    /// exchange-service binary to spawn; defaults to the one next to this executable
+    pub exchange_bin: Option<PathBuf>,
+    /// WebSocket subscribers, assigned round-robin across symbols
+    pub subscribers: usize,
+    /// Symbols traded and subscribed to (capped at what the service lists)
+    pub symbols: usize,
+    /// How long order flow runs with all subscribers connected
+    pub duration: Duration,
+    /// Bot driver tick per symbol, in milliseconds
+    pub tick_ms: u64,
+}
+
+#[derive(Deserialize)]
+struct SymbolsResponse {
+    symbols: Vec<String>,
+}
+
+/// What one subscriber observed.
+struct SubscriberStats {
+    symbol: usize,
+    received: u64,
+    lag: Histogram<u64>,
+    /// Stream ended before the run stopped
+    dropped: bool,
+    /// Gap notices received, and the messages they say were missed
+    gaps: u64,
+    gap_missed: u64,
+}
+
+/// Runs the fan-out scenario and records broadcast lag under the "fanout" suite.
+pub fn run_fanout_test(config: &FanoutConfig, results: &mut RunResults) {
+    println!("\n WebSocket Fan-out Test");
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    if let Err(e) = runtime.block_on(run(config, results)) {
+        eprintln!("  fan-out test failed: {}", e);
+    }
+}
+
+async fn run(config: &FanoutConfig, results: &mut RunResults) -> Result<(), String> {
+    let service = ServiceProcess::connect_or_spawn(config.url.as_deref(), config.exchange_bin.as_ref(), &[]).await?;
+    let http = reqwest::Client::new();
+
+    let mut symbols = http
+        .get(format!("{}/symbols", service.url))
+        .send()
+        .await
+        .map_err(|e| format!("listing symbols: {}", e))?
+        .json::<SymbolsResponse>()
+        .await
+        .map_err(|e| format!("listing symbols: {}", e))?
+        .symbols;
+    symbols.sort();
+    symbols.truncate(config.symbols.max(1));
+    println!(
+        "  service: {} ({} subscribers over {} symbols, {}s)",
+        service.url,
+        config.subscribers,
+        symbols.len(),
+        config.duration.as_secs()
+    );
+
+    // Connect everyone before any flow starts so all subscribers see the same trades
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let mut tasks = Vec::with_capacity(config.subscribers);
+    for chunk_start in (0..config.subscribers).step_by(CONNECT_CHUNK) {
+        let chunk_end = (chunk_start + CONNECT_CHUNK).min(config.subscribers);
+        let connects = (chunk_start..chunk_end).map(|i| {
+            let symbol = i % symbols.len();
+            let url = format!("{}/symbols/{}/trades/stream", service.ws_url(), symbols[symbol]);
+            async move { (symbol, tokio_tungstenite::connect_async(url.as_str()).await) }
+        });
+        for (symbol, connected) in futures::future::join_all(connects).await {
+            let (ws, _) = connected.map_err(|e| format!("subscriber {} failed to connect: {}", tasks.len(), e))?;
+            tasks.push(tokio::spawn(subscribe(ws, symbol, stop_rx.clone())));
+        }
+    }
+
+    for symbol in &symbols {
+        http.post(format!("{}/sim/start", service.url))
+            .json(&serde_json::json!({
+                "symbol": symbol,
+                "makers": 4,
+                "takers": 4,
+                "aggression": 50,
+                "tick_ms": config.tick_ms,
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("starting sim on {}: {}", symbol, e))?;
+    }
+
+    let cpu_before = service.cpu_time();
+    tokio::time::sleep(config.duration).await;
+    let cpu_after = service.cpu_time();
+
+    for symbol in &symbols {
+        let _ = http.post(format!("{}/sim/stop", service.url))
+            .json(&serde_json::json!({ "symbol": symbol }))
+            .send()
+            .await;
+    }
+    // Let in-flight frames land before tallying
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let _ = stop_tx.send(true);
+
+    let mut stats = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        stats.push(task.await.map_err(|e| e.to_string())?);
+    }
+
+    let mut lag = new_histogram();
+    let mut best_per_symbol = vec![0u64; symbols.len()];
+    for s in &stats {
+        lag.add(&s.lag).unwrap();
+        best_per_symbol[s.symbol] = best_per_symbol[s.symbol].max(s.received);
+    }
+    let delivered: u64 = stats.iter().map(|s| s.received).sum();
+    let expected: u64 = stats.iter().map(|s| best_per_symbol[s.symbol]).sum();
+    let dropped = stats.iter().filter(|s| s.dropped).count();
+    let gaps: u64 = stats.iter().map(|s| s.gaps).sum();
+    let gap_missed: u64 = stats.iter().map(|s| s.gap_missed).sum();
+
+    let label = format!("Broadcast lag ({} subscribers)", stats.len());
+    results.record("fanout", &label, &lag).throughput_per_sec =
+        Some(delivered as f64 / config.duration.as_secs_f64());
+    println!(
+        "  frames delivered: {}, missed vs best peer: {:.3}%, dropped receivers: {} ({:.2}%)",
+        delivered,
+        100.0 * expected.saturating_sub(delivered) as f64 / expected.max(1) as f64,
+        dropped,
+        100.0 * dropped as f64 / stats.len().max(1) as f64
+    );
+    println!("  gap notices: {} ({} messages missed by the server's count)", gaps, gap_missed);
+    if let (Some(before), Some(after)) = (cpu_before, cpu_after) {
+        let cores = (after - before).as_secs_f64() / config.duration.as_secs_f64();
+        println!(
+            "  service CPU: {:.2} cores total, {:.4}% of a core per connection",
+            cores,
+            100.0 * cores / stats.len().max(1) as f64
+        );
+    }
+    Ok(())
+}
+
+/// Reads trade frames until told to stop or the server closes the stream.
+async fn subscribe(
+    ws: tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
+    symbol: usize,
+    mut stop: watch::Receiver<bool>,
+) -> SubscriberStats {
+    let (_, mut stream) = ws.split();
+    let mut stats = SubscriberStats {
+        symbol,
+        received: 0,
+        lag: new_histogram(),
+        dropped: false,
+        gaps: 0,
+        gap_missed: 0,
+    };
+
+    loop {
+        tokio::select! {
+            _ = stop.changed() => break,
+            msg = stream.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    if let Some(trade) = parse_trade_frame(&text) {
+                        let now_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+                        let lag_ns = now_ns.saturating_sub(trade.ts_ns) as u64;
+                        stats.lag.record(lag_ns.clamp(1, stats.lag.high())).unwrap();
+                        stats.received += 1;
+                    } else if let Some(missed) = parse_gap_frame(&text) {
+                        stats.gaps += 1;
+                        stats.gap_missed += missed;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    stats.dropped = true;
+                    break;
+                }
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    stats
+}
@@ -2,27 +2,38 @@
 //!
 //! Measures latency and throughput under various scenarios:
 //! - Market data access, order submission, matching, cancellation
-//! - Sustained throughput testing with mixed workloads
-//! - Statistical analysis with multiple iterations
+//! - Sustained throughput testing with mixed workloads, either flat out or at
+//!   a constant arrival rate with coordinated-omission-corrected latencies
+//! - Tail latency via HDR histograms (p50/p90/p99/p99.9/max) rather than means
 
-use orderbook::{OrderBook, Order, OrderId, Side};
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use hdrhistogram::Histogram;
+use orderbook::flow::{FlowConfig, FlowEvent, FlowGenerator};
+use orderbook::rng::Rng;
+use orderbook::{OrderBook, Order, OrderId, ParticipantId, Side, SymbolId, TimeInForce};
+use crate::alloc_counter::{self, AllocStats};
+use crate::profiling::Profiler;
+use crate::results::RunResults;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Calls per timed sample for operations too fast to time individually.
+/// `Instant::now()` costs ~20 ns, which would swamp a 3 ns lookup.
+const MARKET_DATA_SAMPLE_BATCH: u64 = 100;
 
 /// Runs complete latency test suite.
-pub fn run_latency_tests() {
+pub fn run_latency_tests(results: &mut RunResults, profiler: &Profiler) {
     println!(" HFT Ledger - Real-time Latency Tests\n");
-    
-    test_market_data_latency();
-    test_order_submission_latency();
-    test_order_matching_latency();
-    test_cancellation_latency();
+
+    profiler.scenario("market_data", || test_market_data_latency(results));
+    profiler.scenario("submission", || test_order_submission_latency(results));
+    profiler.scenario("matching", || test_order_matching_latency(results));
+    profiler.scenario("cancellation", || test_cancellation_latency(results));
 }
 
 /// Creates test order with current timestamp.
-fn create_order(id: u128, symbol: &str, side: Side, price: i64, qty: i64) -> Order {
+fn create_order(id: u128, symbol: impl Into<SymbolId>, side: Side, price: i64, qty: i64) -> Order {
     Order {
         id: OrderId(id),
-        symbol: symbol.to_string(),
+        symbol: symbol.into(),
         side,
         px_ticks: price,
         qty,
@@ -30,272 +41,447 @@ fn create_order(id: u128, symbol: &str, side: Side, price: i64, qty: i64) -> Ord
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_nanos(),
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
     }
 }
 
+/// Creates a nanosecond histogram covering 1 ns to 60 s at 3 significant digits.
+pub fn new_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, 60_000_000_000, 3).expect("valid histogram bounds")
+}
+
 /// Tests best bid/ask lookup performance.
-fn test_market_data_latency() {
+fn test_market_data_latency(results: &mut RunResults) {
     println!(" Market Data Latency Test");
-    
+
     let mut ob = OrderBook::new();
-    
+
     // Populate with 100 orders per side
     for i in 0..100 {
         let ask = create_order(i, "AAPL", Side::Ask, 10000 + i as i64, 100);
-        ob.submit_limit(ask);
+        ob.submit_limit(ask).expect("valid order");
         let bid = create_order(i + 100, "AAPL", Side::Bid, 9999 - i as i64, 100);
-        ob.submit_limit(bid);
-    }
-    
-    let iterations = 1_000_000;
-    
-    let start = Instant::now();
-    for _ in 0..iterations {
-        std::hint::black_box(ob.best_bid());
+        ob.submit_limit(bid).expect("valid order");
     }
-    let bid_duration = start.elapsed();
-    
-    let start = Instant::now();
-    for _ in 0..iterations {
-        std::hint::black_box(ob.best_ask());
+
+    let samples = 10_000;
+    let mut bid_hist = new_histogram();
+    let mut ask_hist = new_histogram();
+
+    // Each sample is the per-call average over a small batch
+    for _ in 0..samples {
+        let start = Instant::now();
+        for _ in 0..MARKET_DATA_SAMPLE_BATCH {
+            std::hint::black_box(ob.best_bid());
+        }
+        bid_hist.saturating_record(start.elapsed().as_nanos() as u64 / MARKET_DATA_SAMPLE_BATCH);
+
+        let start = Instant::now();
+        for _ in 0..MARKET_DATA_SAMPLE_BATCH {
+            std::hint::black_box(ob.best_ask());
+        }
+        ask_hist.saturating_record(start.elapsed().as_nanos() as u64 / MARKET_DATA_SAMPLE_BATCH);
     }
-    let ask_duration = start.elapsed();
-    
-    println!("  Best bid lookup: {:.2} ns/call", bid_duration.as_nanos() as f64 / iterations as f64);
-    println!("  Best ask lookup: {:.2} ns/call", ask_duration.as_nanos() as f64 / iterations as f64);
-    println!("  Combined latency: {:.2} ns\n", (bid_duration.as_nanos() + ask_duration.as_nanos()) as f64 / iterations as f64);
+
+    results.record("market_data", "Best bid lookup", &bid_hist);
+    results.record("market_data", "Best ask lookup", &ask_hist);
+    println!();
 }
 
 /// Tests order submission latency for non-crossing orders.
-fn test_order_submission_latency() {
+fn test_order_submission_latency(results: &mut RunResults) {
     println!(" Order Submission Latency Test");
-    
+
     let iterations = 10_000;
-    let mut total_time = 0u128;
-    
+    let mut hist = new_histogram();
+
     for i in 0..iterations {
         let mut ob = OrderBook::new();
         let order = create_order(i, "AAPL", Side::Bid, 10000 - i as i64, 100);
-        
+
         let start = Instant::now();
-        ob.submit_limit(order);
-        total_time += start.elapsed().as_nanos();
+        ob.submit_limit(order).expect("valid order");
+        hist.saturating_record(start.elapsed().as_nanos() as u64);
     }
-    
-    let avg_latency = total_time as f64 / iterations as f64;
-    println!("  Average order submission: {:.2} ns", avg_latency);
-    println!("  Throughput: {:.0} orders/second\n", 1_000_000_000.0 / avg_latency);
+
+    results.record("submission", "Order submission", &hist);
+    println!("  Throughput at p50: {:.0} orders/second\n", 1_000_000_000.0 / hist.value_at_quantile(0.50).max(1) as f64);
 }
 
 /// Tests order matching latency for crossing orders.
-fn test_order_matching_latency() {
+fn test_order_matching_latency(results: &mut RunResults) {
     println!(" Order Matching Latency Test");
-    
+
     let iterations = 1_000;
-    let mut total_setup_time = 0u128;
-    let mut total_match_time = 0u128;
-    
+    let mut setup_hist = new_histogram();
+    let mut match_hist = new_histogram();
+
     for i in 0..iterations {
         let setup_start = Instant::now();
         let mut ob = OrderBook::new();
-        
+
         // Add 10 resting ask orders
         for j in 0..10 {
             let ask = create_order(j, "AAPL", Side::Ask, 10000 + j as i64, 100);
-            ob.submit_limit(ask);
+            ob.submit_limit(ask).expect("valid order");
         }
-        total_setup_time += setup_start.elapsed().as_nanos();
-        
+        setup_hist.saturating_record(setup_start.elapsed().as_nanos() as u64);
+
         // Crossing bid that matches multiple levels
         let crossing_order = create_order(1000 + i, "AAPL", Side::Bid, 10005, 500);
-        
+
         let match_start = Instant::now();
-        let trades = ob.submit_limit(crossing_order);
-        total_match_time += match_start.elapsed().as_nanos();
-        
+        let trades = ob.submit_limit(crossing_order).expect("valid order").trades;
+        match_hist.saturating_record(match_start.elapsed().as_nanos() as u64);
+
         std::hint::black_box(trades);
     }
-    
-    println!("  Setup (10 resting orders): {:.2} ns", total_setup_time as f64 / iterations as f64);
-    println!("  Crossing order execution: {:.2} ns", total_match_time as f64 / iterations as f64);
-    println!("  Total order-to-trade: {:.2} ns\n", (total_setup_time + total_match_time) as f64 / iterations as f64);
+
+    results.record("matching", "Setup (10 resting orders)", &setup_hist);
+    results.record("matching", "Crossing order execution", &match_hist);
+    println!();
 }
 
 /// Compares lazy vs eager cancellation performance.
-fn test_cancellation_latency() {
+fn test_cancellation_latency(results: &mut RunResults) {
     println!(" Cancellation Latency Test");
-    
+
     let iterations = 1_000;
     let orders_per_test = 100;
-    
+
     // Test lazy cancellation (mark as cancelled)
-    let mut total_lazy_time = 0u128;
+    let mut lazy_hist = new_histogram();
     for i in 0..iterations {
         let mut bids = orderbook::PriceLevels::new(Side::Bid);
         let mut order_ids = Vec::new();
-        
+
         for j in 0..orders_per_test {
             let order = create_order((i * orders_per_test + j) as u128, "AAPL", Side::Bid, 10000, 100);
             order_ids.push(order.id);
             bids.push(order);
         }
-        
-        let start = Instant::now();
+
         // Cancel 50% of orders lazily
         for (idx, &order_id) in order_ids.iter().enumerate() {
             if idx % 2 == 0 {
+                let start = Instant::now();
                 bids.cancel(order_id); // O(1) operation
+                lazy_hist.saturating_record(start.elapsed().as_nanos() as u64);
             }
         }
-        total_lazy_time += start.elapsed().as_nanos();
     }
-    
+
     // Test eager removal (immediate removal)
-    let mut total_eager_time = 0u128;
+    let mut eager_hist = new_histogram();
     for i in 0..iterations {
         let mut bids = orderbook::PriceLevels::new(Side::Bid);
         let mut order_ids = Vec::new();
-        
+
         for j in 0..orders_per_test {
             let order = create_order((i * orders_per_test + j + 1_000_000) as u128, "AAPL", Side::Bid, 10000, 100);
             order_ids.push(order.id);
             bids.push(order);
         }
-        
-        let start = Instant::now();
+
         // Remove 50% of orders eagerly
         for (idx, &order_id) in order_ids.iter().enumerate() {
             if idx % 2 == 0 {
+                let start = Instant::now();
                 bids.remove(order_id); // O(n) operation
+                eager_hist.saturating_record(start.elapsed().as_nanos() as u64);
             }
         }
-        total_eager_time += start.elapsed().as_nanos();
     }
-    
-    println!("  Lazy cancellation: {:.2} ns per order", total_lazy_time as f64 / (iterations * orders_per_test / 2) as f64);
-    println!("  Eager removal: {:.2} ns per order", total_eager_time as f64 / (iterations * orders_per_test / 2) as f64);
-    println!("  Lazy is {:.1}x faster\n", total_eager_time as f64 / total_lazy_time as f64);
+
+    results.record("cancellation", "Lazy cancellation", &lazy_hist);
+    results.record("cancellation", "Eager removal", &eager_hist);
+    println!("  Lazy is {:.1}x faster at p50\n", eager_hist.value_at_quantile(0.50) as f64 / lazy_hist.value_at_quantile(0.50).max(1) as f64);
 }
 
-/// Runs sustained throughput test with mixed workload.
-pub fn run_throughput_test() {
-    println!(" Sustained Throughput Test (10 seconds)");
-    
-    let mut ob = OrderBook::new();
-    let mut order_id = 1u128;
-    let mut orders_processed = 0u64;
-    let mut trades_executed = 0u64;
-    
-    let start_time = Instant::now();
-    let duration = std::time::Duration::from_secs(10);
-    
-    while start_time.elapsed() < duration {
-        // Mix of order types: 25% each of non-crossing bids/asks, crossing bids/asks
-        match order_id % 4 {
-            0 => {
-                // Non-crossing bid
-                let order = create_order(order_id, "AAPL", Side::Bid, 9999 - (order_id % 100) as i64, 100);
-                ob.submit_limit(order);
-            }
-            1 => {
-                // Non-crossing ask
-                let order = create_order(order_id, "AAPL", Side::Ask, 10001 + (order_id % 100) as i64, 100);
-                ob.submit_limit(order);
-            }
-            2 => {
-                // Crossing bid
-                let order = create_order(order_id, "AAPL", Side::Bid, 10001, 50);
-                let trades = ob.submit_limit(order);
-                trades_executed += trades.len() as u64;
-            }
-            3 => {
-                // Crossing ask
-                let order = create_order(order_id, "AAPL", Side::Ask, 9999, 50);
-                let trades = ob.submit_limit(order);
-                trades_executed += trades.len() as u64;
-            }
-            _ => unreachable!(),
+/// Order stream shape used by the sustained throughput test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum FlowKind {
+    /// Uniform prices on a fixed ladder around 10000, fixed sizes
+    Ladder,
+    /// Poisson arrivals around a random-walk fair price (`orderbook::flow`)
+    Poisson,
+}
+
+/// Sustained throughput workload parameters, populated from the CLI.
+pub struct ThroughputConfig {
+    /// Wall-clock length of the run
+    pub duration: Duration,
+    /// Percentage of operations that are spread-crossing orders
+    pub crossing_pct: u32,
+    /// Percentage of operations that cancel a previously rested order
+    pub cancel_pct: u32,
+    /// Number of independent books; operations round-robin across them
+    pub symbols: usize,
+    /// Number of price levels per side that resting orders are spread over
+    pub book_depth: i64,
+    /// Which order stream to drive the books with
+    pub flow: FlowKind,
+    /// Seed for every per-book order stream; same seed, same flow
+    pub seed: u64,
+    /// Stop after this many operations even if time remains, so a seeded run
+    /// replays exactly the same sequence
+    pub max_ops: Option<u64>,
+    /// Constant arrival rate in operations per second; `None` runs flat out
+    pub rate: Option<u64>,
+}
+
+/// Fixed-ladder workload: resting orders uniform within `book_depth` ticks of
+/// 10000, crossing orders sweeping up to half that, fixed sizes.
+struct LadderFlow {
+    symbol: SymbolId,
+    crossing_pct: u32,
+    cancel_pct: u32,
+    book_depth: i64,
+    rng: Rng,
+    next_id: u128,
+    /// Ids of rested orders, the pool cancels are drawn from
+    resting: Vec<(OrderId, Side)>,
+}
+
+impl Iterator for LadderFlow {
+    type Item = FlowEvent;
+
+    fn next(&mut self) -> Option<FlowEvent> {
+        let roll = self.rng.below(100) as u32;
+        let side = if self.rng.next_bool() { Side::Bid } else { Side::Ask };
+
+        if roll < self.cancel_pct && !self.resting.is_empty() {
+            let idx = self.rng.below(self.resting.len() as u64) as usize;
+            let (id, side) = self.resting.swap_remove(idx);
+            return Some(FlowEvent::Cancel { id, side, ts_ns: 0 });
         }
-        
-        order_id += 1;
-        orders_processed += 1;
-        
-        // Periodic market data queries (every 100 orders)
-        if order_id % 100 == 0 {
-            std::hint::black_box(ob.best_bid());
-            std::hint::black_box(ob.best_ask());
+
+        let crossing = roll < self.cancel_pct + self.crossing_pct;
+        let offset = self.rng.below(self.book_depth as u64) as i64;
+        let (price, qty) = match (side, crossing) {
+            (Side::Bid, false) => (9999 - offset, 100),
+            (Side::Ask, false) => (10001 + offset, 100),
+            (Side::Bid, true) => (10001 + offset / 2, 50),
+            (Side::Ask, true) => (9999 - offset / 2, 50),
+        };
+        let order = create_order(self.next_id, self.symbol, side, price, qty);
+        self.next_id += 1;
+        if !crossing {
+            self.resting.push((order.id, side));
         }
+        Some(FlowEvent::Submit(order))
     }
-    
-    let elapsed = start_time.elapsed();
-    let orders_per_sec = orders_processed as f64 / elapsed.as_secs_f64();
-    let trades_per_sec = trades_executed as f64 / elapsed.as_secs_f64();
-    
-    println!("  Duration: {:.1} seconds", elapsed.as_secs_f64());
-    println!("  Orders processed: {}", orders_processed);
-    println!("  Trades executed: {}", trades_executed);
-    println!("  Order throughput: {:.0} orders/second", orders_per_sec);
-    println!("  Trade throughput: {:.0} trades/second", trades_per_sec);
-    println!("  Final book state: bid={:?}, ask={:?}", ob.best_bid(), ob.best_ask());
-} 
-/// Runs 1-minute sustained throughput test with mixed workload.
-pub fn run_throughput_test_1min() {
-    println!(" Sustained Throughput Test (60 seconds)");
-    
-    let mut ob = OrderBook::new();
-    let mut order_id = 1u128;
+}
+
+/// Builds the per-book event stream for the configured flow kind.
+fn make_flow(config: &ThroughputConfig, symbol: &str, seed: u64) -> Box<dyn Iterator<Item = FlowEvent>> {
+    match config.flow {
+        FlowKind::Ladder => Box::new(LadderFlow {
+            symbol: symbol.into(),
+            crossing_pct: config.crossing_pct,
+            cancel_pct: config.cancel_pct,
+            book_depth: config.book_depth,
+            rng: Rng::seed_from_u64(seed),
+            next_id: 1,
+            resting: Vec::new(),
+        }),
+        FlowKind::Poisson => Box::new(FlowGenerator::new(
+            FlowConfig {
+                symbol: symbol.to_string(),
+                marketable_ratio: config.crossing_pct as f64 / 100.0,
+                cancel_ratio: config.cancel_pct as f64 / 100.0,
+                // Exponential placement: most of the book within ~book_depth ticks
+                mean_depth_ticks: (config.book_depth as f64 / 4.0).max(1.0),
+                ..FlowConfig::default()
+            },
+            seed,
+        )),
+    }
+}
+
+/// Busy-waits until `at`, sleeping first if it is far off.
+fn wait_until(at: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= at {
+            return;
+        }
+        let left = at - now;
+        if left > Duration::from_millis(2) {
+            std::thread::sleep(left - Duration::from_millis(1));
+        } else {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Runs sustained throughput test with mixed workload.
+///
+/// Each operation is a cancel, a cancel/replace, a crossing order, or a
+/// resting order, drawn from the configured flow with the configured mix.
+///
+/// With a `rate`, operation `i` is scheduled at `start + i / rate` and its
+/// response time runs from that intended start, not from when the loop got
+/// to it. A stall then shows up in every operation queued behind it, as a
+/// client sending at a fixed rate would see it, instead of as one slow sample
+/// (coordinated omission).
+pub fn run_throughput_test(config: &ThroughputConfig, results: &mut RunResults) {
+    println!(
+        " Sustained Throughput Test ({} seconds, {:?} flow, seed {}, {} symbol(s), {}% crossing, {}% cancel, depth {})",
+        config.duration.as_secs(),
+        config.flow,
+        config.seed,
+        config.symbols,
+        config.crossing_pct,
+        config.cancel_pct,
+        config.book_depth,
+    );
+    if let Some(rate) = config.rate {
+        println!("  Constant arrival rate: {} ops/second (latency from intended start)", rate);
+    }
+
+    let symbols: Vec<String> = (0..config.symbols).map(|i| format!("SYM{}", i)).collect();
+    let mut books: Vec<OrderBook> = (0..config.symbols).map(|_| OrderBook::new()).collect();
+    let mut seeder = Rng::seed_from_u64(config.seed);
+    let mut flows: Vec<_> = symbols
+        .iter()
+        .map(|symbol| make_flow(config, symbol, seeder.next_u64()))
+        .collect();
+
+    let mut ops = 0u64;
     let mut orders_processed = 0u64;
+    let mut cancels_processed = 0u64;
     let mut trades_executed = 0u64;
-    
+    let mut submit_hist = new_histogram();
+    let mut cancel_hist = new_histogram();
+    let mut response_hist = new_histogram();
+    let interval_ns = config.rate.map(|rate| 1e9 / rate.max(1) as f64);
+    // Operations whose intended start had already passed when the loop reached them
+    let mut late_ops = 0u64;
+    // Allocator activity inside submit_limit_into, split by whether it matched
+    let mut submit_allocs = AllocStats::default();
+    let mut matching_allocs = AllocStats::default();
+    // One trade buffer for the whole run, as a hot-path caller would keep
+    let mut trades = Vec::new();
+
     let start_time = Instant::now();
-    let duration = std::time::Duration::from_secs(60);
-    
-    while start_time.elapsed() < duration {
-        // Mix of order types: 25% each of non-crossing bids/asks, crossing bids/asks
-        match order_id % 4 {
-            0 => {
-                // Non-crossing bid
-                let order = create_order(order_id, "AAPL", Side::Bid, 9999 - (order_id % 100) as i64, 100);
-                ob.submit_limit(order);
-            }
-            1 => {
-                // Non-crossing ask
-                let order = create_order(order_id, "AAPL", Side::Ask, 10001 + (order_id % 100) as i64, 100);
-                ob.submit_limit(order);
-            }
-            2 => {
-                // Crossing bid
-                let order = create_order(order_id, "AAPL", Side::Bid, 10001, 50);
-                let trades = ob.submit_limit(order);
-                trades_executed += trades.len() as u64;
+
+    while start_time.elapsed() < config.duration && config.max_ops.is_none_or(|max| ops < max) {
+        let book_idx = (ops % config.symbols as u64) as usize;
+        let ob = &mut books[book_idx];
+        let Some(event) = flows[book_idx].next() else {
+            break;
+        };
+
+        let intended = interval_ns.map(|ns| start_time + Duration::from_nanos((ops as f64 * ns) as u64));
+        if let Some(intended) = intended {
+            if Instant::now() > intended {
+                late_ops += 1;
             }
-            3 => {
-                // Crossing ask
-                let order = create_order(order_id, "AAPL", Side::Ask, 9999, 50);
-                let trades = ob.submit_limit(order);
-                trades_executed += trades.len() as u64;
+            wait_until(intended);
+        }
+
+        let (cancel, order) = match event {
+            FlowEvent::Submit(order) => (None, Some(order)),
+            FlowEvent::Cancel { id, side, .. } => (Some((id, side)), None),
+            FlowEvent::Replace { id, side, order } => (Some((id, side)), Some(order)),
+        };
+
+        if let Some((id, side)) = cancel {
+            let start = Instant::now();
+            ob.levels_mut(side).cancel(id);
+            cancel_hist.saturating_record(start.elapsed().as_nanos() as u64);
+            cancels_processed += 1;
+        }
+
+        if let Some(order) = order {
+            let alloc_before = alloc_counter::snapshot();
+            trades.clear();
+            let start = Instant::now();
+            ob.submit_limit_into(order, &mut trades).expect("flow orders are valid");
+            submit_hist.saturating_record(start.elapsed().as_nanos() as u64);
+            if let (Some(before), Some(after)) = (alloc_before, alloc_counter::snapshot()) {
+                let delta = after.since(before);
+                submit_allocs.allocs += delta.allocs;
+                submit_allocs.bytes += delta.bytes;
+                if !trades.is_empty() {
+                    matching_allocs.allocs += delta.allocs;
+                    matching_allocs.bytes += delta.bytes;
+                }
             }
-            _ => unreachable!(),
+            trades_executed += trades.len() as u64;
+            orders_processed += 1;
+        }
+
+        if let Some(intended) = intended {
+            response_hist.saturating_record(intended.elapsed().as_nanos() as u64);
         }
-        
-        order_id += 1;
-        orders_processed += 1;
-        
-        // Periodic market data queries (every 100 orders)
-        if order_id % 100 == 0 {
+        ops += 1;
+
+        // Periodic market data queries (every 100 operations)
+        if ops.is_multiple_of(100) {
             std::hint::black_box(ob.best_bid());
             std::hint::black_box(ob.best_ask());
         }
     }
-    
+
     let elapsed = start_time.elapsed();
     let orders_per_sec = orders_processed as f64 / elapsed.as_secs_f64();
     let trades_per_sec = trades_executed as f64 / elapsed.as_secs_f64();
-    
+
     println!("  Duration: {:.1} seconds", elapsed.as_secs_f64());
     println!("  Orders processed: {}", orders_processed);
+    println!("  Cancels processed: {}", cancels_processed);
     println!("  Trades executed: {}", trades_executed);
     println!("  Order throughput: {:.0} orders/second", orders_per_sec);
     println!("  Trade throughput: {:.0} trades/second", trades_per_sec);
-    println!("  Final book state: bid={:?}, ask={:?}", ob.best_bid(), ob.best_ask());
+    let submit = results.record("throughput", "Submit latency", &submit_hist);
+    submit.throughput_per_sec = Some(orders_per_sec);
+    if alloc_counter::snapshot().is_some() && orders_processed > 0 {
+        submit.allocs_per_op = Some(submit_allocs.allocs as f64 / orders_processed as f64);
+        submit.alloc_bytes_per_op = Some(submit_allocs.bytes as f64 / orders_processed as f64);
+        println!(
+            "  Allocations per submitted order: {:.3} ({:.1} bytes)",
+            submit_allocs.allocs as f64 / orders_processed as f64,
+            submit_allocs.bytes as f64 / orders_processed as f64,
+        );
+        if trades_executed > 0 {
+            println!(
+                "  Allocations per match: {:.3} ({:.1} bytes)",
+                matching_allocs.allocs as f64 / trades_executed as f64,
+                matching_allocs.bytes as f64 / trades_executed as f64,
+            );
+        }
+    }
+    if cancels_processed > 0 {
+        results.record("throughput", "Cancel latency", &cancel_hist).throughput_per_sec =
+            Some(cancels_processed as f64 / elapsed.as_secs_f64());
+    }
+    if let Some(rate) = config.rate {
+        let label = format!("Response time @ {} ops/s (intended start)", rate);
+        results.record("throughput", &label, &response_hist).throughput_per_sec =
+            Some(ops as f64 / elapsed.as_secs_f64());
+        println!(
+            "  Achieved {:.0} ops/second; {} of {} operations ({:.2}%) started behind schedule",
+            ops as f64 / elapsed.as_secs_f64(),
+            late_ops,
+            ops,
+            100.0 * late_ops as f64 / ops.max(1) as f64,
+        );
+    }
+    for (symbol, ob) in symbols.iter().zip(&books) {
+        println!(
+            "  Final book state {}: bid={:?}, ask={:?}, resting={}/{}",
+            symbol,
+            ob.best_bid(),
+            ob.best_ask(),
+            ob.bids.total_len(),
+            ob.asks.total_len(),
+        );
+    }
 }
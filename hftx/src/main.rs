@@ -1,84 +1,404 @@
 //! HFT Ledger Performance Test Suite
 //!
 //! Runs performance tests followed by a basic trading demo showing
-//! order placement, matching, and trade execution.
+//! order placement, matching, and trade execution. Run length, order mix,
+//! symbol count, and book depth are configurable from the command line.
+//! `--persistence` adds snapshot / journal / replay timings, `--soak` a
+//! long-running leak check, `--itch` a replay of a Nasdaq ITCH 5.0 dump,
+//! `--export-parquet` a Parquet dump of seeded flow, and `--wire` and
+//! `--fanout` end-to-end tests against a live exchange-service.
 
-use orderbook::{OrderBook, Order, OrderId, Side};
+use clap::Parser;
+use orderbook::rng::Rng;
+use orderbook::{OrderBook, Order, OrderId, ParticipantId, PriceConverter, Side, TimeInForce};
 
+mod alloc_counter;
+mod fanout_test;
+mod itch_replay;
 mod latency_test;
+mod parquet_export;
+mod persistence_test;
+mod profiling;
+mod regression;
+mod results;
+mod service;
+mod soak_test;
+mod wire_test;
+
+use fanout_test::FanoutConfig;
+use itch_replay::ItchConfig;
+use latency_test::{FlowKind, ThroughputConfig};
+use parquet_export::ExportConfig;
+use persistence_test::PersistenceConfig;
+use soak_test::SoakConfig;
+use profiling::Profiler;
+use regression::Tolerances;
+use results::RunResults;
+use wire_test::WireConfig;
+
+#[derive(Parser)]
+#[command(name = "hftx-perf")]
+#[command(about = "HFT Ledger Performance Lab - latency and throughput harness")]
+struct Args {
+    /// Sustained throughput run length in seconds
+    #[arg(short, long, default_value = "10")]
+    duration: u64,
+    /// Percentage of submitted orders that cross the spread (0-100)
+    #[arg(long, default_value = "50")]
+    crossing_pct: u32,
+    /// Percentage of operations that cancel a resting order (0-100)
+    #[arg(long, default_value = "0")]
+    cancel_pct: u32,
+    /// Number of independent books the flow is spread across
+    #[arg(long, default_value = "1")]
+    symbols: usize,
+    /// Distinct price levels per side that resting orders are spread over
+    #[arg(long, default_value = "100")]
+    book_depth: i64,
+    /// Order stream driving the throughput test
+    #[arg(long, value_enum, default_value = "ladder")]
+    flow: FlowKind,
+    /// Seed for the generated order flow; random (and printed) when omitted
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Stop the throughput test after this many operations (exact replays)
+    #[arg(long)]
+    max_ops: Option<u64>,
+    /// Run the throughput test at this constant arrival rate (ops/sec) and
+    /// report latency from each operation's intended start time
+    #[arg(long)]
+    rate: Option<u64>,
+    /// Skip the latency micro-tests and only run the throughput test
+    #[arg(long)]
+    skip_latency: bool,
+    /// Skip the basic order book demo
+    #[arg(long)]
+    skip_demo: bool,
+    /// Also measure HTTP submit -> trade-on-WebSocket latency against a live exchange-service
+    #[arg(long)]
+    wire: bool,
+    /// Measure journal append, snapshot write/restore, and journal replay speed
+    #[arg(long)]
+    persistence: bool,
+    /// Flow events journaled and replayed in the persistence test
+    #[arg(long, default_value = "1000000")]
+    persistence_events: usize,
+    /// Run mixed flow for this long (e.g. `90s`, `30m`, `6h`) watching for
+    /// unbounded memory, tombstone, and registry growth; exits 1 on a leak
+    #[arg(long, value_parser = soak_test::parse_duration)]
+    soak: Option<std::time::Duration>,
+    /// Seconds between soak samples
+    #[arg(long, default_value = "10")]
+    soak_sample_secs: u64,
+    /// Allowed growth of a soak metric's peak between run halves, in percent
+    #[arg(long, default_value = "10")]
+    soak_tolerance_pct: f64,
+    /// Compact each side of the soak book once this many canceled orders
+    /// are queued; off by default, so tombstone growth shows
+    #[arg(long)]
+    soak_compact_at: Option<usize>,
+    /// Replay a Nasdaq ITCH 5.0 dump (`.gz` is fine) through the engine
+    #[arg(long)]
+    itch: Option<std::path::PathBuf>,
+    /// Symbols to rebuild from the ITCH dump, comma separated; default all
+    #[arg(long, value_delimiter = ',')]
+    itch_symbols: Vec<String>,
+    /// Stop the ITCH replay after this many messages
+    #[arg(long)]
+    itch_max_messages: Option<u64>,
+    /// Write seeded flow's trades and book snapshots as Parquet into this directory (`parquet` feature)
+    #[arg(long)]
+    export_parquet: Option<std::path::PathBuf>,
+    /// Flow events applied for the Parquet export
+    #[arg(long, default_value = "1000000")]
+    export_events: usize,
+    /// Virtual milliseconds between exported book snapshots
+    #[arg(long, default_value = "10")]
+    export_snapshot_ms: u64,
+    /// Price levels per side in each exported book snapshot
+    #[arg(long, default_value = "10")]
+    export_levels: usize,
+    /// Measure trade broadcast lag and drops with many WebSocket subscribers
+    #[arg(long)]
+    fanout: bool,
+    /// WebSocket subscribers in the fan-out test
+    #[arg(long, default_value = "500")]
+    fanout_subscribers: usize,
+    /// Symbols the fan-out subscribers and order flow are spread across
+    #[arg(long, default_value = "5")]
+    fanout_symbols: usize,
+    /// Seconds of order flow in the fan-out test
+    #[arg(long, default_value = "10")]
+    fanout_secs: u64,
+    /// Bot driver tick per symbol in the fan-out test, in milliseconds
+    #[arg(long, default_value = "5")]
+    fanout_tick_ms: u64,
+    /// Run service scenarios against this running exchange-service instead of spawning one
+    #[arg(long)]
+    service_url: Option<String>,
+    /// exchange-service binary to spawn for service scenarios
+    #[arg(long)]
+    exchange_bin: Option<std::path::PathBuf>,
+    /// Symbol the wire test trades
+    #[arg(long, default_value = "AAPL")]
+    wire_symbol: String,
+    /// Concurrent HTTP clients in the wire test
+    #[arg(long, default_value = "8")]
+    wire_clients: usize,
+    /// Crossing orders each wire-test client sends
+    #[arg(long, default_value = "500")]
+    wire_orders: usize,
+    /// Run the server-side bot driver on the wire symbol as background load
+    #[arg(long)]
+    wire_sim_load: bool,
+    /// Run the wire test with the service's submit batching off, then on
+    #[arg(long)]
+    wire_compare_batching: bool,
+    /// Write structured results to this file (`.csv` for CSV, otherwise JSON)
+    #[arg(short, long)]
+    output: Option<std::path::PathBuf>,
+    /// Compare this run against a stored results file and exit 1 on regression
+    #[arg(long)]
+    baseline: Option<std::path::PathBuf>,
+    /// Allowed p50 growth over the baseline, in percent
+    #[arg(long, default_value = "10")]
+    tolerance_p50: f64,
+    /// Allowed p99 growth over the baseline, in percent
+    #[arg(long, default_value = "25")]
+    tolerance_p99: f64,
+    /// Allowed p99.9 growth over the baseline, in percent
+    #[arg(long, default_value = "50")]
+    tolerance_p999: f64,
+    /// Allowed throughput drop below the baseline, in percent
+    #[arg(long, default_value = "10")]
+    tolerance_throughput: f64,
+    /// Latency growth under this many nanoseconds is never a regression
+    #[arg(long, default_value = "100")]
+    tolerance_floor_ns: u64,
+    /// Write a flamegraph per scenario into this directory (`profiling` feature)
+    #[arg(long)]
+    profile_dir: Option<std::path::PathBuf>,
+}
 
 /// Main entry point - runs performance tests and demo.
 fn main() {
+    let args = Args::parse();
+
+    if args.crossing_pct + args.cancel_pct > 100 {
+        eprintln!("--crossing-pct plus --cancel-pct must not exceed 100");
+        std::process::exit(2);
+    }
+
     println!("=== HFT Ledger Performance Lab ===");
-    
+    let seed = args.seed.unwrap_or_else(Rng::entropy_seed);
+    println!("Seed: {} (reproduce with --seed {})", seed, seed);
+    println!("Allocator: {}", alloc_counter::ALLOCATOR);
+    let mut results = RunResults::start();
+    results.seed = Some(seed);
+    let profiler = Profiler::new(args.profile_dir.clone());
+
     // Run comprehensive performance tests
-    latency_test::run_latency_tests();
-    latency_test::run_throughput_test();
-    
-    // Run 1-minute sustained throughput test
-    println!("\n=== 1-Minute Sustained Throughput Test ===");
-    latency_test::run_throughput_test_1min();
-    
+    if !args.skip_latency {
+        latency_test::run_latency_tests(&mut results, &profiler);
+    }
+
+    let config = ThroughputConfig {
+        duration: std::time::Duration::from_secs(args.duration),
+        crossing_pct: args.crossing_pct,
+        cancel_pct: args.cancel_pct,
+        symbols: args.symbols.max(1),
+        book_depth: args.book_depth.max(1),
+        flow: args.flow,
+        seed,
+        max_ops: args.max_ops,
+        rate: args.rate.filter(|&r| r > 0),
+    };
+    profiler.scenario("throughput", || latency_test::run_throughput_test(&config, &mut results));
+
+    if args.persistence {
+        let persistence = PersistenceConfig {
+            events: args.persistence_events.max(1),
+            seed,
+        };
+        profiler.scenario("persistence", || persistence_test::run_persistence_test(&persistence, &mut results));
+    }
+
+    if let Some(path) = &args.itch {
+        let itch = ItchConfig {
+            path: path.clone(),
+            symbols: args.itch_symbols.clone(),
+            max_messages: args.itch_max_messages,
+        };
+        profiler.scenario("itch", || itch_replay::run_itch_replay(&itch, &mut results));
+    }
+
+    if let Some(dir) = &args.export_parquet {
+        let export = ExportConfig {
+            dir: dir.clone(),
+            events: args.export_events.max(1),
+            snapshot_every_ns: args.export_snapshot_ms.max(1) as u128 * 1_000_000,
+            levels: args.export_levels,
+            seed,
+        };
+        profiler.scenario("parquet-export", || parquet_export::run_parquet_export(&export));
+    }
+
+    let leaked = match args.soak {
+        Some(duration) => {
+            let soak = SoakConfig {
+                duration,
+                sample_every: std::time::Duration::from_secs(args.soak_sample_secs.max(1)),
+                warmup_fraction: 0.25,
+                tolerance_pct: args.soak_tolerance_pct,
+                compact_at: args.soak_compact_at,
+                seed,
+            };
+            profiler.scenario("soak", || soak_test::run_soak_test(&soak, &mut results))
+        }
+        None => Vec::new(),
+    };
+
+    if args.wire {
+        let wire = WireConfig {
+            url: args.service_url.clone(),
+            exchange_bin: args.exchange_bin.clone(),
+            symbol: args.wire_symbol.clone(),
+            clients: args.wire_clients.max(1),
+            orders_per_client: args.wire_orders,
+            sim_load: args.wire_sim_load,
+            compare_batching: args.wire_compare_batching,
+        };
+        profiler.scenario("wire", || wire_test::run_wire_latency_test(&wire, &mut results));
+    }
+
+    if args.fanout {
+        let fanout = FanoutConfig {
+            url: args.service_url.clone(),
+            exchange_bin: args.exchange_bin.clone(),
+            subscribers: args.fanout_subscribers.max(1),
+            symbols: args.fanout_symbols,
+            duration: std::time::Duration::from_secs(args.fanout_secs.max(1)),
+            tick_ms: args.fanout_tick_ms,
+        };
+        profiler.scenario("fanout", || fanout_test::run_fanout_test(&fanout, &mut results));
+    }
+
+    if let Some(path) = &args.output {
+        match results.write_to(path) {
+            Ok(()) => println!("\nResults written to {}", path.display()),
+            Err(e) => eprintln!("\nFailed to write results to {}: {}", path.display(), e),
+        }
+    }
+
     // Show basic order book functionality
-    println!("\n=== Basic Demo ===");
-    run_basic_demo();
+    if !args.skip_demo {
+        println!("\n=== Basic Demo ===");
+        run_basic_demo();
+    }
+
+    if let Some(path) = &args.baseline {
+        let tolerances = Tolerances {
+            p50_pct: args.tolerance_p50,
+            p99_pct: args.tolerance_p99,
+            p999_pct: args.tolerance_p999,
+            throughput_pct: args.tolerance_throughput,
+            floor_ns: args.tolerance_floor_ns,
+        };
+        match regression::check(&results, path, &tolerances) {
+            Ok(regressions) if regressions.is_empty() => {}
+            Ok(regressions) => {
+                eprintln!("\n{} metric(s) regressed beyond tolerance", regressions.len());
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("\n{}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if !leaked.is_empty() {
+        eprintln!("\nSoak test found unbounded growth: {}", leaked.join(", "));
+        std::process::exit(1);
+    }
 }
 
 /// Demonstrates basic order book functionality with trade execution.
 fn run_basic_demo() {
     let mut ob = OrderBook::new();
-    
+    // Whole cents: $150.00 is 15000 ticks
+    let cents = PriceConverter::new(0.01, 2);
+    let ticks = |price: &str| cents.parse(price).expect("a price in cents");
+
     println!("HFT Ledger - Order Book Demo");
-    
+
     // Add ask order at $150.00
     let ask_order = Order {
         id: OrderId(1),
-        symbol: "AAPL".to_string(),
+        symbol: "AAPL".into(),
         side: Side::Ask,
-        px_ticks: 15000, // $150.00 in ticks
+        px_ticks: ticks("150.00"),
         qty: 100,
         ts_ns: 1_000_000_000,
+        tif: TimeInForce::Day,
+        expires_ns: None,
+        display_qty: None,
+        min_qty: None,
+        hidden: false,
+        owner: ParticipantId::NONE,
     };
-    
+
     // Add bid order at $149.50 (creates spread)
     let bid_order = Order {
         id: OrderId(2),
-        symbol: "AAPL".to_string(),
+        symbol: "AAPL".into(),
         side: Side::Bid,
-        px_ticks: 14950, // $149.50 in ticks
+        px_ticks: ticks("149.50"),
         qty: 50,
         ts_ns: 1_000_000_001,
+        tif: TimeInForce::Day,
+        expires_ns: None,
+        display_qty: None,
+        min_qty: None,
+        hidden: false,
+        owner: ParticipantId::NONE,
     };
-    
-    println!("Submitting ask order: {} @ {}", ask_order.qty, ask_order.px_ticks);
-    ob.submit_limit(ask_order);
-    
-    println!("Submitting bid order: {} @ {}", bid_order.qty, bid_order.px_ticks);
-    ob.submit_limit(bid_order);
-    
+
+    println!("Submitting ask order: {} @ ${}", ask_order.qty, cents.format(ask_order.px_ticks));
+    ob.submit_limit(ask_order).expect("valid order");
+
+    println!("Submitting bid order: {} @ ${}", bid_order.qty, cents.format(bid_order.px_ticks));
+    ob.submit_limit(bid_order).expect("valid order");
+
     println!("Best bid: {:?}", ob.best_bid());
     println!("Best ask: {:?}", ob.best_ask());
-    
+
     // Crossing bid that will execute against the ask
     let crossing_bid = Order {
         id: OrderId(3),
-        symbol: "AAPL".to_string(),
+        symbol: "AAPL".into(),
         side: Side::Bid,
-        px_ticks: 15000, // Matches ask price
+        px_ticks: ticks("150.00"), // Matches ask price
         qty: 75,         // Partial fill of ask order
         ts_ns: 1_000_000_002,
+        tif: TimeInForce::Day,
+        expires_ns: None,
+        display_qty: None,
+        min_qty: None,
+        hidden: false,
+        owner: ParticipantId::NONE,
     };
-    
-    println!("Submitting crossing bid: {} @ {}", crossing_bid.qty, crossing_bid.px_ticks);
-    let trades = ob.submit_limit(crossing_bid);
-    
+
+    println!("Submitting crossing bid: {} @ ${}", crossing_bid.qty, cents.format(crossing_bid.px_ticks));
+    let trades = ob.submit_limit(crossing_bid).expect("valid order").trades;
+
     println!("Trades executed: {}", trades.len());
     for trade in trades {
-        println!("  Trade: {} shares @ {} ticks", trade.qty, trade.px_ticks);
+        println!("  Trade: {} shares @ ${} ({} ticks)", trade.qty, cents.format(trade.px_ticks), trade.px_ticks);
         // Note: trade executes at maker's price (15000)
         // Maker: OrderId(1), Taker: OrderId(3)
     }
-    
+
     println!("Final best bid: {:?}", ob.best_bid()); // Original bid remains
     println!("Final best ask: {:?}", ob.best_ask()); // 25 shares left of original ask
 }
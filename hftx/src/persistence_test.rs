@@ -0,0 +1,242 @@
+//! Snapshot, journal, and replay speed.
+//!
+//! Recovery time is something deployments get sized around, so it is measured
+//! alongside matching speed:
+//! - journal append: each inbound event serialized and appended to a buffered
+//!   file as it is applied to the book, then flushed and fsynced at the end
+//! - snapshot: the resting book (live orders, priority order) serialized, with
+//!   its size, and restored into a fresh book
+//! - replay: the journal read back and applied to an empty book, reported as
+//!   events and orders rebuilt per second, and checked against the live book
+//!
+//! Each step runs twice: JSON (one entry per line) and the versioned binary
+//! format from `orderbook::codec`. Binary results carry a "(binary)" suffix.
+
+use crate::latency_test::new_histogram;
+use crate::results::RunResults;
+use orderbook::codec::{self, BookEvent, JournalReader, JournalWriter};
+use orderbook::flow::{FlowConfig, FlowEvent, FlowGenerator};
+use orderbook::{Order, OrderBook};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// Snapshot/restore rounds timed per run.
+const SNAPSHOT_ROUNDS: usize = 20;
+
+/// Settings for the persistence scenario.
+pub struct PersistenceConfig {
+    /// Flow events journaled and replayed
+    pub events: usize,
+    pub seed: u64,
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Binary,
+}
+
+impl Format {
+    /// Result-name suffix; JSON keeps the original unsuffixed names.
+    fn suffix(self) -> &'static str {
+        match self {
+            Format::Json => "",
+            Format::Binary => " (binary)",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Binary => "binary",
+        }
+    }
+}
+
+/// Runs the scenario and records its timings under the "persistence" suite.
+pub fn run_persistence_test(config: &PersistenceConfig, results: &mut RunResults) {
+    println!("\n Snapshot / Journal / Replay Test ({} events, seed {})", config.events, config.seed);
+
+    for format in [Format::Json, Format::Binary] {
+        println!("  [{}]", format.name());
+        let path = std::env::temp_dir().join(format!("hftx-journal-{}.{}", std::process::id(), format.name()));
+        if let Err(e) = run(config, format, &path, results) {
+            eprintln!("  persistence test ({}) failed: {}", format.name(), e);
+        }
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+fn run(config: &PersistenceConfig, format: Format, path: &Path, results: &mut RunResults) -> std::io::Result<()> {
+    let book = journal(config, format, path, results)?;
+    snapshot(&book, format, results)?;
+    replay(path, format, &book, results)
+}
+
+/// Applies generated flow to a book while journaling every event.
+fn journal(
+    config: &PersistenceConfig,
+    format: Format,
+    path: &Path,
+    results: &mut RunResults,
+) -> std::io::Result<OrderBook> {
+    let mut book = OrderBook::new();
+    let out = BufWriter::new(File::create(path)?);
+    let (mut json_out, mut binary_out) = match format {
+        Format::Json => (Some(out), None),
+        Format::Binary => (None, Some(JournalWriter::new(out)?)),
+    };
+    let mut append = new_histogram();
+    let mut line = Vec::with_capacity(256);
+
+    let started = Instant::now();
+    for event in FlowGenerator::new(FlowConfig::default(), config.seed).take(config.events) {
+        let entries = match event {
+            FlowEvent::Submit(order) => vec![BookEvent::Submit(order)],
+            FlowEvent::Cancel { id, side, .. } => vec![BookEvent::Cancel { id, side }],
+            FlowEvent::Replace { id, side, order } => {
+                vec![BookEvent::Cancel { id, side }, BookEvent::Submit(order)]
+            }
+        };
+        for entry in entries {
+            let t0 = Instant::now();
+            if let Some(out) = &mut json_out {
+                line.clear();
+                serde_json::to_writer(&mut line, &entry)?;
+                line.push(b'\n');
+                out.write_all(&line)?;
+            }
+            if let Some(out) = &mut binary_out {
+                out.append(&entry)?;
+            }
+            append.saturating_record(t0.elapsed().as_nanos() as u64);
+            std::hint::black_box(entry.apply(&mut book));
+        }
+    }
+    let sync_started = Instant::now();
+    let out = match (json_out, binary_out) {
+        (Some(out), _) => out,
+        (_, Some(out)) => out.into_inner(),
+        _ => unreachable!("one journal writer is always open"),
+    };
+    let file = out.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    let sync = sync_started.elapsed();
+    let elapsed = started.elapsed();
+
+    let bytes = std::fs::metadata(path)?.len();
+    let entries = append.len();
+    results.record("persistence", &format!("Journal append{}", format.suffix()), &append).throughput_per_sec =
+        Some(entries as f64 / elapsed.as_secs_f64());
+    println!(
+        "  journal: {} entries, {:.1} MiB ({:.0} bytes/entry), {:.0} entries/sec including matching, final fsync {:.1} ms",
+        entries,
+        bytes as f64 / (1024.0 * 1024.0),
+        bytes as f64 / entries.max(1) as f64,
+        entries as f64 / elapsed.as_secs_f64(),
+        sync.as_secs_f64() * 1e3,
+    );
+    Ok(book)
+}
+
+/// Times serializing the resting book and restoring it into a fresh one.
+fn snapshot(book: &OrderBook, format: Format, results: &mut RunResults) -> std::io::Result<()> {
+    let mut write = new_histogram();
+    let mut restore = new_histogram();
+    let mut size = 0;
+    let resting = book.bids.live_orders().count() + book.asks.live_orders().count();
+
+    for _ in 0..SNAPSHOT_ROUNDS {
+        let t0 = Instant::now();
+        let buf = match format {
+            Format::Json => {
+                let mut buf = Vec::new();
+                for order in book.bids.live_orders().chain(book.asks.live_orders()) {
+                    serde_json::to_writer(&mut buf, order)?;
+                    buf.push(b'\n');
+                }
+                buf
+            }
+            Format::Binary => codec::encode_snapshot(book),
+        };
+        write.saturating_record(t0.elapsed().as_nanos() as u64);
+        size = buf.len();
+
+        let t0 = Instant::now();
+        let restored = match format {
+            Format::Json => {
+                let mut restored = OrderBook::new();
+                for line in buf.split(|b| *b == b'\n').filter(|l| !l.is_empty()) {
+                    let order: Order = serde_json::from_slice(line)?;
+                    restored.levels_mut(order.side).push(order)
+                }
+                restored
+            }
+            Format::Binary => codec::decode_snapshot(&buf).map_err(std::io::Error::other)?,
+        };
+        restore.saturating_record(t0.elapsed().as_nanos() as u64);
+        std::hint::black_box(&restored);
+    }
+
+    results.record("persistence", &format!("Snapshot write{}", format.suffix()), &write);
+    results.record("persistence", &format!("Snapshot restore{}", format.suffix()), &restore);
+    println!(
+        "  snapshot: {} resting orders, {:.1} KiB ({:.0} bytes/order), write p50 {:.2} ms, restore p50 {:.2} ms",
+        resting,
+        size as f64 / 1024.0,
+        size as f64 / resting.max(1) as f64,
+        write.value_at_quantile(0.5) as f64 / 1e6,
+        restore.value_at_quantile(0.5) as f64 / 1e6,
+    );
+    Ok(())
+}
+
+/// Rebuilds a book from the journal and checks it against the live one.
+fn replay(path: &Path, format: Format, live: &OrderBook, results: &mut RunResults) -> std::io::Result<()> {
+    let mut book = OrderBook::new();
+    let mut entries = 0u64;
+    let mut orders = 0u64;
+    let mut apply = |entry: BookEvent| {
+        if matches!(entry, BookEvent::Submit(_)) {
+            orders += 1;
+        }
+        std::hint::black_box(entry.apply(&mut book));
+        entries += 1;
+    };
+
+    let started = Instant::now();
+    let input = BufReader::new(File::open(path)?);
+    match format {
+        Format::Json => {
+            for line in input.lines() {
+                apply(serde_json::from_str(&line?)?);
+            }
+        }
+        Format::Binary => {
+            for entry in JournalReader::new(input).map_err(std::io::Error::other)? {
+                apply(entry.map_err(std::io::Error::other)?);
+            }
+        }
+    }
+    let elapsed = started.elapsed();
+
+    let mut hist = new_histogram();
+    hist.saturating_record(elapsed.as_nanos() as u64);
+    results.record("persistence", &format!("Full journal replay{}", format.suffix()), &hist).throughput_per_sec =
+        Some(orders as f64 / elapsed.as_secs_f64());
+    let matches = book.best_bid() == live.best_bid()
+        && book.best_ask() == live.best_ask()
+        && book.bids.total_len() == live.bids.total_len()
+        && book.asks.total_len() == live.asks.total_len();
+    println!(
+        "  replay: {} entries in {:.1} ms, {:.0} entries/sec, {:.0} orders rebuilt/sec, state {}",
+        entries,
+        elapsed.as_secs_f64() * 1e3,
+        entries as f64 / elapsed.as_secs_f64(),
+        orders as f64 / elapsed.as_secs_f64(),
+        if matches { "matches the live book" } else { "DIFFERS from the live book" },
+    );
+    Ok(())
+}
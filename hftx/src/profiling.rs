@@ -0,0 +1,69 @@
+//! Per-scenario CPU profiling for the perf harness (`profiling` feature).
+//!
+//! Each scenario runs under a pprof sampling profiler and its flamegraph is
+//! written to `<dir>/<scenario>.svg`. Without the feature, scenarios run
+//! unprofiled and no files are written.
+
+use std::path::PathBuf;
+
+/// Wraps scenarios with an optional profiler writing one artifact per scenario.
+pub struct Profiler {
+    /// Output directory; `None` disables profiling even when compiled in
+    dir: Option<PathBuf>,
+}
+
+impl Profiler {
+    /// Profiler writing to `dir`, or a pass-through when `dir` is `None`.
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        #[cfg(not(feature = "profiling"))]
+        if dir.is_some() {
+            eprintln!("--profile-dir ignored: rebuild with `--features profiling`");
+        }
+        Self { dir }
+    }
+
+    /// Runs `f`, profiling it as `name` when enabled.
+    #[cfg(feature = "profiling")]
+    pub fn scenario<R>(&self, name: &str, f: impl FnOnce() -> R) -> R {
+        let Some(dir) = &self.dir else {
+            return f();
+        };
+
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(1000)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build();
+        let out = f();
+
+        let guard = match guard {
+            Ok(guard) => guard,
+            Err(e) => {
+                eprintln!("  [profile] {}: failed to start profiler: {}", name, e);
+                return out;
+            }
+        };
+        let written = guard
+            .report()
+            .build()
+            .map_err(|e| e.to_string())
+            .and_then(|report| {
+                std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+                let path = dir.join(format!("{}.svg", name));
+                let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+                report.flamegraph(file).map_err(|e| e.to_string())?;
+                Ok(path)
+            });
+        match written {
+            Ok(path) => println!("  [profile] {} -> {}", name, path.display()),
+            Err(e) => eprintln!("  [profile] {}: {}", name, e),
+        }
+        out
+    }
+
+    /// Runs `f`; profiling is compiled out.
+    #[cfg(not(feature = "profiling"))]
+    pub fn scenario<R>(&self, _name: &str, f: impl FnOnce() -> R) -> R {
+        let _ = &self.dir;
+        f()
+    }
+}
@@ -0,0 +1,136 @@
+//! Performance regression guard against a stored baseline run.
+//!
+//! A baseline is any results file the harness wrote with `--output` (JSON or
+//! CSV). Tests are matched by suite and name. A test regresses when one of its
+//! percentiles grows past the baseline by more than the tolerance, or its
+//! throughput drops by more than the tolerance. Tests present on only one side
+//! are reported but never fail the run, so adding a scenario doesn't need a
+//! new baseline.
+
+use crate::results::{RunResults, TestResult};
+use std::path::Path;
+
+/// Allowed slowdown per metric, in percent of the baseline value.
+#[derive(Clone, Debug)]
+pub struct Tolerances {
+    pub p50_pct: f64,
+    pub p99_pct: f64,
+    pub p999_pct: f64,
+    pub throughput_pct: f64,
+    /// Latency growth below this many nanoseconds never counts, so tests that
+    /// run in tens of nanoseconds don't fail on timer jitter
+    pub floor_ns: u64,
+}
+
+/// One metric of one test outside its tolerance.
+pub struct Regression {
+    pub suite: String,
+    pub name: String,
+    pub metric: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+}
+
+impl Regression {
+    /// Change relative to the baseline, in percent.
+    pub fn change_pct(&self) -> f64 {
+        100.0 * (self.current / self.baseline.max(f64::MIN_POSITIVE) - 1.0)
+    }
+}
+
+/// Compares `current` against the baseline file at `path`, prints a report,
+/// and returns every regression found.
+pub fn check(current: &RunResults, path: &Path, tolerances: &Tolerances) -> Result<Vec<Regression>, String> {
+    let baseline = RunResults::load(path).map_err(|e| format!("loading baseline {}: {}", path.display(), e))?;
+    println!("\n=== Regression check against {} ===", path.display());
+    println!(
+        "  baseline: {} on {} cpus ({}), tolerances: p50 {}%, p99 {}%, p99.9 {}%, throughput {}%, floor {} ns",
+        baseline.git_hash.as_deref().map(|h| &h[..h.len().min(12)]).unwrap_or("unknown commit"),
+        baseline.machine.cpus,
+        if baseline.allocator.is_empty() { "system" } else { &baseline.allocator },
+        tolerances.p50_pct,
+        tolerances.p99_pct,
+        tolerances.p999_pct,
+        tolerances.throughput_pct,
+        tolerances.floor_ns,
+    );
+    if baseline.machine.cpus != current.machine.cpus || baseline.allocator != current.allocator {
+        println!("  note: baseline was taken on a different host or allocator; expect noise");
+    }
+
+    let mut regressions = Vec::new();
+    for test in &current.tests {
+        let Some(base) = baseline.tests.iter().find(|b| b.suite == test.suite && b.name == test.name) else {
+            println!("  {:<10}  {}/{} (no baseline)", "new:", test.suite, test.name);
+            continue;
+        };
+        let found = compare(base, test, tolerances);
+        println!(
+            "  {:<10}  {}/{}: p50 {} -> {} ns, p99 {} -> {} ns, p99.9 {} -> {} ns",
+            if found.is_empty() { "ok:" } else { "REGRESSED:" },
+            test.suite,
+            test.name,
+            base.p50_ns,
+            test.p50_ns,
+            base.p99_ns,
+            test.p99_ns,
+            base.p999_ns,
+            test.p999_ns,
+        );
+        regressions.extend(found);
+    }
+    for base in &baseline.tests {
+        if !current.tests.iter().any(|t| t.suite == base.suite && t.name == base.name) {
+            println!("  {:<10}  {}/{} (in baseline, not run)", "missing:", base.suite, base.name);
+        }
+    }
+
+    for r in &regressions {
+        println!(
+            "  {}/{} {}: {:.0} -> {:.0} ({:+.1}%)",
+            r.suite,
+            r.name,
+            r.metric,
+            r.baseline,
+            r.current,
+            r.change_pct()
+        );
+    }
+    if regressions.is_empty() {
+        println!("  no regressions");
+    }
+    Ok(regressions)
+}
+
+/// Metrics of `current` outside tolerance relative to `base`.
+fn compare(base: &TestResult, current: &TestResult, tolerances: &Tolerances) -> Vec<Regression> {
+    let mut out = Vec::new();
+    let mut latency = |metric, base_ns: u64, current_ns: u64, pct: f64| {
+        let allowed = (base_ns as f64 * (1.0 + pct / 100.0)).max((base_ns + tolerances.floor_ns) as f64);
+        if current_ns as f64 > allowed {
+            out.push(Regression {
+                suite: current.suite.clone(),
+                name: current.name.clone(),
+                metric,
+                baseline: base_ns as f64,
+                current: current_ns as f64,
+            });
+        }
+    };
+    latency("p50_ns", base.p50_ns, current.p50_ns, tolerances.p50_pct);
+    latency("p99_ns", base.p99_ns, current.p99_ns, tolerances.p99_pct);
+    latency("p999_ns", base.p999_ns, current.p999_ns, tolerances.p999_pct);
+
+    if let (Some(base_tp), Some(current_tp)) = (base.throughput_per_sec, current.throughput_per_sec) {
+        if current_tp < base_tp * (1.0 - tolerances.throughput_pct / 100.0) {
+            out.push(Regression {
+                suite: current.suite.clone(),
+                name: current.name.clone(),
+                metric: "throughput_per_sec",
+                baseline: base_tp,
+                current: current_tp,
+            });
+        }
+    }
+    out
+}
@@ -0,0 +1,283 @@
+//! Structured perf results for comparing runs over time.
+//!
+//! Every measured operation becomes one `TestResult` row. A run is written
+//! as JSON (the full `RunResults` document) or CSV (one row per test with the
+//! run metadata repeated, so files can be concatenated and graphed). Either
+//! format loads back as a baseline for the regression guard.
+
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Latency summary for one measured operation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TestResult {
+    /// Test group, e.g. "market_data" or "throughput"
+    pub suite: String,
+    /// Operation label as printed on the console
+    pub name: String,
+    pub samples: u64,
+    pub mean_ns: f64,
+    pub p50_ns: u64,
+    pub p90_ns: u64,
+    pub p99_ns: u64,
+    pub p999_ns: u64,
+    pub max_ns: u64,
+    /// Operations per second for sustained tests; absent for micro-tests
+    pub throughput_per_sec: Option<f64>,
+    /// Heap allocations per operation (`alloc-tracking` builds only)
+    #[serde(default)]
+    pub allocs_per_op: Option<f64>,
+    /// Heap bytes requested per operation (`alloc-tracking` builds only)
+    #[serde(default)]
+    pub alloc_bytes_per_op: Option<f64>,
+}
+
+impl TestResult {
+    /// Summarizes a nanosecond histogram.
+    pub fn from_histogram(suite: &str, name: &str, hist: &Histogram<u64>) -> Self {
+        Self {
+            suite: suite.to_string(),
+            name: name.to_string(),
+            samples: hist.len(),
+            mean_ns: hist.mean(),
+            p50_ns: hist.value_at_quantile(0.50),
+            p90_ns: hist.value_at_quantile(0.90),
+            p99_ns: hist.value_at_quantile(0.99),
+            p999_ns: hist.value_at_quantile(0.999),
+            max_ns: hist.max(),
+            throughput_per_sec: None,
+            allocs_per_op: None,
+            alloc_bytes_per_op: None,
+        }
+    }
+}
+
+/// Host the run executed on.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MachineInfo {
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+    pub hostname: Option<String>,
+}
+
+impl MachineInfo {
+    pub fn detect() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            hostname: std::env::var("HOSTNAME")
+                .ok()
+                .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+                .map(|h| h.trim().to_string())
+                .filter(|h| !h.is_empty()),
+        }
+    }
+}
+
+/// One complete harness run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunResults {
+    /// Run start, milliseconds since epoch
+    pub started_at_ms: u64,
+    /// `git rev-parse HEAD` at run time, if available
+    pub git_hash: Option<String>,
+    pub machine: MachineInfo,
+    /// Command-line arguments the harness was invoked with
+    pub args: Vec<String>,
+    /// Order-flow seed; rerunning with `--seed` reproduces the same flow
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Global allocator the harness was built with
+    #[serde(default)]
+    pub allocator: String,
+    pub tests: Vec<TestResult>,
+}
+
+impl RunResults {
+    /// Starts an empty run stamped with the current time, commit, and host.
+    pub fn start() -> Self {
+        Self {
+            started_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            git_hash: git_hash(),
+            machine: MachineInfo::detect(),
+            args: std::env::args().skip(1).collect(),
+            seed: None,
+            allocator: crate::alloc_counter::ALLOCATOR.to_string(),
+            tests: Vec::new(),
+        }
+    }
+
+    /// Records a histogram and prints its console summary line.
+    pub fn record(&mut self, suite: &str, name: &str, hist: &Histogram<u64>) -> &mut TestResult {
+        let result = TestResult::from_histogram(suite, name, hist);
+        println!(
+            "  {}: p50={} ns, p90={} ns, p99={} ns, p99.9={} ns, max={} ns (n={})",
+            result.name,
+            result.p50_ns,
+            result.p90_ns,
+            result.p99_ns,
+            result.p999_ns,
+            result.max_ns,
+            result.samples,
+        );
+        self.tests.push(result);
+        self.tests.last_mut().unwrap()
+    }
+
+    /// Writes the run to `path`; `.csv` selects CSV, anything else JSON.
+    pub fn write_to(&self, path: &Path) -> std::io::Result<()> {
+        let body = match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => self.to_csv(),
+            _ => serde_json::to_string_pretty(self).map_err(std::io::Error::other)?,
+        };
+        std::fs::write(path, body)
+    }
+
+    /// Reads a run written by `write_to`; `.csv` selects CSV, anything else JSON.
+    /// A CSV file holding several concatenated runs loads as one run.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("csv") => Self::from_csv(&text),
+            _ => serde_json::from_str(&text).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Parses `to_csv` output, looking columns up by header name so files
+    /// written before a column existed still load.
+    fn from_csv(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let header = split_csv_row(lines.next().ok_or("empty CSV")?);
+        let col = |name: &str| header.iter().position(|h| h == name);
+        let required = |name: &str| col(name).ok_or_else(|| format!("CSV has no `{}` column", name));
+        let (suite, name) = (required("suite")?, required("name")?);
+
+        let mut run = Self {
+            started_at_ms: 0,
+            git_hash: None,
+            machine: MachineInfo {
+                os: String::new(),
+                arch: String::new(),
+                cpus: 0,
+                hostname: None,
+            },
+            args: Vec::new(),
+            seed: None,
+            allocator: String::new(),
+            tests: Vec::new(),
+        };
+        for (i, line) in lines.enumerate() {
+            let row = split_csv_row(line);
+            if row.len() != header.len() {
+                return Err(format!("CSV row {} has {} fields, header has {}", i + 2, row.len(), header.len()));
+            }
+            let get = |c: Option<usize>| c.map(|c| row[c].as_str()).filter(|v| !v.is_empty());
+            let num = |c: &str| get(col(c)).and_then(|v| v.parse::<f64>().ok());
+            if i == 0 {
+                run.started_at_ms = num("started_at_ms").unwrap_or(0.0) as u64;
+                run.git_hash = get(col("git_hash")).map(str::to_string);
+                run.machine.os = get(col("os")).unwrap_or_default().to_string();
+                run.machine.arch = get(col("arch")).unwrap_or_default().to_string();
+                run.machine.cpus = num("cpus").unwrap_or(0.0) as usize;
+                run.allocator = get(col("allocator")).unwrap_or_default().to_string();
+            }
+            run.tests.push(TestResult {
+                suite: row[suite].clone(),
+                name: row[name].clone(),
+                samples: num("samples").unwrap_or(0.0) as u64,
+                mean_ns: num("mean_ns").unwrap_or(0.0),
+                p50_ns: num("p50_ns").unwrap_or(0.0) as u64,
+                p90_ns: num("p90_ns").unwrap_or(0.0) as u64,
+                p99_ns: num("p99_ns").unwrap_or(0.0) as u64,
+                p999_ns: num("p999_ns").unwrap_or(0.0) as u64,
+                max_ns: num("max_ns").unwrap_or(0.0) as u64,
+                throughput_per_sec: num("throughput_per_sec"),
+                allocs_per_op: num("allocs_per_op"),
+                alloc_bytes_per_op: num("alloc_bytes_per_op"),
+            });
+        }
+        Ok(run)
+    }
+
+    /// CSV with a header row and one row per test.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "started_at_ms,git_hash,os,arch,cpus,allocator,suite,name,samples,mean_ns,p50_ns,p90_ns,p99_ns,p999_ns,max_ns,throughput_per_sec,allocs_per_op,alloc_bytes_per_op\n",
+        );
+        for t in &self.tests {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{:.2},{},{},{},{},{},{},{},{}",
+                self.started_at_ms,
+                self.git_hash.as_deref().unwrap_or(""),
+                self.machine.os,
+                self.machine.arch,
+                self.machine.cpus,
+                self.allocator,
+                csv_field(&t.suite),
+                csv_field(&t.name),
+                t.samples,
+                t.mean_ns,
+                t.p50_ns,
+                t.p90_ns,
+                t.p99_ns,
+                t.p999_ns,
+                t.max_ns,
+                t.throughput_per_sec.map(|v| format!("{:.0}", v)).unwrap_or_default(),
+                t.allocs_per_op.map(|v| format!("{:.3}", v)).unwrap_or_default(),
+                t.alloc_bytes_per_op.map(|v| format!("{:.1}", v)).unwrap_or_default(),
+            );
+        }
+        out
+    }
+}
+
+/// Quotes a CSV field when it contains a delimiter or quote.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Splits one CSV row, undoing `csv_field` quoting.
+fn split_csv_row(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn git_hash() -> Option<String> {
+    let output = Command::new("git").args(["rev-parse", "HEAD"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!hash.is_empty()).then_some(hash)
+}
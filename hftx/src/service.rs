@@ -0,0 +1,142 @@
+//! Locating or spawning an exchange-service instance for end-to-end scenarios.
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// A reachable exchange-service; a spawned one is killed on drop.
+pub struct ServiceProcess {
+    pub url: String,
+    child: Option<Child>,
+}
+
+impl ServiceProcess {
+    /// Uses the service at `url` if given, otherwise spawns `bin` (default: the
+    /// exchange-service binary next to this executable) on a free loopback port
+    /// with `env` set. Returns once `/health` answers.
+    pub async fn connect_or_spawn(
+        url: Option<&str>,
+        bin: Option<&PathBuf>,
+        env: &[(&str, &str)],
+    ) -> Result<Self, String> {
+        let service = match url {
+            Some(url) => Self {
+                url: url.trim_end_matches('/').to_string(),
+                child: None,
+            },
+            None => Self::spawn(bin, env)?,
+        };
+        service.wait_until_healthy().await?;
+        Ok(service)
+    }
+
+    /// WebSocket base URL for the same host.
+    pub fn ws_url(&self) -> String {
+        self.url.replacen("http", "ws", 1)
+    }
+
+    /// User + system CPU time consumed by a spawned service (Linux only).
+    pub fn cpu_time(&self) -> Option<Duration> {
+        let pid = self.child.as_ref()?.id();
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // Fields after the parenthesised command name; utime and stime are 14 and 15
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        let utime: u64 = fields.get(11)?.parse().ok()?;
+        let stime: u64 = fields.get(12)?.parse().ok()?;
+        // USER_HZ is 100 on every mainstream Linux configuration
+        Some(Duration::from_millis((utime + stime) * 10))
+    }
+
+    fn spawn(bin: Option<&PathBuf>, env: &[(&str, &str)]) -> Result<Self, String> {
+        let bin = match bin {
+            Some(bin) => bin.clone(),
+            None => std::env::current_exe()
+                .map_err(|e| e.to_string())?
+                .with_file_name(format!("exchange-service{}", std::env::consts::EXE_SUFFIX)),
+        };
+        if !bin.exists() {
+            return Err(format!(
+                "{} not found; build it with `cargo build --release -p exchange-service`, \
+                 pass --exchange-bin, or point --service-url at a running service",
+                bin.display()
+            ));
+        }
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|l| l.local_addr())
+            .map_err(|e| e.to_string())?
+            .port();
+        let addr = format!("127.0.0.1:{}", port);
+        let child = Command::new(&bin)
+            .env("EXCHANGE_ADDR", &addr)
+            .envs(env.iter().copied())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("spawning {}: {}", bin.display(), e))?;
+        Ok(Self {
+            url: format!("http://{}", addr),
+            child: Some(child),
+        })
+    }
+
+    async fn wait_until_healthy(&self) -> Result<(), String> {
+        let http = reqwest::Client::new();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            if let Ok(r) = http.get(format!("{}/health", self.url)).send().await {
+                if r.status().is_success() {
+                    return Ok(());
+                }
+            }
+            if Instant::now() > deadline {
+                return Err(format!("{} did not become healthy within 10s", self.url));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+impl Drop for ServiceProcess {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Just enough of a trade stream frame to attribute and time a trade.
+#[derive(Deserialize)]
+struct StreamFrame {
+    #[serde(rename = "type")]
+    kind: String,
+    trade: Option<StreamTrade>,
+    /// Set on `gap` notices
+    missed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct StreamTrade {
+    pub taker: u128,
+    /// Server wall clock when the taker order was accepted
+    pub ts_ns: u128,
+}
+
+/// Extracts the trade from a trade-stream text frame; `None` for pings etc.
+pub fn parse_trade_frame(text: &str) -> Option<StreamTrade> {
+    match serde_json::from_str(text) {
+        Ok(StreamFrame { kind, trade: Some(trade), .. }) if kind == "trade" => Some(trade),
+        _ => None,
+    }
+}
+
+/// How many messages a `gap` notice says the subscriber missed; `None` for
+/// any other frame.
+pub fn parse_gap_frame(text: &str) -> Option<u64> {
+    match serde_json::from_str(text) {
+        Ok(StreamFrame { kind, missed: Some(missed), .. }) if kind == "gap" => Some(missed),
+        _ => None,
+    }
+}
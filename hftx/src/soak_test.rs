@@ -0,0 +1,194 @@
+//! Long-running soak with leak detection.
+//!
+//! Drives one book with mixed Poisson flow (submits, cancels, replaces,
+//! marketable orders) for a long wall-clock run, sampling every interval:
+//! - process RSS (Linux)
+//! - lazily-canceled tombstones still queued on either side
+//! - the id index and the number of price levels held
+//!
+//! Live orders are bounded by the generator, so in steady state none of these
+//! should trend upward. After a warm-up, the samples are split in two halves
+//! and a metric fails if the peak of the second half exceeds the peak of the
+//! first by more than the tolerance, which catches tombstones or registry
+//! entries that are never reclaimed without tripping on ordinary noise.
+
+use crate::latency_test::new_histogram;
+use crate::results::RunResults;
+use orderbook::flow::{FlowConfig, FlowEvent, FlowGenerator};
+use orderbook::OrderBook;
+use std::time::{Duration, Instant};
+
+/// Settings for the soak run.
+pub struct SoakConfig {
+    pub duration: Duration,
+    /// Time between samples
+    pub sample_every: Duration,
+    /// Leading fraction of samples ignored while the book fills up
+    pub warmup_fraction: f64,
+    /// Allowed growth of a metric's peak between halves, in percent
+    pub tolerance_pct: f64,
+    /// Compaction threshold for the book, if any
+    pub compact_at: Option<usize>,
+    pub seed: u64,
+}
+
+/// One periodic measurement.
+struct Sample {
+    elapsed: Duration,
+    ops: u64,
+    rss_bytes: Option<u64>,
+    tombstones: usize,
+    indexed: usize,
+    levels: usize,
+}
+
+/// Parses durations like `90s`, `30m`, `6h`, or bare seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (num, unit) = match s.char_indices().find(|(_, c)| c.is_ascii_alphabetic()) {
+        Some((i, _)) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let value: f64 = num.trim().parse().map_err(|_| format!("invalid duration `{}`", s))?;
+    let secs = match unit {
+        "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => return Err(format!("invalid duration unit in `{}` (use s, m, or h)", s)),
+    };
+    if !(secs > 0.0 && secs.is_finite()) {
+        return Err(format!("duration `{}` must be positive", s));
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+/// Resident set size of this process, from /proc (Linux only).
+fn rss_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // 4 KiB pages on every mainstream Linux target
+    Some(pages * 4096)
+}
+
+fn sample(book: &OrderBook, started: Instant, ops: u64) -> Sample {
+    Sample {
+        elapsed: started.elapsed(),
+        ops,
+        rss_bytes: rss_bytes(),
+        tombstones: book.bids.tombstone_count() + book.asks.tombstone_count(),
+        indexed: book.bids.index_len() + book.asks.index_len(),
+        levels: book.bids.level_count() + book.asks.level_count(),
+    }
+}
+
+/// Runs the soak and returns the names of metrics that kept growing.
+pub fn run_soak_test(config: &SoakConfig, results: &mut RunResults) -> Vec<String> {
+    println!(
+        "\n Soak Test ({:.0}s, sample every {:.0}s, seed {}, tolerance {}%)",
+        config.duration.as_secs_f64(),
+        config.sample_every.as_secs_f64(),
+        config.seed,
+        config.tolerance_pct,
+    );
+    println!(
+        "  {:>8} {:>12} {:>10} {:>11} {:>9} {:>8}",
+        "elapsed", "ops", "rss MiB", "tombstones", "indexed", "levels"
+    );
+
+    let mut book = match config.compact_at {
+        Some(tombstones) => OrderBook::new().with_compact_threshold(tombstones),
+        None => OrderBook::new(),
+    };
+    let mut flow = FlowGenerator::new(FlowConfig::default(), config.seed);
+    let mut submit_hist = new_histogram();
+    let mut samples = Vec::new();
+    let mut ops = 0u64;
+
+    let started = Instant::now();
+    let mut next_sample = started + config.sample_every;
+    while started.elapsed() < config.duration {
+        // Check the clock every few thousand events, not every event
+        for _ in 0..4096 {
+            let Some(event) = flow.next() else {
+                break;
+            };
+            let (cancel, order) = match event {
+                FlowEvent::Submit(order) => (None, Some(order)),
+                FlowEvent::Cancel { id, side, .. } => (Some((id, side)), None),
+                FlowEvent::Replace { id, side, order } => (Some((id, side)), Some(order)),
+            };
+            if let Some((id, side)) = cancel {
+                book.levels_mut(side).cancel(id);
+            }
+            if let Some(order) = order {
+                let t0 = Instant::now();
+                std::hint::black_box(book.submit_limit(order).expect("flow orders are valid").trades);
+                submit_hist.saturating_record(t0.elapsed().as_nanos() as u64);
+            }
+            ops += 1;
+        }
+
+        if Instant::now() >= next_sample {
+            let s = sample(&book, started, ops);
+            println!(
+                "  {:>7.0}s {:>12} {:>10} {:>11} {:>9} {:>8}",
+                s.elapsed.as_secs_f64(),
+                s.ops,
+                s.rss_bytes.map(|b| format!("{:.1}", b as f64 / (1024.0 * 1024.0))).unwrap_or("-".into()),
+                s.tombstones,
+                s.indexed,
+                s.levels,
+            );
+            samples.push(s);
+            next_sample += config.sample_every;
+        }
+    }
+    let elapsed = started.elapsed();
+
+    results.record("soak", "Submit latency", &submit_hist).throughput_per_sec =
+        Some(ops as f64 / elapsed.as_secs_f64());
+
+    let growing = find_growth(&samples, config);
+    if samples.len() < 4 {
+        println!("  too few samples to judge growth; run longer or sample more often");
+    } else if growing.is_empty() {
+        println!("  no unbounded growth detected");
+    } else {
+        for name in &growing {
+            println!("  LEAK: {} kept growing", name);
+        }
+    }
+    growing
+}
+
+/// Reads one tracked metric out of a sample.
+type Metric = fn(&Sample) -> Option<u64>;
+
+/// Metrics whose post-warm-up peak rose by more than the tolerance.
+fn find_growth(samples: &[Sample], config: &SoakConfig) -> Vec<String> {
+    let skip = (samples.len() as f64 * config.warmup_fraction) as usize;
+    let steady = &samples[skip.min(samples.len())..];
+    if steady.len() < 4 {
+        return Vec::new();
+    }
+    let (first, second) = steady.split_at(steady.len() / 2);
+
+    let metrics: [(&str, Metric); 4] = [
+        ("process RSS", |s| s.rss_bytes),
+        ("tombstones", |s| Some(s.tombstones as u64)),
+        ("id index", |s| Some(s.indexed as u64)),
+        ("price levels", |s| Some(s.levels as u64)),
+    ];
+    let mut growing = Vec::new();
+    for (name, metric) in metrics {
+        let peak = |half: &[Sample]| half.iter().filter_map(metric).max();
+        if let (Some(before), Some(after)) = (peak(first), peak(second)) {
+            // Small absolute counts bounce around; require real growth
+            let allowed = (before as f64 * (1.0 + config.tolerance_pct / 100.0)).max(before as f64 + 64.0);
+            if after as f64 > allowed {
+                growing.push(format!("{} ({} -> {})", name, before, after));
+            }
+        }
+    }
+    growing
+}
@@ -0,0 +1,270 @@
+//! Wire-to-wire latency against a real exchange-service process.
+//!
+//! In-process numbers measure the matching engine alone. This scenario goes
+//! through everything an API user pays for: JSON encoding, HTTP, the Axum
+//! handler, the book lock, the trade broadcast, and the WebSocket push.
+//!
+//! Each client repeatedly rests a one-lot ask and then sends a crossing bid.
+//! Two latencies are recorded per crossing bid:
+//! - submit ack: HTTP request sent until the response is parsed
+//! - submit to trade: HTTP request sent until the trade naming that bid as
+//!   taker arrives on the trade WebSocket
+//!
+//! Load comes from the concurrent clients and, optionally, the server-side
+//! bot driver trading the same symbol.
+//!
+//! With `compare_batching` the scenario runs twice against spawned services,
+//! once with submit combining off and once on, and reports the throughput
+//! gain and how many orders shared each book-lock acquisition.
+
+use crate::latency_test::new_histogram;
+use crate::results::RunResults;
+use crate::service::{parse_trade_frame, ServiceProcess};
+use futures::StreamExt;
+use orderbook::Side;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Price the resting asks are placed at, in ticks.
+const WIRE_PX: i64 = 10_000;
+
+/// Limit for the crossing bids: far enough through any book the bot driver
+/// builds that each bid takes the best ask, whoever posted it.
+const WIRE_CROSS_PX: i64 = 1_000_000;
+
+/// How long to wait for straggling WebSocket trades after the last submit.
+const TRADE_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Settings for the wire-to-wire scenario.
+pub struct WireConfig {
+    /// Use an already running service instead of spawning one
+    pub url: Option<String>,
+    /// exchange-service binary to spawn; defaults to the one next to this executable
+    pub exchange_bin: Option<PathBuf>,
+    pub symbol: String,
+    /// Concurrent HTTP clients
+    pub clients: usize,
+    /// Crossing bids each client sends
+    pub orders_per_client: usize,
+    /// Also run the server-side bot driver on the symbol as background load
+    pub sim_load: bool,
+    /// Run once with the service's submit batching off and once on
+    pub compare_batching: bool,
+}
+
+#[derive(Serialize)]
+struct SubmitOrderRequest {
+    side: Side,
+    price: i64,
+    quantity: i64,
+}
+
+#[derive(Deserialize)]
+struct SubmitOrderResponse {
+    order_id: u128,
+}
+
+#[derive(Deserialize)]
+struct SubmitStats {
+    batching: bool,
+    submits: u64,
+    submit_batches: u64,
+}
+
+/// Runs the wire-to-wire scenario and records its latencies under the "wire" suite.
+pub fn run_wire_latency_test(config: &WireConfig, results: &mut RunResults) {
+    println!("\n Wire-to-Wire Latency Test (HTTP submit -> trade on WebSocket)");
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    if !config.compare_batching {
+        if let Err(e) = runtime.block_on(run(config, results, &[], "")) {
+            eprintln!("  wire test failed: {}", e);
+        }
+        return;
+    }
+    if config.url.is_some() {
+        eprintln!("  --wire-compare-batching needs a spawned service; drop --service-url");
+        return;
+    }
+
+    let mut throughput = Vec::new();
+    for (env, mode) in [("0", "batching off"), ("1", "batching on")] {
+        println!("  [{}]", mode);
+        let suffix = format!(" ({})", mode);
+        match runtime.block_on(run(config, results, &[("EXCHANGE_SUBMIT_BATCHING", env)], &suffix)) {
+            Ok(t) => throughput.push(t),
+            Err(e) => {
+                eprintln!("  wire test failed: {}", e);
+                return;
+            }
+        }
+    }
+    println!(
+        "  submit batching throughput gain: {:+.1}%",
+        100.0 * (throughput[1] / throughput[0] - 1.0)
+    );
+}
+
+/// One pass of the scenario; returns crossing-bid throughput in orders/sec.
+async fn run(
+    config: &WireConfig,
+    results: &mut RunResults,
+    env: &[(&str, &str)],
+    label_suffix: &str,
+) -> Result<f64, String> {
+    let service = ServiceProcess::connect_or_spawn(config.url.as_deref(), config.exchange_bin.as_ref(), env).await?;
+    let base_url = service.url.clone();
+    let http = reqwest::Client::new();
+    println!("  service: {} ({} clients x {} orders)", base_url, config.clients, config.orders_per_client);
+
+    if config.sim_load {
+        http.post(format!("{}/sim/start", base_url))
+            .json(&serde_json::json!({
+                "symbol": config.symbol,
+                "makers": 4,
+                "takers": 4,
+                "aggression": 50,
+                "tick_ms": 10,
+            }))
+            .send()
+            .await
+            .map_err(|e| format!("starting sim load: {}", e))?;
+    }
+
+    // Subscribe before the first order so no trade is missed
+    let ws_url = format!(
+        "{}/symbols/{}/trades/stream",
+        service.ws_url(),
+        config.symbol
+    );
+    let (ws, _) = tokio_tungstenite::connect_async(ws_url.as_str())
+        .await
+        .map_err(|e| format!("connecting {}: {}", ws_url, e))?;
+    let (trade_tx, mut trade_rx) = mpsc::unbounded_channel();
+    let reader = tokio::spawn(async move {
+        let (_, mut stream) = ws.split();
+        while let Some(Ok(msg)) = stream.next().await {
+            let received = Instant::now();
+            if let Message::Text(text) = msg {
+                if let Some(trade) = parse_trade_frame(&text) {
+                    if trade_tx.send((trade.taker, received)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+
+    let started = Instant::now();
+    let mut tasks = Vec::with_capacity(config.clients);
+    for _ in 0..config.clients {
+        let http = http.clone();
+        let orders_url = format!("{}/symbols/{}/orders", base_url, config.symbol);
+        let orders = config.orders_per_client;
+        tasks.push(tokio::spawn(async move { run_client(&http, &orders_url, orders).await }));
+    }
+
+    let mut ack = new_histogram();
+    let mut sent_at = HashMap::new();
+    for task in tasks {
+        let client = task.await.map_err(|e| e.to_string())??;
+        for (order_id, t0, ack_ns) in client {
+            ack.record(ack_ns.max(1)).unwrap();
+            sent_at.insert(order_id, t0);
+        }
+    }
+    let elapsed = started.elapsed();
+
+    if config.sim_load {
+        let _ = http.post(format!("{}/sim/stop", base_url))
+            .json(&serde_json::json!({ "symbol": config.symbol }))
+            .send()
+            .await;
+    }
+
+    // Match WebSocket trades back to the bids that caused them; a bid that
+    // sweeps several makers produces several trades, and only the first counts
+    let submitted = sent_at.len();
+    let mut to_trade = new_histogram();
+    let deadline = Instant::now() + TRADE_DRAIN_TIMEOUT;
+    while !sent_at.is_empty() {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match tokio::time::timeout(remaining, trade_rx.recv()).await {
+            Ok(Some((taker, received))) => {
+                if let Some(t0) = sent_at.remove(&taker) {
+                    to_trade.record((received - t0).as_nanos().max(1) as u64).unwrap();
+                }
+            }
+            _ => break,
+        }
+    }
+    reader.abort();
+
+    let throughput = submitted as f64 / elapsed.as_secs_f64();
+    results.record("wire", &format!("HTTP submit ack{}", label_suffix), &ack).throughput_per_sec = Some(throughput);
+    results.record("wire", &format!("Submit -> trade on WS{}", label_suffix), &to_trade);
+    println!(
+        "  crossing bids: {}, trades seen on WS: {}, missing: {}, throughput: {:.0} orders/sec",
+        submitted,
+        to_trade.len(),
+        sent_at.len(),
+        throughput
+    );
+    // Older services have no /stats; the latencies above stand on their own
+    if let Ok(stats) = fetch_stats(&http, &base_url).await {
+        println!(
+            "  service submit batching: {}, {:.2} orders per book-lock acquisition",
+            if stats.batching { "on" } else { "off" },
+            stats.submits as f64 / stats.submit_batches.max(1) as f64
+        );
+    }
+    Ok(throughput)
+}
+
+async fn fetch_stats(http: &reqwest::Client, base_url: &str) -> Result<SubmitStats, reqwest::Error> {
+    http.get(format!("{}/stats", base_url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<SubmitStats>()
+        .await
+}
+
+/// Rests an ask then crosses it, `orders` times; returns (bid id, send time, ack ns).
+async fn run_client(
+    http: &reqwest::Client,
+    orders_url: &str,
+    orders: usize,
+) -> Result<Vec<(u128, Instant, u64)>, String> {
+    let mut samples = Vec::with_capacity(orders);
+    for _ in 0..orders {
+        submit(http, orders_url, Side::Ask, WIRE_PX).await?;
+
+        let t0 = Instant::now();
+        let response = submit(http, orders_url, Side::Bid, WIRE_CROSS_PX).await?;
+        let ack_ns = t0.elapsed().as_nanos() as u64;
+        samples.push((response.order_id, t0, ack_ns));
+    }
+    Ok(samples)
+}
+
+async fn submit(
+    http: &reqwest::Client,
+    orders_url: &str,
+    side: Side,
+    price: i64,
+) -> Result<SubmitOrderResponse, String> {
+    http.post(orders_url)
+        .json(&SubmitOrderRequest { side, price, quantity: 1 })
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("submit: {}", e))?
+        .json::<SubmitOrderResponse>()
+        .await
+        .map_err(|e| format!("submit response: {}", e))
+}
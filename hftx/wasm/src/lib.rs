@@ -0,0 +1,180 @@
+//! WebAssembly bindings for the matching engine.
+//!
+//! Runs a client-side `OrderBook` in the browser so the web UI can demo and
+//! teach matching with no exchange-service running. Results come back as
+//! plain JS objects shaped like the service's JSON (`Trade`, `MarketDepth`
+//! in `web/lib/types.ts`), so the same components can render either source.
+//!
+//! ```js
+//! import init, { OrderBook } from "hftx-wasm";
+//! await init();
+//! const book = new OrderBook("AAPL");
+//! book.submit(1, "ask", 15000, 100);
+//! book.submit(2, "bid", 15000, 40);  // [{ maker: 1, taker: 2, px_ticks: 15000, qty: 40, ... }]
+//! book.depth(10);                    // { symbol, bids: [{ price, quantity, orders }], asks, timestamp }
+//! ```
+//!
+//! JS numbers carry ids, prices, and sizes; they must be integers within
+//! `Number.MAX_SAFE_INTEGER`, and anything else is rejected with an `Error`.
+
+use orderbook::{Order, OrderBook, OrderId, ParticipantId, PriceLevels, Side, SymbolId, TimeInForce, Trade};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Largest integer a JS number holds exactly (2^53 - 1).
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+#[derive(Serialize)]
+struct JsTrade<'a> {
+    maker: u64,
+    taker: u64,
+    symbol: &'a str,
+    px_ticks: i64,
+    qty: i64,
+    ts_ns: u64,
+}
+
+#[derive(Serialize)]
+struct JsPriceLevel {
+    price: i64,
+    quantity: i64,
+    orders: usize,
+}
+
+#[derive(Serialize)]
+struct JsMarketDepth<'a> {
+    symbol: &'a str,
+    bids: Vec<JsPriceLevel>,
+    asks: Vec<JsPriceLevel>,
+    /// Nanoseconds since the epoch, like the service's depth snapshots. Past
+    /// 2^53, so a float; the safe-integer check would reject it as a u64
+    timestamp: f64,
+}
+
+/// Serializes with JS numbers (not BigInts) for every integer.
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// A JS number that must be an exact integer.
+fn integer(name: &str, value: f64) -> Result<i64, JsError> {
+    if value.is_finite() && value.fract() == 0.0 && value.abs() <= MAX_SAFE_INTEGER {
+        Ok(value as i64)
+    } else {
+        Err(JsError::new(&format!("{} must be a safe integer, got {}", name, value)))
+    }
+}
+
+fn order_id(value: f64) -> Result<OrderId, JsError> {
+    match integer("id", value)? {
+        id if id >= 0 => Ok(OrderId(id as u128)),
+        id => Err(JsError::new(&format!("id must not be negative, got {}", id))),
+    }
+}
+
+fn parse_side(side: &str) -> Result<Side, JsError> {
+    match side.to_ascii_lowercase().as_str() {
+        "bid" | "buy" => Ok(Side::Bid),
+        "ask" | "sell" => Ok(Side::Ask),
+        _ => Err(JsError::new(&format!("side must be \"bid\" or \"ask\", got {:?}", side))),
+    }
+}
+
+/// Top `levels` live price levels of one side, best first.
+fn levels(side: &PriceLevels, levels: usize) -> Vec<JsPriceLevel> {
+    side.depth(levels)
+        .into_iter()
+        .map(|level| JsPriceLevel {
+            price: level.px_ticks,
+            quantity: level.qty,
+            orders: level.orders,
+        })
+        .collect()
+}
+
+/// A single-symbol limit order book with price-time priority.
+#[wasm_bindgen(js_name = OrderBook)]
+pub struct WasmOrderBook {
+    book: OrderBook,
+    symbol: SymbolId,
+}
+
+#[wasm_bindgen(js_class = OrderBook)]
+impl WasmOrderBook {
+    #[wasm_bindgen(constructor)]
+    pub fn new(symbol: String) -> WasmOrderBook {
+        WasmOrderBook { book: OrderBook::new(), symbol: symbol.into() }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn symbol(&self) -> String {
+        self.symbol.to_string()
+    }
+
+    /// Submits a limit order and returns the trades it printed.
+    /// `ts_ns` defaults to 0; pass a clock if trade times matter.
+    pub fn submit(&mut self, id: f64, side: &str, price: f64, quantity: f64, ts_ns: Option<f64>) -> Result<JsValue, JsError> {
+        let id = order_id(id)?;
+        let qty = integer("quantity", quantity)?;
+        let report = self.book.submit_limit(Order {
+            id,
+            symbol: self.symbol,
+            side: parse_side(side)?,
+            px_ticks: integer("price", price)?,
+            qty,
+            ts_ns: integer("ts_ns", ts_ns.unwrap_or(0.0))?.max(0) as u128,
+            tif: TimeInForce::Day,
+            expires_ns: None,
+            display_qty: None,
+            min_qty: None,
+            hidden: false,
+            owner: ParticipantId::NONE,
+        })?;
+        to_js(&report.trades.iter().map(js_trade).collect::<Vec<_>>())
+    }
+
+    /// Cancels a resting order on either side; true if it was live.
+    pub fn cancel(&mut self, id: f64) -> Result<bool, JsError> {
+        let id = order_id(id)?;
+        Ok(self.book.cancel(id))
+    }
+
+    /// Top `levels` price levels per side as a `MarketDepth` object.
+    pub fn depth(&self, levels_per_side: usize) -> Result<JsValue, JsError> {
+        to_js(&JsMarketDepth {
+            symbol: self.symbol.as_str(),
+            bids: levels(&self.book.bids, levels_per_side),
+            asks: levels(&self.book.asks, levels_per_side),
+            timestamp: js_sys::Date::now() * 1e6,
+        })
+    }
+
+    #[wasm_bindgen(getter, js_name = bestBid)]
+    pub fn best_bid(&self) -> Option<f64> {
+        self.book.best_bid().map(|px| px as f64)
+    }
+
+    #[wasm_bindgen(getter, js_name = bestAsk)]
+    pub fn best_ask(&self) -> Option<f64> {
+        self.book.best_ask().map(|px| px as f64)
+    }
+
+    /// Live resting orders on both sides.
+    #[wasm_bindgen(getter)]
+    pub fn resting(&self) -> usize {
+        self.book.bids.total_len() + self.book.asks.total_len()
+    }
+}
+
+fn js_trade(trade: &Trade) -> JsTrade<'_> {
+    JsTrade {
+        maker: trade.maker.0 as u64,
+        taker: trade.taker.0 as u64,
+        symbol: trade.symbol.as_str(),
+        px_ticks: trade.px_ticks,
+        qty: trade.qty,
+        ts_ns: trade.ts_ns as u64,
+    }
+}